@@ -0,0 +1,71 @@
+//! Routing and listing cost as the registry grows. Run with
+//! `cargo bench --bench routing`; each line reports the mean time per
+//! request, which should stay flat from 100 to 10,000 tools.
+
+use async_trait::async_trait;
+use mcp_server::{McpRequest, McpServer, Tool, ToolError, ToolHandler, ToolResult};
+use serde_json::{json, Value};
+use std::time::{Duration, Instant};
+
+const ROUNDS: u32 = 2_000;
+
+struct Noop;
+
+#[async_trait]
+impl ToolHandler for Noop {
+    async fn call(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Ok(ToolResult::text("ok"))
+    }
+}
+
+fn server(tools: usize) -> McpServer {
+    let server = McpServer::new();
+    for i in 0..tools {
+        server.register_tool(
+            Tool {
+                name: format!("tool_{:05}", i),
+                description: format!("Tool number {}", i),
+                input_schema: json!({"type": "object"}),
+                output_schema: None,
+                annotations: None,
+                meta: Default::default(),
+            },
+            Noop,
+        );
+    }
+    server
+}
+
+fn request(method: &str, params: Value) -> McpRequest {
+    serde_json::from_value(json!({"jsonrpc": "2.0", "id": 1, "method": method, "params": params}))
+        .unwrap()
+}
+
+async fn mean(server: &McpServer, method: &str, params: Value) -> Duration {
+    let started = Instant::now();
+    for _ in 0..ROUNDS {
+        let response = server.handle_request(request(method, params.clone())).await;
+        assert!(response.unwrap().error.is_none());
+    }
+    started.elapsed() / ROUNDS
+}
+
+#[tokio::main]
+async fn main() {
+    for tools in [100, 1_000, 10_000] {
+        let server = server(tools);
+        let name = format!("tool_{:05}", tools / 2);
+        let call = mean(
+            &server,
+            "tools/call",
+            json!({"name": name, "arguments": {}}),
+        )
+        .await;
+        let list = mean(&server, "tools/list", json!({})).await;
+        let describe = mean(&server, "tools/describe", json!({"names": [name]})).await;
+        println!(
+            "{:>6} tools: tools/call {:>9.2?}  tools/list {:>9.2?}  tools/describe {:>9.2?}",
+            tools, call, list, describe
+        );
+    }
+}
@@ -0,0 +1,55 @@
+//! Records what `server/info` reports about the build: the git commit, the
+//! build time, the enabled features and the compiler.
+
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    let git_hash = output("git", &["rev-parse", "--short=12", "HEAD"]);
+    println!(
+        "cargo:rustc-env=MCP_BUILD_GIT_HASH={}",
+        git_hash.as_deref().unwrap_or("unknown")
+    );
+
+    // Reproducible builds pin the time with SOURCE_DATE_EPOCH.
+    let built_at = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs()
+        });
+    println!("cargo:rustc-env=MCP_BUILD_EPOCH={}", built_at);
+
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(name, _)| name.strip_prefix("CARGO_FEATURE_").map(str::to_string))
+        .map(|feature| feature.to_lowercase().replace('_', "-"))
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=MCP_BUILD_FEATURES={}", features.join(","));
+
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    println!(
+        "cargo:rustc-env=MCP_BUILD_RUSTC={}",
+        output(&rustc, &["--version"])
+            .as_deref()
+            .unwrap_or("unknown")
+    );
+    println!(
+        "cargo:rustc-env=MCP_BUILD_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+}
+
+/// The trimmed stdout of a successful run, or `None`. The build script is
+/// not the server, so the guard on spawning children does not apply.
+#[allow(clippy::disallowed_methods)]
+fn output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,276 @@
+//! Procedural macros for `mcp-server`, re-exported from the main crate. Use
+//! them as `mcp_server::mcp_tool` rather than depending on this crate
+//! directly.
+
+use proc_macro::TokenStream;
+use proc_macro2::{Span, TokenStream as Tokens};
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, Attribute, Expr, FnArg, Ident, ItemFn, LitStr, Pat, Type};
+
+/// Turns a function into a tool: a unit struct named after it in
+/// PascalCase with a `Tool` suffix, whose `definition()` describes the
+/// tool and which implements `ToolHandler` by calling the function.
+///
+/// The function's doc comment is the tool's description, and each
+/// parameter is an argument: its doc comment describes it in the input
+/// schema, and `#[serde(...)]`, `#[schemars(...)]` and `#[validate(...)]`
+/// attributes apply as they would on a struct field. A `&ToolContext`
+/// parameter receives the call's context instead. The function returns
+/// `Result<ToolResult, ToolError>`.
+///
+/// Options: `name = "..."` and `description = "..."` override the
+/// defaults, `read_only` or `destructive` set the tool's annotations, and
+/// `output = Type` publishes `Type`'s schema as the tool's output schema.
+#[proc_macro_attribute]
+pub fn mcp_tool(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut options = Options::default();
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            options.name = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("description") {
+            options.description = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("output") {
+            options.output = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("read_only") {
+            options.read_only = true;
+        } else if meta.path.is_ident("destructive") {
+            options.destructive = true;
+        } else {
+            return Err(meta
+                .error("expected `name`, `description`, `output`, `read_only` or `destructive`"));
+        }
+        Ok(())
+    });
+    parse_macro_input!(attr with parser);
+    let function = parse_macro_input!(item as ItemFn);
+    tool(options, function)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct Options {
+    name: Option<LitStr>,
+    description: Option<LitStr>,
+    output: Option<Type>,
+    read_only: bool,
+    destructive: bool,
+}
+
+/// A parameter of the tool function.
+enum Param {
+    Argument {
+        ident: Ident,
+        ty: Box<Type>,
+        attrs: Vec<Attribute>,
+    },
+    Context,
+}
+
+fn tool(options: Options, mut function: ItemFn) -> syn::Result<Tokens> {
+    let krate = quote!(::mcp_server);
+    let private = quote!(#krate::__private);
+    let sig = &function.sig;
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &sig.generics,
+            "#[mcp_tool] functions cannot be generic",
+        ));
+    }
+    if options.read_only && options.destructive {
+        return Err(syn::Error::new(
+            Span::call_site(),
+            "a tool cannot be both `read_only` and `destructive`",
+        ));
+    }
+
+    let mut params = Vec::new();
+    for input in function.sig.inputs.iter_mut() {
+        let FnArg::Typed(input) = input else {
+            return Err(syn::Error::new_spanned(
+                input,
+                "#[mcp_tool] functions cannot take `self`",
+            ));
+        };
+        let attrs = std::mem::take(&mut input.attrs);
+        if is_context(&input.ty) {
+            params.push(Param::Context);
+            continue;
+        }
+        let Pat::Ident(pat) = &*input.pat else {
+            return Err(syn::Error::new_spanned(
+                &input.pat,
+                "#[mcp_tool] parameters must be plain names",
+            ));
+        };
+        params.push(Param::Argument {
+            ident: pat.ident.clone(),
+            ty: input.ty.clone(),
+            attrs,
+        });
+    }
+
+    let function_name = &function.sig.ident;
+    let name = options
+        .name
+        .map(|name| name.value())
+        .unwrap_or_else(|| function_name.to_string());
+    let description = match options.description {
+        Some(description) => description.value(),
+        None => description(&function.attrs).ok_or_else(|| {
+            syn::Error::new_spanned(
+                function_name,
+                "#[mcp_tool] needs a doc comment or `description = \"...\"`",
+            )
+        })?,
+    };
+    let annotations = if options.read_only {
+        quote!(Some(#krate::ToolAnnotations::read_only()))
+    } else if options.destructive {
+        quote!(Some(#krate::ToolAnnotations::mutating(true)))
+    } else {
+        quote!(None)
+    };
+
+    let output_schema = match &options.output {
+        Some(output) => quote!(Some(#krate::tools::args::schema::<#output>())),
+        None => quote!(None),
+    };
+
+    let handler = format_ident!("{}Tool", pascal_case(&function_name.to_string()));
+    let vis = &function.vis;
+    let docs: Vec<&Attribute> = function
+        .attrs
+        .iter()
+        .filter(|a| a.path().is_ident("doc"))
+        .collect();
+    let fields = params.iter().filter_map(|param| match param {
+        Param::Argument { ident, ty, attrs } => Some(quote! { #(#attrs)* #ident: #ty }),
+        Param::Context => None,
+    });
+    let bindings = params.iter().filter_map(|param| match param {
+        Param::Argument { ident, .. } => Some(ident),
+        Param::Context => None,
+    });
+    let call_args = params.iter().map(|param| match param {
+        Param::Argument { ident, .. } => quote!(#ident),
+        Param::Context => quote!(__context),
+    });
+    let awaited = function.sig.asyncness.is_some().then(|| quote!(.await));
+
+    Ok(quote! {
+        #function
+
+        #(#docs)*
+        #vis struct #handler;
+
+        const _: () = {
+            #[derive(#private::serde::Deserialize, #private::schemars::JsonSchema)]
+            #[serde(crate = "::mcp_server::__private::serde")]
+            #[schemars(crate = "::mcp_server::__private::schemars")]
+            struct Arguments {
+                #(#fields,)*
+            }
+
+            impl #handler {
+                /// The tool's name, description and input schema.
+                pub fn definition() -> #krate::Tool {
+                    #krate::Tool {
+                        name: #name.to_string(),
+                        description: #description.to_string(),
+                        input_schema: #krate::tools::args::schema::<Arguments>(),
+                        output_schema: #output_schema,
+                        annotations: #annotations,
+                        meta: ::core::default::Default::default(),
+                    }
+                }
+            }
+
+            #[#private::async_trait]
+            impl #krate::ToolHandler for #handler {
+                async fn call(
+                    &self,
+                    __arguments: #private::serde_json::Value,
+                ) -> ::core::result::Result<#krate::ToolResult, #krate::ToolError> {
+                    let __context = #krate::ToolContext::default();
+                    self.call_with_context(__arguments, &__context).await
+                }
+
+                async fn call_with_context(
+                    &self,
+                    __arguments: #private::serde_json::Value,
+                    __context: &#krate::ToolContext,
+                ) -> ::core::result::Result<#krate::ToolResult, #krate::ToolError> {
+                    self.call_shared(__arguments.into(), __context).await
+                }
+
+                async fn call_shared(
+                    &self,
+                    __arguments: #krate::tools::args::Arguments,
+                    __context: &#krate::ToolContext,
+                ) -> ::core::result::Result<#krate::ToolResult, #krate::ToolError> {
+                    let Arguments { #(#bindings,)* } = __arguments.parse()?;
+                    #function_name(#(#call_args),*)#awaited
+                }
+            }
+        };
+    })
+}
+
+/// Whether `ty` is `&ToolContext`, however the path is written.
+fn is_context(ty: &Type) -> bool {
+    let Type::Reference(reference) = ty else {
+        return false;
+    };
+    let Type::Path(path) = &*reference.elem else {
+        return false;
+    };
+    path.path
+        .segments
+        .last()
+        .is_some_and(|s| s.ident == "ToolContext")
+}
+
+/// The doc comment with its lines joined, keeping blank lines as
+/// paragraph breaks.
+fn description(attrs: &[Attribute]) -> Option<String> {
+    let mut text = String::new();
+    for attr in attrs.iter().filter(|a| a.path().is_ident("doc")) {
+        let syn::Meta::NameValue(meta) = &attr.meta else {
+            continue;
+        };
+        let Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(line),
+            ..
+        }) = &meta.value
+        else {
+            continue;
+        };
+        let line = line.value();
+        let line = line.trim();
+        if line.is_empty() {
+            text.push_str("\n\n");
+        } else {
+            if !text.is_empty() && !text.ends_with('\n') {
+                text.push(' ');
+            }
+            text.push_str(line);
+        }
+    }
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// `count_tokens` as `CountTokens`.
+fn pascal_case(name: &str) -> String {
+    name.trim_start_matches("r#")
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
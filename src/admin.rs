@@ -0,0 +1,777 @@
+//! Operator channel, kept separate from the agent-facing transport so that
+//! agents cannot approve their own calls. Knowing a pending call's id is not
+//! enough to decide it: the socket also wants a token only the operator can
+//! read.
+
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::approval::ApprovalQueue;
+use crate::backends::Backends;
+use crate::delegation::ChildServers;
+use crate::dlp::DlpFilter;
+use crate::gc::Collector;
+use crate::protocol::{McpError, McpRequest, McpResponse};
+use crate::templates::ResultTemplates;
+use crate::timeline::Timeline;
+use crate::tool_config::ToolConfigStore;
+use crate::tools::warmup::Warmups;
+use crate::transport::access::ConnectionLimiter;
+use crate::transport::auth::{self, Token, TokenAuth};
+use crate::variants::DescriptionVariants;
+
+/// What the admin channel can inspect and control.
+pub struct Admin {
+    pub approvals: Arc<ApprovalQueue>,
+    /// Connection limiters of the running network transports.
+    pub connections: Vec<Arc<ConnectionLimiter>>,
+    pub dlp: Arc<DlpFilter>,
+    pub variants: Arc<DescriptionVariants>,
+    pub backends: Arc<Backends>,
+    pub tool_config: Arc<ToolConfigStore>,
+    pub templates: Arc<ResultTemplates>,
+    pub children: Arc<ChildServers>,
+    pub timeline: Arc<Timeline>,
+    pub warmups: Arc<Warmups>,
+    /// The tokens the network transports require, if they require any.
+    pub token_auth: Option<Arc<TokenAuth>>,
+    pub gc: Option<Arc<Collector>>,
+}
+
+impl Admin {
+    pub fn new(approvals: Arc<ApprovalQueue>) -> Self {
+        Self {
+            approvals,
+            connections: Vec::new(),
+            dlp: Arc::default(),
+            variants: Arc::default(),
+            backends: Arc::default(),
+            tool_config: Arc::default(),
+            templates: Arc::default(),
+            children: Arc::default(),
+            timeline: Arc::new(Timeline::new(0)),
+            warmups: Arc::default(),
+            token_auth: None,
+            gc: None,
+        }
+    }
+
+    pub fn with_connections(mut self, limiter: Arc<ConnectionLimiter>) -> Self {
+        self.connections.push(limiter);
+        self
+    }
+
+    pub fn with_dlp(mut self, dlp: Arc<DlpFilter>) -> Self {
+        self.dlp = dlp;
+        self
+    }
+
+    pub fn with_variants(mut self, variants: Arc<DescriptionVariants>) -> Self {
+        self.variants = variants;
+        self
+    }
+
+    pub fn with_backends(mut self, backends: Arc<Backends>) -> Self {
+        self.backends = backends;
+        self
+    }
+
+    pub fn with_tool_config(mut self, tool_config: Arc<ToolConfigStore>) -> Self {
+        self.tool_config = tool_config;
+        self
+    }
+
+    pub fn with_templates(mut self, templates: Arc<ResultTemplates>) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    pub fn with_children(mut self, children: Arc<ChildServers>) -> Self {
+        self.children = children;
+        self
+    }
+
+    pub fn with_timeline(mut self, timeline: Arc<Timeline>) -> Self {
+        self.timeline = timeline;
+        self
+    }
+
+    pub fn with_warmups(mut self, warmups: Arc<Warmups>) -> Self {
+        self.warmups = warmups;
+        self
+    }
+
+    pub fn with_token_auth(mut self, auth: Arc<TokenAuth>) -> Self {
+        self.token_auth = Some(auth);
+        self
+    }
+
+    pub fn with_gc(mut self, gc: Arc<Collector>) -> Self {
+        self.gc = Some(gc);
+        self
+    }
+}
+
+/// Answers one admin request:
+///
+/// - `approvals/list` returns `{"pending": [...]}`
+/// - `approvals/decide` takes `{id, approved, approver?, reason?}`
+/// - `connections/stats` returns the counters of each network transport,
+///   keyed by transport name
+/// - `dlp/stats` returns the counters of each data-loss-prevention pattern
+/// - `variants/stats` returns the call outcomes of each tool description
+///   variant, keyed by tool and then variant
+/// - `backends/status` returns the split and outcomes of each blue/green
+///   tool, keyed by tool
+/// - `backends/split` takes `{tool, greenPercent}` and clears any rollback
+/// - `config/list` returns the tool config values by namespace and key,
+///   or those of one namespace given `{namespace}`
+/// - `config/set` takes `{namespace, key, value}`
+/// - `config/delete` takes `{namespace, key}` and returns `{removed}`
+/// - `templates/reload` rereads the result templates file and returns
+///   `{tools}`, the tools that now have a template
+/// - `children/list` returns `{children}`, the running child servers
+/// - `children/stop` takes `{id}` and returns `{stopped}`
+/// - `debug/history` returns `{snapshots}`, the dispatcher's recent states,
+///   or those from sequence number `since` on given `{since}`
+/// - `warmup/status` returns `{tools}`, how each tool's warm-up went
+/// - `auth/tokens` returns `{tokens}`, the fingerprint and any `expiresAt`
+///   of each token still accepted
+/// - `auth/rotate` takes `{token?, expiresAt?, graceSecs?}`, starts
+///   accepting the token, or a fresh one, and has the others expire after
+///   `graceSecs` (default 3600); it returns the new `{token, fingerprint}`
+/// - `auth/bans` returns `{bans}`, the addresses banned for wrong tokens
+/// - `auth/unban` takes `{ip}` and returns `{unbanned}`
+/// - `gc/stats` returns the garbage collector's counters; `gc`, answered by
+///   [`handle_gc`], runs a pass first
+pub fn handle(admin: &Admin, request: McpRequest) -> McpResponse {
+    let approvals = &admin.approvals;
+    let params = request.params.unwrap_or(Value::Null);
+    let result = match request.method.as_str() {
+        "approvals/list" => Ok(serde_json::json!({ "pending": approvals.pending() })),
+        "approvals/decide" => {
+            let id = params.get("id").and_then(|v| v.as_str());
+            let approved = params.get("approved").and_then(|v| v.as_bool());
+            match (id, approved) {
+                (Some(id), Some(approved)) => approvals
+                    .decide(
+                        id,
+                        approved,
+                        params.get("approver").and_then(|v| v.as_str()),
+                        params.get("reason").and_then(|v| v.as_str()),
+                    )
+                    .map(|()| serde_json::json!({}))
+                    .map_err(|message| (-32602, message)),
+                _ => Err((-32602, "Invalid params".to_string())),
+            }
+        }
+        "connections/stats" => Ok(Value::Object(
+            admin
+                .connections
+                .iter()
+                .map(|l| {
+                    (
+                        l.transport().to_string(),
+                        serde_json::to_value(l.stats()).unwrap(),
+                    )
+                })
+                .collect(),
+        )),
+        "dlp/stats" => Ok(serde_json::to_value(admin.dlp.stats()).unwrap()),
+        "variants/stats" => Ok(serde_json::to_value(admin.variants.stats()).unwrap()),
+        "backends/status" => Ok(serde_json::to_value(admin.backends.status()).unwrap()),
+        "backends/split" => {
+            let tool = params.get("tool").and_then(|v| v.as_str());
+            let percent = params.get("greenPercent").and_then(|v| v.as_u64());
+            match (tool, percent) {
+                (Some(tool), Some(percent @ 0..=100)) => match admin.backends.get(tool) {
+                    Some(switch) => {
+                        switch.set_green_percent(percent as u8);
+                        Ok(serde_json::to_value(switch.status()).unwrap())
+                    }
+                    None => Err((-32602, format!("Tool '{}' has no backends", tool))),
+                },
+                _ => Err((-32602, "Invalid params".to_string())),
+            }
+        }
+        "config/list" => match params.get("namespace").and_then(|v| v.as_str()) {
+            Some(namespace) => {
+                Ok(serde_json::to_value(admin.tool_config.namespace(namespace)).unwrap())
+            }
+            None => Ok(serde_json::to_value(admin.tool_config.all()).unwrap()),
+        },
+        "config/set" => {
+            let namespace = params.get("namespace").and_then(|v| v.as_str());
+            let key = params.get("key").and_then(|v| v.as_str());
+            match (namespace, key, params.get("value")) {
+                (Some(namespace), Some(key), Some(value)) => admin
+                    .tool_config
+                    .set(namespace, key, value.clone())
+                    .map(|()| serde_json::json!({}))
+                    .map_err(|message| (-32603, message)),
+                _ => Err((-32602, "Invalid params".to_string())),
+            }
+        }
+        "config/delete" => {
+            let namespace = params.get("namespace").and_then(|v| v.as_str());
+            let key = params.get("key").and_then(|v| v.as_str());
+            match (namespace, key) {
+                (Some(namespace), Some(key)) => admin
+                    .tool_config
+                    .remove(namespace, key)
+                    .map(|removed| serde_json::json!({ "removed": removed }))
+                    .map_err(|message| (-32603, message)),
+                _ => Err((-32602, "Invalid params".to_string())),
+            }
+        }
+        "templates/reload" => admin
+            .templates
+            .reload()
+            .map(|tools| serde_json::json!({ "tools": tools }))
+            .map_err(|message| (-32603, message)),
+        "children/list" => Ok(serde_json::json!({ "children": admin.children.list() })),
+        "children/stop" => match params.get("id").and_then(|v| v.as_str()) {
+            Some(id) => Ok(serde_json::json!({ "stopped": admin.children.stop(id) })),
+            None => Err((-32602, "Invalid params".to_string())),
+        },
+        "debug/history" => {
+            let since = params.get("since").and_then(|v| v.as_u64());
+            Ok(serde_json::json!({ "snapshots": admin.timeline.history(since) }))
+        }
+        "warmup/status" => Ok(serde_json::json!({ "tools": admin.warmups.status() })),
+        "gc/stats" => match &admin.gc {
+            Some(gc) => Ok(serde_json::to_value(gc.stats()).unwrap()),
+            None => Err((-32601, "Garbage collection is not available".to_string())),
+        },
+        method if method.starts_with("auth/") => match &admin.token_auth {
+            Some(auth) => handle_auth(auth, method, &params),
+            None => Err((-32601, "Token authentication is not enabled".to_string())),
+        },
+        _ => Err((-32601, "Method not found".to_string())),
+    };
+    respond(request.id, result)
+}
+
+/// Runs a garbage-collection pass in the server and returns `{report,
+/// stats}`, the pass's [`GcReport`](crate::gc::GcReport) and the running
+/// counters. Unlike `mcp-server gc`, it sees the sessions and scratch
+/// directories in use.
+pub async fn handle_gc(admin: &Admin, request: McpRequest) -> McpResponse {
+    let result = match &admin.gc {
+        Some(gc) => {
+            let report = gc.run().await;
+            Ok(serde_json::json!({ "report": report, "stats": gc.stats() }))
+        }
+        None => Err((-32601, "Garbage collection is not available".to_string())),
+    };
+    respond(request.id, result)
+}
+
+fn respond(id: Option<Value>, result: Result<Value, (i32, String)>) -> McpResponse {
+    let (result, error) = match result {
+        Ok(result) => (Some(result), None),
+        Err((code, message)) => (
+            None,
+            Some(McpError {
+                code,
+                message,
+                data: None,
+            }),
+        ),
+    };
+    McpResponse {
+        jsonrpc: "2.0".to_string(),
+        id,
+        result,
+        error,
+    }
+}
+
+/// Answers the `auth/` methods of [`handle`].
+fn handle_auth(auth: &TokenAuth, method: &str, params: &Value) -> Result<Value, (i32, String)> {
+    let invalid = || (-32602, "Invalid params".to_string());
+    match method {
+        "auth/tokens" => {
+            let tokens: Vec<Value> = auth
+                .tokens()
+                .iter()
+                .map(|t| serde_json::json!({"fingerprint": t.fingerprint(), "expiresAt": t.expires_at}))
+                .collect();
+            Ok(serde_json::json!({ "tokens": tokens }))
+        }
+        "auth/rotate" => {
+            let token = match params.get("token") {
+                Some(Value::String(token)) if !token.is_empty() => token.clone(),
+                None => uuid::Uuid::new_v4().simple().to_string(),
+                Some(_) => return Err(invalid()),
+            };
+            let mut token = Token::new(token);
+            if let Some(at) = params.get("expiresAt") {
+                token = token.expiring(at.as_u64().ok_or_else(invalid)?);
+            }
+            let grace = match params.get("graceSecs") {
+                Some(secs) => Duration::from_secs(secs.as_u64().ok_or_else(invalid)?),
+                None => auth::DEFAULT_ROTATION_GRACE,
+            };
+            let rotated =
+                serde_json::json!({"token": token.token, "fingerprint": token.fingerprint()});
+            auth.rotate(token, grace);
+            Ok(rotated)
+        }
+        "auth/bans" => Ok(serde_json::json!({ "bans": auth.bans() })),
+        "auth/unban" => match params.get("ip").and_then(|v| v.as_str()).map(str::parse) {
+            Some(Ok(ip)) => Ok(serde_json::json!({ "unbanned": auth.unban(ip) })),
+            _ => Err(invalid()),
+        },
+        _ => Err((-32601, "Method not found".to_string())),
+    }
+}
+
+/// Serves line-delimited admin requests until `reader` reaches end of input.
+pub async fn serve_lines<R, W>(
+    admin: Arc<Admin>,
+    mut reader: R,
+    mut writer: W,
+) -> std::io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut line = String::new();
+    while reader.read_line(&mut line).await? > 0 {
+        let trimmed = line.trim();
+        if !trimmed.is_empty() {
+            match serde_json::from_str::<McpRequest>(trimmed) {
+                Ok(request) => {
+                    let response = match request.method.as_str() {
+                        "gc" => handle_gc(&admin, request).await,
+                        _ => handle(&admin, request),
+                    };
+                    writer
+                        .write_all(serde_json::to_string(&response)?.as_bytes())
+                        .await?;
+                    writer.write_all(b"\n").await?;
+                    writer.flush().await?;
+                }
+                Err(e) => tracing::warn!("Failed to parse admin request: {}", e),
+            }
+        }
+        line.clear();
+    }
+    Ok(())
+}
+
+/// Listens on a Unix socket at `path` and serves each connection with
+/// [`serve_lines`]. Tools run as the same user as the server, so the socket
+/// is guarded twice over:
+///
+/// - Its directory is created with mode `0700` if missing, and an existing
+///   one that other users can reach is refused. The socket itself is made
+///   `0600`. Deployments keep tools out of this directory, for example by
+///   sandboxing them.
+/// - Each connection must open with an `Authorization: Bearer <token>`
+///   line. A fresh token is written to `<path>.token`, mode `0600`, every
+///   time the server starts; it never appears in the environment that
+///   tools inherit.
+///
+/// A stale socket left at `path` is replaced; any other file there is an
+/// error, never deleted.
+#[cfg(unix)]
+pub async fn serve_unix(path: &std::path::Path, admin: Arc<Admin>) -> std::io::Result<()> {
+    let listener = bind_private(path)?;
+    let token = uuid::Uuid::new_v4().simple().to_string();
+    write_private(&token_path(path), &token)?;
+    let auth = Arc::new(TokenAuth::new([token]));
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let admin = admin.clone();
+        let auth = auth.clone();
+        tokio::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut reader = tokio::io::BufReader::new(reader);
+            let result = match auth::read_token_line(&auth, &mut reader).await {
+                Ok(Ok(())) => serve_lines(admin, reader, writer).await,
+                Ok(Err(e)) => {
+                    tracing::warn!("Refused admin connection: {}", e.message());
+                    auth::refuse_lines(&mut writer, e).await
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                tracing::warn!("Admin connection failed: {}", e);
+            }
+        });
+    }
+}
+
+/// Where [`serve_unix`] writes the token for the socket at `path`.
+pub fn token_path(path: &std::path::Path) -> std::path::PathBuf {
+    let mut token = path.as_os_str().to_owned();
+    token.push(".token");
+    token.into()
+}
+
+#[cfg(unix)]
+fn bind_private(path: &std::path::Path) -> std::io::Result<tokio::net::UnixListener> {
+    use std::io::{Error, ErrorKind};
+    use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => std::path::Path::new("."),
+    };
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    if std::fs::metadata(dir)?.permissions().mode() & 0o077 != 0 {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            format!(
+                "Admin socket directory {} is accessible to other users",
+                dir.display()
+            ),
+        ));
+    }
+
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path)?,
+        Ok(_) => {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("{} exists and is not a socket", path.display()),
+            ))
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let listener = tokio::net::UnixListener::bind(path)?;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    Ok(listener)
+}
+
+/// Writes `contents` to a file only its owner can read, replacing any
+/// earlier one.
+#[cfg(unix)]
+fn write_private(path: &std::path::Path, contents: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    // `mode` only applies to a new file.
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents.as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditLog;
+    use crate::tools::{ToolError, ToolResult};
+    use serde_json::json;
+
+    fn request(method: &str, params: Value) -> McpRequest {
+        McpRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(json!(1)),
+            method: method.to_string(),
+            params: Some(params),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_list_and_decide() {
+        let approvals = Arc::new(ApprovalQueue::new(Duration::from_secs(5), AuditLog::new(1)));
+        approvals.require("python_run");
+        let admin = Admin::new(approvals.clone());
+        let review = tokio::spawn({
+            let approvals = approvals.clone();
+            async move { approvals.review("python_run", &json!({})).await }
+        });
+
+        let pending = loop {
+            let response = handle(&admin, request("approvals/list", json!({})));
+            let pending = response.result.unwrap()["pending"].clone();
+            if !pending.as_array().unwrap().is_empty() {
+                break pending;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        };
+        assert_eq!(pending[0]["tool"], "python_run");
+
+        let response = handle(
+            &admin,
+            request(
+                "approvals/decide",
+                json!({"id": pending[0]["id"], "approved": true}),
+            ),
+        );
+        assert!(response.error.is_none());
+        assert_eq!(review.await.unwrap(), crate::approval::Decision::Approved);
+
+        let response = handle(&admin, request("approvals/decide", json!({"id": "x"})));
+        assert_eq!(response.error.unwrap().code, -32602);
+        let response = handle(&admin, request("tools/list", json!({})));
+        assert_eq!(response.error.unwrap().code, -32601);
+    }
+
+    #[tokio::test]
+    async fn test_gc_runs_in_the_server() {
+        let approvals = Arc::new(ApprovalQueue::new(Duration::from_secs(5), AuditLog::new(1)));
+        let admin = Admin::new(approvals.clone());
+        let response = handle_gc(&admin, request("gc", json!({}))).await;
+        assert_eq!(response.error.unwrap().code, -32601);
+
+        let server = Arc::new(crate::McpServer::new());
+        let collector = Collector::new(server, crate::gc::GcPolicy::from_env());
+        let admin = Admin::new(approvals).with_gc(Arc::new(collector));
+        let response = handle_gc(&admin, request("gc", json!({}))).await;
+        let result = response.result.unwrap();
+        assert!(result["report"]["session_results"].is_u64());
+        assert_eq!(result["stats"]["runs"], 1);
+        let stats = handle(&admin, request("gc/stats", json!({})))
+            .result
+            .unwrap();
+        assert_eq!(stats["runs"], 1);
+        assert!(stats["lastRunAt"].is_string());
+    }
+
+    #[test]
+    fn test_connection_stats() {
+        let approvals = Arc::new(ApprovalQueue::new(Duration::from_secs(5), AuditLog::new(1)));
+        let limiter = Arc::new(ConnectionLimiter::new("tcp", Default::default()));
+        let _permit = limiter.admit("127.0.0.1".parse().unwrap()).unwrap();
+        let admin = Admin::new(approvals).with_connections(limiter);
+
+        let response = handle(&admin, request("connections/stats", json!({})));
+        let stats = response.result.unwrap();
+        assert_eq!(stats["tcp"]["active"], 1);
+        assert_eq!(stats["tcp"]["rejectedDenied"], 0);
+    }
+
+    #[test]
+    fn test_backend_split() {
+        let approvals = Arc::new(ApprovalQueue::new(Duration::from_secs(5), AuditLog::new(1)));
+        let backends = Arc::new(Backends::default());
+        let switch = Arc::new(crate::backends::Switch::new(10, Default::default()));
+        backends.insert("geocode", switch.clone());
+        let admin = Admin::new(approvals).with_backends(backends);
+
+        let response = handle(
+            &admin,
+            request(
+                "backends/split",
+                json!({"tool": "geocode", "greenPercent": 50}),
+            ),
+        );
+        assert_eq!(response.result.unwrap()["greenPercent"], 50);
+        assert_eq!(switch.status().green_percent, 50);
+        let status = handle(&admin, request("backends/status", json!({})))
+            .result
+            .unwrap();
+        assert_eq!(status["geocode"]["blue"]["calls"], 0);
+
+        for params in [
+            json!({"tool": "geocode", "greenPercent": 101}),
+            json!({"tool": "other", "greenPercent": 1}),
+        ] {
+            let response = handle(&admin, request("backends/split", params));
+            assert_eq!(response.error.unwrap().code, -32602);
+        }
+    }
+
+    #[test]
+    fn test_edit_tool_config() {
+        let approvals = Arc::new(ApprovalQueue::new(Duration::from_secs(5), AuditLog::new(1)));
+        let store = Arc::new(ToolConfigStore::default());
+        let admin = Admin::new(approvals).with_tool_config(store.clone());
+
+        let set = json!({"namespace": "geocode", "key": "region", "value": "eu"});
+        assert!(handle(&admin, request("config/set", set)).error.is_none());
+        assert_eq!(store.get("geocode", "region"), Some(json!("eu")));
+        let listed = handle(&admin, request("config/list", json!({}))).result;
+        assert_eq!(listed.unwrap(), json!({"geocode": {"region": "eu"}}));
+        let listed = handle(
+            &admin,
+            request("config/list", json!({"namespace": "other"})),
+        );
+        assert_eq!(listed.result.unwrap(), json!({}));
+
+        let delete = json!({"namespace": "geocode", "key": "region"});
+        let deleted = handle(&admin, request("config/delete", delete)).result;
+        assert_eq!(deleted.unwrap()["removed"], true);
+        let response = handle(
+            &admin,
+            request("config/set", json!({"namespace": "geocode"})),
+        );
+        assert_eq!(response.error.unwrap().code, -32602);
+    }
+
+    #[test]
+    fn test_children() {
+        let approvals = Arc::new(ApprovalQueue::new(Duration::from_secs(5), AuditLog::new(1)));
+        let admin = Admin::new(approvals).with_children(Arc::default());
+
+        let listed = handle(&admin, request("children/list", json!({}))).result;
+        assert_eq!(listed.unwrap(), json!({"children": []}));
+        let stopped = handle(&admin, request("children/stop", json!({"id": "x"}))).result;
+        assert_eq!(stopped.unwrap()["stopped"], false);
+        let response = handle(&admin, request("children/stop", json!({})));
+        assert_eq!(response.error.unwrap().code, -32602);
+    }
+
+    #[test]
+    fn test_debug_history() {
+        let approvals = Arc::new(ApprovalQueue::new(Duration::from_secs(5), AuditLog::new(1)));
+        let timeline = Arc::new(Timeline::new(8));
+        timeline.record(
+            "tool registered: echo".into(),
+            vec!["echo".into()].into(),
+            vec![],
+        );
+        timeline.record("tool unregistered: echo".into(), Vec::new().into(), vec![]);
+        let admin = Admin::new(approvals).with_timeline(timeline);
+
+        let history = handle(&admin, request("debug/history", json!({}))).result;
+        let snapshots = history.unwrap()["snapshots"].clone();
+        assert_eq!(snapshots[0]["tools"], json!(["echo"]));
+        assert_eq!(snapshots[0]["inFlight"], json!([]));
+        let since = handle(&admin, request("debug/history", json!({"since": 2}))).result;
+        assert_eq!(
+            since.unwrap()["snapshots"][0]["change"],
+            "tool unregistered: echo"
+        );
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_socket_is_private_and_needs_its_token() {
+        use std::os::unix::fs::PermissionsExt;
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+        let root = std::env::temp_dir().join(format!("mcp-admin-{}", uuid::Uuid::new_v4()));
+        let path = root.join("admin").join("admin.sock");
+        let admin = || {
+            let approvals = ApprovalQueue::new(Duration::from_secs(5), AuditLog::new(1));
+            Arc::new(Admin::new(Arc::new(approvals)))
+        };
+        let serving = tokio::spawn({
+            let path = path.clone();
+            let admin = admin();
+            async move { serve_unix(&path, admin).await }
+        });
+        let token = loop {
+            match std::fs::read_to_string(token_path(&path)) {
+                Ok(token) if token.len() == 32 => break token,
+                _ => tokio::time::sleep(Duration::from_millis(5)).await,
+            }
+        };
+        let mode = |p: &std::path::Path| std::fs::metadata(p).unwrap().permissions().mode() & 0o777;
+        assert_eq!(mode(path.parent().unwrap()), 0o700);
+        assert_eq!(mode(&path), 0o600);
+        assert_eq!(mode(&token_path(&path)), 0o600);
+
+        let exchange = |first: String| {
+            let path = path.clone();
+            async move {
+                let stream = tokio::net::UnixStream::connect(&path).await.unwrap();
+                let (reader, mut writer) = stream.into_split();
+                let list = r#"{"jsonrpc":"2.0","id":1,"method":"approvals/list"}"#;
+                writer
+                    .write_all(format!("{}\n{}\n", first, list).as_bytes())
+                    .await
+                    .unwrap();
+                let mut line = String::new();
+                BufReader::new(reader).read_line(&mut line).await.unwrap();
+                serde_json::from_str::<McpResponse>(&line).unwrap()
+            }
+        };
+        let refused = exchange("Authorization: Bearer guess".to_string()).await;
+        assert_eq!(refused.error.unwrap().code, auth::UNAUTHORIZED);
+        let listed = exchange(format!("Authorization: Bearer {}", token)).await;
+        assert_eq!(listed.result.unwrap(), json!({"pending": []}));
+        serving.abort();
+
+        // A file in the socket's place is left alone.
+        std::fs::remove_file(&path).unwrap();
+        std::fs::write(&path, "keep").unwrap();
+        let err = serve_unix(&path, admin()).await.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::AlreadyExists);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "keep");
+
+        // As is a directory other users can reach.
+        let shared = root.join("shared");
+        std::fs::create_dir(&shared).unwrap();
+        std::fs::set_permissions(&shared, std::fs::Permissions::from_mode(0o755)).unwrap();
+        let err = serve_unix(&shared.join("admin.sock"), admin())
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::PermissionDenied);
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_rotate_tokens() {
+        let approvals = Arc::new(ApprovalQueue::new(Duration::from_secs(5), AuditLog::new(1)));
+        let admin = Admin::new(approvals);
+        let response = handle(&admin, request("auth/tokens", json!({})));
+        assert_eq!(response.error.unwrap().code, -32601);
+
+        let auth = Arc::new(TokenAuth::new(["old"]));
+        let admin = admin.with_token_auth(auth.clone());
+        let response = handle(&admin, request("auth/rotate", json!({"graceSecs": 0})));
+        let rotated = response.result.unwrap();
+        let token = rotated["token"].as_str().unwrap();
+        assert_eq!(auth.verify(token), Ok(()));
+        assert!(auth.verify("old").is_err());
+
+        let response = handle(&admin, request("auth/tokens", json!({})));
+        let listed = response.result.unwrap();
+        assert_eq!(listed["tokens"][0]["fingerprint"], rotated["fingerprint"]);
+        assert!(!listed.to_string().contains(token));
+
+        let response = handle(&admin, request("auth/unban", json!({"ip": "192.0.2.1"})));
+        assert_eq!(response.result.unwrap()["unbanned"], false);
+        let response = handle(&admin, request("auth/bans", json!({})));
+        assert_eq!(response.result.unwrap()["bans"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_warmup_status() {
+        struct Model;
+
+        #[async_trait::async_trait]
+        impl crate::tools::ToolHandler for Model {
+            async fn call(&self, _args: Value) -> Result<ToolResult, ToolError> {
+                Ok(ToolResult::text("ok"))
+            }
+
+            fn warms_up(&self) -> bool {
+                true
+            }
+        }
+
+        let approvals = Arc::new(ApprovalQueue::new(Duration::from_secs(5), AuditLog::new(1)));
+        let warmups = Arc::new(Warmups::default());
+        warmups.track("model", &Model);
+        let admin = Admin::new(approvals).with_warmups(warmups.clone());
+        let status = handle(&admin, request("warmup/status", json!({}))).result;
+        assert_eq!(
+            status.unwrap(),
+            json!({"tools": {"model": {"state": "pending"}}})
+        );
+
+        let handler: Arc<dyn crate::tools::ToolHandler> = Arc::new(Model);
+        warmups.ensure("model", &handler).await.unwrap();
+        let status = handle(&admin, request("warmup/status", json!({}))).result;
+        assert_eq!(status.unwrap()["tools"]["model"]["state"], "ready");
+    }
+}
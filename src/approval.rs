@@ -0,0 +1,352 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::oneshot;
+
+use crate::audit::AuditLog;
+use crate::events::{EventBus, EventKind};
+use crate::security::{SecurityEvent, SecurityEventKind, SecurityLog};
+use crate::tools::ToolError;
+
+pub const DEFAULT_TIMEOUT_SECS: u64 = 300;
+
+/// What happened to a parked call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decision {
+    Approved,
+    Rejected(String),
+    TimedOut,
+}
+
+/// A call waiting for a human decision, as shown to approvers.
+#[derive(Debug, Clone, Serialize)]
+pub struct PendingCall {
+    pub id: String,
+    pub tool: String,
+    pub arguments: Value,
+    #[serde(rename = "requestedAt")]
+    pub requested_at: u64,
+}
+
+struct Pending {
+    call: PendingCall,
+    decide: oneshot::Sender<Decision>,
+}
+
+/// Parks calls to designated tools until an approver accepts or rejects them
+/// (or the timeout passes), and writes every step to an audit log.
+pub struct ApprovalQueue {
+    tools: Mutex<HashSet<String>>,
+    timeout: Duration,
+    pending: Mutex<HashMap<String, Pending>>,
+    audit: AuditLog,
+    events: EventBus,
+    security: SecurityLog,
+}
+
+impl ApprovalQueue {
+    pub fn new(timeout: Duration, audit: AuditLog) -> Self {
+        Self {
+            tools: Mutex::new(HashSet::new()),
+            timeout,
+            pending: Mutex::new(HashMap::new()),
+            audit,
+            events: EventBus::new(),
+            security: SecurityLog::default(),
+        }
+    }
+
+    /// Publishes an `approval_requested` event for every parked call.
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Reports rejected and timed-out calls as policy denials.
+    pub fn with_security(mut self, security: SecurityLog) -> Self {
+        self.security = security;
+        self
+    }
+
+    /// Queue for the tools in `MCP_APPROVAL_TOOLS` (comma-separated), using
+    /// `MCP_APPROVAL_TIMEOUT_SECS` and the sinks of [`AuditLog::from_env`].
+    pub fn from_env() -> Self {
+        let timeout = std::env::var("MCP_APPROVAL_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+        let queue = Self::new(Duration::from_secs(timeout), AuditLog::from_env());
+        if let Ok(tools) = std::env::var("MCP_APPROVAL_TOOLS") {
+            for tool in tools.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+                queue.require(tool);
+            }
+        }
+        queue
+    }
+
+    /// Makes every later call to `tool` wait for approval.
+    pub fn require(&self, tool: &str) {
+        self.tools.lock().unwrap().insert(tool.to_string());
+    }
+
+    pub fn requires(&self, tool: &str) -> bool {
+        self.tools.lock().unwrap().contains(tool)
+    }
+
+    /// Parks the call until it is decided and returns the decision. Calls to
+    /// tools that do not need approval are approved immediately.
+    pub async fn review(&self, tool: &str, arguments: &Value) -> Decision {
+        if !self.requires(tool) {
+            return Decision::Approved;
+        }
+
+        let (decide, decision) = oneshot::channel();
+        let call = PendingCall {
+            id: uuid::Uuid::new_v4().to_string(),
+            tool: tool.to_string(),
+            arguments: arguments.clone(),
+            requested_at: unix_now(),
+        };
+        let id = call.id.clone();
+        self.audit(serde_json::json!({
+            "event": "requested",
+            "id": id,
+            "tool": tool,
+            "arguments": arguments,
+        }));
+        self.events.publish(
+            EventKind::ApprovalRequested,
+            serde_json::to_value(&call).unwrap(),
+        );
+        self.pending
+            .lock()
+            .unwrap()
+            .insert(id.clone(), Pending { call, decide });
+
+        match tokio::time::timeout(self.timeout, decision).await {
+            Ok(Ok(decision)) => decision,
+            // Timed out, or the sender was dropped without deciding.
+            _ => {
+                self.pending.lock().unwrap().remove(&id);
+                self.audit(serde_json::json!({
+                    "event": "timed_out",
+                    "id": id,
+                    "tool": tool,
+                }));
+                self.security.report(
+                    SecurityEvent::new(
+                        SecurityEventKind::PolicyDenied,
+                        format!("Approval for '{}' timed out", tool),
+                    )
+                    .rule("approval_timeout")
+                    .tool(tool)
+                    .detail("approvalId", id.as_str()),
+                );
+                Decision::TimedOut
+            }
+        }
+    }
+
+    /// [`review`](Self::review) as a tool error: rejected and timed-out calls
+    /// fail without running.
+    pub async fn check(&self, tool: &str, arguments: &Value) -> Result<(), ToolError> {
+        match self.review(tool, arguments).await {
+            Decision::Approved => Ok(()),
+            Decision::Rejected(reason) => Err(ToolError::Execution(format!(
+                "Call to '{}' was rejected: {}",
+                tool, reason
+            ))),
+            Decision::TimedOut => Err(ToolError::Execution(format!(
+                "Approval for '{}' timed out after {}s",
+                tool,
+                self.timeout.as_secs()
+            ))),
+        }
+    }
+
+    pub fn pending(&self) -> Vec<PendingCall> {
+        let mut calls: Vec<PendingCall> = self
+            .pending
+            .lock()
+            .unwrap()
+            .values()
+            .map(|p| p.call.clone())
+            .collect();
+        calls.sort_by_key(|c| c.requested_at);
+        calls
+    }
+
+    /// Releases a parked call. `approver` and `reason` are recorded in the
+    /// audit log; `reason` is also returned to the client on rejection.
+    pub fn decide(
+        &self,
+        id: &str,
+        approved: bool,
+        approver: Option<&str>,
+        reason: Option<&str>,
+    ) -> Result<(), String> {
+        let pending = self
+            .pending
+            .lock()
+            .unwrap()
+            .remove(id)
+            .ok_or_else(|| format!("No pending call with id {}", id))?;
+
+        self.audit(serde_json::json!({
+            "event": if approved { "approved" } else { "rejected" },
+            "id": id,
+            "tool": pending.call.tool,
+            "approver": approver,
+            "reason": reason,
+        }));
+        let decision = if approved {
+            Decision::Approved
+        } else {
+            let mut denial = SecurityEvent::new(
+                SecurityEventKind::PolicyDenied,
+                format!("Call to '{}' was rejected", pending.call.tool),
+            )
+            .rule("approval_rejected")
+            .tool(pending.call.tool.as_str())
+            .detail("approvalId", id);
+            if let Some(approver) = approver {
+                denial = denial.detail("approver", approver);
+            }
+            if let Some(reason) = reason {
+                denial = denial.detail("reason", reason);
+            }
+            self.security.report(denial);
+            Decision::Rejected(reason.unwrap_or("rejected by approver").to_string())
+        };
+        // The caller may have timed out in the meantime; nothing to release.
+        let _ = pending.decide.send(decision);
+        Ok(())
+    }
+
+    fn audit(&self, mut entry: Value) {
+        entry["source"] = "approval".into();
+        self.audit.record(entry);
+    }
+
+    /// Blocks until the audit records written so far reach their sinks.
+    pub fn flush_audit(&self) {
+        self.audit.flush();
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::FileSink;
+    use serde_json::json;
+    use std::path::PathBuf;
+    use std::sync::Arc;
+
+    fn queue(timeout: Duration) -> (Arc<ApprovalQueue>, PathBuf) {
+        let audit = std::env::temp_dir().join(format!("mcp-approval-{}.log", uuid::Uuid::new_v4()));
+        let log = AuditLog::new(16).with_sink(FileSink::new(&audit));
+        let queue = ApprovalQueue::new(timeout, log);
+        queue.require("python_run");
+        (Arc::new(queue), audit)
+    }
+
+    async fn wait_for_pending(queue: &ApprovalQueue) -> PendingCall {
+        loop {
+            if let Some(call) = queue.pending().pop() {
+                return call;
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_undesignated_tools_pass_through() {
+        let (queue, _) = queue(Duration::from_secs(1));
+        assert_eq!(queue.review("echo", &json!({})).await, Decision::Approved);
+        assert!(queue.pending().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_decisions_release_parked_calls_and_are_audited() {
+        let (queue, audit) = queue(Duration::from_secs(5));
+
+        let review = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.review("python_run", &json!({"code": "1"})).await }
+        });
+        let call = wait_for_pending(&queue).await;
+        assert_eq!(call.arguments, json!({"code": "1"}));
+        queue
+            .decide(&call.id, false, Some("ops"), Some("not today"))
+            .unwrap();
+        assert_eq!(
+            review.await.unwrap(),
+            Decision::Rejected("not today".to_string())
+        );
+        assert!(queue.decide(&call.id, true, None, None).is_err());
+
+        queue.flush_audit();
+        let log = std::fs::read_to_string(&audit).unwrap();
+        std::fs::remove_file(&audit).unwrap();
+        let events: Vec<Value> = log
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(events[0]["event"], "requested");
+        assert_eq!(events[1]["event"], "rejected");
+        assert_eq!(events[1]["approver"], "ops");
+    }
+
+    #[tokio::test]
+    async fn test_rejections_are_reported_as_policy_denials() {
+        let path = std::env::temp_dir().join(format!("mcp-security-{}.log", uuid::Uuid::new_v4()));
+        let security = SecurityLog::new(AuditLog::new(16).with_sink(FileSink::new(&path)));
+        let queue = Arc::new(
+            ApprovalQueue::new(Duration::from_secs(5), AuditLog::new(1))
+                .with_security(security.clone()),
+        );
+        queue.require("python_run");
+
+        let review = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.review("python_run", &json!({})).await }
+        });
+        let call = wait_for_pending(&queue).await;
+        queue
+            .decide(&call.id, false, Some("ops"), Some("change freeze"))
+            .unwrap();
+        review.await.unwrap();
+        security.flush();
+
+        let log = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let event: Value = serde_json::from_str(log.trim()).unwrap();
+        assert_eq!(event["event"]["action"], "policy_denied");
+        assert_eq!(event["rule"]["name"], "approval_rejected");
+        assert_eq!(event["mcp"]["tool"], "python_run");
+        assert_eq!(event["mcp"]["approver"], "ops");
+        assert!(event["@timestamp"].is_string());
+        assert!(event.get("at").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_undecided_calls_time_out() {
+        let (queue, audit) = queue(Duration::from_millis(20));
+        assert_eq!(
+            queue.review("python_run", &json!({})).await,
+            Decision::TimedOut
+        );
+        assert!(queue.pending().is_empty());
+        queue.flush_audit();
+        std::fs::remove_file(&audit).unwrap();
+    }
+}
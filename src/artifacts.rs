@@ -0,0 +1,407 @@
+//! Files generated by tools, such as reports or rendered documents, kept
+//! for a limited time so the client can fetch them. A published artifact is
+//! listed in `resources/list` as `artifact://<id>` and, over the HTTP
+//! transport, can be downloaded from a signed URL that stops working when
+//! the artifact expires. Publishing, downloads and expiry are written to an
+//! [`AuditLog`].
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::audit::AuditLog;
+use crate::crypto;
+use crate::resources::{Resource, ResourceContents};
+
+pub const SCHEME: &str = "artifact://";
+/// Path of the HTTP transport's download endpoint; the id follows it.
+pub const DOWNLOAD_PATH: &str = "/mcp/artifacts/";
+pub const DEFAULT_TTL_SECS: u64 = 3600;
+pub const DEFAULT_MAX_BYTES: usize = 16 * 1024 * 1024;
+
+/// A published artifact, as returned to the publishing tool.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Artifact {
+    pub id: String,
+    pub uri: String,
+    pub name: String,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    pub size: u64,
+    pub tool: String,
+    /// Unix seconds after which the artifact is gone.
+    #[serde(rename = "expiresAt")]
+    pub expires_at: u64,
+    /// Signed download URL, when `MCP_ARTIFACT_BASE_URL` is set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+/// Why a download was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DownloadError {
+    /// Unknown id, or an artifact that has expired.
+    NotFound,
+    /// The signature is missing, does not match, or its link has expired.
+    Forbidden,
+    /// Blocked by the server's data-loss-prevention filters.
+    Withheld,
+}
+
+/// Stores artifacts in a private directory under the system temp directory,
+/// removed along with the store.
+pub struct ArtifactStore {
+    dir: PathBuf,
+    ttl: Duration,
+    max_bytes: usize,
+    /// Key for download URL signatures.
+    secret: Vec<u8>,
+    base_url: Option<String>,
+    artifacts: Mutex<HashMap<String, Artifact>>,
+    audit: AuditLog,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl ArtifactStore {
+    /// A store whose download URLs are signed with `secret`.
+    pub fn new(secret: impl Into<Vec<u8>>, ttl: Duration, audit: AuditLog) -> Result<Self, String> {
+        let dir = std::env::temp_dir().join(format!("mcp-artifacts-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create artifact directory: {}", e))?;
+        Ok(Self {
+            dir,
+            ttl,
+            max_bytes: DEFAULT_MAX_BYTES,
+            secret: secret.into(),
+            base_url: None,
+            artifacts: Mutex::new(HashMap::new()),
+            audit,
+        })
+    }
+
+    /// Prefix for the signed URLs handed out, e.g. `https://mcp.example.com`.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into().trim_end_matches('/').to_string());
+        self
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    /// The store when `MCP_ARTIFACTS` is `1` or `true`, with
+    /// `MCP_ARTIFACT_TTL_SECS`, `MCP_ARTIFACT_MAX_BYTES`,
+    /// `MCP_ARTIFACT_BASE_URL` and `MCP_ARTIFACT_SECRET`. Without a secret a
+    /// random one is used, so links do not survive a restart.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        if !std::env::var("MCP_ARTIFACTS").is_ok_and(|v| v == "1" || v == "true") {
+            return Ok(None);
+        }
+        let secs = |key: &str| std::env::var(key).ok().and_then(|s| s.parse::<u64>().ok());
+        let secret = std::env::var("MCP_ARTIFACT_SECRET")
+            .ok()
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| format!("{}{}", uuid::Uuid::new_v4(), uuid::Uuid::new_v4()));
+        let ttl = Duration::from_secs(secs("MCP_ARTIFACT_TTL_SECS").unwrap_or(DEFAULT_TTL_SECS));
+        let mut store = Self::new(secret, ttl, AuditLog::from_env())?;
+        if let Some(max) = secs("MCP_ARTIFACT_MAX_BYTES") {
+            store.max_bytes = max as usize;
+        }
+        if let Ok(base_url) = std::env::var("MCP_ARTIFACT_BASE_URL") {
+            store = store.with_base_url(base_url);
+        }
+        Ok(Some(store))
+    }
+
+    fn audit(&self, mut entry: serde_json::Value) {
+        entry["source"] = "artifacts".into();
+        self.audit.record(entry);
+    }
+
+    /// Stores `contents` on behalf of `tool` until the store's TTL, or `ttl`
+    /// when given, has passed.
+    pub fn publish(
+        &self,
+        tool: &str,
+        name: &str,
+        mime_type: &str,
+        contents: &[u8],
+        ttl: Option<Duration>,
+    ) -> Result<Artifact, String> {
+        if name.is_empty() || name.contains(['/', '\\', '\0']) {
+            return Err(format!("Invalid artifact name '{}'", name));
+        }
+        if contents.len() > self.max_bytes {
+            return Err(format!("Artifacts are limited to {} bytes", self.max_bytes));
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        std::fs::write(self.dir.join(&id), contents)
+            .map_err(|e| format!("Failed to store artifact: {}", e))?;
+
+        let expires_at = unix_now() + ttl.unwrap_or(self.ttl).as_secs();
+        let mut artifact = Artifact {
+            uri: format!("{}{}", SCHEME, id),
+            id,
+            name: name.to_string(),
+            mime_type: mime_type.to_string(),
+            size: contents.len() as u64,
+            tool: tool.to_string(),
+            expires_at,
+            url: None,
+        };
+        artifact.url = self
+            .base_url
+            .as_ref()
+            .map(|base| format!("{}{}", base, self.download_target(&artifact)));
+        self.audit(serde_json::json!({
+            "event": "artifact_published",
+            "id": artifact.id,
+            "tool": tool,
+            "name": name,
+            "mimeType": mime_type,
+            "size": artifact.size,
+            "expiresAt": expires_at,
+        }));
+        self.artifacts
+            .lock()
+            .unwrap()
+            .insert(artifact.id.clone(), artifact.clone());
+        Ok(artifact)
+    }
+
+    /// Artifacts that have not expired, oldest expiry first.
+    pub fn list(&self) -> Vec<Artifact> {
+        let now = unix_now();
+        let mut artifacts: Vec<Artifact> = self
+            .artifacts
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|a| a.expires_at > now)
+            .cloned()
+            .collect();
+        artifacts.sort_by(|a, b| (a.expires_at, &a.id).cmp(&(b.expires_at, &b.id)));
+        artifacts
+    }
+
+    pub fn get(&self, id: &str) -> Option<Artifact> {
+        let artifacts = self.artifacts.lock().unwrap();
+        artifacts
+            .get(id)
+            .filter(|a| a.expires_at > unix_now())
+            .cloned()
+    }
+
+    fn contents(&self, artifact: &Artifact) -> Result<Vec<u8>, String> {
+        std::fs::read(self.dir.join(&artifact.id))
+            .map_err(|e| format!("Failed to read artifact {}: {}", artifact.id, e))
+    }
+
+    /// Path and query of the signed download URL; the link is valid until
+    /// the artifact expires.
+    pub fn download_target(&self, artifact: &Artifact) -> String {
+        format!(
+            "{}{}?expires={}&sig={}",
+            DOWNLOAD_PATH,
+            artifact.id,
+            artifact.expires_at,
+            self.signature(&artifact.id, artifact.expires_at)
+        )
+    }
+
+    fn signature(&self, id: &str, expires: u64) -> String {
+        let message = format!("{}\n{}", id, expires);
+        crypto::hex(&crypto::hmac_sha256(&self.secret, message.as_bytes()))
+    }
+
+    /// Checks a download link and returns the artifact with its contents.
+    /// `client` identifies the downloader in the audit record.
+    pub fn download(
+        &self,
+        id: &str,
+        expires: Option<&str>,
+        signature: Option<&str>,
+        client: &str,
+    ) -> Result<(Artifact, Vec<u8>), DownloadError> {
+        let (Some(expires), Some(signature)) = (expires.and_then(|e| e.parse().ok()), signature)
+        else {
+            return Err(DownloadError::Forbidden);
+        };
+        let expected = self.signature(id, expires);
+        if expires <= unix_now()
+            || !crypto::constant_time_eq(expected.as_bytes(), signature.as_bytes())
+        {
+            return Err(DownloadError::Forbidden);
+        }
+        let artifact = self.get(id).ok_or(DownloadError::NotFound)?;
+        let contents = self
+            .contents(&artifact)
+            .map_err(|_| DownloadError::NotFound)?;
+        self.audit(serde_json::json!({
+            "event": "artifact_downloaded",
+            "id": id,
+            "client": client,
+        }));
+        Ok((artifact, contents))
+    }
+
+    /// The `resources/list` entries for the live artifacts.
+    pub fn resources(&self) -> Vec<Resource> {
+        self.list()
+            .into_iter()
+            .map(|artifact| Resource {
+                description: Some(format!("Artifact published by {}", artifact.tool)),
+                uri: artifact.uri,
+                name: artifact.name,
+                mime_type: Some(artifact.mime_type),
+            })
+            .collect()
+    }
+
+    /// Contents of an `artifact://` URI, or `None` when it is not a live
+    /// artifact.
+    pub fn read(&self, uri: &str) -> Result<Option<Vec<ResourceContents>>, String> {
+        let Some(artifact) = uri.strip_prefix(SCHEME).and_then(|id| self.get(id)) else {
+            return Ok(None);
+        };
+        let bytes = self.contents(&artifact)?;
+        let mime = Some(artifact.mime_type);
+        Ok(Some(vec![match String::from_utf8(bytes) {
+            Ok(text) => ResourceContents::text(uri, mime, text),
+            Err(e) => ResourceContents::blob(uri, mime, e.as_bytes()),
+        }]))
+    }
+
+    /// Deletes expired artifacts and returns how many were removed and
+    /// their total size in bytes.
+    pub fn expire(&self) -> (usize, u64) {
+        let now = unix_now();
+        let mut artifacts = self.artifacts.lock().unwrap();
+        let expired: Vec<Artifact> = artifacts
+            .values()
+            .filter(|a| a.expires_at <= now)
+            .cloned()
+            .collect();
+        let mut bytes = 0;
+        for artifact in &expired {
+            artifacts.remove(&artifact.id);
+            let _ = std::fs::remove_file(self.dir.join(&artifact.id));
+            bytes += artifact.size;
+            self.audit(serde_json::json!({
+                "event": "artifact_expired",
+                "id": artifact.id,
+                "tool": artifact.tool,
+            }));
+        }
+        (expired.len(), bytes)
+    }
+}
+
+impl Drop for ArtifactStore {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audit::AuditSink;
+    use serde_json::Value;
+    use std::sync::Arc;
+
+    struct Recorder(Arc<Mutex<Vec<Value>>>);
+
+    impl AuditSink for Recorder {
+        fn name(&self) -> String {
+            "recorder".to_string()
+        }
+
+        fn write(&mut self, record: &Value) -> Result<(), String> {
+            self.0.lock().unwrap().push(record.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_publish_download_and_expire() {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        let audit = AuditLog::new(16).with_sink(Recorder(records.clone()));
+        let store = ArtifactStore::new("secret", Duration::from_secs(60), audit)
+            .unwrap()
+            .with_base_url("http://localhost:8080/");
+
+        let artifact = store
+            .publish("report", "q3.csv", "text/csv", b"a,b\n1,2\n", None)
+            .unwrap();
+        assert_eq!(artifact.uri, format!("artifact://{}", artifact.id));
+        let url = artifact.url.clone().unwrap();
+        assert!(url.starts_with("http://localhost:8080/mcp/artifacts/"));
+        assert_eq!(store.resources()[0].name, "q3.csv");
+        let contents = store.read(&artifact.uri).unwrap().unwrap();
+        assert_eq!(contents[0].text.as_deref(), Some("a,b\n1,2\n"));
+
+        let expires = artifact.expires_at.to_string();
+        let signature = store.signature(&artifact.id, artifact.expires_at);
+        let (_, bytes) = store
+            .download(&artifact.id, Some(&expires), Some(&signature), "127.0.0.1")
+            .unwrap();
+        assert_eq!(bytes, b"a,b\n1,2\n");
+        let later = (artifact.expires_at + 60).to_string();
+        assert_eq!(
+            store.download(&artifact.id, Some(&later), Some(&signature), "127.0.0.1"),
+            Err(DownloadError::Forbidden)
+        );
+        assert_eq!(
+            store.download(&artifact.id, None, None, "127.0.0.1"),
+            Err(DownloadError::Forbidden)
+        );
+        assert!(store
+            .publish("report", "../x", "text/plain", b"", None)
+            .is_err());
+
+        let gone = store
+            .publish(
+                "report",
+                "old.txt",
+                "text/plain",
+                b"old",
+                Some(Duration::ZERO),
+            )
+            .unwrap();
+        assert!(store.get(&gone.id).is_none());
+        assert_eq!(store.expire(), (1, 3));
+        assert_eq!(store.list(), vec![artifact]);
+
+        store.audit.flush();
+        let events: Vec<Value> = records
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|r| r["event"].clone())
+            .collect();
+        assert!(records
+            .lock()
+            .unwrap()
+            .iter()
+            .all(|r| r["source"] == "artifacts"));
+        assert_eq!(
+            events,
+            [
+                "artifact_published",
+                "artifact_downloaded",
+                "artifact_published",
+                "artifact_expired"
+            ]
+        );
+    }
+}
@@ -0,0 +1,225 @@
+use serde_json::Value;
+
+use regex::Regex;
+
+pub const OPERATORS: &[&str] = &[
+    "eq",
+    "ne",
+    "gt",
+    "gte",
+    "lt",
+    "lte",
+    "contains",
+    "not_contains",
+    "matches",
+    "exists",
+    "not_exists",
+    "type",
+    "length",
+];
+
+/// Evaluates every assertion and returns `{passed, summary, results}`.
+///
+/// Each assertion is `{actual, path?, op, expected?, message?}`; `path` is a
+/// JSON Pointer into `actual`. Malformed assertions (unknown operator,
+/// invalid pattern) are reported as an error rather than a failed check.
+pub fn evaluate(assertions: &[Value]) -> Result<Value, String> {
+    let mut results = Vec::new();
+    for (index, assertion) in assertions.iter().enumerate() {
+        let op = assertion
+            .get("op")
+            .and_then(|o| o.as_str())
+            .ok_or_else(|| format!("Assertion {} is missing 'op'", index))?;
+        let root = assertion.get("actual").unwrap_or(&Value::Null);
+        let actual = match assertion.get("path").and_then(|p| p.as_str()) {
+            Some(path) => root.pointer(path),
+            None => assertion.get("actual"),
+        };
+        let expected = assertion.get("expected").unwrap_or(&Value::Null);
+
+        let passed =
+            check(op, actual, expected).map_err(|e| format!("Assertion {}: {}", index, e))?;
+
+        let mut result = serde_json::json!({
+            "index": index,
+            "op": op,
+            "passed": passed,
+            "actual": actual,
+            "expected": expected,
+        });
+        if let Some(message) = assertion.get("message") {
+            result["message"] = message.clone();
+        }
+        results.push(result);
+    }
+
+    let passed = results.iter().filter(|r| r["passed"] == true).count();
+    Ok(serde_json::json!({
+        "passed": passed == results.len(),
+        "summary": format!("{}/{} assertions passed", passed, results.len()),
+        "results": results,
+    }))
+}
+
+fn check(op: &str, actual: Option<&Value>, expected: &Value) -> Result<bool, String> {
+    match op {
+        "exists" => return Ok(actual.is_some()),
+        "not_exists" => return Ok(actual.is_none()),
+        _ => {}
+    }
+    let Some(actual) = actual else {
+        if OPERATORS.contains(&op) {
+            return Ok(false);
+        }
+        return Err(format!("Unknown operator '{}'", op));
+    };
+
+    Ok(match op {
+        "eq" => values_equal(actual, expected),
+        "ne" => !values_equal(actual, expected),
+        "gt" => compare(actual, expected).is_some_and(|o| o.is_gt()),
+        "gte" => compare(actual, expected).is_some_and(|o| o.is_ge()),
+        "lt" => compare(actual, expected).is_some_and(|o| o.is_lt()),
+        "lte" => compare(actual, expected).is_some_and(|o| o.is_le()),
+        "contains" => contains(actual, expected),
+        "not_contains" => !contains(actual, expected),
+        "matches" => {
+            let pattern = expected
+                .as_str()
+                .ok_or("'matches' expects a pattern string")?;
+            let regex = Regex::new(pattern).map_err(|e| format!("Invalid pattern: {}", e))?;
+            actual.as_str().is_some_and(|text| regex.is_match(text))
+        }
+        "type" => Some(type_name(actual)) == expected.as_str(),
+        "length" => {
+            let len = match actual {
+                Value::String(s) => Some(s.chars().count()),
+                Value::Array(a) => Some(a.len()),
+                Value::Object(o) => Some(o.len()),
+                _ => None,
+            };
+            len.is_some_and(|len| Some(len as u64) == expected.as_u64())
+        }
+        other => return Err(format!("Unknown operator '{}'", other)),
+    })
+}
+
+/// JSON equality, except that numbers compare by value so `1` equals `1.0`.
+fn values_equal(a: &Value, b: &Value) -> bool {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64() == y.as_f64(),
+        _ => a == b,
+    }
+}
+
+fn compare(a: &Value, b: &Value) -> Option<std::cmp::Ordering> {
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x.as_f64()?.partial_cmp(&y.as_f64()?),
+        (Value::String(x), Value::String(y)) => Some(x.cmp(y)),
+        _ => None,
+    }
+}
+
+fn contains(haystack: &Value, needle: &Value) -> bool {
+    match (haystack, needle) {
+        (Value::String(s), Value::String(n)) => s.contains(n.as_str()),
+        (Value::Array(items), needle) => items.iter().any(|item| values_equal(item, needle)),
+        (Value::Object(map), Value::String(key)) => map.contains_key(key),
+        _ => false,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_path_and_comparison_operators() {
+        let report = evaluate(&[
+            json!({"actual": {"user": {"age": 31}}, "path": "/user/age", "op": "gte", "expected": 18}),
+            json!({"actual": 1, "op": "eq", "expected": 1.0}),
+            json!({"actual": "b", "op": "lt", "expected": "a"}),
+        ])
+        .unwrap();
+
+        assert_eq!(report["passed"], false);
+        assert_eq!(report["summary"], "2/3 assertions passed");
+        assert_eq!(report["results"][0]["actual"], 31);
+        assert_eq!(report["results"][2]["passed"], false);
+    }
+
+    #[test]
+    fn test_contains_type_length_and_exists() {
+        let doc = json!({"tags": ["a", "b"], "name": "widget"});
+        let report = evaluate(&[
+            json!({"actual": doc, "path": "/tags", "op": "contains", "expected": "b"}),
+            json!({"actual": doc, "op": "contains", "expected": "name"}),
+            json!({"actual": doc, "path": "/name", "op": "type", "expected": "string"}),
+            json!({"actual": doc, "path": "/tags", "op": "length", "expected": 2}),
+            json!({"actual": doc, "path": "/missing", "op": "not_exists"}),
+            json!({"actual": doc, "path": "/missing", "op": "eq", "expected": null}),
+        ])
+        .unwrap();
+
+        let passed: Vec<bool> = report["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|r| r["passed"].as_bool().unwrap())
+            .collect();
+        assert_eq!(passed, vec![true, true, true, true, true, false]);
+    }
+
+    #[test]
+    fn test_regex_match() {
+        let report = evaluate(&[
+            json!({
+                "actual": "order 12345 shipped",
+                "op": "matches",
+                "expected": "order \\d+ (shipped|delivered)",
+                "message": "status line"
+            }),
+            json!({"actual": "Zoë 42", "op": "matches", "expected": "^\\p{L}+ [[:digit:]]+$"}),
+            json!({"actual": "A", "op": "matches", "expected": "^\\x41$"}),
+        ])
+        .unwrap();
+
+        assert_eq!(report["passed"], true);
+        assert_eq!(report["results"][0]["message"], "status line");
+    }
+
+    #[test]
+    fn test_malformed_assertions_are_errors() {
+        assert_eq!(
+            evaluate(&[json!({"actual": 1, "op": "approx", "expected": 1})]).unwrap_err(),
+            "Assertion 0: Unknown operator 'approx'"
+        );
+        assert!(
+            evaluate(&[json!({"actual": "x", "op": "matches", "expected": "("})])
+                .unwrap_err()
+                .starts_with("Assertion 0: Invalid pattern")
+        );
+        // Backreferences are not supported and must not match literally.
+        assert!(
+            evaluate(&[json!({"actual": "aa", "op": "matches", "expected": "(a)\\1"})])
+                .unwrap_err()
+                .starts_with("Assertion 0: Invalid pattern")
+        );
+        assert_eq!(
+            evaluate(&[json!({"actual": 1})]).unwrap_err(),
+            "Assertion 0 is missing 'op'"
+        );
+    }
+}
@@ -0,0 +1,581 @@
+//! Audit records, fanned out to pluggable sinks.
+//!
+//! Each sink runs on its own thread behind a bounded queue, so a slow or
+//! unreachable sink never stalls the server or the other sinks. When a
+//! queue is full the record is dropped for that sink only, and the sink
+//! receives an `audit_records_dropped` record with the count once it
+//! catches up, so gaps are visible downstream.
+
+use serde_json::Value;
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::http;
+
+pub const DEFAULT_QUEUE_CAPACITY: usize = 1024;
+/// Rotated files a file sink keeps when `MCP_AUDIT_FILE_KEEP` is unset.
+pub const DEFAULT_ROTATED_FILES: usize = 5;
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+const HTTP_ATTEMPTS: u32 = 3;
+
+/// A destination for audit records. Each sink is owned by its own worker
+/// thread, so `write` may block.
+pub trait AuditSink: Send {
+    /// Short description used in error messages, e.g. `file:/var/log/a.log`.
+    fn name(&self) -> String;
+
+    fn write(&mut self, record: &Value) -> Result<(), String>;
+}
+
+/// Appends one JSON line per record. With rotation, a file about to grow
+/// past its limit is renamed `<path>.1`, the one before it `<path>.2` and
+/// so on, and a fresh file started; records are never rewritten.
+pub struct FileSink {
+    path: PathBuf,
+    file: Option<File>,
+    /// Bytes in the current file.
+    size: u64,
+    /// Largest a file may grow, and how many rotated files to keep; `None`
+    /// to never rotate.
+    rotation: Option<(u64, usize)>,
+}
+
+impl FileSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            file: None,
+            size: 0,
+            rotation: None,
+        }
+    }
+
+    /// Rotates the file before it grows past `max_bytes`, keeping `keep`
+    /// rotated files. A `max_bytes` of `0` never rotates.
+    pub fn with_rotation(mut self, max_bytes: u64, keep: usize) -> Self {
+        self.rotation = (max_bytes > 0).then_some((max_bytes, keep.max(1)));
+        self
+    }
+
+    /// `<path>.<n>`.
+    fn rotated(&self, n: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{}", n));
+        path.into()
+    }
+
+    fn rotate(&mut self, keep: usize) -> Result<(), String> {
+        self.file = None;
+        for n in (1..keep).rev() {
+            let from = self.rotated(n);
+            if from.exists() {
+                std::fs::rename(&from, self.rotated(n + 1)).map_err(|e| e.to_string())?;
+            }
+        }
+        std::fs::rename(&self.path, self.rotated(1)).map_err(|e| e.to_string())
+    }
+}
+
+impl AuditSink for FileSink {
+    fn name(&self) -> String {
+        format!("file:{}", self.path.display())
+    }
+
+    fn write(&mut self, record: &Value) -> Result<(), String> {
+        let line = format!("{}\n", record);
+        if self.file.is_none() {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&self.path)
+                .map_err(|e| e.to_string())?;
+            self.size = file.metadata().map(|m| m.len()).unwrap_or(0);
+            self.file = Some(file);
+        }
+        if let Some((max_bytes, keep)) = self.rotation {
+            if self.size > 0 && self.size + line.len() as u64 > max_bytes {
+                self.rotate(keep)?;
+                return self.write(record);
+            }
+        }
+        let written = self.file.as_mut().unwrap().write_all(line.as_bytes());
+        match written {
+            Ok(()) => self.size += line.len() as u64,
+            // Reopen on the next record, e.g. after log rotation.
+            Err(_) => self.file = None,
+        }
+        written.map_err(|e| e.to_string())
+    }
+}
+
+/// Writes `audit: <json>` lines to stderr.
+pub struct StderrSink;
+
+impl AuditSink for StderrSink {
+    fn name(&self) -> String {
+        "stderr".to_string()
+    }
+
+    fn write(&mut self, record: &Value) -> Result<(), String> {
+        eprintln!("audit: {}", record);
+        Ok(())
+    }
+}
+
+/// Sends RFC 5424 messages (facility authpriv, severity info) whose body is
+/// the JSON record, over UDP or, on Unix, to the local `/dev/log` socket.
+pub struct SyslogSink {
+    target: SyslogTarget,
+    hostname: String,
+}
+
+enum SyslogTarget {
+    Udp(std::net::UdpSocket, String),
+    #[cfg(unix)]
+    Local(std::os::unix::net::UnixDatagram, PathBuf),
+}
+
+/// `<PRI>` for facility authpriv (10) and severity informational (6).
+const SYSLOG_PRIORITY: u8 = 10 * 8 + 6;
+
+impl SyslogSink {
+    /// Sink sending datagrams to `addr` (`host:port`).
+    pub fn udp(addr: &str) -> Result<Self, String> {
+        let socket = std::net::UdpSocket::bind("0.0.0.0:0").map_err(|e| e.to_string())?;
+        socket.connect(addr).map_err(|e| e.to_string())?;
+        Ok(Self::with_target(SyslogTarget::Udp(
+            socket,
+            addr.to_string(),
+        )))
+    }
+
+    /// Sink writing to the local syslog daemon's socket, usually `/dev/log`.
+    #[cfg(unix)]
+    pub fn local(path: &Path) -> Result<Self, String> {
+        let socket = std::os::unix::net::UnixDatagram::unbound().map_err(|e| e.to_string())?;
+        socket.connect(path).map_err(|e| e.to_string())?;
+        Ok(Self::with_target(SyslogTarget::Local(
+            socket,
+            path.to_path_buf(),
+        )))
+    }
+
+    fn with_target(target: SyslogTarget) -> Self {
+        Self {
+            target,
+            hostname: std::env::var("HOSTNAME").unwrap_or_else(|_| "-".to_string()),
+        }
+    }
+
+    /// The message for `record`. The timestamp is left as the RFC 5424
+    /// nil value; receivers stamp arrival time, and the record has `at`.
+    fn format(&self, record: &Value) -> String {
+        format!(
+            "<{}>1 - {} mcp-server {} audit - {}",
+            SYSLOG_PRIORITY,
+            self.hostname,
+            std::process::id(),
+            record
+        )
+    }
+}
+
+impl AuditSink for SyslogSink {
+    fn name(&self) -> String {
+        match &self.target {
+            SyslogTarget::Udp(_, addr) => format!("syslog://{}", addr),
+            #[cfg(unix)]
+            SyslogTarget::Local(_, path) => format!("syslog:{}", path.display()),
+        }
+    }
+
+    fn write(&mut self, record: &Value) -> Result<(), String> {
+        let message = self.format(record);
+        let sent = match &self.target {
+            SyslogTarget::Udp(socket, _) => socket.send(message.as_bytes()),
+            #[cfg(unix)]
+            SyslogTarget::Local(socket, _) => socket.send(message.as_bytes()),
+        };
+        sent.map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
+/// POSTs each record as JSON, retrying failed deliveries a few times.
+pub struct HttpSink {
+    url: http::Url,
+    raw_url: String,
+    /// Created on first use, on the worker thread that owns the sink.
+    runtime: Option<tokio::runtime::Runtime>,
+}
+
+impl HttpSink {
+    pub fn new(url: &str) -> Result<Self, String> {
+        Ok(Self {
+            url: http::Url::parse(url)?,
+            raw_url: url.to_string(),
+            runtime: None,
+        })
+    }
+}
+
+impl AuditSink for HttpSink {
+    fn name(&self) -> String {
+        self.raw_url.clone()
+    }
+
+    fn write(&mut self, record: &Value) -> Result<(), String> {
+        if self.runtime.is_none() {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| e.to_string())?;
+            self.runtime = Some(runtime);
+        }
+        let runtime = self.runtime.as_ref().unwrap();
+        let body = serde_json::to_vec(record).unwrap();
+        let headers = [("Content-Type", "application/json".to_string())];
+        let mut delay = Duration::from_millis(200);
+        let mut attempt = 1;
+        loop {
+            let error = match runtime.block_on(http::post(&self.url, &headers, &body, HTTP_TIMEOUT))
+            {
+                Ok(status) if (200..300).contains(&status) => return Ok(()),
+                Ok(status) => format!("HTTP {}", status),
+                Err(e) => e,
+            };
+            if attempt >= HTTP_ATTEMPTS {
+                return Err(format!("{} after {} attempts", error, attempt));
+            }
+            attempt += 1;
+            std::thread::sleep(delay);
+            delay *= 2;
+        }
+    }
+}
+
+/// Parses one `MCP_AUDIT_SINKS` entry:
+/// - `stderr`
+/// - `file:<path>`, rotated past `MCP_AUDIT_FILE_MAX_BYTES` when set,
+///   keeping `MCP_AUDIT_FILE_KEEP` rotated files
+/// - `syslog` for the local daemon, or `syslog://host:port` over UDP
+/// - an `http://` or `https://` URL
+pub fn parse_sink(spec: &str) -> Result<Box<dyn AuditSink>, String> {
+    if spec == "stderr" {
+        return Ok(Box::new(StderrSink));
+    }
+    if let Some(path) = spec.strip_prefix("file:") {
+        let env = |name: &str| std::env::var(name).ok().and_then(|v| v.trim().parse().ok());
+        let sink = FileSink::new(path).with_rotation(
+            env("MCP_AUDIT_FILE_MAX_BYTES").unwrap_or(0),
+            env("MCP_AUDIT_FILE_KEEP").map_or(DEFAULT_ROTATED_FILES, |keep| keep as usize),
+        );
+        return Ok(Box::new(sink));
+    }
+    if let Some(addr) = spec.strip_prefix("syslog://") {
+        let addr = if addr.contains(':') {
+            addr.to_string()
+        } else {
+            format!("{}:514", addr)
+        };
+        return Ok(Box::new(SyslogSink::udp(&addr)?));
+    }
+    #[cfg(unix)]
+    if spec == "syslog" {
+        return Ok(Box::new(SyslogSink::local(Path::new("/dev/log"))?));
+    }
+    if spec.starts_with("http://") || spec.starts_with("https://") {
+        return Ok(Box::new(HttpSink::new(spec)?));
+    }
+    Err(format!("Unknown audit sink '{}'", spec))
+}
+
+/// Splits a comma-separated list of sink specs.
+pub fn parse_sink_list(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// `MCP_AUDIT_SINKS` plus `MCP_APPROVAL_AUDIT_FILE`, or stderr when both are unset.
+pub(crate) fn sink_specs_from_env() -> Vec<String> {
+    let mut specs = parse_sink_list(&std::env::var("MCP_AUDIT_SINKS").unwrap_or_default());
+    if let Ok(path) = std::env::var("MCP_APPROVAL_AUDIT_FILE") {
+        specs.push(format!("file:{}", path));
+    }
+    if specs.is_empty() {
+        specs.push("stderr".to_string());
+    }
+    specs
+}
+
+enum Message {
+    Record(Value),
+    Flush(mpsc::Sender<()>),
+}
+
+struct Queue {
+    sender: SyncSender<Message>,
+    dropped: Arc<AtomicU64>,
+}
+
+/// Fans records out to every registered sink. With no sinks, records are
+/// discarded.
+pub struct AuditLog {
+    queues: Vec<Queue>,
+    capacity: usize,
+}
+
+impl AuditLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            queues: Vec::new(),
+            capacity: capacity.max(1),
+        }
+    }
+
+    /// Log for the sinks in `MCP_AUDIT_SINKS` (comma-separated), queueing up
+    /// to `MCP_AUDIT_QUEUE_CAPACITY` records per sink. `MCP_APPROVAL_AUDIT_FILE`
+    /// adds a file sink. With neither set, records go to stderr. Sinks that
+    /// fail to open are reported and skipped.
+    pub fn from_env() -> Self {
+        Self::from_specs(&sink_specs_from_env())
+    }
+
+    /// Log for the given [`parse_sink`] specs, with the queue capacity from
+    /// `MCP_AUDIT_QUEUE_CAPACITY`.
+    pub fn from_specs(specs: &[String]) -> Self {
+        let capacity = std::env::var("MCP_AUDIT_QUEUE_CAPACITY")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_QUEUE_CAPACITY);
+        let mut log = Self::new(capacity);
+        for spec in specs {
+            match parse_sink(spec) {
+                Ok(sink) => log.add(sink),
+                Err(e) => tracing::warn!("Skipping audit sink '{}': {}", spec, e),
+            }
+        }
+        log
+    }
+
+    pub fn with_sink(mut self, sink: impl AuditSink + 'static) -> Self {
+        self.add(Box::new(sink));
+        self
+    }
+
+    /// Starts a worker thread for `sink`.
+    pub fn add(&mut self, mut sink: Box<dyn AuditSink>) {
+        let (sender, receiver) = mpsc::sync_channel(self.capacity);
+        let dropped = Arc::new(AtomicU64::new(0));
+        let pending_drops = dropped.clone();
+        std::thread::spawn(move || {
+            for message in receiver {
+                let record = match message {
+                    Message::Record(record) => record,
+                    Message::Flush(done) => {
+                        let _ = done.send(());
+                        continue;
+                    }
+                };
+                let skipped = pending_drops.swap(0, Ordering::Relaxed);
+                if skipped > 0 {
+                    let notice = stamp(serde_json::json!({
+                        "event": "audit_records_dropped",
+                        "count": skipped,
+                    }));
+                    if let Err(e) = sink.write(&notice) {
+                        tracing::warn!("Audit sink {} failed: {}", sink.name(), e);
+                    }
+                }
+                if let Err(e) = sink.write(&record) {
+                    tracing::warn!("Audit sink {} failed: {}", sink.name(), e);
+                }
+            }
+        });
+        self.queues.push(Queue { sender, dropped });
+    }
+
+    /// Stamps `record` with `at` (Unix seconds) unless it carries its own
+    /// `at` or ECS `@timestamp`, and queues it for every sink without
+    /// blocking.
+    pub fn record(&self, record: Value) {
+        let record = stamp(record);
+        for queue in &self.queues {
+            match queue.sender.try_send(Message::Record(record.clone())) {
+                Ok(()) => {}
+                Err(TrySendError::Full(_)) => {
+                    queue.dropped.fetch_add(1, Ordering::Relaxed);
+                }
+                Err(TrySendError::Disconnected(_)) => {}
+            }
+        }
+    }
+
+    /// Blocks until every record queued so far has been handed to its sink.
+    pub fn flush(&self) {
+        let waits: Vec<_> = self
+            .queues
+            .iter()
+            .filter_map(|queue| {
+                let (done, wait) = mpsc::channel();
+                queue.sender.send(Message::Flush(done)).ok().map(|_| wait)
+            })
+            .collect();
+        for wait in waits {
+            let _ = wait.recv();
+        }
+    }
+}
+
+fn stamp(mut record: Value) -> Value {
+    if record.get("at").is_none() && record.get("@timestamp").is_none() {
+        record["at"] = serde_json::json!(SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0));
+    }
+    record
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Mutex;
+
+    /// Collects records in memory. With `hold`, the first write signals
+    /// the sender and then waits for a permit, stalling the worker.
+    struct MemorySink {
+        records: Arc<Mutex<Vec<Value>>>,
+        hold: Option<(mpsc::Sender<()>, mpsc::Receiver<()>)>,
+    }
+
+    impl AuditSink for MemorySink {
+        fn name(&self) -> String {
+            "memory".to_string()
+        }
+
+        fn write(&mut self, record: &Value) -> Result<(), String> {
+            if let Some((started, permit)) = self.hold.take() {
+                started.send(()).unwrap();
+                permit.recv().unwrap();
+            }
+            self.records.lock().unwrap().push(record.clone());
+            Ok(())
+        }
+    }
+
+    fn memory(
+        hold: Option<(mpsc::Sender<()>, mpsc::Receiver<()>)>,
+    ) -> (MemorySink, Arc<Mutex<Vec<Value>>>) {
+        let records = Arc::new(Mutex::new(Vec::new()));
+        (
+            MemorySink {
+                records: records.clone(),
+                hold,
+            },
+            records,
+        )
+    }
+
+    #[test]
+    fn test_records_fan_out_to_every_sink() {
+        let (a, a_records) = memory(None);
+        let path = std::env::temp_dir().join(format!("mcp-audit-{}.log", uuid::Uuid::new_v4()));
+        let log = AuditLog::new(16)
+            .with_sink(a)
+            .with_sink(FileSink::new(&path));
+
+        log.record(json!({"event": "requested", "tool": "python_run"}));
+        log.flush();
+
+        let a_records = a_records.lock().unwrap();
+        assert_eq!(a_records.len(), 1);
+        assert_eq!(a_records[0]["tool"], "python_run");
+        assert!(a_records[0]["at"].is_u64());
+        let line = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(serde_json::from_str::<Value>(&line).unwrap(), a_records[0]);
+    }
+
+    #[test]
+    fn test_full_queue_drops_and_reports_count() {
+        let (started, wait_started) = mpsc::channel();
+        let (release, permit) = mpsc::channel();
+        let (sink, records) = memory(Some((started, permit)));
+        let log = AuditLog::new(1).with_sink(sink);
+
+        // The worker stalls on the first record; the second fills the queue
+        // and the next two are dropped.
+        log.record(json!({"n": 1}));
+        wait_started.recv().unwrap();
+        for n in 2..=4 {
+            log.record(json!({"n": n}));
+        }
+        release.send(()).unwrap();
+        log.flush();
+
+        let records = records.lock().unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0]["n"], 1);
+        assert_eq!(records[1]["event"], "audit_records_dropped");
+        assert_eq!(records[1]["count"], 2);
+        assert_eq!(records[2]["n"], 2);
+    }
+
+    #[test]
+    fn test_file_sink_rotates() {
+        let dir = std::env::temp_dir().join(format!("mcp-audit-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+        let path = dir.join("audit.log");
+        // Each record is an 8-byte line, so two fit in a file.
+        let mut sink = FileSink::new(&path).with_rotation(20, 2);
+        for n in 0..7 {
+            sink.write(&json!({"n": n})).unwrap();
+        }
+
+        let read = |path: &Path| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(&path), "{\"n\":6}\n");
+        assert_eq!(read(&sink.rotated(1)), "{\"n\":4}\n{\"n\":5}\n");
+        assert_eq!(read(&sink.rotated(2)), "{\"n\":2}\n{\"n\":3}\n");
+        assert!(!sink.rotated(3).exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_syslog_message_format() {
+        let receiver = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+        let mut sink = SyslogSink::udp(&receiver.local_addr().unwrap().to_string()).unwrap();
+        sink.write(&json!({"event": "approved"})).unwrap();
+
+        let mut buf = [0u8; 1024];
+        let n = receiver.recv(&mut buf).unwrap();
+        let message = String::from_utf8_lossy(&buf[..n]);
+        assert!(message.starts_with("<86>1 - "));
+        assert!(message.ends_with(r#" audit - {"event":"approved"}"#));
+    }
+
+    #[test]
+    fn test_parse_sink_specs() {
+        assert_eq!(parse_sink("stderr").unwrap().name(), "stderr");
+        assert_eq!(
+            parse_sink("file:/tmp/a.log").unwrap().name(),
+            "file:/tmp/a.log"
+        );
+        assert_eq!(
+            parse_sink("syslog://127.0.0.1").unwrap().name(),
+            "syslog://127.0.0.1:514"
+        );
+        assert_eq!(
+            parse_sink("https://siem.example/audit").unwrap().name(),
+            "https://siem.example/audit"
+        );
+        assert!(parse_sink("kafka://x").is_err());
+    }
+}
@@ -0,0 +1,413 @@
+//! Blue/green switching for tools backed by external services. A switched
+//! tool has two backends: calls go to `blue`, the one in service, except for
+//! `greenPercent` of them, which try the candidate `green`. If green's calls
+//! fail noticeably more often than blue's, the switch rolls back to blue on
+//! its own. Operators watch and steer the split over the admin socket.
+//!
+//! Backends are usually HTTP services, configured in `MCP_BACKENDS_FILE`;
+//! embedders can switch between any two handlers with
+//! [`McpServer::register_blue_green`](crate::McpServer::register_blue_green).
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use crate::events::{EventBus, EventKind};
+use crate::http;
+use crate::protocol::Tool;
+use crate::tools::args::Arguments;
+use crate::tools::{ToolContext, ToolError, ToolHandler, ToolResult};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_RESPONSE_BYTES: usize = 4 * 1024 * 1024;
+
+/// When the candidate backend is taken out of rotation.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct RollbackPolicy {
+    /// Share of green's recent calls that may fail, from 0 to 1.
+    pub max_error_rate: f64,
+    /// Green calls needed in the window before the rate is judged.
+    pub min_calls: usize,
+    /// Recent calls remembered per backend.
+    pub window: usize,
+}
+
+impl Default for RollbackPolicy {
+    fn default() -> Self {
+        Self {
+            max_error_rate: 0.25,
+            min_calls: 20,
+            window: 100,
+        }
+    }
+}
+
+/// Which backend answered a call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Color {
+    Blue,
+    Green,
+}
+
+/// Calls one backend took, over its lifetime and in the rollback window.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendStats {
+    pub calls: u64,
+    pub errors: u64,
+    /// Error rate over the last `window` calls.
+    pub recent_error_rate: f64,
+    #[serde(skip)]
+    recent: VecDeque<bool>,
+}
+
+impl BackendStats {
+    fn record(&mut self, failed: bool, window: usize) {
+        self.calls += 1;
+        self.errors += u64::from(failed);
+        self.recent.push_back(failed);
+        while self.recent.len() > window {
+            self.recent.pop_front();
+        }
+        let failures = self.recent.iter().filter(|f| **f).count();
+        self.recent_error_rate = failures as f64 / self.recent.len() as f64;
+    }
+}
+
+/// Why and when a switch last rolled back on its own.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rollback {
+    pub at: String,
+    pub green_error_rate: f64,
+    pub blue_error_rate: f64,
+}
+
+/// The state of one switched tool, as `backends/status` reports it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SwitchStatus {
+    pub green_percent: u8,
+    pub policy: RollbackPolicy,
+    pub blue: BackendStats,
+    pub green: BackendStats,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rolled_back: Option<Rollback>,
+    #[serde(skip)]
+    routed: u64,
+}
+
+/// Traffic split and outcomes of one tool's two backends.
+pub struct Switch {
+    status: Mutex<SwitchStatus>,
+}
+
+impl Switch {
+    pub fn new(green_percent: u8, policy: RollbackPolicy) -> Self {
+        Self {
+            status: Mutex::new(SwitchStatus {
+                green_percent: green_percent.min(100),
+                policy,
+                blue: BackendStats::default(),
+                green: BackendStats::default(),
+                rolled_back: None,
+                routed: 0,
+            }),
+        }
+    }
+
+    pub fn status(&self) -> SwitchStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    /// Sends `percent` of calls to green, clearing any rollback.
+    pub fn set_green_percent(&self, percent: u8) {
+        let mut status = self.status.lock().unwrap();
+        status.green_percent = percent.min(100);
+        status.rolled_back = None;
+    }
+
+    /// The backend for the next call. Spreads green's share evenly, so 10%
+    /// is every tenth call rather than a random tenth.
+    fn route(&self) -> Color {
+        let mut status = self.status.lock().unwrap();
+        let percent = u64::from(status.green_percent);
+        let before = status.routed * percent / 100;
+        status.routed += 1;
+        if status.routed * percent / 100 > before {
+            Color::Green
+        } else {
+            Color::Blue
+        }
+    }
+
+    /// Counts a call's outcome, returning the rollback it triggered.
+    fn record(&self, color: Color, failed: bool) -> Option<Rollback> {
+        let mut status = self.status.lock().unwrap();
+        let status = &mut *status;
+        let window = status.policy.window;
+        match color {
+            Color::Blue => status.blue.record(failed, window),
+            Color::Green => status.green.record(failed, window),
+        }
+        let (green, blue) = (&status.green, &status.blue);
+        let failing = color == Color::Green
+            && status.green_percent > 0
+            && green.recent.len() >= status.policy.min_calls
+            && green.recent_error_rate > status.policy.max_error_rate
+            && green.recent_error_rate > blue.recent_error_rate;
+        if !failing {
+            return None;
+        }
+        let rollback = Rollback {
+            at: crate::security::rfc3339(SystemTime::now()),
+            green_error_rate: green.recent_error_rate,
+            blue_error_rate: blue.recent_error_rate,
+        };
+        status.green_percent = 0;
+        status.rolled_back = Some(rollback.clone());
+        Some(rollback)
+    }
+}
+
+/// Every switched tool, shared with the admin socket.
+#[derive(Default)]
+pub struct Backends {
+    switches: Mutex<BTreeMap<String, Arc<Switch>>>,
+}
+
+impl Backends {
+    pub(crate) fn insert(&self, tool: &str, switch: Arc<Switch>) {
+        self.switches
+            .lock()
+            .unwrap()
+            .insert(tool.to_string(), switch);
+    }
+
+    pub(crate) fn remove(&self, tool: &str) {
+        self.switches.lock().unwrap().remove(tool);
+    }
+
+    pub fn get(&self, tool: &str) -> Option<Arc<Switch>> {
+        self.switches.lock().unwrap().get(tool).cloned()
+    }
+
+    /// The status of every switch, by tool.
+    pub fn status(&self) -> BTreeMap<String, SwitchStatus> {
+        let switches = self.switches.lock().unwrap();
+        switches
+            .iter()
+            .map(|(tool, switch)| (tool.clone(), switch.status()))
+            .collect()
+    }
+}
+
+/// Routes each call of one tool to blue or green.
+pub struct BlueGreenTool {
+    pub name: String,
+    pub blue: Arc<dyn ToolHandler>,
+    pub green: Arc<dyn ToolHandler>,
+    pub switch: Arc<Switch>,
+    pub events: EventBus,
+}
+
+#[async_trait]
+impl ToolHandler for BlueGreenTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        self.call_with_context(args, &ToolContext::default()).await
+    }
+
+    async fn call_with_context(
+        &self,
+        args: Value,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        self.call_shared(args.into(), context).await
+    }
+
+    async fn call_shared(
+        &self,
+        args: Arguments,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let color = self.switch.route();
+        let backend = match color {
+            Color::Blue => &self.blue,
+            Color::Green => &self.green,
+        };
+        let result = backend.call_shared(args, context).await;
+        // Rejected arguments are the caller's fault, not the backend's.
+        let failed = match &result {
+            Ok(result) => result.is_error,
+            Err(e) => matches!(e, ToolError::Execution(_)),
+        };
+        if let Some(rollback) = self.switch.record(color, failed) {
+            tracing::warn!(
+                "Rolled {} back to blue: green failed {:.0}% of recent calls, blue {:.0}%",
+                self.name,
+                rollback.green_error_rate * 100.0,
+                rollback.blue_error_rate * 100.0
+            );
+            self.events.publish(
+                EventKind::BackendRolledBack,
+                serde_json::json!({
+                    "tool": self.name,
+                    "greenErrorRate": rollback.green_error_rate,
+                    "blueErrorRate": rollback.blue_error_rate,
+                }),
+            );
+        }
+        result
+    }
+
+    fn warms_up(&self) -> bool {
+        self.blue.warms_up() || self.green.warms_up()
+    }
+
+    /// Warms up both backends, since either may take the next call.
+    async fn warmup(&self) -> Result<(), String> {
+        let (blue, green) = tokio::join!(self.blue.warmup(), self.green.warmup());
+        blue.map_err(|e| format!("blue: {}", e))?;
+        green.map_err(|e| format!("green: {}", e))
+    }
+}
+
+/// Forwards calls to an HTTP service: the arguments are POSTed as JSON, and
+/// a JSON answer with a `content` array is the tool result, keeping its
+/// `structuredContent` and `isError`. Any other answer becomes a single
+/// JSON text block.
+pub struct HttpBackend {
+    pub url: http::Url,
+}
+
+#[async_trait]
+impl ToolHandler for HttpBackend {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        let body = serde_json::to_vec(&args).unwrap();
+        let headers = [("Content-Type", "application/json".to_string())];
+        let send = async {
+            let stream = tokio::net::TcpStream::connect((self.url.host.as_str(), self.url.port))
+                .await
+                .map_err(|e| e.to_string())?;
+            http::client::request(
+                stream,
+                "POST",
+                &self.url,
+                &headers,
+                &body,
+                MAX_RESPONSE_BYTES,
+            )
+            .await
+        };
+        let fetched = tokio::time::timeout(TIMEOUT, send)
+            .await
+            .map_err(|_| {
+                ToolError::Execution(format!("Backend timed out after {}s", TIMEOUT.as_secs()))
+            })?
+            .map_err(|e| ToolError::Execution(format!("Backend unreachable: {}", e)))?;
+        if !(200..300).contains(&fetched.status) {
+            return Err(ToolError::Execution(format!(
+                "Backend answered {}: {}",
+                fetched.status,
+                String::from_utf8_lossy(&fetched.body)
+            )));
+        }
+        let answer: Value = serde_json::from_slice(&fetched.body)
+            .map_err(|e| ToolError::Execution(format!("Backend answered invalid JSON: {}", e)))?;
+        match answer.get("content") {
+            Some(Value::Array(content)) => Ok(ToolResult {
+                content: content.clone(),
+                structured_content: answer.get("structuredContent").cloned(),
+                is_error: answer["isError"] == true,
+            }),
+            _ => Ok(ToolResult::json(&answer)),
+        }
+    }
+}
+
+/// One tool of `MCP_BACKENDS_FILE`: its definition, as `tools/list` shows
+/// it, with the URLs of its two backends.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackendToolConfig {
+    #[serde(flatten)]
+    pub tool: Tool,
+    pub blue: String,
+    pub green: String,
+    #[serde(default)]
+    pub green_percent: u8,
+    #[serde(default)]
+    pub rollback: RollbackPolicy,
+}
+
+impl BackendToolConfig {
+    /// The blue and green handlers.
+    pub fn handlers(&self) -> Result<(HttpBackend, HttpBackend), String> {
+        Ok((
+            HttpBackend {
+                url: http::Url::parse(&self.blue)?,
+            },
+            HttpBackend {
+                url: http::Url::parse(&self.green)?,
+            },
+        ))
+    }
+}
+
+/// Reads a JSON array of [`BackendToolConfig`]s.
+pub fn load(path: &Path) -> Result<Vec<BackendToolConfig>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let configs: Vec<BackendToolConfig> = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    for config in &configs {
+        config
+            .handlers()
+            .map_err(|e| format!("Tool '{}': {}", config.tool.name, e))?;
+    }
+    Ok(configs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_splits_traffic_and_rolls_back() {
+        let switch = Switch::new(
+            25,
+            RollbackPolicy {
+                max_error_rate: 0.5,
+                min_calls: 4,
+                window: 10,
+            },
+        );
+        let routes: Vec<Color> = (0..8).map(|_| switch.route()).collect();
+        assert_eq!(routes.iter().filter(|c| **c == Color::Green).count(), 2);
+        assert_eq!(routes[3], Color::Green);
+
+        for _ in 0..10 {
+            assert_eq!(switch.record(Color::Blue, false), None);
+        }
+        for failed in [true, false, true] {
+            assert_eq!(switch.record(Color::Green, failed), None);
+        }
+        let rollback = switch.record(Color::Green, true).unwrap();
+        assert_eq!(rollback.green_error_rate, 0.75);
+        assert_eq!(rollback.blue_error_rate, 0.0);
+
+        let status = switch.status();
+        assert_eq!(status.green_percent, 0);
+        assert_eq!(status.green.calls, 4);
+        assert_eq!(status.green.errors, 3);
+        assert!((0..8).all(|_| switch.route() == Color::Blue));
+
+        switch.set_green_percent(100);
+        assert!(switch.status().rolled_back.is_none());
+        assert_eq!(switch.route(), Color::Green);
+    }
+}
@@ -0,0 +1,38 @@
+use ::base64::engine::general_purpose::{GeneralPurpose, GeneralPurposeConfig};
+use ::base64::engine::DecodePaddingMode;
+use ::base64::{alphabet, Engine};
+
+/// Standard alphabet; padding is written, and optional when reading.
+const STANDARD: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// Standard (RFC 4648) base64 with padding, as used for MCP `blob` and
+/// image `data` fields.
+pub fn encode(bytes: &[u8]) -> String {
+    STANDARD.encode(bytes)
+}
+
+/// Decodes standard base64, with or without padding. `None` for anything
+/// else.
+pub fn decode(text: &str) -> Option<Vec<u8>> {
+    STANDARD.decode(text).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode_pads_to_four_characters() {
+        assert_eq!(encode(b""), "");
+        assert_eq!(encode(b"f"), "Zg==");
+        assert_eq!(encode(b"fo"), "Zm8=");
+        assert_eq!(encode(b"foo"), "Zm9v");
+        assert_eq!(encode(&[0xff, 0xfe, 0x00, 0x01]), "//4AAQ==");
+        assert_eq!(decode("//4AAQ==").unwrap(), [0xff, 0xfe, 0x00, 0x01]);
+        assert_eq!(decode("Zm8").unwrap(), b"fo");
+        assert!(decode("not base64!").is_none());
+    }
+}
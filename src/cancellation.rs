@@ -0,0 +1,50 @@
+//! Cooperative cancellation for in-flight requests.
+
+use std::sync::Arc;
+use tokio::sync::watch;
+
+/// Set once a request is cancelled by `notifications/cancelled`. Clones share
+/// the same state.
+///
+/// `tools/call` stops awaiting a handler as soon as its token is cancelled,
+/// which drops the handler's future and anything it owns, such as a child
+/// process started with `kill_on_drop`. Handlers doing blocking or detached
+/// work should check [`is_cancelled`](Self::is_cancelled) between steps, or
+/// race [`cancelled`](Self::cancelled) against it.
+#[derive(Clone)]
+pub struct CancellationToken {
+    state: Arc<watch::Sender<bool>>,
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(watch::Sender::new(false)),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.state.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.state.borrow()
+    }
+
+    /// Completes once the token is cancelled, at once if it already is.
+    pub async fn cancelled(&self) {
+        let mut state = self.state.subscribe();
+        // The sender lives as long as `self`, so this only returns once set.
+        let _ = state.wait_for(|cancelled| *cancelled).await;
+    }
+
+    pub(crate) fn same_as(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.state, &other.state)
+    }
+}
@@ -0,0 +1,107 @@
+//! Argument completion for `completion/complete`: suggestions for a prompt
+//! argument or a resource template variable as the user types it.
+
+use async_trait::async_trait;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Most values one completion returns, as the spec allows.
+pub const MAX_VALUES: usize = 100;
+
+/// Suggests values for one argument. `value` is what has been typed so
+/// far and `arguments` the other arguments already filled in, so a
+/// `db://{table}/{id}` completer can offer ids of the chosen table.
+#[async_trait]
+pub trait Completer: Send + Sync {
+    async fn complete(
+        &self,
+        value: &str,
+        arguments: &BTreeMap<String, String>,
+    ) -> Result<Vec<String>, String>;
+}
+
+#[async_trait]
+impl<F> Completer for F
+where
+    F: Fn(&str, &BTreeMap<String, String>) -> Vec<String> + Send + Sync,
+{
+    async fn complete(
+        &self,
+        value: &str,
+        arguments: &BTreeMap<String, String>,
+    ) -> Result<Vec<String>, String> {
+        Ok(self(value, arguments))
+    }
+}
+
+/// Completes from a fixed list, keeping the values that start with what
+/// was typed, ignoring case.
+pub struct Values(pub Vec<String>);
+
+#[async_trait]
+impl Completer for Values {
+    async fn complete(
+        &self,
+        value: &str,
+        _arguments: &BTreeMap<String, String>,
+    ) -> Result<Vec<String>, String> {
+        let typed = value.to_lowercase();
+        Ok(self
+            .0
+            .iter()
+            .filter(|v| v.to_lowercase().starts_with(&typed))
+            .cloned()
+            .collect())
+    }
+}
+
+/// The `completion` object of a `completion/complete` result.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Completion {
+    pub values: Vec<String>,
+    pub total: usize,
+    pub has_more: bool,
+}
+
+impl Completion {
+    /// The first [`MAX_VALUES`] of `values`, noting whether there were more.
+    pub fn new(mut values: Vec<String>) -> Self {
+        let total = values.len();
+        values.truncate(MAX_VALUES);
+        Self {
+            values,
+            total,
+            has_more: total > MAX_VALUES,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_completions_are_truncated() {
+        let colours = Values(vec!["Red".into(), "green".into(), "grey".into()]);
+        let found = colours.complete("gr", &BTreeMap::new()).await.unwrap();
+        assert_eq!(found, vec!["green", "grey"]);
+        assert_eq!(
+            Completion::new(found),
+            Completion {
+                values: vec!["green".into(), "grey".into()],
+                total: 2,
+                has_more: false
+            }
+        );
+
+        let numbers = |value: &str, _: &BTreeMap<String, String>| {
+            (0..250).map(|n| format!("{}{}", value, n)).collect()
+        };
+        let completion = Completion::new(numbers.complete("id-", &BTreeMap::new()).await.unwrap());
+        assert_eq!(completion.values.len(), MAX_VALUES);
+        assert_eq!(completion.values[0], "id-0");
+        assert_eq!(completion.total, 250);
+        assert!(completion.has_more);
+    }
+}
@@ -0,0 +1,520 @@
+//! `--config server.toml`: the server's name, transport, tool packs,
+//! timeouts and limits in one file, instead of flags and `MCP_*` variables.
+//! Settings the file leaves out keep their defaults or environment values;
+//! command-line flags override the file.
+//!
+//! ```toml
+//! [server]
+//! name = "docs-assistant"
+//! version = "2.1.0"
+//!
+//! [transport]
+//! kind = "http"
+//! bind = "127.0.0.1:8080"
+//!
+//! [tools]
+//! packs = ["core", "memory"]
+//!
+//! [timeouts]
+//! default_secs = 120
+//! tools = { python_run = 600 }
+//!
+//! [limits]
+//! max_connections = 64
+//! concurrency = { python_run = 4 }
+//!
+//! [diagnostics]
+//! format = "json"
+//! filter = "info,mcp_server::transport=debug"
+//!
+//! [runtime]
+//! worker_threads = 4
+//! ```
+
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::diagnostics::{self, LogFormat};
+use crate::logging::LogLevel;
+use crate::runtime::RuntimeSettings;
+use crate::tools::concurrency::AdaptiveLimit;
+use crate::tools::rate_limits::RateLimit;
+use crate::tools::warmup::WarmupMode;
+use crate::transport::access::AccessPolicy;
+use crate::transport::auth::{BanPolicy, Token, TokenAuth};
+use crate::McpServer;
+
+/// The built-in tools by pack, for `[tools] packs`. Tools in no pack, such
+/// as plugins and tools an embedder registers, are always offered.
+pub const PACKS: &[(&str, &[&str])] = &[
+    ("core", &["echo", "help", "count_tokens", "assert"]),
+    ("code", &["python_run", "js_run", "repl_list", "repl_reset"]),
+    ("http", &["http_fetch"]),
+    (
+        "memory",
+        &[
+            "memory_create_entities",
+            "memory_create_relations",
+            "memory_search",
+            "memory_graph",
+        ],
+    ),
+    (
+        "workspace",
+        &[
+            "workspace_write",
+            "workspace_read",
+            "workspace_list",
+            "workspace_delete",
+        ],
+    ),
+    ("fs", &["fs_read", "fs_write", "fs_list", "fs_delete"]),
+    (
+        "workflow",
+        &["workflow_start", "workflow_advance", "workflow_status"],
+    ),
+];
+
+/// The pack offering `tool`, whatever its version.
+pub fn pack_of(tool: &str) -> Option<&'static str> {
+    let (base, _) = crate::tools::versions::split(tool);
+    PACKS
+        .iter()
+        .find(|(_, tools)| tools.contains(&base))
+        .map(|(pack, _)| *pack)
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub transport: TransportConfig,
+    pub tools: ToolsConfig,
+    pub timeouts: TimeoutsConfig,
+    pub limits: LimitsConfig,
+    pub diagnostics: DiagnosticsConfig,
+    pub runtime: RuntimeConfig,
+    pub auth: AuthConfig,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// `serverInfo.name` on `initialize`.
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub read_only: Option<bool>,
+    /// Level sessions start at, as `--log-level`.
+    pub log_level: Option<LogLevel>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TransportConfig {
+    /// `stdio`, `tcp`, `http` or `sse`, as `--transport`.
+    pub kind: Option<String>,
+    /// Address to listen on, as `--bind`, or `--tcp` for `tcp`.
+    pub bind: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolsConfig {
+    /// Packs of built-in tools to offer, from [`PACKS`]; every pack when
+    /// unset.
+    pub packs: Option<Vec<String>>,
+    /// When tools warm up, as `MCP_WARMUP`: `startup`, `first-use` or
+    /// `off`.
+    pub warmup: Option<String>,
+    /// As `MCP_WARMUP_TIMEOUT_SECS`; `0` for no limit.
+    pub warmup_timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TimeoutsConfig {
+    /// Longest a tool call may run, as `MCP_TOOL_TIMEOUT_SECS`; `0` for no
+    /// limit.
+    pub default_secs: Option<u64>,
+    /// Overrides by tool name, as `MCP_TOOL_TIMEOUTS`.
+    pub tools: BTreeMap<String, u64>,
+    /// Longest a call waits under a concurrency limit, as
+    /// `MCP_TOOL_QUEUE_TIMEOUT_SECS`; `0` to wait as long as it takes.
+    pub queue_secs: Option<u64>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsConfig {
+    /// As `MCP_PAGE_SIZE`.
+    pub page_size: Option<usize>,
+    /// Caps for the network transports, as `MCP_<TRANSPORT>_MAX_CONNECTIONS`.
+    pub max_connections: Option<usize>,
+    pub max_connections_per_ip: Option<usize>,
+    /// Simultaneous calls by tool name, as `MCP_TOOL_CONCURRENCY`.
+    pub concurrency: BTreeMap<String, usize>,
+    /// Bounds of adaptive caps by tool name, or `*` for every other tool,
+    /// as `MCP_TOOL_ADAPTIVE_CONCURRENCY`: `"2..32"`, or `""` for the
+    /// defaults.
+    pub adaptive_concurrency: BTreeMap<String, String>,
+    /// Calls each session may make by tool name, or `*` for every other
+    /// tool, as `MCP_TOOL_RATE_LIMITS`: `"10/min"`, or `"0"` for none.
+    pub rate_limits: BTreeMap<String, String>,
+    /// Load shedding, as `MCP_SHED_MAX_IN_FLIGHT`, `MCP_SHED_MAX_MEMORY_MB`
+    /// and `MCP_SHED_RETRY_AFTER_SECS`.
+    pub max_in_flight: Option<usize>,
+    pub max_memory_mb: Option<u64>,
+    pub retry_after_secs: Option<u64>,
+}
+
+/// The server's own logs; see [`diagnostics`](crate::diagnostics).
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DiagnosticsConfig {
+    /// `text`, `pretty` or `json`, as `MCP_LOG_FORMAT`.
+    pub format: Option<LogFormat>,
+    /// Which events to show, as `RUST_LOG`.
+    pub filter: Option<String>,
+}
+
+/// The binary's tokio runtime; see [`runtime`](crate::runtime).
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuntimeConfig {
+    /// As `MCP_WORKER_THREADS`.
+    pub worker_threads: Option<usize>,
+    /// As `MCP_MAX_BLOCKING_THREADS`.
+    pub max_blocking_threads: Option<usize>,
+    /// As `MCP_THREAD_NAME`.
+    pub thread_name: Option<String>,
+}
+
+/// Tokens the network transports require; see
+/// [`auth`](crate::transport::auth).
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// As `MCP_AUTH_TOKENS`; any one of them is accepted. A token is a
+    /// string, or a table `{ token, expiresAt }` with the Unix time it
+    /// stops being accepted.
+    pub tokens: Option<Vec<AuthToken>>,
+    /// As `MCP_AUTH_MAX_FAILURES`.
+    pub max_failures: Option<u32>,
+    /// As `MCP_AUTH_FAILURE_WINDOW_SECS`.
+    pub failure_window_secs: Option<u64>,
+    /// As `MCP_AUTH_BAN_SECS`.
+    pub ban_secs: Option<u64>,
+}
+
+/// One of the [`AuthConfig`] tokens.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(untagged)]
+pub enum AuthToken {
+    Plain(String),
+    Expiring(Token),
+}
+
+impl AuthToken {
+    pub fn token(&self) -> Token {
+        match self {
+            Self::Plain(token) => Token::new(token.clone()),
+            Self::Expiring(token) => token.clone(),
+        }
+    }
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("Invalid config {}: {}", path.display(), e))
+    }
+
+    /// Parses a config, refusing unknown keys, packs and transports so a
+    /// typo is not silently ignored.
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(text).map_err(|e| e.message().to_string())?;
+        for pack in config.tools.packs.iter().flatten() {
+            if !PACKS.iter().any(|(name, _)| name == pack) {
+                return Err(format!("Unknown tool pack '{}'", pack));
+            }
+        }
+        if let Some(mode) = &config.tools.warmup {
+            if WarmupMode::parse(mode).is_none() {
+                return Err(format!(
+                    "Unknown warm-up mode '{}'; expected startup, first-use or off",
+                    mode
+                ));
+            }
+        }
+        if let Some(kind) = &config.transport.kind {
+            if !["stdio", "tcp", "http", "sse"].contains(&kind.as_str()) {
+                return Err(format!(
+                    "Unknown transport '{}'; expected stdio, tcp, http or sse",
+                    kind
+                ));
+            }
+        }
+        if let Some(filter) = &config.diagnostics.filter {
+            diagnostics::parse_filter(filter)?;
+        }
+        if config.runtime.worker_threads == Some(0)
+            || config.runtime.max_blocking_threads == Some(0)
+        {
+            return Err("Runtime thread counts must be at least 1".to_string());
+        }
+        if let Some(tokens) = &config.auth.tokens {
+            if tokens.is_empty() || tokens.iter().any(|t| t.token().token.is_empty()) {
+                return Err("Auth tokens must not be empty".to_string());
+            }
+        }
+        if config.limits.concurrency.values().any(|&limit| limit == 0) {
+            return Err("Concurrency limits must be at least 1".to_string());
+        }
+        for (tool, limit) in &config.limits.rate_limits {
+            if limit.trim() != "0" && RateLimit::parse(limit).is_none() {
+                return Err(format!(
+                    "Invalid rate limit '{}' for {}; expected calls/period, such as 10/min",
+                    limit, tool
+                ));
+            }
+        }
+        for (tool, bounds) in &config.limits.adaptive_concurrency {
+            if AdaptiveLimit::parse(bounds).is_none() {
+                return Err(format!(
+                    "Invalid adaptive concurrency '{}' for {}; expected min..max",
+                    bounds, tool
+                ));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Applies the server settings, withdrawing the tools of packs left
+    /// out. The transport, connection limits and diagnostics are for the
+    /// caller.
+    pub fn apply(&self, server: &mut McpServer) {
+        if self.server.name.is_some() || self.server.version.is_some() {
+            server.set_server_info(
+                self.server
+                    .name
+                    .as_deref()
+                    .unwrap_or(env!("CARGO_PKG_NAME")),
+                self.server
+                    .version
+                    .as_deref()
+                    .unwrap_or(env!("CARGO_PKG_VERSION")),
+            );
+        }
+        if let Some(read_only) = self.server.read_only {
+            server.set_read_only(read_only);
+        }
+        if let Some(level) = self.server.log_level {
+            server.set_default_log_level(level);
+        }
+        if let Some(packs) = &self.tools.packs {
+            for tool in server.tools() {
+                if pack_of(&tool.name).is_some_and(|pack| !packs.iter().any(|p| p == pack)) {
+                    server.unregister_tool(&tool.name);
+                }
+            }
+        }
+
+        let secs = |secs: u64| (secs > 0).then(|| Duration::from_secs(secs));
+        let timeouts = server.tool_timeouts();
+        if let Some(default) = self.timeouts.default_secs {
+            timeouts.set_default(secs(default));
+        }
+        for (tool, &timeout) in &self.timeouts.tools {
+            timeouts.set(tool, secs(timeout));
+        }
+        let concurrency = server.tool_concurrency();
+        if let Some(queue) = self.timeouts.queue_secs {
+            concurrency.set_queue_timeout(secs(queue));
+        }
+        for (tool, bounds) in &self.limits.adaptive_concurrency {
+            match (tool.as_str(), AdaptiveLimit::parse(bounds)) {
+                ("*", bounds) => concurrency.set_adaptive_default(bounds),
+                (tool, Some(bounds)) => concurrency.set_adaptive(tool, bounds),
+                (_, None) => {}
+            }
+        }
+        for (tool, &limit) in &self.limits.concurrency {
+            concurrency.set_limit(tool, Some(limit));
+        }
+        let rate_limits = server.tool_rate_limits();
+        for (tool, limit) in &self.limits.rate_limits {
+            match tool.as_str() {
+                "*" => rate_limits.set_default(RateLimit::parse(limit)),
+                tool => rate_limits.set(tool, RateLimit::parse(limit)),
+            }
+        }
+        if let Some(size) = self.limits.page_size {
+            server.set_page_size(size);
+        }
+        let warmups = server.warmups();
+        if let Some(mode) = self.tools.warmup.as_deref().and_then(WarmupMode::parse) {
+            warmups.set_mode(mode);
+        }
+        if let Some(timeout) = self.tools.warmup_timeout_secs {
+            warmups.set_timeout(secs(timeout));
+        }
+        let shedder = server.load_shedder();
+        if let Some(limit) = self.limits.max_in_flight {
+            shedder.set_max_in_flight((limit > 0).then_some(limit));
+        }
+        if let Some(mb) = self.limits.max_memory_mb {
+            shedder.set_max_memory((mb > 0).then_some(mb * 1024 * 1024));
+        }
+        if let Some(secs) = self.limits.retry_after_secs {
+            shedder.set_retry_after(Duration::from_secs(secs));
+        }
+        let tokens = match &self.auth.tokens {
+            Some(tokens) => Some(tokens.iter().map(AuthToken::token).collect()),
+            None => server.token_auth().map(|auth| auth.tokens()),
+        };
+        if let Some(tokens) = tokens {
+            let policy = self.ban_policy(BanPolicy::from_env());
+            server.set_token_auth(TokenAuth::with_tokens(tokens).with_ban_policy(policy));
+        }
+    }
+
+    /// `policy` with the file's ban settings in place of its own.
+    pub fn ban_policy(&self, mut policy: BanPolicy) -> BanPolicy {
+        if let Some(max) = self.auth.max_failures {
+            policy.max_failures = max;
+        }
+        if let Some(secs) = self.auth.failure_window_secs {
+            policy.window = Duration::from_secs(secs);
+        }
+        if let Some(secs) = self.auth.ban_secs {
+            policy.ban = Duration::from_secs(secs);
+        }
+        policy
+    }
+
+    /// `settings` with the file's runtime settings in place of its own.
+    pub fn runtime_settings(&self, mut settings: RuntimeSettings) -> RuntimeSettings {
+        if let Some(threads) = self.runtime.worker_threads {
+            settings.worker_threads = threads;
+        }
+        if let Some(threads) = self.runtime.max_blocking_threads {
+            settings.max_blocking_threads = threads;
+        }
+        if let Some(name) = &self.runtime.thread_name {
+            settings.thread_name = name.clone();
+        }
+        settings
+    }
+
+    /// `policy` with the file's connection caps in place of its own.
+    pub fn access_policy(&self, mut policy: AccessPolicy) -> AccessPolicy {
+        if let Some(max) = self.limits.max_connections {
+            policy.max_connections = Some(max);
+        }
+        if let Some(max) = self.limits.max_connections_per_ip {
+            policy.max_connections_per_ip = Some(max);
+        }
+        policy
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_config_applies_to_the_server() {
+        let config = Config::parse(
+            r#"
+            [server]
+            name = "docs-assistant"
+            version = "2.1.0"
+
+            [transport]
+            kind = "http"
+
+            [tools]
+            packs = ["core"]
+
+            [timeouts]
+            default_secs = 0
+            tools = { echo = 5 }
+
+            [limits]
+            page_size = 2
+            max_connections = 8
+            concurrency = { echo = 1 }
+            rate_limits = { "*" = "30/min", echo = "0" }
+
+            [runtime]
+            worker_threads = 3
+
+            [auth]
+            tokens = ["s3cret", { token = "old", expiresAt = 4102444800 }]
+            max_failures = 3
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.transport.kind.as_deref(), Some("http"));
+        let policy = config.access_policy(AccessPolicy::default());
+        assert_eq!(policy.max_connections, Some(8));
+        assert_eq!(policy.max_connections_per_ip, None);
+        let runtime = config.runtime_settings(RuntimeSettings::default());
+        assert_eq!(runtime.worker_threads, 3);
+        assert_eq!(runtime.thread_name, crate::runtime::DEFAULT_THREAD_NAME);
+
+        let mut server = McpServer::new();
+        config.apply(&mut server);
+        let names: Vec<String> = server.tools().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, ["assert", "count_tokens", "echo", "help"]);
+        assert_eq!(
+            server.tool_timeouts().get("echo"),
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(server.tool_timeouts().get("help"), None);
+        assert_eq!(server.tool_concurrency().limit("echo"), Some(1));
+        assert_eq!(server.tool_rate_limits().get("echo"), None);
+        assert_eq!(
+            server.tool_rate_limits().get("help"),
+            RateLimit::parse("30/min")
+        );
+        let auth = server.token_auth().unwrap();
+        assert_eq!(
+            auth.tokens(),
+            [Token::new("s3cret"), Token::new("old").expiring(4102444800)]
+        );
+        assert_eq!(config.ban_policy(BanPolicy::default()).max_failures, 3);
+
+        let request = json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {}});
+        let response = server
+            .handle_request(serde_json::from_value(request).unwrap())
+            .await
+            .unwrap();
+        let info = &response.result.unwrap()["serverInfo"];
+        assert_eq!(info["name"], "docs-assistant");
+        assert_eq!(info["version"], "2.1.0");
+    }
+
+    #[test]
+    fn test_mistakes_are_refused() {
+        assert!(Config::parse("[tools]\npacks = [\"browser\"]")
+            .unwrap_err()
+            .contains("browser"));
+        assert!(Config::parse("[server]\nnmae = \"x\"")
+            .unwrap_err()
+            .contains("nmae"));
+        assert!(Config::parse("[transport]\nkind = \"udp\"").is_err());
+        assert!(Config::parse("[tools]\nwarmup = \"eager\"").is_err());
+        assert!(Config::parse("[diagnostics]\nfilter = \"mcp_server=loud\"").is_err());
+        assert!(Config::parse("[runtime]\nworker_threads = 0").is_err());
+        assert!(Config::parse("[limits]\nrate_limits = { echo = \"lots\" }").is_err());
+        assert!(Config::parse("[auth]\ntokens = []").is_err());
+        assert!(Config::parse("[auth]\ntokens = [{ token = \"\" }]").is_err());
+        assert!(Config::parse("[auth]\ntokens = [{ token = \"a\", expires = 1 }]").is_err());
+        assert_eq!(Config::parse("").unwrap(), Config::default());
+    }
+}
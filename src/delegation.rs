@@ -0,0 +1,342 @@
+//! Child servers that hand a narrow toolset to a sub-agent. An agent calls
+//! `spawn_child_server` with the tools to pass on; the server starts a
+//! [scoped](crate::McpServer::scoped) copy of itself on a loopback TCP port
+//! and returns the address with a token minted for that child alone, which
+//! clients must present before anything else. The child lives until its TTL runs out or an
+//! operator stops it, and then its tools are withdrawn, even from clients
+//! still connected.
+
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpListener;
+use tokio::task::JoinHandle;
+
+use crate::protocol::{Tool, ToolAnnotations, ToolMeta};
+use crate::tools::{ToolError, ToolHandler, ToolResult};
+use crate::transport::access::ConnectionLimiter;
+use crate::transport::auth::TokenAuth;
+use crate::McpServer;
+
+pub const NAME: &str = "spawn_child_server";
+/// Used when a call gives no `ttlSecs`.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(600);
+/// Longest `ttlSecs` a call may ask for.
+pub const MAX_TTL: Duration = Duration::from_secs(3600);
+
+/// How to reach a child server, and what it offers.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChildInfo {
+    pub id: String,
+    pub transport: String,
+    pub address: String,
+    /// Sent as `Authorization: Bearer <token>` on the connection's first
+    /// line; the child accepts no other.
+    pub token: String,
+    pub tools: Vec<String>,
+    pub read_only: bool,
+    /// Unix time, in seconds, at which the child is stopped.
+    pub expires_at: u64,
+}
+
+struct Child {
+    info: ChildInfo,
+    server: Arc<McpServer>,
+    task: JoinHandle<()>,
+}
+
+impl Child {
+    /// Closes the listener and withdraws every tool, so clients already
+    /// connected can call nothing further.
+    fn stop(&self) {
+        self.task.abort();
+        for tool in &self.info.tools {
+            self.server.unregister_tool(tool);
+        }
+    }
+}
+
+/// The running child servers, by id.
+#[derive(Default)]
+pub struct ChildServers {
+    children: Mutex<BTreeMap<String, Child>>,
+}
+
+impl ChildServers {
+    /// Starts `server` on a free loopback port for `ttl`, requiring a fresh
+    /// token in place of any the parent accepts. Other local users can
+    /// reach the port, and under stdio the parent has no token to inherit.
+    pub async fn spawn(
+        self: &Arc<Self>,
+        mut server: McpServer,
+        ttl: Duration,
+    ) -> Result<ChildInfo, String> {
+        let token = uuid::Uuid::new_v4().simple().to_string();
+        server.set_token_auth(TokenAuth::new([token.clone()]));
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .map_err(|e| format!("Failed to start child server: {}", e))?;
+        let address = listener.local_addr().map_err(|e| e.to_string())?;
+        let mut tools: Vec<String> = server.tools().into_iter().map(|t| t.name).collect();
+        tools.sort();
+        let info = ChildInfo {
+            id: uuid::Uuid::new_v4().to_string(),
+            transport: "tcp".to_string(),
+            address: address.to_string(),
+            token,
+            tools,
+            read_only: server.is_read_only(),
+            expires_at: (SystemTime::now() + ttl)
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        };
+
+        let server = Arc::new(server);
+        let serving = crate::transport::tcp::serve_listener(
+            server.clone(),
+            listener,
+            Arc::new(ConnectionLimiter::default()),
+        );
+        let children = Arc::downgrade(self);
+        let id = info.id.clone();
+        let task = tokio::spawn(async move {
+            if let Ok(Err(e)) = tokio::time::timeout(ttl, serving).await {
+                tracing::warn!("Child server {} failed: {}", id, e);
+            }
+            if let Some(children) = children.upgrade() {
+                children.stop(&id);
+            }
+        });
+        self.children.lock().unwrap().insert(
+            info.id.clone(),
+            Child {
+                info: info.clone(),
+                server,
+                task,
+            },
+        );
+        Ok(info)
+    }
+
+    pub fn list(&self) -> Vec<ChildInfo> {
+        self.children
+            .lock()
+            .unwrap()
+            .values()
+            .map(|c| c.info.clone())
+            .collect()
+    }
+
+    /// Stops a child before its TTL runs out. Returns `false` when no such
+    /// child is running.
+    pub fn stop(&self, id: &str) -> bool {
+        let child = self.children.lock().unwrap().remove(id);
+        child.map(|child| child.stop()).is_some()
+    }
+}
+
+impl Drop for ChildServers {
+    fn drop(&mut self) {
+        for child in self.children.get_mut().unwrap().values() {
+            child.stop();
+        }
+    }
+}
+
+/// Offers `spawn_child_server` on `server`, with children tracked in
+/// `children`.
+pub fn register(server: &Arc<McpServer>, children: Arc<ChildServers>) {
+    server.register_tool(
+        definition(),
+        SpawnChildTool {
+            server: Arc::downgrade(server),
+            children,
+        },
+    );
+}
+
+pub fn definition() -> Tool {
+    Tool {
+        name: NAME.to_string(),
+        description: "Start a temporary server offering only some of your tools, and return its \
+                      address, so a sub-agent can be given just those tools"
+            .to_string(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "tools": {
+                    "type": "array",
+                    "items": {"type": "string"},
+                    "minItems": 1,
+                    "description": "Names of the tools the child offers"
+                },
+                "readOnly": {
+                    "type": "boolean",
+                    "description": "Offer only the read-only tools among them"
+                },
+                "ttlSecs": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "maximum": MAX_TTL.as_secs(),
+                    "description": "Seconds until the child stops (default 600)"
+                },
+                "timeoutSecs": {
+                    "type": "integer",
+                    "minimum": 1,
+                    "description": "Longest any one call on the child may run"
+                }
+            },
+            "required": ["tools"]
+        }),
+        output_schema: None,
+        annotations: Some(ToolAnnotations::mutating(false)),
+        meta: ToolMeta::default(),
+    }
+}
+
+pub struct SpawnChildTool {
+    server: Weak<McpServer>,
+    children: Arc<ChildServers>,
+}
+
+#[async_trait]
+impl ToolHandler for SpawnChildTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        let tools: Vec<String> = args
+            .get("tools")
+            .and_then(|t| serde_json::from_value(t.clone()).ok())
+            .filter(|t: &Vec<String>| !t.is_empty())
+            .ok_or_else(|| {
+                ToolError::InvalidArguments("'tools' must name at least one tool".into())
+            })?;
+        // Children can't spawn children of their own.
+        if tools.iter().any(|t| t == NAME) {
+            return Err(ToolError::InvalidArguments(format!(
+                "'{}' can't be passed on to a child",
+                NAME
+            )));
+        }
+        let read_only = args.get("readOnly").and_then(Value::as_bool) == Some(true);
+        let ttl = args
+            .get("ttlSecs")
+            .and_then(Value::as_u64)
+            .map(|secs| Duration::from_secs(secs).min(MAX_TTL))
+            .unwrap_or(DEFAULT_TTL);
+        let timeout = args
+            .get("timeoutSecs")
+            .and_then(Value::as_u64)
+            .map(Duration::from_secs);
+
+        let server = self
+            .server
+            .upgrade()
+            .ok_or_else(|| ToolError::Execution("The server is shutting down".into()))?;
+        let child = server
+            .scoped(&tools, read_only, timeout)
+            .map_err(ToolError::InvalidArguments)?;
+        let info = self
+            .children
+            .spawn(child, ttl)
+            .await
+            .map_err(ToolError::Execution)?;
+        Ok(ToolResult::structured(serde_json::to_value(info).unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpStream;
+
+    async fn call(stream: &mut BufReader<TcpStream>, id: u64, name: &str) -> Value {
+        let request = serde_json::json!({
+            "jsonrpc": "2.0", "id": id, "method": "tools/call",
+            "params": {"name": name, "arguments": {"text": "hi"}}
+        });
+        stream
+            .get_mut()
+            .write_all(format!("{}\n", request).as_bytes())
+            .await
+            .unwrap();
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_children_offer_only_their_tools_until_stopped() {
+        let server = Arc::new(McpServer::new());
+        let children = Arc::new(ChildServers::default());
+        register(&server, children.clone());
+
+        let spawn = |id: u64, arguments: Value| {
+            let request = serde_json::json!({
+                "jsonrpc": "2.0", "id": id, "method": "tools/call",
+                "params": {"name": NAME, "arguments": arguments}
+            });
+            server.handle_request(serde_json::from_value(request).unwrap())
+        };
+        let refused = spawn(1, serde_json::json!({"tools": ["echo", "no_such_tool"]}))
+            .await
+            .unwrap();
+        assert_eq!(refused.error.unwrap().code, -32602);
+        let spawned = spawn(2, serde_json::json!({"tools": ["echo"], "ttlSecs": 60}))
+            .await
+            .unwrap();
+        let info = spawned.result.unwrap()["structuredContent"].clone();
+        assert_eq!(info["tools"], serde_json::json!(["echo"]));
+        assert_eq!(children.list().len(), 1);
+
+        let address = info["address"].as_str().unwrap();
+        let handshake = concat!(
+            r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "\n"
+        );
+        let mut stream = BufReader::new(TcpStream::connect(address).await.unwrap());
+        stream
+            .get_mut()
+            .write_all(handshake.as_bytes())
+            .await
+            .unwrap();
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        let refused: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(
+            refused["error"]["code"],
+            crate::transport::auth::UNAUTHORIZED
+        );
+
+        let token = info["token"].as_str().unwrap();
+        assert_eq!(token.len(), 32);
+        let mut stream = BufReader::new(TcpStream::connect(address).await.unwrap());
+        let authorization = format!("Authorization: Bearer {}\n{}", token, handshake);
+        stream
+            .get_mut()
+            .write_all(authorization.as_bytes())
+            .await
+            .unwrap();
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+
+        let echoed = call(&mut stream, 1, "echo").await;
+        assert_eq!(echoed["result"]["content"][0]["text"], "Echo: hi");
+        let withheld = call(&mut stream, 2, "count_tokens").await;
+        assert_eq!(withheld["error"]["code"], -32601);
+
+        assert!(children.stop(info["id"].as_str().unwrap()));
+        assert!(children.list().is_empty());
+        // Skip the list-changed notification sent as echo was withdrawn.
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        let stopped = call(&mut stream, 3, "echo").await;
+        assert_eq!(stopped["error"]["code"], -32601);
+    }
+}
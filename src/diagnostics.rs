@@ -0,0 +1,102 @@
+//! The server's own diagnostics, for operators, as opposed to the log
+//! entries tools send clients through [`logging`](crate::logging). The
+//! library reports through `tracing`, with a span per request carrying its
+//! method, id, session, tool and duration; the `mcp-server` binary installs
+//! a subscriber writing to stderr, since stdout may carry the protocol.
+//! Embedders install their own subscriber, or none.
+
+use serde::Deserialize;
+use std::io::IsTerminal;
+use std::str::FromStr;
+use tracing_subscriber::EnvFilter;
+
+/// How diagnostics are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One line per event, for reading as the server runs.
+    #[default]
+    Text,
+    /// Several lines per event, with each field on its own, for
+    /// development.
+    Pretty,
+    /// One JSON object per line, for log collectors in production.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "pretty" => Ok(Self::Pretty),
+            "json" => Ok(Self::Json),
+            _ => Err(format!(
+                "Unknown log format '{}'; expected text, pretty or json",
+                s
+            )),
+        }
+    }
+}
+
+impl LogFormat {
+    /// `MCP_LOG_FORMAT`, or [`Text`](Self::Text). A malformed value is
+    /// reported and ignored.
+    pub fn from_env() -> Self {
+        match std::env::var("MCP_LOG_FORMAT") {
+            Ok(value) => value.trim().parse().unwrap_or_else(|e| {
+                eprintln!("{}", e);
+                Self::default()
+            }),
+            Err(_) => Self::default(),
+        }
+    }
+}
+
+/// Checks a filter in `RUST_LOG` syntax, such as
+/// `info,mcp_server::transport=debug`.
+pub fn parse_filter(filter: &str) -> Result<EnvFilter, String> {
+    EnvFilter::try_new(filter).map_err(|e| format!("Invalid log filter '{}': {}", filter, e))
+}
+
+/// Installs the subscriber for the process, showing the events `filter`
+/// lets through. Without one, `RUST_LOG` decides, and failing that `info`
+/// and up are shown.
+pub fn init(format: LogFormat, filter: Option<&str>) -> Result<(), String> {
+    let filter = match filter
+        .map(str::to_string)
+        .or_else(|| std::env::var("RUST_LOG").ok())
+    {
+        Some(filter) => parse_filter(&filter)?,
+        None => EnvFilter::new("info"),
+    };
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    let installed = match format {
+        LogFormat::Text => builder
+            .with_ansi(std::io::stderr().is_terminal())
+            .try_init(),
+        LogFormat::Pretty => builder.pretty().try_init(),
+        LogFormat::Json => builder.json().flatten_event(true).try_init(),
+    };
+    installed.map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_formats_parse() {
+        assert_eq!("json".parse(), Ok(LogFormat::Json));
+        assert_eq!("pretty".parse(), Ok(LogFormat::Pretty));
+        assert!("yaml"
+            .parse::<LogFormat>()
+            .unwrap_err()
+            .contains("text, pretty or json"));
+        assert!(parse_filter("info,mcp_server::transport=debug").is_ok());
+        assert!(parse_filter("mcp_server=loud").is_err());
+    }
+}
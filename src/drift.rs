@@ -0,0 +1,345 @@
+//! Schema drift between the tools a server offers now and a snapshot
+//! exported earlier, for teams that version their tool surface like an API.
+//! `mcp-server schema export` writes the snapshot and `mcp-server schema
+//! diff <file>` reports what changed since, failing on breaking changes.
+//!
+//! Changes are judged from a caller's side: a call that was valid against
+//! the snapshot and may be rejected now is breaking.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use crate::protocol::Tool;
+
+/// One difference between the snapshot and the current tools.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    pub tool: String,
+    /// JSON path of the changed schema, such as `input_schema.properties.url`.
+    /// Empty for a tool added or removed as a whole.
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub path: String,
+    pub breaking: bool,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DriftReport {
+    pub breaking: usize,
+    pub changes: Vec<Change>,
+}
+
+impl DriftReport {
+    pub fn is_breaking(&self) -> bool {
+        self.breaking > 0
+    }
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    tools: Vec<Tool>,
+}
+
+/// The snapshot `schema export` prints: the tools sorted by name, in the
+/// same shape as a `tools/list` result.
+pub fn export(tools: &[Tool]) -> Value {
+    let mut tools = tools.to_vec();
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    json!({ "tools": tools })
+}
+
+/// Reads a snapshot, which may also be a saved `tools/list` response.
+pub fn load(path: &Path) -> Result<Vec<Tool>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut value: Value = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    if let Some(result) = value.get_mut("result") {
+        value = result.take();
+    }
+    let snapshot: Snapshot = serde_json::from_value(value).map_err(|e| e.to_string())?;
+    Ok(snapshot.tools)
+}
+
+pub fn compare(snapshot: &[Tool], current: &[Tool]) -> DriftReport {
+    let before: BTreeMap<&str, &Tool> = snapshot.iter().map(|t| (t.name.as_str(), t)).collect();
+    let after: BTreeMap<&str, &Tool> = current.iter().map(|t| (t.name.as_str(), t)).collect();
+    let mut changes = Vec::new();
+    for (name, old) in &before {
+        let Some(new) = after.get(name) else {
+            changes.push(Change {
+                tool: name.to_string(),
+                path: String::new(),
+                breaking: true,
+                message: "Tool removed".to_string(),
+            });
+            continue;
+        };
+        let mut diff = Diff {
+            tool: name,
+            changes: &mut changes,
+        };
+        diff.schema("input_schema", &old.input_schema, &new.input_schema);
+    }
+    for name in after.keys().filter(|name| !before.contains_key(*name)) {
+        changes.push(Change {
+            tool: name.to_string(),
+            path: String::new(),
+            breaking: false,
+            message: "Tool added".to_string(),
+        });
+    }
+    DriftReport {
+        breaking: changes.iter().filter(|c| c.breaking).count(),
+        changes,
+    }
+}
+
+struct Diff<'a> {
+    tool: &'a str,
+    changes: &'a mut Vec<Change>,
+}
+
+impl Diff<'_> {
+    fn push(&mut self, path: &str, breaking: bool, message: String) {
+        self.changes.push(Change {
+            tool: self.tool.to_string(),
+            path: path.to_string(),
+            breaking,
+            message,
+        });
+    }
+
+    fn schema(&mut self, path: &str, old: &Value, new: &Value) {
+        let (old_types, new_types) = (types(old), types(new));
+        // No `type` accepts anything.
+        if !new_types.is_empty() && old_types != new_types {
+            let widened = !old_types.is_empty() && old_types.is_subset(&new_types);
+            self.push(
+                path,
+                !widened,
+                format!(
+                    "Type changed from {} to {}",
+                    describe(&old_types),
+                    describe(&new_types)
+                ),
+            );
+        }
+
+        match (values(old, "enum"), values(new, "enum")) {
+            (None, Some(_)) => self.push(path, true, "Values restricted to an enum".to_string()),
+            (Some(_), None) => self.push(path, false, "Enum restriction lifted".to_string()),
+            (Some(old_enum), Some(new_enum)) => {
+                let removed: Vec<&Value> =
+                    old_enum.iter().filter(|v| !new_enum.contains(v)).collect();
+                if !removed.is_empty() {
+                    self.push(
+                        path,
+                        true,
+                        format!("Enum values removed: {}", list(&removed)),
+                    );
+                }
+                let added: Vec<&Value> =
+                    new_enum.iter().filter(|v| !old_enum.contains(v)).collect();
+                if !added.is_empty() {
+                    self.push(path, false, format!("Enum values added: {}", list(&added)));
+                }
+            }
+            (None, None) => {}
+        }
+
+        let old_required = strings(old, "required");
+        let new_required = strings(new, "required");
+        let empty = serde_json::Map::new();
+        let old_properties = old
+            .get("properties")
+            .and_then(Value::as_object)
+            .unwrap_or(&empty);
+        let new_properties = new
+            .get("properties")
+            .and_then(Value::as_object)
+            .unwrap_or(&empty);
+        for (name, old_property) in old_properties {
+            let property_path = format!("{}.properties.{}", path, name);
+            match new_properties.get(name) {
+                Some(new_property) => self.schema(&property_path, old_property, new_property),
+                // Callers sending it have it ignored at best, and rejected
+                // by a schema closed to additional properties.
+                None => self.push(&property_path, true, "Property removed".to_string()),
+            }
+        }
+        for name in new_properties.keys() {
+            if !old_properties.contains_key(name) {
+                let required = new_required.contains(name.as_str());
+                let message = if required {
+                    "Required property added"
+                } else {
+                    "Optional property added"
+                };
+                let property_path = format!("{}.properties.{}", path, name);
+                self.push(&property_path, required, message.to_string());
+            }
+        }
+        for name in new_required.difference(&old_required) {
+            if old_properties.contains_key(*name) {
+                let property_path = format!("{}.properties.{}", path, name);
+                self.push(&property_path, true, "Property became required".to_string());
+            }
+        }
+        for name in old_required.difference(&new_required) {
+            if new_properties.contains_key(*name) {
+                let property_path = format!("{}.properties.{}", path, name);
+                self.push(
+                    &property_path,
+                    false,
+                    "Property became optional".to_string(),
+                );
+            }
+        }
+        let closed =
+            |schema: &Value| schema.get("additionalProperties") == Some(&Value::Bool(false));
+        if closed(new) && !closed(old) {
+            self.push(
+                path,
+                true,
+                "Additional properties no longer allowed".to_string(),
+            );
+        }
+
+        if let (Some(old_items), Some(new_items)) = (old.get("items"), new.get("items")) {
+            self.schema(&format!("{}.items", path), old_items, new_items);
+        }
+    }
+}
+
+/// The schema's `type`, which may be a single name or a list.
+fn types(schema: &Value) -> BTreeSet<&str> {
+    match schema.get("type") {
+        Some(Value::String(name)) => BTreeSet::from([name.as_str()]),
+        Some(Value::Array(names)) => names.iter().filter_map(Value::as_str).collect(),
+        _ => BTreeSet::new(),
+    }
+}
+
+fn describe(types: &BTreeSet<&str>) -> String {
+    if types.is_empty() {
+        return "any".to_string();
+    }
+    types.iter().copied().collect::<Vec<_>>().join(" | ")
+}
+
+fn values<'a>(schema: &'a Value, key: &str) -> Option<&'a Vec<Value>> {
+    schema.get(key).and_then(Value::as_array)
+}
+
+fn strings<'a>(schema: &'a Value, key: &str) -> BTreeSet<&'a str> {
+    values(schema, key)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect()
+}
+
+fn list(values: &[&Value]) -> String {
+    values
+        .iter()
+        .map(|v| v.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(name: &str, input_schema: Value) -> Tool {
+        Tool {
+            name: name.to_string(),
+            description: String::new(),
+            input_schema,
+            output_schema: None,
+            annotations: None,
+            meta: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_reports_breaking_schema_changes() {
+        let snapshot = vec![
+            tool(
+                "fetch",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "url": {"type": "string"},
+                        "method": {"type": "string", "enum": ["GET", "POST", "PUT"]},
+                        "timeout": {"type": "integer"},
+                        "headers": {"type": "object"}
+                    },
+                    "required": ["url"]
+                }),
+            ),
+            tool("legacy", json!({"type": "object"})),
+        ];
+        let current = vec![
+            tool(
+                "fetch",
+                json!({
+                    "type": "object",
+                    "properties": {
+                        "url": {"type": "string"},
+                        "method": {"type": "string", "enum": ["GET", "POST", "PATCH"]},
+                        "timeout": {"type": ["integer", "string"]},
+                        "retries": {"type": "integer"},
+                        "body": {"type": "string"}
+                    },
+                    "required": ["url", "body"]
+                }),
+            ),
+            tool("search", json!({"type": "object"})),
+        ];
+
+        let report = compare(&snapshot, &current);
+        let found: Vec<(&str, &str, bool)> = report
+            .changes
+            .iter()
+            .map(|c| (c.path.as_str(), c.message.as_str(), c.breaking))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                ("input_schema.properties.headers", "Property removed", true),
+                (
+                    "input_schema.properties.method",
+                    "Enum values removed: \"PUT\"",
+                    true
+                ),
+                (
+                    "input_schema.properties.method",
+                    "Enum values added: \"PATCH\"",
+                    false
+                ),
+                (
+                    "input_schema.properties.timeout",
+                    "Type changed from integer to integer | string",
+                    false
+                ),
+                (
+                    "input_schema.properties.body",
+                    "Required property added",
+                    true
+                ),
+                (
+                    "input_schema.properties.retries",
+                    "Optional property added",
+                    false
+                ),
+                ("", "Tool removed", true),
+                ("", "Tool added", false),
+            ]
+        );
+        assert_eq!(report.breaking, 4);
+        assert!(!compare(&current, &current).is_breaking());
+        assert!(compare(&current, &current).changes.is_empty());
+    }
+}
@@ -0,0 +1,248 @@
+//! Outbound network policy for tools. Network-using tools connect through
+//! [`EgressPolicy::connect`], which resolves the host itself and only dials
+//! addresses the tool's rules allow, so enabling a tool such as `http_fetch`
+//! does not open the whole internal network to it.
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::path::Path;
+use tokio::net::TcpStream;
+
+use crate::security::{SecurityEvent, SecurityEventKind, SecurityLog};
+use crate::transport::access::Cidr;
+
+#[derive(Debug, Clone, PartialEq)]
+enum HostPattern {
+    Exact(String),
+    /// `*.example.com`: any subdomain, but not `example.com` itself.
+    Subdomains(String),
+    Block(Cidr),
+}
+
+/// One destination a tool may contact.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EgressRule {
+    host: HostPattern,
+    /// Allowed ports; empty allows any.
+    ports: Vec<u16>,
+}
+
+impl EgressRule {
+    /// `host` is a name such as `api.example.com`, a wildcard such as
+    /// `*.example.com`, or an address block such as `10.1.0.0/16`.
+    pub fn new(host: &str, ports: Vec<u16>) -> Result<Self, String> {
+        let host = host.trim().to_ascii_lowercase();
+        let pattern = if let Some(domain) = host.strip_prefix("*.") {
+            HostPattern::Subdomains(format!(".{}", domain))
+        } else if host.contains('/') || host.parse::<IpAddr>().is_ok() {
+            HostPattern::Block(Cidr::parse(&host)?)
+        } else if !host.is_empty() && !host.contains(['*', ':', ' ']) {
+            HostPattern::Exact(host)
+        } else {
+            return Err(format!("Invalid egress host '{}'", host));
+        };
+        Ok(Self {
+            host: pattern,
+            ports,
+        })
+    }
+
+    /// Whether this rule lets a connection to `host`, resolved to `addr`,
+    /// through. Names only authorise public addresses: an allowed name that
+    /// resolves into a private range (DNS rebinding, or a typo) is refused
+    /// unless a block rule covers that range explicitly.
+    fn allows(&self, host: &str, addr: SocketAddr) -> bool {
+        if !self.ports.is_empty() && !self.ports.contains(&addr.port()) {
+            return false;
+        }
+        match &self.host {
+            HostPattern::Block(cidr) => cidr.contains(addr.ip()),
+            HostPattern::Exact(name) => name == host && !is_internal(addr.ip()),
+            HostPattern::Subdomains(suffix) => host.ends_with(suffix) && !is_internal(addr.ip()),
+        }
+    }
+}
+
+/// Loopback, private, link-local and other non-public addresses.
+fn is_internal(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                // Carrier-grade NAT, 100.64.0.0/10.
+                || (a == 100 && (64..128).contains(&b))
+        }
+        IpAddr::V6(v6) => {
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_unique_local()
+                || v6.is_unicast_link_local()
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct RuleConfig {
+    host: String,
+    #[serde(default)]
+    ports: Vec<u16>,
+}
+
+#[derive(Debug, Deserialize)]
+struct EgressFile {
+    tools: HashMap<String, Vec<RuleConfig>>,
+}
+
+/// Destinations per tool. A tool without rules may not connect anywhere.
+#[derive(Default)]
+pub struct EgressPolicy {
+    rules: HashMap<String, Vec<EgressRule>>,
+    security: SecurityLog,
+}
+
+impl EgressPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow(mut self, tool: &str, rule: EgressRule) -> Self {
+        self.rules.entry(tool.to_string()).or_default().push(rule);
+        self
+    }
+
+    pub fn with_security(mut self, security: SecurityLog) -> Self {
+        self.security = security;
+        self
+    }
+
+    /// Reads a `{"tools": {"<tool>": [{"host": ..., "ports": [...]}]}}` JSON
+    /// file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let file: EgressFile = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+        let mut policy = Self::new();
+        for (tool, rules) in file.tools {
+            for rule in rules {
+                policy = policy.allow(&tool, EgressRule::new(&rule.host, rule.ports)?);
+            }
+        }
+        Ok(policy)
+    }
+
+    /// Policy from `MCP_EGRESS_FILE`, or one allowing nothing when unset. A
+    /// file that fails to load is reported and ignored.
+    pub fn from_env() -> Self {
+        let Ok(path) = std::env::var("MCP_EGRESS_FILE") else {
+            return Self::default();
+        };
+        Self::load(Path::new(&path)).unwrap_or_else(|e| {
+            tracing::warn!("Failed to load egress policy from {}: {}", path, e);
+            Self::default()
+        })
+    }
+
+    /// Whether `tool` may contact anything at all.
+    pub fn has_rules(&self, tool: &str) -> bool {
+        self.rules.get(tool).is_some_and(|rules| !rules.is_empty())
+    }
+
+    /// Resolves `host` and connects to the first address `tool` is allowed
+    /// to reach. Refusals are reported as security events.
+    pub async fn connect(&self, tool: &str, host: &str, port: u16) -> Result<TcpStream, String> {
+        let host = host
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .to_ascii_lowercase();
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), port))
+            .await
+            .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+            .collect();
+        let rules = self.rules.get(tool).map(Vec::as_slice).unwrap_or_default();
+        let Some(addr) = addrs
+            .iter()
+            .find(|addr| rules.iter().any(|rule| rule.allows(&host, **addr)))
+        else {
+            let mut event = SecurityEvent::new(
+                SecurityEventKind::PolicyDenied,
+                "Outbound connection refused by egress policy",
+            )
+            .rule("egress")
+            .tool(tool)
+            .detail("host", host.as_str())
+            .detail("port", port);
+            if let Some(addr) = addrs.first() {
+                event = event.detail("address", addr.ip().to_string());
+            }
+            self.security.report(event);
+            return Err(format!(
+                "Egress to {}:{} is not allowed for {}",
+                host, port, tool
+            ));
+        };
+        TcpStream::connect(addr).await.map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> SocketAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_rules() {
+        let api = EgressRule::new("api.example.com", vec![443]).unwrap();
+        assert!(api.allows("api.example.com", addr("93.184.216.34:443")));
+        assert!(!api.allows("api.example.com", addr("93.184.216.34:80")));
+        assert!(!api.allows("api.example.com", addr("10.0.0.5:443")));
+        assert!(!api.allows("other.example.com", addr("93.184.216.34:443")));
+
+        let wildcard = EgressRule::new("*.example.com", Vec::new()).unwrap();
+        assert!(wildcard.allows("cdn.example.com", addr("93.184.216.34:80")));
+        assert!(!wildcard.allows("example.com", addr("93.184.216.34:80")));
+        assert!(!wildcard.allows("cdn.example.com", addr("169.254.169.254:80")));
+
+        let block = EgressRule::new("10.1.0.0/16", vec![8080]).unwrap();
+        assert!(block.allows("svc.internal", addr("10.1.2.3:8080")));
+        assert!(!block.allows("svc.internal", addr("10.2.0.1:8080")));
+
+        assert!(EgressRule::new("bad host", Vec::new()).is_err());
+        assert!(EgressRule::new("10.0.0.0/99", Vec::new()).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_connect_enforces_rules() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let by_name = EgressPolicy::new().allow(
+            "http_fetch",
+            EgressRule::new("localhost", Vec::new()).unwrap(),
+        );
+        assert!(by_name
+            .connect("http_fetch", "localhost", port)
+            .await
+            .unwrap_err()
+            .contains("not allowed"));
+
+        let by_block = EgressPolicy::new().allow(
+            "http_fetch",
+            EgressRule::new("127.0.0.0/8", vec![port]).unwrap(),
+        );
+        assert!(by_block
+            .connect("http_fetch", "127.0.0.1", port)
+            .await
+            .is_ok());
+        assert!(by_block
+            .connect("other_tool", "127.0.0.1", port)
+            .await
+            .is_err());
+    }
+}
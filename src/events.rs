@@ -0,0 +1,201 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+
+/// Events are dropped for subscribers that fall this far behind.
+const CHANNEL_CAPACITY: usize = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EventKind {
+    /// A `tools/call` finished, successfully or not.
+    ToolCallCompleted,
+    /// The share of failing tool calls crossed the configured threshold.
+    ErrorRateSpike,
+    /// A workflow instance reached a final state.
+    JobFinished,
+    /// A call was parked waiting for human approval.
+    ApprovalRequested,
+    /// A blue/green tool took its candidate backend out of rotation.
+    BackendRolledBack,
+}
+
+impl EventKind {
+    pub const ALL: [EventKind; 5] = [
+        EventKind::ToolCallCompleted,
+        EventKind::ErrorRateSpike,
+        EventKind::JobFinished,
+        EventKind::ApprovalRequested,
+        EventKind::BackendRolledBack,
+    ];
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Event {
+    pub id: String,
+    pub kind: EventKind,
+    /// Unix time in milliseconds.
+    pub at: u64,
+    pub data: Value,
+}
+
+/// Fan-out of server events to sinks such as webhooks. Clones publish to
+/// the same subscribers; publishing with no subscribers is a no-op.
+#[derive(Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self {
+            sender: broadcast::channel(CHANNEL_CAPACITY).0,
+        }
+    }
+
+    pub fn publish(&self, kind: EventKind, data: Value) {
+        let event = Event {
+            id: uuid::Uuid::new_v4().to_string(),
+            kind,
+            at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_millis() as u64)
+                .unwrap_or(0),
+            data,
+        };
+        let _ = self.sender.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub const DEFAULT_ERROR_RATE_THRESHOLD: f64 = 0.5;
+pub const DEFAULT_ERROR_RATE_WINDOW_SECS: u64 = 60;
+/// Below this many calls in the window the rate is too noisy to report.
+const MIN_CALLS: usize = 10;
+
+/// Tracks tool-call outcomes over a sliding window and reports a spike when
+/// the error share reaches the threshold. After reporting, it stays quiet
+/// for one window so a sustained outage yields one event per window.
+pub struct ErrorRateMonitor {
+    threshold: f64,
+    window: Duration,
+    state: Mutex<Window>,
+}
+
+#[derive(Default)]
+struct Window {
+    /// When each call in the window finished and whether it failed.
+    outcomes: VecDeque<(Instant, bool)>,
+    last_spike: Option<Instant>,
+}
+
+impl ErrorRateMonitor {
+    pub fn new(threshold: f64, window: Duration) -> Self {
+        Self {
+            threshold,
+            window,
+            state: Mutex::new(Window::default()),
+        }
+    }
+
+    /// Monitor using `MCP_ERROR_RATE_THRESHOLD` (0–1) and
+    /// `MCP_ERROR_RATE_WINDOW_SECS`.
+    pub fn from_env() -> Self {
+        Self::new(
+            std::env::var("MCP_ERROR_RATE_THRESHOLD")
+                .ok()
+                .and_then(|s| s.parse().ok())
+                .unwrap_or(DEFAULT_ERROR_RATE_THRESHOLD),
+            Duration::from_secs(
+                std::env::var("MCP_ERROR_RATE_WINDOW_SECS")
+                    .ok()
+                    .and_then(|s| s.parse().ok())
+                    .unwrap_or(DEFAULT_ERROR_RATE_WINDOW_SECS),
+            ),
+        )
+    }
+
+    /// Records one call and returns the spike details when it tips the
+    /// window over the threshold.
+    pub fn record(&self, failed: bool) -> Option<Value> {
+        self.record_at(Instant::now(), failed)
+    }
+
+    fn record_at(&self, now: Instant, failed: bool) -> Option<Value> {
+        let mut state = self.state.lock().unwrap();
+        let Window {
+            outcomes,
+            last_spike,
+        } = &mut *state;
+        outcomes.push_back((now, failed));
+        while outcomes
+            .front()
+            .is_some_and(|(at, _)| now.duration_since(*at) > self.window)
+        {
+            outcomes.pop_front();
+        }
+
+        if last_spike.is_some_and(|at| now.duration_since(at) < self.window) {
+            return None;
+        }
+        let errors = outcomes.iter().filter(|(_, failed)| *failed).count();
+        let rate = errors as f64 / outcomes.len() as f64;
+        if outcomes.len() < MIN_CALLS || rate < self.threshold {
+            return None;
+        }
+        *last_spike = Some(now);
+        Some(serde_json::json!({
+            "errors": errors,
+            "calls": outcomes.len(),
+            "rate": rate,
+            "windowSecs": self.window.as_secs(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_subscribers_receive_published_events() {
+        let bus = EventBus::new();
+        let mut events = bus.subscribe();
+        bus.publish(EventKind::JobFinished, serde_json::json!({"workflow": "x"}));
+
+        let event = events.recv().await.unwrap();
+        assert_eq!(event.kind, EventKind::JobFinished);
+        assert_eq!(event.data["workflow"], "x");
+        assert_eq!(serde_json::to_value(event.kind).unwrap(), "job_finished");
+    }
+
+    #[test]
+    fn test_error_rate_spike_reported_once_per_window() {
+        let monitor = ErrorRateMonitor::new(0.5, Duration::from_secs(60));
+        let start = Instant::now();
+
+        for i in 0..9 {
+            assert!(monitor.record_at(start, i % 2 == 0).is_none());
+        }
+        let spike = monitor.record_at(start, true).unwrap();
+        assert_eq!(spike["errors"], 6);
+        assert_eq!(spike["calls"], 10);
+
+        assert!(monitor.record_at(start, true).is_none());
+        assert!(monitor
+            .record_at(start + Duration::from_secs(30), true)
+            .is_none());
+    }
+}
@@ -0,0 +1,369 @@
+//! Reclaiming expired state. A running server collects everything it owns
+//! on a timer and on the admin socket's `gc`; `mcp-server gc`, which runs in
+//! a process of its own, only touches persisted state.
+
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+
+use crate::McpServer;
+
+pub const DEFAULT_INTERVAL_SECS: u64 = 300;
+pub const DEFAULT_TEMP_DIR_TTL_SECS: u64 = 3600;
+pub const DEFAULT_SESSION_TTL_SECS: u64 = 3600;
+pub const DEFAULT_JOB_TTL_SECS: u64 = 86400;
+
+/// Used by `mcp-server gc` when `--older-than` is not given.
+pub const DEFAULT_OFFLINE_MIN_AGE_SECS: u64 = 86400;
+
+/// Prefixes of the scratch directories tools create under the system temp
+/// directory. Normally removed by the tool itself; anything left behind by a
+/// crash is swept once it is older than the temp-dir TTL.
+const TEMP_DIR_PREFIXES: &[&str] = &["mcp-python-", "mcp-kernel-"];
+
+/// Scratch directories of runs still in progress in this process. Tool
+/// timeouts are chosen per call, so no TTL is long enough to be safe; the
+/// sweep skips these instead. Directories of other processes carry their
+/// pid in the name and are skipped while it is running.
+static IN_FLIGHT: Mutex<Option<HashSet<PathBuf>>> = Mutex::new(None);
+
+/// A tool scratch directory under the system temp directory, removed when
+/// dropped and never swept while it exists.
+pub struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    /// Creates a fresh directory named `prefix`, this process's pid and a
+    /// random suffix. `prefix` should be one of the prefixes the sweep knows
+    /// about.
+    pub fn create(prefix: &str) -> std::io::Result<Self> {
+        Self::create_in(&std::env::temp_dir(), prefix)
+    }
+
+    fn create_in(root: &Path, prefix: &str) -> std::io::Result<Self> {
+        let name = format!("{}{}-{}", prefix, std::process::id(), uuid::Uuid::new_v4());
+        let path = root.join(name);
+        std::fs::create_dir_all(&path)?;
+        IN_FLIGHT
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashSet::new)
+            .insert(path.clone());
+        Ok(Self { path })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.path);
+        if let Some(in_flight) = IN_FLIGHT.lock().unwrap().as_mut() {
+            in_flight.remove(&self.path);
+        }
+    }
+}
+
+/// How often garbage collection runs and what counts as expired.
+#[derive(Debug, Clone)]
+pub struct GcPolicy {
+    /// Interval of the background sweep; zero disables it.
+    pub interval: Duration,
+    /// Age after which memory entities are dropped; `None` keeps them forever.
+    pub memory_ttl: Option<Duration>,
+    pub temp_dir_ttl: Duration,
+    /// Age after which a session's split-result parts, summarized results
+    /// and uploads are dropped.
+    pub session_ttl: Duration,
+    /// Age after which a finished workflow instance is dropped.
+    pub job_ttl: Duration,
+}
+
+impl GcPolicy {
+    /// Policy from `MCP_GC_INTERVAL_SECS`, `MCP_MEMORY_TTL_SECS`,
+    /// `MCP_TEMP_DIR_TTL_SECS`, `MCP_SESSION_RESULT_TTL_SECS` and
+    /// `MCP_JOB_TTL_SECS`.
+    pub fn from_env() -> Self {
+        let secs = |key: &str| std::env::var(key).ok().and_then(|s| s.parse::<u64>().ok());
+        Self {
+            interval: Duration::from_secs(
+                secs("MCP_GC_INTERVAL_SECS").unwrap_or(DEFAULT_INTERVAL_SECS),
+            ),
+            memory_ttl: secs("MCP_MEMORY_TTL_SECS").map(Duration::from_secs),
+            temp_dir_ttl: Duration::from_secs(
+                secs("MCP_TEMP_DIR_TTL_SECS").unwrap_or(DEFAULT_TEMP_DIR_TTL_SECS),
+            ),
+            session_ttl: Duration::from_secs(
+                secs("MCP_SESSION_RESULT_TTL_SECS").unwrap_or(DEFAULT_SESSION_TTL_SECS),
+            ),
+            job_ttl: Duration::from_secs(secs("MCP_JOB_TTL_SECS").unwrap_or(DEFAULT_JOB_TTL_SECS)),
+        }
+    }
+}
+
+/// What a garbage-collection pass reclaimed.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct GcReport {
+    pub repl_sessions: usize,
+    pub memory_entities: usize,
+    pub memory_relations: usize,
+    pub temp_dirs: usize,
+    pub artifacts: usize,
+    /// Split-result parts and summarized results.
+    pub session_results: usize,
+    pub uploads: usize,
+    /// Finished workflow instances.
+    pub jobs: usize,
+    pub bytes_reclaimed: u64,
+}
+
+impl GcReport {
+    pub fn is_empty(&self) -> bool {
+        *self == GcReport::default()
+    }
+
+    pub fn merge(&mut self, other: &GcReport) {
+        self.repl_sessions += other.repl_sessions;
+        self.memory_entities += other.memory_entities;
+        self.memory_relations += other.memory_relations;
+        self.temp_dirs += other.temp_dirs;
+        self.artifacts += other.artifacts;
+        self.session_results += other.session_results;
+        self.uploads += other.uploads;
+        self.jobs += other.jobs;
+        self.bytes_reclaimed += other.bytes_reclaimed;
+    }
+}
+
+/// Counters of a server's garbage collection, returned by the admin
+/// socket's `gc` and `gc/stats`.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcStats {
+    pub runs: u64,
+    /// RFC 3339 time the last pass finished.
+    pub last_run_at: Option<String>,
+    pub last: GcReport,
+    pub total: GcReport,
+}
+
+/// Runs [`McpServer::gc`] inside the server, so it sees the scratch
+/// directories and sessions in use, and keeps [`GcStats`].
+pub struct Collector {
+    server: Arc<McpServer>,
+    policy: GcPolicy,
+    stats: Mutex<GcStats>,
+}
+
+impl Collector {
+    pub fn new(server: Arc<McpServer>, policy: GcPolicy) -> Self {
+        Self {
+            server,
+            policy,
+            stats: Mutex::default(),
+        }
+    }
+
+    pub fn policy(&self) -> &GcPolicy {
+        &self.policy
+    }
+
+    /// Runs one pass, logging a non-empty report with running totals to
+    /// stderr.
+    pub async fn run(&self) -> GcReport {
+        let report = self.server.gc(&self.policy).await;
+        let mut stats = self.stats.lock().unwrap();
+        stats.runs += 1;
+        stats.last_run_at = Some(crate::security::rfc3339(SystemTime::now()));
+        stats.last = report.clone();
+        stats.total.merge(&report);
+        if !report.is_empty() {
+            tracing::info!(
+                "GC reclaimed {} (total {})",
+                serde_json::to_string(&report).unwrap(),
+                serde_json::to_string(&stats.total).unwrap()
+            );
+        }
+        report
+    }
+
+    pub fn stats(&self) -> GcStats {
+        self.stats.lock().unwrap().clone()
+    }
+}
+
+/// Runs the collector every `policy.interval`.
+pub fn spawn(collector: Arc<Collector>) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(collector.policy.interval);
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            collector.run().await;
+        }
+    })
+}
+
+/// Removes tool scratch directories under `root` older than `max_age`,
+/// other than those of runs still in progress here or in another running
+/// process, and returns how many were removed and their total size in
+/// bytes.
+pub fn sweep_temp_dirs(root: &Path, max_age: Duration) -> (usize, u64) {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return (0, 0);
+    };
+    let in_flight = IN_FLIGHT.lock().unwrap().clone().unwrap_or_default();
+
+    let mut removed = 0;
+    let mut bytes = 0;
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if !TEMP_DIR_PREFIXES
+            .iter()
+            .any(|prefix| name.starts_with(prefix))
+        {
+            continue;
+        }
+        if in_flight.contains(&entry.path()) || owner_running(&name) {
+            continue;
+        }
+        let expired = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age >= max_age);
+        if !expired {
+            continue;
+        }
+
+        let size = dir_size(&entry.path());
+        if std::fs::remove_dir_all(entry.path()).is_ok() {
+            removed += 1;
+            bytes += size;
+        }
+    }
+    (removed, bytes)
+}
+
+/// Whether the directory `name` belongs to another process that is still
+/// running. Names without a pid, from before pids were recorded, are
+/// judged by age alone.
+fn owner_running(name: &str) -> bool {
+    let Some(pid) = TEMP_DIR_PREFIXES
+        .iter()
+        .find_map(|prefix| name.strip_prefix(prefix))
+        .and_then(|rest| rest.split_once('-'))
+        .and_then(|(pid, _)| pid.parse::<u32>().ok())
+    else {
+        return false;
+    };
+    pid != std::process::id() && process_running(pid)
+}
+
+#[cfg(target_os = "linux")]
+fn process_running(pid: u32) -> bool {
+    Path::new("/proc").join(pid.to_string()).exists()
+}
+
+#[cfg(not(target_os = "linux"))]
+fn process_running(_pid: u32) -> bool {
+    false
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(entries) = std::fs::read_dir(path) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(m) if m.is_dir() => dir_size(&entry.path()),
+            Ok(m) => m.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sweep_removes_only_expired_tool_dirs() {
+        let root = std::env::temp_dir().join(format!("gc-test-{}", uuid::Uuid::new_v4()));
+        let stale = root.join("mcp-python-stale");
+        let other = root.join("unrelated");
+        std::fs::create_dir_all(stale.join("nested")).unwrap();
+        std::fs::create_dir_all(&other).unwrap();
+        std::fs::write(stale.join("figures.json"), "[]").unwrap();
+        std::fs::write(stale.join("nested").join("data"), "12345").unwrap();
+
+        assert_eq!(sweep_temp_dirs(&root, Duration::from_secs(3600)), (0, 0));
+        assert_eq!(sweep_temp_dirs(&root, Duration::ZERO), (1, 7));
+        assert!(!stale.exists());
+        assert!(other.exists());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_sweep_skips_dirs_in_use() {
+        let root = std::env::temp_dir().join(format!("gc-test-{}", uuid::Uuid::new_v4()));
+        let scratch = ScratchDir::create_in(&root, "mcp-python-").unwrap();
+        std::fs::write(scratch.path().join("figures.json"), "[]").unwrap();
+
+        assert_eq!(sweep_temp_dirs(&root, Duration::ZERO), (0, 0));
+        assert!(scratch.path().exists());
+
+        let path = scratch.path().to_path_buf();
+        drop(scratch);
+        assert!(!path.exists());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sweep_skips_dirs_of_running_processes() {
+        // As `mcp-server gc` sees a running server's directories.
+        let root = std::env::temp_dir().join(format!("gc-test-{}", uuid::Uuid::new_v4()));
+        let parent = std::os::unix::process::parent_id();
+        let running = root.join(format!("mcp-kernel-{}-{}", parent, uuid::Uuid::new_v4()));
+        let exited = root.join(format!("mcp-kernel-{}-{}", u32::MAX, uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&running).unwrap();
+        std::fs::create_dir_all(&exited).unwrap();
+
+        assert_eq!(sweep_temp_dirs(&root, Duration::ZERO), (1, 0));
+        assert!(running.exists());
+        assert!(!exited.exists());
+        std::fs::remove_dir_all(root).unwrap();
+    }
+
+    #[test]
+    fn test_report_merge() {
+        let mut total = GcReport::default();
+        assert!(total.is_empty());
+
+        total.merge(&GcReport {
+            repl_sessions: 1,
+            bytes_reclaimed: 10,
+            ..Default::default()
+        });
+        total.merge(&GcReport {
+            temp_dirs: 2,
+            bytes_reclaimed: 5,
+            ..Default::default()
+        });
+
+        assert_eq!(total.repl_sessions, 1);
+        assert_eq!(total.temp_dirs, 2);
+        assert_eq!(total.bytes_reclaimed, 15);
+    }
+}
@@ -0,0 +1,292 @@
+//! Inbound webhooks, which let external systems push context to connected
+//! agents. The HTTP transport accepts `POST /hooks/<name>`, signed like the
+//! server's own [outbound webhooks](crate::webhooks): `X-MCP-Signature-256:
+//! sha256=<hex>`, the HMAC-SHA256 of the body under the hook's secret.
+//!
+//! Each hook is also a resource, `hook://<name>`, holding the last body it
+//! received. A delivery notifies the sessions subscribed to it, either with
+//! a custom notification carrying the body or with
+//! `notifications/resources/updated`. A hook may also name resources the
+//! body reports as changed, each sent to that resource's own subscribers.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::crypto;
+use crate::resources::ResourceContents;
+use crate::webhooks;
+
+/// Requests for a hook go to this path followed by its name.
+pub const PATH: &str = "/hooks/";
+
+pub const SCHEME: &str = "hook://";
+
+pub const SIGNATURE_HEADER: &str = "X-MCP-Signature-256";
+
+/// Largest body a hook accepts.
+pub const MAX_BODY_BYTES: usize = 1 << 20;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct InboundHook {
+    pub name: String,
+    pub secret: String,
+    /// Sent to the hook's subscribers with the body as params, instead of
+    /// `notifications/resources/updated`.
+    #[serde(default)]
+    pub notification: Option<String>,
+    /// URI prefixes the body may report as changed, in `uri` or `uris`.
+    #[serde(default)]
+    pub resources: Vec<String>,
+}
+
+impl InboundHook {
+    pub fn uri(&self) -> String {
+        format!("{}{}", SCHEME, self.name)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct HookFile {
+    hooks: Vec<InboundHook>,
+}
+
+/// Why a delivery was refused.
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookError {
+    NotFound,
+    BadSignature,
+    Invalid(String),
+}
+
+impl HookError {
+    pub fn status(&self) -> u16 {
+        match self {
+            HookError::NotFound => 404,
+            HookError::BadSignature => 401,
+            HookError::Invalid(_) => 400,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            HookError::NotFound => "Unknown hook",
+            HookError::BadSignature => "Invalid signature",
+            HookError::Invalid(message) => message,
+        }
+    }
+}
+
+/// One notification a delivery asks for: `method` with `params`, to the
+/// sessions subscribed to `uri`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Notification {
+    pub uri: String,
+    pub method: String,
+    pub params: Value,
+}
+
+/// The configured hooks and the last body each received.
+#[derive(Debug, Default)]
+pub struct InboundHooks {
+    hooks: HashMap<String, InboundHook>,
+    last: Mutex<HashMap<String, Value>>,
+}
+
+impl InboundHooks {
+    pub fn new(hooks: Vec<InboundHook>) -> Result<Self, String> {
+        let mut by_name = HashMap::new();
+        for hook in hooks {
+            if hook.name.is_empty() || hook.name.contains(['/', '?', '#']) {
+                return Err(format!("Invalid hook name '{}'", hook.name));
+            }
+            if hook.secret.is_empty() {
+                return Err(format!("Hook '{}' has no secret", hook.name));
+            }
+            if by_name.insert(hook.name.clone(), hook.clone()).is_some() {
+                return Err(format!("Hook '{}' is defined twice", hook.name));
+            }
+        }
+        Ok(Self {
+            hooks: by_name,
+            last: Mutex::default(),
+        })
+    }
+
+    /// Reads a `{"hooks": [...]}` JSON file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let file: HookFile = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+        Self::new(file.hooks)
+    }
+
+    /// Hooks from `MCP_INBOUND_HOOKS_FILE`, or `None` when unset. A file
+    /// that fails to load is reported and ignored.
+    pub fn from_env() -> Option<Self> {
+        let path = std::env::var("MCP_INBOUND_HOOKS_FILE").ok()?;
+        Self::load(Path::new(&path))
+            .map_err(|e| tracing::warn!("Failed to load inbound hooks from {}: {}", path, e))
+            .ok()
+    }
+
+    /// Checks a delivery to the hook `name` and keeps its body, returning
+    /// the notifications it asks for.
+    pub fn receive(
+        &self,
+        name: &str,
+        body: &[u8],
+        signature: Option<&str>,
+    ) -> Result<Vec<Notification>, HookError> {
+        let hook = self.hooks.get(name).ok_or(HookError::NotFound)?;
+        let expected = webhooks::sign(&hook.secret, body);
+        if !signature.is_some_and(|s| crypto::constant_time_eq(s.as_bytes(), expected.as_bytes())) {
+            return Err(HookError::BadSignature);
+        }
+        if body.len() > MAX_BODY_BYTES {
+            return Err(HookError::Invalid(format!(
+                "Body is longer than {} bytes",
+                MAX_BODY_BYTES
+            )));
+        }
+        let body: Value = serde_json::from_slice(body)
+            .ok()
+            .filter(Value::is_object)
+            .ok_or_else(|| HookError::Invalid("Body must be a JSON object".to_string()))?;
+
+        let mut changed = Vec::new();
+        if let Some(uri) = body.get("uri") {
+            changed.push(uri);
+        }
+        if let Some(uris) = body.get("uris") {
+            let uris = uris
+                .as_array()
+                .ok_or_else(|| HookError::Invalid("'uris' must be an array".to_string()))?;
+            changed.extend(uris);
+        }
+        let changed = changed
+            .into_iter()
+            .map(|uri| match uri.as_str() {
+                Some(uri) if hook.resources.iter().any(|p| uri.starts_with(p.as_str())) => {
+                    Ok(uri.to_string())
+                }
+                _ => Err(HookError::Invalid(format!(
+                    "Hook '{}' may not report {} as changed",
+                    name, uri
+                ))),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let uri = hook.uri();
+        let mut notifications = vec![match &hook.notification {
+            Some(method) => Notification {
+                uri: uri.clone(),
+                method: method.clone(),
+                params: body.clone(),
+            },
+            None => updated(uri),
+        }];
+        notifications.extend(changed.into_iter().map(updated));
+        self.last.lock().unwrap().insert(name.to_string(), body);
+        Ok(notifications)
+    }
+
+    /// `hook://<name>`: the last body the hook received, or `{}` before the
+    /// first.
+    pub fn read(&self, uri: &str) -> Option<Vec<ResourceContents>> {
+        let name = uri.strip_prefix(SCHEME)?;
+        self.hooks.get(name)?;
+        let body = self
+            .last
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| serde_json::json!({}));
+        let mime = Some("application/json".to_string());
+        Some(vec![ResourceContents::text(uri, mime, body.to_string())])
+    }
+}
+
+fn updated(uri: String) -> Notification {
+    Notification {
+        params: serde_json::json!({ "uri": uri }),
+        method: "notifications/resources/updated".to_string(),
+        uri,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn hooks() -> InboundHooks {
+        InboundHooks::new(vec![
+            InboundHook {
+                name: "docs".to_string(),
+                secret: "s1".to_string(),
+                notification: None,
+                resources: vec!["docs://".to_string()],
+            },
+            InboundHook {
+                name: "deploys".to_string(),
+                secret: "s2".to_string(),
+                notification: Some("notifications/deploys".to_string()),
+                resources: Vec::new(),
+            },
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_deliveries_become_notifications() {
+        let hooks = hooks();
+        let body = br#"{"uris": ["docs://guide.md"]}"#;
+        let signature = webhooks::sign("s1", body);
+        let notifications = hooks.receive("docs", body, Some(&signature)).unwrap();
+        assert_eq!(notifications.len(), 2);
+        assert_eq!(notifications[0].uri, "hook://docs");
+        assert_eq!(notifications[1].uri, "docs://guide.md");
+        assert_eq!(notifications[1].method, "notifications/resources/updated");
+        assert_eq!(notifications[1].params, json!({"uri": "docs://guide.md"}));
+
+        let body = br#"{"version": "1.2.0"}"#;
+        let signature = webhooks::sign("s2", body);
+        let notifications = hooks.receive("deploys", body, Some(&signature)).unwrap();
+        assert_eq!(notifications[0].method, "notifications/deploys");
+        assert_eq!(notifications[0].params, json!({"version": "1.2.0"}));
+        let contents = hooks.read("hook://deploys").unwrap();
+        assert_eq!(contents[0].text.as_deref(), Some(r#"{"version":"1.2.0"}"#));
+    }
+
+    #[test]
+    fn test_refuses_unsigned_and_out_of_scope_deliveries() {
+        let hooks = hooks();
+        let body = br#"{"uri": "file:///etc/passwd"}"#;
+        assert_eq!(
+            hooks.receive("docs", body, None).unwrap_err(),
+            HookError::BadSignature
+        );
+        let wrong = webhooks::sign("s2", body);
+        assert_eq!(
+            hooks.receive("docs", body, Some(&wrong)).unwrap_err(),
+            HookError::BadSignature
+        );
+        let signature = webhooks::sign("s1", body);
+        assert_eq!(
+            hooks
+                .receive("docs", body, Some(&signature))
+                .unwrap_err()
+                .status(),
+            400
+        );
+        assert_eq!(
+            hooks.receive("other", body, Some(&signature)).unwrap_err(),
+            HookError::NotFound
+        );
+        assert!(hooks.read("hook://other").is_none());
+    }
+}
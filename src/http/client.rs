@@ -0,0 +1,395 @@
+//! Outgoing requests, used for webhook delivery and `http_fetch`. `https`
+//! URLs are fetched over TLS, checked against the Mozilla root
+//! certificates.
+
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+/// An `http://` or `https://host[:port]/path` URL.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Url {
+    /// Whether the URL is `https`.
+    pub tls: bool,
+    pub host: String,
+    pub port: u16,
+    pub path: String,
+}
+
+impl Url {
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let (tls, rest) = match url.split_once("://") {
+            Some(("http", rest)) => (false, rest),
+            Some(("https", rest)) => (true, rest),
+            Some((scheme, _)) => {
+                return Err(format!(
+                    "Unsupported URL scheme '{}'; only http and https are supported",
+                    scheme
+                ))
+            }
+            None => return Err(format!("Invalid URL: {}", url)),
+        };
+        let (authority, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        let (host, port) = match authority.rsplit_once(':') {
+            Some((host, port)) => (
+                host,
+                port.parse()
+                    .map_err(|_| format!("Invalid port in URL: {}", url))?,
+            ),
+            None => (authority, if tls { 443 } else { 80 }),
+        };
+        if host.is_empty() {
+            return Err(format!("Invalid URL: {}", url));
+        }
+        Ok(Self {
+            tls,
+            host: host.to_string(),
+            port,
+            path: path.to_string(),
+        })
+    }
+
+    /// `host:port`, or only the host when the port is the scheme's default,
+    /// as sent in the `Host` header.
+    pub fn authority(&self) -> String {
+        match (self.tls, self.port) {
+            (false, 80) | (true, 443) => self.host.clone(),
+            _ => format!("{}:{}", self.host, self.port),
+        }
+    }
+}
+
+/// A connection to a URL's host, over TLS when the URL is `https`.
+trait Connection: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Connection for T {}
+
+fn tls_config() -> &'static Arc<ClientConfig> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    CONFIG.get_or_init(|| {
+        let roots = RootCertStore {
+            roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+        };
+        let provider = tokio_rustls::rustls::crypto::ring::default_provider();
+        Arc::new(
+            ClientConfig::builder_with_provider(Arc::new(provider))
+                .with_safe_default_protocol_versions()
+                .expect("the ring provider supports the default protocol versions")
+                .with_root_certificates(roots)
+                .with_no_client_auth(),
+        )
+    })
+}
+
+/// `stream`, connected to `url`'s host, with a TLS session on top when
+/// `url` is `https`.
+async fn secure(
+    stream: TcpStream,
+    url: &Url,
+    config: &Arc<ClientConfig>,
+) -> Result<Box<dyn Connection>, String> {
+    if !url.tls {
+        return Ok(Box::new(stream));
+    }
+    let name = ServerName::try_from(url.host.clone())
+        .map_err(|_| format!("Invalid TLS server name: {}", url.host))?;
+    let stream = TlsConnector::from(config.clone())
+        .connect(name, stream)
+        .await
+        .map_err(|e| format!("TLS handshake with {} failed: {}", url.host, e))?;
+    Ok(Box::new(stream))
+}
+
+/// Sends a POST with `body` and returns the response status code.
+pub async fn post(
+    url: &Url,
+    headers: &[(&str, String)],
+    body: &[u8],
+    timeout: Duration,
+) -> Result<u16, String> {
+    tokio::time::timeout(timeout, send_post(url, headers, body))
+        .await
+        .map_err(|_| format!("Request timed out after {}s", timeout.as_secs()))?
+}
+
+async fn send_post(url: &Url, headers: &[(&str, String)], body: &[u8]) -> Result<u16, String> {
+    let stream = TcpStream::connect((url.host.as_str(), url.port))
+        .await
+        .map_err(|e| e.to_string())?;
+    let mut stream = secure(stream, url, tls_config()).await?;
+
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        url.path,
+        url.authority(),
+        body.len()
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    stream.write_all(body).await.map_err(|e| e.to_string())?;
+
+    // Only the status line matters; read until it is complete.
+    let mut response = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !response.windows(2).any(|w| w == b"\r\n") {
+        let n = stream.read(&mut buf).await.map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        response.extend_from_slice(&buf[..n]);
+    }
+    let status_line = String::from_utf8_lossy(&response);
+    status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| "Malformed HTTP response".to_string())
+}
+
+/// A response read to the end of the connection.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fetched {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+/// Sends a GET over `stream`, which must already be connected to `url`'s
+/// host, and reads the response until the server closes the connection.
+/// For an `https` URL the TLS handshake happens on `stream` first. Fails
+/// once more than `max_bytes` arrive.
+pub async fn get(stream: TcpStream, url: &Url, max_bytes: usize) -> Result<Fetched, String> {
+    request(stream, "GET", url, &[], &[], max_bytes).await
+}
+
+/// Like [`get`], for any method, with extra `headers` and a `body`. The
+/// `Host` header is [`Url::authority`].
+pub async fn request(
+    stream: TcpStream,
+    method: &str,
+    url: &Url,
+    headers: &[(&str, String)],
+    body: &[u8],
+    max_bytes: usize,
+) -> Result<Fetched, String> {
+    let stream = secure(stream, url, tls_config()).await?;
+    exchange(stream, method, url, headers, body, max_bytes).await
+}
+
+async fn exchange(
+    mut stream: Box<dyn Connection>,
+    method: &str,
+    url: &Url,
+    headers: &[(&str, String)],
+    body: &[u8],
+    max_bytes: usize,
+) -> Result<Fetched, String> {
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nAccept: */*\r\nConnection: close\r\n",
+        method,
+        url.path,
+        url.authority()
+    );
+    if !body.is_empty() || method == "PUT" || method == "POST" {
+        request.push_str(&format!("Content-Length: {}\r\n", body.len()));
+    }
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    let mut request = request.into_bytes();
+    request.extend_from_slice(body);
+    stream
+        .write_all(&request)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut response = Vec::new();
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match stream.read(&mut buf).await {
+            Ok(n) => n,
+            // Many TLS servers close without a close_notify alert.
+            Err(e) if url.tls && e.kind() == std::io::ErrorKind::UnexpectedEof => 0,
+            Err(e) => return Err(e.to_string()),
+        };
+        if n == 0 {
+            break;
+        }
+        if response.len() + n > max_bytes {
+            return Err(format!("Response larger than {} bytes", max_bytes));
+        }
+        response.extend_from_slice(&buf[..n]);
+    }
+
+    let end = response
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| "Malformed HTTP response".to_string())?;
+    let head = String::from_utf8_lossy(&response[..end]).to_string();
+    let mut lines = head.lines();
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| "Malformed HTTP response".to_string())?;
+    let headers: Vec<(String, String)> = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+
+    let mut body = &response[end + 4..];
+    let chunked = headers.iter().any(|(name, value)| {
+        name.eq_ignore_ascii_case("Transfer-Encoding") && value.eq_ignore_ascii_case("chunked")
+    });
+    let body = if chunked {
+        super::server::read_chunked(&mut body)
+            .await
+            .map_err(|e| e.to_string())?
+    } else {
+        body.to_vec()
+    };
+    Ok(Fetched {
+        status,
+        headers,
+        body,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            Url::parse("http://hooks.internal:8080/mcp?x=1").unwrap(),
+            Url {
+                tls: false,
+                host: "hooks.internal".to_string(),
+                port: 8080,
+                path: "/mcp?x=1".to_string()
+            }
+        );
+        assert_eq!(Url::parse("http://localhost").unwrap().path, "/");
+        let https = Url::parse("https://example.com/x").unwrap();
+        assert!(https.tls);
+        assert_eq!(https.port, 443);
+        assert_eq!(https.authority(), "example.com");
+        assert_eq!(
+            Url::parse("http://example.com:443").unwrap().authority(),
+            "example.com:443"
+        );
+        assert!(Url::parse("ftp://example.com")
+            .unwrap_err()
+            .contains("only http and https"));
+        assert!(Url::parse("localhost:80").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_https_requests_check_the_certificate() {
+        use tokio_rustls::rustls::pki_types::PrivatePkcs8KeyDer;
+        use tokio_rustls::rustls::ServerConfig;
+
+        let certified = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        let provider = Arc::new(tokio_rustls::rustls::crypto::ring::default_provider());
+        let server_config = ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![certified.cert.der().clone()],
+                PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()).into(),
+            )
+            .unwrap();
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(server_config));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            loop {
+                let (stream, _) = listener.accept().await.unwrap();
+                let Ok(mut stream) = acceptor.accept(stream).await else {
+                    continue;
+                };
+                let mut request = vec![0u8; 4096];
+                let _ = stream.read(&mut request).await;
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nsecret")
+                    .await;
+                let _ = stream.shutdown().await;
+            }
+        });
+
+        let url = Url::parse(&format!("https://localhost:{}/", port)).unwrap();
+        let connect = || TcpStream::connect(("127.0.0.1", port));
+
+        let mut roots = RootCertStore::empty();
+        roots.add(certified.cert.der().clone()).unwrap();
+        let trusting = Arc::new(
+            ClientConfig::builder_with_provider(provider)
+                .with_safe_default_protocol_versions()
+                .unwrap()
+                .with_root_certificates(roots)
+                .with_no_client_auth(),
+        );
+        let stream = secure(connect().await.unwrap(), &url, &trusting)
+            .await
+            .unwrap();
+        let fetched = exchange(stream, "GET", &url, &[], &[], 1024).await.unwrap();
+        assert_eq!(fetched.status, 200);
+        assert_eq!(fetched.body, b"secret");
+
+        // The self-signed certificate is not among the public roots.
+        let err = get(connect().await.unwrap(), &url, 1024).await.unwrap_err();
+        assert!(
+            err.contains("TLS handshake with localhost failed"),
+            "{}",
+            err
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post_returns_status() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 4096];
+            let n = stream.read(&mut request).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 202 Accepted\r\nContent-Length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&request[..n]).to_string()
+        });
+
+        let url = Url::parse(&format!("http://127.0.0.1:{}/hook", port)).unwrap();
+        let status = post(
+            &url,
+            &[("X-Test", "1".to_string())],
+            b"{}",
+            Duration::from_secs(5),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(status, 202);
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /hook HTTP/1.1\r\n"));
+        assert!(request.contains("X-Test: 1\r\n"));
+    }
+}
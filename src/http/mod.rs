@@ -0,0 +1,8 @@
+//! Minimal HTTP/1.1 support. The server speaks plain HTTP, so it needs a
+//! terminating proxy in front of it for TLS; the client fetches `https`
+//! URLs itself.
+
+pub mod client;
+pub mod server;
+
+pub use client::{post, Url};
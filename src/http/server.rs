@@ -0,0 +1,405 @@
+//! Incoming requests, used by the HTTP transports.
+
+use std::io;
+use std::net::SocketAddr;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::security::{SecurityEvent, SecurityEventKind};
+
+pub const MAX_HEADER_BYTES: usize = 64 * 1024;
+pub const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Longest chunk-size or trailer line of a chunked body.
+const MAX_CHUNK_LINE_BYTES: u64 = 4096;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    pub method: String,
+    /// Path and query as sent, e.g. `/mcp?x=1`.
+    pub target: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    /// First header named `name`, compared case-insensitively.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or("")
+    }
+
+    /// Value of `key` in the query string.
+    pub fn query(&self, key: &str) -> Option<&str> {
+        self.target
+            .split_once('?')?
+            .1
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| v)
+    }
+
+    pub fn keep_alive(&self) -> bool {
+        !self
+            .header("Connection")
+            .is_some_and(|v| v.eq_ignore_ascii_case("close"))
+    }
+}
+
+/// Origins from `MCP_HTTP_ALLOWED_ORIGINS` (comma-separated).
+pub fn allowed_origins_from_env() -> Vec<String> {
+    std::env::var("MCP_HTTP_ALLOWED_ORIGINS")
+        .map(|v| v.split(',').map(|o| o.trim().to_string()).collect())
+        .unwrap_or_default()
+}
+
+/// Browsers always send `Origin`; only local pages and `allowed` origins may
+/// call the server, which blocks DNS rebinding attacks against a locally
+/// bound port. Requests without the header (non-browser clients) pass.
+pub fn origin_allowed(request: &Request, allowed: &[String]) -> bool {
+    let Some(origin) = request.header("Origin") else {
+        return true;
+    };
+    if allowed.iter().any(|o| o == origin) {
+        return true;
+    }
+    let host = origin
+        .split_once("://")
+        .map(|(_, rest)| rest)
+        .unwrap_or(origin);
+    let host = match host.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or(""),
+        None => host.split(':').next().unwrap_or(""),
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+/// The security event for a request refused by [`origin_allowed`].
+pub fn origin_denial(request: &Request, peer: SocketAddr) -> SecurityEvent {
+    SecurityEvent::new(SecurityEventKind::PolicyDenied, "Origin not allowed")
+        .rule("http_origin")
+        .source_ip(peer.ip())
+        .detail("origin", request.header("Origin").unwrap_or_default())
+        .detail("method", request.method.as_str())
+        .detail("path", request.path())
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Reads one request, or `None` when the peer closed the connection
+/// between requests. Bodies may be sized by `Content-Length` or chunked.
+pub async fn read_request<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Option<Request>> {
+    let mut head = Vec::new();
+    loop {
+        let before = head.len();
+        // One byte past the limit, so an overlong line is caught below
+        // without buffering the rest of it.
+        let limit = (MAX_HEADER_BYTES + 1 - head.len()) as u64;
+        let n = (&mut *reader)
+            .take(limit)
+            .read_until(b'\n', &mut head)
+            .await?;
+        if n == 0 {
+            if head.is_empty() {
+                return Ok(None);
+            }
+            return Err(invalid("Connection closed mid-request"));
+        }
+        if head.len() > MAX_HEADER_BYTES {
+            return Err(invalid("Request headers too large"));
+        }
+        let line = &head[before..];
+        if line == b"\r\n" || line == b"\n" {
+            // Tolerate blank lines before the request line.
+            if before == 0 {
+                head.clear();
+                continue;
+            }
+            break;
+        }
+    }
+
+    let head = String::from_utf8(head).map_err(|_| invalid("Request headers are not UTF-8"))?;
+    let mut lines = head.lines();
+    let mut request_line = lines.next().unwrap_or("").split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return Err(invalid("Malformed request line"));
+    };
+
+    let mut headers = Vec::new();
+    for line in lines.filter(|l| !l.is_empty()) {
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| invalid("Malformed header"))?;
+        headers.push((name.trim().to_string(), value.trim().to_string()));
+    }
+
+    let mut request = Request {
+        method: method.to_string(),
+        target: target.to_string(),
+        headers,
+        body: Vec::new(),
+    };
+
+    if request
+        .header("Transfer-Encoding")
+        .is_some_and(|v| v.eq_ignore_ascii_case("chunked"))
+    {
+        request.body = read_chunked(reader).await?;
+    } else if let Some(length) = request.header("Content-Length") {
+        let length: usize = length
+            .parse()
+            .map_err(|_| invalid("Invalid Content-Length"))?;
+        if length > MAX_BODY_BYTES {
+            return Err(invalid("Request body too large"));
+        }
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).await?;
+        request.body = body;
+    }
+    Ok(Some(request))
+}
+
+pub(super) async fn read_chunked<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    loop {
+        let size_line = read_chunk_line(reader).await?;
+        let size = usize::from_str_radix(size_line.trim().split(';').next().unwrap_or(""), 16)
+            .map_err(|_| invalid("Invalid chunk size"))?;
+        // The size is the client's, so the sum may overflow.
+        let end = body
+            .len()
+            .checked_add(size)
+            .filter(|&end| end <= MAX_BODY_BYTES)
+            .ok_or_else(|| invalid("Request body too large"))?;
+        if size == 0 {
+            // Skip trailers up to the terminating blank line.
+            loop {
+                let line = read_chunk_line(reader).await?;
+                if line.is_empty() || line.trim().is_empty() {
+                    return Ok(body);
+                }
+            }
+        }
+        let start = body.len();
+        body.resize(end, 0);
+        reader.read_exact(&mut body[start..]).await?;
+        read_chunk_line(reader).await?;
+    }
+}
+
+/// One line of a chunked body's framing, refused when longer than
+/// `MAX_CHUNK_LINE_BYTES`. Empty at the end of the stream.
+async fn read_chunk_line<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<String> {
+    let mut line = Vec::new();
+    (&mut *reader)
+        .take(MAX_CHUNK_LINE_BYTES)
+        .read_until(b'\n', &mut line)
+        .await?;
+    if line.len() as u64 == MAX_CHUNK_LINE_BYTES && !line.ends_with(b"\n") {
+        return Err(invalid("Chunk line too long"));
+    }
+    String::from_utf8(line).map_err(|_| invalid("Chunk line is not UTF-8"))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn json(status: u16, body: &impl serde::Serialize) -> Self {
+        Self {
+            body: serde_json::to_vec(body).unwrap(),
+            ..Self::new(status)
+        }
+        .header("Content-Type", "application/json")
+    }
+
+    pub fn text(status: u16, body: &str) -> Self {
+        Self {
+            body: body.as_bytes().to_vec(),
+            ..Self::new(status)
+        }
+        .header("Content-Type", "text/plain; charset=utf-8")
+    }
+
+    pub fn header(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.headers.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub async fn write_to<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut W,
+        keep_alive: bool,
+    ) -> io::Result<()> {
+        let mut head = status_line(self.status);
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        if !keep_alive {
+            head.push_str("Connection: close\r\n");
+        }
+        head.push_str("\r\n");
+        writer.write_all(head.as_bytes()).await?;
+        writer.write_all(&self.body).await?;
+        writer.flush().await
+    }
+}
+
+/// Writes the head of a `text/event-stream` response. The body is then
+/// written as events until the connection closes.
+pub async fn write_event_stream_head<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    headers: &[(&str, String)],
+) -> io::Result<()> {
+    let mut head = status_line(200);
+    head.push_str(
+        "Content-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n",
+    );
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    writer.write_all(head.as_bytes()).await?;
+    writer.flush().await
+}
+
+/// Writes one server-sent event.
+pub async fn write_event<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    event: Option<&str>,
+    data: &str,
+) -> io::Result<()> {
+    let mut frame = String::new();
+    if let Some(event) = event {
+        frame.push_str(&format!("event: {}\n", event));
+    }
+    for line in data.lines() {
+        frame.push_str(&format!("data: {}\n", line));
+    }
+    frame.push('\n');
+    writer.write_all(frame.as_bytes()).await?;
+    writer.flush().await
+}
+
+fn status_line(status: u16) -> String {
+    let reason = match status {
+        200 => "OK",
+        201 => "Created",
+        202 => "Accepted",
+        204 => "No Content",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        406 => "Not Acceptable",
+        413 => "Payload Too Large",
+        415 => "Unsupported Media Type",
+        422 => "Unprocessable Content",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        503 => "Service Unavailable",
+        _ => "",
+    };
+    format!("HTTP/1.1 {} {}\r\n", status, reason)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_request_with_content_length() {
+        let raw = b"POST /mcp?x=1 HTTP/1.1\r\nHost: a\r\ncontent-length: 2\r\n\r\n{}GET / HTTP/1.1\r\n\r\n";
+        let mut reader = &raw[..];
+
+        let request = read_request(&mut reader).await.unwrap().unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path(), "/mcp");
+        assert_eq!(request.query("x"), Some("1"));
+        assert_eq!(request.header("Content-Length"), Some("2"));
+        assert_eq!(request.body, b"{}");
+
+        let next = read_request(&mut reader).await.unwrap().unwrap();
+        assert_eq!(next.method, "GET");
+        assert!(read_request(&mut reader).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_read_chunked_request() {
+        let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\n5\r\npedia\r\n0\r\n\r\n";
+        let request = read_request(&mut &raw[..]).await.unwrap().unwrap();
+        assert_eq!(request.body, b"Wikipedia");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_malformed_requests() {
+        assert!(read_request(&mut &b"garbage\r\n\r\n"[..]).await.is_err());
+        let oversized = format!(
+            "POST / HTTP/1.1\r\nContent-Length: {}\r\n\r\n",
+            MAX_BODY_BYTES + 1
+        );
+        assert!(read_request(&mut oversized.as_bytes()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_rejects_oversized_chunks_and_lines() {
+        // A chunk size that would overflow the body length.
+        let raw = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nWiki\r\nffffffffffffffff\r\n";
+        let error = read_request(&mut &raw[..]).await.unwrap_err();
+        assert_eq!(error.to_string(), "Request body too large");
+
+        // A header line without an end is refused once over the limit,
+        // without reading the rest.
+        let endless = format!(
+            "GET / HTTP/1.1\r\nX-A: {}",
+            "a".repeat(MAX_HEADER_BYTES * 2)
+        );
+        let mut reader = endless.as_bytes();
+        let error = read_request(&mut reader).await.unwrap_err();
+        assert_eq!(error.to_string(), "Request headers too large");
+        assert!(reader.len() >= MAX_HEADER_BYTES - 32);
+
+        let long_size = format!(
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n{}\r\n",
+            "0".repeat(8192)
+        );
+        assert!(read_request(&mut long_size.as_bytes()).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_write_response() {
+        let mut out = Vec::new();
+        Response::text(404, "nope")
+            .header("X-A", "1")
+            .write_to(&mut out, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "HTTP/1.1 404 Not Found\r\nContent-Type: text/plain; charset=utf-8\r\nX-A: 1\r\nContent-Length: 4\r\nConnection: close\r\n\r\nnope"
+        );
+    }
+}
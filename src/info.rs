@@ -0,0 +1,44 @@
+//! `server/info`: what is deployed, for operators taking inventory of a
+//! fleet. The build details are recorded by `build.rs`.
+
+use serde::Serialize;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::protocol::SUPPORTED_PROTOCOL_VERSIONS;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildInfo {
+    pub name: &'static str,
+    pub version: &'static str,
+    /// Short hash of the commit built, or `unknown` outside a git checkout.
+    pub git_hash: &'static str,
+    /// When the binary was built, in RFC 3339, or `SOURCE_DATE_EPOCH` when
+    /// set.
+    pub built_at: String,
+    /// Cargo features enabled in the build.
+    pub features: Vec<&'static str>,
+    pub rustc: &'static str,
+    pub target: &'static str,
+    pub protocol_versions: &'static [&'static str],
+}
+
+impl BuildInfo {
+    /// The running binary's build.
+    pub fn current() -> Self {
+        let epoch = env!("MCP_BUILD_EPOCH").parse().unwrap_or(0);
+        Self {
+            name: env!("CARGO_PKG_NAME"),
+            version: env!("CARGO_PKG_VERSION"),
+            git_hash: env!("MCP_BUILD_GIT_HASH"),
+            built_at: crate::security::rfc3339(UNIX_EPOCH + Duration::from_secs(epoch)),
+            features: env!("MCP_BUILD_FEATURES")
+                .split(',')
+                .filter(|f| !f.is_empty())
+                .collect(),
+            rustc: env!("MCP_BUILD_RUSTC"),
+            target: env!("MCP_BUILD_TARGET"),
+            protocol_versions: SUPPORTED_PROTOCOL_VERSIONS,
+        }
+    }
+}
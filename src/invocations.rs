@@ -0,0 +1,251 @@
+//! An append-only record of every tool call, for showing exactly what a
+//! model did through the server: when, in which session, which tool, with
+//! what arguments, and how it ended. Written through its own [`AuditLog`],
+//! so the records can go to a file of their own, rotated as it grows.
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use crate::audit::{self, AuditLog};
+use crate::security::rfc3339;
+
+/// How much of a call's arguments a record keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArgumentsMode {
+    /// The arguments as the client sent them.
+    Full,
+    /// The arguments with secrets masked: properties the tool's schema
+    /// marks `writeOnly` or `"format": "password"`, and properties named
+    /// like credentials, such as `password` or `api_key`.
+    Redacted,
+    /// No arguments at all.
+    Omitted,
+}
+
+impl ArgumentsMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "full" => Some(Self::Full),
+            "redacted" => Some(Self::Redacted),
+            "omitted" => Some(Self::Omitted),
+            _ => None,
+        }
+    }
+}
+
+/// How a call ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Status {
+    Success,
+    /// The tool ran, or was stopped before it could, and failed.
+    Error,
+    /// The arguments were rejected before or by the tool.
+    InvalidArguments,
+}
+
+/// One call, as the server saw it.
+pub struct Invocation<'a> {
+    pub started_at: SystemTime,
+    pub session: Option<&'a str>,
+    pub request_id: Option<&'a Value>,
+    pub correlation_id: Option<&'a str>,
+    /// The tool called, after resolving versions and near-miss names.
+    pub tool: &'a str,
+    /// The name the client asked for, when it differs.
+    pub requested: Option<&'a str>,
+    pub input_schema: &'a Value,
+    pub arguments: &'a Value,
+    pub status: Status,
+    pub error: Option<&'a str>,
+    pub duration_ms: u64,
+}
+
+/// Where invocation records go. Clones share the same sinks; a log with no
+/// sinks records nothing.
+#[derive(Clone)]
+pub struct InvocationLog {
+    log: Arc<AuditLog>,
+    enabled: bool,
+    arguments: ArgumentsMode,
+}
+
+impl InvocationLog {
+    pub fn new(log: AuditLog, arguments: ArgumentsMode) -> Self {
+        Self {
+            log: Arc::new(log),
+            enabled: true,
+            arguments,
+        }
+    }
+
+    /// Log for the sinks in `MCP_INVOCATION_SINKS`, keeping arguments as
+    /// `MCP_INVOCATION_ARGUMENTS` says: `full`, `redacted` (the default) or
+    /// `omitted`. Off when no sinks are set.
+    pub fn from_env() -> Self {
+        let specs =
+            audit::parse_sink_list(&std::env::var("MCP_INVOCATION_SINKS").unwrap_or_default());
+        if specs.is_empty() {
+            return Self::default();
+        }
+        let arguments = match std::env::var("MCP_INVOCATION_ARGUMENTS") {
+            Ok(value) => ArgumentsMode::parse(value.trim()).unwrap_or_else(|| {
+                tracing::warn!("Invalid MCP_INVOCATION_ARGUMENTS '{}'", value);
+                ArgumentsMode::Redacted
+            }),
+            Err(_) => ArgumentsMode::Redacted,
+        };
+        Self::new(AuditLog::from_specs(&specs), arguments)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn record(&self, invocation: Invocation<'_>) {
+        if !self.enabled {
+            return;
+        }
+        let mut record = json!({
+            "event": "tool_called",
+            "source": "tools",
+            "timestamp": rfc3339(invocation.started_at),
+            "session": invocation.session,
+            "requestId": invocation.request_id,
+            "correlationId": invocation.correlation_id,
+            "tool": invocation.tool,
+            "status": invocation.status,
+            "durationMs": invocation.duration_ms,
+        });
+        if let Some(requested) = invocation.requested {
+            record["requestedTool"] = requested.into();
+        }
+        match self.arguments {
+            ArgumentsMode::Full => record["arguments"] = invocation.arguments.clone(),
+            ArgumentsMode::Redacted => {
+                record["arguments"] = redact(invocation.input_schema, invocation.arguments)
+            }
+            ArgumentsMode::Omitted => {}
+        }
+        if let Some(error) = invocation.error {
+            record["error"] = error.into();
+        }
+        self.log.record(record);
+    }
+
+    /// Blocks until the records written so far reach their sinks.
+    pub fn flush(&self) {
+        self.log.flush();
+    }
+}
+
+impl Default for InvocationLog {
+    fn default() -> Self {
+        Self {
+            log: Arc::new(AuditLog::new(1)),
+            enabled: false,
+            arguments: ArgumentsMode::Redacted,
+        }
+    }
+}
+
+/// Property names taken for credentials, compared without case, `_` or
+/// `-`.
+const SECRET_NAMES: &[&str] = &[
+    "password",
+    "passwd",
+    "secret",
+    "clientsecret",
+    "token",
+    "accesstoken",
+    "refreshtoken",
+    "apikey",
+    "authorization",
+    "privatekey",
+];
+
+fn is_secret(name: &str, schema: &Value) -> bool {
+    let name: String = name
+        .chars()
+        .filter(|c| *c != '_' && *c != '-')
+        .flat_map(char::to_lowercase)
+        .collect();
+    schema["writeOnly"] == true
+        || schema["format"] == "password"
+        || SECRET_NAMES.contains(&name.as_str())
+}
+
+/// `value` with the secrets among its properties, at any depth, replaced
+/// by `"[REDACTED]"`.
+pub fn redact(schema: &Value, value: &Value) -> Value {
+    match value {
+        Value::Object(object) => Value::Object(
+            object
+                .iter()
+                .map(|(name, value)| {
+                    let schema = &schema["properties"][name];
+                    let value = if is_secret(name, schema) {
+                        "[REDACTED]".into()
+                    } else {
+                        redact(schema, value)
+                    };
+                    (name.clone(), value)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| redact(&schema["items"], item))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redaction_masks_secrets_at_any_depth() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "query": {"type": "string"},
+                "pin": {"type": "string", "writeOnly": true},
+                "accounts": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {"passphrase": {"type": "string", "format": "password"}}
+                    }
+                }
+            }
+        });
+        let arguments = json!({
+            "query": "balance",
+            "pin": "1234",
+            "API-Key": "sk-1",
+            "accounts": [{"id": 7, "passphrase": "hunter2"}],
+            "headers": {"Authorization": "Bearer x", "Accept": "text/plain"}
+        });
+        assert_eq!(
+            redact(&schema, &arguments),
+            json!({
+                "query": "balance",
+                "pin": "[REDACTED]",
+                "API-Key": "[REDACTED]",
+                "accounts": [{"id": 7, "passphrase": "[REDACTED]"}],
+                "headers": {"Authorization": "[REDACTED]", "Accept": "text/plain"}
+            })
+        );
+        assert_eq!(
+            ArgumentsMode::parse("omitted"),
+            Some(ArgumentsMode::Omitted)
+        );
+        assert!(!InvocationLog::default().is_enabled());
+    }
+}
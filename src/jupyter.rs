@@ -0,0 +1,797 @@
+//! Jupyter kernels for the `kernel_*` tools, driven over the
+//! [messaging protocol](https://jupyter-client.readthedocs.io/en/latest/messaging.html).
+//! Each kernel gets a scratch directory and a connection file naming Unix
+//! sockets in it, so it needs no network and runs under the same
+//! [sandbox](crate::sandbox) as `python_run`. Outputs arrive on the IOPub
+//! channel and are gathered per cell, so a cell still running when
+//! `kernel_execute` stops waiting can be fetched later.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::path::Path;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::process::Child;
+use tokio::sync::Notify;
+use zeromq::{DealerSendHalf, DealerSocket, Socket, SocketRecv, SocketSend, SubSocket, ZmqMessage};
+
+use crate::crypto;
+use crate::gc::ScratchDir;
+use crate::sandbox::Sandbox;
+use crate::tools::{content, python};
+
+pub const DEFAULT_MAX_KERNELS: usize = 4;
+
+/// How long `kernel_execute` waits for a cell by default.
+pub const DEFAULT_WAIT_SECS: u64 = 30;
+
+/// How long a kernel has to connect and answer its first
+/// `kernel_info_request`.
+pub const STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Cells kept per kernel for `kernel_fetch`; the oldest finished ones go
+/// first.
+const MAX_CELLS: usize = 64;
+
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+
+const PROTOCOL_VERSION: &str = "5.3";
+
+/// The fields of a Jupyter connection file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionInfo {
+    pub transport: String,
+    /// An address for `tcp`; for `ipc`, the path the socket names start
+    /// with.
+    pub ip: String,
+    pub shell_port: u16,
+    pub iopub_port: u16,
+    pub stdin_port: u16,
+    pub control_port: u16,
+    pub hb_port: u16,
+    pub key: String,
+    pub signature_scheme: String,
+}
+
+impl ConnectionInfo {
+    /// Unix sockets named `<prefix>-<port>`, as the `ipc` transport names
+    /// them, signed with a fresh key.
+    pub fn ipc(prefix: &Path) -> Self {
+        Self {
+            transport: "ipc".to_string(),
+            ip: prefix.display().to_string(),
+            shell_port: 1,
+            iopub_port: 2,
+            stdin_port: 3,
+            control_port: 4,
+            hb_port: 5,
+            key: uuid::Uuid::new_v4().simple().to_string(),
+            signature_scheme: "hmac-sha256".to_string(),
+        }
+    }
+
+    fn endpoint(&self, port: u16) -> String {
+        match self.transport.as_str() {
+            "ipc" => format!("ipc://{}-{}", self.ip, port),
+            transport => format!("{}://{}:{}", transport, self.ip, port),
+        }
+    }
+}
+
+/// One message, less the metadata, which no reply here needs.
+#[derive(Debug, Clone)]
+struct Message {
+    header: Value,
+    parent_header: Value,
+    content: Value,
+}
+
+impl Message {
+    fn new(msg_type: &str, session: &str, content: Value) -> Self {
+        Self {
+            header: json!({
+                "msg_id": uuid::Uuid::new_v4().to_string(),
+                "session": session,
+                "username": "mcp-server",
+                "date": crate::security::rfc3339(SystemTime::now()),
+                "msg_type": msg_type,
+                "version": PROTOCOL_VERSION,
+            }),
+            parent_header: json!({}),
+            content,
+        }
+    }
+
+    fn id(&self) -> &str {
+        self.header["msg_id"].as_str().unwrap_or_default()
+    }
+
+    fn msg_type(&self) -> &str {
+        self.header["msg_type"].as_str().unwrap_or_default()
+    }
+
+    fn parent_id(&self) -> &str {
+        self.parent_header["msg_id"].as_str().unwrap_or_default()
+    }
+
+    /// The delimiter, the signature, then the header, parent header,
+    /// metadata and content. A `ROUTER` adds the routing identities.
+    fn encode(&self, key: &[u8]) -> ZmqMessage {
+        let parts = [
+            self.header.to_string(),
+            self.parent_header.to_string(),
+            "{}".to_string(),
+            self.content.to_string(),
+        ];
+        let mut message = ZmqMessage::from(DELIMITER.to_vec());
+        message.push_back(sign(key, &parts).into_bytes().into());
+        for part in parts {
+            message.push_back(part.into_bytes().into());
+        }
+        message
+    }
+
+    fn decode(key: &[u8], message: ZmqMessage) -> Result<Self, String> {
+        let frames = message.into_vec();
+        let start = frames
+            .iter()
+            .position(|frame| frame.as_ref() == DELIMITER)
+            .ok_or("no delimiter")?;
+        let frames = &frames[start + 1..];
+        if frames.len() < 5 {
+            return Err("too few frames".to_string());
+        }
+        let parts: Vec<&[u8]> = frames[1..5].iter().map(|frame| frame.as_ref()).collect();
+        if !crypto::constant_time_eq(&frames[0], sign(key, &parts).as_bytes()) {
+            return Err("bad signature".to_string());
+        }
+        let parse = |bytes: &[u8]| serde_json::from_slice(bytes).map_err(|e| e.to_string());
+        Ok(Self {
+            header: parse(parts[0])?,
+            parent_header: parse(parts[1])?,
+            content: parse(parts[3])?,
+        })
+    }
+}
+
+/// The hex HMAC-SHA256 of the parts; empty when there is no key, as the
+/// protocol asks.
+fn sign(key: &[u8], parts: &[impl AsRef<[u8]>]) -> String {
+    if key.is_empty() {
+        return String::new();
+    }
+    let message: Vec<u8> = parts
+        .iter()
+        .flat_map(|part| part.as_ref().to_vec())
+        .collect();
+    crypto::hex(&crypto::hmac_sha256(key, &message))
+}
+
+/// What one request has produced so far.
+#[derive(Debug, Clone, Default)]
+pub struct Cell {
+    /// Content blocks: text for streams, errors and plain results, images
+    /// for PNG and JPEG data.
+    pub outputs: Vec<Value>,
+    pub execution_count: Option<u64>,
+    pub error: bool,
+    /// Set once the kernel has replied and gone idle after the request.
+    pub done: bool,
+    /// The stream the last output came from, so chunks of it are joined.
+    stream: Option<String>,
+    // The reply and the idle status come on different channels, in either
+    // order.
+    replied: bool,
+    idle: bool,
+}
+
+impl Cell {
+    fn record(&mut self, message: &Message) {
+        let content = &message.content;
+        match message.msg_type() {
+            "stream" => {
+                let name = content["name"].as_str().unwrap_or("stdout");
+                let text = content["text"].as_str().unwrap_or_default();
+                match self.outputs.last_mut() {
+                    Some(block) if self.stream.as_deref() == Some(name) => {
+                        let joined =
+                            format!("{}{}", block["text"].as_str().unwrap_or_default(), text);
+                        block["text"] = joined.into();
+                    }
+                    _ if name == "stderr" => {
+                        self.outputs
+                            .push(content::text(format!("stderr:\n{}", text)));
+                    }
+                    _ => self.outputs.push(content::text(text)),
+                }
+                self.stream = Some(name.to_string());
+                return;
+            }
+            "execute_result" | "display_data" => self.outputs.extend(data_block(&content["data"])),
+            "error" => {
+                self.error = true;
+                let traceback: Vec<&str> = content["traceback"]
+                    .as_array()
+                    .map(|lines| lines.iter().filter_map(Value::as_str).collect())
+                    .unwrap_or_default();
+                let text = match traceback.is_empty() {
+                    true => format!(
+                        "{}: {}",
+                        content["ename"].as_str().unwrap_or("Error"),
+                        content["evalue"].as_str().unwrap_or_default()
+                    ),
+                    false => strip_ansi(&traceback.join("\n")),
+                };
+                self.outputs.push(content::text(text));
+            }
+            "clear_output" => self.outputs.clear(),
+            "status" => {
+                self.idle |= content["execution_state"] == "idle";
+                self.done = self.idle && self.replied;
+                return;
+            }
+            reply if reply.ends_with("_reply") => {
+                self.execution_count = content["execution_count"].as_u64();
+                self.error |= content["status"] == "error";
+                self.replied = true;
+                self.done = self.idle;
+                return;
+            }
+            _ => return,
+        }
+        self.stream = None;
+    }
+}
+
+/// A display's richest form this server can send: an image, else its plain
+/// text.
+fn data_block(data: &Value) -> Option<Value> {
+    for mime_type in ["image/png", "image/jpeg"] {
+        if let Some(image) = data[mime_type].as_str() {
+            let image: String = image.chars().filter(|c| !c.is_whitespace()).collect();
+            return Some(json!({"type": "image", "data": image, "mimeType": mime_type}));
+        }
+    }
+    data["text/plain"].as_str().map(content::text)
+}
+
+/// `text` without the terminal colour codes IPython puts in tracebacks.
+fn strip_ansi(text: &str) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            stripped.push(c);
+        }
+    }
+    stripped
+}
+
+type Cells = Arc<Mutex<Vec<(String, Cell)>>>;
+
+/// A running kernel and the cells sent to it.
+pub struct Kernel {
+    key: Vec<u8>,
+    session: String,
+    shell: tokio::sync::Mutex<DealerSendHalf>,
+    control: tokio::sync::Mutex<DealerSocket>,
+    cells: Cells,
+    changed: Arc<Notify>,
+    readers: Vec<tokio::task::JoinHandle<()>>,
+    /// `None` for a kernel this server did not start.
+    child: Mutex<Option<Child>>,
+    // Held so the sockets and connection file outlive the kernel.
+    _dir: Option<ScratchDir>,
+    started_at: Instant,
+}
+
+impl Kernel {
+    /// Connects to the kernel `info` describes and waits for it to answer.
+    /// `child` is the kernel's process, if this server started it.
+    pub async fn connect(
+        info: &ConnectionInfo,
+        child: Option<Child>,
+        dir: Option<ScratchDir>,
+    ) -> Result<Self, String> {
+        let sockets = async {
+            let mut shell = DealerSocket::new();
+            shell.connect(&info.endpoint(info.shell_port)).await?;
+            let mut control = DealerSocket::new();
+            control.connect(&info.endpoint(info.control_port)).await?;
+            let mut iopub = SubSocket::new();
+            iopub.connect(&info.endpoint(info.iopub_port)).await?;
+            iopub.subscribe("").await?;
+            Ok::<_, zeromq::ZmqError>((shell, control, iopub))
+        };
+        let (shell, control, iopub) = tokio::time::timeout(STARTUP_TIMEOUT, sockets)
+            .await
+            .map_err(|_| "The kernel did not open its sockets in time".to_string())?
+            .map_err(|e| format!("Failed to connect to the kernel: {}", e))?;
+        let (shell, shell_replies) = shell.split();
+
+        let key = info.key.as_bytes().to_vec();
+        let cells = Cells::default();
+        let changed = Arc::new(Notify::new());
+        let readers = vec![
+            tokio::spawn(read(iopub, key.clone(), cells.clone(), changed.clone())),
+            tokio::spawn(read(
+                shell_replies,
+                key.clone(),
+                cells.clone(),
+                changed.clone(),
+            )),
+        ];
+        let kernel = Self {
+            key,
+            session: uuid::Uuid::new_v4().to_string(),
+            shell: tokio::sync::Mutex::new(shell),
+            control: tokio::sync::Mutex::new(control),
+            cells,
+            changed,
+            readers,
+            child: Mutex::new(child),
+            _dir: dir,
+            started_at: Instant::now(),
+        };
+        kernel.handshake().await?;
+        Ok(kernel)
+    }
+
+    /// Asks for `kernel_info` until the kernel goes idle after one request,
+    /// which also shows that IOPub is subscribed: a kernel publishes to
+    /// nobody until the subscription reaches it.
+    async fn handshake(&self) -> Result<(), String> {
+        let deadline = Instant::now() + STARTUP_TIMEOUT;
+        while Instant::now() < deadline {
+            let id = self.send("kernel_info_request", json!({})).await?;
+            let answered = self
+                .wait(&id, Duration::from_secs(1))
+                .await
+                .is_some_and(|cell| cell.done);
+            self.cells.lock().unwrap().retain(|(cell, _)| *cell != id);
+            if answered {
+                return Ok(());
+            }
+            if let Some(status) = self.exit_status() {
+                return Err(format!("The kernel exited while starting ({})", status));
+            }
+        }
+        Err(format!(
+            "The kernel did not answer within {}s",
+            STARTUP_TIMEOUT.as_secs()
+        ))
+    }
+
+    /// Sends a request on the shell channel and tracks it as a cell.
+    async fn send(&self, msg_type: &str, content: Value) -> Result<String, String> {
+        let message = Message::new(msg_type, &self.session, content);
+        let id = message.id().to_string();
+        {
+            let mut cells = self.cells.lock().unwrap();
+            while cells.len() >= MAX_CELLS {
+                match cells.iter().position(|(_, cell)| cell.done) {
+                    Some(oldest) => cells.remove(oldest),
+                    None => cells.remove(0),
+                };
+            }
+            cells.push((id.clone(), Cell::default()));
+        }
+        self.shell
+            .lock()
+            .await
+            .send(message.encode(&self.key))
+            .await
+            .map_err(|e| format!("Failed to reach the kernel: {}", e))?;
+        Ok(id)
+    }
+
+    /// Runs `code` as a cell and waits up to `wait` for it to finish.
+    pub async fn execute(&self, code: &str, wait: Duration) -> Result<(String, Cell), String> {
+        let id = self
+            .send(
+                "execute_request",
+                json!({
+                    "code": code,
+                    "silent": false,
+                    "store_history": true,
+                    "user_expressions": {},
+                    "allow_stdin": false,
+                    "stop_on_error": true,
+                }),
+            )
+            .await?;
+        let cell = self.wait(&id, wait).await.unwrap_or_default();
+        Ok((id, cell))
+    }
+
+    /// The cell `id` once it has finished, or as it stands after `wait`, or
+    /// `None` if there is no such cell.
+    pub async fn wait(&self, id: &str, wait: Duration) -> Option<Cell> {
+        let deadline = tokio::time::Instant::now() + wait;
+        loop {
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+            let cell = self.cell(id)?;
+            if cell.done || self.exit_status().is_some() {
+                return Some(cell);
+            }
+            // Woken at least once a second to notice the kernel exiting.
+            let tick = deadline.min(tokio::time::Instant::now() + Duration::from_secs(1));
+            if tokio::time::timeout_at(tick, changed).await.is_err()
+                && tokio::time::Instant::now() >= deadline
+            {
+                return self.cell(id);
+            }
+        }
+    }
+
+    fn cell(&self, id: &str) -> Option<Cell> {
+        let cells = self.cells.lock().unwrap();
+        cells
+            .iter()
+            .find(|(cell, _)| cell == id)
+            .map(|(_, cell)| cell.clone())
+    }
+
+    /// How the kernel's process ended, once it has.
+    pub fn exit_status(&self) -> Option<String> {
+        let mut child = self.child.lock().unwrap();
+        let status = child.as_mut()?.try_wait().ok()??;
+        Some(status.to_string())
+    }
+
+    /// Asks the kernel to shut down on the control channel, giving it a
+    /// moment to do so before it is killed.
+    pub async fn shutdown(&self) {
+        let message = Message::new("shutdown_request", &self.session, json!({"restart": false}));
+        let sent = self
+            .control
+            .lock()
+            .await
+            .send(message.encode(&self.key))
+            .await;
+        if sent.is_ok() && self.child.lock().unwrap().is_some() {
+            let deadline = Instant::now() + Duration::from_secs(2);
+            while self.exit_status().is_none() && Instant::now() < deadline {
+                tokio::time::sleep(Duration::from_millis(50)).await;
+            }
+        }
+    }
+
+    pub fn describe(&self, id: &str) -> Value {
+        let cells = self.cells.lock().unwrap();
+        json!({
+            "kernel": id,
+            "ageSecs": self.started_at.elapsed().as_secs(),
+            "cells": cells.len(),
+            "running": cells.iter().filter(|(_, cell)| !cell.done).count(),
+            "exited": self.exit_status(),
+        })
+    }
+}
+
+impl Drop for Kernel {
+    fn drop(&mut self) {
+        for reader in &self.readers {
+            reader.abort();
+        }
+    }
+}
+
+/// Files each message `socket` receives under the cell it answers.
+async fn read(mut socket: impl SocketRecv, key: Vec<u8>, cells: Cells, changed: Arc<Notify>) {
+    while let Ok(message) = socket.recv().await {
+        let message = match Message::decode(&key, message) {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::warn!("Dropped a kernel message: {}", e);
+                continue;
+            }
+        };
+        let mut cells = cells.lock().unwrap();
+        if let Some((_, cell)) = cells.iter_mut().find(|(id, _)| id == message.parent_id()) {
+            cell.record(&message);
+            changed.notify_waiters();
+        }
+    }
+}
+
+/// Names a kernel: the id of the client session that started it, empty
+/// outside any client session, and the kernel's own id.
+type Key = (String, String);
+
+/// The kernels each client session has started. A client session can only
+/// reach its own.
+pub struct KernelManager {
+    kernels: Mutex<HashMap<Key, Arc<Kernel>>>,
+    /// Live kernels allowed per client session.
+    max_kernels: usize,
+    sandbox: Sandbox,
+    /// The kernel's command line, with `{connection_file}` where the
+    /// connection file's path goes.
+    command: Vec<String>,
+}
+
+impl KernelManager {
+    /// Kernels started with `python -m ipykernel_launcher`, using the
+    /// interpreter `python_run` uses, under `sandbox`.
+    pub fn new(sandbox: Sandbox) -> Self {
+        Self {
+            kernels: Mutex::new(HashMap::new()),
+            max_kernels: DEFAULT_MAX_KERNELS,
+            sandbox,
+            command: vec![
+                python::interpreter().display().to_string(),
+                "-m".to_string(),
+                "ipykernel_launcher".to_string(),
+                "-f".to_string(),
+                "{connection_file}".to_string(),
+            ],
+        }
+    }
+
+    pub fn with_max_kernels(mut self, max_kernels: usize) -> Self {
+        self.max_kernels = max_kernels;
+        self
+    }
+
+    /// Manager using the `MCP_SANDBOX` backend, the command line in
+    /// `MCP_JUPYTER_KERNEL` (split on whitespace) and the per-session limit
+    /// in `MCP_JUPYTER_MAX_KERNELS`.
+    pub fn from_env() -> Self {
+        let mut manager = Self::new(Sandbox::from_env());
+        if let Ok(command) = std::env::var("MCP_JUPYTER_KERNEL") {
+            let words: Vec<String> = command.split_whitespace().map(str::to_string).collect();
+            match words.iter().any(|word| word.contains("{connection_file}")) {
+                true => manager.command = words,
+                false => tracing::warn!("Invalid MCP_JUPYTER_KERNEL '{}'", command),
+            }
+        }
+        if let Ok(value) = std::env::var("MCP_JUPYTER_MAX_KERNELS") {
+            match value.parse() {
+                Ok(max_kernels) => manager.max_kernels = max_kernels,
+                Err(_) => tracing::warn!("Invalid MCP_JUPYTER_MAX_KERNELS '{}'", value),
+            }
+        }
+        manager
+    }
+
+    /// Starts a kernel for `owner` and returns its id.
+    pub async fn start(&self, owner: &str) -> Result<String, String> {
+        self.check_room(owner)?;
+        let dir = ScratchDir::create("mcp-kernel-").map_err(|e| e.to_string())?;
+        let path = dir.path();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o700))
+                .map_err(|e| e.to_string())?;
+        }
+        self.sandbox.grant(path).map_err(|e| e.to_string())?;
+        let info = ConnectionInfo::ipc(&path.join("kernel"));
+        let file = path.join("connection.json");
+        std::fs::write(&file, serde_json::to_vec(&info).unwrap()).map_err(|e| e.to_string())?;
+        self.sandbox.grant(&file).map_err(|e| e.to_string())?;
+
+        let file = file.display().to_string();
+        let mut command = self.sandbox.command(&self.command[0], &[path]);
+        command
+            .args(
+                self.command[1..]
+                    .iter()
+                    .map(|arg| arg.replace("{connection_file}", &file)),
+            )
+            .env("HOME", path);
+        let mut child = crate::script::spawn(&mut command, Stdio::inherit())?;
+        // Kernels send their output on IOPub; anything else is dropped.
+        let mut stdout = child.stdout.take().expect("stdout is piped");
+        tokio::spawn(async move { tokio::io::copy(&mut stdout, &mut tokio::io::sink()).await });
+
+        let kernel = Kernel::connect(&info, Some(child), Some(dir)).await?;
+        self.adopt(owner, kernel)
+    }
+
+    fn check_room(&self, owner: &str) -> Result<(), String> {
+        let kernels = self.kernels.lock().unwrap();
+        if kernels.keys().filter(|(o, _)| o == owner).count() >= self.max_kernels {
+            return Err(format!(
+                "At most {} kernels can run at once; shut one down first",
+                self.max_kernels
+            ));
+        }
+        Ok(())
+    }
+
+    /// Registers a connected kernel as one of `owner`'s and returns its id.
+    pub fn adopt(&self, owner: &str, kernel: Kernel) -> Result<String, String> {
+        // Checked again, for kernels started while this one was.
+        self.check_room(owner)?;
+        let id = uuid::Uuid::new_v4().simple().to_string()[..12].to_string();
+        self.kernels
+            .lock()
+            .unwrap()
+            .insert((owner.to_string(), id.clone()), Arc::new(kernel));
+        Ok(id)
+    }
+
+    pub fn get(&self, owner: &str, id: &str) -> Result<Arc<Kernel>, String> {
+        self.kernels
+            .lock()
+            .unwrap()
+            .get(&(owner.to_string(), id.to_string()))
+            .cloned()
+            .ok_or_else(|| format!("Kernel '{}' not found", id))
+    }
+
+    /// Shuts down `owner`'s kernel `id`. Returns false if it did not exist.
+    pub async fn shutdown(&self, owner: &str, id: &str) -> bool {
+        let key = (owner.to_string(), id.to_string());
+        let Some(kernel) = self.kernels.lock().unwrap().remove(&key) else {
+            return false;
+        };
+        kernel.shutdown().await;
+        true
+    }
+
+    /// Describes `owner`'s kernels.
+    pub fn list(&self, owner: &str) -> Vec<Value> {
+        let kernels = self.kernels.lock().unwrap();
+        let mut entries: Vec<Value> = kernels
+            .iter()
+            .filter(|((o, _), _)| o == owner)
+            .map(|((_, id), kernel)| kernel.describe(id))
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry["ageSecs"].as_u64()));
+        entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zeromq::{PubSocket, RouterSocket};
+
+    /// Answers like a kernel: every request gets busy and idle on IOPub
+    /// around its outputs, and a reply on the shell channel.
+    async fn fake_kernel(info: ConnectionInfo) {
+        let key = info.key.as_bytes().to_vec();
+        let mut shell = RouterSocket::new();
+        shell.bind(&info.endpoint(info.shell_port)).await.unwrap();
+        let mut control = RouterSocket::new();
+        control
+            .bind(&info.endpoint(info.control_port))
+            .await
+            .unwrap();
+        let mut iopub = PubSocket::new();
+        iopub.bind(&info.endpoint(info.iopub_port)).await.unwrap();
+        tokio::spawn(async move {
+            let _control = control;
+            let mut count = 0;
+            while let Ok(frames) = shell.recv().await {
+                let identity = frames.get(0).unwrap().clone();
+                let request = Message::decode(&key, frames).unwrap();
+                let publish = |msg_type: &str, content: Value| {
+                    let mut message = Message::new(msg_type, "kernel", content);
+                    message.parent_header = request.header.clone();
+                    message.encode(&key)
+                };
+                iopub
+                    .send(publish("status", json!({"execution_state": "busy"})))
+                    .await
+                    .unwrap();
+                let reply_type = match request.msg_type() {
+                    "execute_request" => {
+                        count += 1;
+                        let code = request.content["code"].as_str().unwrap().to_string();
+                        if code == "sleep" {
+                            // Never finishes.
+                            continue;
+                        }
+                        for chunk in ["hel", "lo\n"] {
+                            let stream = json!({"name": "stdout", "text": chunk});
+                            iopub.send(publish("stream", stream)).await.unwrap();
+                        }
+                        let data =
+                            json!({"data": {"image/png": "iVBO\nRw==", "text/plain": "<Figure>"}});
+                        iopub.send(publish("display_data", data)).await.unwrap();
+                        let result =
+                            json!({"data": {"text/plain": code}, "execution_count": count});
+                        iopub.send(publish("execute_result", result)).await.unwrap();
+                        "execute_reply"
+                    }
+                    _ => "kernel_info_reply",
+                };
+                iopub
+                    .send(publish("status", json!({"execution_state": "idle"})))
+                    .await
+                    .unwrap();
+                let mut reply = publish(
+                    reply_type,
+                    json!({"status": "ok", "execution_count": count}),
+                );
+                reply.push_front(identity);
+                shell.send(reply).await.unwrap();
+            }
+        });
+    }
+
+    #[tokio::test]
+    async fn test_cells_gather_their_outputs() {
+        let dir = ScratchDir::create("mcp-kernel-").unwrap();
+        let info = ConnectionInfo::ipc(&dir.path().join("kernel"));
+        fake_kernel(info.clone()).await;
+        let kernel = Kernel::connect(&info, None, Some(dir)).await.unwrap();
+
+        let (_, cell) = kernel
+            .execute("1 + 1", Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(cell.done && !cell.error);
+        assert_eq!(cell.execution_count, Some(1));
+        assert_eq!(
+            cell.outputs,
+            vec![
+                content::text("hello\n"),
+                json!({"type": "image", "data": "iVBORw==", "mimeType": "image/png"}),
+                content::text("1 + 1"),
+            ]
+        );
+
+        let (id, cell) = kernel
+            .execute("sleep", Duration::from_millis(100))
+            .await
+            .unwrap();
+        assert!(!cell.done);
+        assert!(!kernel.wait(&id, Duration::ZERO).await.unwrap().done);
+        assert!(kernel.wait("unknown", Duration::ZERO).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_messages_with_a_bad_signature_are_refused() {
+        let message = Message::new("status", "s", json!({})).encode(b"right");
+        assert!(Message::decode(b"right", message.clone()).is_ok());
+        assert_eq!(
+            Message::decode(b"wrong", message).unwrap_err(),
+            "bad signature"
+        );
+    }
+
+    #[test]
+    fn test_tracebacks_lose_their_colours() {
+        assert_eq!(
+            strip_ansi("\u{1b}[0;31mNameError\u{1b}[0m: x"),
+            "NameError: x"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_kernels_belong_to_their_client_session() {
+        let manager = KernelManager::new(Sandbox::default()).with_max_kernels(1);
+        let dir = ScratchDir::create("mcp-kernel-").unwrap();
+        let info = ConnectionInfo::ipc(&dir.path().join("kernel"));
+        fake_kernel(info.clone()).await;
+        let kernel = Kernel::connect(&info, None, Some(dir)).await.unwrap();
+        let id = manager.adopt("first", kernel).unwrap();
+
+        assert!(manager.get("first", &id).is_ok());
+        assert!(manager.get("second", &id).is_err());
+        assert_eq!(manager.list("first").len(), 1);
+        assert!(manager.list("second").is_empty());
+        assert!(manager
+            .start("first")
+            .await
+            .unwrap_err()
+            .starts_with("At most 1"));
+        assert!(!manager.shutdown("second", &id).await);
+        assert!(manager.shutdown("first", &id).await);
+    }
+}
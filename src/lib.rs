@@ -0,0 +1,94 @@
+//! A Model Context Protocol server.
+//!
+//! [`McpServer`] holds the tool registry and answers JSON-RPC requests;
+//! the [`transport`] module connects it to a byte stream such as stdio.
+//! Embedders create a server, optionally register their own tools, and run
+//! a transport inside their own tokio runtime:
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! # async fn run() -> std::io::Result<()> {
+//! let server = Arc::new(mcp_server::McpServer::new());
+//! mcp_server::transport::stdio::serve(server).await
+//! # }
+//! ```
+
+// Lets `#[mcp_tool]` name this crate as `::mcp_server` from inside it.
+extern crate self as mcp_server;
+
+pub mod admin;
+pub mod approval;
+pub mod artifacts;
+mod assertions;
+pub mod audit;
+pub mod backends;
+mod base64;
+pub mod cancellation;
+pub mod completion;
+pub mod config;
+mod crypto;
+pub mod delegation;
+pub mod diagnostics;
+pub mod dlp;
+pub mod drift;
+pub mod egress;
+pub mod events;
+pub mod gc;
+pub mod hooks;
+mod http;
+pub mod info;
+pub mod invocations;
+pub mod jupyter;
+pub mod locale;
+pub mod logging;
+pub mod memory;
+pub mod middleware;
+pub mod nats;
+pub mod overload;
+mod pagination;
+pub mod plugins;
+pub mod prompts;
+pub mod protocol;
+pub mod repl;
+pub mod resources;
+pub mod roots;
+pub mod runtime;
+pub mod sampling;
+pub mod sandbox;
+mod script;
+pub mod security;
+pub mod server;
+pub mod session;
+pub mod startup;
+pub mod summarize;
+pub mod templates;
+pub mod timeline;
+mod tokens;
+pub mod tool_config;
+pub mod tools;
+pub mod transport;
+#[cfg(feature = "self-update")]
+pub mod update;
+pub mod uploads;
+pub mod variants;
+pub mod vfs;
+pub mod webhooks;
+pub mod workflow;
+pub mod workspace;
+
+pub use mcp_server_macros::mcp_tool;
+pub use middleware::RequestInterceptor;
+pub use protocol::{
+    Deprecation, McpError, McpRequest, McpResponse, Tool, ToolAnnotations, ToolExample, ToolMeta,
+};
+pub use server::McpServer;
+pub use tools::{ToolContext, ToolError, ToolHandler, ToolResult};
+
+#[doc(hidden)]
+pub mod __private {
+    pub use async_trait::async_trait;
+    pub use schemars;
+    pub use serde;
+    pub use serde_json;
+}
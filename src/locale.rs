@@ -0,0 +1,338 @@
+//! Locale-aware rendering of numbers and dates for text meant for people.
+//! A session's locale comes from the `locale` the client sends with
+//! `initialize`, such as `"de-DE"`, and otherwise from `MCP_LOCALE`;
+//! tools reach it through [`ToolContext::locale`](crate::ToolContext::locale):
+//!
+//! ```
+//! use mcp_server::locale::Locale;
+//!
+//! let german = Locale::parse("de-DE").unwrap();
+//! assert_eq!(german.number(1234567.891, 2), "1.234.567,89");
+//! assert_eq!(Locale::default().number(1234567.891, 2), "1,234,567.89");
+//! ```
+//!
+//! Conventions are built in for the common languages, with regional
+//! overrides where they differ; anything else is rendered the `en` way.
+//! Dates are in UTC. Machine-readable output, such as JSON results, should
+//! keep plain numbers and RFC 3339 timestamps instead.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Order of the parts of a numeric date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Order {
+    DayMonthYear,
+    MonthDayYear,
+    YearMonthDay,
+}
+
+/// How one locale writes numbers and dates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Conventions {
+    decimal: char,
+    group: char,
+    order: Order,
+    date_separator: char,
+    /// Whether day and month are written with two digits.
+    pad: bool,
+    hour12: bool,
+}
+
+const EN_US: Conventions = Conventions {
+    decimal: '.',
+    group: ',',
+    order: Order::MonthDayYear,
+    date_separator: '/',
+    pad: false,
+    hour12: true,
+};
+
+/// What a locale uses, from its language and region subtags, both
+/// lowercase.
+fn conventions(language: &str, region: Option<&str>) -> Conventions {
+    let european = |decimal, group, date_separator| Conventions {
+        decimal,
+        group,
+        order: Order::DayMonthYear,
+        date_separator,
+        pad: true,
+        hour12: false,
+    };
+    let east_asian = Conventions {
+        order: Order::YearMonthDay,
+        hour12: false,
+        ..EN_US
+    };
+    match (language, region) {
+        ("en", Some("gb" | "ie" | "au" | "nz" | "in" | "za")) => Conventions {
+            hour12: region != Some("gb") && region != Some("ie"),
+            ..european('.', ',', '/')
+        },
+        ("en", Some("ca")) => Conventions {
+            order: Order::YearMonthDay,
+            date_separator: '-',
+            pad: true,
+            ..EN_US
+        },
+        ("de", Some("ch")) => european('.', '\u{2019}', '.'),
+        ("de", _) => european(',', '.', '.'),
+        ("fr", Some("ch")) => european(',', '\u{202f}', '.'),
+        ("fr", Some("ca")) => Conventions {
+            order: Order::YearMonthDay,
+            date_separator: '-',
+            ..european(',', '\u{a0}', '-')
+        },
+        ("fr", _) => european(',', '\u{202f}', '/'),
+        ("es", Some("mx" | "us")) => european('.', ',', '/'),
+        ("es" | "it" | "pt" | "el" | "tr" | "id", _) => european(',', '.', '/'),
+        ("nl", _) => european(',', '.', '-'),
+        ("da", _) => european(',', '.', '.'),
+        ("ru" | "uk" | "pl" | "cs" | "fi" | "nb" | "no", _) => european(',', '\u{a0}', '.'),
+        ("sv", _) => Conventions {
+            order: Order::YearMonthDay,
+            date_separator: '-',
+            ..european(',', '\u{a0}', '-')
+        },
+        ("ja" | "zh", _) => east_asian,
+        ("ko", _) => Conventions {
+            date_separator: '.',
+            ..east_asian
+        },
+        _ => EN_US,
+    }
+}
+
+/// A BCP 47 language tag and the conventions it selects.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Locale {
+    tag: String,
+    conventions: Conventions,
+}
+
+impl Default for Locale {
+    /// `en-US`.
+    fn default() -> Self {
+        Self {
+            tag: "en-US".to_string(),
+            conventions: EN_US,
+        }
+    }
+}
+
+impl Locale {
+    /// Reads a tag such as `de`, `pt-BR` or `zh-Hant-TW`; `_` is accepted
+    /// in place of `-`, as in POSIX `LANG` values, and any `.UTF-8`
+    /// encoding suffix is dropped. `None` when it is not a language tag.
+    pub fn parse(tag: &str) -> Option<Self> {
+        let tag = tag.split(['.', '@']).next().unwrap_or_default();
+        let subtags: Vec<&str> = tag.split(['-', '_']).collect();
+        let language = subtags[0].to_ascii_lowercase();
+        if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            return None;
+        }
+        if subtags[1..]
+            .iter()
+            .any(|s| s.is_empty() || s.len() > 8 || !s.chars().all(|c| c.is_ascii_alphanumeric()))
+        {
+            return None;
+        }
+        // The region is the first two-letter or three-digit subtag after
+        // the language, past any script such as `Hant`.
+        let region = subtags[1..]
+            .iter()
+            .find(|s| {
+                (s.len() == 2 && s.chars().all(|c| c.is_ascii_alphabetic()))
+                    || (s.len() == 3 && s.chars().all(|c| c.is_ascii_digit()))
+            })
+            .map(|s| s.to_ascii_lowercase());
+        Some(Self {
+            tag: subtags.join("-"),
+            conventions: conventions(&language, region.as_deref()),
+        })
+    }
+
+    /// The server's default, from `MCP_LOCALE` (or `en-US`).
+    pub fn from_env() -> Self {
+        std::env::var("MCP_LOCALE")
+            .ok()
+            .and_then(|tag| Self::parse(&tag))
+            .unwrap_or_default()
+    }
+
+    /// The tag as the client sent it, with `-` separators.
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// `value` with digit grouping, as in `1,234,567` or `1.234.567`.
+    pub fn integer(&self, value: i64) -> String {
+        let digits = group(&value.unsigned_abs().to_string(), self.conventions.group);
+        if value < 0 {
+            format!("-{}", digits)
+        } else {
+            digits
+        }
+    }
+
+    /// `value` rounded to `decimals` places, with digit grouping and the
+    /// locale's decimal mark. Infinities and NaN are written as Rust does.
+    pub fn number(&self, value: f64, decimals: usize) -> String {
+        if !value.is_finite() {
+            return value.to_string();
+        }
+        let plain = format!("{:.*}", decimals, value.abs());
+        let (whole, fraction) = plain.split_once('.').unwrap_or((&plain, ""));
+        let mut text = group(whole, self.conventions.group);
+        if !fraction.is_empty() {
+            text.push(self.conventions.decimal);
+            text.push_str(fraction);
+        }
+        // `-0.00` reads as a mistake, so only a value that is still
+        // negative after rounding keeps its sign.
+        if value < 0.0 && plain.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            text.insert(0, '-');
+        }
+        text
+    }
+
+    /// `ratio` as a percentage: `0.256` with one decimal is `25.6%`, or
+    /// `25,6 %` where the locale spaces the sign.
+    pub fn percent(&self, ratio: f64, decimals: usize) -> String {
+        let number = self.number(ratio * 100.0, decimals);
+        if self.conventions.decimal == ',' && self.conventions.group != '.' {
+            format!("{}\u{a0}%", number)
+        } else {
+            format!("{}%", number)
+        }
+    }
+
+    /// The UTC calendar date of `at`, as in `3/14/2026`, `14.03.2026` or
+    /// `2026/3/14`.
+    pub fn date(&self, at: SystemTime) -> String {
+        let (year, month, day) = civil_date(days_since_epoch(at));
+        let c = &self.conventions;
+        let (month, day) = if c.pad {
+            (format!("{:02}", month), format!("{:02}", day))
+        } else {
+            (month.to_string(), day.to_string())
+        };
+        let sep = c.date_separator;
+        match c.order {
+            Order::DayMonthYear => format!("{}{sep}{}{sep}{}", day, month, year),
+            Order::MonthDayYear => format!("{}{sep}{}{sep}{}", month, day, year),
+            Order::YearMonthDay => format!("{}{sep}{}{sep}{}", year, month, day),
+        }
+    }
+
+    /// [`date`](Self::date) followed by the UTC time of day to the minute,
+    /// on a 12- or 24-hour clock as the locale prefers.
+    pub fn date_time(&self, at: SystemTime) -> String {
+        let secs = at.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        let (hour, minute) = (secs % 86_400 / 3600, secs % 3600 / 60);
+        let time = if self.conventions.hour12 {
+            let suffix = if hour < 12 { "AM" } else { "PM" };
+            format!("{}:{:02}\u{a0}{}", (hour + 11) % 12 + 1, minute, suffix)
+        } else {
+            format!("{:02}:{:02}", hour, minute)
+        };
+        format!("{} {} UTC", self.date(at), time)
+    }
+}
+
+/// `digits` with `separator` between each group of three, from the right.
+fn group(digits: &str, separator: char) -> String {
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3 * 3);
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            grouped.push(separator);
+        }
+        grouped.push(c);
+    }
+    grouped
+}
+
+fn days_since_epoch(at: SystemTime) -> i64 {
+    match at.duration_since(UNIX_EPOCH) {
+        Ok(since) => (since.as_secs() / 86_400) as i64,
+        Err(before) => -(before.duration().as_secs().div_ceil(86_400) as i64),
+    }
+}
+
+/// Year, month and day of the date `days` after 1970-01-01 (Howard
+/// Hinnant's algorithm).
+pub(crate) fn civil_date(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_numbers_follow_the_locale() {
+        let us = Locale::default();
+        let de = Locale::parse("de-DE").unwrap();
+        let fr = Locale::parse("fr_FR.UTF-8").unwrap();
+        let ch = Locale::parse("de-CH").unwrap();
+
+        assert_eq!(us.integer(-1234567), "-1,234,567");
+        assert_eq!(us.integer(999), "999");
+        assert_eq!(de.integer(1000), "1.000");
+        assert_eq!(fr.integer(1000), "1\u{202f}000");
+        assert_eq!(ch.integer(1000), "1\u{2019}000");
+
+        assert_eq!(us.number(1234.5, 2), "1,234.50");
+        assert_eq!(de.number(-1234.5, 1), "-1.234,5");
+        assert_eq!(us.number(-0.001, 2), "0.00");
+        assert_eq!(us.number(9.6, 0), "10");
+        assert_eq!(us.number(f64::NAN, 2), "NaN");
+
+        assert_eq!(us.percent(0.256, 1), "25.6%");
+        assert_eq!(fr.percent(0.5, 0), "50\u{a0}%");
+        assert_eq!(de.percent(0.5, 0), "50%");
+    }
+
+    #[test]
+    fn test_dates_follow_the_locale() {
+        // 2026-03-04 14:05:09 UTC.
+        let at = UNIX_EPOCH + Duration::from_secs(1_772_633_109);
+        let date = |tag: &str| Locale::parse(tag).unwrap().date(at);
+        assert_eq!(Locale::default().date(at), "3/4/2026");
+        assert_eq!(date("en-GB"), "04/03/2026");
+        assert_eq!(date("de"), "04.03.2026");
+        assert_eq!(date("sv-SE"), "2026-03-04");
+        assert_eq!(date("zh-Hant-TW"), "2026/3/4");
+        assert_eq!(date("xx"), "3/4/2026");
+
+        assert_eq!(Locale::default().date_time(at), "3/4/2026 2:05\u{a0}PM UTC");
+        assert_eq!(
+            Locale::parse("de-DE").unwrap().date_time(at),
+            "04.03.2026 14:05 UTC"
+        );
+        assert_eq!(
+            Locale::default().date(UNIX_EPOCH - Duration::from_secs(1)),
+            "12/31/1969"
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Locale::parse("pt_BR").unwrap().tag(), "pt-BR");
+        assert_eq!(Locale::parse("en_US.UTF-8").unwrap().tag(), "en-US");
+        assert!(Locale::parse("").is_none());
+        assert!(Locale::parse("english").is_none());
+        assert!(Locale::parse("en-").is_none());
+        assert!(Locale::parse("en-US;q=0.9").is_none());
+    }
+}
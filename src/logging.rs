@@ -0,0 +1,80 @@
+//! The MCP logging capability. A client picks the least severe level it
+//! wants with `logging/setLevel`, and tools send it log entries as
+//! `notifications/message` through a [`Logger`] taken from their
+//! [`ToolContext`](crate::ToolContext), so a long-running call can be
+//! followed from the client while it works.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::session::Session;
+
+/// Severity of a log entry, the syslog levels of RFC 5424 from least to
+/// most severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+    Alert,
+    Emergency,
+}
+
+impl std::str::FromStr for LogLevel {
+    type Err = String;
+
+    /// Parses the lowercase name, as in `logging/setLevel`.
+    fn from_str(s: &str) -> Result<Self, String> {
+        serde_json::from_value(Value::from(s)).map_err(|_| {
+            format!(
+                "Unknown log level '{}'; expected debug, info, notice, warning, error, \
+                 critical, alert or emergency",
+                s
+            )
+        })
+    }
+}
+
+/// Sends log entries to the calling session's client. Cheap to clone, so a
+/// tool can hand it to tasks it spawns. Outside a session entries are
+/// dropped.
+#[derive(Clone, Default)]
+pub struct Logger {
+    session: Option<Arc<Session>>,
+    name: Option<String>,
+}
+
+impl Logger {
+    pub fn new(session: Option<Arc<Session>>, name: Option<String>) -> Self {
+        Self { session, name }
+    }
+
+    /// Sends `data`, a message or any JSON value, if the client asked for
+    /// entries at `level`.
+    pub fn log(&self, level: LogLevel, data: impl Into<Value>) {
+        if let Some(session) = &self.session {
+            session.log(level, self.name.as_deref(), data.into());
+        }
+    }
+
+    pub fn debug(&self, data: impl Into<Value>) {
+        self.log(LogLevel::Debug, data);
+    }
+
+    pub fn info(&self, data: impl Into<Value>) {
+        self.log(LogLevel::Info, data);
+    }
+
+    pub fn warning(&self, data: impl Into<Value>) {
+        self.log(LogLevel::Warning, data);
+    }
+
+    pub fn error(&self, data: impl Into<Value>) {
+        self.log(LogLevel::Error, data);
+    }
+}
@@ -1,491 +1,380 @@
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use std::collections::HashMap;
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
-use uuid::Uuid;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct McpRequest {
-    pub jsonrpc: String,
-    pub id: Option<Value>,
-    pub method: String,
-    pub params: Option<Value>,
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use mcp_server::config::Config;
+use mcp_server::logging::LogLevel;
+use mcp_server::runtime::RuntimeSettings;
+use mcp_server::tools::warmup::WarmupMode;
+use mcp_server::transport::access::{AccessPolicy, ConnectionLimiter};
+use mcp_server::transport::trace::WireTrace;
+use mcp_server::{
+    admin, delegation, diagnostics, drift, gc, nats, startup, transport, webhooks, McpServer,
+};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// A Model Context Protocol server. Without a subcommand it serves, as
+/// `serve` does.
+#[derive(Parser)]
+#[command(name = "mcp-server", version)]
+struct Cli {
+    /// TOML file with the server's settings. Flags override it.
+    #[arg(long, global = true, value_name = "FILE")]
+    config: Option<PathBuf>,
+    /// Least severe log level sent to clients until they pick their own.
+    #[arg(long, global = true, value_name = "LEVEL")]
+    log_level: Option<LogLevel>,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[command(flatten)]
+    serve: ServeArgs,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct McpResponse {
-    pub jsonrpc: String,
-    pub id: Option<Value>,
-    pub result: Option<Value>,
-    pub error: Option<McpError>,
+#[derive(Subcommand)]
+enum Command {
+    /// Serve clients over a transport.
+    Serve(ServeArgs),
+    /// Print the tools a client would be offered.
+    ListTools {
+        /// Print the full definitions as JSON.
+        #[arg(long)]
+        json: bool,
+    },
+    /// Call one tool, print its result, and exit with status 1 if it
+    /// failed.
+    Call {
+        tool: String,
+        /// The arguments, as a JSON object, or `-` to read them from stdin.
+        #[arg(long, value_name = "JSON", default_value = "{}")]
+        args: String,
+    },
+    /// Run one garbage collection over the persisted state and print what
+    /// was reclaimed. A running server's sessions and jobs are collected by
+    /// the server itself; see the admin socket's `gc`.
+    Gc {
+        /// Leave tool scratch directories younger than this many seconds,
+        /// as a running server may still be using them.
+        #[arg(long, value_name = "SECS", default_value_t = gc::DEFAULT_OFFLINE_MIN_AGE_SECS)]
+        older_than: u64,
+    },
+    /// Export or check the tool schemas.
+    #[command(subcommand)]
+    Schema(SchemaCommand),
+    /// Install the latest signed release over this binary.
+    SelfUpdate {
+        /// Only report whether a newer release exists.
+        #[arg(long)]
+        check: bool,
+        /// Install the release even when it is not newer.
+        #[arg(long)]
+        force: bool,
+    },
+    /// List or install plugins from the registry.
+    #[command(subcommand)]
+    Plugins(PluginsCommand),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct McpError {
-    pub code: i32,
-    pub message: String,
-    pub data: Option<Value>,
+#[derive(Args, Default, PartialEq)]
+struct ServeArgs {
+    #[arg(long, value_parser = ["stdio", "tcp", "http", "sse"])]
+    transport: Option<String>,
+    /// Address for the tcp, http or sse transport.
+    #[arg(long, value_name = "ADDR")]
+    bind: Option<String>,
+    /// Shorthand for `--transport tcp --bind <ADDR>`.
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["transport", "bind"])]
+    tcp: Option<String>,
+    /// Write the full startup report, as JSON, to stderr.
+    #[arg(long)]
+    verbose_startup: bool,
+    /// Record every JSON-RPC message sent or received in FILE.
+    #[arg(long, value_name = "FILE")]
+    trace_io: Option<PathBuf>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Tool {
-    pub name: String,
-    pub description: String,
-    pub input_schema: Value,
+#[derive(Subcommand)]
+enum SchemaCommand {
+    /// Print the tools as a snapshot.
+    Export,
+    /// Report how the tools changed since a snapshot, exiting with status 1
+    /// on a breaking change.
+    Diff { snapshot: PathBuf },
 }
 
-pub struct McpServer {
-    tools: HashMap<String, Tool>,
+#[derive(Subcommand)]
+enum PluginsCommand {
+    /// Print the registry's verified index.
+    List,
+    /// Install plugins into `MCP_PLUGINS_DIR`.
+    Install {
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
 }
 
-impl McpServer {
-    pub fn new() -> Self {
-        let mut server = Self {
-            tools: HashMap::new(),
-        };
-        
-        server.register_example_tool();
-        server
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    if !matches!(cli.command, None | Some(Command::Serve(_))) && cli.serve != ServeArgs::default() {
+        Cli::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "serving flags go with `serve`, or with no subcommand",
+            )
+            .exit();
     }
-    
-    fn register_example_tool(&mut self) {
-        let tool = Tool {
-            name: "echo".to_string(),
-            description: "Echo back the input text".to_string(),
-            input_schema: serde_json::json!({
-                "type": "object",
-                "properties": {
-                    "text": {
-                        "type": "string",
-                        "description": "Text to echo back"
-                    }
-                },
-                "required": ["text"]
-            }),
-        };
-        
-        self.tools.insert("echo".to_string(), tool);
+
+    // The config file sets up the server before anything else runs, so
+    // `schema export` and `list-tools` see the same tools a client would.
+    let config = match &cli.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    // Before the server exists, so its warnings about the environment
+    // reach the subscriber.
+    let format = config
+        .diagnostics
+        .format
+        .unwrap_or_else(diagnostics::LogFormat::from_env);
+    diagnostics::init(format, config.diagnostics.filter.as_deref())?;
+    // Built here rather than by `#[tokio::main]`, so the config file can
+    // size it.
+    let runtime = config.runtime_settings(RuntimeSettings::from_env());
+    runtime.build()?.block_on(run(cli, config, runtime))
+}
+
+async fn run(
+    cli: Cli,
+    config: Config,
+    runtime: RuntimeSettings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut server = McpServer::new();
+    config.apply(&mut server);
+    if let Some(level) = cli.log_level {
+        server.set_default_log_level(level);
     }
-    
-    pub async fn handle_request(&self, request: McpRequest) -> McpResponse {
-        match request.method.as_str() {
-            "initialize" => self.handle_initialize(request).await,
-            "tools/list" => self.handle_tools_list(request).await,
-            "tools/call" => self.handle_tools_call(request).await,
-            _ => McpResponse {
-                jsonrpc: "2.0".to_string(),
-                id: request.id,
-                result: None,
-                error: Some(McpError {
-                    code: -32601,
-                    message: "Method not found".to_string(),
-                    data: None,
-                }),
-            },
-        }
+    let trace_io = match &cli.command {
+        None => cli.serve.trace_io.as_ref(),
+        Some(Command::Serve(args)) => args.trace_io.as_ref(),
+        _ => None,
+    };
+    if let Some(path) = trace_io {
+        let trace = WireTrace::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        server.set_wire_trace(trace);
     }
-    
-    async fn handle_initialize(&self, request: McpRequest) -> McpResponse {
-        McpResponse {
-            jsonrpc: "2.0".to_string(),
-            id: request.id,
-            result: Some(serde_json::json!({
-                "protocolVersion": "2024-11-05",
-                "capabilities": {
-                    "tools": {}
-                },
-                "serverInfo": {
-                    "name": "mcp-server",
-                    "version": "0.1.0"
+    let server = Arc::new(server);
+    let gc_policy = gc::GcPolicy::from_env();
+
+    let serve_args = match cli.command {
+        None => cli.serve,
+        Some(Command::Serve(args)) => args,
+        Some(Command::ListTools { json }) => {
+            let tools: Vec<_> = server
+                .tools()
+                .into_iter()
+                .filter(|t| !server.is_read_only() || t.is_read_only())
+                .collect();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&tools)?);
+            } else {
+                let width = tools.iter().map(|t| t.name.len()).max().unwrap_or(0);
+                for tool in &tools {
+                    let summary = tool.description.lines().next().unwrap_or_default();
+                    println!("{:width$}  {}", tool.name, summary);
                 }
-            })),
-            error: None,
-        }
-    }
-    
-    async fn handle_tools_list(&self, request: McpRequest) -> McpResponse {
-        let tools: Vec<&Tool> = self.tools.values().collect();
-        
-        McpResponse {
-            jsonrpc: "2.0".to_string(),
-            id: request.id,
-            result: Some(serde_json::json!({
-                "tools": tools
-            })),
-            error: None,
+            }
+            return Ok(());
         }
-    }
-    
-    async fn handle_tools_call(&self, request: McpRequest) -> McpResponse {
-        let params = match request.params {
-            Some(params) => params,
-            None => {
-                return McpResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id: request.id,
-                    result: None,
-                    error: Some(McpError {
-                        code: -32602,
-                        message: "Invalid params".to_string(),
-                        data: None,
-                    }),
-                };
+        Some(Command::Call { tool, args }) => {
+            let args = if args == "-" {
+                std::io::read_to_string(std::io::stdin())?
+            } else {
+                args
+            };
+            let arguments: serde_json::Value = serde_json::from_str(&args)
+                .map_err(|e| format!("--args is not valid JSON: {}", e))?;
+            let request = serde_json::from_value(serde_json::json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": {"name": tool, "arguments": arguments}
+            }))?;
+            let response = server
+                .handle_request(request)
+                .await
+                .ok_or("The call was cancelled")?;
+            if let Some(error) = response.error {
+                return Err(format!("{} ({})", error.message, error.code).into());
             }
-        };
-        
-        let tool_name = match params.get("name") {
-            Some(Value::String(name)) => name,
-            _ => {
-                return McpResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id: request.id,
-                    result: None,
-                    error: Some(McpError {
-                        code: -32602,
-                        message: "Tool name required".to_string(),
-                        data: None,
-                    }),
-                };
+            let result = response.result.unwrap_or_default();
+            println!("{}", serde_json::to_string_pretty(&result)?);
+            if result["isError"] == true {
+                std::process::exit(1);
             }
-        };
-        
-        match tool_name.as_str() {
-            "echo" => {
-                let text = params
-                    .get("arguments")
-                    .and_then(|args| args.get("text"))
-                    .and_then(|t| t.as_str())
-                    .unwrap_or("No text provided");
-                
-                McpResponse {
-                    jsonrpc: "2.0".to_string(),
-                    id: request.id,
-                    result: Some(serde_json::json!({
-                        "content": [{
-                            "type": "text",
-                            "text": format!("Echo: {}", text)
-                        }]
-                    })),
-                    error: None,
+            return Ok(());
+        }
+        Some(Command::Gc { older_than }) => {
+            let mut policy = gc_policy;
+            policy.temp_dir_ttl = std::time::Duration::from_secs(older_than);
+            let report = server.gc_persisted(&policy);
+            println!("{}", serde_json::to_string_pretty(&report)?);
+            return Ok(());
+        }
+        Some(Command::Schema(command)) => {
+            let tools = server.tools();
+            match command {
+                SchemaCommand::Export => {
+                    println!("{}", serde_json::to_string_pretty(&drift::export(&tools))?);
+                }
+                SchemaCommand::Diff { snapshot } => {
+                    let snapshot = drift::load(&snapshot).map_err(|e| {
+                        format!("Failed to read snapshot {}: {}", snapshot.display(), e)
+                    })?;
+                    let report = drift::compare(&snapshot, &tools);
+                    println!("{}", serde_json::to_string_pretty(&report)?);
+                    if report.is_breaking() {
+                        std::process::exit(1);
+                    }
                 }
             }
-            _ => McpResponse {
-                jsonrpc: "2.0".to_string(),
-                id: request.id,
-                result: None,
-                error: Some(McpError {
-                    code: -32601,
-                    message: "Tool not found".to_string(),
-                    data: None,
-                }),
-            },
+            return Ok(());
         }
-    }
-}
-
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let server = McpServer::new();
-    
-    let stdin = tokio::io::stdin();
-    let mut reader = BufReader::new(stdin);
-    let mut stdout = tokio::io::stdout();
-    
-    loop {
-        let mut line = String::new();
-        match reader.read_line(&mut line).await {
-            Ok(0) => break,
-            Ok(_) => {
-                let line = line.trim();
-                if line.is_empty() {
-                    continue;
-                }
-                
-                match serde_json::from_str::<McpRequest>(line) {
-                    Ok(request) => {
-                        let response = server.handle_request(request).await;
-                        let response_json = serde_json::to_string(&response)?;
-                        stdout.write_all(response_json.as_bytes()).await?;
-                        stdout.write_all(b"\n").await?;
-                        stdout.flush().await?;
+        Some(Command::SelfUpdate { check, force }) => {
+            #[cfg(feature = "self-update")]
+            {
+                let config = mcp_server::update::UpdateConfig::from_env()?;
+                let executable = std::env::current_exe()?;
+                let report = mcp_server::update::run(&config, &executable, !check, force).await?;
+                println!("{}", serde_json::to_string_pretty(&report)?);
+                return Ok(());
+            }
+            #[cfg(not(feature = "self-update"))]
+            {
+                let _ = (check, force);
+                return Err("mcp-server was built without the self-update feature".into());
+            }
+        }
+        Some(Command::Plugins(command)) => {
+            #[cfg(feature = "plugins")]
+            {
+                use mcp_server::plugins;
+                let registry = plugins::Registry::from_env().await?;
+                match command {
+                    PluginsCommand::List => {
+                        println!("{}", serde_json::to_string_pretty(&registry.plugins)?);
                     }
-                    Err(e) => {
-                        eprintln!("Failed to parse request: {}", e);
+                    PluginsCommand::Install { names } => {
+                        let dir = plugins::dir_from_env().ok_or("MCP_PLUGINS_DIR is not set")?;
+                        for name in &names {
+                            let entry = registry
+                                .get(name)
+                                .ok_or_else(|| format!("No plugin '{}' in the registry", name))?;
+                            plugins::install(&dir, entry).await?;
+                            eprintln!("Installed {} {}", entry.name, entry.version);
+                        }
                     }
                 }
+                return Ok(());
             }
-            Err(e) => {
-                eprintln!("Failed to read line: {}", e);
-                break;
+            #[cfg(not(feature = "plugins"))]
+            {
+                let _ = command;
+                return Err("mcp-server was built without the plugins feature".into());
             }
         }
-    }
-    
-    Ok(())
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use serde_json::json;
-
-    #[test]
-    fn test_mcp_request_serialization() {
-        let request = McpRequest {
-            jsonrpc: "2.0".to_string(),
-            id: Some(json!(1)),
-            method: "initialize".to_string(),
-            params: Some(json!({"clientInfo": {"name": "test", "version": "1.0"}})),
-        };
-
-        let serialized = serde_json::to_string(&request).unwrap();
-        let deserialized: McpRequest = serde_json::from_str(&serialized).unwrap();
-
-        assert_eq!(request.jsonrpc, deserialized.jsonrpc);
-        assert_eq!(request.id, deserialized.id);
-        assert_eq!(request.method, deserialized.method);
-        assert_eq!(request.params, deserialized.params);
-    }
-
-    #[test]
-    fn test_mcp_response_serialization() {
-        let response = McpResponse {
-            jsonrpc: "2.0".to_string(),
-            id: Some(json!(1)),
-            result: Some(json!({"success": true})),
-            error: None,
-        };
-
-        let serialized = serde_json::to_string(&response).unwrap();
-        let deserialized: McpResponse = serde_json::from_str(&serialized).unwrap();
-
-        assert_eq!(response.jsonrpc, deserialized.jsonrpc);
-        assert_eq!(response.id, deserialized.id);
-        assert_eq!(response.result, deserialized.result);
-        assert_eq!(response.error.is_none(), deserialized.error.is_none());
-    }
-
-    #[test]
-    fn test_mcp_error_serialization() {
-        let error = McpError {
-            code: -32601,
-            message: "Method not found".to_string(),
-            data: Some(json!({"method": "unknown"})),
-        };
-
-        let serialized = serde_json::to_string(&error).unwrap();
-        let deserialized: McpError = serde_json::from_str(&serialized).unwrap();
-
-        assert_eq!(error.code, deserialized.code);
-        assert_eq!(error.message, deserialized.message);
-        assert_eq!(error.data, deserialized.data);
-    }
-
-    #[tokio::test]
-    async fn test_server_initialization() {
-        let server = McpServer::new();
-        let request = McpRequest {
-            jsonrpc: "2.0".to_string(),
-            id: Some(json!(1)),
-            method: "initialize".to_string(),
-            params: Some(json!({"clientInfo": {"name": "test", "version": "1.0"}})),
-        };
-
-        let response = server.handle_request(request).await;
-
-        assert_eq!(response.jsonrpc, "2.0");
-        assert_eq!(response.id, Some(json!(1)));
-        assert!(response.error.is_none());
-        
-        let result = response.result.unwrap();
-        assert_eq!(result["protocolVersion"], "2024-11-05");
-        assert_eq!(result["serverInfo"]["name"], "mcp-server");
-        assert_eq!(result["serverInfo"]["version"], "0.1.0");
-    }
-
-    #[tokio::test]
-    async fn test_tools_list() {
-        let server = McpServer::new();
-        let request = McpRequest {
-            jsonrpc: "2.0".to_string(),
-            id: Some(json!(2)),
-            method: "tools/list".to_string(),
-            params: None,
-        };
-
-        let response = server.handle_request(request).await;
-
-        assert_eq!(response.jsonrpc, "2.0");
-        assert_eq!(response.id, Some(json!(2)));
-        assert!(response.error.is_none());
-        
-        let result = response.result.unwrap();
-        let tools = result["tools"].as_array().unwrap();
-        assert_eq!(tools.len(), 1);
-        assert_eq!(tools[0]["name"], "echo");
-        assert_eq!(tools[0]["description"], "Echo back the input text");
-    }
-
-    #[tokio::test]
-    async fn test_echo_tool_execution() {
-        let server = McpServer::new();
-        let request = McpRequest {
-            jsonrpc: "2.0".to_string(),
-            id: Some(json!(3)),
-            method: "tools/call".to_string(),
-            params: Some(json!({
-                "name": "echo",
-                "arguments": {
-                    "text": "Hello, World!"
-                }
-            })),
-        };
-
-        let response = server.handle_request(request).await;
+    };
 
-        assert_eq!(response.jsonrpc, "2.0");
-        assert_eq!(response.id, Some(json!(3)));
-        assert!(response.error.is_none());
-        
-        let result = response.result.unwrap();
-        let content = result["content"].as_array().unwrap();
-        assert_eq!(content.len(), 1);
-        assert_eq!(content[0]["type"], "text");
-        assert_eq!(content[0]["text"], "Echo: Hello, World!");
+    let collector = Arc::new(gc::Collector::new(server.clone(), gc_policy));
+    if !collector.policy().interval.is_zero() {
+        gc::spawn(collector.clone());
     }
 
-    #[tokio::test]
-    async fn test_echo_tool_without_text() {
-        let server = McpServer::new();
-        let request = McpRequest {
-            jsonrpc: "2.0".to_string(),
-            id: Some(json!(4)),
-            method: "tools/call".to_string(),
-            params: Some(json!({
-                "name": "echo",
-                "arguments": {}
-            })),
-        };
-
-        let response = server.handle_request(request).await;
-
-        assert_eq!(response.jsonrpc, "2.0");
-        assert_eq!(response.id, Some(json!(4)));
-        assert!(response.error.is_none());
-        
-        let result = response.result.unwrap();
-        let content = result["content"].as_array().unwrap();
-        assert_eq!(content[0]["text"], "Echo: No text provided");
+    // Off unless asked for, since every child is another listening port.
+    let children = Arc::new(delegation::ChildServers::default());
+    if std::env::var("MCP_CHILD_SERVERS").is_ok_and(|v| v == "1" || v == "true") {
+        delegation::register(&server, children.clone());
     }
 
-    #[tokio::test]
-    async fn test_unknown_method_error() {
-        let server = McpServer::new();
-        let request = McpRequest {
-            jsonrpc: "2.0".to_string(),
-            id: Some(json!(5)),
-            method: "unknown/method".to_string(),
-            params: None,
-        };
-
-        let response = server.handle_request(request).await;
-
-        assert_eq!(response.jsonrpc, "2.0");
-        assert_eq!(response.id, Some(json!(5)));
-        assert!(response.result.is_none());
-        
-        let error = response.error.unwrap();
-        assert_eq!(error.code, -32601);
-        assert_eq!(error.message, "Method not found");
+    let hooks = webhooks::from_env();
+    if !hooks.is_empty() {
+        webhooks::spawn(&server.events(), hooks);
     }
-
-    #[tokio::test]
-    async fn test_unknown_tool_error() {
-        let server = McpServer::new();
-        let request = McpRequest {
-            jsonrpc: "2.0".to_string(),
-            id: Some(json!(6)),
-            method: "tools/call".to_string(),
-            params: Some(json!({
-                "name": "unknown_tool",
-                "arguments": {}
-            })),
-        };
-
-        let response = server.handle_request(request).await;
-
-        assert_eq!(response.jsonrpc, "2.0");
-        assert_eq!(response.id, Some(json!(6)));
-        assert!(response.result.is_none());
-        
-        let error = response.error.unwrap();
-        assert_eq!(error.code, -32601);
-        assert_eq!(error.message, "Tool not found");
+    if let Some(config) = nats::NatsConfig::from_env() {
+        nats::spawn(&server.events(), config);
     }
 
-    #[tokio::test]
-    async fn test_tool_call_without_params() {
-        let server = McpServer::new();
-        let request = McpRequest {
-            jsonrpc: "2.0".to_string(),
-            id: Some(json!(7)),
-            method: "tools/call".to_string(),
-            params: None,
-        };
-
-        let response = server.handle_request(request).await;
-
-        assert_eq!(response.jsonrpc, "2.0");
-        assert_eq!(response.id, Some(json!(7)));
-        assert!(response.result.is_none());
-        
-        let error = response.error.unwrap();
-        assert_eq!(error.code, -32602);
-        assert_eq!(error.message, "Invalid params");
+    // `--tcp <addr>` runs as a daemon that several local processes can
+    // connect to, each speaking the same line-delimited protocol as stdio.
+    // Flags win over the config file.
+    let transport = serve_args
+        .transport
+        .as_deref()
+        .or(config.transport.kind.as_deref());
+    let bind = serve_args
+        .bind
+        .as_deref()
+        .or(config.transport.bind.as_deref());
+    let (kind, bind) = match (serve_args.tcp.as_deref(), transport.unwrap_or("stdio")) {
+        (Some(addr), _) => ("tcp", addr),
+        (None, "tcp") => (
+            "tcp",
+            bind.ok_or("The tcp transport needs an address to bind")?,
+        ),
+        (None, "http") => ("http", bind.unwrap_or(transport::http::DEFAULT_BIND)),
+        (None, "sse") => ("sse", bind.unwrap_or(transport::sse::DEFAULT_BIND)),
+        // The config file's transport was checked as it was loaded.
+        (None, _) => ("stdio", ""),
+    };
+    let limiter = Arc::new(
+        ConnectionLimiter::new(kind, config.access_policy(AccessPolicy::from_env(kind)?))
+            .with_security(server.security()),
+    );
+
+    #[cfg(unix)]
+    if let Ok(path) = std::env::var("MCP_ADMIN_SOCKET") {
+        let mut admin = admin::Admin::new(server.approvals())
+            .with_dlp(server.dlp())
+            .with_variants(server.description_variants())
+            .with_backends(server.backends())
+            .with_tool_config(server.tool_config())
+            .with_templates(server.result_templates())
+            .with_children(children.clone())
+            .with_timeline(server.timeline())
+            .with_warmups(server.warmups())
+            .with_gc(collector.clone());
+        if kind != "stdio" {
+            admin = admin.with_connections(limiter.clone());
+        }
+        if let Some(auth) = server.token_auth() {
+            admin = admin.with_token_auth(auth);
+        }
+        let admin = Arc::new(admin);
+        tokio::spawn(async move {
+            if let Err(e) = admin::serve_unix(std::path::Path::new(&path), admin).await {
+                tracing::error!("Admin socket failed: {}", e);
+            }
+        });
     }
 
-    #[tokio::test]
-    async fn test_tool_call_without_name() {
-        let server = McpServer::new();
-        let request = McpRequest {
-            jsonrpc: "2.0".to_string(),
-            id: Some(json!(8)),
-            method: "tools/call".to_string(),
-            params: Some(json!({
-                "arguments": {"text": "test"}
-            })),
-        };
-
-        let response = server.handle_request(request).await;
-
-        assert_eq!(response.jsonrpc, "2.0");
-        assert_eq!(response.id, Some(json!(8)));
-        assert!(response.result.is_none());
-        
-        let error = response.error.unwrap();
-        assert_eq!(error.code, -32602);
-        assert_eq!(error.message, "Tool name required");
+    // stdout carries the protocol on stdio, so the report goes to stderr.
+    let mut report = startup::StartupReport::new(&server, kind, (!bind.is_empty()).then_some(bind));
+    if let Some(path) = &cli.config {
+        report = report.with_config_file(&path.display().to_string());
     }
-
-    #[test]
-    fn test_server_creation() {
-        let server = McpServer::new();
-        assert_eq!(server.tools.len(), 1);
-        assert!(server.tools.contains_key("echo"));
+    report = report.with_runtime(runtime);
+    report.emit(serve_args.verbose_startup);
+
+    // In the background, so clients are served while tools warm up; a call
+    // that arrives first waits for its tool's warm-up.
+    if server.warmups().mode() == WarmupMode::Startup {
+        let server = server.clone();
+        tokio::spawn(async move { server.warm_up().await });
     }
 
-    #[test]
-    fn test_tool_schema() {
-        let server = McpServer::new();
-        let echo_tool = server.tools.get("echo").unwrap();
-        
-        assert_eq!(echo_tool.name, "echo");
-        assert_eq!(echo_tool.description, "Echo back the input text");
-        
-        let schema = &echo_tool.input_schema;
-        assert_eq!(schema["type"], "object");
-        assert!(schema["properties"]["text"].is_object());
-        assert_eq!(schema["required"].as_array().unwrap().len(), 1);
-        assert_eq!(schema["required"][0], "text");
+    match kind {
+        "tcp" => transport::tcp::serve(server, bind.parse()?, limiter).await?,
+        "http" => transport::http::serve(server, bind.parse()?, limiter).await?,
+        "sse" => transport::sse::serve(server, bind.parse()?, limiter).await?,
+        _ => transport::stdio::serve(server).await?,
     }
+    Ok(())
 }
@@ -0,0 +1,438 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Entity {
+    pub name: String,
+    #[serde(rename = "entityType")]
+    pub entity_type: String,
+    #[serde(default)]
+    pub observations: Vec<String>,
+    /// Unix time the entity was last written, used for TTL-based cleanup.
+    #[serde(rename = "updatedAt", default, skip_serializing_if = "Option::is_none")]
+    pub updated_at: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Relation {
+    pub from: String,
+    pub to: String,
+    #[serde(rename = "relationType")]
+    pub relation_type: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct KnowledgeGraph {
+    pub entities: Vec<Entity>,
+    pub relations: Vec<Relation>,
+}
+
+/// Entity/relation store backing the `memory_*` tools. When a path is set the
+/// whole graph is rewritten to it after every change, and a change only
+/// takes effect once it has been written.
+pub struct MemoryStore {
+    graph: Mutex<KnowledgeGraph>,
+    path: Option<PathBuf>,
+    read_only: AtomicBool,
+    /// Set when the file could not be read. Unlike `read_only`, it is never
+    /// cleared, so turning read-only mode off cannot overwrite the file.
+    load_failed: bool,
+}
+
+impl MemoryStore {
+    /// Store loaded from `path`, if it exists. A file that does not parse is
+    /// moved aside to `<path>.corrupt` and the store starts empty; one that
+    /// cannot be read at all leaves the store read-only, so it is never
+    /// overwritten.
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let mut load_failed = false;
+        let graph = match &path {
+            Some(path) => load(path).unwrap_or_else(|e| {
+                tracing::error!("Failed to load memory graph {}: {}", path.display(), e);
+                load_failed = true;
+                KnowledgeGraph::default()
+            }),
+            None => KnowledgeGraph::default(),
+        };
+        Self {
+            graph: Mutex::new(graph),
+            path,
+            read_only: AtomicBool::new(false),
+            load_failed,
+        }
+    }
+
+    /// Store persisted to `MCP_MEMORY_FILE`, or kept in memory when unset.
+    pub fn from_env() -> Self {
+        Self::new(std::env::var("MCP_MEMORY_FILE").ok().map(PathBuf::from))
+    }
+
+    /// While set, every change is refused and the file is never written. A
+    /// store whose file could not be read stays read-only regardless.
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed) || self.load_failed
+    }
+
+    fn check_writable(&self) -> Result<(), String> {
+        if self.is_read_only() {
+            return Err("Memory store is read-only".to_string());
+        }
+        Ok(())
+    }
+
+    /// Adds entities whose names are not yet known and returns the ones added.
+    pub fn create_entities(&self, entities: Vec<Entity>) -> Result<Vec<Entity>, String> {
+        self.update(|graph| {
+            let mut created = Vec::new();
+            for mut entity in entities {
+                if !graph.entities.iter().any(|e| e.name == entity.name) {
+                    entity.updated_at = Some(unix_now());
+                    graph.entities.push(entity.clone());
+                    created.push(entity);
+                }
+            }
+            Ok(created)
+        })
+    }
+
+    /// Adds relations between existing entities, skipping duplicates, and
+    /// returns the ones added. Both ends of each added relation count as
+    /// updated.
+    pub fn create_relations(&self, relations: Vec<Relation>) -> Result<Vec<Relation>, String> {
+        self.update(|graph| {
+            let mut missing: Vec<&str> = relations
+                .iter()
+                .flat_map(|r| [r.from.as_str(), r.to.as_str()])
+                .filter(|name| !graph.entities.iter().any(|e| e.name == *name))
+                .collect();
+            if !missing.is_empty() {
+                missing.sort_unstable();
+                missing.dedup();
+                return Err(format!("Unknown entities: {}", missing.join(", ")));
+            }
+
+            let mut created = Vec::new();
+            for relation in relations {
+                if !graph.relations.contains(&relation) {
+                    graph.relations.push(relation.clone());
+                    created.push(relation);
+                }
+            }
+            let now = unix_now();
+            for entity in &mut graph.entities {
+                if created
+                    .iter()
+                    .any(|r| r.from == entity.name || r.to == entity.name)
+                {
+                    entity.updated_at = Some(now);
+                }
+            }
+            Ok(created)
+        })
+    }
+
+    /// Entities whose name, type or observations contain `query`
+    /// (case-insensitively), with the relations among them.
+    pub fn search(&self, query: &str) -> KnowledgeGraph {
+        let graph = self.graph.lock().unwrap();
+        let query = query.to_lowercase();
+        let entities: Vec<Entity> = graph
+            .entities
+            .iter()
+            .filter(|e| {
+                e.name.to_lowercase().contains(&query)
+                    || e.entity_type.to_lowercase().contains(&query)
+                    || e.observations
+                        .iter()
+                        .any(|o| o.to_lowercase().contains(&query))
+            })
+            .cloned()
+            .collect();
+        let relations = graph
+            .relations
+            .iter()
+            .filter(|r| {
+                entities.iter().any(|e| e.name == r.from) && entities.iter().any(|e| e.name == r.to)
+            })
+            .cloned()
+            .collect();
+        KnowledgeGraph {
+            entities,
+            relations,
+        }
+    }
+
+    pub fn graph(&self) -> KnowledgeGraph {
+        self.graph.lock().unwrap().clone()
+    }
+
+    /// Removes entities stored more than `max_age` ago, together with every
+    /// relation touching them. Entities without a timestamp (written by older
+    /// versions) are kept. Returns the number of entities and relations removed.
+    pub fn prune_older_than(&self, max_age: Duration) -> Result<(usize, usize), String> {
+        let cutoff = unix_now().saturating_sub(max_age.as_secs());
+        self.update(|graph| {
+            let before = (graph.entities.len(), graph.relations.len());
+            graph
+                .entities
+                .retain(|e| e.updated_at.is_none_or(|t| t > cutoff));
+            let KnowledgeGraph {
+                entities,
+                relations,
+            } = graph;
+            relations.retain(|r| {
+                entities.iter().any(|e| e.name == r.from) && entities.iter().any(|e| e.name == r.to)
+            });
+            Ok((before.0 - entities.len(), before.1 - relations.len()))
+        })
+    }
+
+    /// Applies `change` to a copy of the graph and, if anything changed,
+    /// writes the copy out before swapping it in. A failed write leaves the
+    /// graph as it was.
+    fn update<T>(
+        &self,
+        change: impl FnOnce(&mut KnowledgeGraph) -> Result<T, String>,
+    ) -> Result<T, String> {
+        self.check_writable()?;
+        let mut graph = self.graph.lock().unwrap();
+        let mut next = graph.clone();
+        let result = change(&mut next)?;
+        if next != *graph {
+            self.persist(&next)?;
+            *graph = next;
+        }
+        Ok(result)
+    }
+
+    fn persist(&self, graph: &KnowledgeGraph) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let tmp = path.with_extension("tmp");
+        let json = serde_json::to_string_pretty(graph).map_err(|e| e.to_string())?;
+        std::fs::write(&tmp, json)
+            .and_then(|_| std::fs::rename(&tmp, path))
+            .map_err(|e| format!("Failed to persist memory graph: {}", e))
+    }
+}
+
+/// The graph stored at `path`, or an empty one if there is no file yet or it
+/// had to be moved aside.
+fn load(path: &Path) -> std::io::Result<KnowledgeGraph> {
+    let json = match std::fs::read_to_string(path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(KnowledgeGraph::default()),
+        Err(e) => return Err(e),
+    };
+    match serde_json::from_str(&json) {
+        Ok(graph) => Ok(graph),
+        Err(e) => {
+            let mut aside = path.as_os_str().to_owned();
+            aside.push(".corrupt");
+            std::fs::rename(path, &aside)?;
+            tracing::warn!(
+                "Invalid memory graph {} ({}); moved it to {}",
+                path.display(),
+                e,
+                Path::new(&aside).display()
+            );
+            Ok(KnowledgeGraph::default())
+        }
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entity(name: &str, entity_type: &str, observations: &[&str]) -> Entity {
+        Entity {
+            name: name.to_string(),
+            entity_type: entity_type.to_string(),
+            observations: observations.iter().map(|o| o.to_string()).collect(),
+            updated_at: None,
+        }
+    }
+
+    fn relation(from: &str, to: &str, relation_type: &str) -> Relation {
+        Relation {
+            from: from.to_string(),
+            to: to.to_string(),
+            relation_type: relation_type.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_create_entities_skips_existing_names() {
+        let store = MemoryStore::new(None);
+        store
+            .create_entities(vec![entity("alice", "person", &[])])
+            .unwrap();
+
+        let created = store
+            .create_entities(vec![
+                entity("alice", "robot", &[]),
+                entity("bob", "person", &[]),
+            ])
+            .unwrap();
+
+        assert_eq!(created.len(), 1);
+        assert_eq!(created[0].name, "bob");
+        assert_eq!(store.graph().entities[0].entity_type, "person");
+    }
+
+    #[test]
+    fn test_create_relations_requires_known_entities() {
+        let store = MemoryStore::new(None);
+        store
+            .create_entities(vec![entity("alice", "person", &[])])
+            .unwrap();
+
+        let err = store
+            .create_relations(vec![relation("alice", "carol", "knows")])
+            .unwrap_err();
+        assert_eq!(err, "Unknown entities: carol");
+    }
+
+    #[test]
+    fn test_search_matches_observations_and_keeps_inner_relations() {
+        let store = MemoryStore::new(None);
+        store
+            .create_entities(vec![
+                entity("alice", "person", &["Works on the Rust compiler"]),
+                entity("bob", "person", &["rust enthusiast"]),
+                entity("carol", "person", &["Prefers Go"]),
+            ])
+            .unwrap();
+        store
+            .create_relations(vec![
+                relation("alice", "bob", "mentors"),
+                relation("alice", "carol", "knows"),
+            ])
+            .unwrap();
+
+        let found = store.search("RUST");
+        let names: Vec<&str> = found.entities.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, vec!["alice", "bob"]);
+        assert_eq!(found.relations, vec![relation("alice", "bob", "mentors")]);
+    }
+
+    #[test]
+    fn test_graph_is_persisted_and_reloaded() {
+        let path = std::env::temp_dir().join(format!("mcp-memory-{}.json", uuid::Uuid::new_v4()));
+        let store = MemoryStore::new(Some(path.clone()));
+        store
+            .create_entities(vec![entity("alice", "person", &["likes tea"])])
+            .unwrap();
+
+        let reloaded = MemoryStore::new(Some(path.clone()));
+        let entities = reloaded.graph().entities;
+        assert_eq!(entities.len(), 1);
+        assert_eq!(entities[0].observations, vec!["likes tea"]);
+        assert!(entities[0].updated_at.is_some());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_new_relations_refresh_their_entities() {
+        let store = MemoryStore::new(None);
+        store
+            .create_entities(vec![
+                entity("alice", "person", &[]),
+                entity("bob", "person", &[]),
+                entity("carol", "person", &[]),
+            ])
+            .unwrap();
+        for entity in &mut store.graph.lock().unwrap().entities {
+            entity.updated_at = Some(unix_now() - 7200);
+        }
+
+        store
+            .create_relations(vec![relation("alice", "bob", "knows")])
+            .unwrap();
+
+        let removed = store.prune_older_than(Duration::from_secs(3600)).unwrap();
+        assert_eq!(removed, (1, 0));
+        let names: Vec<String> = store.graph().entities.into_iter().map(|e| e.name).collect();
+        assert_eq!(names, vec!["alice", "bob"]);
+    }
+
+    #[test]
+    fn test_corrupt_file_is_moved_aside() {
+        let path = std::env::temp_dir().join(format!("mcp-memory-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "{not json").unwrap();
+
+        let store = MemoryStore::new(Some(path.clone()));
+        assert!(store.graph().entities.is_empty());
+        assert!(!store.is_read_only());
+
+        let aside = path.with_extension("json.corrupt");
+        assert_eq!(std::fs::read_to_string(&aside).unwrap(), "{not json");
+        std::fs::remove_file(aside).unwrap();
+    }
+
+    #[test]
+    fn test_unreadable_file_stays_read_only() {
+        let dir = std::env::temp_dir().join(format!("mcp-memory-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir(&dir).unwrap();
+
+        let store = MemoryStore::new(Some(dir.clone()));
+        assert!(store.is_read_only());
+        store.set_read_only(false);
+        assert!(store.is_read_only());
+        assert!(store
+            .create_entities(vec![entity("alice", "person", &[])])
+            .is_err());
+        assert!(dir.is_dir());
+        std::fs::remove_dir(dir).unwrap();
+    }
+
+    #[test]
+    fn test_failed_persist_leaves_graph_unchanged() {
+        let dir = std::env::temp_dir().join(format!("mcp-memory-{}", uuid::Uuid::new_v4()));
+        let store = MemoryStore::new(Some(dir.join("graph.json")));
+
+        assert!(store
+            .create_entities(vec![entity("alice", "person", &[])])
+            .is_err());
+        assert!(store.graph().entities.is_empty());
+    }
+
+    #[test]
+    fn test_prune_removes_expired_entities_and_their_relations() {
+        let store = MemoryStore::new(None);
+        store
+            .create_entities(vec![
+                entity("alice", "person", &[]),
+                entity("bob", "person", &[]),
+            ])
+            .unwrap();
+        store
+            .create_relations(vec![relation("alice", "bob", "knows")])
+            .unwrap();
+        store.graph.lock().unwrap().entities[0].updated_at = Some(unix_now() - 7200);
+
+        let removed = store.prune_older_than(Duration::from_secs(3600)).unwrap();
+
+        assert_eq!(removed, (1, 1));
+        let graph = store.graph();
+        assert_eq!(graph.entities.len(), 1);
+        assert_eq!(graph.entities[0].name, "bob");
+        assert!(graph.relations.is_empty());
+    }
+}
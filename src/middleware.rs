@@ -0,0 +1,147 @@
+//! Hooks around every request the server answers, for logging, auth,
+//! metrics or argument rewriting without touching the dispatch code. Added
+//! with [`McpServer::add_interceptor`](crate::McpServer::add_interceptor),
+//! interceptors see each request on the way in, in the order they were
+//! added, and its response on the way out, in reverse order.
+
+use async_trait::async_trait;
+
+use crate::protocol::{McpRequest, McpResponse};
+use crate::tools::ToolContext;
+
+#[async_trait]
+pub trait RequestInterceptor: Send + Sync {
+    /// Runs before the request is handled, and may rewrite it, such as the
+    /// `arguments` of a `tools/call`. Returning a response answers the
+    /// request with it instead: later interceptors and the handler are
+    /// skipped. Notifications pass through here too, but a response to one
+    /// is never sent.
+    async fn before(
+        &self,
+        _request: &mut McpRequest,
+        _context: &ToolContext,
+    ) -> Option<McpResponse> {
+        None
+    }
+
+    /// Runs once the request has a response, which it may change. Only
+    /// interceptors whose `before` ran see it, including the one that
+    /// answered the request. Notifications, and requests cancelled while
+    /// running, have no response and never reach here.
+    async fn after(
+        &self,
+        _request: &McpRequest,
+        _response: &mut McpResponse,
+        _context: &ToolContext,
+    ) {
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::McpServer;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    /// Refuses `tools/call` without a `token` argument, and strips the token
+    /// from the arguments the tool sees.
+    struct Auth;
+
+    #[async_trait]
+    impl RequestInterceptor for Auth {
+        async fn before(
+            &self,
+            request: &mut McpRequest,
+            _context: &ToolContext,
+        ) -> Option<McpResponse> {
+            if request.method != "tools/call" {
+                return None;
+            }
+            let params = request.params.as_mut()?;
+            match params["arguments"].as_object_mut()?.remove("token") {
+                Some(token) if token == "secret" => None,
+                _ => Some(McpResponse::error(
+                    request.id.clone(),
+                    -32001,
+                    "Unauthorized",
+                )),
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct Recorder {
+        seen: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl RequestInterceptor for Arc<Recorder> {
+        async fn before(
+            &self,
+            request: &mut McpRequest,
+            _context: &ToolContext,
+        ) -> Option<McpResponse> {
+            self.seen
+                .lock()
+                .unwrap()
+                .push(format!("> {}", request.method));
+            None
+        }
+
+        async fn after(
+            &self,
+            request: &McpRequest,
+            response: &mut McpResponse,
+            _context: &ToolContext,
+        ) {
+            let outcome = if response.error.is_some() {
+                "error"
+            } else {
+                "ok"
+            };
+            self.seen
+                .lock()
+                .unwrap()
+                .push(format!("< {} {}", request.method, outcome));
+            if let Some(result) = response.result.as_mut() {
+                result["_meta"]["recorded"] = json!(true);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interceptors_wrap_requests_in_order() {
+        let recorder = Arc::new(Recorder::default());
+        let mut server = McpServer::new();
+        server.add_interceptor(recorder.clone());
+        server.add_interceptor(Auth);
+        let call = |id: u64, arguments: serde_json::Value| {
+            let request = json!({
+                "jsonrpc": "2.0", "id": id, "method": "tools/call",
+                "params": {"name": "echo", "arguments": arguments}
+            });
+            server.handle_request(serde_json::from_value(request).unwrap())
+        };
+
+        let refused = call(1, json!({"text": "hi"})).await.unwrap();
+        assert_eq!(refused.error.unwrap().code, -32001);
+        let allowed = call(2, json!({"text": "hi", "token": "secret"}))
+            .await
+            .unwrap()
+            .result
+            .unwrap();
+        assert_eq!(allowed["content"][0]["text"], "Echo: hi");
+        assert_eq!(allowed["_meta"]["recorded"], true);
+
+        assert_eq!(
+            *recorder.seen.lock().unwrap(),
+            [
+                "> tools/call",
+                "< tools/call error",
+                "> tools/call",
+                "< tools/call ok"
+            ]
+        );
+    }
+}
@@ -0,0 +1,315 @@
+//! Publishes bus events to a NATS server over its text protocol, one subject
+//! per event kind.
+
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use crate::events::{Event, EventBus, EventKind};
+
+pub const DEFAULT_PORT: u16 = 4222;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NatsConfig {
+    /// `host:port` of the server.
+    pub addr: String,
+    pub user: Option<String>,
+    pub pass: Option<String>,
+    /// Subject for each published kind; kinds not listed are not published.
+    pub subjects: HashMap<EventKind, String>,
+}
+
+impl NatsConfig {
+    /// Parses `nats://[user:pass@]host[:port]` and gives every event kind the
+    /// subject `mcp.<kind>`.
+    pub fn new(url: &str) -> Result<Self, String> {
+        let rest = url
+            .strip_prefix("nats://")
+            .ok_or_else(|| format!("Unsupported NATS URL '{}'; expected nats://", url))?;
+        let rest = rest.trim_end_matches('/');
+        let (credentials, host) = match rest.rsplit_once('@') {
+            Some((credentials, host)) => (Some(credentials), host),
+            None => (None, rest),
+        };
+        if host.is_empty() {
+            return Err(format!("NATS URL '{}' has no host", url));
+        }
+        let addr = if host.contains(':') {
+            host.to_string()
+        } else {
+            format!("{}:{}", host, DEFAULT_PORT)
+        };
+        let (user, pass) = match credentials.map(|c| c.split_once(':').unwrap_or((c, ""))) {
+            Some((user, pass)) => (Some(user.to_string()), Some(pass.to_string())),
+            None => (None, None),
+        };
+        let subjects = EventKind::ALL
+            .into_iter()
+            .map(|kind| (kind, format!("mcp.{}", kind_name(kind))))
+            .collect();
+        Ok(Self {
+            addr,
+            user,
+            pass,
+            subjects,
+        })
+    }
+
+    /// Applies `kind=subject` overrides separated by commas. An empty
+    /// subject stops that kind from being published.
+    pub fn with_subjects(mut self, spec: &str) -> Result<Self, String> {
+        for entry in spec.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (kind, subject) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Expected kind=subject, got '{}'", entry))?;
+            let kind: EventKind = serde_json::from_value(serde_json::json!(kind.trim()))
+                .map_err(|_| format!("Unknown event kind '{}'", kind.trim()))?;
+            match subject.trim() {
+                "" => self.subjects.remove(&kind),
+                subject if subject.contains(char::is_whitespace) => {
+                    return Err(format!("Invalid NATS subject '{}'", subject))
+                }
+                subject => self.subjects.insert(kind, subject.to_string()),
+            };
+        }
+        Ok(self)
+    }
+
+    /// Configuration from `MCP_NATS_URL` and `MCP_NATS_SUBJECTS`, or `None`
+    /// when no URL is set. Invalid settings are reported and ignored.
+    pub fn from_env() -> Option<Self> {
+        let url = std::env::var("MCP_NATS_URL").ok()?;
+        let subjects = std::env::var("MCP_NATS_SUBJECTS").unwrap_or_default();
+        Self::new(&url)
+            .and_then(|config| config.with_subjects(&subjects))
+            .map_err(|e| tracing::warn!("Ignoring NATS settings: {}", e))
+            .ok()
+    }
+}
+
+fn kind_name(kind: EventKind) -> String {
+    serde_json::to_value(kind)
+        .unwrap()
+        .as_str()
+        .unwrap_or_default()
+        .to_string()
+}
+
+struct Connection {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+    /// Bytes of a line not yet terminated; kept here so a read cancelled by
+    /// `select!` loses nothing.
+    partial: Vec<u8>,
+}
+
+impl Connection {
+    /// Connects and completes the handshake; the server's `PONG` to our
+    /// `PING` confirms it accepted `CONNECT`.
+    async fn open(config: &NatsConfig) -> Result<Self, String> {
+        let stream = tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(&config.addr))
+            .await
+            .map_err(|_| "Connection timed out".to_string())?
+            .map_err(|e| e.to_string())?;
+        let (reader, writer) = stream.into_split();
+        let mut connection = Self {
+            reader: BufReader::new(reader),
+            writer,
+            partial: Vec::new(),
+        };
+
+        let info = connection.read_line().await?;
+        if !info.starts_with("INFO") {
+            return Err(format!("Expected INFO, got '{}'", info));
+        }
+        let mut options = serde_json::json!({
+            "verbose": false,
+            "pedantic": false,
+            "name": "mcp-server",
+            "lang": "rust",
+            "version": env!("CARGO_PKG_VERSION"),
+        });
+        if let Some(user) = &config.user {
+            options["user"] = user.clone().into();
+            options["pass"] = config.pass.clone().unwrap_or_default().into();
+        }
+        connection
+            .write(format!("CONNECT {}\r\nPING\r\n", options).as_bytes())
+            .await?;
+        loop {
+            match connection.read_line().await?.as_str() {
+                "PONG" => return Ok(connection),
+                "PING" => connection.write(b"PONG\r\n").await?,
+                line if line.starts_with("-ERR") => return Err(line.to_string()),
+                _ => {}
+            }
+        }
+    }
+
+    async fn read_line(&mut self) -> Result<String, String> {
+        match self.reader.read_until(b'\n', &mut self.partial).await {
+            Ok(0) => Err("Connection closed by server".to_string()),
+            Ok(_) => {
+                let line = String::from_utf8_lossy(&self.partial)
+                    .trim_end()
+                    .to_string();
+                self.partial.clear();
+                Ok(line)
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> Result<(), String> {
+        self.writer
+            .write_all(bytes)
+            .await
+            .map_err(|e| e.to_string())?;
+        self.writer.flush().await.map_err(|e| e.to_string())
+    }
+
+    async fn publish(&mut self, subject: &str, event: &Event) -> Result<(), String> {
+        let payload = serde_json::to_vec(event).unwrap();
+        let mut frame = format!("PUB {} {}\r\n", subject, payload.len()).into_bytes();
+        frame.extend_from_slice(&payload);
+        frame.extend_from_slice(b"\r\n");
+        self.write(&frame).await
+    }
+}
+
+/// Publishes every event with a configured subject. The connection is
+/// opened on the first event and reopened after a failure; events that
+/// arrive while the server is unreachable are dropped and reported.
+pub fn spawn(events: &EventBus, config: NatsConfig) -> JoinHandle<()> {
+    let mut receiver = events.subscribe();
+    tokio::spawn(async move {
+        let mut connection: Option<Connection> = None;
+        loop {
+            let event = tokio::select! {
+                event = receiver.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        tracing::warn!("NATS publisher fell behind; {} events not published", skipped);
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+                // Answer the server's keep-alive pings while idle.
+                line = async { connection.as_mut().unwrap().read_line().await },
+                    if connection.is_some() =>
+                {
+                    let ok = match line {
+                        Ok(line) if line == "PING" => {
+                            connection.as_mut().unwrap().write(b"PONG\r\n").await.is_ok()
+                        }
+                        Ok(line) => !line.starts_with("-ERR"),
+                        Err(_) => false,
+                    };
+                    if !ok {
+                        connection = None;
+                    }
+                    continue;
+                }
+            };
+            let Some(subject) = config.subjects.get(&event.kind) else {
+                continue;
+            };
+            if connection.is_none() {
+                match Connection::open(&config).await {
+                    Ok(opened) => connection = Some(opened),
+                    Err(e) => {
+                        tracing::warn!("NATS connection to {} failed: {}", config.addr, e);
+                        continue;
+                    }
+                }
+            }
+            if let Err(e) = connection.as_mut().unwrap().publish(subject, &event).await {
+                tracing::warn!("NATS publish to {} failed: {}", subject, e);
+                connection = None;
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_config_from_url_and_overrides() {
+        let config = NatsConfig::new("nats://alice:pw@queue.internal")
+            .unwrap()
+            .with_subjects("job_finished=jobs.done, approval_requested=")
+            .unwrap();
+        assert_eq!(config.addr, "queue.internal:4222");
+        assert_eq!(config.user.as_deref(), Some("alice"));
+        assert_eq!(config.pass.as_deref(), Some("pw"));
+        assert_eq!(config.subjects[&EventKind::JobFinished], "jobs.done");
+        assert_eq!(
+            config.subjects[&EventKind::ToolCallCompleted],
+            "mcp.tool_call_completed"
+        );
+        assert!(!config.subjects.contains_key(&EventKind::ApprovalRequested));
+
+        assert!(NatsConfig::new("http://x").is_err());
+        assert!(NatsConfig::new("nats://x")
+            .unwrap()
+            .with_subjects("nope=x")
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_publishes_events_to_subjects() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let config = NatsConfig::new(&format!("nats://{}", listener.local_addr().unwrap()))
+            .unwrap()
+            .with_subjects("job_finished=")
+            .unwrap();
+        let bus = EventBus::new();
+        spawn(&bus, config);
+
+        bus.publish(EventKind::JobFinished, serde_json::json!({}));
+        bus.publish(
+            EventKind::ToolCallCompleted,
+            serde_json::json!({"tool": "echo"}),
+        );
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+        writer
+            .write_all(b"INFO {\"server_id\":\"test\"}\r\n")
+            .await
+            .unwrap();
+        let mut line = String::new();
+        reader.read_line(&mut line).await.unwrap();
+        assert!(line.starts_with("CONNECT {"));
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        assert_eq!(line, "PING\r\n");
+        writer.write_all(b"PONG\r\n").await.unwrap();
+
+        line.clear();
+        reader.read_line(&mut line).await.unwrap();
+        let (subject, size) = line
+            .strip_prefix("PUB ")
+            .unwrap()
+            .trim_end()
+            .split_once(' ')
+            .unwrap();
+        assert_eq!(subject, "mcp.tool_call_completed");
+        let mut payload = vec![0; size.parse::<usize>().unwrap() + 2];
+        reader.read_exact(&mut payload).await.unwrap();
+        let event: serde_json::Value =
+            serde_json::from_slice(&payload[..payload.len() - 2]).unwrap();
+        assert_eq!(event["kind"], "tool_call_completed");
+        assert_eq!(event["data"]["tool"], "echo");
+    }
+}
@@ -0,0 +1,215 @@
+//! Load shedding. Once too many requests are in flight, or the process uses
+//! more memory than its watermark, new `tools/call` requests are refused at
+//! once with an `overloaded` error that says when to retry. Pings, listings
+//! and calls already running carry on, so clients can tell a busy server
+//! from a dead one instead of watching every request slow down.
+
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::protocol::McpResponse;
+
+/// JSON-RPC error code of a shed request.
+pub const OVERLOADED: i32 = -32003;
+
+/// Used when `MCP_SHED_RETRY_AFTER_SECS` is unset.
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+/// Why a call was shed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overload {
+    /// `in_flight` requests were running, against a limit of `limit`.
+    InFlight { in_flight: usize, limit: usize },
+    /// The process held `bytes` of memory, over the `limit` watermark.
+    Memory { bytes: u64, limit: u64 },
+}
+
+/// The limits, and the count of requests in flight they are checked
+/// against. Both limits are off by default.
+pub struct LoadShedder {
+    /// `0` for no limit, here and in `max_memory`.
+    max_in_flight: AtomicUsize,
+    max_memory: AtomicU64,
+    retry_after_secs: AtomicU64,
+    in_flight: AtomicUsize,
+    shed: AtomicU64,
+}
+
+impl Default for LoadShedder {
+    fn default() -> Self {
+        Self {
+            max_in_flight: AtomicUsize::new(0),
+            max_memory: AtomicU64::new(0),
+            retry_after_secs: AtomicU64::new(DEFAULT_RETRY_AFTER.as_secs()),
+            in_flight: AtomicUsize::new(0),
+            shed: AtomicU64::new(0),
+        }
+    }
+}
+
+impl LoadShedder {
+    /// Limits from `MCP_SHED_MAX_IN_FLIGHT` and `MCP_SHED_MAX_MEMORY_MB`,
+    /// and the advice from `MCP_SHED_RETRY_AFTER_SECS`. Malformed values
+    /// are reported and ignored.
+    pub fn from_env() -> Self {
+        let shedder = Self::default();
+        let var = |name: &str| -> Option<u64> {
+            let value = std::env::var(name).ok()?;
+            match value.trim().parse() {
+                Ok(n) => Some(n),
+                Err(_) => {
+                    tracing::warn!("Invalid {} '{}'", name, value);
+                    None
+                }
+            }
+        };
+        if let Some(limit) = var("MCP_SHED_MAX_IN_FLIGHT") {
+            shedder.set_max_in_flight((limit > 0).then_some(limit as usize));
+        }
+        if let Some(mb) = var("MCP_SHED_MAX_MEMORY_MB") {
+            match mb.checked_mul(1024 * 1024) {
+                Some(bytes) => shedder.set_max_memory((bytes > 0).then_some(bytes)),
+                None => tracing::warn!("Invalid MCP_SHED_MAX_MEMORY_MB '{}'", mb),
+            }
+        }
+        if let Some(secs) = var("MCP_SHED_RETRY_AFTER_SECS") {
+            shedder.set_retry_after(Duration::from_secs(secs));
+        }
+        shedder
+    }
+
+    /// Most requests handled at once before calls are shed; `None` for no
+    /// limit.
+    pub fn set_max_in_flight(&self, limit: Option<usize>) {
+        self.max_in_flight
+            .store(limit.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// Resident memory, in bytes, past which calls are shed; `None` for no
+    /// watermark.
+    pub fn set_max_memory(&self, bytes: Option<u64>) {
+        self.max_memory.store(bytes.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    /// How long shed clients are told to wait before retrying.
+    pub fn set_retry_after(&self, delay: Duration) {
+        self.retry_after_secs
+            .store(delay.as_secs().max(1), Ordering::Relaxed);
+    }
+
+    /// Requests being handled now.
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::Relaxed)
+    }
+
+    /// Calls shed since start-up.
+    pub fn shed(&self) -> u64 {
+        self.shed.load(Ordering::Relaxed)
+    }
+
+    /// Counts a request as in flight until the guard is dropped.
+    pub(crate) fn enter(&self) -> InFlight<'_> {
+        self.in_flight.fetch_add(1, Ordering::Relaxed);
+        InFlight(self)
+    }
+
+    /// Whether a new call, itself already [entered](Self::enter), should be
+    /// shed.
+    pub fn check(&self) -> Result<(), Overload> {
+        let limit = self.max_in_flight.load(Ordering::Relaxed);
+        let in_flight = self.in_flight();
+        if limit > 0 && in_flight > limit {
+            return Err(Overload::InFlight { in_flight, limit });
+        }
+        let limit = self.max_memory.load(Ordering::Relaxed);
+        if limit > 0 {
+            if let Some(bytes) = resident_memory().filter(|&bytes| bytes > limit) {
+                return Err(Overload::Memory { bytes, limit });
+            }
+        }
+        Ok(())
+    }
+
+    /// The error answering a shed call, counting it.
+    pub(crate) fn refuse(&self, id: Option<Value>, overload: Overload) -> McpResponse {
+        self.shed.fetch_add(1, Ordering::Relaxed);
+        let retry_after = self.retry_after_secs.load(Ordering::Relaxed);
+        let (reason, mut data) = match overload {
+            Overload::InFlight { in_flight, limit } => (
+                format!("{} requests in flight, limit {}", in_flight, limit),
+                json!({"reason": "inFlight", "inFlight": in_flight, "limit": limit}),
+            ),
+            Overload::Memory { bytes, limit } => (
+                format!("using {} MB, limit {} MB", bytes >> 20, limit >> 20),
+                json!({"reason": "memory", "bytes": bytes, "limit": limit}),
+            ),
+        };
+        data["retryAfterSecs"] = json!(retry_after);
+        McpResponse::error(
+            id,
+            OVERLOADED,
+            format!(
+                "Server overloaded ({}); retry after {}s",
+                reason, retry_after
+            ),
+        )
+        .with_data(data)
+    }
+}
+
+pub(crate) struct InFlight<'a>(&'a LoadShedder);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.in_flight.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The process's resident memory in bytes, where the platform reports it.
+pub fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|l| l.starts_with("VmRSS:"))?;
+    let kb: u64 = line
+        .trim_start_matches("VmRSS:")
+        .trim()
+        .trim_end_matches("kB")
+        .trim()
+        .parse()
+        .ok()?;
+    Some(kb * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_calls_over_the_limits_are_shed() {
+        let shedder = LoadShedder::default();
+        shedder.set_max_in_flight(Some(1));
+        let first = shedder.enter();
+        assert_eq!(shedder.check(), Ok(()));
+        let second = shedder.enter();
+        assert_eq!(
+            shedder.check(),
+            Err(Overload::InFlight {
+                in_flight: 2,
+                limit: 1
+            })
+        );
+        let refused = shedder.refuse(Some(7.into()), shedder.check().unwrap_err());
+        let error = refused.error.unwrap();
+        assert_eq!(error.code, OVERLOADED);
+        assert_eq!(error.data.unwrap()["retryAfterSecs"], 5);
+        assert_eq!(shedder.shed(), 1);
+        drop((first, second));
+        assert_eq!(shedder.in_flight(), 0);
+
+        if resident_memory().is_some() {
+            shedder.set_max_memory(Some(1));
+            let _call = shedder.enter();
+            assert!(matches!(shedder.check(), Err(Overload::Memory { .. })));
+        }
+    }
+}
@@ -0,0 +1,67 @@
+//! Cursor-based pagination for `tools/list`, `resources/list` and
+//! `prompts/list`. A cursor is opaque to clients; it encodes the offset of
+//! the next page.
+
+use serde_json::Value;
+
+/// Entries per page unless `MCP_PAGE_SIZE` says otherwise.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+pub fn page_size_from_env() -> usize {
+    std::env::var("MCP_PAGE_SIZE")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&size| size > 0)
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+}
+
+fn encode(offset: usize) -> String {
+    crate::base64::encode(format!("offset:{}", offset).as_bytes())
+}
+
+fn decode(cursor: &str) -> Option<usize> {
+    let text = String::from_utf8(crate::base64::decode(cursor)?).ok()?;
+    text.strip_prefix("offset:")?.parse().ok()
+}
+
+/// The page of `items` that `params.cursor` asks for, or the first page,
+/// and the cursor of the page after it, if any. The error is the message
+/// for a `-32602` reply.
+pub fn paginate<'a, T>(
+    items: &'a [T],
+    params: Option<&Value>,
+    page_size: usize,
+) -> Result<(&'a [T], Option<String>), &'static str> {
+    let offset = match params.and_then(|p| p.get("cursor")) {
+        None | Some(Value::Null) => 0,
+        Some(Value::String(cursor)) => decode(cursor).ok_or("Invalid cursor")?,
+        Some(_) => return Err("Invalid cursor"),
+    };
+    let end = offset.saturating_add(page_size);
+    let next = (end < items.len()).then(|| encode(end));
+    let start = offset.min(items.len());
+    Ok((&items[start..end.min(items.len())], next))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_pages_follow_cursors() {
+        let items: Vec<u32> = (0..5).collect();
+        let (first, next) = paginate(&items, None, 2).unwrap();
+        assert_eq!(first, [0, 1]);
+        let params = json!({ "cursor": next.unwrap() });
+        let (second, next) = paginate(&items, Some(&params), 2).unwrap();
+        assert_eq!(second, [2, 3]);
+        let params = json!({ "cursor": next.unwrap() });
+        let (last, next) = paginate(&items, Some(&params), 2).unwrap();
+        assert_eq!((last, next), (&[4][..], None));
+
+        assert_eq!(paginate(&items, None, 5).unwrap().1, None);
+        let bad = json!({ "cursor": "bm9wZQ==" });
+        assert!(paginate(&items, Some(&bad), 2).is_err());
+    }
+}
@@ -0,0 +1,480 @@
+//! Tools distributed as scripts rather than Rust, for authors who would
+//! rather write Python or JavaScript. Each plugin lives in its own
+//! directory under `MCP_PLUGINS_DIR`:
+//!
+//! ```text
+//! plugins/csv_stats/plugin.json   the registry entry it was installed from
+//! plugins/csv_stats/main.py       the script
+//! ```
+//!
+//! A call runs the script with the arguments as JSON on stdin; its stdout
+//! and stderr become the result, and a non-zero exit makes it an error.
+//! A plugin whose entry is marked `lazy` is offered from its manifest
+//! alone, and its script is read and checked on first use.
+//!
+//! With the `plugins` feature, `mcp-server plugins` installs them from a
+//! registry: `MCP_PLUGIN_REGISTRY_URL` names a JSON index, signed with the
+//! Ed25519 key in `MCP_PLUGIN_REGISTRY_PUBLIC_KEY`, that gives each
+//! plugin's script URL and SHA-256.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::crypto;
+use crate::protocol::{Tool, ToolMeta};
+use crate::script;
+use crate::tools::args::Arguments;
+use crate::tools::lazy::LazyTool;
+use crate::tools::{ToolContext, ToolError, ToolHandler, ToolResult};
+
+/// Name of each plugin's record of what was installed.
+pub const MANIFEST: &str = "plugin.json";
+
+/// Backstop for a script that never exits; the server's tool timeout
+/// normally ends the call well before.
+const SCRIPT_TIMEOUT: Duration = Duration::from_secs(3600);
+
+/// Languages a plugin can be written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Runtime {
+    Python,
+    Node,
+}
+
+impl Runtime {
+    fn script(self) -> &'static str {
+        match self {
+            Runtime::Python => "main.py",
+            Runtime::Node => "main.js",
+        }
+    }
+
+    fn interpreter(self) -> PathBuf {
+        match self {
+            Runtime::Python => crate::tools::python::interpreter(),
+            Runtime::Node => {
+                PathBuf::from(std::env::var("MCP_NODE").unwrap_or_else(|_| "node".to_string()))
+            }
+        }
+    }
+}
+
+/// A plugin as the registry index lists it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PluginEntry {
+    /// Also the tool name, so letters, digits, `_` and `-` only.
+    pub name: String,
+    pub version: String,
+    pub description: String,
+    /// Anything but `python` or `node`, such as `wasm`, can't be run by
+    /// this server and is refused.
+    pub runtime: String,
+    pub url: String,
+    /// Hex SHA-256 of the script.
+    pub sha256: String,
+    #[serde(default = "empty_schema")]
+    pub input_schema: Value,
+    /// Load the script on first use rather than at startup.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub lazy: bool,
+}
+
+fn empty_schema() -> Value {
+    serde_json::json!({"type": "object"})
+}
+
+impl PluginEntry {
+    fn runtime(&self) -> Result<Runtime, String> {
+        serde_json::from_value(Value::String(self.runtime.clone())).map_err(|_| {
+            format!(
+                "Plugin '{}' needs the {} runtime, which this server does not support",
+                self.name, self.runtime
+            )
+        })
+    }
+
+    fn check_name(&self) -> Result<(), String> {
+        let valid = !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if valid {
+            Ok(())
+        } else {
+            Err(format!("Invalid plugin name '{}'", self.name))
+        }
+    }
+
+    fn definition(&self) -> Tool {
+        Tool {
+            name: self.name.clone(),
+            description: self.description.clone(),
+            input_schema: self.input_schema.clone(),
+            output_schema: None,
+            annotations: None,
+            meta: ToolMeta::default(),
+        }
+    }
+}
+
+/// `MCP_PLUGINS_DIR`, when set.
+pub fn dir_from_env() -> Option<PathBuf> {
+    std::env::var("MCP_PLUGINS_DIR").ok().map(PathBuf::from)
+}
+
+/// The plugins installed in `dir`, as tools. A plugin that can't be loaded,
+/// including one whose script no longer matches its hash, is reported and
+/// skipped; a lazy one fails its first call instead.
+pub fn load(dir: &Path) -> Vec<(Tool, Arc<dyn ToolHandler>)> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut plugins = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if !path.join(MANIFEST).exists() {
+            continue;
+        }
+        match load_one(&path) {
+            Ok(plugin) => plugins.push(plugin),
+            Err(e) => tracing::warn!("Skipping plugin {}: {}", path.display(), e),
+        }
+    }
+    plugins.sort_by(|a, b| a.0.name.cmp(&b.0.name));
+    plugins
+}
+
+fn load_one(path: &Path) -> Result<(Tool, Arc<dyn ToolHandler>), String> {
+    let entry = installed(path)?;
+    entry.check_name()?;
+    let runtime = entry.runtime()?;
+    let script = path.join(runtime.script());
+    if !entry.lazy {
+        let handler = verified(&entry, runtime, script)?;
+        return Ok((entry.definition(), Arc::new(handler)));
+    }
+    let definition = entry.definition();
+    let handler = LazyTool::new(&definition.name, move || {
+        let (entry, script) = (entry.clone(), script.clone());
+        async move {
+            tokio::task::spawn_blocking(move || verified(&entry, runtime, script))
+                .await
+                .map_err(|e| e.to_string())?
+        }
+    });
+    Ok((definition, Arc::new(handler)))
+}
+
+/// The handler running `script`, once it matches the entry's hash.
+fn verified(
+    entry: &PluginEntry,
+    runtime: Runtime,
+    script: PathBuf,
+) -> Result<ScriptPluginTool, String> {
+    let bytes = std::fs::read(&script).map_err(|e| e.to_string())?;
+    check_hash(entry, &bytes)?;
+    Ok(ScriptPluginTool { runtime, script })
+}
+
+/// The entry a plugin directory was installed from.
+pub fn installed(path: &Path) -> Result<PluginEntry, String> {
+    let text = std::fs::read_to_string(path.join(MANIFEST)).map_err(|e| e.to_string())?;
+    serde_json::from_str(&text).map_err(|e| e.to_string())
+}
+
+fn check_hash(entry: &PluginEntry, bytes: &[u8]) -> Result<(), String> {
+    let actual = crypto::hex(&crypto::sha256(bytes));
+    if crypto::constant_time_eq(actual.as_bytes(), entry.sha256.to_lowercase().as_bytes()) {
+        Ok(())
+    } else {
+        Err(format!(
+            "SHA-256 of plugin '{}' is {}, not {}",
+            entry.name, actual, entry.sha256
+        ))
+    }
+}
+
+/// Runs an installed plugin's script.
+pub struct ScriptPluginTool {
+    runtime: Runtime,
+    script: PathBuf,
+}
+
+#[async_trait]
+impl ToolHandler for ScriptPluginTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        self.run(&args).await
+    }
+
+    /// The arguments are only written to the script, so they are never
+    /// copied.
+    async fn call_shared(
+        &self,
+        args: Arguments,
+        _context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        self.run(&args).await
+    }
+}
+
+impl ScriptPluginTool {
+    async fn run(&self, args: &Value) -> Result<ToolResult, ToolError> {
+        let mut command = Command::new(self.runtime.interpreter());
+        command.arg(&self.script);
+        if let Some(dir) = self.script.parent() {
+            command.current_dir(dir);
+        }
+        let output = script::run_with_input(command, &args.to_string(), SCRIPT_TIMEOUT)
+            .await
+            .map_err(ToolError::Execution)?;
+        let mut content = script::output_content(&output);
+        if content.is_empty() {
+            content.push(crate::tools::content::text(""));
+        }
+        let mut result = ToolResult::new(content);
+        result.is_error = !output.status.success();
+        Ok(result)
+    }
+}
+
+#[cfg(feature = "plugins")]
+pub use registry::{install, Registry};
+
+#[cfg(feature = "plugins")]
+mod registry {
+    use ed25519_dalek::{Signature, VerifyingKey};
+    use serde::Deserialize;
+    use std::path::Path;
+    use std::time::Duration;
+    use tokio::net::TcpStream;
+
+    use super::{check_hash, PluginEntry, MANIFEST};
+    use crate::http::client::{self, Url};
+
+    const TIMEOUT: Duration = Duration::from_secs(60);
+    const MAX_INDEX_BYTES: usize = 4 * 1024 * 1024;
+    const MAX_SCRIPT_BYTES: usize = 16 * 1024 * 1024;
+
+    #[derive(Deserialize)]
+    struct Index {
+        plugins: Vec<PluginEntry>,
+    }
+
+    /// A registry's verified index.
+    #[derive(Debug, Clone)]
+    pub struct Registry {
+        pub plugins: Vec<PluginEntry>,
+    }
+
+    impl Registry {
+        /// Fetches the index at `MCP_PLUGIN_REGISTRY_URL` and its signature
+        /// at the same URL plus `.sig`, and checks it against
+        /// `MCP_PLUGIN_REGISTRY_PUBLIC_KEY`.
+        pub async fn from_env() -> Result<Self, String> {
+            let url = std::env::var("MCP_PLUGIN_REGISTRY_URL")
+                .map_err(|_| "MCP_PLUGIN_REGISTRY_URL is not set")?;
+            let key = std::env::var("MCP_PLUGIN_REGISTRY_PUBLIC_KEY")
+                .map_err(|_| "MCP_PLUGIN_REGISTRY_PUBLIC_KEY is not set")?;
+            let key = public_key(&key)?;
+            let index = fetch(&Url::parse(&url)?, MAX_INDEX_BYTES).await?;
+            let signature = fetch(&Url::parse(&format!("{}.sig", url))?, 1024).await?;
+            Self::verified(&key, &index, &String::from_utf8_lossy(&signature))
+        }
+
+        /// Parses `index` once `signature`, in base64, checks out over it.
+        pub fn verified(key: &VerifyingKey, index: &[u8], signature: &str) -> Result<Self, String> {
+            let bytes: [u8; 64] = crate::base64::decode(signature.trim())
+                .and_then(|bytes| bytes.try_into().ok())
+                .ok_or("Registry signature is not a base64 Ed25519 signature")?;
+            key.verify_strict(index, &Signature::from_bytes(&bytes))
+                .map_err(|_| "Registry index signature does not match".to_string())?;
+            let index: Index = serde_json::from_slice(index)
+                .map_err(|e| format!("Invalid registry index: {}", e))?;
+            Ok(Self {
+                plugins: index.plugins,
+            })
+        }
+
+        pub fn get(&self, name: &str) -> Option<&PluginEntry> {
+            self.plugins.iter().find(|p| p.name == name)
+        }
+    }
+
+    fn public_key(text: &str) -> Result<VerifyingKey, String> {
+        let bytes: [u8; 32] = crate::base64::decode(text.trim())
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or("MCP_PLUGIN_REGISTRY_PUBLIC_KEY must be a base64 Ed25519 public key")?;
+        VerifyingKey::from_bytes(&bytes).map_err(|e| format!("Invalid registry public key: {}", e))
+    }
+
+    async fn fetch(url: &Url, max_bytes: usize) -> Result<Vec<u8>, String> {
+        let fetch = async {
+            let stream = TcpStream::connect((url.host.as_str(), url.port))
+                .await
+                .map_err(|e| e.to_string())?;
+            client::get(stream, url, max_bytes).await
+        };
+        let fetched = tokio::time::timeout(TIMEOUT, fetch)
+            .await
+            .map_err(|_| format!("Request timed out after {}s", TIMEOUT.as_secs()))??;
+        match fetched.status {
+            200 => Ok(fetched.body),
+            status => Err(format!("GET {} returned HTTP {}", url.path, status)),
+        }
+    }
+
+    /// Downloads `entry`'s script and installs it under `dir`, replacing
+    /// any earlier version. A script whose hash doesn't match is never
+    /// written.
+    pub async fn install(dir: &Path, entry: &PluginEntry) -> Result<(), String> {
+        entry.check_name()?;
+        let runtime = entry.runtime()?;
+        let script = fetch(&Url::parse(&entry.url)?, MAX_SCRIPT_BYTES).await?;
+        check_hash(entry, &script)?;
+        write(dir, entry, runtime.script(), &script)
+    }
+
+    /// Stages the plugin in a hidden directory beside its final place, then
+    /// swaps it in, so a failed install leaves the old version untouched.
+    pub(super) fn write(
+        dir: &Path,
+        entry: &PluginEntry,
+        script_name: &str,
+        script: &[u8],
+    ) -> Result<(), String> {
+        let target = dir.join(&entry.name);
+        let staged = dir.join(format!(".{}.install", entry.name));
+        let write = || -> std::io::Result<()> {
+            std::fs::create_dir_all(dir)?;
+            let _ = std::fs::remove_dir_all(&staged);
+            std::fs::create_dir(&staged)?;
+            std::fs::write(staged.join(script_name), script)?;
+            std::fs::write(
+                staged.join(MANIFEST),
+                serde_json::to_string_pretty(entry).unwrap(),
+            )?;
+            if target.exists() {
+                std::fs::remove_dir_all(&target)?;
+            }
+            std::fs::rename(&staged, &target)
+        };
+        write().map_err(|e| {
+            let _ = std::fs::remove_dir_all(&staged);
+            format!("Failed to install plugin '{}': {}", entry.name, e)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(script: &[u8]) -> PluginEntry {
+        PluginEntry {
+            name: "shout".to_string(),
+            version: "1.0.0".to_string(),
+            description: "Upper-cases text".to_string(),
+            runtime: "python".to_string(),
+            url: "http://plugins.example.com/shout.py".to_string(),
+            sha256: crypto::hex(&crypto::sha256(script)),
+            input_schema: empty_schema(),
+            lazy: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_only_intact_plugins_load() {
+        let dir = std::env::temp_dir().join(format!("plugins-{}", uuid::Uuid::new_v4()));
+        let script = b"import json, sys\nprint(json.load(sys.stdin)['text'].upper())\n";
+        for (name, runtime, body) in [
+            ("shout", "python", &script[..]),
+            ("tampered", "python", b"print('pwned')"),
+            ("module", "wasm", b"\0asm"),
+            ("lazy", "python", b"print('pwned')"),
+        ] {
+            let plugin = dir.join(name);
+            std::fs::create_dir_all(&plugin).unwrap();
+            let mut entry = entry(script);
+            entry.name = name.to_string();
+            entry.runtime = runtime.to_string();
+            entry.lazy = name == "lazy";
+            std::fs::write(plugin.join("main.py"), body).unwrap();
+            std::fs::write(
+                plugin.join(MANIFEST),
+                serde_json::to_string(&entry).unwrap(),
+            )
+            .unwrap();
+        }
+
+        let loaded = load(&dir);
+        let names: Vec<&str> = loaded.iter().map(|(tool, _)| tool.name.as_str()).collect();
+        assert_eq!(names, ["lazy", "shout"]);
+        assert_eq!(loaded[1].0.description, "Upper-cases text");
+        // A lazy plugin is only checked once called.
+        let error = loaded[0].1.call(serde_json::json!({})).await.unwrap_err();
+        assert!(error
+            .message()
+            .starts_with("Tool 'lazy' failed to load: SHA-256 of plugin 'lazy'"));
+        assert!(load(&dir.join("missing")).is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_plugins_read_arguments_on_stdin() {
+        let dir = std::env::temp_dir().join(format!("plugins-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("main.py");
+        std::fs::write(
+            &script,
+            "import json, sys\nargs = json.load(sys.stdin)\nprint(args['text'].upper())\nsys.exit(args.get('status', 0))\n",
+        )
+        .unwrap();
+        let tool = ScriptPluginTool {
+            runtime: Runtime::Python,
+            script,
+        };
+
+        let result = tool.call(serde_json::json!({"text": "hi"})).await.unwrap();
+        assert!(!result.is_error);
+        assert_eq!(result.content[0]["text"], "HI\n");
+        let result = tool
+            .call(serde_json::json!({"text": "hi", "status": 3}))
+            .await
+            .unwrap();
+        assert!(result.is_error);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(feature = "plugins")]
+    #[test]
+    fn test_registry_index_must_be_signed() {
+        use ed25519_dalek::{Signer, SigningKey};
+
+        let signing = SigningKey::from_bytes(&[9; 32]);
+        let index =
+            serde_json::to_vec(&serde_json::json!({"plugins": [entry(b"print(1)")]})).unwrap();
+        let signature = crate::base64::encode(&signing.sign(&index).to_bytes());
+        let key = signing.verifying_key();
+
+        let registry = Registry::verified(&key, &index, &signature).unwrap();
+        assert_eq!(registry.get("shout"), Some(&entry(b"print(1)")));
+        let mut tampered = index.clone();
+        tampered.extend_from_slice(b" ");
+        assert!(Registry::verified(&key, &tampered, &signature).is_err());
+
+        let dir = std::env::temp_dir().join(format!("plugins-{}", uuid::Uuid::new_v4()));
+        registry::write(&dir, &entry(b"print(1)"), "main.py", b"print(1)").unwrap();
+        registry::write(&dir, &entry(b"print(2)"), "main.py", b"print(2)").unwrap();
+        assert_eq!(load(&dir).len(), 1);
+        assert_eq!(installed(&dir.join("shout")).unwrap(), entry(b"print(2)"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
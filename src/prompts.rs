@@ -0,0 +1,169 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// A reusable prompt. Message text may reference arguments as `{{name}}`;
+/// optional arguments that are not supplied expand to an empty string.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptTemplate {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub arguments: Vec<PromptArgument>,
+    pub messages: Vec<PromptMessageTemplate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptArgument {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub required: bool,
+    /// Suggested by `completion/complete` when no completer is registered
+    /// for the argument. Not listed.
+    #[serde(default, skip_serializing)]
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromptMessageTemplate {
+    /// `user` or `assistant`.
+    pub role: String,
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PromptFile {
+    prompts: Vec<PromptTemplate>,
+}
+
+impl PromptTemplate {
+    /// The `prompts/list` entry: everything but the messages.
+    pub fn listing(&self) -> Value {
+        let mut listing = serde_json::json!({
+            "name": self.name,
+            "arguments": self.arguments,
+        });
+        if let Some(description) = &self.description {
+            listing["description"] = serde_json::json!(description);
+        }
+        listing
+    }
+
+    /// The `prompts/get` result with `arguments` substituted into every
+    /// message.
+    pub fn render(&self, arguments: &BTreeMap<String, String>) -> Result<Value, String> {
+        let missing: Vec<&str> = self
+            .arguments
+            .iter()
+            .filter(|a| a.required && !arguments.contains_key(&a.name))
+            .map(|a| a.name.as_str())
+            .collect();
+        if !missing.is_empty() {
+            return Err(format!(
+                "Missing required arguments: {}",
+                missing.join(", ")
+            ));
+        }
+
+        let messages: Vec<Value> = self
+            .messages
+            .iter()
+            .map(|message| {
+                serde_json::json!({
+                    "role": message.role,
+                    "content": {"type": "text", "text": self.substitute(&message.text, arguments)}
+                })
+            })
+            .collect();
+        let mut result = serde_json::json!({ "messages": messages });
+        if let Some(description) = &self.description {
+            result["description"] = serde_json::json!(description);
+        }
+        Ok(result)
+    }
+
+    /// Replaces `{{name}}` for every declared argument; other braces are
+    /// left as written.
+    fn substitute(&self, text: &str, arguments: &BTreeMap<String, String>) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let Some(end) = after.find("}}") else {
+                rest = &rest[start..];
+                break;
+            };
+            let name = after[..end].trim();
+            if self.arguments.iter().any(|a| a.name == name) {
+                out.push_str(arguments.get(name).map(String::as_str).unwrap_or(""));
+            } else {
+                out.push_str(&rest[start..start + 2 + end + 2]);
+            }
+            rest = &after[end + 2..];
+        }
+        out.push_str(rest);
+        out
+    }
+}
+
+/// Reads the templates in a `{"prompts": [...]}` JSON file.
+pub fn load(path: &Path) -> Result<Vec<PromptTemplate>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let file: PromptFile = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    Ok(file.prompts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn review() -> PromptTemplate {
+        serde_json::from_value(json!({
+            "name": "code_review",
+            "description": "Review a diff",
+            "arguments": [
+                {"name": "diff", "required": true},
+                {"name": "focus"}
+            ],
+            "messages": [
+                {"role": "user", "text": "Review this diff{{ focus }}:\n{{diff}}\nKeep {{other}} as is."}
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_render_substitutes_declared_arguments() {
+        let arguments = BTreeMap::from([("diff".to_string(), "+fn main() {}".to_string())]);
+        let rendered = review().render(&arguments).unwrap();
+
+        assert_eq!(rendered["description"], "Review a diff");
+        assert_eq!(rendered["messages"][0]["role"], "user");
+        assert_eq!(
+            rendered["messages"][0]["content"]["text"],
+            "Review this diff:\n+fn main() {}\nKeep {{other}} as is."
+        );
+    }
+
+    #[test]
+    fn test_render_requires_required_arguments() {
+        assert_eq!(
+            review().render(&BTreeMap::new()).unwrap_err(),
+            "Missing required arguments: diff"
+        );
+    }
+
+    #[test]
+    fn test_listing_omits_messages() {
+        let listing = review().listing();
+        assert_eq!(listing["name"], "code_review");
+        assert_eq!(listing["arguments"][0]["required"], true);
+        assert!(listing.get("messages").is_none());
+    }
+}
@@ -0,0 +1,272 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// The newest protocol revision, answered to clients asking for one the
+/// server does not know.
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Every protocol revision the server can speak, newest first.
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &[PROTOCOL_VERSION, "2025-03-26", "2024-11-05"];
+
+/// The revision to answer a client's `initialize` with: the one it asked
+/// for when the server speaks it, or else the newest, which the client
+/// may then refuse.
+pub fn negotiate_version(requested: Option<&str>) -> &'static str {
+    SUPPORTED_PROTOCOL_VERSIONS
+        .iter()
+        .find(|&&version| Some(version) == requested)
+        .unwrap_or(&PROTOCOL_VERSION)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpRequest {
+    pub jsonrpc: String,
+    pub id: Option<Value>,
+    pub method: String,
+    pub params: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpResponse {
+    pub jsonrpc: String,
+    pub id: Option<Value>,
+    pub result: Option<Value>,
+    pub error: Option<McpError>,
+}
+
+impl McpResponse {
+    pub fn success(id: Option<Value>, result: Value) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: Some(result),
+            error: None,
+        }
+    }
+
+    pub fn error(id: Option<Value>, code: i32, message: impl Into<String>) -> Self {
+        Self {
+            jsonrpc: "2.0".to_string(),
+            id,
+            result: None,
+            error: Some(McpError {
+                code,
+                message: message.into(),
+                data: None,
+            }),
+        }
+    }
+
+    /// Attaches `data` to an error response; success responses are
+    /// returned unchanged.
+    pub fn with_data(mut self, data: Value) -> Self {
+        if let Some(error) = self.error.as_mut() {
+            error.data = Some(data);
+        }
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct McpError {
+    pub code: i32,
+    pub message: String,
+    pub data: Option<Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tool {
+    pub name: String,
+    pub description: String,
+    pub input_schema: Value,
+    /// Schema of the `structuredContent` the tool returns. When set, every
+    /// successful result must carry structured content that matches it.
+    #[serde(
+        rename = "outputSchema",
+        alias = "output_schema",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub output_schema: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+    #[serde(rename = "_meta", default, skip_serializing_if = "ToolMeta::is_empty")]
+    pub meta: ToolMeta,
+}
+
+impl Tool {
+    pub fn with_examples(mut self, examples: impl IntoIterator<Item = ToolExample>) -> Self {
+        self.meta.examples.extend(examples);
+        self
+    }
+
+    pub fn deprecated(mut self, deprecation: Deprecation) -> Self {
+        self.meta.deprecation = Some(deprecation);
+        self
+    }
+
+    /// Whether the tool's deprecation sunset is on or before `today`, a
+    /// `YYYY-MM-DD` date.
+    pub fn is_retired(&self, today: &str) -> bool {
+        let sunset = self
+            .meta
+            .deprecation
+            .as_ref()
+            .and_then(|d| d.sunset.as_deref());
+        sunset.is_some_and(|sunset| sunset <= today)
+    }
+
+    /// Whether the tool is annotated as never modifying its environment.
+    /// Unannotated tools are assumed to modify it, as in the MCP spec.
+    pub fn is_read_only(&self) -> bool {
+        self.annotations
+            .as_ref()
+            .and_then(|a| a.read_only_hint)
+            .unwrap_or(false)
+    }
+}
+
+/// Extras listed with a tool as `_meta`, outside what MCP defines.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolMeta {
+    /// Calls that show how the tool is meant to be used. Models call tools
+    /// far more accurately with a concrete example in front of them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub examples: Vec<ToolExample>,
+    /// Set on a tool version being phased out; see
+    /// [`tools::versions`](crate::tools::versions).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deprecation: Option<Deprecation>,
+}
+
+impl ToolMeta {
+    pub fn is_empty(&self) -> bool {
+        self.examples.is_empty() && self.deprecation.is_none()
+    }
+}
+
+/// The timeline of a deprecated tool version.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Deprecation {
+    /// What changed, or what callers should do instead.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    /// The tool to call instead, such as `search@v2`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<String>,
+    /// The first day, as `YYYY-MM-DD` in UTC, on which the version is no
+    /// longer listed or callable.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sunset: Option<String>,
+}
+
+/// A sample invocation: what it does and the `arguments` to send.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolExample {
+    pub description: String,
+    pub arguments: Value,
+}
+
+impl ToolExample {
+    pub fn new(description: impl Into<String>, arguments: Value) -> Self {
+        Self {
+            description: description.into(),
+            arguments,
+        }
+    }
+}
+
+/// Hints about a tool's behaviour, as defined by MCP. Clients must not rely
+/// on them for safety, but the server uses `readOnlyHint` to decide which
+/// tools stay available in read-only mode.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    /// Whether a modifying tool may destroy data rather than only add to it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    /// Whether the tool reaches outside the server, e.g. to the network.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+}
+
+impl ToolAnnotations {
+    pub fn read_only() -> Self {
+        Self {
+            read_only_hint: Some(true),
+            ..Self::default()
+        }
+    }
+
+    pub fn mutating(destructive: bool) -> Self {
+        Self {
+            read_only_hint: Some(false),
+            destructive_hint: Some(destructive),
+            ..Self::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_mcp_request_serialization() {
+        let request = McpRequest {
+            jsonrpc: "2.0".to_string(),
+            id: Some(json!(1)),
+            method: "initialize".to_string(),
+            params: Some(json!({"clientInfo": {"name": "test", "version": "1.0"}})),
+        };
+
+        let serialized = serde_json::to_string(&request).unwrap();
+        let deserialized: McpRequest = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!(request.jsonrpc, deserialized.jsonrpc);
+        assert_eq!(request.id, deserialized.id);
+        assert_eq!(request.method, deserialized.method);
+        assert_eq!(request.params, deserialized.params);
+    }
+
+    #[test]
+    fn test_mcp_response_serialization() {
+        let response = McpResponse {
+            jsonrpc: "2.0".to_string(),
+            id: Some(json!(1)),
+            result: Some(json!({"success": true})),
+            error: None,
+        };
+
+        let serialized = serde_json::to_string(&response).unwrap();
+        let deserialized: McpResponse = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!(response.jsonrpc, deserialized.jsonrpc);
+        assert_eq!(response.id, deserialized.id);
+        assert_eq!(response.result, deserialized.result);
+        assert_eq!(response.error.is_none(), deserialized.error.is_none());
+    }
+
+    #[test]
+    fn test_mcp_error_serialization() {
+        let error = McpError {
+            code: -32601,
+            message: "Method not found".to_string(),
+            data: Some(json!({"method": "unknown"})),
+        };
+
+        let serialized = serde_json::to_string(&error).unwrap();
+        let deserialized: McpError = serde_json::from_str(&serialized).unwrap();
+
+        assert_eq!(error.code, deserialized.code);
+        assert_eq!(error.message, deserialized.message);
+        assert_eq!(error.data, deserialized.data);
+    }
+}
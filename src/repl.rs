@@ -0,0 +1,404 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::sync::Mutex;
+
+use crate::sandbox::Sandbox;
+use crate::tools::{javascript, python};
+
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 900;
+
+pub const DEFAULT_MAX_SESSIONS: usize = 8;
+
+/// Python session driver: executes each request line in one shared namespace,
+/// answering on a private copy of stdout so stray fd-level writes can't
+/// corrupt the framing.
+const PYTHON_DRIVER: &str = r#"
+import base64, contextlib, io, json, os, sys, traceback
+proto = os.fdopen(os.dup(1), "w")
+os.dup2(2, 1)
+namespace = {"__name__": "__main__"}
+for line in sys.stdin:
+    request = json.loads(line)
+    out, err = io.StringIO(), io.StringIO()
+    ok = True
+    with contextlib.redirect_stdout(out), contextlib.redirect_stderr(err):
+        try:
+            exec(compile(request["code"], "<repl>", "exec"), namespace)
+        except BaseException:
+            traceback.print_exc()
+            ok = False
+    figures = []
+    if "matplotlib.pyplot" in sys.modules:
+        plt = sys.modules["matplotlib.pyplot"]
+        for num in plt.get_fignums():
+            buf = io.BytesIO()
+            plt.figure(num).savefig(buf, format="png")
+            figures.append(base64.b64encode(buf.getvalue()).decode("ascii"))
+        plt.close("all")
+    proto.write(json.dumps({"stdout": out.getvalue(), "stderr": err.getvalue(), "ok": ok, "figures": figures}) + "\n")
+    proto.flush()
+"#;
+
+/// Node session driver: each request runs as a script in the main context so
+/// top-level declarations persist; console output is captured per request.
+/// Runs in the same sandbox as one-shot `js_run` scripts.
+const JAVASCRIPT_DRIVER: &str = r#"
+const readline = require('readline');
+const util = require('util');
+const vm = require('vm');
+sandbox(new Set(JSON.parse(process.env.MCP_JS_ALLOWED_MODULES)));
+const write = process.stdout.write.bind(process.stdout);
+let out = [];
+let err = [];
+process.stdout.write = (chunk) => { out.push(String(chunk)); return true; };
+process.stderr.write = (chunk) => { err.push(String(chunk)); return true; };
+console.log = console.info = (...args) => { out.push(util.format(...args) + '\n'); };
+console.error = console.warn = (...args) => { err.push(util.format(...args) + '\n'); };
+globalThis.require = require;
+(async () => {
+  for await (const line of readline.createInterface({ input: process.stdin })) {
+    const request = JSON.parse(line);
+    out = [];
+    err = [];
+    let ok = true;
+    try {
+      globalThis.input = request.input;
+      let value = vm.runInThisContext(request.code, { filename: '<repl>' });
+      if (value instanceof Promise) {
+        value = await value;
+      }
+      if (value !== undefined) {
+        out.push(util.inspect(value) + '\n');
+      }
+    } catch (e) {
+      err.push((e && e.stack ? e.stack : String(e)) + '\n');
+      ok = false;
+    }
+    write(JSON.stringify({ stdout: out.join(''), stderr: err.join(''), ok, figures: [] }) + '\n');
+  }
+})();
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    Python,
+    JavaScript,
+}
+
+impl Language {
+    pub fn name(self) -> &'static str {
+        match self {
+            Language::Python => "python",
+            Language::JavaScript => "javascript",
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct DriverReply {
+    stdout: String,
+    stderr: String,
+    figures: Vec<String>,
+}
+
+struct Session {
+    language: Language,
+    // Held so the interpreter is killed when the session is dropped.
+    _child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    created_at: Instant,
+    last_used: Instant,
+    executions: u64,
+    /// Set while `execute` waits for the interpreter. Still set afterwards
+    /// when the call was cancelled midway, so its reply may be pending.
+    interrupted: bool,
+}
+
+impl Session {
+    async fn spawn(language: Language, sandbox: &Sandbox) -> Result<Self, String> {
+        let mut command = match language {
+            Language::Python => {
+                let mut command = sandbox.command(python::interpreter(), &[]);
+                command
+                    .arg("-c")
+                    .arg(PYTHON_DRIVER)
+                    .env("MPLBACKEND", "Agg");
+                command
+            }
+            Language::JavaScript => {
+                javascript::node_command(JAVASCRIPT_DRIVER, javascript::DEFAULT_MAX_MEMORY_MB)
+                    .await?
+            }
+        };
+
+        let mut child = crate::script::spawn(&mut command, Stdio::inherit())?;
+        let stdin = child.stdin.take().expect("stdin is piped");
+        let stdout = BufReader::new(child.stdout.take().expect("stdout is piped"));
+
+        let now = Instant::now();
+        Ok(Self {
+            language,
+            _child: child,
+            stdin,
+            stdout,
+            created_at: now,
+            last_used: now,
+            executions: 0,
+            interrupted: false,
+        })
+    }
+
+    async fn execute(&mut self, code: &str, input: &Value) -> Result<DriverReply, String> {
+        let mut request = serde_json::json!({"code": code, "input": input}).to_string();
+        request.push('\n');
+        self.interrupted = true;
+        self.stdin
+            .write_all(request.as_bytes())
+            .await
+            .map_err(|e| format!("Session interpreter is not running: {}", e))?;
+        self.stdin.flush().await.map_err(|e| e.to_string())?;
+
+        let mut line = String::new();
+        let read = self.stdout.read_line(&mut line).await;
+        self.interrupted = false;
+        match read {
+            Ok(0) => Err("Session interpreter exited".to_string()),
+            Ok(_) => {
+                serde_json::from_str(&line).map_err(|e| format!("Malformed session reply: {}", e))
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
+}
+
+/// Names a session: the id of the client session that started it, empty
+/// outside any client session, and the name the client gave it.
+type Key = (String, String);
+
+/// Named interpreter sessions whose variables survive across tool calls.
+/// Each client session has its own namespace of names, so one client can
+/// neither reach nor reset another's interpreter. Sessions idle for longer
+/// than the idle timeout are dropped on the next access, killing their
+/// interpreter.
+pub struct ReplManager {
+    sessions: Mutex<HashMap<Key, Arc<Mutex<Session>>>>,
+    idle_timeout: Duration,
+    /// Most sessions one owner may keep at once.
+    max_sessions: usize,
+    /// Python sessions run under it.
+    sandbox: Sandbox,
+}
+
+impl ReplManager {
+    pub fn new(idle_timeout: Duration) -> Self {
+        Self {
+            sessions: Mutex::new(HashMap::new()),
+            idle_timeout,
+            max_sessions: DEFAULT_MAX_SESSIONS,
+            sandbox: Sandbox::default(),
+        }
+    }
+
+    pub fn with_max_sessions(mut self, max_sessions: usize) -> Self {
+        self.max_sessions = max_sessions;
+        self
+    }
+
+    pub fn with_sandbox(mut self, sandbox: Sandbox) -> Self {
+        self.sandbox = sandbox;
+        self
+    }
+
+    /// Manager using `MCP_REPL_IDLE_TIMEOUT_SECS` or the default idle
+    /// timeout and the per-owner limit in `MCP_REPL_MAX_SESSIONS`, running
+    /// Python sessions under the `MCP_SANDBOX` backend.
+    pub fn from_env() -> Self {
+        let secs = std::env::var("MCP_REPL_IDLE_TIMEOUT_SECS")
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS);
+        let mut manager = Self::new(Duration::from_secs(secs)).with_sandbox(Sandbox::from_env());
+        if let Ok(value) = std::env::var("MCP_REPL_MAX_SESSIONS") {
+            match value.parse() {
+                Ok(max_sessions) => manager.max_sessions = max_sessions,
+                Err(_) => tracing::warn!("Invalid MCP_REPL_MAX_SESSIONS '{}'", value),
+            }
+        }
+        manager
+    }
+
+    /// Runs `code` in `owner`'s session called `name`, starting it on first
+    /// use, unless `owner` already has the most sessions allowed. A timeout
+    /// or interpreter failure discards the session. Interpreters start
+    /// without the sessions lock held, so a slow start holds up no one
+    /// else.
+    pub async fn execute(
+        &self,
+        owner: &str,
+        name: &str,
+        language: Language,
+        code: &str,
+        input: &Value,
+        timeout: Duration,
+    ) -> Result<Vec<Value>, String> {
+        let key = (owner.to_string(), name.to_string());
+        let existing = {
+            let mut sessions = self.sessions.lock().await;
+            self.expire_idle(&mut sessions);
+            let existing = sessions.get(&key).cloned();
+            if existing.is_none() {
+                self.check_room(&sessions, owner)?;
+            }
+            existing
+        };
+        let handle = match existing {
+            Some(handle) => handle,
+            None => {
+                let started = Arc::new(Mutex::new(Session::spawn(language, &self.sandbox).await?));
+                let mut sessions = self.sessions.lock().await;
+                match sessions.get(&key) {
+                    // Another call started it meanwhile; that one is kept
+                    // and this interpreter is killed on drop.
+                    Some(handle) => handle.clone(),
+                    None => {
+                        // Checked again, for sessions started while this
+                        // one was.
+                        self.check_room(&sessions, owner)?;
+                        sessions.insert(key.clone(), started.clone());
+                        started
+                    }
+                }
+            }
+        };
+
+        let mut session = handle.lock().await;
+        if session.language != language {
+            return Err(format!(
+                "Session '{}' is a {} session",
+                name,
+                session.language.name()
+            ));
+        }
+        // A cancelled call left the interpreter out of step; start over.
+        if session.interrupted {
+            *session = Session::spawn(language, &self.sandbox).await?;
+        }
+
+        let reply = match tokio::time::timeout(timeout, session.execute(code, input)).await {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(message)) => {
+                self.discard(&key, &handle).await;
+                return Err(message);
+            }
+            Err(_) => {
+                self.discard(&key, &handle).await;
+                return Err(format!(
+                    "Execution timed out after {}s; session '{}' was reset",
+                    timeout.as_secs(),
+                    name
+                ));
+            }
+        };
+        session.last_used = Instant::now();
+        session.executions += 1;
+
+        let mut content = Vec::new();
+        if !reply.stdout.is_empty() {
+            content.push(serde_json::json!({"type": "text", "text": reply.stdout}));
+        }
+        if !reply.stderr.is_empty() {
+            content.push(
+                serde_json::json!({"type": "text", "text": format!("stderr:\n{}", reply.stderr)}),
+            );
+        }
+        for data in reply.figures {
+            content
+                .push(serde_json::json!({"type": "image", "data": data, "mimeType": "image/png"}));
+        }
+        if content.is_empty() {
+            content.push(serde_json::json!({"type": "text", "text": ""}));
+        }
+        Ok(content)
+    }
+
+    fn check_room(
+        &self,
+        sessions: &HashMap<Key, Arc<Mutex<Session>>>,
+        owner: &str,
+    ) -> Result<(), String> {
+        if sessions.keys().filter(|(o, _)| o == owner).count() >= self.max_sessions {
+            return Err(format!(
+                "At most {} sessions can run at once; reset one with repl_reset first",
+                self.max_sessions
+            ));
+        }
+        Ok(())
+    }
+
+    /// Removes the session at `key` if it is still `handle`, and not one
+    /// started in its place since.
+    async fn discard(&self, key: &Key, handle: &Arc<Mutex<Session>>) {
+        let mut sessions = self.sessions.lock().await;
+        if sessions
+            .get(key)
+            .is_some_and(|current| Arc::ptr_eq(current, handle))
+        {
+            sessions.remove(key);
+        }
+    }
+
+    /// Describes `owner`'s live sessions, most recently created last.
+    pub async fn list(&self, owner: &str) -> Vec<Value> {
+        let mut sessions = self.sessions.lock().await;
+        self.expire_idle(&mut sessions);
+
+        let mut entries = Vec::new();
+        for ((_, name), session) in sessions.iter().filter(|((o, _), _)| o == owner) {
+            // A locked session is executing right now; report it as busy.
+            let entry = match session.try_lock() {
+                Ok(session) => serde_json::json!({
+                    "name": name,
+                    "language": session.language.name(),
+                    "executions": session.executions,
+                    "age_secs": session.created_at.elapsed().as_secs(),
+                    "idle_secs": session.last_used.elapsed().as_secs(),
+                    "busy": false
+                }),
+                Err(_) => serde_json::json!({"name": name, "busy": true}),
+            };
+            entries.push(entry);
+        }
+        entries.sort_by(|a, b| b["age_secs"].as_u64().cmp(&a["age_secs"].as_u64()));
+        entries
+    }
+
+    /// Discards `owner`'s session called `name` and its state. Returns false
+    /// if it did not exist.
+    pub async fn reset(&self, owner: &str, name: &str) -> bool {
+        let key = (owner.to_string(), name.to_string());
+        self.sessions.lock().await.remove(&key).is_some()
+    }
+
+    /// Drops every session idle for longer than the idle timeout and returns
+    /// how many were discarded.
+    pub async fn expire(&self) -> usize {
+        let mut sessions = self.sessions.lock().await;
+        self.expire_idle(&mut sessions)
+    }
+
+    fn expire_idle(&self, sessions: &mut HashMap<Key, Arc<Mutex<Session>>>) -> usize {
+        let before = sessions.len();
+        sessions.retain(|_, session| match session.try_lock() {
+            Ok(session) => session.last_used.elapsed() < self.idle_timeout,
+            Err(_) => true,
+        });
+        before - sessions.len()
+    }
+}
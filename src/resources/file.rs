@@ -0,0 +1,217 @@
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+
+use super::{Resource, ResourceContents, ResourceProvider, ResourceTemplate};
+use crate::vfs::local::LocalDir;
+
+/// Listing stops after this many files so a large tree cannot flood the client.
+pub const MAX_LISTED_FILES: usize = 1000;
+
+/// Exposes the files under a directory as `file://` resources. Paths are
+/// resolved by a [`LocalDir`], as for a `local` mount: hidden files and
+/// directories, such as `.env` and `.git`, are neither listed nor served,
+/// and URIs resolving outside the root (through `..` or symlinks) are not
+/// served.
+pub struct FileProvider {
+    dir: LocalDir,
+}
+
+impl FileProvider {
+    pub fn new(root: impl AsRef<Path>) -> std::io::Result<Self> {
+        Ok(Self {
+            dir: LocalDir::new(root).map_err(std::io::Error::other)?,
+        })
+    }
+
+    fn resolve(&self, uri: &str) -> Option<PathBuf> {
+        let path = percent_decode(uri.strip_prefix("file://")?)?;
+        let relative = Path::new(&path).strip_prefix(self.dir.root()).ok()?;
+        let path = self.dir.resolve(relative.to_str()?).ok()?;
+        path.is_file().then_some(path)
+    }
+}
+
+#[async_trait]
+impl ResourceProvider for FileProvider {
+    async fn list(&self) -> Result<Vec<Resource>, String> {
+        let files = self.dir.files("", MAX_LISTED_FILES)?;
+
+        Ok(files
+            .into_iter()
+            .map(|file| {
+                let path = self.dir.root().join(&file.path);
+                Resource {
+                    uri: file_uri(&path),
+                    name: file.path,
+                    description: None,
+                    mime_type: mime_type(&path).map(str::to_string),
+                }
+            })
+            .collect())
+    }
+
+    async fn read(&self, uri: &str) -> Result<Option<Vec<ResourceContents>>, String> {
+        let Some(path) = self.resolve(uri) else {
+            return Ok(None);
+        };
+        let bytes = std::fs::read(&path).map_err(|e| e.to_string())?;
+        let mime = mime_type(&path).map(str::to_string);
+
+        let contents = match String::from_utf8(bytes) {
+            Ok(text) => ResourceContents::text(uri, mime, text),
+            Err(e) => ResourceContents::blob(uri, mime, e.as_bytes()),
+        };
+        Ok(Some(vec![contents]))
+    }
+
+    /// Any file under the root, including those past the listing limit.
+    fn templates(&self) -> Vec<ResourceTemplate> {
+        vec![ResourceTemplate {
+            uri_template: format!("{}/{{+path}}", file_uri(self.dir.root())),
+            name: self.dir.root().display().to_string(),
+            description: Some("A file under the directory".to_string()),
+            mime_type: None,
+        }]
+    }
+
+    async fn fingerprint(&self, uri: &str) -> Result<Option<String>, String> {
+        let Some(path) = self.resolve(uri) else {
+            return Ok(None);
+        };
+        let metadata = std::fs::metadata(&path).map_err(|e| e.to_string())?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .unwrap_or_default();
+        Ok(Some(format!("{}:{}", modified.as_nanos(), metadata.len())))
+    }
+
+    fn watch_path(&self, uri: &str) -> Option<PathBuf> {
+        self.resolve(uri)
+    }
+}
+
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for byte in path.to_string_lossy().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+pub(crate) fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+pub(crate) fn mime_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    Some(match extension.as_str() {
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "csv" => "text/csv",
+        "json" => "application/json",
+        "js" => "text/javascript",
+        "py" => "text/x-python",
+        "rs" => "text/x-rust",
+        "toml" => "application/toml",
+        "yaml" | "yml" => "application/yaml",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> PathBuf {
+        let root = std::env::temp_dir().join(format!("mcp-resources-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(root.join("docs")).unwrap();
+        std::fs::write(root.join("docs/read me.md"), "# Hello").unwrap();
+        std::fs::write(root.join("logo.png"), [0x89, b'P', b'N', b'G', 0xff]).unwrap();
+        std::fs::write(root.join(".secret"), "hidden").unwrap();
+        root
+    }
+
+    #[tokio::test]
+    async fn test_lists_and_reads_files() {
+        let root = fixture();
+        let provider = FileProvider::new(&root).unwrap();
+
+        let resources = provider.list().await.unwrap();
+        let names: Vec<&str> = resources.iter().map(|r| r.name.as_str()).collect();
+        assert_eq!(names, vec!["docs/read me.md", "logo.png"]);
+        assert!(resources[0].uri.ends_with("/docs/read%20me.md"));
+        assert_eq!(resources[0].mime_type.as_deref(), Some("text/markdown"));
+
+        let text = provider.read(&resources[0].uri).await.unwrap().unwrap();
+        assert_eq!(text[0].text.as_deref(), Some("# Hello"));
+
+        let blob = provider.read(&resources[1].uri).await.unwrap().unwrap();
+        assert_eq!(blob[0].blob.as_deref(), Some("iVBOR/8="));
+        assert!(blob[0].text.is_none());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_does_not_serve_outside_root() {
+        let root = fixture();
+        let provider = FileProvider::new(root.join("docs")).unwrap();
+
+        let escape = format!("{}/../logo.png", file_uri(&root.join("docs")));
+        assert!(provider.read(&escape).await.unwrap().is_none());
+        assert!(provider
+            .read("https://example.com/x")
+            .await
+            .unwrap()
+            .is_none());
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_does_not_serve_hidden_files() {
+        let root = fixture();
+        std::fs::create_dir_all(root.join(".git")).unwrap();
+        std::fs::write(root.join(".git/config"), "[remote]").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(root.join(".secret"), root.join("docs/public")).unwrap();
+        let provider = FileProvider::new(&root).unwrap();
+        let base = file_uri(provider.dir.root());
+
+        for path in [".secret", ".git/config", "docs/../.secret", "docs/public"] {
+            let uri = format!("{}/{}", base, path);
+            assert!(provider.read(&uri).await.unwrap().is_none(), "{}", path);
+            assert!(provider.fingerprint(&uri).await.unwrap().is_none());
+        }
+
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,106 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub mod file;
+pub mod template;
+pub mod watch;
+
+pub use template::ResourceTemplate;
+
+/// A source of resources. The server asks every registered provider for its
+/// listing and reads a URI from the first provider that recognises it,
+/// trying first the providers with a template matching the URI.
+#[async_trait]
+pub trait ResourceProvider: Send + Sync {
+    async fn list(&self) -> Result<Vec<Resource>, String>;
+
+    /// Contents of `uri`, or `None` when the URI does not belong to this
+    /// provider.
+    async fn read(&self, uri: &str) -> Result<Option<Vec<ResourceContents>>, String>;
+
+    /// Templates of URIs this provider can read whether or not they are
+    /// listed, for `resources/templates/list`. [`read`](Self::read) gets
+    /// the matching URIs, and can take them apart with
+    /// [`ResourceTemplate::matches`].
+    fn templates(&self) -> Vec<ResourceTemplate> {
+        Vec::new()
+    }
+
+    /// A value that changes whenever `uri`'s contents do, polled for
+    /// `resources/subscribe`; `None` when the URI is not this provider's.
+    /// The default hashes what [`read`](Self::read) returns; providers that
+    /// can tell more cheaply, say from file metadata, should override it.
+    async fn fingerprint(&self, uri: &str) -> Result<Option<String>, String> {
+        Ok(self.read(uri).await?.map(|contents| {
+            let serialized = serde_json::to_vec(&contents).unwrap();
+            crate::crypto::hex(&crate::crypto::sha256(&serialized))
+        }))
+    }
+
+    /// The local file behind `uri`, which subscriptions then watch for OS
+    /// file events instead of polling; `None` when the URI is not a file of
+    /// this provider's.
+    fn watch_path(&self, _uri: &str) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// `providers` in the order to try for `uri`: those with a template
+/// matching it first, then the rest, each group in registration order.
+pub fn by_template<'a>(
+    providers: &'a [Arc<dyn ResourceProvider>],
+    uri: &str,
+) -> impl Iterator<Item = &'a Arc<dyn ResourceProvider>> {
+    let (matching, rest): (Vec<_>, Vec<_>) = providers.iter().partition(|provider| {
+        provider
+            .templates()
+            .iter()
+            .any(|template| template.matches(uri).is_some())
+    });
+    matching.into_iter().chain(rest)
+}
+
+/// An entry of `resources/list`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Resource {
+    pub uri: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// One item of a `resources/read` result: UTF-8 `text` or base64 `blob`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResourceContents {
+    pub uri: String,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blob: Option<String>,
+}
+
+impl ResourceContents {
+    pub fn text(uri: impl Into<String>, mime_type: Option<String>, text: String) -> Self {
+        Self {
+            uri: uri.into(),
+            mime_type,
+            text: Some(text),
+            blob: None,
+        }
+    }
+
+    pub fn blob(uri: impl Into<String>, mime_type: Option<String>, bytes: &[u8]) -> Self {
+        Self {
+            uri: uri.into(),
+            mime_type,
+            text: None,
+            blob: Some(crate::base64::encode(bytes)),
+        }
+    }
+}
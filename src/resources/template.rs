@@ -0,0 +1,177 @@
+//! RFC 6570 URI templates, as far as resource templates need them: simple
+//! `{var}` expressions, which match one path segment, and reserved
+//! `{+var}` expressions, which may span several, such as `file:///{+path}`
+//! or `db://{table}/{id}`.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// An entry of `resources/templates/list`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ResourceTemplate {
+    #[serde(rename = "uriTemplate")]
+    pub uri_template: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+impl ResourceTemplate {
+    /// The variables of `uri` if it matches this template. An invalid
+    /// template matches nothing.
+    pub fn matches(&self, uri: &str) -> Option<BTreeMap<String, String>> {
+        UriTemplate::parse(&self.uri_template).ok()?.matches(uri)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Part {
+    Literal(String),
+    Variable { name: String, reserved: bool },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UriTemplate {
+    parts: Vec<Part>,
+}
+
+impl UriTemplate {
+    /// Fails on operators other than `+`, on expressions listing several
+    /// variables and on two expressions in a row, which could not be told
+    /// apart when matching.
+    pub fn parse(template: &str) -> Result<Self, String> {
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Literal(rest[..start].to_string()));
+            } else if matches!(parts.last(), Some(Part::Variable { .. })) {
+                return Err(format!("Adjacent expressions in {}", template));
+            }
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| format!("Unclosed expression in {}", template))?;
+            let expression = &rest[start + 1..start + end];
+            let (name, reserved) = match expression.strip_prefix('+') {
+                Some(name) => (name, true),
+                None => (expression, false),
+            };
+            let valid = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '.';
+            if name.is_empty() || !name.chars().all(valid) {
+                return Err(format!("Unsupported expression {{{}}}", expression));
+            }
+            parts.push(Part::Variable {
+                name: name.to_string(),
+                reserved,
+            });
+            rest = &rest[start + end + 1..];
+        }
+        if rest.contains('}') {
+            return Err(format!("Unopened expression in {}", template));
+        }
+        if !rest.is_empty() {
+            parts.push(Part::Literal(rest.to_string()));
+        }
+        Ok(Self { parts })
+    }
+
+    /// The percent-decoded value of each variable, when `uri` matches. Every
+    /// value must be non-empty, and a simple variable's may not contain
+    /// `/`, `?` or `#`.
+    pub fn matches(&self, uri: &str) -> Option<BTreeMap<String, String>> {
+        let mut values = BTreeMap::new();
+        match_parts(&self.parts, uri, &mut values).then_some(values)
+    }
+}
+
+fn match_parts(parts: &[Part], uri: &str, values: &mut BTreeMap<String, String>) -> bool {
+    let Some((part, rest)) = parts.split_first() else {
+        return uri.is_empty();
+    };
+    match part {
+        Part::Literal(literal) => uri
+            .strip_prefix(literal.as_str())
+            .is_some_and(|uri| match_parts(rest, uri, values)),
+        Part::Variable { name, reserved } => {
+            // Shortest value first, so a later literal matches its first
+            // occurrence.
+            let ends = uri
+                .char_indices()
+                .skip(1)
+                .map(|(i, _)| i)
+                .chain([uri.len()]);
+            for end in ends {
+                let value = &uri[..end];
+                if !reserved && value.contains(['/', '?', '#']) {
+                    return false;
+                }
+                let Some(decoded) = percent_decode(value) else {
+                    continue;
+                };
+                if match_parts(rest, &uri[end..], values) {
+                    values.insert(name.clone(), decoded);
+                    return true;
+                }
+            }
+            false
+        }
+    }
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_uri_templates() {
+        let row = UriTemplate::parse("db://{table}/{id}").unwrap();
+        assert_eq!(
+            row.matches("db://users/42"),
+            Some(vars(&[("table", "users"), ("id", "42")]))
+        );
+        assert_eq!(
+            row.matches("db://my%20table/1"),
+            Some(vars(&[("table", "my table"), ("id", "1")]))
+        );
+        assert_eq!(row.matches("db://users/42/extra"), None);
+        assert_eq!(row.matches("db:///42"), None);
+        assert_eq!(row.matches("file://users/42"), None);
+
+        let file = UriTemplate::parse("file:///{+path}.json").unwrap();
+        assert_eq!(
+            file.matches("file:///a.json/b.json"),
+            Some(vars(&[("path", "a.json/b")]))
+        );
+        assert_eq!(file.matches("file:///notes.txt"), None);
+
+        for bad in ["db://{a}{b}", "db://{a", "db://a}", "db://{?q}", "db://{}"] {
+            assert!(UriTemplate::parse(bad).is_err(), "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Change detection for `resources/subscribe`. Each session with
+//! subscriptions gets one task that sends `notifications/resources/updated`
+//! for the subscribed URIs that changed. URIs a provider maps to a local
+//! file are checked when the OS reports an event in the file's directory;
+//! the rest, and all of them where file events are unavailable, are
+//! fingerprinted every interval. The task ends with the session or its last
+//! subscription.
+
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+use super::ResourceProvider;
+use crate::session::Session;
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// `MCP_WATCH_INTERVAL_MS`, or [`DEFAULT_INTERVAL`].
+pub fn interval_from_env() -> Duration {
+    std::env::var("MCP_WATCH_INTERVAL_MS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&ms| ms > 0)
+        .map_or(DEFAULT_INTERVAL, Duration::from_millis)
+}
+
+/// The fingerprint from the first provider serving `uri`, tried in the
+/// order reads use. `None` when no provider serves it any more, which
+/// counts as a change from any fingerprint. Failures count as the resource
+/// being gone.
+pub async fn fingerprint(providers: &[Arc<dyn ResourceProvider>], uri: &str) -> Option<String> {
+    for provider in super::by_template(providers, uri) {
+        match provider.fingerprint(uri).await {
+            Ok(Some(fingerprint)) => return Some(fingerprint),
+            Ok(None) => {}
+            Err(_) => return None,
+        }
+    }
+    None
+}
+
+/// The directory to watch for `uri`: that of the file the first provider
+/// serving it names.
+fn watch_dir(providers: &[Arc<dyn ResourceProvider>], uri: &str) -> Option<PathBuf> {
+    super::by_template(providers, uri)
+        .find_map(|provider| provider.watch_path(uri))
+        .and_then(|path| path.parent().map(PathBuf::from))
+}
+
+/// OS file events for the directories of subscribed files. Directories,
+/// rather than the files, are watched so a file replaced by a rename, as
+/// editors save, is still seen.
+struct FileEvents {
+    watcher: RecommendedWatcher,
+    events: mpsc::Receiver<()>,
+    /// Subscribed URIs by the directory watched for them.
+    dirs: HashMap<String, PathBuf>,
+}
+
+impl FileEvents {
+    /// `None` when the platform offers no file events, leaving every URI
+    /// to polling.
+    fn new() -> Option<Self> {
+        // One pending wake-up is enough: each rechecks every watched URI.
+        let (sender, events) = mpsc::channel(1);
+        let watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+            if event.is_ok() {
+                let _ = sender.try_send(());
+            }
+        })
+        .map_err(|e| tracing::warn!("File events unavailable, polling instead: {}", e))
+        .ok()?;
+        Some(Self {
+            watcher,
+            events,
+            dirs: HashMap::new(),
+        })
+    }
+
+    /// Watches the directories of `uris` that are local files and stops
+    /// watching those of URIs no longer subscribed. Returns the URIs newly
+    /// watched, which may have changed before their watch began.
+    fn update(
+        &mut self,
+        providers: &[Arc<dyn ResourceProvider>],
+        uris: &[String],
+    ) -> HashSet<String> {
+        let before: HashSet<PathBuf> = self.dirs.values().cloned().collect();
+        self.dirs.retain(|uri, _| uris.contains(uri));
+        let mut added = HashSet::new();
+        for uri in uris {
+            if !self.dirs.contains_key(uri) {
+                if let Some(dir) = watch_dir(providers, uri) {
+                    self.dirs.insert(uri.clone(), dir);
+                    added.insert(uri.clone());
+                }
+            }
+        }
+        let after: HashSet<PathBuf> = self.dirs.values().cloned().collect();
+        for dir in before.difference(&after) {
+            let _ = self.watcher.unwatch(dir);
+        }
+        for dir in after.difference(&before) {
+            if let Err(e) = self.watcher.watch(dir, RecursiveMode::NonRecursive) {
+                tracing::warn!("Polling {} instead of watching it: {}", dir.display(), e);
+                self.dirs.retain(|_, watched| watched != dir);
+            }
+        }
+        added
+    }
+
+    fn watches(&self, uri: &str) -> bool {
+        self.dirs.contains_key(uri)
+    }
+}
+
+/// What woke the watcher.
+#[derive(PartialEq)]
+enum Wake {
+    /// The interval passed: poll the URIs that aren't watched.
+    Poll,
+    /// A watched directory changed: check the watched URIs.
+    FileEvent,
+    /// A URI was subscribed to: start watching it.
+    Subscribed,
+}
+
+/// Checks `session`'s subscriptions until it is dropped or has none left:
+/// watched files on each file event, everything else every `interval`.
+pub fn spawn(
+    session: Weak<Session>,
+    providers: Vec<Arc<dyn ResourceProvider>>,
+    interval: Duration,
+) {
+    let Some(added) = session
+        .upgrade()
+        .map(|session| session.subscriptions_added())
+    else {
+        return;
+    };
+    tokio::spawn(async move {
+        let mut files = FileEvents::new();
+        let mut next_poll = tokio::time::Instant::now() + interval;
+        let mut wake = Wake::Subscribed;
+        loop {
+            let Some(session) = session.upgrade() else {
+                return;
+            };
+            let uris = session.subscriptions();
+            if uris.is_empty() && session.stop_watching_if_idle() {
+                return;
+            }
+            let fresh = match files.as_mut() {
+                Some(files) => files.update(&providers, &uris),
+                None => HashSet::new(),
+            };
+            for uri in uris {
+                let watched = files.as_ref().is_some_and(|files| files.watches(&uri));
+                let due = match wake {
+                    Wake::Poll => !watched,
+                    Wake::FileEvent => true,
+                    Wake::Subscribed => fresh.contains(&uri),
+                };
+                if !due {
+                    continue;
+                }
+                let fingerprint = fingerprint(&providers, &uri).await;
+                if session.record_fingerprint(&uri, fingerprint) {
+                    session.notify("notifications/resources/updated", json!({ "uri": uri }));
+                }
+            }
+            drop(session);
+
+            let changed = async {
+                match files.as_mut() {
+                    Some(files) => files.events.recv().await,
+                    None => std::future::pending().await,
+                }
+            };
+            wake = tokio::select! {
+                _ = tokio::time::sleep_until(next_poll) => {
+                    next_poll = tokio::time::Instant::now() + interval;
+                    Wake::Poll
+                }
+                _ = changed => Wake::FileEvent,
+                _ = added.notified() => Wake::Subscribed,
+            };
+        }
+    });
+}
@@ -0,0 +1,147 @@
+//! Client roots: the directories a client lets the server work in. Clients
+//! declaring the `roots` capability are asked for `roots/list` once the
+//! handshake is done and again on `notifications/roots/list_changed`;
+//! tools read the result through
+//! [`ToolContext::roots`](crate::ToolContext::roots) and confine paths
+//! with [`ToolContext::resolve_path`](crate::ToolContext::resolve_path).
+
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::resources::file::percent_decode;
+use crate::session::Session;
+
+/// How long to wait for the client's `roots/list` answer.
+const LIST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    /// A `file://` URI.
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl Root {
+    /// The local directory, or `None` for a URI other than `file://`.
+    pub fn path(&self) -> Option<PathBuf> {
+        let path = self.uri.strip_prefix("file://")?;
+        Some(PathBuf::from(percent_decode(path)?))
+    }
+}
+
+#[derive(Deserialize)]
+struct RootList {
+    roots: Vec<Root>,
+}
+
+/// Fetches the client's roots into `session`, if the client has any and
+/// the transport can ask. Failures are logged and keep the previous list.
+pub(crate) async fn refresh(session: Arc<Session>) {
+    if session.client_capabilities().get("roots").is_none() || !session.can_notify() {
+        return;
+    }
+    let request = session.request("roots/list", serde_json::json!({}), LIST_TIMEOUT);
+    let result = match request.await {
+        Ok(result) => result,
+        Err(e) => return tracing::warn!("roots/list failed: {}", e.message),
+    };
+    match serde_json::from_value::<RootList>(result) {
+        Ok(list) => session.set_roots(list.roots),
+        Err(e) => tracing::warn!("Ignoring invalid roots/list result: {}", e),
+    }
+}
+
+/// `path` made absolute and normalized, if it lies within one of `roots`.
+/// A relative path is taken from the first root. Existing paths have their
+/// symlinks resolved, so a link cannot lead outside a root.
+pub fn confine(roots: &[Root], path: &Path) -> Result<PathBuf, String> {
+    let directories: Vec<PathBuf> = roots.iter().filter_map(Root::path).map(real).collect();
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        directories
+            .first()
+            .ok_or("The client has not shared any directories")?
+            .join(path)
+    };
+    let path = real(path);
+    if directories.iter().any(|root| path.starts_with(root)) {
+        Ok(path)
+    } else {
+        Err(format!(
+            "{} is outside the directories the client shared",
+            path.display()
+        ))
+    }
+}
+
+/// The canonical path when it exists, otherwise the path with `.` and `..`
+/// worked out, under the canonical form of its deepest existing ancestor.
+fn real(path: PathBuf) -> PathBuf {
+    if let Ok(path) = std::fs::canonicalize(&path) {
+        return path;
+    }
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::CurDir => {}
+            component => normalized.push(component),
+        }
+    }
+    let mut existing = normalized.as_path();
+    let mut rest = Vec::new();
+    loop {
+        if let Ok(base) = std::fs::canonicalize(existing) {
+            return rest.iter().rev().fold(base, |path, part| path.join(part));
+        }
+        match (existing.file_name(), existing.parent()) {
+            (Some(name), Some(parent)) => {
+                rest.push(name);
+                existing = parent;
+            }
+            _ => return normalized,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_confines_paths_to_roots() {
+        let workspace = std::env::temp_dir().join(format!("roots-{}", uuid::Uuid::new_v4()));
+        let project = workspace.join("my project");
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        let roots = [Root {
+            uri: format!("file://{}", project.display()).replace(' ', "%20"),
+            name: Some("project".to_string()),
+        }];
+        let project = std::fs::canonicalize(&project).unwrap();
+
+        assert_eq!(
+            confine(&roots, Path::new("src/main.rs")).unwrap(),
+            project.join("src/main.rs")
+        );
+        assert_eq!(
+            confine(&roots, &project.join("src/../new/file.txt")).unwrap(),
+            project.join("new/file.txt")
+        );
+        assert!(confine(&roots, Path::new("../secret")).is_err());
+        assert!(confine(&roots, &workspace).is_err());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(&workspace, project.join("escape")).unwrap();
+            assert!(confine(&roots, Path::new("escape/x")).is_err());
+        }
+        assert!(confine(&[], Path::new("/tmp")).is_err());
+
+        std::fs::remove_dir_all(&workspace).unwrap();
+    }
+}
@@ -0,0 +1,97 @@
+//! The tokio runtime the `mcp-server` binary runs on: multi-threaded, with
+//! its worker threads, blocking pool and thread names set from the
+//! environment or the `[runtime]` section of the config file. Embedders run
+//! the server on a runtime of their own and need none of this.
+
+use serde::Serialize;
+use std::io;
+
+/// Used when `MCP_MAX_BLOCKING_THREADS` is unset. Blocking work here is
+/// file access and parsing large messages, so tokio's default of 512 only
+/// lets a burst of it crowd out the workers.
+pub const DEFAULT_MAX_BLOCKING_THREADS: usize = 64;
+
+/// Used when `MCP_THREAD_NAME` is unset.
+pub const DEFAULT_THREAD_NAME: &str = "mcp-worker";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RuntimeSettings {
+    /// Threads running tasks, one per CPU by default.
+    pub worker_threads: usize,
+    /// Most threads for blocking work at once.
+    pub max_blocking_threads: usize,
+    /// Name of every thread, as `top -H` and debuggers show it.
+    pub thread_name: String,
+}
+
+impl Default for RuntimeSettings {
+    fn default() -> Self {
+        Self {
+            worker_threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            max_blocking_threads: DEFAULT_MAX_BLOCKING_THREADS,
+            thread_name: DEFAULT_THREAD_NAME.to_string(),
+        }
+    }
+}
+
+impl RuntimeSettings {
+    /// Settings from `MCP_WORKER_THREADS`, `MCP_MAX_BLOCKING_THREADS` and
+    /// `MCP_THREAD_NAME`. Malformed values, and counts of zero, are
+    /// reported and ignored.
+    pub fn from_env() -> Self {
+        let mut settings = Self::default();
+        let var = |name: &str| -> Option<usize> {
+            let value = std::env::var(name).ok()?;
+            match value.trim().parse() {
+                Ok(n) if n > 0 => Some(n),
+                _ => {
+                    tracing::warn!("Invalid {} '{}'", name, value);
+                    None
+                }
+            }
+        };
+        if let Some(threads) = var("MCP_WORKER_THREADS") {
+            settings.worker_threads = threads;
+        }
+        if let Some(threads) = var("MCP_MAX_BLOCKING_THREADS") {
+            settings.max_blocking_threads = threads;
+        }
+        if let Ok(name) = std::env::var("MCP_THREAD_NAME") {
+            settings.thread_name = name;
+        }
+        settings
+    }
+
+    pub fn build(&self) -> io::Result<tokio::runtime::Runtime> {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(self.worker_threads)
+            .max_blocking_threads(self.max_blocking_threads)
+            .thread_name(&self.thread_name)
+            .enable_all()
+            .build()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runtime_follows_settings() {
+        let settings = RuntimeSettings {
+            worker_threads: 2,
+            max_blocking_threads: 1,
+            thread_name: "mcp-test".to_string(),
+        };
+        let runtime = settings.build().unwrap();
+        assert_eq!(runtime.metrics().num_workers(), 2);
+        let name = runtime.block_on(async {
+            tokio::spawn(async { std::thread::current().name().map(str::to_string) })
+                .await
+                .unwrap()
+        });
+        assert_eq!(name.as_deref(), Some("mcp-test"));
+        assert!(RuntimeSettings::default().worker_threads >= 1);
+    }
+}
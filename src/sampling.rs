@@ -0,0 +1,134 @@
+//! Requests for the client's model to generate a message, sent as
+//! `sampling/createMessage` from a tool call via
+//! [`ToolContext::sample`](crate::ToolContext::sample). The client decides
+//! which model runs, and may show the request to its user first.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+/// How long a tool waits for the client's answer. Clients may ask their
+/// user to approve each request, so this is generous.
+pub const TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SamplingMessage {
+    /// `user` or `assistant`.
+    pub role: String,
+    /// A content block, such as `{"type": "text", "text": ...}`.
+    pub content: Value,
+}
+
+impl SamplingMessage {
+    pub fn text(role: &str, text: impl Into<String>) -> Self {
+        Self {
+            role: role.to_string(),
+            content: serde_json::json!({ "type": "text", "text": text.into() }),
+        }
+    }
+}
+
+/// The params of `sampling/createMessage`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingRequest {
+    pub messages: Vec<SamplingMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    pub max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub stop_sequences: Vec<String>,
+    /// Hints such as `{"hints": [{"name": "claude"}], "speedPriority": 0.8}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_preferences: Option<Value>,
+}
+
+impl SamplingRequest {
+    pub fn new(max_tokens: u32) -> Self {
+        Self {
+            messages: Vec::new(),
+            system_prompt: None,
+            max_tokens,
+            temperature: None,
+            stop_sequences: Vec::new(),
+            model_preferences: None,
+        }
+    }
+
+    pub fn user(mut self, text: impl Into<String>) -> Self {
+        self.messages.push(SamplingMessage::text("user", text));
+        self
+    }
+
+    pub fn assistant(mut self, text: impl Into<String>) -> Self {
+        self.messages.push(SamplingMessage::text("assistant", text));
+        self
+    }
+
+    pub fn system(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    pub fn temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+}
+
+/// The client's answer to `sampling/createMessage`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SamplingResponse {
+    pub role: String,
+    pub content: Value,
+    /// The model that actually ran.
+    pub model: String,
+    #[serde(default)]
+    pub stop_reason: Option<String>,
+}
+
+impl SamplingResponse {
+    /// The generated text, when the content is a text block.
+    pub fn text(&self) -> Option<&str> {
+        match self.content.get("type").and_then(Value::as_str) {
+            Some("text") => self.content.get("text").and_then(Value::as_str),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sampling_messages_serialize_as_the_spec_says() {
+        let request = SamplingRequest::new(200)
+            .system("Be brief")
+            .user("Summarize this")
+            .temperature(0.2);
+        assert_eq!(
+            serde_json::to_value(&request).unwrap(),
+            json!({
+                "messages": [{"role": "user", "content": {"type": "text", "text": "Summarize this"}}],
+                "systemPrompt": "Be brief",
+                "maxTokens": 200,
+                "temperature": 0.2
+            })
+        );
+
+        let response: SamplingResponse = serde_json::from_value(json!({
+            "role": "assistant",
+            "content": {"type": "text", "text": "Short."},
+            "model": "claude-3-5-sonnet",
+            "stopReason": "endTurn"
+        }))
+        .unwrap();
+        assert_eq!(response.text(), Some("Short."));
+        assert_eq!(response.stop_reason.as_deref(), Some("endTurn"));
+    }
+}
@@ -0,0 +1,188 @@
+//! Isolation for the interpreters that run client code. Every script starts
+//! with a cleared environment, so tokens such as `MCP_AUTH_TOKENS` are not
+//! inherited, and under the backend `MCP_SANDBOX` names:
+//!
+//! - `bwrap`: [bubblewrap](https://github.com/containers/bubblewrap), with a
+//!   read-only view of the filesystem, private `/tmp` and `/run`, no network
+//!   and no view of other processes
+//! - `user:<uid>[:<gid>]`: another user, in a network namespace of its own,
+//!   entered with `unshare(1)`. The server must run as root. Files the
+//!   server keeps private, such as the admin socket's token, are out of
+//!   reach
+//!
+//! Either way the script has no network, so it cannot get around the
+//! [egress policy](crate::egress).
+
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// Variables passed through to scripts; everything else is cleared.
+pub const PASSTHROUGH_ENV: &[&str] = &["PATH", "LANG", "LC_ALL", "TZ"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Backend {
+    Bwrap,
+    User { uid: u32, gid: u32 },
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Sandbox {
+    backend: Option<Backend>,
+    /// Paths hidden from scripts, such as the admin socket and its token.
+    hidden: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    pub fn new(backend: Backend) -> Self {
+        Self {
+            backend: Some(backend),
+            hidden: Vec::new(),
+        }
+    }
+
+    /// The backend `MCP_SANDBOX` names; unset leaves scripts unsandboxed.
+    /// Under bubblewrap, the admin socket `MCP_ADMIN_SOCKET` names is hidden.
+    pub fn from_env() -> Self {
+        let Ok(value) = std::env::var("MCP_SANDBOX") else {
+            return Self::default();
+        };
+        let Some(backend) = parse_backend(&value) else {
+            tracing::warn!("Invalid MCP_SANDBOX '{}'", value);
+            return Self::default();
+        };
+        let mut sandbox = Self::new(backend);
+        if let Some(socket) = std::env::var_os("MCP_ADMIN_SOCKET") {
+            let socket = PathBuf::from(socket);
+            sandbox.hidden.push(crate::admin::token_path(&socket));
+            sandbox.hidden.push(socket);
+        }
+        sandbox
+    }
+
+    /// Whether scripts run under a backend rather than as the server.
+    pub fn is_isolated(&self) -> bool {
+        self.backend.is_some()
+    }
+
+    /// A command running `program` in the sandbox with a cleared
+    /// environment. `writable` paths stay writable under bubblewrap, which
+    /// otherwise mounts everything read-only.
+    pub fn command(&self, program: impl AsRef<OsStr>, writable: &[&Path]) -> Command {
+        let mut command = match &self.backend {
+            None => Command::new(program),
+            Some(Backend::Bwrap) => {
+                let mut command = Command::new("bwrap");
+                command
+                    .args(["--die-with-parent", "--new-session", "--unshare-all"])
+                    .args(["--ro-bind", "/", "/", "--dev", "/dev", "--proc", "/proc"])
+                    .args(["--tmpfs", "/tmp", "--tmpfs", "/run"]);
+                for path in self.hidden.iter().filter(|path| path.exists()) {
+                    command.arg("--ro-bind").arg("/dev/null").arg(path);
+                }
+                for path in writable {
+                    command.arg("--bind").arg(path).arg(path);
+                }
+                command.arg("--").arg(program);
+                command
+            }
+            Some(Backend::User { uid, gid }) => {
+                let mut command = Command::new("unshare");
+                command
+                    .arg("--net")
+                    .arg(format!("--setuid={}", uid))
+                    .arg(format!("--setgid={}", gid))
+                    .arg("--")
+                    .arg(program);
+                command
+            }
+        };
+        command.env_clear();
+        for name in PASSTHROUGH_ENV {
+            if let Some(value) = std::env::var_os(name) {
+                command.env(name, value);
+            }
+        }
+        command
+    }
+
+    /// Hands `path` to the sandbox's user, for a scratch directory the
+    /// script writes into. Only the `user` backend needs this.
+    pub fn grant(&self, path: &Path) -> std::io::Result<()> {
+        match &self.backend {
+            #[cfg(unix)]
+            Some(Backend::User { uid, gid }) => {
+                std::os::unix::fs::chown(path, Some(*uid), Some(*gid))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+fn parse_backend(value: &str) -> Option<Backend> {
+    if value == "bwrap" {
+        return Some(Backend::Bwrap);
+    }
+    let ids = value.strip_prefix("user:")?;
+    let (uid, gid) = match ids.split_once(':') {
+        Some((uid, gid)) => (uid.parse().ok()?, gid.parse().ok()?),
+        None => {
+            let uid = ids.parse().ok()?;
+            (uid, uid)
+        }
+    };
+    Some(Backend::User { uid, gid })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_backend() {
+        assert_eq!(parse_backend("bwrap"), Some(Backend::Bwrap));
+        assert_eq!(
+            parse_backend("user:65534"),
+            Some(Backend::User {
+                uid: 65534,
+                gid: 65534
+            })
+        );
+        assert_eq!(
+            parse_backend("user:1000:100"),
+            Some(Backend::User {
+                uid: 1000,
+                gid: 100
+            })
+        );
+        assert_eq!(parse_backend("user:nobody"), None);
+        assert_eq!(parse_backend("docker"), None);
+    }
+
+    #[test]
+    fn test_commands_run_under_the_backend_with_a_cleared_environment() {
+        let mut sandbox = Sandbox::new(Backend::Bwrap);
+        sandbox.hidden.push(std::env::temp_dir());
+        let scratch = Path::new("/tmp/scratch");
+        let command = sandbox.command("python3", &[scratch]);
+        let command = command.as_std();
+        assert_eq!(command.get_program(), "bwrap");
+        let args: Vec<_> = command.get_args().map(|a| a.to_str().unwrap()).collect();
+        assert!(args.contains(&"--unshare-all"));
+        assert!(args
+            .windows(3)
+            .any(|a| a[0] == "--ro-bind" && a[1] == "/dev/null"));
+        assert!(args
+            .windows(3)
+            .any(|a| a == ["--bind", "/tmp/scratch", "/tmp/scratch"]));
+        assert_eq!(args.last(), Some(&"python3"));
+        let env: Vec<_> = command.get_envs().map(|(name, _)| name).collect();
+        assert!(env
+            .iter()
+            .all(|name| PASSTHROUGH_ENV.contains(&name.to_str().unwrap())));
+
+        let command = Sandbox::new(Backend::User { uid: 7, gid: 8 }).command("python3", &[]);
+        let args: Vec<_> = command.as_std().get_args().collect();
+        assert_eq!(args, ["--net", "--setuid=7", "--setgid=8", "--", "python3"]);
+    }
+}
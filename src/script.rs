@@ -0,0 +1,143 @@
+use serde_json::Value;
+use std::process::{Output, Stdio};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, Command};
+
+/// Spawns `command` with its stdin and stdout piped, whatever it was set up
+/// with, so neither the child nor anything it starts can read the client's
+/// requests or write into the protocol stream on the server's stdout.
+/// `stderr` may still be inherited, since the server's stderr is only ever
+/// a log. The child is killed when dropped.
+///
+/// Every subprocess goes through here: `clippy.toml` disallows spawning a
+/// `Command` anywhere else.
+#[allow(clippy::disallowed_methods)]
+pub fn spawn(command: &mut Command, stderr: Stdio) -> Result<Child, String> {
+    command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(stderr)
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| format!("Failed to start interpreter: {}", e))
+}
+
+/// Most of a script's stdout, and of its stderr, kept for the result. The
+/// rest is read and dropped, so a chatty script neither blocks on a full
+/// pipe nor grows the server's memory.
+pub const MAX_OUTPUT_BYTES: usize = 1 << 20;
+
+/// Spawns `command`, feeds `input` on stdin and waits for it to exit,
+/// killing the child if it runs longer than `timeout`. Each of stdout and
+/// stderr is cut at [`MAX_OUTPUT_BYTES`], with a note saying so.
+pub async fn run_with_input(
+    mut command: Command,
+    input: &str,
+    timeout: Duration,
+) -> Result<Output, String> {
+    let mut child = spawn(&mut command, Stdio::piped())?;
+
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    stdin
+        .write_all(input.as_bytes())
+        .await
+        .map_err(|e| e.to_string())?;
+    drop(stdin);
+
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let run = async {
+        let (stdout, stderr, status) = tokio::join!(
+            read_capped(stdout, MAX_OUTPUT_BYTES),
+            read_capped(stderr, MAX_OUTPUT_BYTES),
+            child.wait()
+        );
+        Ok::<_, std::io::Error>(Output {
+            status: status?,
+            stdout: stdout?,
+            stderr: stderr?,
+        })
+    };
+    match tokio::time::timeout(timeout, run).await {
+        Ok(output) => output.map_err(|e| e.to_string()),
+        Err(_) => Err(format!("Execution timed out after {}s", timeout.as_secs())),
+    }
+}
+
+/// Everything `reader` yields, up to `limit` bytes followed by a note when
+/// there was more.
+async fn read_capped(mut reader: impl AsyncRead + Unpin, limit: usize) -> std::io::Result<Vec<u8>> {
+    let mut kept = Vec::new();
+    let mut dropped = 0u64;
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = reader.read(&mut buf).await?;
+        if n == 0 {
+            break;
+        }
+        let keep = n.min(limit - kept.len());
+        kept.extend_from_slice(&buf[..keep]);
+        dropped += (n - keep) as u64;
+    }
+    if dropped > 0 {
+        kept.extend_from_slice(format!("\n[{} more bytes not shown]\n", dropped).as_bytes());
+    }
+    Ok(kept)
+}
+
+/// Converts a finished script into text content blocks: stdout, stderr and
+/// the exit status when it is non-zero.
+pub fn output_content(output: &Output) -> Vec<Value> {
+    let mut content = Vec::new();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stdout.is_empty() {
+        content.push(serde_json::json!({"type": "text", "text": stdout}));
+    }
+    if !stderr.is_empty() {
+        content.push(serde_json::json!({"type": "text", "text": format!("stderr:\n{}", stderr)}));
+    }
+    if !output.status.success() {
+        let status = output
+            .status
+            .code()
+            .map(|c| c.to_string())
+            .unwrap_or_else(|| "signal".to_string());
+        content
+            .push(serde_json::json!({"type": "text", "text": format!("Exit status: {}", status)}));
+    }
+    content
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_child_stdout_is_always_captured() {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("cat; echo out; echo err >&2");
+        command.stdout(Stdio::inherit()).stdin(Stdio::inherit());
+        let output = run_with_input(command, "in\n", Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "in\nout\n");
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "err\n");
+    }
+
+    #[tokio::test]
+    async fn test_output_is_capped() {
+        let mut command = Command::new("sh");
+        command.arg("-c").arg("head -c 2000000 /dev/zero");
+        let output = run_with_input(command, "", Duration::from_secs(5))
+            .await
+            .unwrap();
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        assert!(stdout.ends_with(&format!(
+            "\n[{} more bytes not shown]\n",
+            2_000_000 - MAX_OUTPUT_BYTES
+        )));
+        assert!(output.stdout.len() < MAX_OUTPUT_BYTES + 64);
+    }
+}
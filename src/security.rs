@@ -0,0 +1,246 @@
+//! Security events for SIEMs, in an Elastic Common Schema (ECS) compatible
+//! shape, written through their own [`AuditLog`] so they stay separate from
+//! the general audit trail.
+
+use serde_json::{json, Value};
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::audit::{self, AuditLog};
+
+pub const ECS_VERSION: &str = "8.11.0";
+pub const DATASET: &str = "mcp_server.security";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecurityEventKind {
+    /// A client presented missing or invalid credentials.
+    AuthFailure,
+    /// A request was refused by configured policy: an origin check, a
+    /// rejected or timed-out approval, a workflow transition.
+    PolicyDenied,
+    /// A client exceeded a rate limit.
+    RateLimited,
+    /// Sandboxed code tried to escape its restrictions.
+    SandboxViolation,
+}
+
+impl SecurityEventKind {
+    /// `event.action` in the ECS record.
+    pub fn action(self) -> &'static str {
+        match self {
+            Self::AuthFailure => "auth_failure",
+            Self::PolicyDenied => "policy_denied",
+            Self::RateLimited => "rate_limited",
+            Self::SandboxViolation => "sandbox_violation",
+        }
+    }
+
+    /// ECS `event.kind`, `event.category` and `event.type`.
+    fn classification(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            Self::AuthFailure => ("event", "authentication", "info"),
+            Self::PolicyDenied => ("event", "intrusion_detection", "denied"),
+            Self::RateLimited => ("event", "network", "denied"),
+            Self::SandboxViolation => ("alert", "intrusion_detection", "denied"),
+        }
+    }
+
+    /// ECS `event.severity`, higher is more severe.
+    fn severity(self) -> u8 {
+        match self {
+            Self::RateLimited => 2,
+            Self::PolicyDenied => 3,
+            Self::AuthFailure => 4,
+            Self::SandboxViolation => 6,
+        }
+    }
+}
+
+/// One security event. Build with [`new`](Self::new) and the setters, then
+/// pass to [`SecurityLog::report`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SecurityEvent {
+    pub kind: SecurityEventKind,
+    pub message: String,
+    /// Name of the rule or policy that fired, as ECS `rule.name`.
+    pub rule: Option<String>,
+    pub source_ip: Option<IpAddr>,
+    pub user: Option<String>,
+    pub tool: Option<String>,
+    /// Extra fields, published under `mcp`.
+    pub details: serde_json::Map<String, Value>,
+    pub at: SystemTime,
+}
+
+impl SecurityEvent {
+    pub fn new(kind: SecurityEventKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            rule: None,
+            source_ip: None,
+            user: None,
+            tool: None,
+            details: serde_json::Map::new(),
+            at: SystemTime::now(),
+        }
+    }
+
+    pub fn rule(mut self, rule: impl Into<String>) -> Self {
+        self.rule = Some(rule.into());
+        self
+    }
+
+    pub fn source_ip(mut self, ip: IpAddr) -> Self {
+        self.source_ip = Some(ip);
+        self
+    }
+
+    pub fn user(mut self, user: impl Into<String>) -> Self {
+        self.user = Some(user.into());
+        self
+    }
+
+    pub fn tool(mut self, tool: impl Into<String>) -> Self {
+        self.tool = Some(tool.into());
+        self
+    }
+
+    pub fn detail(mut self, key: &str, value: impl Into<Value>) -> Self {
+        self.details.insert(key.to_string(), value.into());
+        self
+    }
+
+    /// The event as an ECS document.
+    pub fn to_ecs(&self) -> Value {
+        let (kind, category, event_type) = self.kind.classification();
+        let mut record = json!({
+            "@timestamp": rfc3339(self.at),
+            "ecs": {"version": ECS_VERSION},
+            "event": {
+                "kind": kind,
+                "category": [category],
+                "type": [event_type],
+                "action": self.kind.action(),
+                "outcome": "failure",
+                "severity": self.kind.severity(),
+                "dataset": DATASET,
+                "provider": "mcp-server",
+            },
+            "message": self.message,
+            "service": {"name": "mcp-server", "version": env!("CARGO_PKG_VERSION")},
+        });
+        if let Some(rule) = &self.rule {
+            record["rule"] = json!({ "name": rule });
+        }
+        if let Some(ip) = self.source_ip {
+            record["source"] = json!({ "ip": ip.to_string() });
+        }
+        if let Some(user) = &self.user {
+            record["user"] = json!({ "name": user });
+        }
+        let mut mcp = self.details.clone();
+        if let Some(tool) = &self.tool {
+            mcp.insert("tool".to_string(), tool.clone().into());
+        }
+        if !mcp.is_empty() {
+            record["mcp"] = Value::Object(mcp);
+        }
+        record
+    }
+}
+
+/// Where security events go. Clones share the same sinks; a log with no
+/// sinks discards events.
+#[derive(Clone)]
+pub struct SecurityLog {
+    log: Arc<AuditLog>,
+}
+
+impl SecurityLog {
+    pub fn new(log: AuditLog) -> Self {
+        Self { log: Arc::new(log) }
+    }
+
+    /// Log for the sinks in `MCP_SECURITY_SINKS`, or the same destinations
+    /// as the audit log when it is unset.
+    pub fn from_env() -> Self {
+        let specs = match std::env::var("MCP_SECURITY_SINKS") {
+            Ok(list) => audit::parse_sink_list(&list),
+            Err(_) => audit::sink_specs_from_env(),
+        };
+        Self::new(AuditLog::from_specs(&specs))
+    }
+
+    pub fn report(&self, event: SecurityEvent) {
+        self.log.record(event.to_ecs());
+    }
+
+    /// Blocks until the events reported so far reach their sinks.
+    pub fn flush(&self) {
+        self.log.flush();
+    }
+}
+
+impl Default for SecurityLog {
+    fn default() -> Self {
+        Self::new(AuditLog::new(1))
+    }
+}
+
+/// `YYYY-MM-DDTHH:MM:SS.mmmZ` in UTC.
+pub(crate) fn rfc3339(at: SystemTime) -> String {
+    let since_epoch = at.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs();
+    let (days, rem) = (secs / 86_400, secs % 86_400);
+    let (year, month, day) = crate::locale::civil_date(days as i64);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        since_epoch.subsec_millis()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(UNIX_EPOCH), "1970-01-01T00:00:00.000Z");
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_millis(951_782_400_123)),
+            "2000-02-29T00:00:00.123Z"
+        );
+        assert_eq!(
+            rfc3339(UNIX_EPOCH + Duration::from_secs(1_790_000_000)),
+            "2026-09-21T14:13:20.000Z"
+        );
+    }
+
+    #[test]
+    fn test_ecs_document() {
+        let event = SecurityEvent::new(SecurityEventKind::PolicyDenied, "Origin not allowed")
+            .rule("http_origin")
+            .source_ip("10.0.0.7".parse().unwrap())
+            .detail("origin", "http://evil.example");
+        let doc = event.to_ecs();
+
+        assert_eq!(doc["ecs"]["version"], ECS_VERSION);
+        assert_eq!(doc["event"]["action"], "policy_denied");
+        assert_eq!(doc["event"]["category"], json!(["intrusion_detection"]));
+        assert_eq!(doc["event"]["type"], json!(["denied"]));
+        assert_eq!(doc["event"]["outcome"], "failure");
+        assert_eq!(doc["rule"]["name"], "http_origin");
+        assert_eq!(doc["source"]["ip"], "10.0.0.7");
+        assert_eq!(doc["mcp"]["origin"], "http://evil.example");
+        assert!(doc.get("user").is_none());
+    }
+}
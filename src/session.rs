@@ -0,0 +1,829 @@
+//! Per-connection state. The [`McpServer`](crate::McpServer) is shared by
+//! every client, so anything that belongs to one client's conversation lives
+//! in a [`Session`] owned by its transport: one per stdio or TCP connection,
+//! one per HTTP `Mcp-Session-Id` and one per SSE stream.
+
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::oneshot;
+
+use crate::cancellation::CancellationToken;
+use crate::gc::GcReport;
+use crate::locale::Locale;
+use crate::logging::LogLevel;
+use crate::protocol::{self, McpError, McpRequest, McpResponse};
+use crate::roots::Root;
+use crate::summarize;
+use crate::transport::frames;
+use crate::uploads::Upload;
+use crate::workspace::{self, Workspace};
+
+/// Where a session is in the MCP initialization handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lifecycle {
+    /// Waiting for `initialize`.
+    Uninitialized,
+    /// `initialize` has been admitted; waiting for `notifications/initialized`.
+    Initializing,
+    /// The handshake is done and every method is available.
+    Ready,
+}
+
+/// Outcome of [`Session::ping_due`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Ping {
+    /// The client spoke recently, or a ping is still awaiting its answer.
+    NotDue,
+    /// The client has been quiet; send this `ping` request.
+    Send(Value),
+    /// The last ping went unanswered for a whole interval.
+    Unanswered,
+}
+
+/// Delivers a server-initiated message to the session's client, returning
+/// whether it could be handed to the transport.
+type Notifier = Arc<dyn Fn(Value) -> bool + Send + Sync>;
+
+/// When the client was last heard from, and the ping sent since, if any.
+struct Heartbeat {
+    last_seen: Instant,
+    pings_sent: u64,
+    pending_since: Option<Instant>,
+}
+
+/// Texts a session keeps for `resources/read`, by URI. Once it holds more
+/// than `max_entries` or `max_bytes`, the oldest are evicted.
+struct Stash {
+    entries: HashMap<String, (String, Instant)>,
+    /// URIs, oldest first.
+    order: VecDeque<String>,
+    bytes: usize,
+    max_entries: usize,
+    max_bytes: usize,
+}
+
+impl Stash {
+    fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            bytes: 0,
+            max_entries,
+            max_bytes,
+        }
+    }
+
+    fn get(&self, uri: &str) -> Option<String> {
+        self.entries.get(uri).map(|(text, _)| text.clone())
+    }
+
+    /// Stores `text`, then evicts from the oldest until both caps hold
+    /// again. The newest entry is always kept.
+    fn insert(&mut self, uri: String, text: String) {
+        self.remove(&uri);
+        self.bytes += text.len();
+        self.order.push_back(uri.clone());
+        self.entries.insert(uri, (text, Instant::now()));
+        while self.order.len() > 1
+            && (self.order.len() > self.max_entries || self.bytes > self.max_bytes)
+        {
+            let oldest = self.order.pop_front().unwrap();
+            if let Some((text, _)) = self.entries.remove(&oldest) {
+                self.bytes -= text.len();
+            }
+        }
+    }
+
+    fn remove(&mut self, uri: &str) {
+        if let Some((text, _)) = self.entries.remove(uri) {
+            self.bytes -= text.len();
+            self.order.retain(|u| u != uri);
+        }
+    }
+
+    /// Drops entries stored more than `max_age` ago, returning how many
+    /// and their size in bytes.
+    fn expire(&mut self, max_age: Duration) -> (usize, u64) {
+        let (mut removed, mut bytes) = (0, 0);
+        while let Some(oldest) = self.order.front() {
+            if self.entries[oldest].1.elapsed() < max_age {
+                break;
+            }
+            let (text, _) = self.entries.remove(oldest).unwrap();
+            self.order.pop_front();
+            self.bytes -= text.len();
+            removed += 1;
+            bytes += text.len() as u64;
+        }
+        (removed, bytes)
+    }
+}
+
+pub struct Session {
+    id: String,
+    lifecycle: Mutex<Lifecycle>,
+    heartbeat: Mutex<Heartbeat>,
+    /// Created on first use and removed from disk when the session is
+    /// dropped.
+    workspace: Mutex<Option<Arc<Workspace>>>,
+    accepts_uploads: bool,
+    /// Stored uploads and when they arrived, by URI.
+    uploads: Mutex<BTreeMap<String, (Upload, Instant)>>,
+    /// Frames longer than this are split; `None` sends them whole.
+    max_frame: Option<usize>,
+    /// Incoming messages longer than this are parsed as they arrive.
+    stream_threshold: usize,
+    /// Incoming messages longer than this are refused.
+    max_message: usize,
+    /// Parts of split results, up to
+    /// [`MAX_STORED_PARTS`](crate::transport::frames::MAX_STORED_PARTS).
+    frame_parts: Mutex<Stash>,
+    /// Full text of summarized results, up to
+    /// [`MAX_STORED_RESULTS`](crate::summarize::MAX_STORED_RESULTS).
+    full_results: Mutex<Stash>,
+    /// Set by the transport; `None` when it cannot send to the client.
+    notifier: Mutex<Option<Notifier>>,
+    log_level: Mutex<LogLevel>,
+    /// Cancellation tokens of requests being answered, by JSON-encoded id.
+    in_flight: Mutex<HashMap<String, CancellationToken>>,
+    subscriptions: Mutex<Subscriptions>,
+    /// The `capabilities` the client sent with `initialize`.
+    client_capabilities: Mutex<Value>,
+    outgoing: Mutex<Outgoing>,
+    /// The client's roots, once `roots/list` has answered.
+    roots: Mutex<Option<Vec<Root>>>,
+    /// The `locale` the client sent with `initialize`, if any.
+    locale: Mutex<Option<Locale>>,
+    /// The revision agreed on `initialize`.
+    protocol_version: Mutex<&'static str>,
+}
+
+/// Requests sent to the client that await its response, by id.
+#[derive(Default)]
+struct Outgoing {
+    sent: u64,
+    pending: HashMap<String, oneshot::Sender<McpResponse>>,
+}
+
+/// Forgets a request to the client once its caller stops waiting.
+struct Awaiting<'a> {
+    session: &'a Session,
+    id: String,
+}
+
+impl Drop for Awaiting<'_> {
+    fn drop(&mut self) {
+        self.session
+            .outgoing
+            .lock()
+            .unwrap()
+            .pending
+            .remove(&self.id);
+    }
+}
+
+/// Subscribed resource URIs with their last fingerprints, and whether a
+/// watcher task is checking them.
+#[derive(Default)]
+struct Subscriptions {
+    fingerprints: BTreeMap<String, Option<String>>,
+    watching: bool,
+    /// Wakes the watcher when a URI is added, to start watching its file.
+    added: Arc<tokio::sync::Notify>,
+}
+
+/// Tracks a request from [`Session::begin_request`] until it is dropped.
+pub struct InFlight {
+    session: Arc<Session>,
+    key: String,
+    token: CancellationToken,
+}
+
+impl InFlight {
+    pub fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        let mut in_flight = self.session.in_flight.lock().unwrap();
+        // A later request may have reused the id.
+        if in_flight
+            .get(&self.key)
+            .is_some_and(|t| t.same_as(&self.token))
+        {
+            in_flight.remove(&self.key);
+        }
+    }
+}
+
+impl Default for Session {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Session {
+    pub fn new() -> Self {
+        Self::with_id(uuid::Uuid::new_v4().to_string())
+    }
+
+    pub fn with_id(id: impl Into<String>) -> Self {
+        Self {
+            id: id.into(),
+            lifecycle: Mutex::new(Lifecycle::Uninitialized),
+            heartbeat: Mutex::new(Heartbeat {
+                last_seen: Instant::now(),
+                pings_sent: 0,
+                pending_since: None,
+            }),
+            workspace: Mutex::new(None),
+            accepts_uploads: false,
+            uploads: Mutex::new(BTreeMap::new()),
+            max_frame: None,
+            stream_threshold: crate::transport::stream::DEFAULT_THRESHOLD,
+            max_message: crate::transport::stream::DEFAULT_MAX_MESSAGE,
+            frame_parts: Mutex::new(Stash::new(
+                frames::MAX_STORED_PARTS,
+                frames::MAX_STORED_BYTES,
+            )),
+            full_results: Mutex::new(Stash::new(
+                summarize::MAX_STORED_RESULTS,
+                summarize::MAX_STORED_BYTES,
+            )),
+            notifier: Mutex::new(None),
+            log_level: Mutex::new(LogLevel::Info),
+            in_flight: Mutex::new(HashMap::new()),
+            subscriptions: Mutex::new(Subscriptions::default()),
+            client_capabilities: Mutex::new(Value::Null),
+            outgoing: Mutex::new(Outgoing::default()),
+            roots: Mutex::new(None),
+            locale: Mutex::new(None),
+            protocol_version: Mutex::new(protocol::PROTOCOL_VERSION),
+        }
+    }
+
+    /// Marks a session on a transport that takes uploads, so `initialize`
+    /// advertises resources even when no provider is registered.
+    pub fn accepting_uploads(mut self) -> Self {
+        self.accepts_uploads = true;
+        self
+    }
+
+    pub fn accepts_uploads(&self) -> bool {
+        self.accepts_uploads
+    }
+
+    /// Marks a session on a transport that caps its frames at `bytes`, so
+    /// longer tool results are split into parts read as resources.
+    pub fn with_max_frame(mut self, bytes: usize) -> Self {
+        self.max_frame = Some(bytes);
+        self
+    }
+
+    pub fn max_frame(&self) -> Option<usize> {
+        self.max_frame
+    }
+
+    /// Has a line-delimited transport parse an incoming message as it
+    /// arrives once it passes `bytes`, instead of buffering it whole; see
+    /// [`stream`](crate::transport::stream).
+    pub fn with_stream_threshold(mut self, bytes: usize) -> Self {
+        self.stream_threshold = bytes;
+        self
+    }
+
+    pub fn stream_threshold(&self) -> usize {
+        self.stream_threshold
+    }
+
+    /// Has a line-delimited transport refuse an incoming message longer
+    /// than `bytes`, skipping the rest of its line unread.
+    pub fn with_max_message(mut self, bytes: usize) -> Self {
+        self.max_message = bytes;
+        self
+    }
+
+    pub fn max_message(&self) -> usize {
+        self.max_message
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn lifecycle(&self) -> Lifecycle {
+        *self.lifecycle.lock().unwrap()
+    }
+
+    /// Records that the client sent something, which also answers any
+    /// outstanding ping: a client busy sending requests is alive.
+    pub fn touch(&self) {
+        let mut heartbeat = self.heartbeat.lock().unwrap();
+        heartbeat.last_seen = Instant::now();
+        heartbeat.pending_since = None;
+    }
+
+    /// Whether a network transport should ping the client: after `interval`
+    /// of silence a `ping` request is due, and a ping still unanswered an
+    /// interval later means the connection is dead.
+    pub fn ping_due(&self, interval: Duration) -> Ping {
+        let mut heartbeat = self.heartbeat.lock().unwrap();
+        match heartbeat.pending_since {
+            Some(sent) if sent.elapsed() >= interval => Ping::Unanswered,
+            Some(_) => Ping::NotDue,
+            None if heartbeat.last_seen.elapsed() < interval => Ping::NotDue,
+            None => {
+                heartbeat.pings_sent += 1;
+                heartbeat.pending_since = Some(Instant::now());
+                Ping::Send(serde_json::json!({
+                    "jsonrpc": "2.0",
+                    "id": format!("ping-{}", heartbeat.pings_sent),
+                    "method": "ping"
+                }))
+            }
+        }
+    }
+
+    /// Lets the server send notifications to the client. Transports call
+    /// this when they create the session; `notifier` returns `false` when
+    /// the message cannot be delivered right now, such as an HTTP session
+    /// with no `GET` stream open.
+    pub fn set_notifier(&self, notifier: impl Fn(Value) -> bool + Send + Sync + 'static) {
+        *self.notifier.lock().unwrap() = Some(Arc::new(notifier));
+    }
+
+    /// Drops the notifier, e.g. when the session is ended while tasks still
+    /// hold it.
+    pub fn clear_notifier(&self) {
+        *self.notifier.lock().unwrap() = None;
+    }
+
+    /// Whether [`notify`](Self::notify) reaches the client.
+    pub fn can_notify(&self) -> bool {
+        self.notifier.lock().unwrap().is_some()
+    }
+
+    /// Sends a JSON-RPC notification to the client. Returns `false` when
+    /// the transport could not deliver it.
+    pub fn notify(&self, method: &str, params: Value) -> bool {
+        let Some(notifier) = self.notifier.lock().unwrap().clone() else {
+            return false;
+        };
+        notifier(serde_json::json!({
+            "jsonrpc": "2.0",
+            "method": method,
+            "params": params,
+        }))
+    }
+
+    /// Sends a request to the client, such as `sampling/createMessage`,
+    /// and waits up to `timeout` for its response. Fails at once when the
+    /// transport cannot deliver the request. Dropping the future stops
+    /// waiting; a response arriving later is ignored.
+    pub async fn request(
+        &self,
+        method: &str,
+        params: Value,
+        timeout: Duration,
+    ) -> Result<Value, McpError> {
+        let failure = |message: &str| McpError {
+            code: -32603,
+            message: message.to_string(),
+            data: None,
+        };
+        let Some(notifier) = self.notifier.lock().unwrap().clone() else {
+            return Err(failure("The transport cannot send requests to the client"));
+        };
+        let (sender, receiver) = oneshot::channel();
+        let id = {
+            let mut outgoing = self.outgoing.lock().unwrap();
+            outgoing.sent += 1;
+            let id = format!("server-{}", outgoing.sent);
+            outgoing.pending.insert(id.clone(), sender);
+            id
+        };
+        let _awaiting = Awaiting {
+            session: self,
+            id: id.clone(),
+        };
+        let sent = notifier(serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params,
+        }));
+        if !sent {
+            return Err(failure("No stream to the client is open"));
+        }
+        let response = tokio::time::timeout(timeout, receiver)
+            .await
+            .map_err(|_| failure(&format!("The client did not answer {}", method)))?
+            .map_err(|_| failure("The client went away"))?;
+        match response.error {
+            Some(error) => Err(error),
+            None => Ok(response.result.unwrap_or(Value::Null)),
+        }
+    }
+
+    /// Hands a response from the client to the [`request`](Self::request)
+    /// awaiting it. Responses to pings, or to requests no longer awaited,
+    /// only count as the client being alive.
+    pub fn receive_response(&self, response: McpResponse) {
+        self.touch();
+        let Some(Value::String(id)) = &response.id else {
+            return;
+        };
+        let waiting = self.outgoing.lock().unwrap().pending.remove(id);
+        if let Some(waiting) = waiting {
+            let _ = waiting.send(response);
+        }
+    }
+
+    /// What the client said it supports, such as `sampling` or `roots`.
+    pub fn client_capabilities(&self) -> Value {
+        self.client_capabilities.lock().unwrap().clone()
+    }
+
+    pub fn set_client_capabilities(&self, capabilities: Value) {
+        *self.client_capabilities.lock().unwrap() = capabilities;
+    }
+
+    /// The directories the client shared, or `None` when it has not said,
+    /// either because it lacks the `roots` capability or because
+    /// `roots/list` has not answered yet.
+    pub fn roots(&self) -> Option<Vec<Root>> {
+        self.roots.lock().unwrap().clone()
+    }
+
+    pub fn set_roots(&self, roots: Vec<Root>) {
+        *self.roots.lock().unwrap() = Some(roots);
+    }
+
+    /// The locale the client asked for, or `None` to use the server's.
+    pub fn locale(&self) -> Option<Locale> {
+        self.locale.lock().unwrap().clone()
+    }
+
+    pub fn set_locale(&self, locale: Locale) {
+        *self.locale.lock().unwrap() = Some(locale);
+    }
+
+    /// The protocol revision agreed with the client, the newest until it
+    /// has initialized.
+    pub fn protocol_version(&self) -> &'static str {
+        *self.protocol_version.lock().unwrap()
+    }
+
+    pub fn set_protocol_version(&self, version: &'static str) {
+        *self.protocol_version.lock().unwrap() = version;
+    }
+
+    pub fn log_level(&self) -> LogLevel {
+        *self.log_level.lock().unwrap()
+    }
+
+    /// The least severe level sent to the client, from `logging/setLevel`.
+    /// Until it is set, entries at `info` and above are sent.
+    pub fn set_log_level(&self, level: LogLevel) {
+        *self.log_level.lock().unwrap() = level;
+    }
+
+    /// Sends a `notifications/message` entry unless `level` is below the
+    /// client's chosen level.
+    pub fn log(&self, level: LogLevel, logger: Option<&str>, data: Value) {
+        if level < self.log_level() {
+            return;
+        }
+        let mut params = serde_json::json!({ "level": level, "data": data });
+        if let Some(logger) = logger {
+            params["logger"] = logger.into();
+        }
+        self.notify("notifications/message", params);
+    }
+
+    /// Starts tracking request `id` so `notifications/cancelled` can reach
+    /// it, until the returned guard is dropped.
+    pub fn begin_request(self: &Arc<Self>, id: &Value) -> InFlight {
+        let key = id.to_string();
+        let token = CancellationToken::new();
+        self.in_flight
+            .lock()
+            .unwrap()
+            .insert(key.clone(), token.clone());
+        InFlight {
+            session: self.clone(),
+            key,
+            token,
+        }
+    }
+
+    /// Cancels request `id` if it is still in flight. Returns `false` for an
+    /// unknown or finished request, which the spec says to ignore.
+    pub fn cancel_request(&self, id: &Value) -> bool {
+        match self.in_flight.lock().unwrap().get(&id.to_string()) {
+            Some(token) => {
+                token.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Subscribes to `uri`, whose contents currently have `fingerprint`.
+    /// Returns `true` when no watcher is checking this session's
+    /// subscriptions yet, so the caller should start one.
+    pub fn subscribe(&self, uri: &str, fingerprint: Option<String>) -> bool {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        subscriptions
+            .fingerprints
+            .insert(uri.to_string(), fingerprint);
+        subscriptions.added.notify_one();
+        !std::mem::replace(&mut subscriptions.watching, true)
+    }
+
+    /// Notified each time [`subscribe`](Self::subscribe) adds a URI.
+    pub(crate) fn subscriptions_added(&self) -> Arc<tokio::sync::Notify> {
+        self.subscriptions.lock().unwrap().added.clone()
+    }
+
+    /// Returns `false` when the session was not subscribed to `uri`.
+    pub fn unsubscribe(&self, uri: &str) -> bool {
+        self.subscriptions
+            .lock()
+            .unwrap()
+            .fingerprints
+            .remove(uri)
+            .is_some()
+    }
+
+    /// Subscribed URIs, sorted.
+    pub fn subscriptions(&self) -> Vec<String> {
+        let subscriptions = self.subscriptions.lock().unwrap();
+        subscriptions.fingerprints.keys().cloned().collect()
+    }
+
+    /// Stores `uri`'s latest fingerprint. Returns `true` when it differs
+    /// from the last one and the session is still subscribed.
+    pub(crate) fn record_fingerprint(&self, uri: &str, fingerprint: Option<String>) -> bool {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        match subscriptions.fingerprints.get_mut(uri) {
+            Some(last) if *last != fingerprint => {
+                *last = fingerprint;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Marks the watcher stopped if there is nothing left to poll, checked
+    /// under the same lock as [`subscribe`](Self::subscribe) so a new
+    /// subscription either sees it running or starts another.
+    pub(crate) fn stop_watching_if_idle(&self) -> bool {
+        let mut subscriptions = self.subscriptions.lock().unwrap();
+        if subscriptions.fingerprints.is_empty() {
+            subscriptions.watching = false;
+        }
+        !subscriptions.watching
+    }
+
+    /// This session's scratch directory, created with the
+    /// `MCP_WORKSPACE_QUOTA_BYTES` quota the first time it is asked for.
+    pub fn workspace(&self) -> Result<Arc<Workspace>, String> {
+        let mut slot = self.workspace.lock().unwrap();
+        if let Some(workspace) = slot.as_ref() {
+            return Ok(workspace.clone());
+        }
+        let workspace = Arc::new(Workspace::create(workspace::quota_from_env())?);
+        *slot = Some(workspace.clone());
+        Ok(workspace)
+    }
+
+    /// Files uploaded in this session, sorted by URI.
+    pub fn uploads(&self) -> Vec<Upload> {
+        let uploads = self.uploads.lock().unwrap();
+        uploads.values().map(|(upload, _)| upload.clone()).collect()
+    }
+
+    pub fn upload(&self, uri: &str) -> Option<Upload> {
+        let uploads = self.uploads.lock().unwrap();
+        uploads.get(uri).map(|(upload, _)| upload.clone())
+    }
+
+    pub(crate) fn add_upload(&self, upload: Upload) {
+        self.uploads
+            .lock()
+            .unwrap()
+            .insert(upload.uri.clone(), (upload, Instant::now()));
+    }
+
+    pub(crate) fn remove_upload(&self, uri: &str) {
+        self.uploads.lock().unwrap().remove(uri);
+    }
+
+    /// One part of a split result, by its `frame://` URI.
+    pub fn frame_part(&self, uri: &str) -> Option<String> {
+        self.frame_parts.lock().unwrap().get(uri)
+    }
+
+    /// Stores one part of a split result, evicting the oldest parts once
+    /// the session holds too many or too large.
+    pub(crate) fn add_frame_part(&self, uri: String, part: String) {
+        self.frame_parts.lock().unwrap().insert(uri, part);
+    }
+
+    /// The full text of a summarized result, by its `summary://` URI.
+    pub fn full_result(&self, uri: &str) -> Option<String> {
+        self.full_results.lock().unwrap().get(uri)
+    }
+
+    /// Stores a summarized result's full text, evicting the oldest once the
+    /// session holds too many or too large.
+    pub(crate) fn add_full_result(&self, uri: String, text: String) {
+        self.full_results.lock().unwrap().insert(uri, text);
+    }
+
+    /// Drops split-result parts, summarized results and uploads stored
+    /// longer than `max_age` ago, deleting the uploads from the workspace,
+    /// and reports what was reclaimed.
+    pub fn expire(&self, max_age: Duration) -> GcReport {
+        let mut report = GcReport::default();
+        for stash in [&self.frame_parts, &self.full_results] {
+            let (results, bytes) = stash.lock().unwrap().expire(max_age);
+            report.session_results += results;
+            report.bytes_reclaimed += bytes;
+        }
+        let workspace = self.workspace.lock().unwrap().clone();
+        self.uploads.lock().unwrap().retain(|uri, (upload, at)| {
+            if at.elapsed() < max_age {
+                return true;
+            }
+            if let Some(workspace) = &workspace {
+                let _ = workspace.delete(uri);
+            }
+            report.uploads += 1;
+            report.bytes_reclaimed += upload.size;
+            false
+        });
+        report
+    }
+
+    /// Checks `request` against the handshake and advances it: `initialize`
+    /// is only accepted once, `notifications/initialized` completes the
+    /// handshake, and every other request except `ping` is refused until
+    /// then. The error is the message for a `-32600` reply. Transports call
+    /// this in the order messages arrive, before dispatching, so a request
+    /// sent right after `notifications/initialized` is admitted even when
+    /// the two are handled concurrently.
+    pub fn admit(&self, request: &McpRequest) -> Result<(), &'static str> {
+        self.touch();
+        let mut lifecycle = self.lifecycle.lock().unwrap();
+        let refusal = match (*lifecycle, request.method.as_str()) {
+            (_, "ping") => return Ok(()),
+            (Lifecycle::Uninitialized, "initialize") => {
+                *lifecycle = Lifecycle::Initializing;
+                return Ok(());
+            }
+            (_, "initialize") => "Session is already initialized",
+            (Lifecycle::Initializing, "notifications/initialized") => {
+                *lifecycle = Lifecycle::Ready;
+                return Ok(());
+            }
+            (Lifecycle::Ready, _) => return Ok(()),
+            (Lifecycle::Uninitialized, _) => "Server not initialized: send initialize first",
+            (Lifecycle::Initializing, _) => {
+                "Server not initialized: send notifications/initialized first"
+            }
+        };
+        Err(refusal)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn message(id: Option<i64>, method: &str) -> McpRequest {
+        McpRequest {
+            jsonrpc: "2.0".to_string(),
+            id: id.map(|id| json!(id)),
+            method: method.to_string(),
+            params: None,
+        }
+    }
+
+    #[test]
+    fn test_handshake() {
+        let session = Session::new();
+        assert!(session.admit(&message(Some(0), "ping")).is_ok());
+        assert!(session
+            .admit(&message(Some(1), "tools/list"))
+            .unwrap_err()
+            .contains("send initialize"));
+
+        assert!(session.admit(&message(Some(2), "initialize")).is_ok());
+        assert_eq!(session.lifecycle(), Lifecycle::Initializing);
+        assert!(session
+            .admit(&message(Some(3), "tools/list"))
+            .unwrap_err()
+            .contains("notifications/initialized"));
+
+        assert!(session
+            .admit(&message(None, "notifications/initialized"))
+            .is_ok());
+        assert_eq!(session.lifecycle(), Lifecycle::Ready);
+        assert!(session.admit(&message(Some(4), "tools/list")).is_ok());
+        assert!(session.admit(&message(Some(5), "initialize")).is_err());
+    }
+
+    #[test]
+    fn test_log_entries_respect_level() {
+        let session = Session::new();
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        assert!(!session.notify("notifications/message", json!({})));
+
+        let outbox = sent.clone();
+        session.set_notifier(move |message| {
+            outbox.lock().unwrap().push(message);
+            true
+        });
+        session.log(LogLevel::Debug, None, json!("hidden"));
+        session.log(LogLevel::Warning, Some("python_run"), json!("slow"));
+        session.set_log_level(LogLevel::Debug);
+        session.log(LogLevel::Debug, None, json!({"step": 1}));
+
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(sent[0]["method"], "notifications/message");
+        assert_eq!(
+            sent[0]["params"],
+            json!({"level": "warning", "logger": "python_run", "data": "slow"})
+        );
+        assert_eq!(sent[1]["params"]["level"], "debug");
+    }
+
+    #[tokio::test]
+    async fn test_requests_fail_fast_when_undeliverable_and_time_out() {
+        let session = Session::new();
+        session.set_notifier(|_| false);
+        let err = session
+            .request("roots/list", json!({}), Duration::from_secs(60))
+            .await
+            .unwrap_err();
+        assert_eq!(err.message, "No stream to the client is open");
+
+        session.set_notifier(|_| true);
+        let err = session
+            .request("roots/list", json!({}), Duration::from_millis(10))
+            .await
+            .unwrap_err();
+        assert_eq!(err.message, "The client did not answer roots/list");
+        assert!(session.outgoing.lock().unwrap().pending.is_empty());
+    }
+
+    #[test]
+    fn test_pings_idle_clients() {
+        let session = Session::new();
+        let interval = Duration::from_millis(20);
+        assert_eq!(session.ping_due(interval), Ping::NotDue);
+
+        std::thread::sleep(interval);
+        let Ping::Send(ping) = session.ping_due(interval) else {
+            panic!("expected a ping");
+        };
+        assert_eq!(ping["method"], "ping");
+        assert_eq!(session.ping_due(interval), Ping::NotDue);
+        session.touch();
+        assert_eq!(session.ping_due(interval), Ping::NotDue);
+
+        std::thread::sleep(interval);
+        assert!(matches!(session.ping_due(interval), Ping::Send(_)));
+        std::thread::sleep(interval);
+        assert_eq!(session.ping_due(interval), Ping::Unanswered);
+    }
+
+    #[test]
+    fn test_stash_evicts_the_oldest_over_either_cap() {
+        let mut stash = Stash::new(2, 10);
+        stash.insert("a".to_string(), "1234".to_string());
+        stash.insert("b".to_string(), "1234".to_string());
+        stash.insert("c".to_string(), "1234".to_string());
+        assert!(stash.get("a").is_none());
+        assert_eq!(stash.get("b").as_deref(), Some("1234"));
+
+        stash.insert("d".to_string(), "12345678".to_string());
+        assert!(stash.get("c").is_none());
+        assert_eq!(stash.bytes, 8);
+        // One entry over the byte cap is still kept.
+        stash.insert("e".to_string(), "x".repeat(20));
+        assert_eq!(stash.order, ["e"]);
+
+        assert_eq!(stash.expire(Duration::from_secs(60)), (0, 0));
+        assert_eq!(stash.expire(Duration::ZERO), (1, 20));
+        assert_eq!(stash.bytes, 0);
+    }
+}
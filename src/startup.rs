@@ -0,0 +1,167 @@
+//! What the server reports about itself as it starts. stdout belongs to the
+//! protocol on stdio, so the report only ever goes to stderr: a one-line
+//! summary by default, followed by the full report as JSON with
+//! `--verbose-startup`.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::runtime::RuntimeSettings;
+use crate::McpServer;
+
+/// Suffixes of configuration variables whose values are paths, and so
+/// shown. Other values may be secrets, such as `MCP_HTTP_SIGNING_SECRET`,
+/// and are only reported as set.
+const SHOWN_SUFFIXES: [&str; 3] = ["_FILE", "_DIR", "_SOCKET"];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StartupReport {
+    pub version: String,
+    pub transport: String,
+    /// Address of a network transport.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bind: Option<String>,
+    pub read_only: bool,
+    /// Names of the listed tools, sorted.
+    pub tools: Vec<String>,
+    /// The `MCP_*` variables set, with paths shown and other values hidden.
+    pub config: BTreeMap<String, String>,
+    /// The `--config` file, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub config_file: Option<String>,
+    /// The runtime the binary built, if it built one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub runtime: Option<RuntimeSettings>,
+}
+
+impl StartupReport {
+    /// The report for `server` about to serve `transport`, with the
+    /// configuration read from the environment.
+    pub fn new(server: &McpServer, transport: &str, bind: Option<&str>) -> Self {
+        let mut tools: Vec<String> = server.tools().into_iter().map(|t| t.name).collect();
+        tools.sort();
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            transport: transport.to_string(),
+            bind: bind.map(str::to_string),
+            read_only: server.is_read_only(),
+            tools,
+            config: config(std::env::vars()),
+            config_file: None,
+            runtime: None,
+        }
+    }
+
+    pub fn with_config_file(mut self, path: &str) -> Self {
+        self.config_file = Some(path.to_string());
+        self
+    }
+
+    pub fn with_runtime(mut self, settings: RuntimeSettings) -> Self {
+        self.runtime = Some(settings);
+        self
+    }
+
+    /// One line, such as `mcp-server 0.1.0 serving stdio with 12 tools;
+    /// config: --config server.toml, MCP_DLP_FILE=/etc/dlp.json,
+    /// MCP_READ_ONLY`.
+    pub fn summary(&self) -> String {
+        let mut line = format!("mcp-server {} serving {}", self.version, self.transport);
+        if let Some(bind) = &self.bind {
+            line.push_str(&format!(" on {}", bind));
+        }
+        line.push_str(&format!(" with {} tools", self.tools.len()));
+        if self.read_only {
+            line.push_str(" (read-only)");
+        }
+        let config: Vec<String> = self
+            .config_file
+            .iter()
+            .map(|path| format!("--config {}", path))
+            .chain(self.config.iter().map(|(name, value)| {
+                if shown(name) {
+                    format!("{}={}", name, value)
+                } else {
+                    name.clone()
+                }
+            }))
+            .collect();
+        if config.is_empty() {
+            line.push_str("; config: defaults");
+        } else {
+            line.push_str(&format!("; config: {}", config.join(", ")));
+        }
+        line
+    }
+
+    /// Logs the summary, and with `verbose` writes the whole report to
+    /// stderr as JSON.
+    pub fn emit(&self, verbose: bool) {
+        tracing::info!("{}", self.summary());
+        if verbose {
+            eprintln!("{}", serde_json::to_string_pretty(self).unwrap());
+        }
+    }
+}
+
+fn shown(name: &str) -> bool {
+    SHOWN_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+/// The `MCP_*` entries of `vars`, with values hidden unless they are paths.
+fn config(vars: impl Iterator<Item = (String, String)>) -> BTreeMap<String, String> {
+    vars.filter(|(name, _)| name.starts_with("MCP_"))
+        .map(|(name, value)| {
+            let value = if shown(&name) {
+                value
+            } else {
+                "(set)".to_string()
+            };
+            (name, value)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_hides_values_that_are_not_paths() {
+        let vars = [
+            ("MCP_DLP_FILE", "/etc/dlp.json"),
+            ("MCP_HTTP_SIGNING_SECRET", "hunter2"),
+            ("HOME", "/root"),
+        ]
+        .map(|(name, value)| (name.to_string(), value.to_string()));
+        let mut report = StartupReport {
+            version: "1.2.3".to_string(),
+            transport: "http".to_string(),
+            bind: Some("127.0.0.1:8080".to_string()),
+            read_only: true,
+            tools: vec!["echo".to_string(), "help".to_string()],
+            config: config(vars.into_iter()),
+            config_file: None,
+            runtime: None,
+        };
+        assert_eq!(
+            report.summary(),
+            "mcp-server 1.2.3 serving http on 127.0.0.1:8080 with 2 tools (read-only); \
+             config: MCP_DLP_FILE=/etc/dlp.json, MCP_HTTP_SIGNING_SECRET"
+        );
+        let json = serde_json::to_value(&report).unwrap();
+        assert_eq!(json["config"]["MCP_HTTP_SIGNING_SECRET"], "(set)");
+        assert_eq!(json["readOnly"], true);
+
+        report.config.clear();
+        report.bind = None;
+        assert!(report
+            .summary()
+            .ends_with("with 2 tools (read-only); config: defaults"));
+        assert!(report
+            .with_config_file("server.toml")
+            .summary()
+            .ends_with("; config: --config server.toml"));
+    }
+}
@@ -0,0 +1,174 @@
+//! Summaries of oversized tool results. When a `tools/call` result's text
+//! runs past a token threshold, the server has it summarized, either by the
+//! client's model over `sampling/createMessage` or by a tool of its own
+//! such as a local LLM, and answers with the summary plus a
+//! `resource_link` to the full text. The full text is stored on the session
+//! and read back with `resources/read`.
+
+use serde_json::Value;
+
+use crate::session::Session;
+use crate::tools::content;
+
+/// Scheme of the URIs under which full results are stored.
+pub const SCHEME: &str = "summary://";
+
+pub const DEFAULT_MAX_TOKENS: u32 = 1024;
+
+/// Full results a session keeps, newest first; older ones are evicted and
+/// their links stop resolving.
+pub const MAX_STORED_RESULTS: usize = 32;
+/// Total size of the full results a session keeps.
+pub const MAX_STORED_BYTES: usize = 32 << 20;
+
+pub const SYSTEM_PROMPT: &str = "Summarize the tool output the user sends. Keep every figure, \
+    name and identifier the reader is likely to need, and say what was left out.";
+
+/// What produces the summaries.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Backend {
+    /// The client's model, asked with `sampling/createMessage`.
+    Sampling,
+    /// A registered tool, called with `{"text": ...}`; its text content is
+    /// the summary.
+    Tool(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summarizer {
+    /// Results with more tokens of text than this are summarized.
+    pub threshold: usize,
+    pub backend: Backend,
+    /// The longest summary asked of the client's model.
+    pub max_tokens: u32,
+}
+
+impl Summarizer {
+    pub fn new(threshold: usize, backend: Backend) -> Self {
+        Self {
+            threshold,
+            backend,
+            max_tokens: DEFAULT_MAX_TOKENS,
+        }
+    }
+
+    /// From `MCP_SUMMARIZE_THRESHOLD_TOKENS`, which turns summaries on, and
+    /// `MCP_SUMMARIZER`, either `sampling` (the default) or `tool:<name>`.
+    /// `MCP_SUMMARY_MAX_TOKENS` caps a sampled summary's length.
+    pub fn from_env() -> Option<Self> {
+        let threshold = std::env::var("MCP_SUMMARIZE_THRESHOLD_TOKENS").ok()?;
+        let threshold = match threshold.parse::<usize>() {
+            Ok(0) => return None,
+            Ok(threshold) => threshold,
+            Err(_) => {
+                tracing::warn!("Invalid MCP_SUMMARIZE_THRESHOLD_TOKENS '{}'", threshold);
+                return None;
+            }
+        };
+        let backend = match std::env::var("MCP_SUMMARIZER") {
+            Err(_) => Backend::Sampling,
+            Ok(value) => match parse_backend(&value) {
+                Some(backend) => backend,
+                None => {
+                    tracing::warn!("Invalid MCP_SUMMARIZER '{}'", value);
+                    return None;
+                }
+            },
+        };
+        let mut summarizer = Self::new(threshold, backend);
+        if let Ok(value) = std::env::var("MCP_SUMMARY_MAX_TOKENS") {
+            match value.parse() {
+                Ok(max_tokens) if max_tokens > 0 => summarizer.max_tokens = max_tokens,
+                _ => tracing::warn!("Invalid MCP_SUMMARY_MAX_TOKENS '{}'", value),
+            }
+        }
+        Some(summarizer)
+    }
+}
+
+fn parse_backend(value: &str) -> Option<Backend> {
+    match value.split_once(':') {
+        None if value == "sampling" => Some(Backend::Sampling),
+        Some(("tool", name)) if !name.is_empty() => Some(Backend::Tool(name.to_string())),
+        _ => None,
+    }
+}
+
+/// The text blocks of a result's `content`, joined by blank lines.
+pub fn full_text(result: &Value) -> String {
+    result
+        .get("content")
+        .and_then(Value::as_array)
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter_map(|block| block.get("text").and_then(Value::as_str))
+                .collect::<Vec<_>>()
+                .join("\n\n")
+        })
+        .unwrap_or_default()
+}
+
+/// Stores `full` on the session and replaces the text blocks of `result`
+/// with `summary` and a link to the full text. Other blocks, such as
+/// images, are kept after them.
+pub fn replace(session: &Session, result: &mut Value, summary: &str, full: String) {
+    let uri = format!("{}{}", SCHEME, uuid::Uuid::new_v4());
+    let mut content = vec![
+        content::text(summary),
+        content::resource_link(&uri, "Full result", Some("text/plain")),
+    ];
+    if let Some(blocks) = result.get("content").and_then(Value::as_array) {
+        content.extend(
+            blocks
+                .iter()
+                .filter(|block| block.get("text").is_none())
+                .cloned(),
+        );
+    }
+    result["content"] = Value::Array(content);
+    result["_meta"]["summarized"] = Value::Bool(true);
+    session.add_full_result(uri, full);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_backend() {
+        assert_eq!(parse_backend("sampling"), Some(Backend::Sampling));
+        assert_eq!(
+            parse_backend("tool:local_llm"),
+            Some(Backend::Tool("local_llm".to_string()))
+        );
+        assert_eq!(parse_backend("tool:"), None);
+        assert_eq!(parse_backend("openai"), None);
+    }
+
+    #[test]
+    fn test_replace_keeps_other_blocks_and_stores_the_full_text() {
+        let session = Session::new();
+        let mut result = json!({
+            "content": [
+                {"type": "text", "text": "one"},
+                {"type": "image", "data": "AA==", "mimeType": "image/png"},
+                {"type": "text", "text": "two"},
+            ],
+            "isError": false,
+        });
+        let full = full_text(&result);
+        assert_eq!(full, "one\n\ntwo");
+
+        replace(&session, &mut result, "short", full);
+        let content = result["content"].as_array().unwrap();
+        assert_eq!(content.len(), 3);
+        assert_eq!(content[0]["text"], "short");
+        assert_eq!(content[2]["type"], "image");
+        let uri = content[1]["uri"].as_str().unwrap();
+        assert!(uri.starts_with(SCHEME));
+        assert_eq!(session.full_result(uri).unwrap(), "one\n\ntwo");
+        assert_eq!(result["_meta"]["summarized"], true);
+    }
+}
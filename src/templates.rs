@@ -0,0 +1,261 @@
+//! Per-tool output templates. A template turns a tool's structured result
+//! into the text the client shows, so operators can tune how results read
+//! without recompiling. `MCP_RESULT_TEMPLATES_FILE` names a JSON object of
+//! [minijinja](https://docs.rs/minijinja) templates keyed by tool name:
+//!
+//! ```json
+//! {"count_tokens": "{{ tokens }} tokens ({{ encoding }})"}
+//! ```
+//!
+//! The template sees the result's `structuredContent` as `result`, and an
+//! object's fields also as variables of their own. Without structured
+//! content, the result's text is parsed as JSON when it can be, as the text
+//! of [`ToolResult::json`](crate::ToolResult::json) results is, and text
+//! that is not JSON is `result` as a string.
+
+use minijinja::Environment;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::tools::ToolResult;
+
+/// Compiled templates, by tool name.
+#[derive(Default)]
+pub struct ResultTemplates {
+    environment: RwLock<Environment<'static>>,
+    /// Where the templates were loaded from, for [`reload`](Self::reload).
+    path: Option<PathBuf>,
+}
+
+impl ResultTemplates {
+    /// Compiles `templates`, keyed by tool name. Fails on the first
+    /// template with a syntax error.
+    pub fn new(templates: BTreeMap<String, String>) -> Result<Self, String> {
+        Ok(Self {
+            environment: RwLock::new(compile(templates)?),
+            path: None,
+        })
+    }
+
+    /// Reads a JSON object of templates keyed by tool name.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        Ok(Self {
+            environment: RwLock::new(compile(read(path)?)?),
+            path: Some(path.to_path_buf()),
+        })
+    }
+
+    /// Templates from `MCP_RESULT_TEMPLATES_FILE`, or none when unset. A
+    /// file that fails to load is reported and ignored.
+    pub fn from_env() -> Self {
+        let Ok(path) = std::env::var("MCP_RESULT_TEMPLATES_FILE") else {
+            return Self::default();
+        };
+        Self::load(Path::new(&path)).unwrap_or_else(|e| {
+            tracing::warn!("Failed to load result templates from {}: {}", path, e);
+            Self {
+                path: Some(PathBuf::from(path)),
+                ..Self::default()
+            }
+        })
+    }
+
+    /// Reads the file again, replacing every template. On failure the
+    /// templates in use are kept. Returns the names of the tools that have
+    /// a template.
+    pub fn reload(&self) -> Result<Vec<String>, String> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or("Templates were not loaded from a file")?;
+        let environment = compile(read(path)?)?;
+        *self.environment.write().unwrap() = environment;
+        Ok(self.tools())
+    }
+
+    /// Tools that have a template, sorted.
+    pub fn tools(&self) -> Vec<String> {
+        let environment = self.environment.read().unwrap();
+        let mut tools: Vec<String> = environment
+            .templates()
+            .map(|(name, _)| name.to_string())
+            .collect();
+        tools.sort();
+        tools
+    }
+
+    /// `result` with its text blocks replaced by one block rendered from
+    /// `tool`'s template, in place of the first. Other blocks, such as
+    /// images, are kept. Results of tools without a template, or without
+    /// text, pass unchanged, and so does a result the template fails on,
+    /// after the failure is reported on stderr.
+    pub fn apply(&self, tool: &str, mut result: ToolResult) -> ToolResult {
+        let environment = self.environment.read().unwrap();
+        let Ok(template) = environment.get_template(tool) else {
+            return result;
+        };
+        let texts: Vec<&str> = result
+            .content
+            .iter()
+            .filter(|block| block["type"] == "text")
+            .filter_map(|block| block["text"].as_str())
+            .collect();
+        if texts.is_empty() {
+            return result;
+        }
+        let data = match &result.structured_content {
+            Some(structured) => structured.clone(),
+            None => {
+                let text = texts.concat();
+                serde_json::from_str(&text).unwrap_or(Value::String(text))
+            }
+        };
+        let mut context = match &data {
+            Value::Object(fields) => fields.clone(),
+            _ => serde_json::Map::new(),
+        };
+        context.insert("result".to_string(), data);
+        let rendered = match template.render(&context) {
+            Ok(rendered) => rendered,
+            Err(e) => {
+                tracing::warn!("Result template for {} failed: {:#}", tool, e);
+                return result;
+            }
+        };
+
+        let first = result
+            .content
+            .iter()
+            .position(|block| block["type"] == "text")
+            .unwrap();
+        result.content.retain(|block| block["type"] != "text");
+        result.content.insert(
+            first.min(result.content.len()),
+            serde_json::json!({"type": "text", "text": rendered}),
+        );
+        result
+    }
+}
+
+fn read(path: &Path) -> Result<BTreeMap<String, String>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&text).map_err(|e| e.to_string())
+}
+
+fn compile(templates: BTreeMap<String, String>) -> Result<Environment<'static>, String> {
+    let mut environment = Environment::new();
+    for (tool, source) in templates {
+        environment
+            .add_template_owned(tool.clone(), source)
+            .map_err(|e| format!("Template for {} is invalid: {}", tool, e))?;
+    }
+    Ok(environment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn templates(entries: &[(&str, &str)]) -> ResultTemplates {
+        let entries = entries
+            .iter()
+            .map(|(tool, source)| (tool.to_string(), source.to_string()))
+            .collect();
+        ResultTemplates::new(entries).unwrap()
+    }
+
+    #[test]
+    fn test_renders_structured_results() {
+        let templates = templates(&[
+            (
+                "count_tokens",
+                "{{ tokens }} tokens{% if approximate %} (approx.){% endif %}",
+            ),
+            ("echo", "> {{ result }}"),
+            (
+                "list",
+                "{% for item in result %}- {{ item.name }}\n{% endfor %}",
+            ),
+        ]);
+
+        let counted = templates.apply(
+            "count_tokens",
+            ToolResult::json(&json!({"tokens": 12, "approximate": true})),
+        );
+        assert_eq!(counted, ToolResult::text("12 tokens (approx.)"));
+
+        let echoed = templates.apply("echo", ToolResult::text("Echo: hi"));
+        assert_eq!(echoed, ToolResult::text("> Echo: hi"));
+
+        let listed = templates.apply(
+            "list",
+            ToolResult::json(&json!([{"name": "a"}, {"name": "b"}])),
+        );
+        assert_eq!(listed, ToolResult::text("- a\n- b\n"));
+
+        let untouched = ToolResult::json(&json!({"tokens": 1}));
+        assert_eq!(templates.apply("other", untouched.clone()), untouched);
+    }
+
+    #[test]
+    fn test_keeps_other_blocks() {
+        let templates = templates(&[("chart", "{{ title }}")]);
+        let image = json!({"type": "image", "data": "AAAA", "mimeType": "image/png"});
+        let result = ToolResult::new(vec![
+            image.clone(),
+            json!({"type": "text", "text": "{\"title\": "}),
+            json!({"type": "text", "text": "\"Sales\"}"}),
+        ]);
+        let rendered = templates.apply("chart", result);
+        assert_eq!(
+            rendered.content,
+            vec![image, json!({"type": "text", "text": "Sales"})]
+        );
+    }
+
+    #[test]
+    fn test_failures_keep_the_result() {
+        let error = ResultTemplates::new(BTreeMap::from([(
+            "echo".to_string(),
+            "{% if %}".to_string(),
+        )]))
+        .err()
+        .unwrap();
+        assert!(error.starts_with("Template for echo is invalid"));
+
+        let templates = templates(&[("echo", "{{ result | missing_filter }}")]);
+        let result = ToolResult::text("hi");
+        assert_eq!(templates.apply("echo", result.clone()), result);
+    }
+
+    #[test]
+    fn test_reload() {
+        let path = std::env::temp_dir().join(format!("templates-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, r#"{"echo": "A {{ result }}"}"#).unwrap();
+        let templates = ResultTemplates::load(&path).unwrap();
+        assert_eq!(
+            templates.apply("echo", ToolResult::text("x")),
+            ToolResult::text("A x")
+        );
+
+        std::fs::write(
+            &path,
+            r#"{"echo": "B {{ result }}", "help": "{{ result }}"}"#,
+        )
+        .unwrap();
+        assert_eq!(templates.reload().unwrap(), vec!["echo", "help"]);
+        assert_eq!(
+            templates.apply("echo", ToolResult::text("x")),
+            ToolResult::text("B x")
+        );
+
+        std::fs::write(&path, r#"{"echo": "{% endif %}"}"#).unwrap();
+        assert!(templates.reload().is_err());
+        assert_eq!(templates.tools(), vec!["echo", "help"]);
+        std::fs::remove_file(&path).unwrap();
+        assert!(ResultTemplates::default().reload().is_err());
+    }
+}
@@ -0,0 +1,201 @@
+//! A ring buffer of dispatcher snapshots, one per state change, for
+//! diagnosing "tool missing" or "stuck request" reports after the fact.
+//! Each snapshot holds the registered tools, the live sessions and the
+//! requests in flight just after the change; the admin channel's
+//! `debug/history` returns them.
+
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Snapshots kept when `MCP_DEBUG_HISTORY` is unset.
+pub const DEFAULT_CAPACITY: usize = 256;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Snapshot {
+    /// Numbers every change, so gaps show where the buffer wrapped.
+    pub seq: u64,
+    /// Unix time in milliseconds.
+    pub at: u64,
+    /// What changed, such as `tool unregistered: search`.
+    pub change: String,
+    /// Shared with the snapshots before and after, until the tools change.
+    pub tools: Arc<[String]>,
+    /// Ids of the sessions still open. A session that closes is not a
+    /// change of its own; it is gone from the next snapshot.
+    pub sessions: Vec<String>,
+    pub in_flight: Vec<InFlightRequest>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InFlightRequest {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session: Option<String>,
+    pub id: Value,
+    pub method: String,
+    /// The tool, for `tools/call`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    /// Unix time in milliseconds.
+    pub started_at: u64,
+}
+
+pub struct Timeline {
+    capacity: usize,
+    snapshots: Mutex<VecDeque<Snapshot>>,
+    in_flight: Mutex<BTreeMap<u64, InFlightRequest>>,
+    next_seq: AtomicU64,
+    next_request: AtomicU64,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl Timeline {
+    /// Keeps the latest `capacity` snapshots; `0` records nothing.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            snapshots: Mutex::new(VecDeque::new()),
+            in_flight: Mutex::new(BTreeMap::new()),
+            next_seq: AtomicU64::new(1),
+            next_request: AtomicU64::new(0),
+        }
+    }
+
+    /// Capacity from `MCP_DEBUG_HISTORY`, where `0` turns recording off.
+    pub fn from_env() -> Self {
+        match std::env::var("MCP_DEBUG_HISTORY") {
+            Ok(value) => match value.trim().parse() {
+                Ok(capacity) => Self::new(capacity),
+                Err(_) => {
+                    tracing::warn!("Invalid MCP_DEBUG_HISTORY '{}'", value);
+                    Self::default()
+                }
+            },
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+
+    /// The snapshots still held, oldest first, from `since` on when given.
+    pub fn history(&self, since: Option<u64>) -> Vec<Snapshot> {
+        self.snapshots
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|s| since.is_none_or(|since| s.seq >= since))
+            .cloned()
+            .collect()
+    }
+
+    /// Appends a snapshot of `tools`, already sorted, and `sessions`, with
+    /// the requests in flight, dropping the oldest once full.
+    pub(crate) fn record(&self, change: String, tools: Arc<[String]>, mut sessions: Vec<String>) {
+        if !self.is_enabled() {
+            return;
+        }
+        sessions.sort();
+        let mut snapshots = self.snapshots.lock().unwrap();
+        let snapshot = Snapshot {
+            seq: self.next_seq.fetch_add(1, Ordering::Relaxed),
+            at: now_ms(),
+            change,
+            tools,
+            sessions,
+            in_flight: self.in_flight.lock().unwrap().values().cloned().collect(),
+        };
+        if snapshots.len() == self.capacity {
+            snapshots.pop_front();
+        }
+        snapshots.push_back(snapshot);
+    }
+
+    /// Marks a request as in flight until [`end`](Self::end) is called with
+    /// the returned key.
+    pub(crate) fn begin(
+        &self,
+        session: Option<String>,
+        id: Value,
+        method: String,
+        tool: Option<String>,
+    ) -> u64 {
+        let key = self.next_request.fetch_add(1, Ordering::Relaxed);
+        if self.is_enabled() {
+            let request = InFlightRequest {
+                session,
+                id,
+                method,
+                tool,
+                started_at: now_ms(),
+            };
+            self.in_flight.lock().unwrap().insert(key, request);
+        }
+        key
+    }
+
+    pub(crate) fn end(&self, key: u64) -> Option<InFlightRequest> {
+        self.in_flight.lock().unwrap().remove(&key)
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_the_latest_snapshots() {
+        let timeline = Timeline::new(2);
+        let key = timeline.begin(
+            None,
+            Value::from(7),
+            "tools/call".into(),
+            Some("echo".into()),
+        );
+        timeline.record(
+            "tool registered: echo".into(),
+            vec!["echo".into()].into(),
+            vec![],
+        );
+        timeline.end(key);
+        timeline.record(
+            "request finished".into(),
+            vec!["echo".into()].into(),
+            vec![],
+        );
+        timeline.record(
+            "tool unregistered: echo".into(),
+            Arc::new([]),
+            vec!["s1".into()],
+        );
+
+        let history = timeline.history(None);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].seq, 2);
+        assert!(history[0].in_flight.is_empty());
+        assert_eq!(history[1].sessions, ["s1"]);
+        assert_eq!(timeline.history(Some(3)).len(), 1);
+
+        let disabled = Timeline::new(0);
+        disabled.record("anything".into(), Arc::new([]), vec![]);
+        assert!(disabled.history(None).is_empty());
+    }
+}
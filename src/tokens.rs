@@ -0,0 +1,90 @@
+use serde_json::Value;
+use tiktoken_rs::CoreBPE;
+
+/// Encodings accepted by `count_tokens`, spelled as tiktoken spells them.
+pub const ENCODINGS: &[&str] = &["cl100k_base", "o200k_base", "p50k_base", "r50k_base"];
+
+pub const DEFAULT_ENCODING: &str = "cl100k_base";
+
+/// Counts the tokens the given tiktoken encoding produces for `text`. Text
+/// that spells a special token is counted as ordinary text. Each encoding's
+/// ranks are loaded the first time it is used.
+pub fn count(text: &str, encoding: &str) -> Result<usize, String> {
+    let bpe = match encoding {
+        "cl100k_base" => tiktoken_rs::cl100k_base_singleton(),
+        "o200k_base" => tiktoken_rs::o200k_base_singleton(),
+        "p50k_base" => tiktoken_rs::p50k_base_singleton(),
+        "r50k_base" => tiktoken_rs::r50k_base_singleton(),
+        _ => {
+            return Err(format!(
+                "Unknown encoding '{}'; expected one of {}",
+                encoding,
+                ENCODINGS.join(", ")
+            ))
+        }
+    };
+    Ok(encoded_len(bpe, text))
+}
+
+/// Sums the [`DEFAULT_ENCODING`] count over every text block of a tool
+/// result's `content`.
+pub fn count_content(result: &Value) -> usize {
+    let bpe = tiktoken_rs::cl100k_base_singleton();
+    result
+        .get("content")
+        .and_then(|c| c.as_array())
+        .map(|blocks| {
+            blocks
+                .iter()
+                .filter_map(|block| block.get("text").and_then(|t| t.as_str()))
+                .map(|text| encoded_len(bpe, text))
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+fn encoded_len(bpe: &CoreBPE, text: &str) -> usize {
+    bpe.encode_ordinary(text).len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_count_simple_sentence() {
+        // "Hello", ",", " world", "!"
+        assert_eq!(count("Hello, world!", "cl100k_base").unwrap(), 4);
+    }
+
+    #[test]
+    fn test_count_depends_on_the_encoding() {
+        // cl100k_base groups digits in threes; r50k_base merges them freely.
+        assert_eq!(count("1234567", "cl100k_base").unwrap(), 3);
+        let text = "    indented code";
+        assert_ne!(
+            count(text, "cl100k_base").unwrap(),
+            count(text, "r50k_base").unwrap()
+        );
+        // Special tokens are not recognized in the text.
+        assert!(count("<|endoftext|>", "cl100k_base").unwrap() > 1);
+    }
+
+    #[test]
+    fn test_count_rejects_unknown_encoding() {
+        let err = count("hi", "gpt2").unwrap_err();
+        assert!(err.contains("Unknown encoding 'gpt2'"));
+    }
+
+    #[test]
+    fn test_count_content_sums_text_blocks() {
+        let result = serde_json::json!({
+            "content": [
+                {"type": "text", "text": "Hello"},
+                {"type": "image", "data": "AAAA", "mimeType": "image/png"},
+                {"type": "text", "text": " world"}
+            ]
+        });
+        assert_eq!(count_content(&result), 2);
+    }
+}
@@ -0,0 +1,205 @@
+//! Settings that tools keep for themselves, such as a default region or the
+//! last sync cursor, so stateful tools do not each invent a file format.
+//! Every tool has its own namespace of JSON values, reached through
+//! [`ToolContext::config`](crate::ToolContext::config); operators inspect
+//! and edit all of them over the admin socket.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+type Namespaces = BTreeMap<String, BTreeMap<String, Value>>;
+
+/// Values by namespace and key. When a path is set, the whole store is
+/// rewritten to it after every change.
+#[derive(Default)]
+pub struct ToolConfigStore {
+    namespaces: Mutex<Namespaces>,
+    path: Option<PathBuf>,
+    read_only: AtomicBool,
+}
+
+impl ToolConfigStore {
+    pub fn new(path: Option<PathBuf>) -> Result<Self, String> {
+        let namespaces = match &path {
+            Some(path) if path.exists() => {
+                let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+                serde_json::from_str(&text).map_err(|e| e.to_string())?
+            }
+            _ => Namespaces::new(),
+        };
+        Ok(Self {
+            namespaces: Mutex::new(namespaces),
+            path,
+            read_only: AtomicBool::new(false),
+        })
+    }
+
+    /// Store persisted to `MCP_TOOL_CONFIG_FILE`, or kept in memory when
+    /// unset. A file that fails to load is reported, and the store is kept
+    /// in memory so the file is not overwritten.
+    pub fn from_env() -> Self {
+        let path = std::env::var("MCP_TOOL_CONFIG_FILE")
+            .ok()
+            .map(PathBuf::from);
+        Self::new(path.clone()).unwrap_or_else(|e| {
+            tracing::warn!(
+                "Failed to load tool config from {}: {}",
+                path.unwrap_or_default().display(),
+                e
+            );
+            Self::default()
+        })
+    }
+
+    /// While set, every change is refused and the file is never written.
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    pub fn get(&self, namespace: &str, key: &str) -> Option<Value> {
+        let namespaces = self.namespaces.lock().unwrap();
+        namespaces.get(namespace)?.get(key).cloned()
+    }
+
+    /// Every value in `namespace`, by key.
+    pub fn namespace(&self, namespace: &str) -> BTreeMap<String, Value> {
+        let namespaces = self.namespaces.lock().unwrap();
+        namespaces.get(namespace).cloned().unwrap_or_default()
+    }
+
+    /// Every value, by namespace and key.
+    pub fn all(&self) -> Namespaces {
+        self.namespaces.lock().unwrap().clone()
+    }
+
+    pub fn set(&self, namespace: &str, key: &str, value: Value) -> Result<(), String> {
+        self.change(|namespaces| {
+            namespaces
+                .entry(namespace.to_string())
+                .or_default()
+                .insert(key.to_string(), value);
+        })
+    }
+
+    /// Returns whether the key was set. An emptied namespace is dropped.
+    pub fn remove(&self, namespace: &str, key: &str) -> Result<bool, String> {
+        let mut removed = false;
+        self.change(|namespaces| {
+            if let Some(values) = namespaces.get_mut(namespace) {
+                removed = values.remove(key).is_some();
+                if values.is_empty() {
+                    namespaces.remove(namespace);
+                }
+            }
+        })?;
+        Ok(removed)
+    }
+
+    /// Applies `edit` and persists the result, leaving the store as it was
+    /// when the file cannot be written.
+    fn change(&self, edit: impl FnOnce(&mut Namespaces)) -> Result<(), String> {
+        if self.read_only.load(Ordering::Relaxed) {
+            return Err("Tool config is read-only".to_string());
+        }
+        let mut namespaces = self.namespaces.lock().unwrap();
+        let mut edited = namespaces.clone();
+        edit(&mut edited);
+        if let Some(path) = &self.path {
+            let tmp = path.with_extension("tmp");
+            let json = serde_json::to_string_pretty(&edited).unwrap();
+            std::fs::write(&tmp, json)
+                .and_then(|_| std::fs::rename(&tmp, path))
+                .map_err(|e| format!("Failed to persist tool config: {}", e))?;
+        }
+        *namespaces = edited;
+        Ok(())
+    }
+}
+
+/// One tool's namespace of a [`ToolConfigStore`].
+#[derive(Clone)]
+pub struct ToolConfig {
+    store: Arc<ToolConfigStore>,
+    namespace: String,
+}
+
+impl ToolConfig {
+    pub fn new(store: Arc<ToolConfigStore>, namespace: &str) -> Self {
+        Self {
+            store,
+            namespace: namespace.to_string(),
+        }
+    }
+
+    pub fn get(&self, key: &str) -> Option<Value> {
+        self.store.get(&self.namespace, key)
+    }
+
+    /// The value of `key` as a `T`, or `None` when unset. A value of another
+    /// shape, perhaps edited by hand, is an error rather than unset.
+    pub fn get_as<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, String> {
+        self.get(key)
+            .map(|value| {
+                serde_json::from_value(value).map_err(|e| {
+                    format!("Config '{}' of {} is invalid: {}", key, self.namespace, e)
+                })
+            })
+            .transpose()
+    }
+
+    pub fn set(&self, key: &str, value: impl Serialize) -> Result<(), String> {
+        let value = serde_json::to_value(value).map_err(|e| e.to_string())?;
+        self.store.set(&self.namespace, key, value)
+    }
+
+    pub fn remove(&self, key: &str) -> Result<bool, String> {
+        self.store.remove(&self.namespace, key)
+    }
+
+    pub fn all(&self) -> BTreeMap<String, Value> {
+        self.store.namespace(&self.namespace)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_namespaced_values_persist() {
+        let path = std::env::temp_dir().join(format!("tool-config-{}.json", uuid::Uuid::new_v4()));
+        let store = Arc::new(ToolConfigStore::new(Some(path.clone())).unwrap());
+        let geocode = ToolConfig::new(store.clone(), "geocode");
+        geocode.set("region", "eu").unwrap();
+        geocode.set("retries", 3).unwrap();
+        ToolConfig::new(store.clone(), "other")
+            .set("region", "us")
+            .unwrap();
+
+        assert_eq!(
+            geocode.get_as::<String>("region").unwrap().as_deref(),
+            Some("eu")
+        );
+        assert_eq!(geocode.get_as::<u32>("missing").unwrap(), None);
+        assert!(geocode.get_as::<u32>("region").is_err());
+        assert!(geocode.remove("retries").unwrap());
+        assert!(!geocode.remove("retries").unwrap());
+
+        let reopened = ToolConfigStore::new(Some(path.clone())).unwrap();
+        assert_eq!(
+            serde_json::to_value(reopened.all()).unwrap(),
+            json!({"geocode": {"region": "eu"}, "other": {"region": "us"}})
+        );
+        reopened.set_read_only(true);
+        assert!(reopened.set("geocode", "region", json!("us")).is_err());
+        assert_eq!(reopened.get("geocode", "region"), Some(json!("eu")));
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
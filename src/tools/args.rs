@@ -0,0 +1,309 @@
+//! Typed tool arguments. A tool that deserializes its `arguments` into a
+//! struct can derive [`JsonSchema`] on it and publish [`schema`] of it as
+//! its `input_schema`, so the schema and what the tool actually accepts
+//! cannot drift apart:
+//!
+//! ```
+//! use mcp_server::tools::args::{self, JsonSchema};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize, JsonSchema)]
+//! struct SearchArgs {
+//!     /// What to look for
+//!     query: String,
+//!     /// At most this many results
+//!     #[validate(range(min = 1, max = 50))]
+//!     limit: Option<u32>,
+//! }
+//!
+//! let schema = args::schema::<SearchArgs>();
+//! assert_eq!(schema["required"], serde_json::json!(["query"]));
+//! assert_eq!(schema["properties"]["limit"]["maximum"], 50.0);
+//!
+//! let args: SearchArgs = args::parse(serde_json::json!({"query": "rust"})).unwrap();
+//! assert_eq!(args.limit, None);
+//! ```
+//!
+//! Nested types are inlined into the schema rather than referenced from
+//! `definitions`, since some clients do not follow `$ref`. Recursive types
+//! still come out as references.
+//!
+//! The server hands a call's arguments to
+//! [`call_shared`](super::ToolHandler::call_shared) as [`Arguments`],
+//! shared rather than copied, so a document passed inline is held once
+//! however many steps read it. Typed arguments can borrow their strings
+//! from it:
+//!
+//! ```
+//! use mcp_server::tools::args::Arguments;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Summarize<'a> {
+//!     document: &'a str,
+//! }
+//!
+//! let arguments = Arguments::from(serde_json::json!({"document": "A long text"}));
+//! let args: Summarize = arguments.parse().unwrap();
+//! assert_eq!(args.document, "A long text");
+//! ```
+
+use schemars::gen::SchemaSettings;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::Value;
+use std::ops::Deref;
+use std::sync::Arc;
+
+use super::ToolError;
+
+pub use schemars::JsonSchema;
+
+/// The `input_schema` for arguments deserialized as `T`: a plain object
+/// schema with nested types inlined, an `Option` field simply left out of
+/// `required`, and no `$schema` or `title`.
+pub fn schema<T: JsonSchema>() -> Value {
+    let settings = SchemaSettings::draft07().with(|s| {
+        s.inline_subschemas = true;
+        s.option_nullable = false;
+        s.option_add_null_type = false;
+        s.meta_schema = None;
+    });
+    let schema = settings.into_generator().into_root_schema_for::<T>();
+    let mut schema = serde_json::to_value(schema).unwrap_or_default();
+    if let Some(object) = schema.as_object_mut() {
+        object.remove("title");
+        if object
+            .get("definitions")
+            .is_some_and(|d| d == &serde_json::json!({}))
+        {
+            object.remove("definitions");
+        }
+    }
+    schema
+}
+
+/// `arguments` as a `T`, or an `InvalidArguments` error saying why not.
+pub fn parse<T: DeserializeOwned>(arguments: Value) -> Result<T, ToolError> {
+    serde_json::from_value(arguments).map_err(invalid)
+}
+
+fn invalid(error: serde_json::Error) -> ToolError {
+    ToolError::InvalidArguments(format!("Invalid arguments: {}", error))
+}
+
+/// A call's `arguments`, shared between the server and the handler.
+/// Cloning one is cheap; reading it goes through `Deref` to the `Value`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arguments(Arc<Value>);
+
+impl Arguments {
+    /// The arguments as a `T`, deserialized from the shared value without
+    /// copying it. `T` may borrow strings from it.
+    pub fn parse<'a, T: Deserialize<'a>>(&'a self) -> Result<T, ToolError> {
+        T::deserialize(&*self.0).map_err(invalid)
+    }
+
+    /// The arguments as an owned `Value`, copied only if still shared.
+    pub fn into_value(self) -> Value {
+        Arc::unwrap_or_clone(self.0)
+    }
+}
+
+impl From<Value> for Arguments {
+    fn from(value: Value) -> Self {
+        Self(Arc::new(value))
+    }
+}
+
+impl Deref for Arguments {
+    type Target = Value;
+
+    fn deref(&self) -> &Value {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::{ToolContext, ToolHandler, ToolResult};
+    use crate::{mcp_tool, ToolAnnotations};
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    #[serde(rename_all = "lowercase")]
+    enum Mode {
+        Fast,
+        Slow,
+        #[serde(rename = "best-effort")]
+        BestEffort,
+    }
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    #[serde(rename_all = "camelCase", deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Filters {
+        /// Only results newer than this, as `YYYY-MM-DD`.
+        modified_since: String,
+        #[serde(default)]
+        include_archived: bool,
+    }
+
+    #[derive(Debug, Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct SearchArgs {
+        /// What to look for
+        #[validate(length(min = 1))]
+        query: String,
+        #[validate(range(min = 1, max = 50))]
+        limit: Option<u32>,
+        mode: Mode,
+        tags: Vec<String>,
+        filters: Option<Filters>,
+        #[serde(rename = "type")]
+        kind: Option<String>,
+        #[serde(skip)]
+        cache: Option<String>,
+    }
+
+    #[test]
+    fn test_schema_follows_the_struct() {
+        let schema = schema::<SearchArgs>();
+        assert_eq!(schema["type"], "object");
+        assert_eq!(schema["required"], json!(["mode", "query", "tags"]));
+        assert!(schema.get("title").is_none());
+        assert!(schema.get("$schema").is_none());
+        assert!(schema.get("definitions").is_none());
+
+        let properties = &schema["properties"];
+        assert_eq!(properties["query"]["description"], "What to look for");
+        assert_eq!(properties["query"]["minLength"], 1);
+        assert_eq!(properties["limit"]["type"], "integer");
+        assert_eq!(properties["limit"]["maximum"], 50.0);
+        assert_eq!(
+            properties["mode"]["enum"],
+            json!(["fast", "slow", "best-effort"])
+        );
+        assert_eq!(properties["tags"]["items"]["type"], "string");
+        assert_eq!(properties["type"]["type"], "string");
+        assert!(properties.get("cache").is_none());
+
+        let filters = &properties["filters"];
+        assert_eq!(filters["required"], json!(["modifiedSince"]));
+        assert_eq!(filters["additionalProperties"], false);
+        assert_eq!(filters["properties"]["includeArchived"]["type"], "boolean");
+
+        let arguments = json!({
+            "query": "rust",
+            "mode": "best-effort",
+            "tags": [],
+            "filters": {"modifiedSince": "2026-01-01"}
+        });
+        assert!(super::super::schema::validate(&schema, &arguments).is_ok());
+        let bad = json!({"query": "", "mode": "fast", "tags": []});
+        assert!(super::super::schema::validate(&schema, &bad).is_err());
+
+        let args: SearchArgs = parse(arguments).unwrap();
+        assert!(matches!(args.mode, Mode::BestEffort));
+        assert!(!args.filters.unwrap().include_archived);
+
+        let error = parse::<SearchArgs>(json!({"query": 1})).unwrap_err();
+        assert_eq!(error.code(), -32602);
+        assert!(error.message().starts_with("Invalid arguments: "));
+    }
+
+    #[test]
+    fn test_shared_arguments_borrow_in_place() {
+        #[derive(Deserialize)]
+        struct Document<'a> {
+            text: &'a str,
+        }
+
+        let arguments = Arguments::from(json!({"text": "a long document"}));
+        let shared = arguments.clone();
+        let document: Document = shared.parse().unwrap();
+        assert!(std::ptr::eq(
+            document.text,
+            arguments["text"].as_str().unwrap()
+        ));
+        let wrong = Arguments::from(json!({"text": 1}));
+        let error = wrong.parse::<Document>().map(|_| ()).unwrap_err();
+        assert_eq!(error.code(), -32602);
+        drop(shared);
+        assert_eq!(arguments.into_value(), json!({"text": "a long document"}));
+    }
+
+    /// Repeat a word
+    ///
+    /// Joined with spaces.
+    #[mcp_tool(name = "repeat_word", read_only)]
+    async fn repeat(
+        /// The word to repeat
+        #[validate(length(min = 1))]
+        word: String,
+        context: &ToolContext,
+        #[serde(default)] times: u8,
+    ) -> Result<ToolResult, ToolError> {
+        let times = context
+            .config("repeat_word")
+            .get_as::<u8>("times")
+            .unwrap()
+            .unwrap_or(times);
+        Ok(ToolResult::text(vec![word; times.into()].join(" ")))
+    }
+
+    #[tokio::test]
+    async fn test_mcp_tool_declares_a_tool() {
+        let tool = RepeatTool::definition();
+        assert_eq!(tool.name, "repeat_word");
+        assert_eq!(tool.description, "Repeat a word\n\nJoined with spaces.");
+        assert_eq!(tool.annotations, Some(ToolAnnotations::read_only()));
+        assert_eq!(tool.input_schema["required"], json!(["word"]));
+        assert_eq!(
+            tool.input_schema["properties"]["word"]["description"],
+            "The word to repeat"
+        );
+        assert_eq!(tool.input_schema["properties"]["times"]["type"], "integer");
+
+        let result = RepeatTool
+            .call(json!({"word": "hi", "times": 3}))
+            .await
+            .unwrap();
+        assert_eq!(result, ToolResult::text("hi hi hi"));
+
+        let context = ToolContext::default();
+        context.config("repeat_word").set("times", 2).unwrap();
+        let result = RepeatTool
+            .call_with_context(json!({"word": "ho"}), &context)
+            .await
+            .unwrap();
+        assert_eq!(result, ToolResult::text("ho ho"));
+
+        let error = RepeatTool.call(json!({"times": 1})).await.unwrap_err();
+        assert_eq!(error.code(), -32602);
+    }
+
+    #[derive(JsonSchema)]
+    #[allow(dead_code)]
+    struct Length {
+        characters: usize,
+    }
+
+    /// Measure a text
+    #[mcp_tool(output = Length)]
+    fn measure(text: String) -> Result<ToolResult, ToolError> {
+        Ok(ToolResult::structured(
+            json!({"characters": text.chars().count()}),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_mcp_tool_output_schema() {
+        let tool = MeasureTool::definition();
+        assert_eq!(tool.output_schema, Some(schema::<Length>()));
+        let result = MeasureTool.call(json!({"text": "héllo"})).await.unwrap();
+        assert!(super::super::schema::check_result(&tool, &result).is_ok());
+    }
+}
@@ -0,0 +1,82 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::args::Arguments;
+use super::{ToolContext, ToolError, ToolHandler, ToolResult};
+use crate::assertions;
+use crate::protocol::{Tool, ToolAnnotations};
+
+/// `assert`: evaluates a list of assertions and reports pass/fail.
+pub struct AssertTool;
+
+pub fn definition() -> Tool {
+    Tool {
+        name: "assert".to_string(),
+        description:
+            "Check JSON values and text against expectations and report structured pass/fail"
+                .to_string(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "assertions": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "actual": {
+                                "description": "Value under test"
+                            },
+                            "path": {
+                                "type": "string",
+                                "description": "JSON Pointer into actual, e.g. /items/0/id"
+                            },
+                            "op": {
+                                "type": "string",
+                                "enum": assertions::OPERATORS
+                            },
+                            "expected": {
+                                "description": "Expected value, pattern, type name or length"
+                            },
+                            "message": {
+                                "type": "string",
+                                "description": "Label echoed back in the result"
+                            }
+                        },
+                        "required": ["actual", "op"]
+                    }
+                }
+            },
+            "required": ["assertions"]
+        }),
+        output_schema: None,
+        annotations: Some(ToolAnnotations::read_only()),
+        meta: Default::default(),
+    }
+}
+
+#[async_trait]
+impl ToolHandler for AssertTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        check(&args)
+    }
+
+    /// The values are only read, so large ones are never copied.
+    async fn call_shared(
+        &self,
+        args: Arguments,
+        _context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        check(&args)
+    }
+}
+
+fn check(args: &Value) -> Result<ToolResult, ToolError> {
+    let assertions = args
+        .get("assertions")
+        .and_then(|a| a.as_array())
+        .map(|a| a.as_slice())
+        .unwrap_or_default();
+
+    let report = assertions::evaluate(assertions).map_err(ToolError::InvalidArguments)?;
+    Ok(ToolResult::json(&report))
+}
@@ -0,0 +1,392 @@
+//! Caps on how many calls of a tool run at once, so an expensive tool, such
+//! as one driving a headless browser, cannot take over the machine while
+//! cheap tools run freely. Calls over the cap wait their turn, for at most
+//! the queue timeout.
+//!
+//! A cap is either fixed or adaptive. An adaptive cap is tuned as calls
+//! finish, additive-increase/multiplicative-decrease: it grows by one for
+//! each full cap's worth of calls that ran about as fast as the fastest
+//! seen, and shrinks by a tenth whenever one ran much slower. It settles
+//! near the most calls the tool's backend can take before queueing inside
+//! it, without anyone measuring that by hand.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use super::ToolError;
+
+/// Used when `MCP_TOOL_QUEUE_TIMEOUT_SECS` is unset.
+pub const DEFAULT_QUEUE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Bounds of an adaptive cap, and how much slower than the fastest call a
+/// call may run before the cap shrinks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdaptiveLimit {
+    pub min: usize,
+    pub max: usize,
+    pub tolerance: f64,
+}
+
+impl Default for AdaptiveLimit {
+    fn default() -> Self {
+        Self {
+            min: 1,
+            max: 64,
+            tolerance: 2.0,
+        }
+    }
+}
+
+impl AdaptiveLimit {
+    /// `min..max`, such as `2..32`, with the default tolerance; empty for
+    /// the default bounds.
+    pub fn parse(bounds: &str) -> Option<Self> {
+        if bounds.trim().is_empty() {
+            return Some(Self::default());
+        }
+        let (min, max) = bounds.split_once("..")?;
+        let (min, max) = (min.trim().parse().ok()?, max.trim().parse().ok()?);
+        (min > 0 && min <= max).then_some(Self {
+            min,
+            max,
+            ..Self::default()
+        })
+    }
+}
+
+/// One tool's cap.
+struct Limiter {
+    semaphore: Arc<Semaphore>,
+    /// The cap now, which only an adaptive cap changes.
+    limit: AtomicUsize,
+    adaptive: Option<Mutex<Aimd>>,
+}
+
+impl Limiter {
+    fn fixed(limit: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            limit: AtomicUsize::new(limit),
+            adaptive: None,
+        }
+    }
+
+    /// Starts at the lower bound, as slow start does.
+    fn adaptive(bounds: AdaptiveLimit) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(bounds.min)),
+            limit: AtomicUsize::new(bounds.min),
+            adaptive: Some(Mutex::new(Aimd {
+                bounds,
+                limit: bounds.min as f64,
+                fastest: None,
+                debt: 0,
+            })),
+        }
+    }
+
+    /// Tunes an adaptive cap with a call that took `latency`. Returns
+    /// `true` when the call's permit must be forgotten, not returned,
+    /// because the cap shrank below the calls running.
+    fn record(&self, latency: Duration) -> bool {
+        let Some(aimd) = &self.adaptive else {
+            return false;
+        };
+        let mut aimd = aimd.lock().unwrap();
+        let before = aimd.limit as usize;
+        aimd.record(latency);
+        let after = aimd.limit as usize;
+        self.limit.store(after, Ordering::Relaxed);
+        if after > before {
+            let mut grown = after - before;
+            let repaid = grown.min(aimd.debt);
+            aimd.debt -= repaid;
+            grown -= repaid;
+            self.semaphore.add_permits(grown);
+        } else if after < before {
+            let shrunk = before - after;
+            aimd.debt += shrunk - self.semaphore.forget_permits(shrunk);
+        }
+        if aimd.debt > 0 {
+            aimd.debt -= 1;
+            return true;
+        }
+        false
+    }
+}
+
+struct Aimd {
+    bounds: AdaptiveLimit,
+    limit: f64,
+    /// Latency of the fastest recent call, drifting up slowly so one lucky
+    /// call cannot hold the cap down forever.
+    fastest: Option<Duration>,
+    /// Permits still to be forgotten after the cap shrank while they were
+    /// held.
+    debt: usize,
+}
+
+impl Aimd {
+    fn record(&mut self, latency: Duration) {
+        let fastest = match self.fastest {
+            Some(fastest) if latency > fastest => fastest + (latency - fastest) / 100,
+            _ => latency,
+        };
+        self.fastest = Some(fastest);
+        let (min, max) = (self.bounds.min as f64, self.bounds.max as f64);
+        self.limit = if latency.as_secs_f64() <= fastest.as_secs_f64() * self.bounds.tolerance {
+            (self.limit + 1.0 / self.limit).min(max)
+        } else {
+            (self.limit * 0.9).max(min)
+        };
+    }
+}
+
+/// A place to run one call. Dropping it when the call finishes frees the
+/// place and, under an adaptive cap, reports how long the call took.
+pub struct ToolPermit {
+    permit: Option<OwnedSemaphorePermit>,
+    limiter: Arc<Limiter>,
+    acquired: Instant,
+}
+
+impl std::fmt::Debug for ToolPermit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolPermit")
+            .field("acquired", &self.acquired)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for ToolPermit {
+    fn drop(&mut self) {
+        if self.limiter.record(self.acquired.elapsed()) {
+            if let Some(permit) = self.permit.take() {
+                permit.forget();
+            }
+        }
+    }
+}
+
+pub struct ToolConcurrency {
+    /// By tool name. Tools without one are not limited.
+    limits: RwLock<HashMap<String, Arc<Limiter>>>,
+    /// Bounds of the adaptive cap given to any tool without a cap of its
+    /// own.
+    adaptive_default: RwLock<Option<AdaptiveLimit>>,
+    queue_timeout: RwLock<Option<Duration>>,
+}
+
+impl Default for ToolConcurrency {
+    fn default() -> Self {
+        Self {
+            limits: RwLock::new(HashMap::new()),
+            adaptive_default: RwLock::new(None),
+            queue_timeout: RwLock::new(Some(DEFAULT_QUEUE_TIMEOUT)),
+        }
+    }
+}
+
+impl ToolConcurrency {
+    /// Limits from `MCP_TOOL_CONCURRENCY`, such as `browse=2,python_run=4`,
+    /// adaptive caps from `MCP_TOOL_ADAPTIVE_CONCURRENCY`, such as
+    /// `http_fetch=2..32,*`, where a bare name or `*` for every other tool
+    /// takes the default bounds, and the queue timeout from
+    /// `MCP_TOOL_QUEUE_TIMEOUT_SECS`, where `0` waits as long as it takes.
+    /// Malformed values are reported and ignored.
+    pub fn from_env() -> Self {
+        let concurrency = Self::default();
+        if let Ok(value) = std::env::var("MCP_TOOL_QUEUE_TIMEOUT_SECS") {
+            match value.trim().parse::<u64>() {
+                Ok(secs) => {
+                    concurrency.set_queue_timeout((secs > 0).then(|| Duration::from_secs(secs)))
+                }
+                Err(_) => tracing::warn!("Invalid MCP_TOOL_QUEUE_TIMEOUT_SECS '{}'", value),
+            }
+        }
+        for entry in entries("MCP_TOOL_ADAPTIVE_CONCURRENCY") {
+            let (tool, bounds) = entry.split_once('=').unwrap_or((&entry, ""));
+            match (tool.trim(), AdaptiveLimit::parse(bounds)) {
+                ("*", Some(bounds)) => concurrency.set_adaptive_default(Some(bounds)),
+                (tool, Some(bounds)) => concurrency.set_adaptive(tool, bounds),
+                (_, None) => {
+                    tracing::warn!("Invalid MCP_TOOL_ADAPTIVE_CONCURRENCY entry '{}'", entry)
+                }
+            }
+        }
+        for entry in entries("MCP_TOOL_CONCURRENCY") {
+            match entry
+                .split_once('=')
+                .and_then(|(tool, limit)| Some((tool.trim(), limit.trim().parse().ok()?)))
+                .filter(|&(_, limit)| limit > 0)
+            {
+                Some((tool, limit)) => concurrency.set_limit(tool, Some(limit)),
+                None => tracing::warn!("Invalid MCP_TOOL_CONCURRENCY entry '{}'", entry),
+            }
+        }
+        concurrency
+    }
+
+    /// Caps `tool` at `limit` simultaneous calls; `None` lifts the cap.
+    /// Calls already running keep their places under the old limit.
+    pub fn set_limit(&self, tool: &str, limit: Option<usize>) {
+        let mut limits = self.limits.write().unwrap();
+        match limit {
+            Some(limit) => {
+                limits.insert(tool.to_string(), Arc::new(Limiter::fixed(limit)));
+            }
+            None => {
+                limits.remove(tool);
+            }
+        }
+    }
+
+    /// Gives `tool` an adaptive cap within `bounds`, replacing any cap it
+    /// had.
+    pub fn set_adaptive(&self, tool: &str, bounds: AdaptiveLimit) {
+        self.limits
+            .write()
+            .unwrap()
+            .insert(tool.to_string(), Arc::new(Limiter::adaptive(bounds)));
+    }
+
+    /// Gives every tool without a cap of its own an adaptive one within
+    /// `bounds`, from its next call; `None` stops giving them.
+    pub fn set_adaptive_default(&self, bounds: Option<AdaptiveLimit>) {
+        *self.adaptive_default.write().unwrap() = bounds;
+    }
+
+    /// The cap on `tool` now, which an adaptive cap changes as calls finish.
+    pub fn limit(&self, tool: &str) -> Option<usize> {
+        self.limits
+            .read()
+            .unwrap()
+            .get(tool)
+            .map(|limiter| limiter.limit.load(Ordering::Relaxed))
+    }
+
+    /// How long a call waits for a place; `None` waits as long as it takes.
+    pub fn set_queue_timeout(&self, timeout: Option<Duration>) {
+        *self.queue_timeout.write().unwrap() = timeout;
+    }
+
+    /// Waits for a place to run `tool`. The call holds it until the permit
+    /// is dropped; tools without a limit get `None` at once. Fails when the
+    /// wait outlasts the queue timeout.
+    pub async fn acquire(&self, tool: &str) -> Result<Option<ToolPermit>, ToolError> {
+        let Some(limiter) = self.limiter(tool) else {
+            return Ok(None);
+        };
+        let acquire = limiter.semaphore.clone().acquire_owned();
+        let queue_timeout = *self.queue_timeout.read().unwrap();
+        let permit = match queue_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, acquire).await.map_err(|_| {
+                ToolError::Execution(format!(
+                    "Tool '{}' is busy: {} calls already running, and none finished within {:?}",
+                    tool,
+                    limiter.limit.load(Ordering::Relaxed),
+                    timeout
+                ))
+            })?,
+            None => acquire.await,
+        };
+        Ok(Some(ToolPermit {
+            // Semaphores are never closed.
+            permit: Some(permit.unwrap()),
+            limiter,
+            acquired: Instant::now(),
+        }))
+    }
+
+    fn limiter(&self, tool: &str) -> Option<Arc<Limiter>> {
+        if let Some(limiter) = self.limits.read().unwrap().get(tool) {
+            return Some(limiter.clone());
+        }
+        let bounds = (*self.adaptive_default.read().unwrap())?;
+        let mut limits = self.limits.write().unwrap();
+        let limiter = limits
+            .entry(tool.to_string())
+            .or_insert_with(|| Arc::new(Limiter::adaptive(bounds)));
+        Some(limiter.clone())
+    }
+}
+
+/// The comma-separated entries of variable `name`.
+fn entries(name: &str) -> Vec<String> {
+    std::env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|e| !e.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_calls_over_the_limit_wait() {
+        let concurrency = Arc::new(ToolConcurrency::default());
+        concurrency.set_limit("browse", Some(1));
+        concurrency.set_queue_timeout(Some(Duration::from_millis(20)));
+        assert!(concurrency.acquire("echo").await.unwrap().is_none());
+
+        let first = concurrency.acquire("browse").await.unwrap();
+        let error = concurrency.acquire("browse").await.unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Tool 'browse' is busy: 1 calls already running, and none finished within 20ms"
+        );
+
+        concurrency.set_queue_timeout(None);
+        let waiting = tokio::spawn({
+            let concurrency = concurrency.clone();
+            async move { concurrency.acquire("browse").await.unwrap().is_some() }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+        drop(first);
+        assert!(waiting.await.unwrap());
+
+        concurrency.set_limit("browse", None);
+        assert_eq!(concurrency.limit("browse"), None);
+    }
+
+    #[test]
+    fn test_adaptive_limits_follow_latency() {
+        let bounds = AdaptiveLimit::parse("2..4").unwrap();
+        let limiter = Limiter::adaptive(bounds);
+        let fast = Duration::from_millis(10);
+        // Two full windows of fast calls grow the cap from 2 to 4, its
+        // upper bound.
+        for _ in 0..10 {
+            assert!(!limiter.record(fast));
+        }
+        assert_eq!(limiter.limit.load(Ordering::Relaxed), 4);
+        assert_eq!(limiter.semaphore.available_permits(), 4);
+
+        // All four places taken when a slow call shrinks the cap: the
+        // permit freed is forgotten instead of returned.
+        let mut held: Vec<_> = (0..4)
+            .map(|_| limiter.semaphore.clone().try_acquire_owned().unwrap())
+            .collect();
+        assert!(limiter.record(Duration::from_millis(100)));
+        held.pop().unwrap().forget();
+        assert_eq!(limiter.limit.load(Ordering::Relaxed), 3);
+        drop(held);
+        assert_eq!(limiter.semaphore.available_permits(), 3);
+
+        for _ in 0..10 {
+            limiter.record(Duration::from_secs(1));
+        }
+        assert_eq!(limiter.limit.load(Ordering::Relaxed), 2);
+        assert_eq!(limiter.semaphore.available_permits(), 2);
+        assert_eq!(AdaptiveLimit::parse("0..4"), None);
+        assert_eq!(AdaptiveLimit::parse(""), Some(AdaptiveLimit::default()));
+    }
+}
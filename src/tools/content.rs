@@ -0,0 +1,89 @@
+//! Builders for the blocks of a tool result's `content`. Text is the common
+//! case, but a tool such as a screenshot or chart renderer can return
+//! images, audio, links to resources the client reads separately, or whole
+//! resources embedded in the result.
+
+use serde_json::{json, Value};
+
+use crate::resources::ResourceContents;
+
+/// `{"type": "text"}`.
+pub fn text(text: impl Into<String>) -> Value {
+    json!({"type": "text", "text": text.into()})
+}
+
+/// `{"type": "image"}` with `bytes` base64-encoded, for a `mime_type` such
+/// as `image/png`.
+pub fn image(bytes: &[u8], mime_type: &str) -> Value {
+    json!({
+        "type": "image",
+        "data": crate::base64::encode(bytes),
+        "mimeType": mime_type,
+    })
+}
+
+/// `{"type": "audio"}` with `bytes` base64-encoded, for a `mime_type` such
+/// as `audio/wav`.
+pub fn audio(bytes: &[u8], mime_type: &str) -> Value {
+    json!({
+        "type": "audio",
+        "data": crate::base64::encode(bytes),
+        "mimeType": mime_type,
+    })
+}
+
+/// `{"type": "resource_link"}`: a resource the client can fetch with
+/// `resources/read` instead of receiving it inline.
+pub fn resource_link(
+    uri: impl Into<String>,
+    name: impl Into<String>,
+    mime_type: Option<&str>,
+) -> Value {
+    let mut link = json!({"type": "resource_link", "uri": uri.into(), "name": name.into()});
+    if let Some(mime_type) = mime_type {
+        link["mimeType"] = mime_type.into();
+    }
+    link
+}
+
+/// `{"type": "resource"}`: a resource embedded whole, as text or, for
+/// other binary payloads, a base64 blob.
+pub fn resource(contents: ResourceContents) -> Value {
+    json!({"type": "resource", "resource": contents})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(text("hi"), json!({"type": "text", "text": "hi"}));
+        assert_eq!(
+            image(b"\x89PNG", "image/png"),
+            json!({"type": "image", "data": "iVBORw==", "mimeType": "image/png"})
+        );
+        assert_eq!(
+            audio(b"RIFF", "audio/wav"),
+            json!({"type": "audio", "data": "UklGRg==", "mimeType": "audio/wav"})
+        );
+        assert_eq!(
+            resource_link("file:///a.csv", "a.csv", Some("text/csv")),
+            json!({"type": "resource_link", "uri": "file:///a.csv", "name": "a.csv", "mimeType": "text/csv"})
+        );
+        assert!(resource_link("file:///a", "a", None)
+            .get("mimeType")
+            .is_none());
+        assert_eq!(
+            resource(ResourceContents::blob(
+                "file:///a.bin",
+                Some("application/octet-stream".to_string()),
+                &[0, 1, 2],
+            )),
+            json!({
+                "type": "resource",
+                "resource": {"uri": "file:///a.bin", "mimeType": "application/octet-stream", "blob": "AAEC"}
+            })
+        );
+    }
+}
@@ -0,0 +1,19 @@
+use super::{ToolError, ToolResult};
+use crate::mcp_tool;
+use crate::protocol::{Tool, ToolExample};
+
+/// Echo back the input text
+#[mcp_tool(read_only)]
+pub async fn echo(
+    /// Text to echo back
+    text: String,
+) -> Result<ToolResult, ToolError> {
+    Ok(ToolResult::text(format!("Echo: {}", text)))
+}
+
+pub fn definition() -> Tool {
+    EchoTool::definition().with_examples([ToolExample::new(
+        "Echo a greeting",
+        serde_json::json!({"text": "Hello, world"}),
+    )])
+}
@@ -0,0 +1,162 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+
+use super::{required_str, ToolError, ToolHandler, ToolResult};
+use crate::protocol::{Tool, ToolAnnotations};
+use crate::vfs::Vfs;
+
+/// Which `fs_*` operation an [`FsTool`] performs.
+#[derive(Debug, Clone, Copy)]
+pub enum FsOperation {
+    Read,
+    Write,
+    List,
+    Delete,
+}
+
+impl FsOperation {
+    /// The tool's name, which is also what mount `tools` lists refer to.
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Read => "fs_read",
+            Self::Write => "fs_write",
+            Self::List => "fs_list",
+            Self::Delete => "fs_delete",
+        }
+    }
+}
+
+/// One of the tools working on the mounted file systems.
+pub struct FsTool {
+    pub vfs: Arc<Vfs>,
+    pub operation: FsOperation,
+}
+
+fn uri_schema(vfs: &Vfs) -> Value {
+    let prefixes: Vec<&str> = vfs.mounts().map(|m| m.prefix.as_str()).collect();
+    serde_json::json!({
+        "type": "string",
+        "description": format!("File URI under one of the mounts: {}", prefixes.join(", "))
+    })
+}
+
+/// The four fs tools with their operations, describing the mounts of `vfs`.
+pub fn definitions(vfs: &Vfs) -> Vec<(Tool, FsOperation)> {
+    vec![
+        (
+            Tool {
+                name: FsOperation::Read.name().to_string(),
+                description: "Read a text file from a mounted file system".to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {"uri": uri_schema(vfs)},
+                    "required": ["uri"]
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations::read_only()),
+                meta: Default::default(),
+            },
+            FsOperation::Read,
+        ),
+        (
+            Tool {
+                name: FsOperation::Write.name().to_string(),
+                description: "Write a text file to a writable mounted file system".to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "uri": uri_schema(vfs),
+                        "content": {"type": "string"}
+                    },
+                    "required": ["uri", "content"]
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations {
+                    idempotent_hint: Some(true),
+                    ..ToolAnnotations::mutating(true)
+                }),
+                meta: Default::default(),
+            },
+            FsOperation::Write,
+        ),
+        (
+            Tool {
+                name: FsOperation::List.name().to_string(),
+                description: "List the files under a directory URI of a mounted file system"
+                    .to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {"uri": uri_schema(vfs)},
+                    "required": ["uri"]
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations::read_only()),
+                meta: Default::default(),
+            },
+            FsOperation::List,
+        ),
+        (
+            Tool {
+                name: FsOperation::Delete.name().to_string(),
+                description: "Delete a file from a writable mounted file system".to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {"uri": uri_schema(vfs)},
+                    "required": ["uri"]
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations::mutating(true)),
+                meta: Default::default(),
+            },
+            FsOperation::Delete,
+        ),
+    ]
+}
+
+#[async_trait]
+impl ToolHandler for FsTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        let tool = self.operation.name();
+        let uri = required_str(&args, "uri")?;
+        match self.operation {
+            FsOperation::Read => {
+                let bytes = self
+                    .vfs
+                    .read(tool, uri)
+                    .await
+                    .map_err(ToolError::Execution)?;
+                let text = String::from_utf8(bytes)
+                    .map_err(|_| ToolError::Execution(format!("{} is not UTF-8 text", uri)))?;
+                Ok(ToolResult::text(text))
+            }
+            FsOperation::Write => {
+                let content = required_str(&args, "content")?;
+                self.vfs
+                    .write(tool, uri, content.as_bytes())
+                    .await
+                    .map_err(ToolError::Execution)?;
+                Ok(ToolResult::text(format!(
+                    "Wrote {} bytes to {}",
+                    content.len(),
+                    uri
+                )))
+            }
+            FsOperation::List => {
+                let files = self
+                    .vfs
+                    .list(tool, uri)
+                    .await
+                    .map_err(ToolError::Execution)?;
+                Ok(ToolResult::json(&serde_json::json!({ "files": files })))
+            }
+            FsOperation::Delete => {
+                self.vfs
+                    .delete(tool, uri)
+                    .await
+                    .map_err(ToolError::Execution)?;
+                Ok(ToolResult::text(format!("Deleted {}", uri)))
+            }
+        }
+    }
+}
@@ -0,0 +1,248 @@
+//! `help`: usage documentation for a registered tool, for a model that
+//! called it wrongly and needs to recover on its own: the description,
+//! each argument, the tool's own examples and ones built from the schema,
+//! annotations and the errors recent calls ran into.
+
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use super::hints::example;
+use super::index::ToolIndex;
+use super::names::{resolve, Resolution};
+use super::{ToolError, ToolHandler, ToolResult};
+use crate::protocol::{Tool, ToolAnnotations};
+
+pub const NAME: &str = "help";
+
+/// Failed calls remembered per tool.
+const ERRORS_PER_TOOL: usize = 20;
+
+/// One failed call, as `help` reports it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+struct RecordedError {
+    code: i32,
+    message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    property: Option<String>,
+}
+
+/// A kind of failure and how many of the remembered calls hit it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ErrorPattern {
+    pub code: i32,
+    pub message: String,
+    /// The argument at fault, when the arguments were rejected.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub property: Option<String>,
+    pub count: usize,
+}
+
+/// The last [`ERRORS_PER_TOOL`] failures of each tool.
+#[derive(Default)]
+pub struct RecentErrors {
+    errors: Mutex<HashMap<String, VecDeque<RecordedError>>>,
+}
+
+impl RecentErrors {
+    pub fn record(&self, tool: &str, code: i32, message: &str, property: Option<&str>) {
+        let mut errors = self.errors.lock().unwrap();
+        let recent = errors.entry(tool.to_string()).or_default();
+        if recent.len() == ERRORS_PER_TOOL {
+            recent.pop_front();
+        }
+        recent.push_back(RecordedError {
+            code,
+            message: message.to_string(),
+            property: property.map(str::to_string),
+        });
+    }
+
+    /// `tool`'s remembered failures grouped by code, message and property,
+    /// most frequent first.
+    pub fn patterns(&self, tool: &str) -> Vec<ErrorPattern> {
+        let errors = self.errors.lock().unwrap();
+        let mut patterns: Vec<ErrorPattern> = Vec::new();
+        for error in errors.get(tool).into_iter().flatten() {
+            match patterns.iter_mut().find(|p| {
+                p.code == error.code && p.message == error.message && p.property == error.property
+            }) {
+                Some(pattern) => pattern.count += 1,
+                None => patterns.push(ErrorPattern {
+                    code: error.code,
+                    message: error.message.clone(),
+                    property: error.property.clone(),
+                    count: 1,
+                }),
+            }
+        }
+        // Stable, so equally frequent patterns keep first-seen order.
+        patterns.sort_by_key(|p| std::cmp::Reverse(p.count));
+        patterns
+    }
+
+    pub fn forget(&self, tool: &str) {
+        self.errors.lock().unwrap().remove(tool);
+    }
+}
+
+pub struct HelpTool {
+    pub tools: Arc<RwLock<ToolIndex>>,
+    pub errors: Arc<RecentErrors>,
+    /// While set, only read-only tools are described, as only they are
+    /// listed.
+    pub read_only: Arc<AtomicBool>,
+}
+
+pub fn definition() -> Tool {
+    Tool {
+        name: NAME.to_string(),
+        description: "Explain how to call a tool: its arguments, example arguments, \
+                      annotations and the mistakes recent calls made. Without a tool, \
+                      list every tool"
+            .to_string(),
+        input_schema: json!({
+            "type": "object",
+            "properties": {
+                "tool": {
+                    "type": "string",
+                    "description": "Name of the tool to explain"
+                }
+            }
+        }),
+        output_schema: None,
+        annotations: Some(ToolAnnotations::read_only()),
+        meta: Default::default(),
+    }
+}
+
+/// Each property of `schema` with its type, whether it is required and its
+/// description.
+fn arguments(schema: &Value) -> Vec<Value> {
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str)
+        .collect();
+    let properties = schema.get("properties").and_then(Value::as_object);
+    properties
+        .into_iter()
+        .flatten()
+        .map(|(name, property)| {
+            let mut argument = json!({
+                "name": name,
+                "required": required.contains(&name.as_str()),
+            });
+            for key in ["type", "description", "enum", "default"] {
+                if let Some(value) = property.get(key) {
+                    argument[key] = value.clone();
+                }
+            }
+            argument
+        })
+        .collect()
+}
+
+impl HelpTool {
+    fn describe(&self, tool: &Tool) -> Value {
+        let mut help = json!({
+            "name": tool.name,
+            "description": tool.description,
+            "arguments": arguments(&tool.input_schema),
+            "example": example(&tool.input_schema, "value"),
+        });
+        // Without `required`, `example` fills in every property.
+        let mut full = tool.input_schema.clone();
+        if let Some(schema) = full.as_object_mut() {
+            schema.remove("required");
+        }
+        let full = example(&full, "value");
+        if full != help["example"] {
+            help["fullExample"] = full;
+        }
+        if !tool.meta.examples.is_empty() {
+            help["examples"] = serde_json::to_value(&tool.meta.examples).unwrap();
+        }
+        if let Some(annotations) = &tool.annotations {
+            help["annotations"] = serde_json::to_value(annotations).unwrap();
+        }
+        let patterns = self.errors.patterns(&tool.name);
+        if !patterns.is_empty() {
+            help["recentErrors"] = serde_json::to_value(patterns).unwrap();
+        }
+        help
+    }
+}
+
+#[async_trait]
+impl ToolHandler for HelpTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        let tools = self.tools.read().unwrap();
+        let read_only = self.read_only.load(Ordering::Relaxed);
+        let tools: HashMap<&str, &Tool> = tools
+            .iter()
+            .filter(|tool| !read_only || tool.is_read_only())
+            .map(|tool| (tool.name.as_str(), tool))
+            .collect();
+        let Some(requested) = args.get("tool").and_then(Value::as_str) else {
+            let mut index: Vec<Value> = tools
+                .values()
+                .map(|tool| {
+                    let summary = tool.description.lines().next().unwrap_or_default();
+                    json!({ "name": tool.name, "description": summary })
+                })
+                .collect();
+            index.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+            return Ok(ToolResult::json(&json!({ "tools": index })));
+        };
+        match resolve(requested, tools.keys().copied(), None) {
+            Resolution::Found(name) => Ok(ToolResult::json(&self.describe(tools[name.as_str()]))),
+            Resolution::NotFound(suggestions) if suggestions.is_empty() => Err(
+                ToolError::InvalidArguments(format!("No tool named '{}'", requested)),
+            ),
+            Resolution::NotFound(suggestions) => Err(ToolError::InvalidArguments(format!(
+                "No tool named '{}'. Did you mean {}?",
+                requested,
+                suggestions.join(", ")
+            ))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_groups_recent_errors() {
+        let errors = RecentErrors::default();
+        for _ in 0..3 {
+            errors.record("echo", -32602, "text required", Some("text"));
+        }
+        errors.record("echo", -32603, "boom", None);
+        for _ in 0..ERRORS_PER_TOOL {
+            errors.record("other", -32603, "boom", None);
+        }
+        errors.record("other", -32602, "bad", None);
+
+        let patterns = errors.patterns("echo");
+        assert_eq!(patterns.len(), 2);
+        assert_eq!(patterns[0].count, 3);
+        assert_eq!(patterns[0].property.as_deref(), Some("text"));
+        assert_eq!(
+            errors
+                .patterns("other")
+                .iter()
+                .map(|p| p.count)
+                .sum::<usize>(),
+            ERRORS_PER_TOOL
+        );
+        errors.forget("echo");
+        assert!(errors.patterns("echo").is_empty());
+    }
+}
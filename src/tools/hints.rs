@@ -0,0 +1,176 @@
+//! `error.data` for rejected tool arguments, written for the calling model:
+//! what the schema expects where the arguments went wrong, what was sent
+//! instead, and a complete example of valid arguments.
+
+use serde_json::{Map, Value};
+
+use super::schema::validate;
+
+/// The JSON type name of `value`, as a schema's `type` spells it.
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_i64() || n.is_u64() => "integer",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Whether `value` has one of the types `schema` allows. Schemas without a
+/// `type` allow anything.
+pub fn matches_type(schema: &Value, value: &Value) -> bool {
+    let actual = type_name(value);
+    let allowed = |expected: &Value| {
+        expected
+            .as_str()
+            .is_some_and(|t| t == actual || (t == "number" && actual == "integer"))
+    };
+    match schema.get("type") {
+        None => true,
+        Some(Value::Array(types)) => types.iter().any(allowed),
+        Some(expected) => allowed(expected),
+    }
+}
+
+/// A value `schema` accepts: its first `examples`, `default`, `const` or
+/// `enum` entry, or else a placeholder of its type. Objects get their
+/// required properties, or all of them when none are required.
+pub fn example(schema: &Value, name: &str) -> Value {
+    for key in ["examples", "enum"] {
+        if let Some(first) = schema.get(key).and_then(|v| v.get(0)) {
+            return first.clone();
+        }
+    }
+    for key in ["default", "const"] {
+        if let Some(value) = schema.get(key) {
+            return value.clone();
+        }
+    }
+    let kind = match schema.get("type") {
+        Some(Value::Array(types)) => types.iter().find_map(Value::as_str),
+        Some(kind) => kind.as_str(),
+        None if schema.get("properties").is_some() => Some("object"),
+        None => None,
+    };
+    match kind {
+        Some("string") => Value::String(format!("<{}>", name)),
+        Some("integer") | Some("number") => {
+            let minimum = schema.get("minimum").and_then(Value::as_i64).unwrap_or(1);
+            Value::from(minimum.max(1))
+        }
+        Some("boolean") => Value::Bool(true),
+        Some("array") => match schema.get("items") {
+            Some(items) => Value::Array(vec![example(items, name)]),
+            None => Value::Array(Vec::new()),
+        },
+        Some("object") => {
+            let properties = schema.get("properties").and_then(Value::as_object);
+            let required: Vec<&str> = schema
+                .get("required")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+                .filter_map(Value::as_str)
+                .collect();
+            let mut object = Map::new();
+            for (key, property) in properties.into_iter().flatten() {
+                if required.is_empty() || required.contains(&key.as_str()) {
+                    object.insert(key.clone(), example(property, key));
+                }
+            }
+            Value::Object(object)
+        }
+        _ => Value::Null,
+    }
+}
+
+/// `error.data` for a `-32602` reply to a call of a tool with `schema`:
+///
+/// ```json
+/// {"property": "text", "keyword": "type", "expected": {"type": "string"}, "received": 42,
+///  "example": {"text": "<text>"},
+///  "hint": "Property 'text' must be string, got 42. Valid arguments look like {\"text\":\"<text>\"}"}
+/// ```
+///
+/// `property` is where [`validate`] found the first problem, absent for the
+/// arguments as a whole. When the arguments satisfy the schema and the tool
+/// refused them anyway, `expected` is the whole schema and `received` all
+/// the arguments.
+pub fn argument_hint(schema: &Value, arguments: &Value) -> Value {
+    let example = example(schema, "value");
+    let mut data = Map::new();
+    let (problem, expected, received) = match validate(schema, arguments) {
+        Err(violation) => {
+            if !violation.path.is_empty() {
+                data.insert("property".to_string(), Value::String(violation.path));
+            }
+            data.insert("keyword".to_string(), violation.keyword.into());
+            (violation.message, violation.expected, violation.received)
+        }
+        Ok(()) => (
+            "The arguments were rejected".to_string(),
+            schema.clone(),
+            arguments.clone(),
+        ),
+    };
+    data.insert(
+        "hint".to_string(),
+        Value::String(format!(
+            "{}. Valid arguments look like {}",
+            problem, example
+        )),
+    );
+    data.insert("expected".to_string(), expected);
+    data.insert("received".to_string(), received);
+    data.insert("example".to_string(), example);
+    Value::Object(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_hints_name_the_failing_property() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "text": {"type": "string"},
+                "count": {"type": "integer", "minimum": 2},
+                "mode": {"type": "string", "enum": ["fast", "slow"]},
+                "tags": {"type": "array", "items": {"type": "string"}}
+            },
+            "required": ["text", "count"]
+        });
+        assert_eq!(
+            example(&schema, "value"),
+            json!({"text": "<text>", "count": 2})
+        );
+
+        let hint = argument_hint(&schema, &json!({"text": 42, "count": 3}));
+        assert_eq!(hint["property"], "text");
+        assert_eq!(hint["expected"], json!({"type": "string"}));
+        assert_eq!(hint["received"], 42);
+        assert_eq!(
+            hint["hint"],
+            "Property 'text' must be string, got 42. Valid arguments look like {\"count\":2,\"text\":\"<text>\"}"
+        );
+
+        let hint = argument_hint(&schema, &json!({"text": "hi"}));
+        assert_eq!(hint["property"], "count");
+        assert!(hint["hint"]
+            .as_str()
+            .unwrap()
+            .starts_with("Property 'count' is required"));
+
+        // A value the schema allows but the tool refused.
+        let hint = argument_hint(&schema, &json!({"text": "hi", "count": 3}));
+        assert!(hint.get("property").is_none());
+        assert_eq!(hint["expected"], schema);
+        assert!(matches_type(&json!({"type": "number"}), &json!(3)));
+    }
+}
@@ -0,0 +1,136 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{required_str, ToolError, ToolHandler, ToolResult};
+use crate::egress::EgressPolicy;
+use crate::http;
+use crate::protocol::{Tool, ToolAnnotations};
+
+pub const NAME: &str = "http_fetch";
+const TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_RESPONSE_BYTES: usize = 4 * 1024 * 1024;
+
+/// GETs an `http://` or `https://` URL, connecting only where the egress
+/// policy allows.
+pub struct HttpFetchTool {
+    pub egress: Arc<EgressPolicy>,
+}
+
+pub fn definition() -> Tool {
+    Tool {
+        name: NAME.to_string(),
+        description: "Fetch an http:// or https:// URL with GET and return the status and body"
+            .to_string(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "url": {
+                    "type": "string",
+                    "description": "http:// or https:// URL to fetch"
+                }
+            },
+            "required": ["url"]
+        }),
+        output_schema: None,
+        annotations: Some(ToolAnnotations {
+            open_world_hint: Some(true),
+            ..ToolAnnotations::read_only()
+        }),
+        meta: Default::default(),
+    }
+}
+
+#[async_trait]
+impl ToolHandler for HttpFetchTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        let url =
+            http::Url::parse(required_str(&args, "url")?).map_err(ToolError::InvalidArguments)?;
+        let fetch = async {
+            let stream = self.egress.connect(NAME, &url.host, url.port).await?;
+            http::client::get(stream, &url, MAX_RESPONSE_BYTES).await
+        };
+        let fetched = tokio::time::timeout(TIMEOUT, fetch)
+            .await
+            .map_err(|_| {
+                ToolError::Execution(format!("Request timed out after {}s", TIMEOUT.as_secs()))
+            })?
+            .map_err(ToolError::Execution)?;
+
+        let content_type = fetched
+            .headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("Content-Type"))
+            .map_or("", |(_, value)| value.as_str());
+        Ok(ToolResult::text(format!(
+            "HTTP {} {}\n\n{}",
+            fetched.status,
+            content_type,
+            String::from_utf8_lossy(&fetched.body)
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::egress::EgressRule;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_fetch_through_egress_policy() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 4096];
+            let _ = stream.read(&mut request).await.unwrap();
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\n\r\n5\r\nhello\r\n0\r\n\r\n")
+                .await
+                .unwrap();
+        });
+        let url = format!("http://127.0.0.1:{}/greeting", port);
+
+        let denied = HttpFetchTool {
+            egress: Arc::new(EgressPolicy::new()),
+        };
+        assert!(matches!(
+            denied.call(serde_json::json!({ "url": url })).await,
+            Err(ToolError::Execution(e)) if e.contains("not allowed")
+        ));
+
+        let allowed = HttpFetchTool {
+            egress: Arc::new(
+                EgressPolicy::new().allow(NAME, EgressRule::new("127.0.0.1", vec![port]).unwrap()),
+            ),
+        };
+        let result = allowed
+            .call(serde_json::json!({ "url": url }))
+            .await
+            .unwrap();
+        assert_eq!(result.content[0]["text"], "HTTP 200 text/plain\n\nhello");
+    }
+
+    #[tokio::test]
+    async fn test_https_urls_are_fetched_over_tls() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\n\r\nplain").await;
+        });
+        let tool = HttpFetchTool {
+            egress: Arc::new(
+                EgressPolicy::new().allow(NAME, EgressRule::new("127.0.0.1", vec![port]).unwrap()),
+            ),
+        };
+
+        let url = format!("https://127.0.0.1:{}/", port);
+        assert!(matches!(
+            tool.call(serde_json::json!({ "url": url })).await,
+            Err(ToolError::Execution(e)) if e.contains("TLS handshake")
+        ));
+    }
+}
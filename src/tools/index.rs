@@ -0,0 +1,180 @@
+//! The registered tool definitions, indexed so that routing a call costs
+//! the same with ten tools as with ten thousand: names are hashed, each
+//! tool's versions are kept under its base name, and the sorted order is
+//! maintained as tools come and go rather than rebuilt for every listing.
+//! A generation number counts the changes, for caches built on top.
+
+use std::collections::{BTreeSet, HashMap};
+use std::sync::{Arc, OnceLock};
+
+use super::versions;
+use crate::protocol::Tool;
+
+#[derive(Debug, Default)]
+pub struct ToolIndex {
+    tools: HashMap<String, Tool>,
+    /// Every name, in order.
+    names: BTreeSet<String>,
+    /// Names of the versions of each tool, by base name.
+    versions: HashMap<String, BTreeSet<String>>,
+    generation: u64,
+    /// The sorted names, shared until the next change.
+    shared: OnceLock<Arc<[String]>>,
+}
+
+impl ToolIndex {
+    /// Adds `tool`, returning the definition it replaced.
+    pub fn insert(&mut self, tool: Tool) -> Option<Tool> {
+        self.changed();
+        let name = tool.name.clone();
+        if let (base, Some(_)) = versions::split(&name) {
+            self.versions
+                .entry(base.to_string())
+                .or_default()
+                .insert(name.clone());
+        }
+        self.names.insert(name.clone());
+        self.tools.insert(name, tool)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Tool> {
+        let tool = self.tools.remove(name)?;
+        self.changed();
+        self.names.remove(name);
+        if let (base, Some(_)) = versions::split(name) {
+            if let Some(names) = self.versions.get_mut(base) {
+                names.remove(name);
+                if names.is_empty() {
+                    self.versions.remove(base);
+                }
+            }
+        }
+        Some(tool)
+    }
+
+    /// Changes a definition in place. Returns `false` when no such tool is
+    /// registered.
+    pub fn update(&mut self, name: &str, change: impl FnOnce(&mut Tool)) -> bool {
+        match self.tools.get_mut(name) {
+            Some(tool) => {
+                change(tool);
+                self.changed();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Tool> {
+        self.tools.get(name)
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.tools.contains_key(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.tools.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    /// Every name, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.names.iter().map(String::as_str)
+    }
+
+    /// Every name, sorted, in a list built once per change and shared by
+    /// those who keep it, such as the timeline's snapshots.
+    pub fn shared_names(&self) -> Arc<[String]> {
+        self.shared
+            .get_or_init(|| self.names.iter().cloned().collect())
+            .clone()
+    }
+
+    /// Every tool, sorted by name.
+    pub fn iter(&self) -> impl Iterator<Item = &Tool> {
+        self.names.iter().map(|name| &self.tools[name])
+    }
+
+    /// The versions of `base`, such as `search@v1` and `search@v2` for
+    /// `search`.
+    pub fn versions(&self, base: &str) -> impl Iterator<Item = &Tool> {
+        self.versions
+            .get(base)
+            .into_iter()
+            .flatten()
+            .map(|name| &self.tools[name])
+    }
+
+    /// Bumped by every change.
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    fn changed(&mut self) {
+        self.generation += 1;
+        self.shared = OnceLock::new();
+    }
+}
+
+impl FromIterator<Tool> for ToolIndex {
+    fn from_iter<I: IntoIterator<Item = Tool>>(tools: I) -> Self {
+        let mut index = Self::default();
+        for tool in tools {
+            index.insert(tool);
+        }
+        index
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(name: &str) -> Tool {
+        Tool {
+            name: name.to_string(),
+            description: String::new(),
+            input_schema: serde_json::json!({"type": "object"}),
+            output_schema: None,
+            annotations: None,
+            meta: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_index_follows_changes() {
+        let mut index: ToolIndex = ["search@v2", "echo", "search@v1", "searcher"]
+            .into_iter()
+            .map(tool)
+            .collect();
+        assert_eq!(
+            index.names().collect::<Vec<_>>(),
+            ["echo", "search@v1", "search@v2", "searcher"]
+        );
+        let versions = |index: &ToolIndex| -> Vec<String> {
+            index.versions("search").map(|t| t.name.clone()).collect()
+        };
+        assert_eq!(versions(&index), ["search@v1", "search@v2"]);
+        assert_eq!(index.versions("echo").count(), 0);
+
+        let generation = index.generation();
+        let shared = index.shared_names();
+        assert!(Arc::ptr_eq(&shared, &index.shared_names()));
+        assert!(index.update("echo", |t| t.description = "Echoes".into()));
+        assert!(!index.update("missing", |_| {}));
+        assert_eq!(index.get("echo").unwrap().description, "Echoes");
+        assert!(index.remove("search@v1").is_some());
+        assert!(index.remove("search@v1").is_none());
+        assert_eq!(index.generation(), generation + 2);
+        assert_eq!(&shared[..], ["echo", "search@v1", "search@v2", "searcher"]);
+        assert_eq!(&index.shared_names()[..], ["echo", "search@v2", "searcher"]);
+        assert_eq!(versions(&index), ["search@v2"]);
+        index.remove("search@v2");
+        assert!(index.versions.is_empty());
+        assert_eq!(index.iter().count(), index.len());
+    }
+}
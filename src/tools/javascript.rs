@@ -0,0 +1,316 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::OnceCell;
+
+use super::args::Arguments;
+use super::{required_str, ToolContext, ToolError, ToolHandler, ToolResult};
+use crate::protocol::{Tool, ToolAnnotations, ToolExample, ToolMeta};
+use crate::repl::{Language, ReplManager};
+use crate::script;
+use crate::security::{SecurityEvent, SecurityEventKind, SecurityLog};
+
+pub const DEFAULT_TIMEOUT_SECS: u64 = 10;
+pub const DEFAULT_MAX_MEMORY_MB: u64 = 128;
+
+/// Memory a script may use beyond its V8 heap, for Node.js itself: its
+/// code, stacks and the buffers behind `Buffer`s.
+#[cfg(target_os = "linux")]
+const RUNTIME_OVERHEAD_MB: u64 = 64;
+
+/// Modules scripts may `require` unless `MCP_JS_ALLOWED_MODULES` overrides
+/// the list. None of them reach the filesystem, network or other processes
+/// by themselves; [`SANDBOX`] and Node's permission model close the routes
+/// around `require`.
+pub const DEFAULT_ALLOWED_MODULES: &[&str] = &[
+    "assert",
+    "buffer",
+    "querystring",
+    "string_decoder",
+    "url",
+    "util",
+];
+
+/// Locks a runner down before any user code runs. Runners load what they
+/// need themselves, then call `sandbox(allowed)`, which limits `require` to
+/// the allowlist, makes the process escapes that bypass it throw, such as
+/// `process.binding`, and removes the network globals. Node's permission
+/// model, which every runner runs under, also refuses filesystem access,
+/// child processes, workers and native addons.
+pub(crate) const SANDBOX: &str = r#"
+function sandbox(allowed) {
+  const Module = require('module');
+  const load = Module._load;
+  Module._load = function (request, parent, isMain) {
+    const name = request.startsWith('node:') ? request.slice(5) : request;
+    if (!allowed.has(name)) {
+      throw new Error(`Module '${request}' is not in the allowlist`);
+    }
+    return load.apply(this, arguments);
+  };
+  for (const name of ['binding', '_linkedBinding', 'dlopen', 'kill']) {
+    Object.defineProperty(process, name, {
+      value() { throw new Error(`process.${name} is not available`); },
+      writable: false,
+      configurable: false,
+    });
+  }
+  for (const name of ['fetch', 'WebSocket', 'EventSource']) {
+    delete globalThis[name];
+  }
+}
+"#;
+
+/// Reads the script from stdin, enters the sandbox and runs the script as
+/// the body of an async function taking `input`. A returned value is
+/// printed as JSON so scripts can act as data transforms.
+const RUNNER: &str = r#"
+const fs = require('fs');
+const vm = require('vm');
+const source = fs.readFileSync(0, 'utf8');
+const input = JSON.parse(process.env.MCP_JS_INPUT);
+sandbox(new Set(JSON.parse(process.env.MCP_JS_ALLOWED_MODULES)));
+const main = vm.runInThisContext(
+  '(async function (require, input) {\n' + source + '\n})',
+  { filename: '<js_run>', lineOffset: -1 }
+);
+main(require, input).then(
+  (value) => {
+    if (value !== undefined) {
+      process.stdout.write(JSON.stringify(value) + '\n');
+    }
+  },
+  (err) => {
+    console.error(err && err.stack ? err.stack : String(err));
+    process.exitCode = 1;
+  }
+);
+"#;
+
+/// Modules scripts are allowed to load, from the comma-separated
+/// `MCP_JS_ALLOWED_MODULES` or [`DEFAULT_ALLOWED_MODULES`].
+pub fn allowed_modules() -> Vec<String> {
+    match std::env::var("MCP_JS_ALLOWED_MODULES") {
+        Ok(list) => list
+            .split(',')
+            .map(|m| m.trim().to_string())
+            .filter(|m| !m.is_empty())
+            .collect(),
+        Err(_) => DEFAULT_ALLOWED_MODULES
+            .iter()
+            .map(|m| m.to_string())
+            .collect(),
+    }
+}
+
+/// A Node.js command (`MCP_NODE`, default `node`) running `program` after
+/// [`SANDBOX`], under Node's permission model and with the V8 heap capped
+/// at `max_memory_mb`. The environment is cleared, so secrets such as
+/// `MCP_AUTH_TOKENS` or cloud credentials are not inherited; only `PATH`
+/// and `MCP_JS_ALLOWED_MODULES` are set.
+///
+/// The heap cap leaves out memory V8 doesn't manage, such as `Buffer`s, so
+/// on Linux the whole process is also held to `max_memory_mb` plus
+/// [`RUNTIME_OVERHEAD_MB`] through `RLIMIT_DATA`, set with `prlimit(1)`.
+/// An allocation past it throws in the script or, when V8 can't recover,
+/// ends the run.
+pub(crate) async fn node_command(program: &str, max_memory_mb: u64) -> Result<Command, String> {
+    let node = std::env::var("MCP_NODE").unwrap_or_else(|_| "node".to_string());
+    #[cfg(target_os = "linux")]
+    let mut command = {
+        let limit_mb = max_memory_mb.saturating_add(RUNTIME_OVERHEAD_MB);
+        let mut command = Command::new("prlimit");
+        command
+            .arg(format!("--data={}", limit_mb.saturating_mul(1 << 20)))
+            .arg("--")
+            .arg(&node);
+        command
+    };
+    #[cfg(not(target_os = "linux"))]
+    let mut command = Command::new(&node);
+    command.env_clear();
+    if let Some(path) = std::env::var_os("PATH") {
+        command.env("PATH", path);
+    }
+    command
+        .arg(permission_flag(&node).await?)
+        // Node warns on stderr that the permission model is experimental.
+        .arg("--no-warnings")
+        .arg(format!("--max-old-space-size={}", max_memory_mb))
+        .arg("-e")
+        .arg(format!("{}{}", SANDBOX, program))
+        .env(
+            "MCP_JS_ALLOWED_MODULES",
+            serde_json::to_string(&allowed_modules()).unwrap(),
+        );
+    Ok(command)
+}
+
+/// The flag enabling Node's permission model: `--permission`, or
+/// `--experimental-permission` before Node.js 22.13. Scripts are refused
+/// when `node` knows neither.
+async fn permission_flag(node: &str) -> Result<&'static str, String> {
+    static FLAG: OnceCell<&'static str> = OnceCell::const_new();
+    let flag = FLAG
+        .get_or_try_init(|| async {
+            for flag in ["--permission", "--experimental-permission"] {
+                let mut probe = Command::new(node);
+                probe.arg(flag).arg("--no-warnings").arg("-e").arg("0");
+                let probe = script::run_with_input(probe, "", Duration::from_secs(10)).await;
+                if probe.is_ok_and(|output| output.status.success()) {
+                    return Ok(flag);
+                }
+            }
+            Err("js_run needs Node.js 20 or later, to run scripts under its permission model")
+        })
+        .await?;
+    Ok(flag)
+}
+
+/// Executes `code` with Node.js, capping its memory at `max_memory_mb`; see
+/// [`node_command`].
+pub async fn run(
+    code: &str,
+    input: &Value,
+    timeout: Duration,
+    max_memory_mb: u64,
+) -> Result<Vec<Value>, String> {
+    let mut command = node_command(RUNNER, max_memory_mb).await?;
+    command.env("MCP_JS_INPUT", input.to_string());
+
+    let output = script::run_with_input(command, code, timeout).await?;
+    let mut content = script::output_content(&output);
+    if content.is_empty() {
+        content.push(serde_json::json!({"type": "text", "text": ""}));
+    }
+    Ok(content)
+}
+
+/// `js_run`: one-shot scripts, or code run in a named REPL session.
+pub struct JsRunTool {
+    pub repl: Arc<ReplManager>,
+    /// Receives a sandbox violation whenever a script is refused a module.
+    pub security: SecurityLog,
+}
+
+pub fn definition() -> Tool {
+    Tool {
+        name: "js_run".to_string(),
+        description:
+            "Execute a JavaScript snippet with Node.js; a returned value is printed as JSON"
+                .to_string(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "code": {
+                    "type": "string",
+                    "description": "Body of an async function; `input` and an allowlisted `require` are in scope"
+                },
+                "input": {
+                    "description": "JSON value made available to the script as `input`"
+                },
+                "timeout_secs": {
+                    "type": "integer",
+                    "description": "Maximum execution time in seconds (default 10)"
+                },
+                "max_memory_mb": {
+                    "type": "integer",
+                    "description": "Memory limit in megabytes (default 128, ignored for sessions)"
+                },
+                "session": {
+                    "type": "string",
+                    "description": "Name of a persistent session; variables survive across calls"
+                }
+            },
+            "required": ["code"]
+        }),
+        output_schema: None,
+        annotations: Some(ToolAnnotations::mutating(true)),
+        meta: ToolMeta {
+            examples: vec![ToolExample::new(
+                "Transform the input and return the result",
+                serde_json::json!({
+                    "code": "return input.map((n) => n * 2);",
+                    "input": [1, 2, 3]
+                }),
+            )],
+            ..Default::default()
+        },
+    }
+}
+
+#[async_trait]
+impl ToolHandler for JsRunTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        self.call_with_context(args, &ToolContext::default()).await
+    }
+
+    async fn call_with_context(
+        &self,
+        args: Value,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        self.call_shared(args.into(), context).await
+    }
+
+    /// Sessions are the calling client session's own; see [`ReplManager`].
+    /// The code and input are only read, so large ones are never copied.
+    async fn call_shared(
+        &self,
+        args: Arguments,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let code = required_str(&args, "code")?;
+        let input = args.get("input").unwrap_or(&Value::Null);
+        let timeout_secs = args
+            .get("timeout_secs")
+            .and_then(|t| t.as_u64())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+        let max_memory_mb = args
+            .get("max_memory_mb")
+            .and_then(|m| m.as_u64())
+            .unwrap_or(DEFAULT_MAX_MEMORY_MB);
+        let timeout = Duration::from_secs(timeout_secs);
+
+        let outcome = match args.get("session").and_then(|s| s.as_str()) {
+            Some(name) => {
+                self.repl
+                    .execute(
+                        context.session_id(),
+                        name,
+                        Language::JavaScript,
+                        code,
+                        input,
+                        timeout,
+                    )
+                    .await
+            }
+            None => run(code, input, timeout, max_memory_mb).await,
+        };
+        let content = outcome.map_err(ToolError::Execution)?;
+        if let Some(module) = blocked_module(&content) {
+            self.security.report(
+                SecurityEvent::new(
+                    SecurityEventKind::SandboxViolation,
+                    format!("Script tried to load module '{}'", module),
+                )
+                .rule("js_module_allowlist")
+                .tool("js_run")
+                .detail("module", module),
+            );
+        }
+        Ok(ToolResult::new(content))
+    }
+}
+
+/// The module named in the allowlist error the runner prints on stderr.
+fn blocked_module(content: &[Value]) -> Option<String> {
+    content.iter().find_map(|block| {
+        let text = block["text"].as_str()?;
+        let end = text.find("' is not in the allowlist")?;
+        let start = text[..end].rfind("Module '")? + "Module '".len();
+        Some(text[start..end].to_string())
+    })
+}
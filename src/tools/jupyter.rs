@@ -0,0 +1,241 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::{required_str, ToolContext, ToolError, ToolHandler, ToolResult};
+use crate::jupyter::{Cell, KernelManager, DEFAULT_WAIT_SECS};
+use crate::protocol::{Tool, ToolAnnotations, ToolExample, ToolMeta};
+
+/// Which `kernel_*` operation a [`KernelTool`] performs.
+#[derive(Debug, Clone, Copy)]
+pub enum KernelOperation {
+    Start,
+    Execute,
+    Fetch,
+    Shutdown,
+    List,
+}
+
+/// One of the Jupyter kernel tools, all sharing the same kernels. Kernels
+/// are the calling client session's own; see [`KernelManager`].
+pub struct KernelTool {
+    pub kernels: Arc<KernelManager>,
+    pub operation: KernelOperation,
+}
+
+fn kernel_property() -> Value {
+    serde_json::json!({
+        "type": "string",
+        "description": "Id returned by kernel_start"
+    })
+}
+
+fn wait_property() -> Value {
+    serde_json::json!({
+        "type": "integer",
+        "description": "Seconds to wait for the cell to finish (default 30)"
+    })
+}
+
+/// The five kernel tools with their operations.
+pub fn definitions() -> Vec<(Tool, KernelOperation)> {
+    vec![
+        (
+            Tool {
+                name: "kernel_start".to_string(),
+                description:
+                    "Start a Jupyter kernel whose state persists across kernel_execute calls"
+                        .to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations::mutating(false)),
+                meta: Default::default(),
+            },
+            KernelOperation::Start,
+        ),
+        (
+            Tool {
+                name: "kernel_execute".to_string(),
+                description:
+                    "Run a cell in a Jupyter kernel and return its outputs, text and images"
+                        .to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "kernel": kernel_property(),
+                        "code": {
+                            "type": "string",
+                            "description": "Source of the cell"
+                        },
+                        "wait_secs": wait_property()
+                    },
+                    "required": ["kernel", "code"]
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations::mutating(true)),
+                meta: ToolMeta {
+                    examples: vec![ToolExample::new(
+                        "Plot in a running kernel",
+                        serde_json::json!({
+                            "kernel": "3f2a9c1d04be",
+                            "code": "import matplotlib.pyplot as plt\nplt.plot([1, 4, 9])"
+                        }),
+                    )],
+                    ..Default::default()
+                },
+            },
+            KernelOperation::Execute,
+        ),
+        (
+            Tool {
+                name: "kernel_fetch".to_string(),
+                description: "Fetch the outputs of a cell kernel_execute stopped waiting for"
+                    .to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "kernel": kernel_property(),
+                        "cell": {
+                            "type": "string",
+                            "description": "Cell id reported by kernel_execute"
+                        },
+                        "wait_secs": wait_property()
+                    },
+                    "required": ["kernel", "cell"]
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations::read_only()),
+                meta: Default::default(),
+            },
+            KernelOperation::Fetch,
+        ),
+        (
+            Tool {
+                name: "kernel_shutdown".to_string(),
+                description: "Shut down a Jupyter kernel, discarding its state".to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "kernel": kernel_property()
+                    },
+                    "required": ["kernel"]
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations::mutating(true)),
+                meta: Default::default(),
+            },
+            KernelOperation::Shutdown,
+        ),
+        (
+            Tool {
+                name: "kernel_list".to_string(),
+                description: "List the running Jupyter kernels".to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations::read_only()),
+                meta: Default::default(),
+            },
+            KernelOperation::List,
+        ),
+    ]
+}
+
+/// A text block describing the cell, then its outputs.
+fn cell_result(kernel: &str, id: &str, cell: Cell) -> ToolResult {
+    let status = match (cell.done, cell.error) {
+        (false, _) => "running",
+        (true, false) => "ok",
+        (true, true) => "error",
+    };
+    let mut content = vec![super::content::text(
+        serde_json::json!({
+            "kernel": kernel,
+            "cell": id,
+            "status": status,
+            "executionCount": cell.execution_count,
+        })
+        .to_string(),
+    )];
+    content.extend(cell.outputs);
+    ToolResult::new(content)
+}
+
+fn wait(args: &Value) -> Duration {
+    Duration::from_secs(
+        args.get("wait_secs")
+            .and_then(Value::as_u64)
+            .unwrap_or(DEFAULT_WAIT_SECS),
+    )
+}
+
+#[async_trait]
+impl ToolHandler for KernelTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        self.call_with_context(args, &ToolContext::default()).await
+    }
+
+    async fn call_with_context(
+        &self,
+        args: Value,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let owner = context.session_id();
+        match self.operation {
+            KernelOperation::Start => {
+                let id = self
+                    .kernels
+                    .start(owner)
+                    .await
+                    .map_err(ToolError::Execution)?;
+                Ok(ToolResult::json(&serde_json::json!({ "kernel": id })))
+            }
+            KernelOperation::Execute => {
+                let id = required_str(&args, "kernel")?;
+                let code = required_str(&args, "code")?;
+                let kernel = self
+                    .kernels
+                    .get(owner, id)
+                    .map_err(ToolError::InvalidArguments)?;
+                let (cell_id, cell) = kernel
+                    .execute(code, wait(&args))
+                    .await
+                    .map_err(ToolError::Execution)?;
+                Ok(cell_result(id, &cell_id, cell))
+            }
+            KernelOperation::Fetch => {
+                let id = required_str(&args, "kernel")?;
+                let cell_id = required_str(&args, "cell")?;
+                let kernel = self
+                    .kernels
+                    .get(owner, id)
+                    .map_err(ToolError::InvalidArguments)?;
+                match kernel.wait(cell_id, wait(&args)).await {
+                    Some(cell) => Ok(cell_result(id, cell_id, cell)),
+                    None => Err(ToolError::InvalidArguments(format!(
+                        "Cell '{}' not found",
+                        cell_id
+                    ))),
+                }
+            }
+            KernelOperation::Shutdown => {
+                let id = required_str(&args, "kernel")?;
+                if self.kernels.shutdown(owner, id).await {
+                    Ok(ToolResult::text(format!("Kernel '{}' shut down", id)))
+                } else {
+                    Err(ToolError::InvalidArguments(format!(
+                        "Kernel '{}' not found",
+                        id
+                    )))
+                }
+            }
+            KernelOperation::List => Ok(ToolResult::json(&Value::Array(self.kernels.list(owner)))),
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Tools whose backend is loaded on first use. The definition, and so the
+//! schema clients see in `tools/list`, is registered at once, while the
+//! handler behind it, such as a plugin or a model server, is built only
+//! when the tool is first warmed up or called. A server with dozens of
+//! heavyweight tools then starts fast and holds only those it uses.
+
+use async_trait::async_trait;
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+
+use super::args::Arguments;
+use super::{ToolContext, ToolError, ToolHandler, ToolResult};
+
+type Loading = Pin<Box<dyn Future<Output = Result<Arc<dyn ToolHandler>, String>> + Send>>;
+
+/// Builds its handler on first use, and keeps it. A load that fails fails
+/// the call and is tried again on the next.
+pub struct LazyTool {
+    name: String,
+    loader: Box<dyn Fn() -> Loading + Send + Sync>,
+    handler: OnceCell<Arc<dyn ToolHandler>>,
+}
+
+impl LazyTool {
+    pub fn new<F, Fut, H>(name: &str, loader: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<H, String>> + Send + 'static,
+        H: ToolHandler + 'static,
+    {
+        Self {
+            name: name.to_string(),
+            loader: Box::new(move || {
+                let loading = loader();
+                Box::pin(async move { Ok(Arc::new(loading.await?) as Arc<dyn ToolHandler>) })
+            }),
+            handler: OnceCell::new(),
+        }
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.handler.initialized()
+    }
+
+    async fn load(&self) -> Result<&Arc<dyn ToolHandler>, String> {
+        self.handler.get_or_try_init(|| (self.loader)()).await
+    }
+
+    async fn handler(&self) -> Result<&Arc<dyn ToolHandler>, ToolError> {
+        self.load().await.map_err(|e| {
+            ToolError::Execution(format!("Tool '{}' failed to load: {}", self.name, e))
+        })
+    }
+}
+
+#[async_trait]
+impl ToolHandler for LazyTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        self.handler().await?.call(args).await
+    }
+
+    async fn call_with_context(
+        &self,
+        args: Value,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        self.handler().await?.call_with_context(args, context).await
+    }
+
+    async fn call_shared(
+        &self,
+        args: Arguments,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        self.handler().await?.call_shared(args, context).await
+    }
+
+    /// Loading is the warm-up, so it runs under the warm-up's timeout and
+    /// shows in its status.
+    fn warms_up(&self) -> bool {
+        true
+    }
+
+    async fn warmup(&self) -> Result<(), String> {
+        self.load().await?.warmup().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct Echo;
+
+    #[async_trait]
+    impl ToolHandler for Echo {
+        async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+            Ok(ToolResult::text(args.to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tools_load_once_on_first_use() {
+        let loads = Arc::new(AtomicUsize::new(0));
+        let counter = loads.clone();
+        let tool = LazyTool::new("echo", move || {
+            let attempt = counter.fetch_add(1, Ordering::SeqCst);
+            async move {
+                match attempt {
+                    0 => Err("backend not ready".to_string()),
+                    _ => Ok(Echo),
+                }
+            }
+        });
+        assert!(!tool.is_loaded());
+        assert_eq!(loads.load(Ordering::SeqCst), 0);
+
+        let error = tool.call(serde_json::json!(1)).await.unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Tool 'echo' failed to load: backend not ready"
+        );
+        assert!(!tool.is_loaded());
+        for _ in 0..2 {
+            let result = tool.call(serde_json::json!(2)).await.unwrap();
+            assert_eq!(result.content[0]["text"], "2");
+        }
+        assert!(tool.is_loaded());
+        assert_eq!(loads.load(Ordering::SeqCst), 2);
+    }
+}
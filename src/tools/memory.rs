@@ -0,0 +1,156 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+
+use super::{required_str, ToolError, ToolHandler, ToolResult};
+use crate::memory::MemoryStore;
+use crate::protocol::{Tool, ToolAnnotations};
+
+/// Which `memory_*` operation a [`MemoryTool`] performs.
+#[derive(Debug, Clone, Copy)]
+pub enum MemoryOperation {
+    CreateEntities,
+    CreateRelations,
+    Search,
+    Graph,
+}
+
+/// One of the knowledge-graph tools, all sharing the same store.
+pub struct MemoryTool {
+    pub store: Arc<MemoryStore>,
+    pub operation: MemoryOperation,
+}
+
+/// The four memory tools with their operations.
+pub fn definitions() -> Vec<(Tool, MemoryOperation)> {
+    vec![
+        (
+            Tool {
+                name: "memory_create_entities".to_string(),
+                description:
+                    "Add entities to the knowledge graph; existing names are left unchanged"
+                        .to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "entities": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "name": {"type": "string"},
+                                    "entityType": {"type": "string"},
+                                    "observations": {"type": "array", "items": {"type": "string"}}
+                                },
+                                "required": ["name", "entityType"]
+                            }
+                        }
+                    },
+                    "required": ["entities"]
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations::mutating(false)),
+                meta: Default::default(),
+            },
+            MemoryOperation::CreateEntities,
+        ),
+        (
+            Tool {
+                name: "memory_create_relations".to_string(),
+                description: "Add directed relations between existing entities".to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "relations": {
+                            "type": "array",
+                            "items": {
+                                "type": "object",
+                                "properties": {
+                                    "from": {"type": "string"},
+                                    "to": {"type": "string"},
+                                    "relationType": {"type": "string"}
+                                },
+                                "required": ["from", "to", "relationType"]
+                            }
+                        }
+                    },
+                    "required": ["relations"]
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations::mutating(false)),
+                meta: Default::default(),
+            },
+            MemoryOperation::CreateRelations,
+        ),
+        (
+            Tool {
+                name: "memory_search".to_string(),
+                description: "Find entities whose name, type or observations match a query"
+                    .to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "query": {
+                            "type": "string",
+                            "description": "Case-insensitive text to look for"
+                        }
+                    },
+                    "required": ["query"]
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations::read_only()),
+                meta: Default::default(),
+            },
+            MemoryOperation::Search,
+        ),
+        (
+            Tool {
+                name: "memory_graph".to_string(),
+                description: "Return the whole knowledge graph".to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {}
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations::read_only()),
+                meta: Default::default(),
+            },
+            MemoryOperation::Graph,
+        ),
+    ]
+}
+
+#[async_trait]
+impl ToolHandler for MemoryTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        let argument = |key: &str| args.get(key).cloned().unwrap_or(Value::Null);
+
+        let value = match self.operation {
+            MemoryOperation::CreateEntities => {
+                let entities = serde_json::from_value(argument("entities"))
+                    .map_err(|e| ToolError::InvalidArguments(format!("Invalid entities: {}", e)))?;
+                let created = self
+                    .store
+                    .create_entities(entities)
+                    .map_err(ToolError::Execution)?;
+                serde_json::json!({"created": created})
+            }
+            MemoryOperation::CreateRelations => {
+                let relations = serde_json::from_value(argument("relations")).map_err(|e| {
+                    ToolError::InvalidArguments(format!("Invalid relations: {}", e))
+                })?;
+                let created = self
+                    .store
+                    .create_relations(relations)
+                    .map_err(ToolError::InvalidArguments)?;
+                serde_json::json!({"created": created})
+            }
+            MemoryOperation::Search => {
+                let query = required_str(&args, "query")?;
+                serde_json::to_value(self.store.search(query)).unwrap()
+            }
+            MemoryOperation::Graph => serde_json::to_value(self.store.graph()).unwrap(),
+        };
+        Ok(ToolResult::json(&value))
+    }
+}
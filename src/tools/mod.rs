@@ -0,0 +1,370 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use crate::artifacts::{Artifact, ArtifactStore};
+use crate::cancellation::CancellationToken;
+use crate::locale::Locale;
+use crate::logging::Logger;
+use crate::roots::{self, Root};
+use crate::sampling::{self, SamplingRequest, SamplingResponse};
+use crate::session::Session;
+use crate::tool_config::{ToolConfig, ToolConfigStore};
+
+pub mod args;
+pub mod assert;
+pub mod concurrency;
+pub mod content;
+pub mod echo;
+pub mod fs;
+pub mod help;
+pub mod hints;
+pub mod http_fetch;
+pub mod index;
+pub mod javascript;
+pub mod jupyter;
+pub mod lazy;
+pub mod memory;
+pub mod names;
+pub mod python;
+pub mod rate_limits;
+pub mod repair;
+pub mod repl;
+pub mod schema;
+pub mod timeouts;
+pub mod tokens;
+pub mod versions;
+pub mod warmup;
+pub mod workflow;
+pub mod workspace;
+
+/// Implementation behind a registered tool. `args` is the `arguments` object
+/// of the `tools/call` request, or an empty object when the client sent none.
+#[async_trait]
+pub trait ToolHandler: Send + Sync {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError>;
+
+    /// Called with the caller's context. Tools that need to know who is
+    /// calling, such as the `workspace_*` tools, override this; the default
+    /// ignores the context.
+    async fn call_with_context(
+        &self,
+        args: Value,
+        _context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        self.call(args).await
+    }
+
+    /// What the server actually invokes, with the arguments shared rather
+    /// than copied. Tools taking large inputs, such as whole documents,
+    /// override this to read or [`parse`](args::Arguments::parse) them in
+    /// place; the default takes them as an owned `Value`, which copies them
+    /// while the server still holds them.
+    async fn call_shared(
+        &self,
+        args: args::Arguments,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        self.call_with_context(args.into_value(), context).await
+    }
+
+    /// Whether the tool has a [`warmup`](Self::warmup) routine. Tools that
+    /// override `warmup` return `true` here too.
+    fn warms_up(&self) -> bool {
+        false
+    }
+
+    /// Prepares an expensive backend before the first call needs it, such
+    /// as loading a model, opening a connection pool or priming a cache.
+    /// Runs once, at startup or before the first call; see [`warmup`].
+    async fn warmup(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Runs `handler` in a task of its own, so a panic in it fails only this
+/// call, with an error result, instead of the request or the connection
+/// serving it. The panic itself still goes to stderr. Dropping the returned
+/// future aborts the task, just as dropping an inline call would stop it.
+pub async fn call_isolated(
+    handler: Arc<dyn ToolHandler>,
+    name: &str,
+    args: args::Arguments,
+    context: &ToolContext,
+) -> Result<ToolResult, ToolError> {
+    struct AbortOnDrop(tokio::task::AbortHandle);
+
+    impl Drop for AbortOnDrop {
+        fn drop(&mut self) {
+            self.0.abort();
+        }
+    }
+
+    let context = context.clone();
+    let task = tokio::spawn(async move { handler.call_shared(args, &context).await });
+    let _guard = AbortOnDrop(task.abort_handle());
+    task.await.unwrap_or_else(|_| {
+        Err(ToolError::Execution(format!(
+            "Tool '{}' failed with an internal error",
+            name
+        )))
+    })
+}
+
+/// What a tool call knows about where it came from.
+#[derive(Clone, Default)]
+pub struct ToolContext {
+    /// The calling session; `None` for requests answered by
+    /// [`McpServer::handle_request`](crate::McpServer::handle_request)
+    /// outside any transport.
+    pub session: Option<Arc<Session>>,
+    /// Where generated files go, when the server has an artifact store.
+    pub artifacts: Option<Arc<ArtifactStore>>,
+    /// Cancelled when the client sends `notifications/cancelled` for this
+    /// request.
+    pub cancellation: CancellationToken,
+    pub tool_config: Arc<ToolConfigStore>,
+    /// A fresh UUID for each request the server handles, naming it in the
+    /// server's logs and audit records. `None` outside the server.
+    pub correlation_id: Option<String>,
+}
+
+impl ToolContext {
+    /// The settings `tool` keeps in the server's tool config store.
+    pub fn config(&self, tool: &str) -> ToolConfig {
+        ToolConfig::new(self.tool_config.clone(), tool)
+    }
+
+    /// How to render numbers and dates for the client: the locale it sent
+    /// with `initialize`, or else `MCP_LOCALE`.
+    pub fn locale(&self) -> Locale {
+        self.session
+            .as_ref()
+            .and_then(|s| s.locale())
+            .unwrap_or_else(Locale::from_env)
+    }
+
+    /// Handle for sending log entries to the client as
+    /// `notifications/message`, labelled with `name`, usually the tool's.
+    pub fn logger(&self, name: &str) -> Logger {
+        Logger::new(self.session.clone(), Some(name.to_string()))
+    }
+
+    /// Publishes a file `tool` generated, so the client can read it as a
+    /// resource or download it from the artifact's signed URL. The returned
+    /// artifact is usually included in the tool's result.
+    pub fn publish_artifact(
+        &self,
+        tool: &str,
+        name: &str,
+        mime_type: &str,
+        contents: &[u8],
+    ) -> Result<Artifact, ToolError> {
+        let store = self.artifacts.as_ref().ok_or_else(|| {
+            ToolError::Execution("Artifact publishing is not enabled".to_string())
+        })?;
+        store
+            .publish(tool, name, mime_type, contents, None)
+            .map_err(ToolError::Execution)
+    }
+
+    /// The calling session's id, or an empty string outside any session.
+    pub fn session_id(&self) -> &str {
+        self.session.as_ref().map_or("", |s| s.id())
+    }
+
+    /// The directories the client shared through `roots/list`, or `None`
+    /// when it has not shared any list, such as a client without the
+    /// `roots` capability.
+    pub fn roots(&self) -> Option<Vec<Root>> {
+        self.session.as_ref()?.roots()
+    }
+
+    /// Resolves a path argument, confined to the client's roots when it has
+    /// shared them: a relative path is taken from the first root, and one
+    /// outside every root is rejected. Without roots the path is used as
+    /// given.
+    pub fn resolve_path(&self, path: &str) -> Result<PathBuf, ToolError> {
+        match self.roots() {
+            Some(roots) => {
+                roots::confine(&roots, Path::new(path)).map_err(ToolError::InvalidArguments)
+            }
+            None => Ok(PathBuf::from(path)),
+        }
+    }
+
+    /// Asks the client's model to generate a message, for tools that need
+    /// an LLM without holding credentials of their own. Fails when the
+    /// client did not declare the `sampling` capability, and gives up after
+    /// [`sampling::TIMEOUT`] or when the call is cancelled.
+    pub async fn sample(&self, request: SamplingRequest) -> Result<SamplingResponse, ToolError> {
+        let session = self
+            .session
+            .as_ref()
+            .ok_or_else(|| ToolError::Execution("Sampling needs a client session".to_string()))?;
+        if session.client_capabilities().get("sampling").is_none() {
+            return Err(ToolError::Execution(
+                "The client does not support sampling".to_string(),
+            ));
+        }
+        let params = serde_json::to_value(&request).unwrap();
+        let result = tokio::select! {
+            result = session.request("sampling/createMessage", params, sampling::TIMEOUT) => result,
+            _ = self.cancellation.cancelled() => {
+                return Err(ToolError::Execution("Sampling was cancelled".to_string()));
+            }
+        };
+        let result =
+            result.map_err(|e| ToolError::Execution(format!("Sampling failed: {}", e.message)))?;
+        serde_json::from_value(result).map_err(|e| {
+            ToolError::Execution(format!("The client's sampling result is invalid: {}", e))
+        })
+    }
+}
+
+/// Handlers keyed by tool name. Clones share the same map, so a tool such as
+/// `workflow_advance` can dispatch to other registered tools.
+#[derive(Clone, Default)]
+pub struct Registry {
+    handlers: Arc<RwLock<HashMap<String, Arc<dyn ToolHandler>>>>,
+}
+
+impl Registry {
+    pub fn insert(&self, name: String, handler: Arc<dyn ToolHandler>) {
+        self.handlers.write().unwrap().insert(name, handler);
+    }
+
+    pub fn remove(&self, name: &str) {
+        self.handlers.write().unwrap().remove(name);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn ToolHandler>> {
+        self.handlers.read().unwrap().get(name).cloned()
+    }
+}
+
+/// Tool output, serialized as the `tools/call` result. A result with
+/// `is_error` set reports that the tool ran and failed, in words the model
+/// can read and act on, rather than a protocol error.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ToolResult {
+    pub content: Vec<Value>,
+    /// Output for clients that parse it, checked against the tool's
+    /// `output_schema` before it is sent.
+    #[serde(rename = "structuredContent", skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+    #[serde(rename = "isError", skip_serializing_if = "std::ops::Not::not")]
+    pub is_error: bool,
+}
+
+impl ToolResult {
+    pub fn new(content: Vec<Value>) -> Self {
+        Self {
+            content,
+            structured_content: None,
+            is_error: false,
+        }
+    }
+
+    /// A failed call, explained by `message` in a single text block.
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            is_error: true,
+            ..Self::text(message)
+        }
+    }
+
+    /// The text of a failed call, or `None` when the call succeeded.
+    pub fn error_message(&self) -> Option<String> {
+        self.is_error.then(|| {
+            self.content
+                .iter()
+                .filter_map(|block| block["text"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n")
+        })
+    }
+
+    /// `value` as structured content, repeated as a JSON text block for
+    /// clients that only read `content`.
+    pub fn structured(value: Value) -> Self {
+        Self {
+            structured_content: Some(value.clone()),
+            ..Self::json(&value)
+        }
+    }
+
+    /// A result holding a single text block.
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::new(vec![content::text(text)])
+    }
+
+    /// A result holding a single image, such as a screenshot or a chart.
+    pub fn image(bytes: &[u8], mime_type: &str) -> Self {
+        Self::new(vec![content::image(bytes, mime_type)])
+    }
+
+    /// A result holding a single audio clip.
+    pub fn audio(bytes: &[u8], mime_type: &str) -> Self {
+        Self::new(vec![content::audio(bytes, mime_type)])
+    }
+
+    /// The result with `block`, made by one of the [`content`] builders,
+    /// appended.
+    pub fn with_content(mut self, block: Value) -> Self {
+        self.content.push(block);
+        self
+    }
+
+    /// A single text block holding `value` as pretty-printed JSON.
+    pub fn json(value: &Value) -> Self {
+        Self::text(serde_json::to_string_pretty(value).unwrap())
+    }
+}
+
+/// Why a tool call failed. Rejected arguments are a protocol error; a
+/// failed execution is sent as a result with `isError` set, made by
+/// [`ToolResult::error`], so the model sees what went wrong.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolError {
+    /// The arguments were missing or malformed (`-32602`).
+    InvalidArguments(String),
+    /// The tool could not complete the call. Its code, `-32603`, is only
+    /// used in records such as recent errors; clients get an `isError`
+    /// result.
+    Execution(String),
+}
+
+impl ToolError {
+    pub fn code(&self) -> i32 {
+        match self {
+            ToolError::InvalidArguments(_) => -32602,
+            ToolError::Execution(_) => -32603,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            ToolError::InvalidArguments(message) | ToolError::Execution(message) => message,
+        }
+    }
+}
+
+impl fmt::Display for ToolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.message())
+    }
+}
+
+impl std::error::Error for ToolError {}
+
+/// String argument `key`, or an `InvalidArguments` error naming it.
+pub fn required_str<'a>(args: &'a Value, key: &str) -> Result<&'a str, ToolError> {
+    args.get(key)
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| ToolError::InvalidArguments(format!("Missing required argument: {}", key)))
+}
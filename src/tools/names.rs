@@ -0,0 +1,180 @@
+//! Matching of near-miss tool names, such as `Echo` or `echo_text` for
+//! `echo`, so a model's slip costs a correction instead of a retry loop.
+
+/// Scores at or above this are offered as suggestions.
+const SUGGESTION_THRESHOLD: f64 = 0.5;
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Used when fuzzy matching is switched on without a threshold.
+pub const DEFAULT_THRESHOLD: f64 = 0.75;
+
+/// `MCP_FUZZY_TOOL_NAMES`: `1` or `true` for [`DEFAULT_THRESHOLD`], or a
+/// threshold between 0 and 1. Unset means exact names only.
+pub fn threshold_from_env() -> Option<f64> {
+    let value = std::env::var("MCP_FUZZY_TOOL_NAMES").ok()?;
+    match value.as_str() {
+        "1" | "true" => Some(DEFAULT_THRESHOLD),
+        value => value.parse().ok().filter(|t| (0.0..=1.0).contains(t)),
+    }
+}
+
+/// Outcome of [`resolve`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    /// A registered name; the argument when it matched exactly.
+    Found(String),
+    /// No single name was close enough; the closest, best first.
+    NotFound(Vec<String>),
+}
+
+/// Lower-case alphanumeric words: `getUser-Info` is `get`, `user`, `info`.
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut previous_lower = false;
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            previous_lower = false;
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            continue;
+        }
+        if c.is_uppercase() && previous_lower {
+            words.push(std::mem::take(&mut word));
+        }
+        previous_lower = c.is_lowercase() || c.is_ascii_digit();
+        word.extend(c.to_lowercase());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Edit distance counting an adjacent transposition as one edit, so `ehco`
+/// is one away from `echo`.
+fn distance(a: &[char], b: &[char]) -> usize {
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            row[j] = (rows[i - 1][j] + 1)
+                .min(row[j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
+
+/// How alike two tool names are, from 0 to 1. Names equal but for case and
+/// separators score 1; a name whose words all appear in the other, like
+/// `echo` in `echo_text`, scores 0.85; otherwise the score is the share of
+/// characters that need no edit.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let (a_words, b_words) = (words(a), words(b));
+    let a: Vec<char> = a_words.concat().chars().collect();
+    let b: Vec<char> = b_words.concat().chars().collect();
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+    let contained = |inner: &[String], outer: &[String]| inner.iter().all(|w| outer.contains(w));
+    if contained(&a_words, &b_words) || contained(&b_words, &a_words) {
+        return 0.85;
+    }
+    1.0 - distance(&a, &b) as f64 / a.len().max(b.len()) as f64
+}
+
+/// Finds `requested` among `names`. An exact match always wins. With a
+/// `threshold`, the single name scoring highest at or above it is used
+/// instead; a tie is not resolved, since guessing between two tools is
+/// worse than asking.
+pub fn resolve<'a>(
+    requested: &str,
+    names: impl IntoIterator<Item = &'a str>,
+    threshold: Option<f64>,
+) -> Resolution {
+    let mut scored: Vec<(f64, &str)> = Vec::new();
+    for name in names {
+        if name == requested {
+            return Resolution::Found(name.to_string());
+        }
+        let score = similarity(requested, name);
+        if score >= SUGGESTION_THRESHOLD {
+            scored.push((score, name));
+        }
+    }
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then(a.1.cmp(b.1)));
+    if let (Some(threshold), Some(&(best, name))) = (threshold, scored.first()) {
+        let tied = scored.get(1).is_some_and(|&(next, _)| next == best);
+        if best >= threshold && !tied {
+            return Resolution::Found(name.to_string());
+        }
+    }
+    Resolution::NotFound(
+        scored
+            .into_iter()
+            .take(MAX_SUGGESTIONS)
+            .map(|(_, name)| name.to_string())
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: [&str; 5] = [
+        "echo",
+        "fs_read",
+        "memory_read",
+        "workspace_read",
+        "workspace_write",
+    ];
+
+    #[test]
+    fn test_resolves_near_misses() {
+        let fuzzy = Some(DEFAULT_THRESHOLD);
+        assert_eq!(
+            resolve("echo", NAMES, None),
+            Resolution::Found("echo".into())
+        );
+        for near in ["Echo", "ECHO", "echo_text", "ehco", "echoText"] {
+            assert_eq!(
+                resolve(near, NAMES, fuzzy),
+                Resolution::Found("echo".into()),
+                "{}",
+                near
+            );
+        }
+        assert_eq!(
+            resolve("WorkspaceWrite", NAMES, fuzzy),
+            Resolution::Found("workspace_write".into())
+        );
+        // Without fuzzy matching the closest names are only suggested.
+        assert_eq!(
+            resolve("Echo", NAMES, None),
+            Resolution::NotFound(vec!["echo".into()])
+        );
+        // `read` is in three names, so none is picked.
+        let Resolution::NotFound(suggestions) = resolve("read", NAMES, fuzzy) else {
+            panic!("an ambiguous name resolved");
+        };
+        assert_eq!(
+            suggestions,
+            vec!["fs_read", "memory_read", "workspace_read"]
+        );
+        assert_eq!(
+            resolve("unknown_tool", NAMES, fuzzy),
+            Resolution::NotFound(Vec::new())
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use super::args::Arguments;
+use super::{required_str, ToolContext, ToolError, ToolHandler, ToolResult};
+use crate::gc::ScratchDir;
+use crate::protocol::{Tool, ToolAnnotations, ToolExample, ToolMeta};
+use crate::repl::{Language, ReplManager};
+use crate::sandbox::Sandbox;
+use crate::script;
+
+pub const DEFAULT_TIMEOUT_SECS: u64 = 30;
+
+/// Runs the user code via `exec` so matplotlib figures left open at the end
+/// can be saved and returned as image blocks.
+const RUNNER: &str = r#"
+import base64, json, os, sys, traceback
+source = sys.stdin.read()
+status = 0
+try:
+    exec(compile(source, "<python_run>", "exec"), {"__name__": "__main__"})
+except SystemExit as e:
+    status = e.code if isinstance(e.code, int) else 1
+except BaseException:
+    traceback.print_exc()
+    status = 1
+finally:
+    figures = []
+    if "matplotlib.pyplot" in sys.modules:
+        import io
+        plt = sys.modules["matplotlib.pyplot"]
+        for num in plt.get_fignums():
+            buf = io.BytesIO()
+            plt.figure(num).savefig(buf, format="png")
+            figures.append(base64.b64encode(buf.getvalue()).decode("ascii"))
+    with open(os.path.join(os.environ["MCP_FIGURE_DIR"], "figures.json"), "w") as f:
+        json.dump(figures, f)
+sys.exit(status)
+"#;
+
+/// Interpreter used by `python_run`: the `python` inside `MCP_PYTHON_VENV`
+/// when set, otherwise `MCP_PYTHON`, otherwise `python3` from `PATH`.
+pub fn interpreter() -> PathBuf {
+    if let Ok(venv) = std::env::var("MCP_PYTHON_VENV") {
+        return PathBuf::from(venv).join("bin").join("python");
+    }
+    std::env::var("MCP_PYTHON")
+        .map(PathBuf::from)
+        .unwrap_or_else(|_| PathBuf::from("python3"))
+}
+
+/// Executes `code` under `sandbox` and returns the content blocks for the
+/// tool result: stdout, stderr, the exit status when non-zero, and any
+/// figures.
+pub async fn run(code: &str, timeout: Duration, sandbox: &Sandbox) -> Result<Vec<Value>, String> {
+    let figure_dir = ScratchDir::create("mcp-python-").map_err(|e| e.to_string())?;
+    execute(code, timeout, figure_dir.path(), sandbox).await
+}
+
+async fn execute(
+    code: &str,
+    timeout: Duration,
+    figure_dir: &Path,
+    sandbox: &Sandbox,
+) -> Result<Vec<Value>, String> {
+    sandbox.grant(figure_dir).map_err(|e| e.to_string())?;
+    let mut command = sandbox.command(interpreter(), &[figure_dir]);
+    // The figure directory doubles as matplotlib's config directory, since
+    // `HOME` is not passed through.
+    command
+        .arg("-c")
+        .arg(RUNNER)
+        .env("MCP_FIGURE_DIR", figure_dir)
+        .env("MPLCONFIGDIR", figure_dir)
+        .env("MPLBACKEND", "Agg");
+
+    let output = script::run_with_input(command, code, timeout).await?;
+    let mut content = script::output_content(&output);
+
+    let figures: Vec<String> = std::fs::read_to_string(figure_dir.join("figures.json"))
+        .ok()
+        .and_then(|s| serde_json::from_str(&s).ok())
+        .unwrap_or_default();
+    for data in figures {
+        content.push(serde_json::json!({"type": "image", "data": data, "mimeType": "image/png"}));
+    }
+
+    if content.is_empty() {
+        content.push(serde_json::json!({"type": "text", "text": ""}));
+    }
+    Ok(content)
+}
+
+/// `python_run`: one-shot scripts, or code run in a named REPL session.
+pub struct PythonRunTool {
+    pub repl: Arc<ReplManager>,
+    /// One-shot scripts run under it; sessions under the manager's own.
+    pub sandbox: Sandbox,
+}
+
+pub fn definition() -> Tool {
+    Tool {
+        name: "python_run".to_string(),
+        description: "Execute a Python script and return its stdout, stderr and matplotlib figures"
+            .to_string(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "code": {
+                    "type": "string",
+                    "description": "Python source code to execute"
+                },
+                "timeout_secs": {
+                    "type": "integer",
+                    "description": "Maximum execution time in seconds (default 30)"
+                },
+                "session": {
+                    "type": "string",
+                    "description": "Name of a persistent session; variables survive across calls"
+                }
+            },
+            "required": ["code"]
+        }),
+        output_schema: None,
+        annotations: Some(ToolAnnotations::mutating(true)),
+        meta: ToolMeta {
+            examples: vec![
+                ToolExample::new(
+                    "Print a computed value",
+                    serde_json::json!({"code": "print(sum(range(10)))"}),
+                ),
+                ToolExample::new(
+                    "Keep a variable for later calls",
+                    serde_json::json!({"code": "rows = [1, 2, 3]", "session": "analysis"}),
+                ),
+            ],
+            ..Default::default()
+        },
+    }
+}
+
+#[async_trait]
+impl ToolHandler for PythonRunTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        self.call_with_context(args, &ToolContext::default()).await
+    }
+
+    async fn call_with_context(
+        &self,
+        args: Value,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        self.call_shared(args.into(), context).await
+    }
+
+    /// Sessions are the calling client session's own; see [`ReplManager`].
+    /// The code is only read, so a long script is never copied.
+    async fn call_shared(
+        &self,
+        args: Arguments,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let code = required_str(&args, "code")?;
+        let timeout_secs = args
+            .get("timeout_secs")
+            .and_then(|t| t.as_u64())
+            .unwrap_or(DEFAULT_TIMEOUT_SECS);
+        let timeout = Duration::from_secs(timeout_secs);
+
+        let outcome = match args.get("session").and_then(|s| s.as_str()) {
+            Some(name) => {
+                self.repl
+                    .execute(
+                        context.session_id(),
+                        name,
+                        Language::Python,
+                        code,
+                        &Value::Null,
+                        timeout,
+                    )
+                    .await
+            }
+            None => run(code, timeout, &self.sandbox).await,
+        };
+        outcome.map(ToolResult::new).map_err(ToolError::Execution)
+    }
+}
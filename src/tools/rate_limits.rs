@@ -0,0 +1,231 @@
+//! Caps on how often each session may call a tool, so a model stuck in a
+//! loop cannot hammer an expensive tool indefinitely. A limit such as
+//! `10/min` is a token bucket per session and tool: it holds ten calls,
+//! refilled at ten a minute, so bursts are allowed as long as the rate
+//! over the minute stays within it. A call finding the bucket empty is
+//! refused at once with a `rate limited` error that says when to retry.
+//!
+//! Each bucket is kept as the single instant at which it would next be
+//! full (the generic cell rate algorithm), so idle sessions cost nothing
+//! to track and full buckets are simply forgotten.
+
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use crate::protocol::McpResponse;
+
+/// JSON-RPC error code of a call over its rate limit.
+pub const RATE_LIMITED: i32 = -32004;
+
+/// Buckets tracked before full ones are swept out.
+const SWEEP_AT: usize = 1024;
+
+/// `calls` per `per`, with bursts of up to `calls`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimit {
+    pub calls: u32,
+    pub per: Duration,
+}
+
+impl RateLimit {
+    /// `<calls>/<unit>`, such as `10/min`, where the unit is `s`, `min` or
+    /// `h`, or a number of seconds such as `10/30s`.
+    pub fn parse(limit: &str) -> Option<Self> {
+        let (calls, per) = limit.trim().split_once('/')?;
+        let calls: u32 = calls.trim().parse().ok().filter(|&n| n > 0)?;
+        let per = match per.trim() {
+            "s" | "sec" | "second" => Duration::from_secs(1),
+            "m" | "min" | "minute" => Duration::from_secs(60),
+            "h" | "hour" => Duration::from_secs(3600),
+            secs => Duration::from_secs(secs.strip_suffix('s')?.parse().ok().filter(|&n| n > 0)?),
+        };
+        Some(Self { calls, per })
+    }
+
+    /// Time for one call's token to refill.
+    fn interval(&self) -> Duration {
+        self.per / self.calls
+    }
+}
+
+/// Why a call was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limited {
+    pub limit: RateLimit,
+    pub retry_after: Duration,
+}
+
+impl Limited {
+    /// The error answering the refused call to `tool`.
+    pub fn response(&self, id: Option<Value>, tool: &str) -> McpResponse {
+        // Rounded up, so a client waiting as told is never refused again.
+        let retry_after = self.retry_after.as_millis().div_ceil(1000).max(1) as u64;
+        McpResponse::error(
+            id,
+            RATE_LIMITED,
+            format!(
+                "Rate limit of {} calls per {}s for '{}' exceeded; retry after {}s",
+                self.limit.calls,
+                self.limit.per.as_secs(),
+                tool,
+                retry_after
+            ),
+        )
+        .with_data(json!({
+            "tool": tool,
+            "limit": self.limit.calls,
+            "periodSecs": self.limit.per.as_secs(),
+            "retryAfterSecs": retry_after,
+        }))
+    }
+}
+
+#[derive(Default)]
+pub struct ToolRateLimits {
+    /// For tools without a limit of their own; `None` for no limit.
+    default: RwLock<Option<RateLimit>>,
+    /// By tool name; `None` exempts the tool from the default.
+    overrides: RwLock<HashMap<String, Option<RateLimit>>>,
+    /// When each bucket is next full, by session and tool.
+    buckets: Mutex<HashMap<(String, String), Instant>>,
+    limited: AtomicU64,
+}
+
+impl ToolRateLimits {
+    /// Limits from `MCP_TOOL_RATE_LIMITS`, such as
+    /// `python_run=10/min,http_fetch=5/s`, where `*` names every other tool
+    /// and `0` exempts one. Malformed entries are reported and ignored.
+    pub fn from_env() -> Self {
+        let limits = Self::default();
+        for entry in std::env::var("MCP_TOOL_RATE_LIMITS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+        {
+            match entry
+                .split_once('=')
+                .and_then(|(tool, limit)| Some((tool.trim(), parse_limit(limit)?)))
+            {
+                Some(("*", limit)) => limits.set_default(limit),
+                Some((tool, limit)) => limits.set(tool, limit),
+                None => tracing::warn!("Invalid MCP_TOOL_RATE_LIMITS entry '{}'", entry),
+            }
+        }
+        limits
+    }
+
+    /// The limit on calls to `tool`.
+    pub fn get(&self, tool: &str) -> Option<RateLimit> {
+        match self.overrides.read().unwrap().get(tool) {
+            Some(limit) => *limit,
+            None => *self.default.read().unwrap(),
+        }
+    }
+
+    pub fn set_default(&self, limit: Option<RateLimit>) {
+        *self.default.write().unwrap() = limit;
+    }
+
+    /// Overrides the default for `tool`; `None` exempts it.
+    pub fn set(&self, tool: &str, limit: Option<RateLimit>) {
+        self.overrides
+            .write()
+            .unwrap()
+            .insert(tool.to_string(), limit);
+    }
+
+    /// Returns `tool` to the default.
+    pub fn clear(&self, tool: &str) {
+        self.overrides.write().unwrap().remove(tool);
+    }
+
+    /// Takes a token for a call to `tool` in `session`, or says how long
+    /// until one is free.
+    pub fn check(&self, session: &str, tool: &str) -> Result<(), Limited> {
+        let Some(limit) = self.get(tool) else {
+            return Ok(());
+        };
+        let now = Instant::now();
+        let interval = limit.interval();
+        // How far ahead of now the bucket may be full: all but one token
+        // taken.
+        let tolerance = limit.per.saturating_sub(interval);
+        let mut buckets = self.buckets.lock().unwrap();
+        if buckets.len() >= SWEEP_AT {
+            buckets.retain(|_, full_at| *full_at > now);
+        }
+        let full_at = buckets
+            .get(&(session.to_string(), tool.to_string()))
+            .map_or(now, |&full_at| full_at.max(now));
+        let ahead = full_at - now;
+        if ahead > tolerance {
+            self.limited.fetch_add(1, Ordering::Relaxed);
+            return Err(Limited {
+                limit,
+                retry_after: ahead - tolerance,
+            });
+        }
+        buckets.insert((session.to_string(), tool.to_string()), full_at + interval);
+        Ok(())
+    }
+
+    /// Calls refused so far.
+    pub fn limited(&self) -> u64 {
+        self.limited.load(Ordering::Relaxed)
+    }
+}
+
+/// A limit, or `0` for none.
+fn parse_limit(limit: &str) -> Option<Option<RateLimit>> {
+    match limit.trim() {
+        "0" => Some(None),
+        limit => RateLimit::parse(limit).map(Some),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets_allow_bursts_then_refuse() {
+        let limits = ToolRateLimits::default();
+        limits.set_default(RateLimit::parse("3/min"));
+        limits.set("echo", None);
+        for _ in 0..3 {
+            assert!(limits.check("s1", "python_run").is_ok());
+        }
+        let limited = limits.check("s1", "python_run").unwrap_err();
+        assert!(limited.retry_after > Duration::from_secs(19));
+        assert!(limited.retry_after <= Duration::from_secs(20));
+        // Each session and tool has a bucket of its own.
+        assert!(limits.check("s2", "python_run").is_ok());
+        assert!(limits.check("s1", "http_fetch").is_ok());
+        for _ in 0..10 {
+            assert!(limits.check("s1", "echo").is_ok());
+        }
+        assert_eq!(limits.limited(), 1);
+
+        let error = limited
+            .response(Some(json!(7)), "python_run")
+            .error
+            .unwrap();
+        assert_eq!(error.code, RATE_LIMITED);
+        assert_eq!(error.data.unwrap()["retryAfterSecs"], 20);
+
+        assert_eq!(
+            RateLimit::parse("10/30s"),
+            Some(RateLimit {
+                calls: 10,
+                per: Duration::from_secs(30)
+            })
+        );
+        assert_eq!(RateLimit::parse("0/min"), None);
+        assert_eq!(RateLimit::parse("5/fortnight"), None);
+        assert_eq!(parse_limit("0"), Some(None));
+    }
+}
@@ -0,0 +1,198 @@
+//! Opt-in repair of the argument mistakes models make most often, applied
+//! before a tool sees its arguments: a whole argument object sent as a JSON
+//! string, numbers, booleans, arrays or objects sent as strings, Python-style
+//! single-quoted JSON, and code wrapped in a Markdown fence.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use super::hints::matches_type;
+
+/// One change made to the arguments, reported in the result's
+/// `_meta.repairs`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Repair {
+    /// The property changed; `None` for the arguments as a whole.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub property: Option<String>,
+    /// `parsed_json_string`, `fixed_single_quotes` or `stripped_code_fence`.
+    pub repair: &'static str,
+}
+
+/// `text` without a surrounding Markdown code fence such as
+/// ```` ```python ````, or `None` when it is not one fenced block.
+fn strip_fence(text: &str) -> Option<&str> {
+    let inner = text.trim().strip_prefix("```")?.strip_suffix("```")?;
+    let (info, body) = inner.split_once('\n')?;
+    if info.contains("```") || body.contains("```") || info.trim().contains(' ') {
+        return None;
+    }
+    Some(body.strip_suffix('\n').unwrap_or(body))
+}
+
+/// Rewrites single-quoted strings as double-quoted ones, leaving quotes
+/// inside double-quoted strings alone.
+fn double_quote(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut quote: Option<char> = None;
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '\'') => {
+                quote = Some('\'');
+                out.push('"');
+            }
+            (None, '"') => {
+                quote = Some('"');
+                out.push('"');
+            }
+            (Some(q), '\\') => match chars.next() {
+                // `\'` needs no escape once the string is double-quoted.
+                Some('\'') if q == '\'' => out.push('\''),
+                Some(escaped) => {
+                    out.push('\\');
+                    out.push(escaped);
+                }
+                None => out.push('\\'),
+            },
+            (Some('\''), '\'') => {
+                quote = None;
+                out.push('"');
+            }
+            (Some('\''), '"') => out.push_str("\\\""),
+            (Some('"'), '"') => {
+                quote = None;
+                out.push('"');
+            }
+            (_, c) => out.push(c),
+        }
+    }
+    out
+}
+
+/// Parses `text` as JSON, retrying without a code fence and then with
+/// single quotes fixed. Returns the value and the repairs needed.
+fn parse_lenient(text: &str) -> Option<(Value, Vec<&'static str>)> {
+    let mut repairs = Vec::new();
+    let mut text = text.trim();
+    if let Some(inner) = strip_fence(text) {
+        text = inner.trim();
+        repairs.push("stripped_code_fence");
+    }
+    if let Ok(value) = serde_json::from_str(text) {
+        repairs.insert(0, "parsed_json_string");
+        return Some((value, repairs));
+    }
+    let value = serde_json::from_str(&double_quote(text)).ok()?;
+    repairs.insert(0, "parsed_json_string");
+    repairs.push("fixed_single_quotes");
+    Some((value, repairs))
+}
+
+fn record(repairs: &mut Vec<Repair>, property: Option<&str>, made: Vec<&'static str>) {
+    repairs.extend(made.into_iter().map(|repair| Repair {
+        property: property.map(str::to_string),
+        repair,
+    }));
+}
+
+/// Repairs `arguments` for a tool with `schema`. Only values the schema
+/// rejects are touched, except that a string property holding exactly one
+/// fenced code block loses the fence.
+pub fn repair(schema: &Value, mut arguments: Value) -> (Value, Vec<Repair>) {
+    let mut repairs = Vec::new();
+    if let Value::String(text) = &arguments {
+        if let Some((parsed @ Value::Object(_), made)) = parse_lenient(text) {
+            arguments = parsed;
+            record(&mut repairs, None, made);
+        }
+    }
+    let (Some(properties), Some(values)) = (
+        schema.get("properties").and_then(Value::as_object),
+        arguments.as_object_mut(),
+    ) else {
+        return (arguments, repairs);
+    };
+    for (name, value) in values.iter_mut() {
+        let Some(property) = properties.get(name) else {
+            continue;
+        };
+        let allows_string = matches_type(property, value);
+        let Value::String(text) = value else {
+            continue;
+        };
+        if !allows_string {
+            if let Some((parsed, made)) = parse_lenient(text) {
+                if matches_type(property, &parsed) {
+                    *value = parsed;
+                    record(&mut repairs, Some(name), made);
+                }
+            }
+        } else if let Some(inner) = strip_fence(text) {
+            *value = Value::String(inner.to_string());
+            record(&mut repairs, Some(name), vec!["stripped_code_fence"]);
+        }
+    }
+    (arguments, repairs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_repairs_common_mistakes() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "code": {"type": "string"},
+                "count": {"type": "integer"},
+                "options": {"type": "object"},
+                "note": {"type": "string"}
+            }
+        });
+
+        let (fixed, repairs) = repair(
+            &schema,
+            json!({
+                "code": "```python\nprint('hi')\n```",
+                "count": "3",
+                "options": "{'verbose': true, 'label': 'it\\'s \"x\"'}",
+                "note": "plain ``` text"
+            }),
+        );
+        assert_eq!(
+            fixed,
+            json!({
+                "code": "print('hi')",
+                "count": 3,
+                "options": {"verbose": true, "label": "it's \"x\""},
+                "note": "plain ``` text"
+            })
+        );
+        let made: Vec<(&str, &str)> = repairs
+            .iter()
+            .map(|r| (r.property.as_deref().unwrap(), r.repair))
+            .collect();
+        assert_eq!(
+            made,
+            vec![
+                ("code", "stripped_code_fence"),
+                ("count", "parsed_json_string"),
+                ("options", "parsed_json_string"),
+                ("options", "fixed_single_quotes"),
+            ]
+        );
+
+        let (fixed, repairs) = repair(&schema, json!("```json\n{\"count\": 1}\n```"));
+        assert_eq!(fixed, json!({"count": 1}));
+        assert_eq!(repairs.len(), 2);
+        assert!(repairs[0].property.is_none());
+
+        // Nothing to repair.
+        let (fixed, repairs) = repair(&schema, json!({"count": "many"}));
+        assert_eq!(fixed, json!({"count": "many"}));
+        assert!(repairs.is_empty());
+    }
+}
@@ -0,0 +1,88 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+
+use super::{required_str, ToolContext, ToolError, ToolHandler, ToolResult};
+use crate::protocol::{Tool, ToolAnnotations};
+use crate::repl::ReplManager;
+
+/// `repl_list`: describes the calling client session's live sessions.
+pub struct ReplListTool {
+    pub repl: Arc<ReplManager>,
+}
+
+/// `repl_reset`: discards one of the calling client session's sessions and
+/// its state.
+pub struct ReplResetTool {
+    pub repl: Arc<ReplManager>,
+}
+
+pub fn list_definition() -> Tool {
+    Tool {
+        name: "repl_list".to_string(),
+        description: "List the persistent python_run/js_run sessions".to_string(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {}
+        }),
+        output_schema: None,
+        annotations: Some(ToolAnnotations::read_only()),
+        meta: Default::default(),
+    }
+}
+
+pub fn reset_definition() -> Tool {
+    Tool {
+        name: "repl_reset".to_string(),
+        description: "Discard a persistent session and all of its state".to_string(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "session": {
+                    "type": "string",
+                    "description": "Name of the session to discard"
+                }
+            },
+            "required": ["session"]
+        }),
+        output_schema: None,
+        annotations: Some(ToolAnnotations::mutating(true)),
+        meta: Default::default(),
+    }
+}
+
+#[async_trait]
+impl ToolHandler for ReplListTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        self.call_with_context(args, &ToolContext::default()).await
+    }
+
+    async fn call_with_context(
+        &self,
+        _args: Value,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let sessions = self.repl.list(context.session_id()).await;
+        Ok(ToolResult::json(&Value::Array(sessions)))
+    }
+}
+
+#[async_trait]
+impl ToolHandler for ReplResetTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        self.call_with_context(args, &ToolContext::default()).await
+    }
+
+    async fn call_with_context(
+        &self,
+        args: Value,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let name = required_str(&args, "session")?;
+        if self.repl.reset(context.session_id(), name).await {
+            Ok(ToolResult::text(format!("Session '{}' reset", name)))
+        } else {
+            Err(ToolError::InvalidArguments("Session not found".to_string()))
+        }
+    }
+}
@@ -0,0 +1,522 @@
+//! Checks `tools/call` arguments against the tool's `input_schema` before
+//! the tool runs, so a handler only ever sees arguments its schema allows.
+//!
+//! Covers the JSON Schema keywords tool schemas use in practice: `type`,
+//! `enum`, `const`, `required`, `properties`, `additionalProperties`,
+//! `items`, `minItems`, `maxItems`, `uniqueItems`, `minLength`,
+//! `maxLength`, `pattern`, `minimum`, `maximum`, `exclusiveMinimum`,
+//! `exclusiveMaximum`, `multipleOf`, `allOf`, `anyOf`, `oneOf`, `not`,
+//! and `$ref` to a definition in the same schema. Other keywords, such as
+//! `format`, are not enforced.
+//!
+//! A tool with an `output_schema` has its results checked the same way
+//! before they are sent, by [`check_result`]. Each `pattern` is compiled
+//! once, when [`check_tool`] vets the tool at registration, and reused
+//! by every later call.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+
+use super::hints::matches_type;
+use super::{ToolError, ToolResult};
+use crate::protocol::Tool;
+use regex::Regex;
+
+/// The first place the arguments break their schema.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// Where in the arguments, such as `text`, `filters.since` or
+    /// `tags[2]`. Empty for the arguments as a whole.
+    pub path: String,
+    /// The keyword that failed, such as `type` or `required`.
+    pub keyword: &'static str,
+    /// The schema the value had to satisfy.
+    pub expected: Value,
+    /// The value sent, `null` when a required property is missing.
+    pub received: Value,
+    /// Names the property, as in `Property 'count' must be at least 1, got 0`.
+    pub message: String,
+}
+
+pub fn validate(schema: &Value, value: &Value) -> Result<(), Box<Violation>> {
+    Validator {
+        root: schema,
+        whole: "The arguments",
+    }
+    .check(schema, value, "")
+}
+
+/// Checks a tool's `structuredContent` against its `output_schema`, as
+/// [`validate`] checks arguments.
+pub fn validate_output(schema: &Value, value: &Value) -> Result<(), Box<Violation>> {
+    Validator {
+        root: schema,
+        whole: "The structured content",
+    }
+    .check(schema, value, "")
+}
+
+/// Whether `result` is one `tool` may send: a tool with an
+/// `output_schema` must return matching `structuredContent`. A mismatch is
+/// the tool's fault, so it is an `Execution` error.
+pub fn check_result(tool: &Tool, result: &ToolResult) -> Result<(), ToolError> {
+    let Some(schema) = &tool.output_schema else {
+        return Ok(());
+    };
+    let Some(structured) = &result.structured_content else {
+        return Err(ToolError::Execution(format!(
+            "Tool '{}' returned no structured content",
+            tool.name
+        )));
+    };
+    validate_output(schema, structured).map_err(|violation| {
+        ToolError::Execution(format!(
+            "Tool '{}' returned invalid structured content: {}",
+            tool.name, violation.message
+        ))
+    })
+}
+
+/// Every `pattern` compiled so far, by source.
+fn patterns() -> &'static Mutex<HashMap<String, Regex>> {
+    static PATTERNS: OnceLock<Mutex<HashMap<String, Regex>>> = OnceLock::new();
+    PATTERNS.get_or_init(Mutex::default)
+}
+
+fn compile(pattern: &str) -> Result<Regex, regex::Error> {
+    let mut patterns = patterns().lock().unwrap();
+    if let Some(regex) = patterns.get(pattern) {
+        return Ok(regex.clone());
+    }
+    let regex = Regex::new(pattern)?;
+    patterns.insert(pattern.to_string(), regex.clone());
+    Ok(regex)
+}
+
+/// Compiles every `pattern` in `schema`, failing on the first that is not
+/// a valid regular expression.
+pub fn check_patterns(schema: &Value) -> Result<(), String> {
+    match schema {
+        Value::Object(keywords) => {
+            if let Some(pattern) = keywords.get("pattern").and_then(Value::as_str) {
+                compile(pattern).map_err(|e| format!("invalid pattern '{}': {}", pattern, e))?;
+            }
+            keywords
+                .iter()
+                .try_for_each(|(keyword, value)| match (keyword.as_str(), value) {
+                    // Schemas by name, where any name is allowed.
+                    (
+                        "properties" | "patternProperties" | "$defs" | "definitions",
+                        Value::Object(schemas),
+                    ) => schemas.values().try_for_each(check_patterns),
+                    // Values, not schemas.
+                    ("const" | "enum" | "default" | "examples", _) => Ok(()),
+                    _ => check_patterns(value),
+                })
+        }
+        Value::Array(schemas) => schemas.iter().try_for_each(check_patterns),
+        _ => Ok(()),
+    }
+}
+
+/// Whether `tool` can be registered: each `pattern` in its schemas must
+/// compile, since one that doesn't could never be enforced.
+pub fn check_tool(tool: &Tool) -> Result<(), String> {
+    check_patterns(&tool.input_schema)
+        .and_then(|()| tool.output_schema.as_ref().map_or(Ok(()), check_patterns))
+        .map_err(|e| format!("Tool '{}' has an {}", tool.name, e))
+}
+
+struct Validator<'a> {
+    root: &'a Value,
+    /// How messages name the value as a whole.
+    whole: &'static str,
+}
+
+impl Validator<'_> {
+    fn subject(&self, path: &str) -> String {
+        if path.is_empty() {
+            self.whole.to_string()
+        } else {
+            format!("Property '{}'", path)
+        }
+    }
+
+    fn check(&self, schema: &Value, value: &Value, path: &str) -> Result<(), Box<Violation>> {
+        let fail = |keyword, problem: String| {
+            Err(Box::new(Violation {
+                path: path.to_string(),
+                keyword,
+                expected: schema.clone(),
+                received: value.clone(),
+                message: format!("{} {}", self.subject(path), problem),
+            }))
+        };
+        let schema = match schema {
+            Value::Bool(true) => return Ok(()),
+            Value::Bool(false) => return fail("false", "is not allowed".to_string()),
+            schema => schema,
+        };
+        if let Some(target) = schema.get("$ref").and_then(Value::as_str) {
+            // Unresolvable references are left unchecked rather than
+            // rejecting every call.
+            if let Some(target) = target.strip_prefix('#').and_then(|p| self.root.pointer(p)) {
+                self.check(target, value, path)?;
+            }
+        }
+
+        if !matches_type(schema, value) {
+            let expected = match &schema["type"] {
+                Value::Array(types) => types
+                    .iter()
+                    .map(|t| t.as_str().unwrap_or_default())
+                    .collect::<Vec<_>>()
+                    .join(" or "),
+                kind => kind.as_str().unwrap_or_default().to_string(),
+            };
+            return fail("type", format!("must be {}, got {}", expected, value));
+        }
+        if let Some(allowed) = schema.get("enum").and_then(Value::as_array) {
+            if !allowed.contains(value) {
+                let allowed: Vec<String> = allowed.iter().map(Value::to_string).collect();
+                return fail(
+                    "enum",
+                    format!("must be one of {}, got {}", allowed.join(", "), value),
+                );
+            }
+        }
+        if let Some(expected) = schema.get("const") {
+            if expected != value {
+                return fail("const", format!("must be {}, got {}", expected, value));
+            }
+        }
+
+        match value {
+            Value::Object(object) => {
+                let properties = schema.get("properties").and_then(Value::as_object);
+                let required = schema.get("required").and_then(Value::as_array);
+                for name in required.into_iter().flatten().filter_map(Value::as_str) {
+                    if !object.contains_key(name) {
+                        let property = join(path, name);
+                        return Err(Box::new(Violation {
+                            message: format!("{} is required", self.subject(&property)),
+                            path: property,
+                            keyword: "required",
+                            expected: properties
+                                .and_then(|p| p.get(name))
+                                .cloned()
+                                .unwrap_or(Value::Null),
+                            received: Value::Null,
+                        }));
+                    }
+                }
+                let additional = schema.get("additionalProperties");
+                for (name, item) in object {
+                    let property = join(path, name);
+                    match (properties.and_then(|p| p.get(name)), additional) {
+                        (Some(property_schema), _) => {
+                            self.check(property_schema, item, &property)?
+                        }
+                        (None, Some(Value::Bool(false))) => {
+                            return Err(Box::new(Violation {
+                                message: format!("{} is not allowed", self.subject(&property)),
+                                path: property,
+                                keyword: "additionalProperties",
+                                expected: Value::Bool(false),
+                                received: item.clone(),
+                            }));
+                        }
+                        (None, Some(additional)) => self.check(additional, item, &property)?,
+                        (None, None) => {}
+                    }
+                }
+            }
+            Value::Array(items) => {
+                let count = items.len() as u64;
+                if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                    if count < min {
+                        let problem = format!("must have at least {} items, got {}", min, count);
+                        return fail("minItems", problem);
+                    }
+                }
+                if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                    if count > max {
+                        let problem = format!("must have at most {} items, got {}", max, count);
+                        return fail("maxItems", problem);
+                    }
+                }
+                if schema.get("uniqueItems") == Some(&Value::Bool(true)) {
+                    if let Some(repeated) = items
+                        .iter()
+                        .enumerate()
+                        .find(|(i, item)| items[..*i].contains(item))
+                        .map(|(_, item)| item)
+                    {
+                        return fail("uniqueItems", format!("must not repeat {}", repeated));
+                    }
+                }
+                if let Some(item_schema) = schema.get("items") {
+                    for (i, item) in items.iter().enumerate() {
+                        self.check(item_schema, item, &format!("{}[{}]", path, i))?;
+                    }
+                }
+            }
+            Value::String(text) => {
+                let length = text.chars().count() as u64;
+                if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                    if length < min {
+                        let problem =
+                            format!("must be at least {} characters long, got {}", min, length);
+                        return fail("minLength", problem);
+                    }
+                }
+                if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                    if length > max {
+                        let problem =
+                            format!("must be at most {} characters long, got {}", max, length);
+                        return fail("maxLength", problem);
+                    }
+                }
+                if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+                    match compile(pattern) {
+                        Ok(regex) if regex.is_match(text) => {}
+                        Ok(_) => {
+                            let problem = format!("must match {}, got {}", pattern, value);
+                            return fail("pattern", problem);
+                        }
+                        // Only schemas never vetted by `check_patterns` get here.
+                        Err(_) => {
+                            let problem =
+                                format!("can't be checked: {} is not a valid pattern", pattern);
+                            return fail("pattern", problem);
+                        }
+                    }
+                }
+            }
+            Value::Number(number) => {
+                let number = number.as_f64().unwrap_or_default();
+                let bound = |keyword: &str| schema.get(keyword).and_then(Value::as_f64);
+                let checks = [
+                    ("minimum", "at least"),
+                    ("maximum", "at most"),
+                    ("exclusiveMinimum", "greater than"),
+                    ("exclusiveMaximum", "less than"),
+                ];
+                for (keyword, relation) in checks {
+                    let Some(limit) = bound(keyword) else {
+                        continue;
+                    };
+                    let holds = match keyword {
+                        "minimum" => number >= limit,
+                        "maximum" => number <= limit,
+                        "exclusiveMinimum" => number > limit,
+                        _ => number < limit,
+                    };
+                    if !holds {
+                        let problem = format!("must be {} {}, got {}", relation, limit, value);
+                        return fail(keyword, problem);
+                    }
+                }
+                if let Some(step) = bound("multipleOf").filter(|step| *step > 0.0) {
+                    let quotient = number / step;
+                    if (quotient - quotient.round()).abs() > 1e-9 {
+                        let problem = format!("must be a multiple of {}, got {}", step, value);
+                        return fail("multipleOf", problem);
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        for branch in schema
+            .get("allOf")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            self.check(branch, value, path)?;
+        }
+        if let Some(branches) = schema.get("anyOf").and_then(Value::as_array) {
+            let mut failures = branches.iter().map(|b| self.check(b, value, path));
+            if !failures.any(|result| result.is_ok()) {
+                // With one branch, its own reason says more.
+                if let [only] = branches.as_slice() {
+                    return self.check(only, value, path);
+                }
+                let problem = format!("must match one of the allowed schemas, got {}", value);
+                return fail("anyOf", problem);
+            }
+        }
+        if let Some(branches) = schema.get("oneOf").and_then(Value::as_array) {
+            let matched = branches
+                .iter()
+                .filter(|b| self.check(b, value, path).is_ok())
+                .count();
+            if matched != 1 {
+                let problem = format!(
+                    "must match exactly one of the allowed schemas, matched {}",
+                    matched
+                );
+                return fail("oneOf", problem);
+            }
+        }
+        if let Some(excluded) = schema.get("not") {
+            if self.check(excluded, value, path).is_ok() {
+                return fail("not", format!("must not match {}, got {}", excluded, value));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validates_nested_arguments() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "query": {"type": "string", "minLength": 1, "pattern": "^\\w"},
+                "limit": {"type": "integer", "minimum": 1, "maximum": 100},
+                "mode": {"enum": ["fast", "slow"]},
+                "tags": {"type": "array", "items": {"type": "string"}, "uniqueItems": true},
+                "filters": {"$ref": "#/$defs/filters"}
+            },
+            "required": ["query"],
+            "additionalProperties": false,
+            "$defs": {
+                "filters": {
+                    "type": "object",
+                    "properties": {"since": {"type": "string"}},
+                    "required": ["since"]
+                }
+            }
+        });
+        let error = |arguments: Value| validate(&schema, &arguments).unwrap_err();
+
+        assert!(validate(&schema, &json!({"query": "rust", "limit": 10})).is_ok());
+        let violation = error(json!({"limit": 10}));
+        assert_eq!(
+            (violation.path.as_str(), violation.keyword),
+            ("query", "required")
+        );
+        assert_eq!(violation.message, "Property 'query' is required");
+        assert_eq!(violation.expected["type"], "string");
+
+        let violation = error(json!({"query": "rust", "limit": 0}));
+        assert_eq!(
+            violation.message,
+            "Property 'limit' must be at least 1, got 0"
+        );
+        assert_eq!(violation.received, 0);
+        assert_eq!(
+            error(json!({"query": "rust", "limit": 2.5})).message,
+            "Property 'limit' must be integer, got 2.5"
+        );
+        assert_eq!(error(json!({"query": ""})).keyword, "minLength");
+        assert_eq!(error(json!({"query": "-x"})).keyword, "pattern");
+        assert_eq!(
+            error(json!({"query": "rust", "mode": "eager"})).message,
+            "Property 'mode' must be one of \"fast\", \"slow\", got \"eager\""
+        );
+        assert_eq!(
+            error(json!({"query": "rust", "tags": ["a", 1]})).path,
+            "tags[1]"
+        );
+        assert_eq!(
+            error(json!({"query": "rust", "tags": ["a", "a"]})).keyword,
+            "uniqueItems"
+        );
+        assert_eq!(
+            error(json!({"query": "rust", "filters": {}})).message,
+            "Property 'filters.since' is required"
+        );
+        let violation = error(json!({"query": "rust", "verbose": true}));
+        assert_eq!(violation.keyword, "additionalProperties");
+        assert_eq!(violation.message, "Property 'verbose' is not allowed");
+        assert_eq!(
+            error(json!("rust")).message,
+            "The arguments must be object, got \"rust\""
+        );
+
+        let either = json!({"anyOf": [{"type": "string"}, {"type": "integer", "minimum": 0}]});
+        assert!(validate(&either, &json!(3)).is_ok());
+        assert_eq!(validate(&either, &json!(-1)).unwrap_err().keyword, "anyOf");
+        let one = json!({"oneOf": [{"type": "number"}, {"type": "integer"}]});
+        assert!(validate(&one, &json!(1.5)).is_ok());
+        assert_eq!(validate(&one, &json!(1)).unwrap_err().keyword, "oneOf");
+        assert!(validate(&json!({}), &json!(null)).is_ok());
+    }
+
+    #[test]
+    fn test_tools_with_invalid_patterns_are_refused() {
+        let tool = |input_schema| Tool {
+            name: "search".to_string(),
+            description: String::new(),
+            input_schema,
+            output_schema: None,
+            annotations: None,
+            meta: Default::default(),
+        };
+        let valid = json!({
+            "type": "object",
+            "properties": {
+                "pattern": {"type": "string", "pattern": "^[a-z]+$"},
+                "enum": {"const": {"pattern": "("}}
+            }
+        });
+        assert!(check_tool(&tool(valid)).is_ok());
+        let nested = json!({"type": "array", "items": {"type": "string", "pattern": "(unclosed"}});
+        let message = check_tool(&tool(nested)).unwrap_err();
+        assert!(message.starts_with("Tool 'search' has an invalid pattern '(unclosed'"));
+        assert_eq!(
+            validate(&json!({"pattern": "("}), &json!("x"))
+                .unwrap_err()
+                .keyword,
+            "pattern"
+        );
+    }
+
+    #[test]
+    fn test_checks_structured_results() {
+        let tool = |output_schema| Tool {
+            name: "stats".to_string(),
+            description: String::new(),
+            input_schema: json!({"type": "object"}),
+            output_schema,
+            annotations: None,
+            meta: Default::default(),
+        };
+        let schema = json!({"type": "object", "required": ["count"]});
+        let text = ToolResult::text("3");
+        assert!(check_result(&tool(None), &text).is_ok());
+
+        let error = check_result(&tool(Some(schema.clone())), &text).unwrap_err();
+        assert_eq!(
+            error,
+            ToolError::Execution("Tool 'stats' returned no structured content".to_string())
+        );
+        let error = check_result(
+            &tool(Some(schema.clone())),
+            &ToolResult::structured(json!([])),
+        )
+        .unwrap_err();
+        assert!(error
+            .message()
+            .ends_with("The structured content must be object, got []"));
+        let valid = ToolResult::structured(json!({"count": 3}));
+        assert!(check_result(&tool(Some(schema)), &valid).is_ok());
+    }
+}
@@ -0,0 +1,119 @@
+//! How long a tool call may run before it is abandoned, so one hung handler
+//! cannot hold a request, and its client, forever. Every tool gets the
+//! default unless it has an override of its own.
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+use std::time::Duration;
+
+/// Used when `MCP_TOOL_TIMEOUT_SECS` is unset.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(300);
+
+pub struct ToolTimeouts {
+    default: RwLock<Option<Duration>>,
+    /// By tool name; `None` lets the tool run without a limit.
+    overrides: RwLock<HashMap<String, Option<Duration>>>,
+}
+
+impl Default for ToolTimeouts {
+    fn default() -> Self {
+        Self::new(Some(DEFAULT_TIMEOUT))
+    }
+}
+
+impl ToolTimeouts {
+    /// `default` for every tool; `None` for no limit.
+    pub fn new(default: Option<Duration>) -> Self {
+        Self {
+            default: RwLock::new(default),
+            overrides: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The default from `MCP_TOOL_TIMEOUT_SECS`, where `0` means no limit,
+    /// and overrides from `MCP_TOOL_TIMEOUTS`, such as
+    /// `python_run=600,http_fetch=15`. Malformed values are reported and
+    /// ignored.
+    pub fn from_env() -> Self {
+        let default = match std::env::var("MCP_TOOL_TIMEOUT_SECS") {
+            Ok(value) => match parse_secs(&value) {
+                Some(timeout) => timeout,
+                None => {
+                    tracing::warn!("Invalid MCP_TOOL_TIMEOUT_SECS '{}'", value);
+                    Some(DEFAULT_TIMEOUT)
+                }
+            },
+            Err(_) => Some(DEFAULT_TIMEOUT),
+        };
+        let timeouts = Self::new(default);
+        for entry in std::env::var("MCP_TOOL_TIMEOUTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|e| !e.is_empty())
+        {
+            match entry
+                .split_once('=')
+                .and_then(|(tool, secs)| Some((tool.trim(), parse_secs(secs)?)))
+            {
+                Some((tool, timeout)) => timeouts.set(tool, timeout),
+                None => tracing::warn!("Invalid MCP_TOOL_TIMEOUTS entry '{}'", entry),
+            }
+        }
+        timeouts
+    }
+
+    /// The limit for calls to `tool`.
+    pub fn get(&self, tool: &str) -> Option<Duration> {
+        match self.overrides.read().unwrap().get(tool) {
+            Some(timeout) => *timeout,
+            None => *self.default.read().unwrap(),
+        }
+    }
+
+    pub fn set_default(&self, timeout: Option<Duration>) {
+        *self.default.write().unwrap() = timeout;
+    }
+
+    /// Overrides the default for `tool`; `None` lets it run without a limit.
+    pub fn set(&self, tool: &str, timeout: Option<Duration>) {
+        self.overrides
+            .write()
+            .unwrap()
+            .insert(tool.to_string(), timeout);
+    }
+
+    /// Returns `tool` to the default.
+    pub fn clear(&self, tool: &str) {
+        self.overrides.write().unwrap().remove(tool);
+    }
+}
+
+/// Whole seconds, with `0` for no limit.
+fn parse_secs(value: &str) -> Option<Option<Duration>> {
+    let secs: u64 = value.trim().parse().ok()?;
+    Some((secs > 0).then(|| Duration::from_secs(secs)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overrides_take_precedence() {
+        let timeouts = ToolTimeouts::default();
+        assert_eq!(timeouts.get("echo"), Some(DEFAULT_TIMEOUT));
+
+        timeouts.set("python_run", Some(Duration::from_secs(600)));
+        timeouts.set("watch", None);
+        timeouts.set_default(Some(Duration::from_secs(5)));
+        assert_eq!(timeouts.get("python_run"), Some(Duration::from_secs(600)));
+        assert_eq!(timeouts.get("watch"), None);
+        assert_eq!(timeouts.get("echo"), Some(Duration::from_secs(5)));
+
+        timeouts.clear("watch");
+        assert_eq!(timeouts.get("watch"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_secs("0"), Some(None));
+        assert_eq!(parse_secs("x"), None);
+    }
+}
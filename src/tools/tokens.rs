@@ -0,0 +1,78 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::args::Arguments;
+use super::{ToolContext, ToolError, ToolHandler, ToolResult};
+use crate::protocol::{Tool, ToolAnnotations, ToolExample, ToolMeta};
+use crate::tokens;
+
+/// `count_tokens`: token count of a text in a tiktoken encoding.
+pub struct CountTokensTool;
+
+pub fn definition() -> Tool {
+    Tool {
+        name: "count_tokens".to_string(),
+        description: "Count how many tokens a text occupies in a tiktoken encoding".to_string(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "text": {
+                    "type": "string",
+                    "description": "Text to measure"
+                },
+                "encoding": {
+                    "type": "string",
+                    "enum": tokens::ENCODINGS,
+                    "description": "Encoding name (default cl100k_base)"
+                }
+            },
+            "required": ["text"]
+        }),
+        output_schema: None,
+        annotations: Some(ToolAnnotations::read_only()),
+        meta: ToolMeta {
+            examples: vec![ToolExample::new(
+                "Count tokens for a GPT-4o prompt",
+                serde_json::json!({"text": "How many tokens is this?", "encoding": "o200k_base"}),
+            )],
+            ..Default::default()
+        },
+    }
+}
+
+#[async_trait]
+impl ToolHandler for CountTokensTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        count_tokens(&args)
+    }
+
+    /// The text is only read, so a long one is never copied.
+    async fn call_shared(
+        &self,
+        args: Arguments,
+        _context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        count_tokens(&args)
+    }
+}
+
+fn count_tokens(args: &Value) -> Result<ToolResult, ToolError> {
+    let text = args
+        .get("text")
+        .and_then(|t| t.as_str())
+        .unwrap_or_default();
+    let encoding = args
+        .get("encoding")
+        .and_then(|e| e.as_str())
+        .unwrap_or(tokens::DEFAULT_ENCODING);
+
+    let count = tokens::count(text, encoding).map_err(ToolError::InvalidArguments)?;
+    Ok(ToolResult::text(
+        serde_json::json!({
+            "tokens": count,
+            "characters": text.chars().count(),
+            "encoding": encoding
+        })
+        .to_string(),
+    ))
+}
@@ -0,0 +1,140 @@
+//! Versioned tool names. `search@v1` and `search@v2` can be registered side
+//! by side, so a breaking schema change ships as a new version while
+//! prompts written for the old one keep working. A call to plain `search`
+//! goes to the default version: the one pinned with
+//! [`McpServer::set_default_version`](crate::McpServer::set_default_version),
+//! or else the latest that is not deprecated.
+
+use std::cmp::Ordering;
+use std::time::SystemTime;
+
+use crate::protocol::Tool;
+
+/// Between a tool's name and its version.
+pub const SEPARATOR: char = '@';
+
+/// `search@v2` is `("search", Some("v2"))`.
+pub fn split(name: &str) -> (&str, Option<&str>) {
+    match name.split_once(SEPARATOR) {
+        Some((base, version)) => (base, Some(version)),
+        None => (name, None),
+    }
+}
+
+/// Orders `v2` before `v10` and `1.2` before `1.10`, comparing the numbers
+/// in a version, and falls back to the text for versions without any.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    let numbers = |version: &str| -> Option<Vec<u64>> {
+        version
+            .trim_start_matches(['v', 'V'])
+            .split('.')
+            .map(|part| part.parse().ok())
+            .collect()
+    };
+    match (numbers(a), numbers(b)) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+/// Today as `YYYY-MM-DD` in UTC, the format of a deprecation's `sunset`.
+pub fn today() -> String {
+    crate::security::rfc3339(SystemTime::now())[..10].to_string()
+}
+
+/// The version a call to unversioned `base` goes to, as its full name, or
+/// `None` when `base` has no versions being served. A `pinned` version
+/// wins while it is served; otherwise the latest that is not deprecated,
+/// or the latest of all when every one is.
+pub fn default_version<'a>(
+    base: &str,
+    tools: impl IntoIterator<Item = &'a Tool>,
+    pinned: Option<&str>,
+    today: &str,
+) -> Option<String> {
+    let mut served: Vec<(&str, &Tool)> = tools
+        .into_iter()
+        .filter(|tool| !tool.is_retired(today))
+        .filter_map(|tool| match split(&tool.name) {
+            (name, Some(version)) if name == base => Some((version, tool)),
+            _ => None,
+        })
+        .collect();
+    if let Some((_, tool)) = served.iter().find(|(version, _)| Some(*version) == pinned) {
+        return Some(tool.name.clone());
+    }
+    served.sort_by(|a, b| compare(a.0, b.0));
+    let latest = served.iter().rev();
+    latest
+        .clone()
+        .find(|(_, tool)| tool.meta.deprecation.is_none())
+        .or_else(|| latest.clone().next())
+        .map(|(_, tool)| tool.name.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::Deprecation;
+    use std::collections::HashMap;
+
+    fn tool(name: &str) -> Tool {
+        Tool {
+            name: name.to_string(),
+            description: String::new(),
+            input_schema: serde_json::json!({"type": "object"}),
+            output_schema: None,
+            annotations: None,
+            meta: Default::default(),
+        }
+    }
+
+    #[test]
+    fn test_resolves_default_versions() {
+        assert_eq!(split("search@v2"), ("search", Some("v2")));
+        assert_eq!(split("search"), ("search", None));
+        assert_eq!(compare("v2", "v10"), Ordering::Less);
+        assert_eq!(compare("1.10", "1.2"), Ordering::Greater);
+        assert_eq!(compare("beta", "alpha"), Ordering::Greater);
+
+        let mut tools: HashMap<String, Tool> = ["search@v1", "search@v2", "search@v10", "other"]
+            .into_iter()
+            .map(|name| (name.to_string(), tool(name)))
+            .collect();
+        let date = "2026-06-01";
+        assert_eq!(
+            default_version("search", tools.values(), None, date).as_deref(),
+            Some("search@v10")
+        );
+        assert_eq!(
+            default_version("search", tools.values(), Some("v1"), date).as_deref(),
+            Some("search@v1")
+        );
+        assert_eq!(default_version("other", tools.values(), None, date), None);
+
+        let deprecated = Deprecation {
+            sunset: Some("2026-09-01".to_string()),
+            ..Default::default()
+        };
+        tools.get_mut("search@v10").unwrap().meta.deprecation = Some(deprecated.clone());
+        assert_eq!(
+            default_version("search", tools.values(), None, date).as_deref(),
+            Some("search@v2")
+        );
+        // Past its sunset, a pinned version is no longer served.
+        tools.get_mut("search@v1").unwrap().meta.deprecation = Some(Deprecation {
+            sunset: Some(date.to_string()),
+            ..Default::default()
+        });
+        assert_eq!(
+            default_version("search", tools.values(), Some("v1"), date).as_deref(),
+            Some("search@v2")
+        );
+        tools.get_mut("search@v2").unwrap().meta.deprecation = Some(deprecated);
+        assert_eq!(
+            default_version("search", tools.values(), None, date).as_deref(),
+            Some("search@v10")
+        );
+        assert_eq!(today().len(), 10);
+    }
+}
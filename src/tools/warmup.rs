@@ -0,0 +1,257 @@
+//! Warm-up of expensive tool backends, so the first call does not pay for
+//! loading a model or opening a pool and run past the client's timeout.
+//! Tools with a [`warmup`](super::ToolHandler::warmup) routine are warmed
+//! at startup or just before their first call, as `MCP_WARMUP` says. A
+//! call waits for a warm-up in progress; a warm-up that fails fails the
+//! call, and is tried again on the next one.
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use super::{ToolError, ToolHandler};
+
+/// Used when `MCP_WARMUP_TIMEOUT_SECS` is unset.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// When warm-ups run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarmupMode {
+    /// As the server starts, in the background, so it serves at once.
+    Startup,
+    /// Just before a tool's first call.
+    FirstUse,
+    /// Never; tools warm themselves as they are called.
+    Off,
+}
+
+impl WarmupMode {
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "startup" => Some(Self::Startup),
+            "first-use" => Some(Self::FirstUse),
+            "off" => Some(Self::Off),
+            _ => None,
+        }
+    }
+}
+
+/// Where a tool's warm-up stands.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "state", rename_all = "camelCase")]
+pub enum WarmupStatus {
+    Pending,
+    Running,
+    #[serde(rename_all = "camelCase")]
+    Ready {
+        took_ms: u64,
+    },
+    #[serde(rename_all = "camelCase")]
+    Failed {
+        error: String,
+        took_ms: u64,
+    },
+}
+
+pub struct Warmups {
+    mode: RwLock<WarmupMode>,
+    timeout: RwLock<Option<Duration>>,
+    /// Held while a tool warms up, and `true` once it has; by tool name.
+    done: Mutex<HashMap<String, Arc<tokio::sync::Mutex<bool>>>>,
+    statuses: Mutex<BTreeMap<String, WarmupStatus>>,
+}
+
+impl Default for Warmups {
+    fn default() -> Self {
+        Self {
+            mode: RwLock::new(WarmupMode::FirstUse),
+            timeout: RwLock::new(Some(DEFAULT_TIMEOUT)),
+            done: Mutex::new(HashMap::new()),
+            statuses: Mutex::new(BTreeMap::new()),
+        }
+    }
+}
+
+impl Warmups {
+    /// The mode from `MCP_WARMUP`, `startup`, `first-use` (the default) or
+    /// `off`, and the timeout from `MCP_WARMUP_TIMEOUT_SECS`, where `0`
+    /// means no limit. Malformed values are reported and ignored.
+    pub fn from_env() -> Self {
+        let warmups = Self::default();
+        if let Ok(value) = std::env::var("MCP_WARMUP") {
+            match WarmupMode::parse(value.trim()) {
+                Some(mode) => warmups.set_mode(mode),
+                None => tracing::warn!("Invalid MCP_WARMUP '{}'", value),
+            }
+        }
+        if let Ok(value) = std::env::var("MCP_WARMUP_TIMEOUT_SECS") {
+            match value.trim().parse::<u64>() {
+                Ok(secs) => warmups.set_timeout((secs > 0).then(|| Duration::from_secs(secs))),
+                Err(_) => tracing::warn!("Invalid MCP_WARMUP_TIMEOUT_SECS '{}'", value),
+            }
+        }
+        warmups
+    }
+
+    pub fn mode(&self) -> WarmupMode {
+        *self.mode.read().unwrap()
+    }
+
+    pub fn set_mode(&self, mode: WarmupMode) {
+        *self.mode.write().unwrap() = mode;
+    }
+
+    /// Longest a warm-up may run before it counts as failed; `None` for no
+    /// limit.
+    pub fn set_timeout(&self, timeout: Option<Duration>) {
+        *self.timeout.write().unwrap() = timeout;
+    }
+
+    /// Every tool with a warm-up routine, and where it stands.
+    pub fn status(&self) -> BTreeMap<String, WarmupStatus> {
+        self.statuses.lock().unwrap().clone()
+    }
+
+    /// Starts tracking `tool`, newly registered with `handler`, forgetting
+    /// any warm-up of a handler it replaced.
+    pub(crate) fn track(&self, tool: &str, handler: &dyn ToolHandler) {
+        self.forget(tool);
+        if handler.warms_up() {
+            self.statuses
+                .lock()
+                .unwrap()
+                .insert(tool.to_string(), WarmupStatus::Pending);
+        }
+    }
+
+    pub(crate) fn forget(&self, tool: &str) {
+        self.done.lock().unwrap().remove(tool);
+        self.statuses.lock().unwrap().remove(tool);
+    }
+
+    /// Warms `tool` up unless it already is, waiting for a warm-up already
+    /// running. Succeeds at once for tools without a routine, and when
+    /// warm-ups are off.
+    pub async fn ensure(
+        &self,
+        tool: &str,
+        handler: &Arc<dyn ToolHandler>,
+    ) -> Result<(), ToolError> {
+        if self.mode() == WarmupMode::Off || !handler.warms_up() {
+            return Ok(());
+        }
+        let done = self
+            .done
+            .lock()
+            .unwrap()
+            .entry(tool.to_string())
+            .or_default()
+            .clone();
+        let mut done = done.lock().await;
+        if *done {
+            return Ok(());
+        }
+
+        self.set_status(tool, WarmupStatus::Running);
+        let started = Instant::now();
+        let timeout = *self.timeout.read().unwrap();
+        let outcome = match timeout {
+            Some(timeout) => tokio::time::timeout(timeout, handler.warmup())
+                .await
+                .unwrap_or_else(|_| Err(format!("timed out after {:?}", timeout))),
+            None => handler.warmup().await,
+        };
+        let took_ms = started.elapsed().as_millis() as u64;
+        match outcome {
+            Ok(()) => {
+                *done = true;
+                self.set_status(tool, WarmupStatus::Ready { took_ms });
+                Ok(())
+            }
+            Err(error) => {
+                tracing::warn!(tool, %error, "warm-up failed");
+                let message = format!("Tool '{}' failed to warm up: {}", tool, error);
+                self.set_status(tool, WarmupStatus::Failed { error, took_ms });
+                Err(ToolError::Execution(message))
+            }
+        }
+    }
+
+    /// Records a status, unless the tool was unregistered meanwhile.
+    fn set_status(&self, tool: &str, status: WarmupStatus) {
+        if let Some(current) = self.statuses.lock().unwrap().get_mut(tool) {
+            *current = status;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToolResult;
+    use async_trait::async_trait;
+    use serde_json::Value;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /// Fails its first warm-up, then takes 20ms.
+    #[derive(Default)]
+    struct ModelTool {
+        warmups: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl ToolHandler for ModelTool {
+        async fn call(&self, _args: Value) -> Result<ToolResult, ToolError> {
+            Ok(ToolResult::text("ok"))
+        }
+
+        fn warms_up(&self) -> bool {
+            true
+        }
+
+        async fn warmup(&self) -> Result<(), String> {
+            if self.warmups.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Err("model file missing".into());
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_warmups_run_once_and_retry_failures() {
+        let warmups = Arc::new(Warmups::default());
+        let model = Arc::new(ModelTool::default());
+        let handler: Arc<dyn ToolHandler> = model.clone();
+        warmups.track("model", handler.as_ref());
+        assert_eq!(warmups.status()["model"], WarmupStatus::Pending);
+
+        let error = warmups.ensure("model", &handler).await.unwrap_err();
+        assert_eq!(
+            error.message(),
+            "Tool 'model' failed to warm up: model file missing"
+        );
+        assert!(matches!(
+            warmups.status()["model"],
+            WarmupStatus::Failed { .. }
+        ));
+
+        // Two calls at once share one warm-up.
+        let (first, second) = tokio::join!(
+            warmups.ensure("model", &handler),
+            warmups.ensure("model", &handler)
+        );
+        assert!(first.is_ok() && second.is_ok());
+        assert_eq!(model.warmups.load(Ordering::SeqCst), 2);
+        assert!(matches!(
+            warmups.status()["model"],
+            WarmupStatus::Ready { took_ms } if took_ms >= 20
+        ));
+
+        warmups.set_timeout(Some(Duration::from_millis(5)));
+        warmups.track("model", handler.as_ref());
+        let error = warmups.ensure("model", &handler).await.unwrap_err();
+        assert!(error.message().ends_with("timed out after 5ms"));
+    }
+}
@@ -0,0 +1,183 @@
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+
+use super::{required_str, Registry, ToolError, ToolHandler, ToolResult};
+use crate::approval::ApprovalQueue;
+use crate::events::{EventBus, EventKind};
+use crate::protocol::{Tool, ToolAnnotations};
+use crate::security::{SecurityEvent, SecurityEventKind, SecurityLog};
+use crate::workflow::{Workflow, WorkflowEngine};
+
+/// `workflow_start`: begins a new instance of a configured workflow.
+pub struct WorkflowStartTool {
+    pub engine: Arc<WorkflowEngine>,
+}
+
+/// `workflow_advance`: runs a tool the current state allows and moves the
+/// instance along the matching transition.
+pub struct WorkflowAdvanceTool {
+    pub engine: Arc<WorkflowEngine>,
+    pub handlers: Registry,
+    pub approvals: Arc<ApprovalQueue>,
+    pub events: EventBus,
+    pub security: SecurityLog,
+}
+
+/// `workflow_status`: reports an instance, or lists the configured workflows.
+pub struct WorkflowStatusTool {
+    pub engine: Arc<WorkflowEngine>,
+}
+
+pub fn start_definition() -> Tool {
+    Tool {
+        name: "workflow_start".to_string(),
+        description: "Start an instance of a configured workflow".to_string(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "workflow": {
+                    "type": "string",
+                    "description": "Name of the workflow to start"
+                }
+            },
+            "required": ["workflow"]
+        }),
+        output_schema: None,
+        annotations: Some(ToolAnnotations::mutating(false)),
+        meta: Default::default(),
+    }
+}
+
+pub fn advance_definition() -> Tool {
+    Tool {
+        name: "workflow_advance".to_string(),
+        description:
+            "Call a tool allowed in the instance's current state and move to the next state"
+                .to_string(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "instance_id": {
+                    "type": "string",
+                    "description": "Instance returned by workflow_start"
+                },
+                "tool": {
+                    "type": "string",
+                    "description": "Tool to call; must be one of the state's allowedTools"
+                },
+                "arguments": {
+                    "type": "object",
+                    "description": "Arguments passed to the tool and checked by the transition's guards"
+                }
+            },
+            "required": ["instance_id", "tool"]
+        }),
+        output_schema: None,
+        annotations: Some(ToolAnnotations::mutating(false)),
+        meta: Default::default(),
+    }
+}
+
+pub fn status_definition() -> Tool {
+    Tool {
+        name: "workflow_status".to_string(),
+        description:
+            "Show an instance's state, allowed tools and history, or list workflows when no instance is given"
+                .to_string(),
+        input_schema: serde_json::json!({
+            "type": "object",
+            "properties": {
+                "instance_id": {
+                    "type": "string",
+                    "description": "Instance returned by workflow_start"
+                }
+            }
+        }),
+        output_schema: None,
+        annotations: Some(ToolAnnotations::read_only()),
+        meta: Default::default(),
+    }
+}
+
+#[async_trait]
+impl ToolHandler for WorkflowStartTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        let workflow = required_str(&args, "workflow")?;
+        let instance = self
+            .engine
+            .start(workflow)
+            .map_err(ToolError::InvalidArguments)?;
+        let status = instance.lock().await.status();
+        Ok(ToolResult::json(&status))
+    }
+}
+
+#[async_trait]
+impl ToolHandler for WorkflowAdvanceTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        let instance_id = required_str(&args, "instance_id")?;
+        let tool = required_str(&args, "tool")?;
+        let arguments = args
+            .get("arguments")
+            .cloned()
+            .unwrap_or_else(|| serde_json::json!({}));
+
+        let instance = self.engine.instance(instance_id).ok_or_else(|| {
+            ToolError::InvalidArguments(format!("Workflow instance not found: {}", instance_id))
+        })?;
+        // Held across the tool call so concurrent advances of the same
+        // instance cannot both take a transition.
+        let mut instance = instance.lock().await;
+        let transition = instance.transition(tool, &arguments).map_err(|e| {
+            self.security.report(
+                SecurityEvent::new(SecurityEventKind::PolicyDenied, e.as_str())
+                    .rule("workflow_transition")
+                    .tool(tool)
+                    .detail("workflow", instance.workflow.name.as_str())
+                    .detail("state", instance.state.as_str()),
+            );
+            ToolError::InvalidArguments(e)
+        })?;
+        let handler = self
+            .handlers
+            .get(tool)
+            .ok_or_else(|| ToolError::InvalidArguments(format!("Tool not found: {}", tool)))?;
+
+        self.approvals.check(tool, &arguments).await?;
+        let mut result = handler.call(arguments).await?;
+        // A failed step leaves the instance where it was, to be retried.
+        if result.is_error {
+            return Ok(result);
+        }
+        instance.advance(&transition);
+        let status = instance.status();
+        if status["completed"] == true {
+            self.events.publish(EventKind::JobFinished, status.clone());
+        }
+        result.content.push(serde_json::json!({
+            "type": "text",
+            "text": serde_json::to_string_pretty(&status).unwrap()
+        }));
+        Ok(result)
+    }
+}
+
+#[async_trait]
+impl ToolHandler for WorkflowStatusTool {
+    async fn call(&self, args: Value) -> Result<ToolResult, ToolError> {
+        let Some(instance_id) = args.get("instance_id").and_then(|v| v.as_str()) else {
+            let definitions = self.engine.definitions();
+            let workflows: Vec<&Workflow> = definitions.iter().map(|w| w.as_ref()).collect();
+            return Ok(ToolResult::json(
+                &serde_json::json!({ "workflows": workflows }),
+            ));
+        };
+
+        let instance = self.engine.instance(instance_id).ok_or_else(|| {
+            ToolError::InvalidArguments(format!("Workflow instance not found: {}", instance_id))
+        })?;
+        let status = instance.lock().await.status();
+        Ok(ToolResult::json(&status))
+    }
+}
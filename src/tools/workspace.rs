@@ -0,0 +1,158 @@
+use async_trait::async_trait;
+use serde_json::Value;
+
+use super::args::Arguments;
+use super::{required_str, ToolContext, ToolError, ToolHandler, ToolResult};
+use crate::protocol::{Tool, ToolAnnotations};
+
+/// Which `workspace_*` operation a [`WorkspaceTool`] performs.
+#[derive(Debug, Clone, Copy)]
+pub enum WorkspaceOperation {
+    Write,
+    Read,
+    List,
+    Delete,
+}
+
+/// One of the tools working on the calling session's workspace.
+pub struct WorkspaceTool {
+    pub operation: WorkspaceOperation,
+}
+
+fn uri_schema() -> Value {
+    serde_json::json!({
+        "type": "string",
+        "description": "File in the session workspace, e.g. workspace://data/input.csv"
+    })
+}
+
+/// The four workspace tools with their operations.
+pub fn definitions() -> Vec<(Tool, WorkspaceOperation)> {
+    vec![
+        (
+            Tool {
+                name: "workspace_write".to_string(),
+                description: "Write a text file to this session's temporary workspace".to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {
+                        "uri": uri_schema(),
+                        "content": {"type": "string"}
+                    },
+                    "required": ["uri", "content"]
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations {
+                    idempotent_hint: Some(true),
+                    ..ToolAnnotations::mutating(true)
+                }),
+                meta: Default::default(),
+            },
+            WorkspaceOperation::Write,
+        ),
+        (
+            Tool {
+                name: "workspace_read".to_string(),
+                description: "Read a text file from this session's temporary workspace".to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {"uri": uri_schema()},
+                    "required": ["uri"]
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations::read_only()),
+                meta: Default::default(),
+            },
+            WorkspaceOperation::Read,
+        ),
+        (
+            Tool {
+                name: "workspace_list".to_string(),
+                description: "List the files in this session's temporary workspace and its quota"
+                    .to_string(),
+                input_schema: serde_json::json!({"type": "object", "properties": {}}),
+                output_schema: None,
+                annotations: Some(ToolAnnotations::read_only()),
+                meta: Default::default(),
+            },
+            WorkspaceOperation::List,
+        ),
+        (
+            Tool {
+                name: "workspace_delete".to_string(),
+                description: "Delete a file from this session's temporary workspace".to_string(),
+                input_schema: serde_json::json!({
+                    "type": "object",
+                    "properties": {"uri": uri_schema()},
+                    "required": ["uri"]
+                }),
+                output_schema: None,
+                annotations: Some(ToolAnnotations::mutating(true)),
+                meta: Default::default(),
+            },
+            WorkspaceOperation::Delete,
+        ),
+    ]
+}
+
+#[async_trait]
+impl ToolHandler for WorkspaceTool {
+    async fn call(&self, _args: Value) -> Result<ToolResult, ToolError> {
+        Err(ToolError::Execution(
+            "Workspaces are only available within a session".to_string(),
+        ))
+    }
+
+    async fn call_with_context(
+        &self,
+        args: Value,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        self.call_shared(args.into(), context).await
+    }
+
+    /// The content is only read, so a large file is never copied on its
+    /// way to the workspace.
+    async fn call_shared(
+        &self,
+        args: Arguments,
+        context: &ToolContext,
+    ) -> Result<ToolResult, ToolError> {
+        let Some(session) = &context.session else {
+            return self.call(Value::Null).await;
+        };
+        let workspace = session.workspace().map_err(ToolError::Execution)?;
+
+        match self.operation {
+            WorkspaceOperation::Write => {
+                let uri = required_str(&args, "uri")?;
+                let content = required_str(&args, "content")?;
+                workspace
+                    .write(uri, content.as_bytes())
+                    .map_err(ToolError::Execution)?;
+                Ok(ToolResult::text(format!(
+                    "Wrote {} bytes to {}",
+                    content.len(),
+                    uri
+                )))
+            }
+            WorkspaceOperation::Read => {
+                let uri = required_str(&args, "uri")?;
+                let bytes = workspace.read(uri).map_err(ToolError::InvalidArguments)?;
+                let text = String::from_utf8(bytes)
+                    .map_err(|_| ToolError::Execution(format!("{} is not UTF-8 text", uri)))?;
+                Ok(ToolResult::text(text))
+            }
+            WorkspaceOperation::List => Ok(ToolResult::json(&serde_json::json!({
+                "files": workspace.list(),
+                "usedBytes": workspace.used(),
+                "quotaBytes": workspace.quota(),
+            }))),
+            WorkspaceOperation::Delete => {
+                let uri = required_str(&args, "uri")?;
+                workspace.delete(uri).map_err(ToolError::InvalidArguments)?;
+                Ok(ToolResult::text(format!("Deleted {}", uri)))
+            }
+        }
+    }
+}
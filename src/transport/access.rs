@@ -0,0 +1,349 @@
+//! Connection admission for the network transports: CIDR allow and deny
+//! lists, a cap on connections per client address and a global cap, all
+//! enforced when a connection is accepted.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::security::{SecurityEvent, SecurityEventKind, SecurityLog};
+
+/// An address block such as `10.0.0.0/8` or `fd00::/8`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    network: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    /// Parses `addr/prefix`; a bare address matches only itself.
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let network: IpAddr = addr
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid address in CIDR '{}'", s))?;
+        let max = if network.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            Some(p) => p
+                .trim()
+                .parse()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or_else(|| format!("Invalid prefix length in CIDR '{}'", s))?,
+            None => max,
+        };
+        // Peers are compared in canonical form, so store an IPv4-mapped
+        // block as the IPv4 block it covers.
+        if let IpAddr::V6(v6) = network {
+            if let (Some(v4), true) = (v6.to_ipv4_mapped(), prefix >= 96) {
+                return Ok(Self {
+                    network: IpAddr::V4(v4),
+                    prefix: prefix - 96,
+                });
+            }
+        }
+        Ok(Self { network, prefix })
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                prefix_matches(&net.octets(), &ip.octets(), self.prefix)
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                prefix_matches(&net.octets(), &ip.octets(), self.prefix)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn prefix_matches(network: &[u8], ip: &[u8], prefix: u8) -> bool {
+    let (bytes, bits) = (prefix as usize / 8, prefix % 8);
+    if network[..bytes] != ip[..bytes] {
+        return false;
+    }
+    bits == 0 || {
+        let mask = 0xffu8 << (8 - bits);
+        network[bytes] & mask == ip[bytes] & mask
+    }
+}
+
+/// Who may connect, and how many connections are allowed. The default
+/// admits everyone without limits.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AccessPolicy {
+    /// When non-empty, only addresses in one of these blocks are admitted.
+    pub allow: Vec<Cidr>,
+    /// Addresses in these blocks are refused, even when also allowed.
+    pub deny: Vec<Cidr>,
+    pub max_connections_per_ip: Option<usize>,
+    pub max_connections: Option<usize>,
+}
+
+impl AccessPolicy {
+    /// Policy for one transport from `MCP_<TRANSPORT>_ALLOWED_CIDRS`,
+    /// `_DENIED_CIDRS` (comma-separated), `_MAX_CONNECTIONS_PER_IP` and
+    /// `_MAX_CONNECTIONS`, e.g. `MCP_TCP_MAX_CONNECTIONS` for `"tcp"`.
+    pub fn from_env(transport: &str) -> Result<Self, String> {
+        let var = |suffix: &str| {
+            std::env::var(format!("MCP_{}_{}", transport.to_uppercase(), suffix)).ok()
+        };
+        let cidrs = |suffix: &str| -> Result<Vec<Cidr>, String> {
+            var(suffix)
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(Cidr::parse)
+                .collect()
+        };
+        let limit = |suffix: &str| -> Result<Option<usize>, String> {
+            var(suffix)
+                .map(|v| {
+                    v.trim()
+                        .parse()
+                        .map_err(|_| format!("Invalid {} '{}'", suffix, v))
+                })
+                .transpose()
+        };
+        Ok(Self {
+            allow: cidrs("ALLOWED_CIDRS")?,
+            deny: cidrs("DENIED_CIDRS")?,
+            max_connections_per_ip: limit("MAX_CONNECTIONS_PER_IP")?,
+            max_connections: limit("MAX_CONNECTIONS")?,
+        })
+    }
+}
+
+/// Why a connection was refused.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    /// The address is denied, or not in a non-empty allow list.
+    Denied,
+    PerIpLimit,
+    GlobalLimit,
+}
+
+/// Counters since start-up, plus the current number of open connections.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionStats {
+    pub active: u64,
+    pub accepted: u64,
+    pub rejected_denied: u64,
+    pub rejected_per_ip: u64,
+    pub rejected_global: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    accepted: AtomicU64,
+    rejected_denied: AtomicU64,
+    rejected_per_ip: AtomicU64,
+    rejected_global: AtomicU64,
+}
+
+/// Applies an [`AccessPolicy`] to accepted connections and tracks the open
+/// ones. Refusals are counted and reported as security events.
+#[derive(Default)]
+pub struct ConnectionLimiter {
+    transport: String,
+    policy: AccessPolicy,
+    /// Open connections per client address.
+    open: Mutex<HashMap<IpAddr, usize>>,
+    counters: Counters,
+    security: SecurityLog,
+}
+
+impl ConnectionLimiter {
+    pub fn new(transport: &str, policy: AccessPolicy) -> Self {
+        Self {
+            transport: transport.to_string(),
+            policy,
+            ..Self::default()
+        }
+    }
+
+    pub fn with_security(mut self, security: SecurityLog) -> Self {
+        self.security = security;
+        self
+    }
+
+    /// Admits a connection from `ip`, returning a permit that must be held
+    /// for as long as the connection is open.
+    pub fn admit(self: &Arc<Self>, ip: IpAddr) -> Result<ConnectionPermit, Rejection> {
+        let ip = ip.to_canonical();
+        let rejection = match self.try_admit(ip) {
+            Ok(()) => {
+                self.counters.accepted.fetch_add(1, Ordering::Relaxed);
+                return Ok(ConnectionPermit {
+                    limiter: self.clone(),
+                    ip,
+                });
+            }
+            Err(rejection) => rejection,
+        };
+        let (counter, kind, rule, message) = match rejection {
+            Rejection::Denied => (
+                &self.counters.rejected_denied,
+                SecurityEventKind::PolicyDenied,
+                "ip_access",
+                "Connection refused by IP access list",
+            ),
+            Rejection::PerIpLimit => (
+                &self.counters.rejected_per_ip,
+                SecurityEventKind::RateLimited,
+                "max_connections_per_ip",
+                "Too many connections from this address",
+            ),
+            Rejection::GlobalLimit => (
+                &self.counters.rejected_global,
+                SecurityEventKind::RateLimited,
+                "max_connections",
+                "Connection limit reached",
+            ),
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        self.security.report(
+            SecurityEvent::new(kind, message)
+                .rule(rule)
+                .source_ip(ip)
+                .detail("transport", self.transport.as_str()),
+        );
+        Err(rejection)
+    }
+
+    fn try_admit(&self, ip: IpAddr) -> Result<(), Rejection> {
+        let policy = &self.policy;
+        if policy.deny.iter().any(|c| c.contains(ip))
+            || (!policy.allow.is_empty() && !policy.allow.iter().any(|c| c.contains(ip)))
+        {
+            return Err(Rejection::Denied);
+        }
+        let mut open = self.open.lock().unwrap();
+        let total: usize = open.values().sum();
+        if policy.max_connections.is_some_and(|max| total >= max) {
+            return Err(Rejection::GlobalLimit);
+        }
+        let from_ip = open.entry(ip).or_default();
+        if policy
+            .max_connections_per_ip
+            .is_some_and(|max| *from_ip >= max)
+        {
+            return Err(Rejection::PerIpLimit);
+        }
+        *from_ip += 1;
+        Ok(())
+    }
+
+    pub fn stats(&self) -> ConnectionStats {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        ConnectionStats {
+            active: self.open.lock().unwrap().values().sum::<usize>() as u64,
+            accepted: load(&self.counters.accepted),
+            rejected_denied: load(&self.counters.rejected_denied),
+            rejected_per_ip: load(&self.counters.rejected_per_ip),
+            rejected_global: load(&self.counters.rejected_global),
+        }
+    }
+
+    pub fn transport(&self) -> &str {
+        &self.transport
+    }
+}
+
+/// An admitted connection; dropping it frees its slot.
+pub struct ConnectionPermit {
+    limiter: Arc<ConnectionLimiter>,
+    ip: IpAddr,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let mut open = self.limiter.open.lock().unwrap();
+        if let Some(count) = open.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                open.remove(&self.ip);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_cidr_matching() {
+        let private = Cidr::parse("10.0.0.0/8").unwrap();
+        assert!(private.contains(ip("10.200.3.4")));
+        assert!(!private.contains(ip("11.0.0.1")));
+        assert!(private.contains(ip("::ffff:10.1.2.3")));
+
+        let odd = Cidr::parse("192.168.0.0/23").unwrap();
+        assert!(odd.contains(ip("192.168.1.255")));
+        assert!(!odd.contains(ip("192.168.2.0")));
+
+        assert!(Cidr::parse("fd00::/8").unwrap().contains(ip("fd12::1")));
+        assert!(Cidr::parse("127.0.0.1").unwrap().contains(ip("127.0.0.1")));
+        assert!(Cidr::parse("0.0.0.0/0").unwrap().contains(ip("8.8.8.8")));
+        assert!(Cidr::parse("::ffff:10.0.0.0/104")
+            .unwrap()
+            .contains(ip("10.3.0.1")));
+        assert!(Cidr::parse("10.0.0.0/33").is_err());
+        assert!(Cidr::parse("nope/8").is_err());
+    }
+
+    #[test]
+    fn test_lists_and_limits() {
+        let limiter = Arc::new(ConnectionLimiter::new(
+            "tcp",
+            AccessPolicy {
+                allow: vec![Cidr::parse("10.0.0.0/8").unwrap()],
+                deny: vec![Cidr::parse("10.9.0.0/16").unwrap()],
+                max_connections_per_ip: Some(2),
+                max_connections: Some(3),
+            },
+        ));
+
+        assert_eq!(limiter.admit(ip("8.8.8.8")).err(), Some(Rejection::Denied));
+        assert_eq!(limiter.admit(ip("10.9.1.1")).err(), Some(Rejection::Denied));
+
+        let a1 = limiter.admit(ip("10.0.0.1")).unwrap();
+        let _a2 = limiter.admit(ip("10.0.0.1")).unwrap();
+        assert_eq!(
+            limiter.admit(ip("10.0.0.1")).err(),
+            Some(Rejection::PerIpLimit)
+        );
+        let _b1 = limiter.admit(ip("10.0.0.2")).unwrap();
+        assert_eq!(
+            limiter.admit(ip("10.0.0.3")).err(),
+            Some(Rejection::GlobalLimit)
+        );
+
+        drop(a1);
+        let _c1 = limiter.admit(ip("10.0.0.3")).unwrap();
+        assert_eq!(
+            limiter.stats(),
+            ConnectionStats {
+                active: 3,
+                accepted: 4,
+                rejected_denied: 2,
+                rejected_per_ip: 1,
+                rejected_global: 1,
+            }
+        );
+    }
+}
@@ -0,0 +1,604 @@
+//! Static token authentication for the network transports. With tokens
+//! configured, a client must present one before any of its JSON-RPC
+//! messages are read: on every HTTP request, as `Authorization: Bearer
+//! <token>` or `X-API-Key: <token>`, and on a TCP connection as a first
+//! line in the same `Name: value` form.
+//!
+//! Several tokens may be accepted at once, each until its expiry, so one
+//! can be rotated out without refusing clients that have yet to pick up its
+//! replacement. The admin socket rotates them while the server runs.
+//!
+//! An address that presents too many wrong tokens is banned for a while,
+//! so tokens can't be guessed at network speed.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::crypto;
+use crate::http::server::{Request, Response};
+use crate::protocol::McpResponse;
+use crate::security::{SecurityEvent, SecurityEventKind};
+use crate::tools::rate_limits::RATE_LIMITED;
+use crate::McpServer;
+
+pub const API_KEY_HEADER: &str = "X-API-Key";
+
+/// How long a TCP client has to send its token line.
+pub const LINE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest token line read; anything longer is refused.
+const MAX_LINE_BYTES: u64 = 8 * 1024;
+
+/// JSON-RPC error code refusing a TCP connection without a valid token.
+pub const UNAUTHORIZED: i32 = -32005;
+
+/// Wrong tokens an address may present within [`BanPolicy::window`] before
+/// it is banned, unless `MCP_AUTH_MAX_FAILURES` says otherwise.
+pub const DEFAULT_MAX_FAILURES: u32 = 10;
+
+pub const DEFAULT_FAILURE_WINDOW: Duration = Duration::from_secs(300);
+
+pub const DEFAULT_BAN: Duration = Duration::from_secs(900);
+
+/// How long tokens replaced by the admin socket's `auth/rotate` stay valid,
+/// unless it is given `graceSecs`.
+pub const DEFAULT_ROTATION_GRACE: Duration = Duration::from_secs(3600);
+
+/// Why a client's credentials were not accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthError {
+    Missing,
+    Invalid,
+    /// The address presented too many wrong tokens, and may try again
+    /// after this many seconds.
+    Banned(u64),
+}
+
+impl AuthError {
+    pub fn message(self) -> &'static str {
+        match self {
+            Self::Missing => "Missing bearer token or API key",
+            Self::Invalid => "Invalid bearer token or API key",
+            Self::Banned(_) => "Too many invalid tokens; try again later",
+        }
+    }
+
+    /// The JSON-RPC error code refusing a line-delimited connection.
+    pub fn code(self) -> i32 {
+        match self {
+            Self::Banned(_) => RATE_LIMITED,
+            _ => UNAUTHORIZED,
+        }
+    }
+
+    /// The HTTP response refusing a request: `401` asking for a bearer
+    /// token, or `429` with `Retry-After` for a banned address.
+    pub fn response(self) -> Response {
+        match self {
+            Self::Banned(secs) => {
+                Response::text(429, self.message()).header("Retry-After", secs.to_string())
+            }
+            _ => Response::text(401, self.message()).header("WWW-Authenticate", "Bearer"),
+        }
+    }
+}
+
+/// A token a client may present, and when it stops being accepted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Token {
+    pub token: String,
+    /// Seconds since the Unix epoch; `None` never expires.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+}
+
+impl Token {
+    pub fn new(token: impl Into<String>) -> Self {
+        Self {
+            token: token.into(),
+            expires_at: None,
+        }
+    }
+
+    pub fn expiring(mut self, expires_at: u64) -> Self {
+        self.expires_at = Some(expires_at);
+        self
+    }
+
+    fn live(&self, now: u64) -> bool {
+        self.expires_at.is_none_or(|at| now < at)
+    }
+
+    /// Names the token in listings without revealing it: the first 12 hex
+    /// digits of its SHA-256.
+    pub fn fingerprint(&self) -> String {
+        crypto::hex(&crypto::sha256(self.token.as_bytes()))[..12].to_string()
+    }
+}
+
+/// When wrong tokens get an address banned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BanPolicy {
+    /// Wrong tokens allowed within `window`; 0 never bans.
+    pub max_failures: u32,
+    pub window: Duration,
+    /// How long a ban lasts.
+    pub ban: Duration,
+}
+
+impl Default for BanPolicy {
+    fn default() -> Self {
+        Self {
+            max_failures: DEFAULT_MAX_FAILURES,
+            window: DEFAULT_FAILURE_WINDOW,
+            ban: DEFAULT_BAN,
+        }
+    }
+}
+
+impl BanPolicy {
+    /// The policy from `MCP_AUTH_MAX_FAILURES`,
+    /// `MCP_AUTH_FAILURE_WINDOW_SECS` and `MCP_AUTH_BAN_SECS`, each falling
+    /// back to its default.
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            let value = std::env::var(name).ok()?;
+            let parsed = value.trim().parse().ok();
+            if parsed.is_none() {
+                tracing::warn!("Invalid {} '{}'", name, value);
+            }
+            parsed
+        }
+        let mut policy = Self::default();
+        if let Some(max) = var("MCP_AUTH_MAX_FAILURES") {
+            policy.max_failures = max;
+        }
+        if let Some(secs) = var("MCP_AUTH_FAILURE_WINDOW_SECS") {
+            policy.window = Duration::from_secs(secs);
+        }
+        if let Some(secs) = var("MCP_AUTH_BAN_SECS") {
+            policy.ban = Duration::from_secs(secs);
+        }
+        policy
+    }
+}
+
+/// Wrong tokens from one address.
+#[derive(Debug)]
+struct Failures {
+    /// When the first wrong token of the current window arrived.
+    since: Instant,
+    count: u32,
+    banned_until: Option<Instant>,
+}
+
+/// A banned address, as the admin socket lists it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Ban {
+    pub ip: IpAddr,
+    pub remaining_secs: u64,
+}
+
+/// The tokens a client may present, and the addresses banned for
+/// presenting wrong ones.
+#[derive(Debug)]
+pub struct TokenAuth {
+    tokens: RwLock<Vec<Token>>,
+    policy: BanPolicy,
+    failures: Mutex<HashMap<IpAddr, Failures>>,
+}
+
+impl TokenAuth {
+    /// Accepts any of `tokens`, without expiry; empty ones are dropped.
+    pub fn new<T: Into<String>>(tokens: impl IntoIterator<Item = T>) -> Self {
+        Self::with_tokens(tokens.into_iter().map(Token::new))
+    }
+
+    /// Accepts any of `tokens` until it expires; empty ones are dropped.
+    pub fn with_tokens(tokens: impl IntoIterator<Item = Token>) -> Self {
+        Self {
+            tokens: RwLock::new(tokens.into_iter().filter(|t| !t.token.is_empty()).collect()),
+            policy: BanPolicy::default(),
+            failures: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Bans addresses as `policy` says, instead of by the defaults.
+    pub fn with_ban_policy(mut self, policy: BanPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Tokens from `MCP_AUTH_TOKENS`, comma-separated, or `None` when none
+    /// are set. Bans follow [`BanPolicy::from_env`].
+    pub fn from_env() -> Option<Self> {
+        let tokens = std::env::var("MCP_AUTH_TOKENS").ok()?;
+        let auth = Self::new(tokens.split(',').map(str::trim));
+        let empty = auth.tokens.read().unwrap().is_empty();
+        (!empty).then(|| auth.with_ban_policy(BanPolicy::from_env()))
+    }
+
+    /// The tokens accepted now or later; expired ones are left out.
+    pub fn tokens(&self) -> Vec<Token> {
+        let now = unix_now();
+        let tokens = self.tokens.read().unwrap();
+        tokens.iter().filter(|t| t.live(now)).cloned().collect()
+    }
+
+    /// Starts accepting `token` and has every other token expire within
+    /// `grace`, so clients have that long to switch. Expired tokens are
+    /// forgotten.
+    pub fn rotate(&self, token: Token, grace: Duration) {
+        let now = unix_now();
+        let deadline = now + grace.as_secs();
+        let mut tokens = self.tokens.write().unwrap();
+        tokens.retain(|t| t.live(now) && t.token != token.token);
+        for old in tokens.iter_mut() {
+            old.expires_at = Some(old.expires_at.map_or(deadline, |at| at.min(deadline)));
+        }
+        tokens.push(token);
+    }
+
+    /// Checks a presented token. Every live token is compared in constant
+    /// time, so timing says nothing about which came close.
+    pub fn verify(&self, presented: &str) -> Result<(), AuthError> {
+        let now = unix_now();
+        let tokens = self.tokens.read().unwrap();
+        let matched = tokens.iter().fold(false, |matched, token| {
+            (token.live(now)
+                && crypto::constant_time_eq(token.token.as_bytes(), presented.as_bytes()))
+                | matched
+        });
+        if matched {
+            Ok(())
+        } else {
+            Err(AuthError::Invalid)
+        }
+    }
+
+    /// Checks the `Authorization` and `X-API-Key` headers of `request`.
+    /// Either may carry the token, so an `Authorization` header for some
+    /// other scheme, such as one a proxy adds, does not hide an API key.
+    pub fn verify_request(&self, request: &Request) -> Result<(), AuthError> {
+        let mut result = Err(AuthError::Missing);
+        for name in ["Authorization", API_KEY_HEADER] {
+            let Some(token) = request.header(name).and_then(|v| credential(name, v)) else {
+                continue;
+            };
+            result = self.verify(token);
+            if result.is_ok() {
+                break;
+            }
+        }
+        result
+    }
+
+    /// Checks the first line of a TCP connection, a header such as
+    /// `Authorization: Bearer <token>`.
+    pub fn verify_line(&self, line: &str) -> Result<(), AuthError> {
+        let (name, value) = line.trim().split_once(':').ok_or(AuthError::Missing)?;
+        self.verify(credential(name.trim(), value).ok_or(AuthError::Missing)?)
+    }
+
+    /// Checks the headers of a `request` from `peer`, as
+    /// [`verify_request`](Self::verify_request) does, unless the address
+    /// is banned. The outcome is [recorded](Self::record).
+    pub fn verify_request_from(&self, request: &Request, peer: IpAddr) -> Result<(), AuthError> {
+        self.check_ban(peer)?;
+        self.record(peer, self.verify_request(request))
+    }
+
+    /// Fails with [`AuthError::Banned`] while `peer` is banned.
+    pub fn check_ban(&self, peer: IpAddr) -> Result<(), AuthError> {
+        let now = Instant::now();
+        match self.banned_until(peer, now) {
+            Some(until) => Err(AuthError::Banned((until - now).as_secs().max(1))),
+            None => Ok(()),
+        }
+    }
+
+    /// Counts the outcome of checking a token from `peer` and returns it. A
+    /// wrong token counts toward a ban; a missing one doesn't, since it
+    /// guesses nothing. A right one clears the address's count.
+    pub fn record(&self, peer: IpAddr, result: Result<(), AuthError>) -> Result<(), AuthError> {
+        let now = Instant::now();
+        let mut failures = self.failures.lock().unwrap();
+        match result {
+            Ok(()) => {
+                failures.remove(&peer);
+            }
+            Err(AuthError::Invalid) if self.policy.max_failures > 0 => {
+                // Forget addresses that have gone quiet, so the map stays
+                // as small as the current attack.
+                let policy = self.policy;
+                failures.retain(|_, f| match f.banned_until {
+                    Some(until) => until > now,
+                    None => now.duration_since(f.since) < policy.window,
+                });
+                let entry = failures.entry(peer).or_insert(Failures {
+                    since: now,
+                    count: 0,
+                    banned_until: None,
+                });
+                entry.count += 1;
+                if entry.count >= policy.max_failures {
+                    entry.banned_until = Some(now + policy.ban);
+                }
+            }
+            Err(_) => {}
+        }
+        result
+    }
+
+    fn banned_until(&self, peer: IpAddr, now: Instant) -> Option<Instant> {
+        let failures = self.failures.lock().unwrap();
+        failures
+            .get(&peer)?
+            .banned_until
+            .filter(|&until| until > now)
+    }
+
+    /// The addresses banned now.
+    pub fn bans(&self) -> Vec<Ban> {
+        let now = Instant::now();
+        let failures = self.failures.lock().unwrap();
+        let mut bans: Vec<Ban> = failures
+            .iter()
+            .filter_map(|(&ip, f)| {
+                let until = f.banned_until.filter(|&until| until > now)?;
+                Some(Ban {
+                    ip,
+                    remaining_secs: (until - now).as_secs(),
+                })
+            })
+            .collect();
+        bans.sort_by_key(|ban| ban.ip);
+        bans
+    }
+
+    /// Lifts any ban on `ip` and forgets its wrong tokens. Returns whether
+    /// it was banned.
+    pub fn unban(&self, ip: IpAddr) -> bool {
+        let now = Instant::now();
+        let removed = self.failures.lock().unwrap().remove(&ip);
+        removed.is_some_and(|f| f.banned_until.is_some_and(|until| until > now))
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// The token in an `Authorization` or `X-API-Key` header.
+fn credential<'a>(name: &str, value: &'a str) -> Option<&'a str> {
+    let value = value.trim();
+    if name.eq_ignore_ascii_case(API_KEY_HEADER) {
+        return Some(value);
+    }
+    if !name.eq_ignore_ascii_case("Authorization") {
+        return None;
+    }
+    let (scheme, token) = value.split_once(' ')?;
+    scheme
+        .eq_ignore_ascii_case("Bearer")
+        .then_some(token.trim())
+}
+
+/// Reads and checks the token line a line-delimited connection from `peer`
+/// opens with, when the server requires a token. A client that sends none
+/// in time, or a wrong one, gets an `UNAUTHORIZED` error and `false`, and
+/// the caller closes the connection. The line holds a secret, so it is
+/// never traced.
+pub async fn authenticate_lines<R, W>(
+    server: &McpServer,
+    reader: &mut R,
+    writer: &mut W,
+    peer: SocketAddr,
+) -> io::Result<bool>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let Some(auth) = server.token_auth() else {
+        return Ok(true);
+    };
+    // A banned address is refused before its line is even read.
+    let verified = match auth.check_ban(peer.ip()) {
+        Ok(()) => auth.record(peer.ip(), read_token_line(&auth, reader).await?),
+        Err(e) => Err(e),
+    };
+    let Err(e) = verified else {
+        return Ok(true);
+    };
+    server.security().report(auth_failure("tcp", peer, e));
+    refuse_lines(writer, e).await?;
+    Ok(false)
+}
+
+/// Reads the first line of a line-delimited connection and checks it with
+/// [`TokenAuth::verify_line`]. A line that is too long, not UTF-8 or not
+/// sent within [`LINE_TIMEOUT`] counts as a missing token.
+pub async fn read_token_line<R>(
+    auth: &TokenAuth,
+    reader: &mut R,
+) -> io::Result<Result<(), AuthError>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut line = String::new();
+    let mut limited = (&mut *reader).take(MAX_LINE_BYTES);
+    let read = tokio::time::timeout(LINE_TIMEOUT, limited.read_line(&mut line));
+    Ok(match read.await {
+        Ok(Ok(_)) => auth.verify_line(&line),
+        // Not UTF-8, so not a header either.
+        Ok(Err(e)) if e.kind() == io::ErrorKind::InvalidData => Err(AuthError::Missing),
+        Ok(Err(e)) => return Err(e),
+        Err(_) => Err(AuthError::Missing),
+    })
+}
+
+/// Writes the `UNAUTHORIZED` error a refused line-delimited connection
+/// gets before it is closed.
+pub async fn refuse_lines<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    error: AuthError,
+) -> io::Result<()> {
+    let refusal = McpResponse::error(None, error.code(), error.message());
+    let mut message = serde_json::to_string(&refusal).map_err(io::Error::other)?;
+    message.push('\n');
+    writer.write_all(message.as_bytes()).await?;
+    writer.flush().await
+}
+
+/// The security event for a client refused by [`TokenAuth`]: an
+/// `auth_failure`, or `rate_limited` for a banned address.
+pub fn auth_failure(transport: &str, peer: SocketAddr, error: AuthError) -> SecurityEvent {
+    let (kind, rule) = match error {
+        AuthError::Banned(_) => (SecurityEventKind::RateLimited, "auth_ban"),
+        _ => (SecurityEventKind::AuthFailure, "bearer_token"),
+    };
+    SecurityEvent::new(kind, error.message())
+        .rule(rule)
+        .source_ip(peer.ip())
+        .detail("transport", transport)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(headers: &[(&str, &str)]) -> Request {
+        Request {
+            method: "POST".to_string(),
+            target: "/mcp".to_string(),
+            headers: headers
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+            body: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_tokens_are_accepted_in_either_header() {
+        let auth = TokenAuth::new(["old", "new", ""]);
+        assert_eq!(
+            auth.verify_request(&request(&[("authorization", "Bearer new")])),
+            Ok(())
+        );
+        assert_eq!(
+            auth.verify_request(&request(&[("X-Api-Key", "old")])),
+            Ok(())
+        );
+        assert_eq!(
+            auth.verify_request(&request(&[("Authorization", "Bearer nope")])),
+            Err(AuthError::Invalid)
+        );
+        assert_eq!(
+            auth.verify_request(&request(&[("Authorization", "Basic bmV3")])),
+            Err(AuthError::Missing)
+        );
+        assert_eq!(auth.verify_request(&request(&[])), Err(AuthError::Missing));
+        assert_eq!(
+            auth.verify_request(&request(&[
+                ("Authorization", "Basic bmV3"),
+                ("X-API-Key", "new")
+            ])),
+            Ok(())
+        );
+        assert_eq!(
+            auth.verify_request(&request(&[
+                ("Authorization", "Bearer nope"),
+                ("X-API-Key", "new")
+            ])),
+            Ok(())
+        );
+        // An empty token never matches, though one was configured.
+        assert_eq!(auth.verify(""), Err(AuthError::Invalid));
+
+        assert_eq!(auth.verify_line("Authorization: Bearer new\r\n"), Ok(()));
+        assert_eq!(auth.verify_line("x-api-key: old\n"), Ok(()));
+        assert_eq!(
+            auth.verify_line(r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#),
+            Err(AuthError::Missing)
+        );
+    }
+
+    #[test]
+    fn test_tokens_expire_and_rotate() {
+        let auth = TokenAuth::with_tokens([Token::new("current"), Token::new("stale").expiring(1)]);
+        assert_eq!(auth.verify("current"), Ok(()));
+        assert_eq!(auth.verify("stale"), Err(AuthError::Invalid));
+        assert_eq!(auth.tokens(), [Token::new("current")]);
+
+        // The replaced token keeps working through the grace period.
+        auth.rotate(Token::new("next"), Duration::from_secs(60));
+        assert_eq!(auth.verify("next"), Ok(()));
+        assert_eq!(auth.verify("current"), Ok(()));
+        assert!(auth.tokens()[0].expires_at.is_some());
+
+        // Without one, it stops at once.
+        auth.rotate(Token::new("last"), Duration::ZERO);
+        assert_eq!(auth.verify("last"), Ok(()));
+        assert_eq!(auth.verify("next"), Err(AuthError::Invalid));
+        assert_eq!(auth.verify("current"), Err(AuthError::Invalid));
+        assert_eq!(auth.tokens(), [Token::new("last")]);
+    }
+
+    #[test]
+    fn test_wrong_tokens_get_an_address_banned() {
+        let auth = TokenAuth::new(["right"]).with_ban_policy(BanPolicy {
+            max_failures: 3,
+            window: Duration::from_secs(60),
+            ban: Duration::from_secs(60),
+        });
+        let (guesser, other): (IpAddr, IpAddr) =
+            ("192.0.2.1".parse().unwrap(), "192.0.2.2".parse().unwrap());
+        let wrong = request(&[("Authorization", "Bearer wrong")]);
+        let right = request(&[("Authorization", "Bearer right")]);
+
+        // A right token clears the count, and a missing one adds nothing.
+        for _ in 0..2 {
+            assert_eq!(
+                auth.verify_request_from(&wrong, guesser),
+                Err(AuthError::Invalid)
+            );
+        }
+        assert_eq!(auth.verify_request_from(&right, guesser), Ok(()));
+        for _ in 0..2 {
+            assert_eq!(
+                auth.verify_request_from(&wrong, guesser),
+                Err(AuthError::Invalid)
+            );
+        }
+        assert_eq!(
+            auth.verify_request_from(&request(&[]), guesser),
+            Err(AuthError::Missing)
+        );
+        assert!(auth.bans().is_empty());
+
+        assert_eq!(
+            auth.verify_request_from(&wrong, guesser),
+            Err(AuthError::Invalid)
+        );
+        assert!(matches!(
+            auth.verify_request_from(&right, guesser),
+            Err(AuthError::Banned(1..=60))
+        ));
+        assert_eq!(auth.verify_request_from(&right, other), Ok(()));
+        assert_eq!(auth.bans().len(), 1);
+        assert_eq!(auth.bans()[0].ip, guesser);
+
+        assert!(auth.unban(guesser));
+        assert_eq!(auth.verify_request_from(&right, guesser), Ok(()));
+    }
+}
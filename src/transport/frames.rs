@@ -0,0 +1,165 @@
+//! Splitting of oversized frames. Some clients cap the length of a line and
+//! silently truncate anything longer, so on stdio a tool result whose
+//! response would exceed the frame limit is replaced by a result of
+//! `resource_link` blocks. Each link names one part of the original result's
+//! JSON, stored on the session and readable with `resources/read`; the parts
+//! concatenated in order are the original `result`.
+
+use serde_json::{json, Value};
+
+use crate::session::Session;
+use crate::tools::content;
+
+/// Scheme of the URIs under which parts are stored.
+pub const SCHEME: &str = "frame://";
+
+/// Smallest limit honoured, so parts are large enough that few are needed.
+pub const MIN_FRAME_BYTES: usize = 4096;
+
+/// Parts a session keeps, across all its split results; the oldest are
+/// evicted first.
+pub const MAX_STORED_PARTS: usize = 4096;
+/// Total size of the parts a session keeps.
+pub const MAX_STORED_BYTES: usize = 128 << 20;
+
+/// Room left in each part's frame for the `resources/read` envelope around
+/// it: the JSON-RPC fields, the URI and the MIME type.
+const ENVELOPE_BYTES: usize = 256;
+
+/// Frame limit for stdio from `MCP_STDIO_MAX_FRAME_BYTES`; unset or zero
+/// disables splitting. Limits below [`MIN_FRAME_BYTES`] are raised to it.
+pub fn max_frame_from_env() -> Option<usize> {
+    std::env::var("MCP_STDIO_MAX_FRAME_BYTES")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|&bytes| bytes > 0)
+        .map(|bytes: usize| bytes.max(MIN_FRAME_BYTES))
+}
+
+/// `message` serialized for the wire, with any tool result too long for
+/// `limit` replaced by links to its parts. A batch is split entry by entry.
+/// Messages other than tool results are never split.
+pub fn encode(session: &Session, message: Value, limit: usize) -> String {
+    let line = message.to_string();
+    if line.len() <= limit {
+        return line;
+    }
+    match message {
+        Value::Array(entries) => Value::Array(
+            entries
+                .into_iter()
+                .map(|entry| split(session, entry, limit))
+                .collect(),
+        )
+        .to_string(),
+        message => split(session, message, limit).to_string(),
+    }
+}
+
+fn split(session: &Session, mut response: Value, limit: usize) -> Value {
+    let Some(result) = response.get_mut("result") else {
+        return response;
+    };
+    if !result.get("content").is_some_and(Value::is_array) {
+        return response;
+    }
+    let text = result.to_string();
+    if text.len() <= limit {
+        return response;
+    }
+
+    let id = uuid::Uuid::new_v4();
+    let parts = chunks(&text, limit.max(MIN_FRAME_BYTES) - ENVELOPE_BYTES);
+    let count = parts.len();
+    let mut content = vec![json!({
+        "type": "text",
+        "text": format!(
+            "The result is {} bytes, over this connection's frame limit, so it was split into {} parts. \
+             Read each resource below in order and concatenate their text to get the result's JSON.",
+            text.len(),
+            count
+        ),
+    })];
+    for (index, part) in parts.into_iter().enumerate() {
+        let uri = format!("{}{}/{}", SCHEME, id, index + 1);
+        let name = format!("Part {} of {}", index + 1, count);
+        content.push(content::resource_link(&uri, name, Some("application/json")));
+        session.add_frame_part(uri, part);
+    }
+    let is_error = result.get("isError").cloned().unwrap_or(json!(false));
+    *result = json!({ "content": content, "isError": is_error });
+    response
+}
+
+/// `text` cut on character boundaries into pieces whose JSON string
+/// encodings are at most `budget` bytes each.
+fn chunks(text: &str, budget: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut part = String::new();
+    let mut encoded = 0;
+    for c in text.chars() {
+        let size = match c {
+            '"' | '\\' | '\n' | '\r' | '\t' | '\u{8}' | '\u{c}' => 2,
+            c if (c as u32) < 0x20 => 6,
+            c => c.len_utf8(),
+        };
+        if encoded + size > budget && !part.is_empty() {
+            parts.push(std::mem::take(&mut part));
+            encoded = 0;
+        }
+        part.push(c);
+        encoded += size;
+    }
+    if !part.is_empty() {
+        parts.push(part);
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chunks_fit_the_budget_once_encoded() {
+        let text = "a\"b\\c\u{1}é".repeat(100);
+        let parts = chunks(&text, 64);
+        assert!(parts.len() > 1);
+        assert_eq!(parts.concat(), text);
+        for part in &parts {
+            assert!(Value::String(part.clone()).to_string().len() <= 64 + 2);
+        }
+    }
+
+    #[test]
+    fn test_only_oversized_tool_results_are_split() {
+        let session = Session::new();
+        let small = json!({"jsonrpc": "2.0", "id": 1, "result": {"content": []}});
+        assert_eq!(encode(&session, small.clone(), 4096), small.to_string());
+
+        let listing = json!({"jsonrpc": "2.0", "id": 2, "result": {"tools": ["x".repeat(8192)]}});
+        assert_eq!(encode(&session, listing.clone(), 4096), listing.to_string());
+
+        let result =
+            json!({"content": [{"type": "text", "text": "y".repeat(10000)}], "isError": false});
+        let response = json!({"jsonrpc": "2.0", "id": 3, "result": result});
+        let line = encode(&session, response, 4096);
+        assert!(line.len() <= 4096);
+
+        let split: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(split["id"], 3);
+        let links: Vec<&str> = split["result"]["content"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|block| block["type"] == "resource_link")
+            .map(|block| block["uri"].as_str().unwrap())
+            .collect();
+        assert_eq!(links.len(), 3);
+        let joined: String = links
+            .iter()
+            .map(|uri| session.frame_part(uri).unwrap())
+            .collect();
+        assert_eq!(serde_json::from_str::<Value>(&joined).unwrap(), result);
+    }
+}
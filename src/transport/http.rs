@@ -0,0 +1,980 @@
+//! Streamable HTTP transport (MCP 2025-03-26): JSON-RPC requests are POSTed
+//! to a single endpoint, and a GET on the same endpoint opens an SSE stream
+//! for server-initiated messages.
+//!
+//! `initialize` creates a session whose id is returned in `Mcp-Session-Id`;
+//! every later request must carry it. A DELETE ends the session. Files
+//! POSTed to `/mcp/uploads?name=<file>` become resources of the session.
+//! Signed deliveries POSTed to `/hooks/<name>` notify subscribed sessions;
+//! see [`hooks`](crate::hooks).
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast;
+
+use super::access::ConnectionLimiter;
+use super::auth;
+use super::signing::{self, RequestSigning};
+use super::trace::Direction;
+use super::Incoming;
+use crate::artifacts::{self, DownloadError};
+use crate::hooks::{self, HookError};
+use crate::http::server::{self, Request, Response};
+use crate::protocol::SUPPORTED_PROTOCOL_VERSIONS;
+use crate::security::{SecurityEvent, SecurityEventKind};
+use crate::session::{Ping, Session};
+use crate::uploads::{UploadError, UploadPolicy};
+use crate::McpServer;
+
+pub const DEFAULT_BIND: &str = "127.0.0.1:8080";
+pub const ENDPOINT: &str = "/mcp";
+pub const UPLOAD_ENDPOINT: &str = "/mcp/uploads";
+pub const SESSION_HEADER: &str = "Mcp-Session-Id";
+pub const PROTOCOL_VERSION_HEADER: &str = "MCP-Protocol-Version";
+
+/// Used when `MCP_HTTP_SESSION_IDLE_SECS` is unset.
+pub const DEFAULT_SESSION_IDLE_SECS: u64 = 1800;
+
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// An open session and the channel for messages to its SSE streams.
+struct OpenSession {
+    session: Arc<Session>,
+    messages: broadcast::Sender<Value>,
+    /// The session's last request, or the end of its last stream.
+    last_seen: Instant,
+}
+
+struct State {
+    server: Arc<McpServer>,
+    /// Open sessions, by id.
+    sessions: Mutex<HashMap<String, OpenSession>>,
+    allowed_origins: Vec<String>,
+    signing: Option<RequestSigning>,
+    uploads: UploadPolicy,
+    /// How long a session's stream may sit idle before the server pings.
+    ping_interval: Option<Duration>,
+    /// How long a session without requests or an open stream is kept.
+    session_idle: Option<Duration>,
+}
+
+/// Idle time after which a session is ended, from
+/// `MCP_HTTP_SESSION_IDLE_SECS`; `0` keeps sessions until they are
+/// deleted. A malformed value is reported and ignored.
+pub fn session_idle_from_env() -> Option<Duration> {
+    let secs = match std::env::var("MCP_HTTP_SESSION_IDLE_SECS") {
+        Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+            tracing::warn!("Invalid MCP_HTTP_SESSION_IDLE_SECS '{}'", value);
+            DEFAULT_SESSION_IDLE_SECS
+        }),
+        Err(_) => DEFAULT_SESSION_IDLE_SECS,
+    };
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Listens on `addr` and serves until the listener fails.
+pub async fn serve(
+    server: Arc<McpServer>,
+    addr: SocketAddr,
+    limiter: Arc<ConnectionLimiter>,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!("Listening on http://{}{}", listener.local_addr()?, ENDPOINT);
+    serve_listener(server, listener, limiter).await
+}
+
+/// Serves connections from an already bound listener.
+pub async fn serve_listener(
+    server: Arc<McpServer>,
+    listener: TcpListener,
+    limiter: Arc<ConnectionLimiter>,
+) -> io::Result<()> {
+    let state = Arc::new(State {
+        server,
+        sessions: Mutex::new(HashMap::new()),
+        allowed_origins: server::allowed_origins_from_env(),
+        signing: RequestSigning::from_env()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+        uploads: UploadPolicy::from_env(),
+        ping_interval: super::ping_interval_from_env(),
+        session_idle: session_idle_from_env(),
+    });
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        // Refused connections are closed by dropping the stream.
+        let Ok(permit) = limiter.admit(peer.ip()) else {
+            continue;
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            if let Err(e) = handle_connection(state, stream, peer).await {
+                tracing::warn!("HTTP connection failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    state: Arc<State>,
+    stream: TcpStream,
+    peer: SocketAddr,
+) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    loop {
+        let request = match server::read_request(&mut reader).await {
+            Ok(Some(request)) => request,
+            Ok(None) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                return Response::text(400, &e.to_string())
+                    .write_to(&mut writer, false)
+                    .await;
+            }
+            Err(e) => return Err(e),
+        };
+        let keep_alive = request.keep_alive();
+
+        let response = match refusal(&state, &request, peer) {
+            Some(response) => response,
+            None if request.method == "GET" && request.path() == ENDPOINT => {
+                // The stream owns the rest of the connection.
+                return match stream_session(&state, &request) {
+                    Ok((session, messages)) => {
+                        let result = write_stream(&mut writer, &state, &session, messages).await;
+                        // Idle time counts from the end of the stream.
+                        if let Some(open) = state.sessions.lock().unwrap().get_mut(session.id()) {
+                            open.last_seen = Instant::now();
+                        }
+                        result
+                    }
+                    Err(response) => response.write_to(&mut writer, false).await,
+                };
+            }
+            None => route(&state, request, peer).await,
+        };
+        response.write_to(&mut writer, keep_alive).await?;
+        if !keep_alive {
+            return Ok(());
+        }
+    }
+}
+
+/// The response refusing `request` when it is for another path, comes from
+/// a disallowed origin, names a protocol revision the server does not
+/// speak, lacks a required token or, with signing enabled, is not validly
+/// signed. Artifact downloads carry their own signature in the
+/// URL and inbound hooks an HMAC of their body, so neither tokens nor
+/// request signing apply to them.
+fn refusal(state: &State, request: &Request, peer: SocketAddr) -> Option<Response> {
+    if request.path().starts_with(hooks::PATH) {
+        return None;
+    }
+    let download = request.path().starts_with(artifacts::DOWNLOAD_PATH);
+    if ![ENDPOINT, UPLOAD_ENDPOINT].contains(&request.path()) && !download {
+        return Some(Response::text(404, "Not found"));
+    }
+    if !server::origin_allowed(request, &state.allowed_origins) {
+        state
+            .server
+            .security()
+            .report(server::origin_denial(request, peer));
+        return Some(Response::text(403, "Origin not allowed"));
+    }
+    if download {
+        return None;
+    }
+    if let Some(version) = request.header(PROTOCOL_VERSION_HEADER) {
+        if !SUPPORTED_PROTOCOL_VERSIONS.contains(&version) {
+            return Some(Response::text(400, "Unsupported MCP-Protocol-Version"));
+        }
+    }
+    if let Some(Err(e)) = state
+        .server
+        .token_auth()
+        .map(|auth| auth.verify_request_from(request, peer.ip()))
+    {
+        state
+            .server
+            .security()
+            .report(auth::auth_failure("http", peer, e));
+        return Some(e.response());
+    }
+    if let Err(e) = state.signing.as_ref()?.verify(request) {
+        state
+            .server
+            .security()
+            .report(signing::signature_failure(request, peer, e));
+        return Some(Response::text(401, e.message()));
+    }
+    None
+}
+
+async fn route(state: &State, request: Request, peer: SocketAddr) -> Response {
+    if let Some(name) = request.path().strip_prefix(hooks::PATH) {
+        return match request.method.as_str() {
+            "POST" => deliver_hook(state, name, &request, peer),
+            _ => Response::text(405, "Method not allowed").header("Allow", "POST"),
+        };
+    }
+    if let Some(id) = request.path().strip_prefix(artifacts::DOWNLOAD_PATH) {
+        return match request.method.as_str() {
+            "GET" => download_artifact(state, id, &request, peer),
+            _ => Response::text(405, "Method not allowed").header("Allow", "GET"),
+        };
+    }
+    if request.path() == UPLOAD_ENDPOINT {
+        return match request.method.as_str() {
+            "POST" => handle_upload(state, request, peer).await,
+            _ => Response::text(405, "Method not allowed").header("Allow", "POST"),
+        };
+    }
+    match request.method.as_str() {
+        "POST" => match state.server.wire_trace() {
+            Some(trace) => {
+                let session = request.header(SESSION_HEADER).map(str::to_string);
+                trace.record(session.as_deref(), Direction::In, &request.body);
+                let response = handle_post(state, request).await;
+                // A new session's id comes back with its `initialize` result.
+                let session = session.or_else(|| {
+                    response
+                        .headers
+                        .iter()
+                        .find(|(name, _)| name == SESSION_HEADER)
+                        .map(|(_, id)| id.clone())
+                });
+                if response
+                    .headers
+                    .iter()
+                    .any(|(name, value)| name == "Content-Type" && value == "application/json")
+                {
+                    trace.record(session.as_deref(), Direction::Out, &response.body);
+                }
+                response
+            }
+            None => handle_post(state, request).await,
+        },
+        "DELETE" => match session(state, &request) {
+            Ok(session) => {
+                state.sessions.lock().unwrap().remove(session.id());
+                // Its sender would otherwise keep open streams alive.
+                session.clear_notifier();
+                Response::new(204)
+            }
+            Err(response) => response,
+        },
+        _ => Response::text(405, "Method not allowed").header("Allow", "GET, POST, DELETE"),
+    }
+}
+
+async fn handle_post(state: &State, request: Request) -> Response {
+    if !request
+        .header("Content-Type")
+        .is_some_and(|t| t.starts_with("application/json"))
+    {
+        return Response::text(415, "Content-Type must be application/json");
+    }
+    let message = match Incoming::parse(&request.body) {
+        Ok(Incoming::Single(message)) => message,
+        Ok(Incoming::Batch(batch)) => {
+            // `initialize` must be sent on its own, so a batch always needs
+            // an established session.
+            let session = match session(state, &request) {
+                Ok(session) => session,
+                Err(response) => return response,
+            };
+            return match super::handle_batch(&state.server, &session, batch).await {
+                Some(responses) => Response::json(200, &responses),
+                None => Response::new(202),
+            };
+        }
+        Ok(Incoming::Response(response)) => {
+            // The client answering a ping or another server request.
+            return match session(state, &request) {
+                Ok(session) => {
+                    session.receive_response(response);
+                    Response::new(202)
+                }
+                Err(response) => response,
+            };
+        }
+        Err(malformed) => return Response::json(400, &malformed.response()),
+    };
+
+    let (session, created) = if message.method == "initialize" {
+        let session = Arc::new(Session::new().accepting_uploads());
+        let (messages, _) = broadcast::channel(64);
+        // Notifications reach the client only while a GET stream is open.
+        let notifications = messages.clone();
+        session.set_notifier(move |message| notifications.send(message).is_ok());
+        let open = OpenSession {
+            session: session.clone(),
+            messages,
+            last_seen: Instant::now(),
+        };
+        let mut sessions = state.sessions.lock().unwrap();
+        expire_idle(state, &mut sessions);
+        sessions.insert(session.id().to_string(), open);
+        drop(sessions);
+        (session, true)
+    } else {
+        match session(state, &request) {
+            Ok(session) => (session, false),
+            Err(response) => return response,
+        }
+    };
+
+    let Some(response) = super::dispatch(&state.server, &session, message).await else {
+        return Response::new(202);
+    };
+
+    // A shed call is a 503, and a rate-limited one a 429, so proxies and
+    // plain HTTP clients back off too.
+    let status = match response.error.as_ref().map(|e| e.code) {
+        Some(crate::overload::OVERLOADED) => 503,
+        Some(crate::tools::rate_limits::RATE_LIMITED) => 429,
+        _ => 200,
+    };
+    let retry_after = response
+        .error
+        .as_ref()
+        .filter(|_| status != 200)
+        .and_then(|e| e.data.as_ref()?["retryAfterSecs"].as_u64());
+    let response = match retry_after {
+        Some(secs) => Response::json(status, &response).header("Retry-After", secs.to_string()),
+        None => Response::json(200, &response),
+    };
+    if created {
+        response.header(SESSION_HEADER, session.id())
+    } else {
+        response
+    }
+}
+
+/// Serves an artifact from its signed URL. The link is the credential, so
+/// no session is needed; bad signatures are reported as security events.
+fn download_artifact(state: &State, id: &str, request: &Request, peer: SocketAddr) -> Response {
+    let client = peer.ip().to_string();
+    match state.server.download_artifact(
+        id,
+        request.query("expires"),
+        request.query("sig"),
+        &client,
+    ) {
+        Ok((artifact, contents)) => Response {
+            body: contents,
+            ..Response::new(200)
+        }
+        .header("Content-Type", artifact.mime_type)
+        .header(
+            "Content-Disposition",
+            format!(
+                "attachment; filename=\"{}\"",
+                artifact.name.replace('"', "")
+            ),
+        ),
+        Err(DownloadError::NotFound) => Response::text(404, "Artifact not found"),
+        Err(DownloadError::Forbidden) => {
+            state.server.security().report(
+                SecurityEvent::new(SecurityEventKind::AuthFailure, "Invalid artifact link")
+                    .rule("artifact_signature")
+                    .source_ip(peer.ip())
+                    .detail("id", id),
+            );
+            Response::text(403, "Invalid or expired artifact link")
+        }
+        Err(DownloadError::Withheld) => Response::text(403, "Artifact withheld by policy"),
+    }
+}
+
+/// Hands a delivery to the inbound hook `name` and answers 202 with the
+/// number of sessions notified. Bad signatures are reported as security
+/// events.
+fn deliver_hook(state: &State, name: &str, request: &Request, peer: SocketAddr) -> Response {
+    let signature = request.header(hooks::SIGNATURE_HEADER);
+    match state.server.deliver_hook(name, &request.body, signature) {
+        Ok(delivered) => Response::json(202, &serde_json::json!({ "delivered": delivered })),
+        Err(e) => {
+            if e == HookError::BadSignature {
+                state.server.security().report(
+                    SecurityEvent::new(SecurityEventKind::AuthFailure, e.message())
+                        .rule("hook_signature")
+                        .source_ip(peer.ip())
+                        .detail("hook", name),
+                );
+            }
+            Response::text(e.status(), e.message())
+        }
+    }
+}
+
+/// Stores the body as an upload named by the `name` query parameter and
+/// answers 201 with its resource entry. Files the scanner refuses are
+/// reported as security events.
+async fn handle_upload(state: &State, request: Request, peer: SocketAddr) -> Response {
+    let session = match session(state, &request) {
+        Ok(session) => session,
+        Err(response) => return response,
+    };
+    let Some(name) = request.query("name") else {
+        return Response::text(400, "Missing name");
+    };
+    let mime_type = request
+        .header("Content-Type")
+        .unwrap_or("application/octet-stream");
+    match state
+        .uploads
+        .accept(&session, name, mime_type, &request.body)
+        .await
+    {
+        Ok(upload) => Response::json(201, &upload),
+        Err(e) => {
+            if let UploadError::Rejected(reason) = &e {
+                state.server.security().report(
+                    SecurityEvent::new(SecurityEventKind::PolicyDenied, e.message())
+                        .rule("upload_scan")
+                        .source_ip(peer.ip())
+                        .detail("name", name)
+                        .detail("reason", reason.as_str()),
+                );
+            }
+            Response::text(e.status(), &e.message())
+        }
+    }
+}
+
+/// The session named by the request's `Mcp-Session-Id`, or the error
+/// response when it is missing (400) or unknown (404).
+fn session(state: &State, request: &Request) -> Result<Arc<Session>, Response> {
+    let id = request
+        .header(SESSION_HEADER)
+        .ok_or_else(|| Response::text(400, "Missing Mcp-Session-Id header"))?;
+    match state.sessions.lock().unwrap().get_mut(id) {
+        Some(open) => {
+            open.last_seen = Instant::now();
+            Ok(open.session.clone())
+        }
+        None => Err(Response::text(404, "Session not found")),
+    }
+}
+
+/// Ends sessions idle for longer than the session idle time, as a DELETE
+/// would. A session with a stream open is never idle. Run as each session
+/// is created, so abandoned sessions cannot pile up.
+fn expire_idle(state: &State, sessions: &mut HashMap<String, OpenSession>) {
+    let Some(idle) = state.session_idle else {
+        return;
+    };
+    sessions.retain(|id, open| {
+        let expired = open.messages.receiver_count() == 0 && open.last_seen.elapsed() > idle;
+        if expired {
+            tracing::debug!("Ending idle HTTP session {}", id);
+            open.session.clear_notifier();
+        }
+        !expired
+    });
+}
+
+fn stream_session(
+    state: &State,
+    request: &Request,
+) -> Result<(Arc<Session>, broadcast::Receiver<Value>), Response> {
+    if !request
+        .header("Accept")
+        .is_some_and(|a| a.contains("text/event-stream"))
+    {
+        return Err(Response::text(406, "Accept must include text/event-stream"));
+    }
+    let id = request
+        .header(SESSION_HEADER)
+        .ok_or_else(|| Response::text(400, "Missing Mcp-Session-Id header"))?;
+    // Looked up once, since a DELETE may end the session at any moment.
+    match state.sessions.lock().unwrap().get(id) {
+        Some(open) => Ok((open.session.clone(), open.messages.subscribe())),
+        None => Err(Response::text(404, "Session not found")),
+    }
+}
+
+/// Forwards session messages as SSE events until the session is deleted or
+/// the client goes away, sending a comment periodically to keep proxies from
+/// closing an idle stream. With a ping interval set, an idle client is sent
+/// `ping` requests instead, and the stream ends when one goes unanswered.
+async fn write_stream<W>(
+    writer: &mut W,
+    state: &State,
+    session: &Session,
+    mut messages: broadcast::Receiver<Value>,
+) -> io::Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    let ping_interval = state.ping_interval;
+    server::write_event_stream_head(writer, &[]).await?;
+    let mut keepalive = tokio::time::interval(
+        ping_interval.map_or(KEEPALIVE_INTERVAL, |p| p.min(KEEPALIVE_INTERVAL)),
+    );
+    keepalive.tick().await;
+    loop {
+        tokio::select! {
+            message = messages.recv() => match message {
+                Ok(message) => {
+                    let message = message.to_string();
+                    super::trace(&state.server, Some(session.id()), Direction::Out, message.as_bytes());
+                    server::write_event(writer, Some("message"), &message).await?
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return Ok(()),
+            },
+            _ = keepalive.tick() => match ping_interval.map(|p| session.ping_due(p)) {
+                Some(Ping::Send(ping)) => {
+                    let ping = ping.to_string();
+                    super::trace(&state.server, Some(session.id()), Direction::Out, ping.as_bytes());
+                    server::write_event(writer, Some("message"), &ping).await?
+                }
+                Some(Ping::Unanswered) => return Ok(()),
+                _ => {
+                    use tokio::io::AsyncWriteExt;
+                    writer.write_all(b": keepalive\n\n").await?;
+                    writer.flush().await?;
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt};
+
+    async fn start() -> SocketAddr {
+        start_with(McpServer::new()).await
+    }
+
+    async fn start_with(server: McpServer) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_listener(Arc::new(server), listener, Arc::default()));
+        addr
+    }
+
+    /// Sends one request on a fresh connection and returns status, headers
+    /// and body.
+    async fn send(
+        addr: SocketAddr,
+        method: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> (u16, String, String) {
+        send_to(addr, method, ENDPOINT, headers, body).await
+    }
+
+    async fn send_to(
+        addr: SocketAddr,
+        method: &str,
+        target: &str,
+        headers: &[(&str, &str)],
+        body: &str,
+    ) -> (u16, String, String) {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let mut request = format!(
+            "{} {} HTTP/1.1\r\nHost: x\r\nConnection: close\r\nContent-Length: {}\r\n",
+            method,
+            target,
+            body.len()
+        );
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        request.push_str(body);
+        stream.write_all(request.as_bytes()).await.unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, head.to_string(), body.to_string())
+    }
+
+    fn session_id(head: &str) -> String {
+        head.lines()
+            .find_map(|l| l.strip_prefix("Mcp-Session-Id: "))
+            .unwrap()
+            .to_string()
+    }
+
+    const JSON: (&str, &str) = ("Content-Type", "application/json");
+
+    #[tokio::test]
+    async fn test_session_lifecycle() {
+        let addr = start().await;
+
+        let (status, head, body) = send(
+            addr,
+            "POST",
+            &[JSON],
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+        )
+        .await;
+        assert_eq!(status, 200);
+        let response: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(response["result"]["serverInfo"]["name"], "mcp-server");
+        let session = session_id(&head);
+
+        let call = r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"echo","arguments":{"text":"hi"}}}"#;
+        let (status, _, _) = send(addr, "POST", &[JSON], call).await;
+        assert_eq!(status, 400);
+        let (status, _, _) = send(addr, "POST", &[JSON, (SESSION_HEADER, "nope")], call).await;
+        assert_eq!(status, 404);
+
+        let (status, _, body) = send(addr, "POST", &[JSON, (SESSION_HEADER, &session)], call).await;
+        assert_eq!(status, 200);
+        let response: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(response["error"]["code"], -32600);
+
+        let notification = r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#;
+        let (status, _, body) = send(
+            addr,
+            "POST",
+            &[JSON, (SESSION_HEADER, &session)],
+            notification,
+        )
+        .await;
+        assert_eq!((status, body.as_str()), (202, ""));
+
+        let (status, _, body) = send(addr, "POST", &[JSON, (SESSION_HEADER, &session)], call).await;
+        assert_eq!(status, 200);
+        let response: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(response["result"]["content"][0]["text"], "Echo: hi");
+
+        let batch = format!(
+            "[{},{}]",
+            call, r#"{"jsonrpc":"2.0","method":"notifications/cancelled"}"#
+        );
+        let (status, _, body) =
+            send(addr, "POST", &[JSON, (SESSION_HEADER, &session)], &batch).await;
+        assert_eq!(status, 200);
+        let responses: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(responses.as_array().unwrap().len(), 1);
+        assert_eq!(responses[0]["id"], 2);
+
+        let (status, _, _) = send(addr, "DELETE", &[(SESSION_HEADER, &session)], "").await;
+        assert_eq!(status, 204);
+        let (status, _, _) = send(addr, "POST", &[JSON, (SESSION_HEADER, &session)], call).await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_requests() {
+        let addr = start().await;
+
+        let (status, _, body) = send(addr, "POST", &[JSON], "{not json").await;
+        assert_eq!(status, 400);
+        let response: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(response["error"]["code"], -32700);
+
+        let (status, _, body) = send(addr, "POST", &[JSON], r#"{"id":1}"#).await;
+        assert_eq!(status, 400);
+        let response: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(response["error"]["code"], -32600);
+
+        let (status, _, _) = send(addr, "POST", &[("Content-Type", "text/plain")], "{}").await;
+        assert_eq!(status, 415);
+
+        let initialize = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+        let (status, _, _) = send(
+            addr,
+            "POST",
+            &[JSON, ("Origin", "http://evil.example")],
+            initialize,
+        )
+        .await;
+        assert_eq!(status, 403);
+        let (status, _, _) = send(
+            addr,
+            "POST",
+            &[JSON, ("Origin", "http://localhost:3000")],
+            initialize,
+        )
+        .await;
+        assert_eq!(status, 200);
+
+        let (status, _, _) = send(
+            addr,
+            "POST",
+            &[JSON, (PROTOCOL_VERSION_HEADER, "2023-01-01")],
+            initialize,
+        )
+        .await;
+        assert_eq!(status, 400);
+
+        let (status, _, _) = send(addr, "PUT", &[], "").await;
+        assert_eq!(status, 405);
+    }
+
+    #[test]
+    fn test_idle_sessions_expire_unless_streaming() {
+        let state = State {
+            server: Arc::new(McpServer::new()),
+            sessions: Mutex::new(HashMap::new()),
+            allowed_origins: Vec::new(),
+            signing: None,
+            uploads: UploadPolicy::from_env(),
+            ping_interval: None,
+            session_idle: Some(Duration::from_millis(10)),
+        };
+        let open = |last_seen| {
+            let (messages, _) = broadcast::channel(1);
+            OpenSession {
+                session: Arc::new(Session::new()),
+                messages,
+                last_seen,
+            }
+        };
+        let stale = Instant::now() - Duration::from_secs(60);
+        let mut sessions = state.sessions.lock().unwrap();
+        sessions.insert("abandoned".to_string(), open(stale));
+        sessions.insert("recent".to_string(), open(Instant::now()));
+        let streaming = open(stale);
+        let _stream = streaming.messages.subscribe();
+        sessions.insert("streaming".to_string(), streaming);
+
+        expire_idle(&state, &mut sessions);
+        let mut left: Vec<&str> = sessions.keys().map(String::as_str).collect();
+        left.sort();
+        assert_eq!(left, ["recent", "streaming"]);
+    }
+
+    #[tokio::test]
+    async fn test_requires_a_token_when_configured() {
+        let mut server = McpServer::new();
+        server.set_token_auth(auth::TokenAuth::new(["s3cret"]));
+        let addr = start_with(server).await;
+        let initialize = r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#;
+
+        let (status, head, _) = send(addr, "POST", &[JSON], initialize).await;
+        assert_eq!(status, 401);
+        assert!(head.starts_with("HTTP/1.1 401 Unauthorized\r\n"));
+        assert!(head.contains("WWW-Authenticate: Bearer"));
+        let (status, _, _) = send(
+            addr,
+            "POST",
+            &[JSON, ("Authorization", "Bearer wrong")],
+            initialize,
+        )
+        .await;
+        assert_eq!(status, 401);
+        let (status, _, body) = send(
+            addr,
+            "POST",
+            &[JSON, ("Authorization", "Bearer s3cret")],
+            initialize,
+        )
+        .await;
+        assert_eq!(status, 200);
+        assert!(body.contains("serverInfo"));
+        let (status, _, _) = send(
+            addr,
+            "POST",
+            &[JSON, (auth::API_KEY_HEADER, "s3cret")],
+            initialize,
+        )
+        .await;
+        assert_eq!(status, 200);
+
+        // Wrong tokens get the address banned, right token or not.
+        for _ in 0..auth::DEFAULT_MAX_FAILURES {
+            let wrong = [JSON, ("Authorization", "Bearer guess")];
+            assert_eq!(send(addr, "POST", &wrong, initialize).await.0, 401);
+        }
+        let right = [JSON, ("Authorization", "Bearer s3cret")];
+        let (status, head, _) = send(addr, "POST", &right, initialize).await;
+        assert_eq!(status, 429);
+        assert!(head.contains("Retry-After: "), "{}", head);
+    }
+
+    #[tokio::test]
+    async fn test_uploads_become_session_resources() {
+        let addr = start().await;
+        let (_, head, body) = send(
+            addr,
+            "POST",
+            &[JSON],
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+        )
+        .await;
+        let response: Value = serde_json::from_str(&body).unwrap();
+        assert!(response["result"]["capabilities"]["resources"].is_object());
+        let session = session_id(&head);
+        let headers = [
+            (SESSION_HEADER, session.as_str()),
+            ("Content-Type", "text/csv"),
+        ];
+
+        let target = format!("{}?name=sales.csv", UPLOAD_ENDPOINT);
+        let (status, _, body) = send_to(addr, "POST", &target, &headers, "q,total\n1,10\n").await;
+        assert_eq!(status, 201);
+        let upload: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(upload["uri"], "workspace://uploads/sales.csv");
+        assert_eq!(upload["size"], 13);
+
+        let (status, _, _) = send_to(addr, "POST", UPLOAD_ENDPOINT, &headers, "x").await;
+        assert_eq!(status, 400);
+        let binary = [
+            (SESSION_HEADER, session.as_str()),
+            ("Content-Type", "application/x-msdownload"),
+        ];
+        let (status, _, _) = send_to(addr, "POST", &target, &binary, "MZ").await;
+        assert_eq!(status, 415);
+        let (status, _, _) =
+            send_to(addr, "POST", &target, &[("Content-Type", "text/csv")], "x").await;
+        assert_eq!(status, 400);
+
+        let session = [JSON, (SESSION_HEADER, session.as_str())];
+        send(
+            addr,
+            "POST",
+            &session,
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+        )
+        .await;
+        let (_, _, body) = send(
+            addr,
+            "POST",
+            &session,
+            r#"{"jsonrpc":"2.0","id":2,"method":"resources/list","params":{}}"#,
+        )
+        .await;
+        let listed: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(listed["result"]["resources"][0]["name"], "sales.csv");
+        assert_eq!(listed["result"]["resources"][0]["mimeType"], "text/csv");
+        let (_, _, body) = send(
+            addr,
+            "POST",
+            &session,
+            r#"{"jsonrpc":"2.0","id":3,"method":"resources/read","params":{"uri":"workspace://uploads/sales.csv"}}"#,
+        )
+        .await;
+        let read: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(read["result"]["contents"][0]["text"], "q,total\n1,10\n");
+    }
+
+    #[tokio::test]
+    async fn test_get_opens_event_stream() {
+        let addr = start().await;
+        let (_, head, _) = send(
+            addr,
+            "POST",
+            &[JSON],
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+        )
+        .await;
+        let session = session_id(&head);
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                format!(
+                    "GET {} HTTP/1.1\r\nHost: x\r\nAccept: text/event-stream\r\n{}: {}\r\n\r\n",
+                    ENDPOINT, SESSION_HEADER, session
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut status = String::new();
+        reader.read_line(&mut status).await.unwrap();
+        assert_eq!(status, "HTTP/1.1 200 OK\r\n");
+        let mut content_type = String::new();
+        reader.read_line(&mut content_type).await.unwrap();
+        assert_eq!(content_type, "Content-Type: text/event-stream\r\n");
+    }
+
+    #[tokio::test]
+    async fn test_hooks_notify_subscribed_sessions() {
+        let mut server = McpServer::new();
+        server.set_inbound_hooks(
+            hooks::InboundHooks::new(vec![hooks::InboundHook {
+                name: "docs".to_string(),
+                secret: "s3cret".to_string(),
+                notification: None,
+                resources: Vec::new(),
+            }])
+            .unwrap(),
+        );
+        let addr = start_with(server).await;
+        let (_, head, _) = send(
+            addr,
+            "POST",
+            &[JSON],
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":{}}"#,
+        )
+        .await;
+        let session = session_id(&head);
+        let headers = [JSON, (SESSION_HEADER, session.as_str())];
+        send(
+            addr,
+            "POST",
+            &headers,
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+        )
+        .await;
+        let (_, _, body) = send(
+            addr,
+            "POST",
+            &headers,
+            r#"{"jsonrpc":"2.0","id":2,"method":"resources/subscribe","params":{"uri":"hook://docs"}}"#,
+        )
+        .await;
+        assert!(body.contains(r#""result""#), "{}", body);
+
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(
+                format!(
+                    "GET {} HTTP/1.1\r\nHost: x\r\nAccept: text/event-stream\r\n{}: {}\r\n\r\n",
+                    ENDPOINT, SESSION_HEADER, session
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        while line != "\r\n" {
+            line.clear();
+            reader.read_line(&mut line).await.unwrap();
+        }
+
+        let target = format!("{}docs", hooks::PATH);
+        let body = r#"{"release":"1.4"}"#;
+        let (status, _, _) = send_to(addr, "POST", &target, &[JSON], body).await;
+        assert_eq!(status, 401);
+        let signature = crate::webhooks::sign("s3cret", body.as_bytes());
+        let signed = [JSON, (hooks::SIGNATURE_HEADER, signature.as_str())];
+        let (status, _, _) = send_to(addr, "POST", "/hooks/other", &signed, body).await;
+        assert_eq!(status, 404);
+        let (status, _, response) = send_to(addr, "POST", &target, &signed, body).await;
+        assert_eq!(status, 202);
+        assert_eq!(response, r#"{"delivered":1}"#);
+
+        loop {
+            line.clear();
+            reader.read_line(&mut line).await.unwrap();
+            if let Some(data) = line.strip_prefix("data: ") {
+                let message: Value = serde_json::from_str(data).unwrap();
+                assert_eq!(message["method"], "notifications/resources/updated");
+                assert_eq!(message["params"]["uri"], "hook://docs");
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,1508 @@
+//! Transports connecting an [`McpServer`] to clients.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncWrite, AsyncWriteExt};
+use tokio::sync::{mpsc, Semaphore};
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::protocol::{McpRequest, McpResponse};
+use crate::session::{Ping, Session};
+use crate::McpServer;
+
+pub mod access;
+pub mod auth;
+pub mod frames;
+pub mod http;
+pub mod signing;
+pub mod sse;
+pub mod stdio;
+pub mod stream;
+pub mod tcp;
+pub mod trace;
+#[cfg(all(feature = "uring", target_os = "linux"))]
+pub mod uring;
+
+/// Lines buffered for a client that is slow to read.
+const OUTBOX_CAPACITY: usize = 64;
+/// Requests one connection has in flight before reading pauses until one
+/// finishes.
+const MAX_IN_FLIGHT: usize = 32;
+
+/// One incoming JSON-RPC message: a single request or notification, a
+/// batch of them, or the client's response to a server-initiated request
+/// such as `ping` or `sampling/createMessage`. Serializes back to the
+/// message it was read from.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Incoming {
+    Single(McpRequest),
+    Batch(Vec<Value>),
+    Response(McpResponse),
+}
+
+/// Why a message could not be read.
+#[derive(Debug)]
+pub enum Malformed {
+    /// Not JSON at all (`-32700`).
+    Parse(String),
+    /// JSON, but not a request object or array (`-32600`).
+    InvalidRequest(String),
+}
+
+impl Malformed {
+    /// The error reply, with a null id since none could be read and the
+    /// parser's message in `error.data.detail`.
+    pub fn response(&self) -> McpResponse {
+        let (code, message, detail) = match self {
+            Self::Parse(detail) => (-32700, "Parse error", detail),
+            Self::InvalidRequest(detail) => (-32600, "Invalid Request", detail),
+        };
+        McpResponse::error(None, code, message).with_data(serde_json::json!({ "detail": detail }))
+    }
+}
+
+impl Incoming {
+    /// Reads one message. Pure, and never panics, whatever `text` holds.
+    pub fn parse(text: &[u8]) -> Result<Self, Malformed> {
+        match serde_json::from_slice(text) {
+            Ok(value) => Self::from_json(value),
+            Err(e) => Err(Malformed::Parse(e.to_string())),
+        }
+    }
+
+    /// Reads a whole message already parsed as JSON, which may be a batch.
+    pub fn from_json(value: Value) -> Result<Self, Malformed> {
+        match value {
+            Value::Array(batch) => Ok(Self::Batch(batch)),
+            value => Self::from_value(value),
+        }
+    }
+
+    /// Reads a message already parsed as JSON: a whole message that is not
+    /// a batch, or one entry of a batch. Batches don't nest, so an array
+    /// is invalid here. The result is never [`Incoming::Batch`].
+    pub fn from_value(value: Value) -> Result<Self, Malformed> {
+        let invalid = |detail: &str| Err(Malformed::InvalidRequest(detail.to_string()));
+        let Value::Object(message) = &value else {
+            return invalid("a message must be a JSON object");
+        };
+        if message.get("jsonrpc").and_then(Value::as_str) != Some("2.0") {
+            return invalid("jsonrpc must be \"2.0\"");
+        }
+        if let Some(Value::Bool(_) | Value::Array(_) | Value::Object(_)) = message.get("id") {
+            return invalid("id must be a string, a number or null");
+        }
+        if is_response(&value) {
+            serde_json::from_value(value)
+                .map(Self::Response)
+                .map_err(|e| Malformed::InvalidRequest(e.to_string()))
+        } else {
+            serde_json::from_value(value)
+                .map(Self::Single)
+                .map_err(|e| Malformed::InvalidRequest(e.to_string()))
+        }
+    }
+}
+
+/// What one line of a line-delimited transport holds.
+#[derive(Debug, PartialEq)]
+pub enum Line<'a> {
+    /// Empty, or only whitespace.
+    Blank,
+    /// Text that can't be JSON-RPC because it starts with neither `{` nor
+    /// `[`, such as a banner printed by a wrapper script.
+    Banner(&'a [u8]),
+    /// A message to [parse](Incoming::parse), without a leading byte
+    /// order mark or surrounding whitespace.
+    Message(&'a [u8]),
+}
+
+impl<'a> Line<'a> {
+    pub fn classify(line: &'a [u8]) -> Self {
+        let line = line.strip_prefix(BOM).unwrap_or(line).trim_ascii();
+        match line.first() {
+            None => Self::Blank,
+            Some(b'{' | b'[') => Self::Message(line),
+            Some(_) => Self::Banner(line),
+        }
+    }
+}
+
+/// UTF-8 byte order mark, which some wrappers write before the first line.
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// A message with a result or error and no method answers one of ours.
+fn is_response(message: &Value) -> bool {
+    message.get("method").is_none()
+        && (message.get("result").is_some() || message.get("error").is_some())
+}
+
+/// Interval of server-initiated pings on network transports, from
+/// `MCP_PING_INTERVAL_SECS`; unset or zero disables them.
+pub fn ping_interval_from_env() -> Option<Duration> {
+    std::env::var("MCP_PING_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .filter(|&secs| secs > 0)
+        .map(Duration::from_secs)
+}
+
+/// Messages allowed in one batch unless `MCP_MAX_BATCH_SIZE` says
+/// otherwise.
+pub const DEFAULT_MAX_BATCH: usize = 100;
+
+/// The batch limit from `MCP_MAX_BATCH_SIZE`, or [`DEFAULT_MAX_BATCH`]. A
+/// malformed or zero value is reported and ignored.
+pub fn max_batch_from_env() -> usize {
+    match std::env::var("MCP_MAX_BATCH_SIZE") {
+        Ok(value) => match value.trim().parse() {
+            Ok(size) if size > 0 => size,
+            _ => {
+                tracing::warn!("Invalid MCP_MAX_BATCH_SIZE '{}'", value);
+                DEFAULT_MAX_BATCH
+            }
+        },
+        Err(_) => DEFAULT_MAX_BATCH,
+    }
+}
+
+/// Admits `request` to `session` and returns the future answering it.
+/// Admission happens immediately, so transports call this in the order
+/// messages arrive even though the returned futures may run concurrently.
+/// A request refused by the handshake is answered with the refusal; a
+/// refused notification is dropped.
+pub fn dispatch(
+    server: &Arc<McpServer>,
+    session: &Arc<Session>,
+    request: McpRequest,
+) -> impl Future<Output = Option<McpResponse>> {
+    let admitted = session.admit(&request);
+    let server = server.clone();
+    let session = session.clone();
+    async move {
+        match admitted {
+            Ok(()) => server.handle_session_request(&session, request).await,
+            Err(refusal) => request
+                .id
+                .is_some()
+                .then(|| McpResponse::error(request.id, -32600, refusal)),
+        }
+    }
+}
+
+/// Answers a JSON-RPC 2.0 batch. Entries are admitted in order, then run
+/// concurrently, and their responses keep the batch order. Notifications get
+/// no entry, so `None` means nothing is to be sent back. An empty batch, or
+/// one longer than the server's [limit](McpServer::set_max_batch), is itself
+/// invalid and none of its entries run.
+pub fn handle_batch(
+    server: &Arc<McpServer>,
+    session: &Arc<Session>,
+    batch: Vec<Value>,
+) -> impl Future<Output = Option<Value>> {
+    let refusal = if batch.is_empty() {
+        Some("empty batch".to_string())
+    } else if batch.len() > server.max_batch() {
+        Some(format!(
+            "batch of {} messages is over the limit of {}",
+            batch.len(),
+            server.max_batch()
+        ))
+    } else {
+        None
+    };
+    let entries = if refusal.is_some() {
+        &[][..]
+    } else {
+        &batch[..]
+    };
+    let tasks: Vec<_> = entries
+        .iter()
+        .map(|entry| match Incoming::from_value(entry.clone()) {
+            Ok(Incoming::Single(request)) => tokio::spawn(dispatch(server, session, request)),
+            Ok(Incoming::Response(response)) => {
+                session.receive_response(response);
+                tokio::spawn(async { None })
+            }
+            Ok(Incoming::Batch(_)) => unreachable!("from_value never returns a batch"),
+            Err(malformed) => {
+                let response = malformed.response();
+                tokio::spawn(async move { Some(response) })
+            }
+        })
+        .collect();
+
+    async move {
+        if let Some(detail) = refusal {
+            let response = Malformed::InvalidRequest(detail).response();
+            return Some(serde_json::to_value(response).unwrap());
+        }
+        let mut responses = Vec::new();
+        for task in tasks {
+            if let Ok(Some(response)) = task.await {
+                responses.push(response);
+            }
+        }
+        (!responses.is_empty()).then(|| serde_json::to_value(responses).unwrap())
+    }
+}
+
+/// Serves line-delimited JSON-RPC: one request per line in, one response per
+/// line out. The stream is one [`Session`], so requests other than
+/// `initialize` are refused until the handshake completes. Each request runs
+/// in its own task, so a slow tool call does not hold up later requests;
+/// responses are written as they complete, which may be out of order. At
+/// most 32 requests run at once; past that, reading pauses until one is
+/// answered. Log notifications that find 64 lines already waiting for a
+/// slow client are dropped. Returns when `reader` reaches end of input and every request has been
+/// answered.
+///
+/// A line may arrive over several reads and is handled once complete, or at
+/// end of input. Lines that cannot be JSON-RPC because they start with
+/// neither `{` nor `[`, such as banners printed by a wrapper script, are
+/// reported on stderr and skipped; a leading byte order mark is ignored.
+pub async fn serve_lines<R, W>(server: Arc<McpServer>, reader: R, writer: W) -> std::io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    serve_lines_with_pings(server, reader, writer, None).await
+}
+
+/// [`serve_lines`], additionally pinging the client after `ping_interval`
+/// of silence and closing the connection when a ping goes unanswered for
+/// another interval.
+pub async fn serve_lines_with_pings<R, W>(
+    server: Arc<McpServer>,
+    reader: R,
+    writer: W,
+    ping_interval: Option<Duration>,
+) -> std::io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    serve_session_lines(
+        server,
+        Arc::new(
+            Session::new()
+                .with_stream_threshold(stream::threshold_from_env())
+                .with_max_message(stream::max_message_from_env()),
+        ),
+        reader,
+        writer,
+        ping_interval,
+    )
+    .await
+}
+
+/// [`serve_lines_with_pings`] as `session`, which carries the transport's
+/// settings, such as its [frame limit](Session::with_max_frame).
+pub async fn serve_session_lines<R, W>(
+    server: Arc<McpServer>,
+    session: Arc<Session>,
+    mut reader: R,
+    mut writer: W,
+    ping_interval: Option<Duration>,
+) -> std::io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let (responses, mut outbox) = mpsc::channel::<String>(OUTBOX_CAPACITY);
+    let notifications = responses.clone();
+    session.set_notifier(move |message| {
+        // Log entries are dropped rather than queued behind a slow reader.
+        notifications.try_send(message.to_string()).is_ok()
+    });
+    let in_flight = Arc::new(Semaphore::new(MAX_IN_FLIGHT));
+
+    let wire_trace = server.wire_trace();
+    let traced_session = session.id().to_string();
+
+    let read = async move {
+        let mut ticker = ping_interval.map(tokio::time::interval);
+        // `read_line` keeps a partial line in `line` if the tick wins.
+        let mut line = Vec::new();
+        // A message over the limit is always decoded as it arrives, where
+        // the limit is enforced.
+        let threshold = session.stream_threshold().min(session.max_message());
+        loop {
+            let tick = async {
+                match ticker.as_mut() {
+                    Some(ticker) => ticker.tick().await,
+                    None => std::future::pending().await,
+                }
+            };
+            let read = tokio::select! {
+                read = stream::read_line(&mut reader, &mut line, threshold) => read,
+                _ = tick => {
+                    match session.ping_due(ping_interval.unwrap()) {
+                        Ping::NotDue => {}
+                        Ping::Send(ping) => {
+                            let _ = responses.send(ping.to_string()).await;
+                        }
+                        Ping::Unanswered => {
+                            tracing::warn!("Closing connection: ping went unanswered");
+                            break;
+                        }
+                    }
+                    continue;
+                }
+            };
+            let incoming = match read {
+                Ok(stream::ReadLine::Eof) => break,
+                Ok(stream::ReadLine::Line) => {
+                    let text = std::mem::take(&mut line);
+                    match Line::classify(&text) {
+                        Line::Blank => continue,
+                        Line::Banner(banner) => {
+                            tracing::warn!(
+                                "Ignoring non-JSON line: {}",
+                                String::from_utf8_lossy(banner)
+                            );
+                            continue;
+                        }
+                        Line::Message(message) => {
+                            trace(&server, Some(session.id()), trace::Direction::In, message);
+                            Incoming::parse(message)
+                        }
+                    }
+                }
+                // Not in the `select!`, so a tick cannot cut it short.
+                Ok(stream::ReadLine::Oversized) if !stream::is_message(&line) => {
+                    line.clear();
+                    tracing::warn!("Ignoring non-JSON line of over {} bytes", threshold);
+                    match stream::skip_line(&mut reader).await {
+                        Ok(()) => continue,
+                        Err(e) => {
+                            tracing::warn!("Failed to read line: {}", e);
+                            break;
+                        }
+                    }
+                }
+                Ok(stream::ReadLine::Oversized) => {
+                    let start = std::mem::take(&mut line);
+                    match stream::parse_rest(&mut reader, start, session.max_message()).await {
+                        Ok(incoming) => {
+                            // Decoded as it arrived, so traced as decoded.
+                            if let (Some(wire), Ok(message)) = (server.wire_trace(), &incoming) {
+                                let message = serde_json::to_vec(message).unwrap();
+                                wire.record(Some(session.id()), trace::Direction::In, &message);
+                            }
+                            incoming
+                        }
+                        Err(e) => {
+                            tracing::warn!("Failed to read line: {}", e);
+                            break;
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to read line: {}", e);
+                    break;
+                }
+            };
+            // The writer has failed; no response could be delivered.
+            if responses.is_closed() {
+                break;
+            }
+
+            let responses = responses.clone();
+            match incoming {
+                Ok(Incoming::Single(request)) => {
+                    // Semaphores are never closed.
+                    let permit = in_flight.clone().acquire_owned().await.unwrap();
+                    let answer = dispatch(&server, &session, request);
+                    let session = session.clone();
+                    tokio::spawn(async move {
+                        if let Some(response) = answer.await {
+                            let response = serde_json::to_value(response).unwrap();
+                            let _ = responses.send(encode(&session, response)).await;
+                        }
+                        drop(permit);
+                    });
+                }
+                Ok(Incoming::Batch(batch)) => {
+                    let permit = in_flight.clone().acquire_owned().await.unwrap();
+                    let answer = handle_batch(&server, &session, batch);
+                    let session = session.clone();
+                    tokio::spawn(async move {
+                        if let Some(response) = answer.await {
+                            let _ = responses.send(encode(&session, response)).await;
+                        }
+                        drop(permit);
+                    });
+                }
+                Ok(Incoming::Response(response)) => session.receive_response(response),
+                Err(malformed) => {
+                    let response = malformed.response();
+                    let _ = responses
+                        .send(serde_json::to_string(&response).unwrap())
+                        .await;
+                }
+            }
+        }
+    };
+
+    // The only writer, so responses never interleave. Each line goes out in
+    // one write with its newline, so it is never split around another
+    // process's output on a shared stream. It finishes once the reader and
+    // every in-flight request have dropped their senders.
+    let write = async move {
+        while let Some(mut response) = outbox.recv().await {
+            if let Some(trace) = &wire_trace {
+                trace.record(
+                    Some(&traced_session),
+                    trace::Direction::Out,
+                    response.as_bytes(),
+                );
+            }
+            response.push('\n');
+            writer.write_all(response.as_bytes()).await?;
+            writer.flush().await?;
+        }
+        Ok(())
+    };
+
+    let ((), written) = tokio::join!(read, write);
+    written
+}
+
+/// Records `message` in the server's wire trace, if it keeps one.
+fn trace(server: &McpServer, session: Option<&str>, direction: trace::Direction, message: &[u8]) {
+    if let Some(trace) = server.wire_trace() {
+        trace.record(session, direction, message);
+    }
+}
+
+/// `message` as one line, split to fit the session's frame limit if it has
+/// one.
+fn encode(session: &Session, message: Value) -> String {
+    match session.max_frame() {
+        Some(limit) => frames::encode(session, message, limit),
+        None => message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::io::AsyncBufReadExt;
+
+    /// `initialize` (answered with id 0) and `notifications/initialized`.
+    const HANDSHAKE: &str = concat!(
+        r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{}}"#,
+        "\n",
+        r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+        "\n"
+    );
+
+    #[tokio::test]
+    async fn test_serve_lines_answers_each_request() {
+        let server = Arc::new(McpServer::new());
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"initialize","params":null}"#,
+            "\n\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"echo","arguments":{"text":"hi"}}}"#,
+            "\n"
+        );
+        let mut output = Vec::new();
+
+        serve_lines(server, input.as_bytes(), &mut output)
+            .await
+            .unwrap();
+
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 2);
+        let echo = responses.iter().find(|r| r["id"] == 2).unwrap();
+        assert_eq!(echo["result"]["content"][0]["text"], "Echo: hi");
+    }
+
+    #[tokio::test]
+    async fn test_serve_lines_answers_more_requests_than_it_runs_at_once() {
+        let server = Arc::new(McpServer::new());
+        let mut input = HANDSHAKE.to_string();
+        let count = MAX_IN_FLIGHT + OUTBOX_CAPACITY * 2;
+        for id in 1..=count {
+            input.push_str(&format!(
+                r#"{{"jsonrpc":"2.0","id":{},"method":"ping"}}"#,
+                id
+            ));
+            input.push('\n');
+        }
+        let mut output = Vec::new();
+
+        serve_lines(server, input.as_bytes(), &mut output)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap().lines().count(),
+            count + 1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_malformed_lines_get_error_responses() {
+        let server = Arc::new(McpServer::new());
+        let input = "{not json\n{\"jsonrpc\":\"2.0\",\"id\":1}\n";
+        let mut output = Vec::new();
+
+        serve_lines(server, input.as_bytes(), &mut output)
+            .await
+            .unwrap();
+
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let parse = responses
+            .iter()
+            .find(|r| r["error"]["code"] == -32700)
+            .unwrap();
+        assert_eq!(parse["id"], Value::Null);
+        assert_eq!(parse["error"]["message"], "Parse error");
+        assert!(parse["error"]["data"]["detail"].is_string());
+        assert!(responses.iter().any(|r| r["error"]["code"] == -32600));
+    }
+
+    #[tokio::test]
+    async fn test_banner_lines_are_skipped() {
+        let server = Arc::new(McpServer::new());
+        let input = format!(
+            "\u{feff}Starting wrapper v2...\n\x1b[32mready\x1b[0m\n{}{}\n",
+            HANDSHAKE, r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#
+        );
+        let mut output = Vec::new();
+
+        serve_lines(server, input.as_bytes(), &mut output)
+            .await
+            .unwrap();
+
+        let ids: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap()["id"].clone())
+            .collect();
+        assert_eq!(ids, vec![json!(0), json!(1)]);
+    }
+
+    #[tokio::test]
+    async fn test_lines_split_across_reads_are_reassembled() {
+        let server = Arc::new(McpServer::new());
+        let (client, transport) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(transport);
+        let serving = tokio::spawn(serve_lines(
+            server,
+            tokio::io::BufReader::new(reader),
+            writer,
+        ));
+        let (client_reader, mut client_writer) = tokio::io::split(client);
+        let mut lines = tokio::io::BufReader::new(client_reader).lines();
+        let input = format!(
+            "{}{}\n",
+            HANDSHAKE, r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#
+        );
+        // Small pieces with pauses between them, so reads return partial
+        // lines.
+        for piece in input.as_bytes().chunks(7) {
+            client_writer.write_all(piece).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(1)).await;
+        }
+
+        assert_eq!(next(&mut lines).await["id"], 0);
+        assert_eq!(next(&mut lines).await["id"], 1);
+
+        drop((lines, client_writer));
+        serving.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_oversized_messages_are_parsed_as_they_arrive() {
+        let server = Arc::new(McpServer::new());
+        let session = Arc::new(
+            Session::new()
+                .with_stream_threshold(1024)
+                .with_max_message(300_000),
+        );
+        let (client, transport) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(transport);
+        let serving = tokio::spawn(serve_session_lines(
+            server,
+            session,
+            tokio::io::BufReader::new(reader),
+            writer,
+            None,
+        ));
+        let (client_reader, mut client_writer) = tokio::io::split(client);
+        let mut lines = tokio::io::BufReader::new(client_reader).lines();
+        let call = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": {"name": "count_tokens", "arguments": {"text": "word ".repeat(50_000)}}
+        });
+        let mut too_long = call.clone();
+        too_long["id"] = json!(2);
+        too_long["params"]["arguments"]["text"] = json!("word ".repeat(100_000));
+        let input = format!(
+            "{}{}\n{}\n{}\n",
+            HANDSHAKE,
+            call,
+            "{\"id\": ".repeat(1000),
+            too_long
+        );
+        let writing = tokio::spawn(async move {
+            client_writer.write_all(input.as_bytes()).await.unwrap();
+            client_writer
+        });
+
+        assert_eq!(next(&mut lines).await["id"], 0);
+        // The three are answered in any order.
+        let mut responses = Vec::new();
+        for _ in 0..3 {
+            responses.push(next(&mut lines).await);
+        }
+        responses.sort_by_key(|r| r["error"]["code"].as_i64());
+        let [response, malformed, refused] = &responses[..] else {
+            unreachable!()
+        };
+        assert_eq!(response["id"], 1);
+        let text = response["result"]["content"][0]["text"].as_str().unwrap();
+        let counted: Value = serde_json::from_str(text).unwrap();
+        assert_eq!(counted["characters"], 250_000);
+        // A malformed one is skipped up to its newline, and answered.
+        assert_eq!(malformed["error"]["code"], -32700);
+        // So is one over the limit, never decoded.
+        assert_eq!(refused["error"]["code"], -32600);
+        let client_writer = writing.await.unwrap();
+
+        drop((lines, client_writer));
+        serving.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_wire_trace_records_both_directions() {
+        let path = std::env::temp_dir().join(format!("mcp-trace-{}.log", uuid::Uuid::new_v4()));
+        let mut server = McpServer::new();
+        server.set_wire_trace(trace::WireTrace::create(&path).unwrap());
+        let session = Arc::new(Session::new());
+        let id = session.id().to_string();
+        let input = format!(
+            "{}{}\n",
+            HANDSHAKE, r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#
+        );
+        let mut output = Vec::new();
+
+        serve_session_lines(
+            Arc::new(server),
+            session,
+            input.as_bytes(),
+            &mut output,
+            None,
+        )
+        .await
+        .unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let records: Vec<&str> = text.lines().collect();
+        assert_eq!(records.len(), 5, "{}", text);
+        let inbound = format!(" --> {} ", id);
+        let outbound = format!(" <-- {} ", id);
+        assert_eq!(records.iter().filter(|r| r.contains(&inbound)).count(), 3);
+        let sent: Vec<Value> = records
+            .iter()
+            .filter_map(|r| r.split_once(&outbound))
+            .map(|(_, message)| serde_json::from_str(message).unwrap())
+            .collect();
+        assert_eq!(sent.len(), 2);
+        assert_eq!(
+            (&sent[1]["id"], &sent[1]["result"]),
+            (&json!(1), &json!({}))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_requests_wait_for_handshake() {
+        let server = Arc::new(McpServer::new());
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/list","params":null}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":2,"method":"initialize","params":{}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":3,"method":"tools/list","params":null}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":4,"method":"tools/list","params":null}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":5,"method":"initialize","params":{}}"#,
+            "\n"
+        );
+        let mut output = Vec::new();
+
+        serve_lines(server, input.as_bytes(), &mut output)
+            .await
+            .unwrap();
+
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let by_id = |id: i64| responses.iter().find(|r| r["id"] == id).unwrap();
+        assert_eq!(by_id(1)["error"]["code"], -32600);
+        assert!(by_id(2)["result"]["serverInfo"].is_object());
+        assert!(by_id(3)["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("notifications/initialized"));
+        assert!(by_id(4)["result"]["tools"].is_array());
+        assert_eq!(by_id(5)["error"]["code"], -32600);
+    }
+
+    #[tokio::test]
+    async fn test_log_entries_follow_set_level() {
+        let server = Arc::new(McpServer::new());
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":1,"method":"logging/setLevel","params":{"level":"loud"}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":2,"method":"logging/setLevel","params":{"level":"debug"}}"#,
+            "\n",
+        );
+        let mut output = Vec::new();
+        serve_lines(server.clone(), input.as_bytes(), &mut output)
+            .await
+            .unwrap();
+        let responses: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let by_id = |id: i64| responses.iter().find(|r| r["id"] == id).unwrap();
+        assert_eq!(by_id(0)["result"]["capabilities"]["logging"], json!({}));
+        assert_eq!(by_id(1)["error"]["code"], -32602);
+        assert_eq!(by_id(2)["result"], json!({}));
+
+        // Requests run concurrently, so the level is set before the call.
+        let input = format!(
+            "{}{}\n",
+            HANDSHAKE,
+            r#"{"jsonrpc":"2.0","id":1,"method":"logging/setLevel","params":{"level":"debug"}}"#
+        );
+        let (client, transport) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(transport);
+        let serving = tokio::spawn(serve_lines(
+            server,
+            tokio::io::BufReader::new(reader),
+            writer,
+        ));
+        let (client_reader, mut client_writer) = tokio::io::split(client);
+        let mut lines = tokio::io::BufReader::new(client_reader).lines();
+        client_writer.write_all(input.as_bytes()).await.unwrap();
+        while next(&mut lines).await["id"] != 1 {}
+        client_writer
+            .write_all(
+                concat!(
+                    r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":"echo","arguments":{"text":"hi"}}}"#,
+                    "\n"
+                )
+                .as_bytes(),
+            )
+            .await
+            .unwrap();
+        let entry = next(&mut lines).await;
+        assert_eq!(entry["method"], "notifications/message");
+        assert_eq!(entry["params"]["level"], "debug");
+        assert_eq!(entry["params"]["logger"], "tools");
+        assert_eq!(entry["params"]["data"]["tool"], "echo");
+        assert_eq!(next(&mut lines).await["id"], 2);
+        drop((lines, client_writer));
+        serving.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_batch_answers_requests_in_order_and_skips_notifications() {
+        let server = Arc::new(McpServer::new());
+        let input = concat!(
+            r#"[{"jsonrpc":"2.0","id":0,"method":"initialize","params":{}},"#,
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"},"#,
+            r#"{"jsonrpc":"2.0","id":"a","method":"tools/call","params":{"name":"echo","arguments":{"text":"1"}}},"#,
+            r#"{"jsonrpc":"2.0","method":"notifications/cancelled"},"#,
+            r#"{"oops":true},"#,
+            r#"{"jsonrpc":"2.0","id":"b","method":"tools/list","params":null}]"#,
+            "\n",
+            "[]\n",
+            r#"[{"jsonrpc":"2.0","method":"notifications/cancelled"}]"#,
+            "\n"
+        );
+        let mut output = Vec::new();
+
+        serve_lines(server, input.as_bytes(), &mut output)
+            .await
+            .unwrap();
+
+        let mut lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        lines.sort_by_key(|l| l.is_object());
+        let batch = lines[0].as_array().unwrap();
+        assert_eq!(batch.len(), 4);
+        assert_eq!(batch[0]["id"], 0);
+        assert_eq!(batch[1]["id"], "a");
+        assert_eq!(batch[2]["error"]["code"], -32600);
+        assert_eq!(batch[3]["id"], "b");
+        assert_eq!(lines[1]["error"]["code"], -32600);
+    }
+
+    #[tokio::test]
+    async fn test_batches_over_the_limit_are_refused_whole() {
+        let mut server = McpServer::new();
+        server.set_max_batch(2);
+        let server = Arc::new(server);
+        let ping = |id: u32| json!({"jsonrpc": "2.0", "id": id, "method": "ping"});
+        let input = format!(
+            "{}{}\n{}\n",
+            HANDSHAKE,
+            json!([ping(1), ping(2)]),
+            json!([ping(3), ping(4), ping(5)])
+        );
+        let mut output = Vec::new();
+
+        serve_lines(server, input.as_bytes(), &mut output)
+            .await
+            .unwrap();
+
+        let lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines
+            .iter()
+            .any(|l| l.as_array().is_some_and(|b| b.len() == 2)));
+        let refused = lines.iter().find(|l| l.is_object() && l["id"].is_null());
+        assert_eq!(refused.unwrap()["error"]["code"], -32600);
+    }
+
+    async fn next<R: AsyncBufRead + Unpin>(lines: &mut tokio::io::Lines<R>) -> Value {
+        serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_clients_hear_about_tool_changes() {
+        let server = Arc::new(McpServer::new());
+        let (client, transport) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(transport);
+        let serving = tokio::spawn(serve_lines(
+            server.clone(),
+            tokio::io::BufReader::new(reader),
+            writer,
+        ));
+        let (client_reader, mut client_writer) = tokio::io::split(client);
+        let mut lines = tokio::io::BufReader::new(client_reader).lines();
+        let input = format!(
+            "{}{}\n",
+            HANDSHAKE, r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#
+        );
+        client_writer.write_all(input.as_bytes()).await.unwrap();
+        let initialized = next(&mut lines).await;
+        assert_eq!(
+            initialized["result"]["capabilities"]["tools"]["listChanged"],
+            true
+        );
+        assert_eq!(next(&mut lines).await["id"], 1);
+
+        let changed = json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/tools/list_changed",
+            "params": {}
+        });
+        let mut late = crate::tools::echo::definition();
+        late.name = "late_echo".to_string();
+        server.register_tool(late, crate::tools::echo::EchoTool);
+        assert_eq!(next(&mut lines).await, changed);
+        assert!(server.unregister_tool("late_echo"));
+        assert_eq!(next(&mut lines).await, changed);
+        assert!(!server.unregister_tool("late_echo"));
+
+        drop((lines, client_writer));
+        serving.await.unwrap().unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_idle_clients_are_pinged_until_they_stop_answering() {
+        let server = Arc::new(McpServer::new());
+        let (client, transport) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(transport);
+        let serving = tokio::spawn(serve_lines_with_pings(
+            server,
+            tokio::io::BufReader::new(reader),
+            writer,
+            Some(Duration::from_millis(50)),
+        ));
+        let (client_reader, mut client_writer) = tokio::io::split(client);
+        let mut lines = tokio::io::BufReader::new(client_reader).lines();
+
+        // `ping` is answered even before the handshake.
+        client_writer
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"ping\"}\n")
+            .await
+            .unwrap();
+        let pong = next(&mut lines).await;
+        assert_eq!(pong["id"], 1);
+        assert_eq!(pong["result"], serde_json::json!({}));
+
+        let ping = next(&mut lines).await;
+        assert_eq!(ping["method"], "ping");
+        let answer = serde_json::json!({"jsonrpc": "2.0", "id": ping["id"], "result": {}});
+        client_writer
+            .write_all(format!("{}\n", answer).as_bytes())
+            .await
+            .unwrap();
+
+        // Once the client falls silent the connection is closed.
+        assert_eq!(next(&mut lines).await["method"], "ping");
+        tokio::time::timeout(Duration::from_secs(5), serving)
+            .await
+            .unwrap()
+            .unwrap()
+            .unwrap();
+    }
+
+    struct SlowTool;
+
+    #[async_trait::async_trait]
+    impl crate::ToolHandler for SlowTool {
+        async fn call(&self, _args: Value) -> Result<crate::ToolResult, crate::ToolError> {
+            tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+            Ok(crate::ToolResult::text("done"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_calls_do_not_block_later_requests() {
+        let server = McpServer::new();
+        server.register_tool(
+            crate::Tool {
+                name: "slow".to_string(),
+                description: "Sleeps".to_string(),
+                input_schema: serde_json::json!({"type": "object"}),
+                output_schema: None,
+                annotations: None,
+                meta: Default::default(),
+            },
+            SlowTool,
+        );
+        let input = format!(
+            "{}{}\n{}\n",
+            HANDSHAKE,
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"slow","arguments":{}}}"#,
+            r#"{"jsonrpc":"2.0","id":2,"method":"tools/list","params":null}"#
+        );
+        let mut output = Vec::new();
+
+        serve_lines(Arc::new(server), input.as_bytes(), &mut output)
+            .await
+            .unwrap();
+
+        let ids: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str::<Value>(line).unwrap()["id"].clone())
+            .filter(|id| *id != 0)
+            .collect();
+        assert_eq!(ids, vec![serde_json::json!(2), serde_json::json!(1)]);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_results_are_split_into_readable_parts() {
+        let server = Arc::new(McpServer::new());
+        let session = Arc::new(Session::new().with_max_frame(frames::MIN_FRAME_BYTES));
+        let (client, transport) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(transport);
+        let serving = tokio::spawn(serve_session_lines(
+            server,
+            session,
+            tokio::io::BufReader::new(reader),
+            writer,
+            None,
+        ));
+        let (client_reader, mut client_writer) = tokio::io::split(client);
+        let mut lines = tokio::io::BufReader::new(client_reader).lines();
+        let text = "\"quoted\" ".repeat(1500);
+        let call = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": {"name": "echo", "arguments": {"text": text}}
+        });
+        let input = format!("{}{}\n", HANDSHAKE, call);
+        client_writer.write_all(input.as_bytes()).await.unwrap();
+        let initialized = next(&mut lines).await;
+        assert!(initialized["result"]["capabilities"]["resources"].is_object());
+
+        let line = lines.next_line().await.unwrap().unwrap();
+        assert!(line.len() <= frames::MIN_FRAME_BYTES);
+        let split: Value = serde_json::from_str(&line).unwrap();
+        let links: Vec<Value> = split["result"]["content"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|block| block["type"] == "resource_link")
+            .map(|block| block["uri"].clone())
+            .collect();
+        assert!(links.len() > 1);
+
+        let mut joined = String::new();
+        for (id, uri) in links.iter().enumerate() {
+            let read = json!({
+                "jsonrpc": "2.0",
+                "id": id + 2,
+                "method": "resources/read",
+                "params": {"uri": uri}
+            });
+            client_writer
+                .write_all(format!("{}\n", read).as_bytes())
+                .await
+                .unwrap();
+            let line = lines.next_line().await.unwrap().unwrap();
+            assert!(line.len() <= frames::MIN_FRAME_BYTES);
+            let response: Value = serde_json::from_str(&line).unwrap();
+            joined.push_str(response["result"]["contents"][0]["text"].as_str().unwrap());
+        }
+        let result: Value = serde_json::from_str(&joined).unwrap();
+        assert_eq!(result["content"][0]["text"], format!("Echo: {}", text));
+
+        drop((lines, client_writer));
+        serving.await.unwrap().unwrap();
+    }
+
+    struct SummarizeTool;
+
+    #[async_trait::async_trait]
+    impl crate::ToolHandler for SummarizeTool {
+        async fn call(&self, _args: Value) -> Result<crate::ToolResult, crate::ToolError> {
+            unreachable!("called with a context")
+        }
+
+        async fn call_with_context(
+            &self,
+            args: Value,
+            context: &crate::ToolContext,
+        ) -> Result<crate::ToolResult, crate::ToolError> {
+            let request = crate::sampling::SamplingRequest::new(100)
+                .system("Summarize in one word")
+                .user(args["text"].as_str().unwrap_or_default());
+            let response = context.sample(request).await?;
+            Ok(crate::ToolResult::text(response.text().unwrap_or_default()))
+        }
+    }
+
+    fn server_with_summarize() -> McpServer {
+        let server = McpServer::new();
+        server.register_tool(
+            crate::Tool {
+                name: "summarize".to_string(),
+                description: "Summarizes text".to_string(),
+                input_schema: json!({"type": "object"}),
+                output_schema: None,
+                annotations: None,
+                meta: Default::default(),
+            },
+            SummarizeTool,
+        );
+        server
+    }
+
+    #[tokio::test]
+    async fn test_tools_sample_from_the_client() {
+        let server = Arc::new(server_with_summarize());
+        let (client, transport) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(transport);
+        let serving = tokio::spawn(serve_lines(
+            server,
+            tokio::io::BufReader::new(reader),
+            writer,
+        ));
+        let (client_reader, mut client_writer) = tokio::io::split(client);
+        let mut lines = tokio::io::BufReader::new(client_reader).lines();
+        let call = r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"summarize","arguments":{"text":"Hello there"}}}"#;
+
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{"capabilities":{"sampling":{}}}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "\n",
+        );
+        client_writer
+            .write_all(format!("{}{}\n", input, call).as_bytes())
+            .await
+            .unwrap();
+        next(&mut lines).await;
+        let sampling = next(&mut lines).await;
+        assert_eq!(sampling["method"], "sampling/createMessage");
+        assert_eq!(sampling["params"]["systemPrompt"], "Summarize in one word");
+        assert_eq!(
+            sampling["params"]["messages"][0]["content"]["text"],
+            "Hello there"
+        );
+        let answer = json!({
+            "jsonrpc": "2.0",
+            "id": sampling["id"],
+            "result": {
+                "role": "assistant",
+                "content": {"type": "text", "text": "Greeting"},
+                "model": "test-model"
+            }
+        });
+        client_writer
+            .write_all(format!("{}\n", answer).as_bytes())
+            .await
+            .unwrap();
+        let result = next(&mut lines).await;
+        assert_eq!(result["id"], 1);
+        assert_eq!(result["result"]["content"][0]["text"], "Greeting");
+
+        drop((lines, client_writer));
+        serving.await.unwrap().unwrap();
+
+        // A client that did not declare the capability is refused.
+        let mut output = Vec::new();
+        serve_lines(
+            Arc::new(server_with_summarize()),
+            format!("{}{}\n", HANDSHAKE, call).as_bytes(),
+            &mut output,
+        )
+        .await
+        .unwrap();
+        let refused: Value =
+            serde_json::from_str(String::from_utf8(output).unwrap().lines().nth(1).unwrap())
+                .unwrap();
+        assert_eq!(refused["id"], 1);
+        assert!(refused.to_string().contains("does not support sampling"));
+    }
+
+    struct RootsTool;
+
+    #[async_trait::async_trait]
+    impl crate::ToolHandler for RootsTool {
+        async fn call(&self, _args: Value) -> Result<crate::ToolResult, crate::ToolError> {
+            unreachable!("called with a context")
+        }
+
+        async fn call_with_context(
+            &self,
+            _args: Value,
+            context: &crate::ToolContext,
+        ) -> Result<crate::ToolResult, crate::ToolError> {
+            Ok(crate::ToolResult::json(&json!(context.roots())))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_roots_are_fetched_and_refreshed() {
+        let server = Arc::new(McpServer::new());
+        server.register_tool(
+            crate::Tool {
+                name: "roots".to_string(),
+                description: "Lists the roots".to_string(),
+                input_schema: json!({"type": "object"}),
+                output_schema: None,
+                annotations: None,
+                meta: Default::default(),
+            },
+            RootsTool,
+        );
+        let (client, transport) = tokio::io::duplex(4096);
+        let (reader, writer) = tokio::io::split(transport);
+        let serving = tokio::spawn(serve_lines(
+            server,
+            tokio::io::BufReader::new(reader),
+            writer,
+        ));
+        let (client_reader, mut client_writer) = tokio::io::split(client);
+        let mut lines = tokio::io::BufReader::new(client_reader).lines();
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{"capabilities":{"roots":{"listChanged":true}}}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "\n",
+        );
+        client_writer.write_all(input.as_bytes()).await.unwrap();
+        next(&mut lines).await;
+
+        for (project, changed) in [("/work/a", false), ("/work/b", true)] {
+            if changed {
+                let notification =
+                    r#"{"jsonrpc":"2.0","method":"notifications/roots/list_changed"}"#;
+                client_writer
+                    .write_all(format!("{}\n", notification).as_bytes())
+                    .await
+                    .unwrap();
+            }
+            let list = next(&mut lines).await;
+            assert_eq!(list["method"], "roots/list");
+            let answer = json!({
+                "jsonrpc": "2.0",
+                "id": list["id"],
+                "result": {"roots": [{"uri": format!("file://{}", project), "name": "project"}]}
+            });
+            client_writer
+                .write_all(format!("{}\n", answer).as_bytes())
+                .await
+                .unwrap();
+            // The refresh task stores the answer shortly after it arrives.
+            let expected = json!([{"uri": format!("file://{}", project), "name": "project"}]);
+            let mut roots = Value::Null;
+            for id in 1..=50 {
+                let call = json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "method": "tools/call",
+                    "params": {"name": "roots", "arguments": {}}
+                });
+                client_writer
+                    .write_all(format!("{}\n", call).as_bytes())
+                    .await
+                    .unwrap();
+                let result = next(&mut lines).await;
+                roots =
+                    serde_json::from_str(result["result"]["content"][0]["text"].as_str().unwrap())
+                        .unwrap();
+                if roots == expected {
+                    break;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+            assert_eq!(roots, expected);
+        }
+
+        drop((lines, client_writer));
+        serving.await.unwrap().unwrap();
+    }
+
+    /// Generated frames: valid messages must survive serialization and
+    /// parsing unchanged, and nothing a client sends may panic the parser
+    /// or get an error code outside the spec's.
+    mod properties {
+        use super::*;
+        use crate::protocol::{McpError, Tool, ToolAnnotations};
+        use proptest::prelude::*;
+
+        fn json() -> impl Strategy<Value = Value> {
+            let leaf = prop_oneof![
+                Just(Value::Null),
+                any::<bool>().prop_map(Value::from),
+                any::<i64>().prop_map(Value::from),
+                ".{0,12}".prop_map(Value::from),
+            ];
+            leaf.prop_recursive(3, 24, 4, |inner| {
+                prop_oneof![
+                    prop::collection::vec(inner.clone(), 0..4).prop_map(Value::Array),
+                    object(inner),
+                ]
+            })
+        }
+
+        fn object(values: impl Strategy<Value = Value>) -> impl Strategy<Value = Value> {
+            prop::collection::btree_map("[a-zA-Z_]{1,8}", values, 0..4)
+                .prop_map(|map| Value::Object(map.into_iter().collect()))
+        }
+
+        /// Absent or null ids read back as `None`, so only those are made.
+        fn id() -> impl Strategy<Value = Option<Value>> {
+            prop_oneof![
+                Just(None),
+                any::<i64>().prop_map(|n| Some(n.into())),
+                "[a-z0-9-]{1,12}".prop_map(|s| Some(s.into())),
+            ]
+        }
+
+        fn request() -> impl Strategy<Value = McpRequest> {
+            (
+                id(),
+                "[a-z]{1,8}(/[a-zA-Z]{1,8})?",
+                prop::option::of(object(json())),
+            )
+                .prop_map(|(id, method, params)| McpRequest {
+                    jsonrpc: "2.0".to_string(),
+                    id,
+                    method,
+                    params,
+                })
+        }
+
+        fn error() -> impl Strategy<Value = McpError> {
+            (any::<i32>(), ".{0,16}", prop::option::of(object(json()))).prop_map(
+                |(code, message, data)| McpError {
+                    code,
+                    message,
+                    data,
+                },
+            )
+        }
+
+        fn response() -> impl Strategy<Value = McpResponse> {
+            let outcome = prop_oneof![
+                object(json()).prop_map(|result| (Some(result), None)),
+                error().prop_map(|error| (None, Some(error))),
+            ];
+            (id(), outcome).prop_map(|(id, (result, error))| McpResponse {
+                jsonrpc: "2.0".to_string(),
+                id,
+                result,
+                error,
+            })
+        }
+
+        fn tool() -> impl Strategy<Value = Tool> {
+            let annotations = prop::option::of(any::<bool>().prop_map(|read_only| {
+                if read_only {
+                    ToolAnnotations::read_only()
+                } else {
+                    ToolAnnotations::mutating(true)
+                }
+            }));
+            (
+                "[a-z_]{1,12}",
+                ".{0,24}",
+                object(json()),
+                prop::option::of(object(json())),
+                annotations,
+            )
+                .prop_map(
+                    |(name, description, input_schema, output_schema, annotations)| Tool {
+                        name,
+                        description,
+                        input_schema,
+                        output_schema,
+                        annotations,
+                        meta: Default::default(),
+                    },
+                )
+        }
+
+        fn code(text: &[u8]) -> Option<i32> {
+            Incoming::parse(text)
+                .err()
+                .map(|malformed| malformed.response().error.unwrap().code)
+        }
+
+        proptest! {
+            #[test]
+            fn requests_round_trip(request in request()) {
+                let text = serde_json::to_vec(&request).unwrap();
+                let Ok(Incoming::Single(parsed)) = Incoming::parse(&text) else {
+                    panic!("not read back as a request");
+                };
+                prop_assert_eq!(
+                    serde_json::to_value(parsed).unwrap(),
+                    serde_json::to_value(request).unwrap()
+                );
+            }
+
+            #[test]
+            fn responses_round_trip(response in response()) {
+                let text = serde_json::to_vec(&response).unwrap();
+                let Ok(Incoming::Response(parsed)) = Incoming::parse(&text) else {
+                    panic!("not read back as a response");
+                };
+                prop_assert_eq!(
+                    serde_json::to_value(parsed).unwrap(),
+                    serde_json::to_value(response).unwrap()
+                );
+            }
+
+            #[test]
+            fn tool_listings_round_trip(tools in prop::collection::vec(tool(), 0..4)) {
+                let listing = serde_json::json!({ "tools": tools });
+                let response = McpResponse::success(Some(1.into()), listing.clone());
+                let text = serde_json::to_vec(&response).unwrap();
+                let Ok(Incoming::Response(parsed)) = Incoming::parse(&text) else {
+                    panic!("not read back as a response");
+                };
+                let tools: Vec<Tool> =
+                    serde_json::from_value(parsed.result.unwrap()["tools"].clone()).unwrap();
+                prop_assert_eq!(serde_json::json!({ "tools": tools }), listing);
+            }
+
+            #[test]
+            fn batches_keep_every_entry(requests in prop::collection::vec(request(), 1..5)) {
+                let text = serde_json::to_vec(&requests).unwrap();
+                let Ok(Incoming::Batch(batch)) = Incoming::parse(&text) else {
+                    panic!("not read back as a batch");
+                };
+                prop_assert_eq!(batch.len(), requests.len());
+                for entry in batch {
+                    prop_assert!(matches!(Incoming::from_value(entry), Ok(Incoming::Single(_))));
+                }
+            }
+
+            #[test]
+            fn any_bytes_parse_or_fail_with_a_spec_code(bytes in prop::collection::vec(any::<u8>(), 0..64)) {
+                let is_json = serde_json::from_slice::<Value>(&bytes).is_ok();
+                match code(&bytes) {
+                    Some(code) => prop_assert_eq!(code, if is_json { -32600 } else { -32700 }),
+                    None => prop_assert!(is_json),
+                }
+                let _ = Line::classify(&bytes);
+            }
+
+            #[test]
+            fn json_that_is_not_a_message_is_an_invalid_request(value in json()) {
+                let text = serde_json::to_vec(&value).unwrap();
+                let is_message = value.get("jsonrpc") == Some(&Value::from("2.0"))
+                    && (value["method"].is_string() || is_response(&value));
+                match Incoming::parse(&text) {
+                    Ok(Incoming::Batch(_)) => prop_assert!(value.is_array()),
+                    Ok(_) => prop_assert!(is_message),
+                    Err(_) => prop_assert_eq!(code(&text), Some(-32600)),
+                }
+            }
+
+            #[test]
+            fn nested_batches_are_refused(entry in prop::collection::vec(json(), 0..4)) {
+                let malformed = Incoming::from_value(Value::Array(entry));
+                prop_assert!(matches!(malformed, Err(Malformed::InvalidRequest(_))));
+            }
+
+            #[test]
+            fn lines_are_framed_around_the_message(
+                request in request(),
+                padding in "[ \t\r]{0,3}",
+                bom in any::<bool>(),
+            ) {
+                let message = serde_json::to_vec(&request).unwrap();
+                let mut line = if bom { BOM.to_vec() } else { Vec::new() };
+                line.extend_from_slice(padding.as_bytes());
+                line.extend_from_slice(&message);
+                line.extend_from_slice(padding.as_bytes());
+                line.push(b'\n');
+                prop_assert_eq!(Line::classify(&line), Line::Message(&message));
+            }
+        }
+    }
+}
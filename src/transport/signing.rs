@@ -0,0 +1,256 @@
+//! Optional HMAC request signing for the HTTP transports, for deployments
+//! that want more than a bearer token but do not run an OAuth server.
+//!
+//! A client shares a secret with the server and sends three headers with
+//! every request: `X-MCP-Timestamp`, the Unix time in seconds,
+//! `X-MCP-Nonce`, a value it never sends twice, and
+//! `X-MCP-Signature: sha256=<hex>`, an HMAC-SHA256 over
+//! `<timestamp>\n<nonce>\n<METHOD>\n<path and query>\n<body>`. Requests
+//! that are unsigned, stale or replayed are refused.
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::crypto;
+use crate::http::server::Request;
+use crate::security::{SecurityEvent, SecurityEventKind};
+
+pub const TIMESTAMP_HEADER: &str = "X-MCP-Timestamp";
+pub const NONCE_HEADER: &str = "X-MCP-Nonce";
+pub const SIGNATURE_HEADER: &str = "X-MCP-Signature";
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(300);
+/// Longest nonce accepted, so the replay cache stays small per request.
+pub const MAX_NONCE_LEN: usize = 128;
+
+/// Why a request's signature was not accepted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureError {
+    Missing,
+    Malformed,
+    /// The timestamp is further from the server's clock than the allowed age.
+    Expired,
+    Mismatch,
+    /// The same nonce was already accepted within the allowed age.
+    Replayed,
+}
+
+impl SignatureError {
+    pub fn message(self) -> &'static str {
+        match self {
+            Self::Missing => "Missing request signature",
+            Self::Malformed => "Malformed request signature",
+            Self::Expired => "Request timestamp outside the allowed window",
+            Self::Mismatch => "Invalid request signature",
+            Self::Replayed => "Request nonce already used",
+        }
+    }
+}
+
+/// Verifies signed requests against one shared secret.
+pub struct RequestSigning {
+    secret: Vec<u8>,
+    max_age: Duration,
+    /// Nonces accepted within `max_age`, with their timestamps. Keying on
+    /// the nonce rather than the signature lets a client send the same
+    /// request twice in one second, and still refuses every replay.
+    seen: Mutex<HashMap<String, u64>>,
+}
+
+impl RequestSigning {
+    pub fn new(secret: impl Into<Vec<u8>>, max_age: Duration) -> Self {
+        Self {
+            secret: secret.into(),
+            max_age,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Signing from `MCP_HTTP_SIGNING_SECRET` and
+    /// `MCP_HTTP_SIGNATURE_MAX_AGE_SECS`, or `None` when no secret is set.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Some(secret) = std::env::var("MCP_HTTP_SIGNING_SECRET")
+            .ok()
+            .filter(|s| !s.is_empty())
+        else {
+            return Ok(None);
+        };
+        let max_age = match std::env::var("MCP_HTTP_SIGNATURE_MAX_AGE_SECS") {
+            Ok(v) => Duration::from_secs(
+                v.trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid MCP_HTTP_SIGNATURE_MAX_AGE_SECS '{}'", v))?,
+            ),
+            Err(_) => DEFAULT_MAX_AGE,
+        };
+        Ok(Some(Self::new(secret, max_age)))
+    }
+
+    /// Checks the signature headers of `request` against the current time.
+    pub fn verify(&self, request: &Request) -> Result<(), SignatureError> {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        self.verify_at(request, now)
+    }
+
+    fn verify_at(&self, request: &Request, now: u64) -> Result<(), SignatureError> {
+        let (Some(timestamp), Some(nonce), Some(signature)) = (
+            request.header(TIMESTAMP_HEADER),
+            request.header(NONCE_HEADER),
+            request.header(SIGNATURE_HEADER),
+        ) else {
+            return Err(SignatureError::Missing);
+        };
+        let timestamp: u64 = timestamp
+            .trim()
+            .parse()
+            .map_err(|_| SignatureError::Malformed)?;
+        let nonce = nonce.trim();
+        if nonce.is_empty()
+            || nonce.len() > MAX_NONCE_LEN
+            || !nonce.bytes().all(|b| b.is_ascii_graphic())
+        {
+            return Err(SignatureError::Malformed);
+        }
+        let signature = signature
+            .trim()
+            .strip_prefix("sha256=")
+            .ok_or(SignatureError::Malformed)?
+            .to_ascii_lowercase();
+        if now.abs_diff(timestamp) > self.max_age.as_secs() {
+            return Err(SignatureError::Expired);
+        }
+        let expected = sign(
+            &self.secret,
+            timestamp,
+            nonce,
+            &request.method,
+            &request.target,
+            &request.body,
+        );
+        if !crypto::constant_time_eq(
+            expected.as_bytes(),
+            format!("sha256={}", signature).as_bytes(),
+        ) {
+            return Err(SignatureError::Mismatch);
+        }
+
+        let mut seen = self.seen.lock().unwrap();
+        let max_age = self.max_age.as_secs();
+        seen.retain(|_, at| now.abs_diff(*at) <= max_age);
+        if seen.insert(nonce.to_string(), timestamp).is_some() {
+            return Err(SignatureError::Replayed);
+        }
+        Ok(())
+    }
+}
+
+/// Value of the signature header for a request; clients compute the same.
+pub fn sign(
+    secret: &[u8],
+    timestamp: u64,
+    nonce: &str,
+    method: &str,
+    target: &str,
+    body: &[u8],
+) -> String {
+    let mut message = format!("{}\n{}\n{}\n{}\n", timestamp, nonce, method, target).into_bytes();
+    message.extend_from_slice(body);
+    format!(
+        "sha256={}",
+        crypto::hex(&crypto::hmac_sha256(secret, &message))
+    )
+}
+
+/// The security event for a request refused by [`RequestSigning::verify`].
+pub fn signature_failure(
+    request: &Request,
+    peer: SocketAddr,
+    error: SignatureError,
+) -> SecurityEvent {
+    SecurityEvent::new(SecurityEventKind::AuthFailure, error.message())
+        .rule("request_signature")
+        .source_ip(peer.ip())
+        .detail("method", request.method.as_str())
+        .detail("path", request.path())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(timestamp: u64, nonce: &str, signature: &str) -> Request {
+        Request {
+            method: "POST".to_string(),
+            target: "/mcp".to_string(),
+            headers: vec![
+                (TIMESTAMP_HEADER.to_string(), timestamp.to_string()),
+                (NONCE_HEADER.to_string(), nonce.to_string()),
+                (SIGNATURE_HEADER.to_string(), signature.to_string()),
+            ],
+            body: br#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_verify_signed_requests() {
+        let signing = RequestSigning::new("s3cret", Duration::from_secs(300));
+        let now = 1_790_000_000;
+        let body = br#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#;
+
+        let good = sign(b"s3cret", now - 10, "n1", "POST", "/mcp", body);
+        assert_eq!(
+            signing.verify_at(&request(now - 10, "n1", &good), now),
+            Ok(())
+        );
+        assert_eq!(
+            signing.verify_at(&request(now - 10, "n1", &good), now),
+            Err(SignatureError::Replayed)
+        );
+        // The same request again in the same second, under a new nonce.
+        let again = sign(b"s3cret", now - 10, "n2", "POST", "/mcp", body);
+        assert_eq!(
+            signing.verify_at(&request(now - 10, "n2", &again), now),
+            Ok(())
+        );
+        // A nonce is part of the signed string, so it cannot be swapped.
+        assert_eq!(
+            signing.verify_at(&request(now - 10, "n3", &good), now),
+            Err(SignatureError::Mismatch)
+        );
+
+        let mut unsigned = request(now, "n4", "");
+        unsigned.headers.clear();
+        assert_eq!(
+            signing.verify_at(&unsigned, now),
+            Err(SignatureError::Missing)
+        );
+        assert_eq!(
+            signing.verify_at(&request(now, "n4", "md5=abc"), now),
+            Err(SignatureError::Malformed)
+        );
+        let spaced = sign(b"s3cret", now, "a b", "POST", "/mcp", body);
+        assert_eq!(
+            signing.verify_at(&request(now, "a b", &spaced), now),
+            Err(SignatureError::Malformed)
+        );
+        let stale = sign(b"s3cret", now - 301, "n4", "POST", "/mcp", body);
+        assert_eq!(
+            signing.verify_at(&request(now - 301, "n4", &stale), now),
+            Err(SignatureError::Expired)
+        );
+        let other_path = sign(b"s3cret", now, "n5", "POST", "/other", body);
+        assert_eq!(
+            signing.verify_at(&request(now, "n5", &other_path), now),
+            Err(SignatureError::Mismatch)
+        );
+        let other_key = sign(b"guess", now, "n6", "POST", "/mcp", body);
+        assert_eq!(
+            signing.verify_at(&request(now, "n6", &other_key), now),
+            Err(SignatureError::Mismatch)
+        );
+    }
+}
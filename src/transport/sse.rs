@@ -0,0 +1,374 @@
+//! Legacy HTTP+SSE transport (MCP 2024-11-05), for clients that predate
+//! Streamable HTTP.
+//!
+//! A client opens `GET /sse`; the first event, `endpoint`, carries the URL
+//! to POST requests to (`/messages?sessionId=<id>`). Each POST is answered
+//! with 202 Accepted and the JSON-RPC response arrives on the stream as a
+//! `message` event. The session ends when the stream is closed.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+
+use super::access::ConnectionLimiter;
+use super::auth;
+use super::signing::{self, RequestSigning};
+use super::trace::Direction;
+use super::Incoming;
+use crate::http::server::{self, Request, Response};
+use crate::session::{Ping, Session};
+use crate::McpServer;
+
+pub const DEFAULT_BIND: &str = "127.0.0.1:8080";
+pub const STREAM_ENDPOINT: &str = "/sse";
+pub const MESSAGE_ENDPOINT: &str = "/messages";
+
+const KEEPALIVE_INTERVAL: Duration = Duration::from_secs(15);
+/// Responses buffered for a stream that is slow to read.
+const SESSION_QUEUE: usize = 64;
+
+/// An open stream's session and the sender feeding the stream.
+#[derive(Clone)]
+struct OpenSession {
+    session: Arc<Session>,
+    messages: mpsc::Sender<Value>,
+}
+
+struct State {
+    server: Arc<McpServer>,
+    /// Open streams, by session id.
+    sessions: Mutex<HashMap<String, OpenSession>>,
+    allowed_origins: Vec<String>,
+    signing: Option<RequestSigning>,
+    /// How long a stream may sit idle before the server pings.
+    ping_interval: Option<Duration>,
+}
+
+/// Listens on `addr` and serves until the listener fails.
+pub async fn serve(
+    server: Arc<McpServer>,
+    addr: SocketAddr,
+    limiter: Arc<ConnectionLimiter>,
+) -> io::Result<()> {
+    let listener = TcpListener::bind(addr).await?;
+    tracing::info!(
+        "Listening on http://{}{}",
+        listener.local_addr()?,
+        STREAM_ENDPOINT
+    );
+    serve_listener(server, listener, limiter).await
+}
+
+/// Serves connections from an already bound listener.
+pub async fn serve_listener(
+    server: Arc<McpServer>,
+    listener: TcpListener,
+    limiter: Arc<ConnectionLimiter>,
+) -> io::Result<()> {
+    let state = Arc::new(State {
+        server,
+        sessions: Mutex::new(HashMap::new()),
+        allowed_origins: server::allowed_origins_from_env(),
+        signing: RequestSigning::from_env()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?,
+        ping_interval: super::ping_interval_from_env(),
+    });
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        // Refused connections are closed by dropping the stream.
+        let Ok(permit) = limiter.admit(peer.ip()) else {
+            continue;
+        };
+        let state = state.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            if let Err(e) = handle_connection(state, stream, peer).await {
+                tracing::warn!("SSE connection failed: {}", e);
+            }
+        });
+    }
+}
+
+async fn handle_connection(
+    state: Arc<State>,
+    stream: TcpStream,
+    peer: SocketAddr,
+) -> io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    loop {
+        let request = match server::read_request(&mut reader).await {
+            Ok(Some(request)) => request,
+            Ok(None) => return Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                return Response::text(400, &e.to_string())
+                    .write_to(&mut writer, false)
+                    .await;
+            }
+            Err(e) => return Err(e),
+        };
+        let keep_alive = request.keep_alive();
+
+        let response = match refusal(&state, &request, peer) {
+            Some(response) => response,
+            None if request.path() == STREAM_ENDPOINT && request.method == "GET" => {
+                // The stream owns the rest of the connection.
+                return open_stream(&state, &mut writer).await;
+            }
+            None => route(&state, request).await,
+        };
+        response.write_to(&mut writer, keep_alive).await?;
+        if !keep_alive {
+            return Ok(());
+        }
+    }
+}
+
+/// The response refusing `request` when it is for an unknown path, comes
+/// from a disallowed origin, lacks a required token or, with signing
+/// enabled, is not validly signed.
+fn refusal(state: &State, request: &Request, peer: SocketAddr) -> Option<Response> {
+    if ![STREAM_ENDPOINT, MESSAGE_ENDPOINT].contains(&request.path()) {
+        return Some(Response::text(404, "Not found"));
+    }
+    if !server::origin_allowed(request, &state.allowed_origins) {
+        state
+            .server
+            .security()
+            .report(server::origin_denial(request, peer));
+        return Some(Response::text(403, "Origin not allowed"));
+    }
+    if let Some(Err(e)) = state
+        .server
+        .token_auth()
+        .map(|auth| auth.verify_request_from(request, peer.ip()))
+    {
+        state
+            .server
+            .security()
+            .report(auth::auth_failure("sse", peer, e));
+        return Some(e.response());
+    }
+    if let Err(e) = state.signing.as_ref()?.verify(request) {
+        state
+            .server
+            .security()
+            .report(signing::signature_failure(request, peer, e));
+        return Some(Response::text(401, e.message()));
+    }
+    None
+}
+
+async fn route(state: &State, request: Request) -> Response {
+    let allow = if request.path() == STREAM_ENDPOINT {
+        "GET"
+    } else {
+        "POST"
+    };
+    if request.method != allow {
+        return Response::text(405, "Method not allowed").header("Allow", allow);
+    }
+    handle_message(state, request).await
+}
+
+async fn handle_message(state: &State, request: Request) -> Response {
+    let Some(id) = request.query("sessionId") else {
+        return Response::text(400, "Missing sessionId");
+    };
+    let Some(OpenSession { session, messages }) = state.sessions.lock().unwrap().get(id).cloned()
+    else {
+        return Response::text(404, "Session not found");
+    };
+    super::trace(&state.server, Some(id), Direction::In, &request.body);
+    let message = match Incoming::parse(&request.body) {
+        Ok(message) => message,
+        Err(malformed) => return Response::json(400, &malformed.response()),
+    };
+
+    // The response goes out on the stream, so the POST need not wait for a
+    // slow tool call to finish. Admission happens now, in arrival order.
+    match message {
+        Incoming::Single(request) => {
+            let answer = super::dispatch(&state.server, &session, request);
+            tokio::spawn(async move {
+                if let Some(response) = answer.await {
+                    let _ = messages.send(serde_json::to_value(response).unwrap()).await;
+                }
+            });
+        }
+        Incoming::Batch(batch) => {
+            let answer = super::handle_batch(&state.server, &session, batch);
+            tokio::spawn(async move {
+                if let Some(response) = answer.await {
+                    let _ = messages.send(response).await;
+                }
+            });
+        }
+        // The client answering a ping or another server request.
+        Incoming::Response(response) => session.receive_response(response),
+    }
+    Response::text(202, "Accepted")
+}
+
+/// Registers a session, announces its message endpoint, then forwards its
+/// responses as `message` events until the client goes away or, with pings
+/// enabled, stops answering them.
+async fn open_stream<W: AsyncWrite + Unpin>(state: &State, writer: &mut W) -> io::Result<()> {
+    let session = Arc::new(Session::new());
+    let id = session.id().to_string();
+    let (sender, mut messages) = mpsc::channel(SESSION_QUEUE);
+    let notifications = sender.clone();
+    session.set_notifier(move |message| {
+        // Log entries are dropped rather than queued behind a slow reader.
+        notifications.try_send(message).is_ok()
+    });
+    state.sessions.lock().unwrap().insert(
+        id.clone(),
+        OpenSession {
+            session: session.clone(),
+            messages: sender,
+        },
+    );
+
+    let result = async {
+        server::write_event_stream_head(writer, &[]).await?;
+        let endpoint = format!("{}?sessionId={}", MESSAGE_ENDPOINT, id);
+        server::write_event(writer, Some("endpoint"), &endpoint).await?;
+
+        let mut keepalive = tokio::time::interval(
+            state
+                .ping_interval
+                .map_or(KEEPALIVE_INTERVAL, |p| p.min(KEEPALIVE_INTERVAL)),
+        );
+        keepalive.tick().await;
+        loop {
+            tokio::select! {
+                Some(message) = messages.recv() => {
+                    let message = message.to_string();
+                    super::trace(&state.server, Some(&id), Direction::Out, message.as_bytes());
+                    server::write_event(writer, Some("message"), &message).await?
+                }
+                _ = keepalive.tick() => match state.ping_interval.map(|p| session.ping_due(p)) {
+                    Some(Ping::Send(ping)) => {
+                        let ping = ping.to_string();
+                        super::trace(&state.server, Some(&id), Direction::Out, ping.as_bytes());
+                        server::write_event(writer, Some("message"), &ping).await?
+                    }
+                    Some(Ping::Unanswered) => return Ok(()),
+                    _ => {
+                        writer.write_all(b": keepalive\n\n").await?;
+                        writer.flush().await?;
+                    }
+                },
+            }
+        }
+    }
+    .await;
+
+    state.sessions.lock().unwrap().remove(&id);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+
+    async fn start() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_listener(
+            Arc::new(McpServer::new()),
+            listener,
+            Arc::default(),
+        ));
+        addr
+    }
+
+    /// Sends one request on a fresh connection and returns the status.
+    async fn send(addr: SocketAddr, method: &str, target: &str, body: &str) -> u16 {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "{} {} HTTP/1.1\r\nHost: x\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            target,
+            body.len(),
+            body
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response.split_whitespace().nth(1).unwrap().parse().unwrap()
+    }
+
+    /// Reads the next event from an open stream as (event, data).
+    async fn next_event<R: tokio::io::AsyncBufRead + Unpin>(reader: &mut R) -> (String, String) {
+        let (mut event, mut data) = (String::new(), String::new());
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line).await.unwrap();
+            let line = line.trim_end();
+            if line.is_empty() && !data.is_empty() {
+                return (event, data);
+            }
+            if let Some(v) = line.strip_prefix("event: ") {
+                event = v.to_string();
+            } else if let Some(v) = line.strip_prefix("data: ") {
+                data.push_str(v);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_responses_arrive_on_stream() {
+        let addr = start().await;
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream
+            .write_all(b"GET /sse HTTP/1.1\r\nHost: x\r\nAccept: text/event-stream\r\n\r\n")
+            .await
+            .unwrap();
+        let mut reader = BufReader::new(stream);
+
+        let (event, endpoint) = next_event(&mut reader).await;
+        assert_eq!(event, "endpoint");
+        assert!(endpoint.starts_with("/messages?sessionId="));
+
+        let call = r#"{"jsonrpc":"2.0","id":7,"method":"tools/call","params":{"name":"echo","arguments":{"text":"hi"}}}"#;
+        assert_eq!(send(addr, "POST", &endpoint, call).await, 202);
+        let (_, data) = next_event(&mut reader).await;
+        let refused: Value = serde_json::from_str(&data).unwrap();
+        assert_eq!(refused["error"]["code"], -32600);
+
+        let initialize = r#"{"jsonrpc":"2.0","id":6,"method":"initialize","params":{}}"#;
+        assert_eq!(send(addr, "POST", &endpoint, initialize).await, 202);
+        next_event(&mut reader).await;
+        let initialized = r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#;
+        assert_eq!(send(addr, "POST", &endpoint, initialized).await, 202);
+
+        assert_eq!(send(addr, "POST", &endpoint, call).await, 202);
+        let (event, data) = next_event(&mut reader).await;
+        assert_eq!(event, "message");
+        let response: Value = serde_json::from_str(&data).unwrap();
+        assert_eq!(response["id"], 7);
+        assert_eq!(response["result"]["content"][0]["text"], "Echo: hi");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_requests() {
+        let addr = start().await;
+        let call = r#"{"jsonrpc":"2.0","id":1,"method":"tools/list"}"#;
+        assert_eq!(send(addr, "POST", "/messages", call).await, 400);
+        assert_eq!(
+            send(addr, "POST", "/messages?sessionId=nope", call).await,
+            404
+        );
+        assert_eq!(send(addr, "GET", "/messages", "").await, 405);
+        assert_eq!(send(addr, "POST", "/other", call).await, 404);
+    }
+}
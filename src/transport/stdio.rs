@@ -0,0 +1,18 @@
+use std::sync::Arc;
+use tokio::io::BufReader;
+
+use crate::session::Session;
+use crate::McpServer;
+
+/// Serves the process's stdin/stdout until stdin is closed. Results longer
+/// than `MCP_STDIO_MAX_FRAME_BYTES` are split; see [`super::frames`].
+pub async fn serve(server: Arc<McpServer>) -> std::io::Result<()> {
+    let reader = BufReader::new(tokio::io::stdin());
+    let mut session = Session::new()
+        .with_stream_threshold(super::stream::threshold_from_env())
+        .with_max_message(super::stream::max_message_from_env());
+    if let Some(limit) = super::frames::max_frame_from_env() {
+        session = session.with_max_frame(limit);
+    }
+    super::serve_session_lines(server, Arc::new(session), reader, tokio::io::stdout(), None).await
+}
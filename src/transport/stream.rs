@@ -0,0 +1,651 @@
+//! Incremental decoding of messages too large to buffer whole. A line is
+//! read into memory as usual until it passes the session's
+//! [stream threshold](crate::session::Session::with_stream_threshold); the
+//! rest of it is then fed, a chunk at a time as it is read, to a
+//! [`Decoder`] on the connection's own task, which builds the message as the
+//! bytes arrive. No chunk is held once it has been decoded, so a
+//! `tools/call` carrying a multi-megabyte document costs about the size of
+//! that document, not two or three times it, and the message is handed on
+//! as a typed [`Incoming`] without another copy.
+//!
+//! A message longer than the session's
+//! [message limit](crate::session::Session::with_max_message) is refused,
+//! and a long line that is not a message at all is skipped, so no line
+//! costs more than the limit.
+
+use serde_json::{Map, Number, Value};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+use super::{Incoming, Line, Malformed};
+
+/// Used when `MCP_STREAM_MESSAGE_BYTES` is unset.
+pub const DEFAULT_THRESHOLD: usize = 256 * 1024;
+
+/// Used when `MCP_MAX_MESSAGE_BYTES` is unset.
+pub const DEFAULT_MAX_MESSAGE: usize = 64 * 1024 * 1024;
+
+/// Deepest nesting of arrays and objects decoded, as in `serde_json`.
+const MAX_DEPTH: usize = 128;
+
+/// The stream threshold from `MCP_STREAM_MESSAGE_BYTES`, or
+/// [`DEFAULT_THRESHOLD`]. A malformed value is reported and ignored.
+pub fn threshold_from_env() -> usize {
+    bytes_from_env("MCP_STREAM_MESSAGE_BYTES", DEFAULT_THRESHOLD)
+}
+
+/// The message limit from `MCP_MAX_MESSAGE_BYTES`, or
+/// [`DEFAULT_MAX_MESSAGE`]. A malformed value is reported and ignored.
+pub fn max_message_from_env() -> usize {
+    bytes_from_env("MCP_MAX_MESSAGE_BYTES", DEFAULT_MAX_MESSAGE)
+}
+
+fn bytes_from_env(name: &str, default: usize) -> usize {
+    match std::env::var(name) {
+        Ok(value) => value.trim().parse().unwrap_or_else(|_| {
+            tracing::warn!("Invalid {} '{}'", name, value);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+/// How far [`read_line`] got.
+#[derive(Debug, PartialEq)]
+pub enum ReadLine {
+    /// End of input, with nothing read.
+    Eof,
+    /// A whole line, or the last one without its newline, is in the buffer.
+    Line,
+    /// The buffer holds the start of a line longer than the threshold;
+    /// [`parse_rest`] reads the rest of a message, and [`skip_line`] the
+    /// rest of anything else.
+    Oversized,
+}
+
+/// Reads up to and including the next newline into `line`, stopping early
+/// once `line` holds more than `threshold` bytes. Cancel-safe: whatever was
+/// read stays in `line` for the next call.
+pub async fn read_line<R>(
+    reader: &mut R,
+    line: &mut Vec<u8>,
+    threshold: usize,
+) -> std::io::Result<ReadLine>
+where
+    R: AsyncBufRead + Unpin,
+{
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            return Ok(if line.is_empty() {
+                ReadLine::Eof
+            } else {
+                ReadLine::Line
+            });
+        }
+        if let Some(end) = available.iter().position(|&b| b == b'\n') {
+            line.extend_from_slice(&available[..=end]);
+            reader.consume(end + 1);
+            return Ok(ReadLine::Line);
+        }
+        let read = available.len();
+        line.extend_from_slice(available);
+        reader.consume(read);
+        if line.len() > threshold {
+            return Ok(ReadLine::Oversized);
+        }
+    }
+}
+
+/// Decodes the message `start` begins, reading the rest of its line from
+/// `reader` and feeding it to a [`Decoder`] as it arrives. A line longer
+/// than `limit` bytes is refused with `-32600`. The `Err` is a failed read;
+/// otherwise the whole line has been consumed, whatever it held.
+pub async fn parse_rest<R>(
+    reader: &mut R,
+    start: Vec<u8>,
+    limit: usize,
+) -> std::io::Result<Result<Incoming, Malformed>>
+where
+    R: AsyncBufRead + Unpin,
+{
+    let mut decoder = Decoder::new();
+    let mut length = start.len();
+    // Only the front is trimmed: the end of `start` may be inside a string.
+    let message = start.strip_prefix(super::BOM).unwrap_or(&start);
+    let mut failure = feed(&mut decoder, message.trim_ascii_start(), length, limit);
+    drop(start);
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            break;
+        }
+        let (chunk, end) = match available.iter().position(|&b| b == b'\n') {
+            Some(end) => (&available[..end], true),
+            None => (available, false),
+        };
+        length += chunk.len();
+        // Once decoding has failed, the rest of the line is only skipped.
+        if failure.is_none() {
+            failure = feed(&mut decoder, chunk, length, limit);
+            if failure.is_some() {
+                decoder = Decoder::new();
+            }
+        }
+        let read = chunk.len() + usize::from(end);
+        reader.consume(read);
+        if end {
+            break;
+        }
+    }
+    Ok(match failure {
+        Some(malformed) => Err(malformed),
+        None => decoder
+            .finish()
+            .map_err(Malformed::Parse)
+            .and_then(Incoming::from_json),
+    })
+}
+
+/// Feeds `chunk` to `decoder` unless the line, `length` bytes so far, is
+/// over `limit`.
+fn feed(decoder: &mut Decoder, chunk: &[u8], length: usize, limit: usize) -> Option<Malformed> {
+    if length > limit {
+        return Some(Malformed::InvalidRequest(format!(
+            "message is longer than {} bytes",
+            limit
+        )));
+    }
+    decoder.feed(chunk).err().map(Malformed::Parse)
+}
+
+/// Skips the rest of the current line, holding none of it.
+pub async fn skip_line<R>(reader: &mut R) -> std::io::Result<()>
+where
+    R: AsyncBufRead + Unpin,
+{
+    loop {
+        let available = reader.fill_buf().await?;
+        if available.is_empty() {
+            return Ok(());
+        }
+        match available.iter().position(|&b| b == b'\n') {
+            Some(end) => {
+                reader.consume(end + 1);
+                return Ok(());
+            }
+            None => {
+                let read = available.len();
+                reader.consume(read);
+            }
+        }
+    }
+}
+
+/// Whether `line`, the start of an oversized line, is the start of a
+/// message rather than a banner or whitespace.
+pub fn is_message(line: &[u8]) -> bool {
+    matches!(Line::classify(line), Line::Message(_))
+}
+
+/// A JSON parser fed one chunk at a time, which builds the value as the
+/// bytes arrive rather than waiting for all of them. Strings are decoded
+/// straight into the value, so a chunk is never needed again once fed.
+#[derive(Default)]
+pub struct Decoder {
+    /// The arrays and objects still open, innermost last.
+    open: Vec<Open>,
+    expect: Expect,
+    token: Token,
+    value: Option<Value>,
+    /// Bytes fed so far, for error messages.
+    position: usize,
+}
+
+enum Open {
+    Array(Vec<Value>),
+    /// An object and the key of the value being read.
+    Object(Map<String, Value>, Option<String>),
+}
+
+#[derive(Default, Clone, Copy, PartialEq)]
+enum Expect {
+    #[default]
+    Value,
+    /// Just after `[`.
+    ValueOrEnd,
+    /// Just after `{`.
+    KeyOrEnd,
+    Key,
+    Colon,
+    CommaOrEnd,
+    Done,
+}
+
+/// A token read over more than one byte, and so maybe over more than one
+/// chunk.
+#[derive(Default)]
+enum Token {
+    #[default]
+    None,
+    Text(Text, bool),
+    Number(Vec<u8>),
+    Literal(&'static [u8], usize, Value),
+}
+
+/// A string being decoded, after its opening quote.
+#[derive(Default)]
+struct Text {
+    bytes: Vec<u8>,
+    escape: Escape,
+    /// A leading surrogate waiting for its trailing one.
+    high: Option<u16>,
+}
+
+#[derive(Default, Clone, Copy)]
+enum Escape {
+    #[default]
+    None,
+    Backslash,
+    Unicode(u8, u16),
+}
+
+impl Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Decodes the next chunk of the text. An error leaves the decoder
+    /// unusable.
+    pub fn feed(&mut self, mut chunk: &[u8]) -> Result<(), String> {
+        while !chunk.is_empty() {
+            let used = self
+                .step(chunk)
+                .map_err(|e| format!("{} at byte {}", e, self.position))?;
+            self.position += used;
+            chunk = &chunk[used..];
+        }
+        Ok(())
+    }
+
+    /// The value once the text has ended, or why the text is not one whole
+    /// JSON value.
+    pub fn finish(mut self) -> Result<Value, String> {
+        if let Token::Number(_) = self.token {
+            self.end_number()
+                .map_err(|e| format!("{} at byte {}", e, self.position))?;
+        }
+        match self.value {
+            Some(value) if matches!(self.token, Token::None) => Ok(value),
+            _ => Err(format!(
+                "EOF while parsing a value at byte {}",
+                self.position
+            )),
+        }
+    }
+
+    /// Decodes from the start of `chunk` and returns how many bytes were
+    /// used, which is 0 only when a number ended just before it.
+    fn step(&mut self, chunk: &[u8]) -> Result<usize, &'static str> {
+        match &mut self.token {
+            Token::Text(text, key) => {
+                let key = *key;
+                let (used, closed) = text.push(chunk)?;
+                if closed {
+                    let Token::Text(text, _) = std::mem::take(&mut self.token) else {
+                        unreachable!()
+                    };
+                    let text =
+                        String::from_utf8(text.bytes).map_err(|_| "invalid unicode code point")?;
+                    if key {
+                        if let Some(Open::Object(_, pending)) = self.open.last_mut() {
+                            *pending = Some(text);
+                        }
+                        self.expect = Expect::Colon;
+                    } else {
+                        self.complete(Value::String(text));
+                    }
+                }
+                return Ok(used);
+            }
+            Token::Number(digits) => {
+                let used = chunk
+                    .iter()
+                    .position(|b| !matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+                    .unwrap_or(chunk.len());
+                digits.extend_from_slice(&chunk[..used]);
+                if used < chunk.len() {
+                    self.end_number()?;
+                }
+                return Ok(used);
+            }
+            Token::Literal(text, matched, _) => {
+                if chunk[0] != text[*matched] {
+                    return Err("expected ident");
+                }
+                *matched += 1;
+                if *matched == text.len() {
+                    let Token::Literal(_, _, value) = std::mem::take(&mut self.token) else {
+                        unreachable!()
+                    };
+                    self.complete(value);
+                }
+                return Ok(1);
+            }
+            Token::None => {}
+        }
+
+        let byte = chunk[0];
+        if byte.is_ascii_whitespace() {
+            return Ok(1);
+        }
+        match (self.expect, byte) {
+            (Expect::ValueOrEnd, b']') | (Expect::KeyOrEnd, b'}') => self.close(),
+            (Expect::Value | Expect::ValueOrEnd, _) => self.begin_value(byte)?,
+            (Expect::KeyOrEnd | Expect::Key, b'"') => {
+                self.token = Token::Text(Text::default(), true);
+            }
+            (Expect::KeyOrEnd | Expect::Key, _) => return Err("key must be a string"),
+            (Expect::Colon, b':') => self.expect = Expect::Value,
+            (Expect::Colon, _) => return Err("expected `:`"),
+            (Expect::CommaOrEnd, _) => match (self.open.last(), byte) {
+                (Some(Open::Array(_)), b',') => self.expect = Expect::Value,
+                (Some(Open::Object(..)), b',') => self.expect = Expect::Key,
+                (Some(Open::Array(_)), b']') | (Some(Open::Object(..)), b'}') => self.close(),
+                (Some(Open::Array(_)), _) => return Err("expected `,` or `]`"),
+                _ => return Err("expected `,` or `}`"),
+            },
+            (Expect::Done, _) => return Err("trailing characters"),
+        }
+        Ok(1)
+    }
+
+    fn begin_value(&mut self, byte: u8) -> Result<(), &'static str> {
+        match byte {
+            b'{' | b'[' => {
+                if self.open.len() == MAX_DEPTH {
+                    return Err("recursion limit exceeded");
+                }
+                if byte == b'{' {
+                    self.open.push(Open::Object(Map::new(), None));
+                    self.expect = Expect::KeyOrEnd;
+                } else {
+                    self.open.push(Open::Array(Vec::new()));
+                    self.expect = Expect::ValueOrEnd;
+                }
+            }
+            b'"' => self.token = Token::Text(Text::default(), false),
+            b'-' | b'0'..=b'9' => self.token = Token::Number(vec![byte]),
+            b't' => self.token = Token::Literal(b"true", 1, Value::Bool(true)),
+            b'f' => self.token = Token::Literal(b"false", 1, Value::Bool(false)),
+            b'n' => self.token = Token::Literal(b"null", 1, Value::Null),
+            _ => return Err("expected value"),
+        }
+        Ok(())
+    }
+
+    fn end_number(&mut self) -> Result<(), &'static str> {
+        let Token::Number(digits) = std::mem::take(&mut self.token) else {
+            return Ok(());
+        };
+        let number: Number = serde_json::from_slice(&digits).map_err(|_| "invalid number")?;
+        self.complete(Value::Number(number));
+        Ok(())
+    }
+
+    /// Ends the innermost array or object.
+    fn close(&mut self) {
+        let value = match self.open.pop() {
+            Some(Open::Array(items)) => Value::Array(items),
+            Some(Open::Object(map, _)) => Value::Object(map),
+            None => unreachable!("only open containers are closed"),
+        };
+        self.complete(value);
+    }
+
+    /// Places a finished value in its container, or as the whole value.
+    fn complete(&mut self, value: Value) {
+        match self.open.last_mut() {
+            Some(Open::Array(items)) => items.push(value),
+            Some(Open::Object(map, key)) => {
+                map.insert(key.take().unwrap_or_default(), value);
+            }
+            None => {
+                self.value = Some(value);
+                self.expect = Expect::Done;
+                return;
+            }
+        }
+        self.expect = Expect::CommaOrEnd;
+    }
+}
+
+impl Text {
+    /// Decodes from the start of `chunk`, returning how many bytes were
+    /// used and whether the closing quote was among them.
+    fn push(&mut self, chunk: &[u8]) -> Result<(usize, bool), &'static str> {
+        let mut i = 0;
+        while i < chunk.len() {
+            match self.escape {
+                Escape::None => {
+                    let plain = chunk[i..]
+                        .iter()
+                        .position(|&b| b == b'"' || b == b'\\' || b < 0x20)
+                        .unwrap_or(chunk.len() - i);
+                    if plain > 0 {
+                        self.no_surrogate()?;
+                        self.bytes.extend_from_slice(&chunk[i..i + plain]);
+                        i += plain;
+                        continue;
+                    }
+                    match chunk[i] {
+                        b'"' => {
+                            self.no_surrogate()?;
+                            return Ok((i + 1, true));
+                        }
+                        b'\\' => self.escape = Escape::Backslash,
+                        _ => return Err("control character found while parsing a string"),
+                    }
+                }
+                Escape::Backslash => {
+                    let unescaped = match chunk[i] {
+                        b'u' => None,
+                        b'"' => Some(b'"'),
+                        b'\\' => Some(b'\\'),
+                        b'/' => Some(b'/'),
+                        b'b' => Some(0x08),
+                        b'f' => Some(0x0c),
+                        b'n' => Some(b'\n'),
+                        b'r' => Some(b'\r'),
+                        b't' => Some(b'\t'),
+                        _ => return Err("invalid escape"),
+                    };
+                    self.escape = match unescaped {
+                        Some(byte) => {
+                            self.no_surrogate()?;
+                            self.bytes.push(byte);
+                            Escape::None
+                        }
+                        None => Escape::Unicode(0, 0),
+                    };
+                }
+                Escape::Unicode(digits, unit) => {
+                    let digit = (chunk[i] as char).to_digit(16).ok_or("invalid escape")?;
+                    let unit = unit << 4 | digit as u16;
+                    self.escape = if digits < 3 {
+                        Escape::Unicode(digits + 1, unit)
+                    } else {
+                        self.code_unit(unit)?;
+                        Escape::None
+                    };
+                }
+            }
+            i += 1;
+        }
+        Ok((i, false))
+    }
+
+    /// Appends the character a `\u` escape names, pairing surrogates.
+    fn code_unit(&mut self, unit: u16) -> Result<(), &'static str> {
+        let code = match (self.high.take(), unit) {
+            (None, 0xD800..=0xDBFF) => {
+                self.high = Some(unit);
+                return Ok(());
+            }
+            (None, 0xDC00..=0xDFFF) => return Err("lone trailing surrogate in hex escape"),
+            (None, _) => u32::from(unit),
+            (Some(high), 0xDC00..=0xDFFF) => {
+                0x10000 + ((u32::from(high) - 0xD800) << 10) + (u32::from(unit) - 0xDC00)
+            }
+            (Some(_), _) => return Err("lone leading surrogate in hex escape"),
+        };
+        let c = char::from_u32(code).ok_or("invalid unicode code point")?;
+        let mut utf8 = [0; 4];
+        self.bytes
+            .extend_from_slice(c.encode_utf8(&mut utf8).as_bytes());
+        Ok(())
+    }
+
+    /// Fails when a leading surrogate is not followed by its trailing one.
+    fn no_surrogate(&self) -> Result<(), &'static str> {
+        match self.high {
+            Some(_) => Err("lone leading surrogate in hex escape"),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::io::BufReader;
+
+    #[test]
+    fn test_decoder_agrees_with_serde_json_in_any_chunks() {
+        let texts = [
+            r#"{"a": [1, -2.5e3, true, false, null], "b": {"c": "d\"e\\fé😀\n"}}"#,
+            r#"[{}, [], "", 0, 1E+2, {"x": [[]]}]"#,
+            "  \"caf\u{e9} \u{1f600}\"  ",
+            r#"{"a": 1,}"#,
+            r#"{"a" 1}"#,
+            r#"[1 2]"#,
+            r#"{"a": "\ud83d"}"#,
+            r#"{"a": "\q"}"#,
+            "[\"tab\there\"]",
+            r#"{"a": 01}"#,
+            r#"{"a": tru}"#,
+            r#"{"a": 1} x"#,
+            r#"{"a": [1"#,
+            r#"{1: 2}"#,
+        ];
+        for text in texts {
+            let expected: Result<Value, _> = serde_json::from_str(text);
+            for size in [1, 2, 3, 7, text.len()] {
+                let mut decoder = Decoder::new();
+                let decoded = text
+                    .as_bytes()
+                    .chunks(size)
+                    .try_for_each(|chunk| decoder.feed(chunk))
+                    .and_then(|()| decoder.finish());
+                match &expected {
+                    Ok(value) => assert_eq!(decoded.as_ref(), Ok(value), "{}", text),
+                    Err(_) => assert!(decoded.is_err(), "{} decoded as {:?}", text, decoded),
+                }
+            }
+        }
+        let deep = "[".repeat(MAX_DEPTH + 1);
+        let error = Decoder::new().feed(deep.as_bytes()).unwrap_err();
+        assert!(error.contains("recursion limit"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_long_lines_are_parsed_as_they_arrive() {
+        let document = "lorem ipsum ".repeat(10_000);
+        let message = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "method": "tools/call",
+            "params": {"name": "count_tokens", "arguments": {"text": document}}
+        });
+        let input = format!("\u{feff}{}\r\n{{\"jsonrpc\": \"2.0\"}}\n", message);
+        // Reads of 64 bytes at most, so the line arrives in many pieces.
+        let mut reader = BufReader::with_capacity(64, input.as_bytes());
+        let mut line = Vec::new();
+        assert_eq!(
+            read_line(&mut reader, &mut line, 1024).await.unwrap(),
+            ReadLine::Oversized
+        );
+        assert!(line.len() < 2048);
+        assert!(is_message(&line));
+        let parsed = parse_rest(&mut reader, std::mem::take(&mut line), usize::MAX).await;
+        let Ok(Ok(Incoming::Single(request))) = parsed else {
+            panic!("{:?}", parsed);
+        };
+        assert_eq!(request.method, "tools/call");
+        assert_eq!(request.params.unwrap(), message["params"]);
+
+        // The next line is read as usual.
+        assert_eq!(
+            read_line(&mut reader, &mut line, 1024).await.unwrap(),
+            ReadLine::Line
+        );
+        assert_eq!(line, b"{\"jsonrpc\": \"2.0\"}\n");
+        line.clear();
+        assert_eq!(
+            read_line(&mut reader, &mut line, 1024).await.unwrap(),
+            ReadLine::Eof
+        );
+    }
+
+    #[tokio::test]
+    async fn test_malformed_long_lines_are_skipped_whole() {
+        let input = format!(
+            "{{\"text\": \"{}\" oops {}\n[]\n",
+            "a".repeat(4096),
+            "b".repeat(4096)
+        );
+        let mut reader = BufReader::with_capacity(64, input.as_bytes());
+        let mut line = Vec::new();
+        read_line(&mut reader, &mut line, 1024).await.unwrap();
+        let parsed = parse_rest(&mut reader, line, usize::MAX).await.unwrap();
+        let Err(Malformed::Parse(error)) = parsed else {
+            panic!("{:?}", parsed);
+        };
+        assert!(error.contains("expected"), "{}", error);
+
+        let mut line = Vec::new();
+        read_line(&mut reader, &mut line, 1024).await.unwrap();
+        assert_eq!(line, b"[]\n");
+    }
+
+    #[tokio::test]
+    async fn test_lines_past_the_limit_are_refused_and_skipped() {
+        let input = format!(
+            "{{\"text\": \"{}\"}}\n{}\n[]\n",
+            "a".repeat(8192),
+            "banner ".repeat(1024)
+        );
+        let mut reader = BufReader::with_capacity(64, input.as_bytes());
+        let mut line = Vec::new();
+        read_line(&mut reader, &mut line, 1024).await.unwrap();
+        let parsed = parse_rest(&mut reader, line, 4096).await.unwrap();
+        let Err(Malformed::InvalidRequest(error)) = parsed else {
+            panic!("{:?}", parsed);
+        };
+        assert!(error.contains("4096 bytes"), "{}", error);
+
+        // A long line that is no message is dropped without being held.
+        let mut line = Vec::new();
+        assert_eq!(
+            read_line(&mut reader, &mut line, 1024).await.unwrap(),
+            ReadLine::Oversized
+        );
+        assert!(!is_message(&line));
+        assert!(line.len() < 2048);
+        skip_line(&mut reader).await.unwrap();
+
+        let mut line = Vec::new();
+        read_line(&mut reader, &mut line, 1024).await.unwrap();
+        assert_eq!(line, b"[]\n");
+    }
+}
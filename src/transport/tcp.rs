@@ -0,0 +1,155 @@
+use std::io;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::BufReader;
+use tokio::net::TcpListener;
+
+use super::access::ConnectionLimiter;
+use super::auth;
+use crate::McpServer;
+
+/// Listens on `addr` and serves until the listener fails. Built with the
+/// `uring` feature on Linux, this serves on [io_uring](super::uring).
+pub async fn serve(
+    server: Arc<McpServer>,
+    addr: SocketAddr,
+    limiter: Arc<ConnectionLimiter>,
+) -> io::Result<()> {
+    #[cfg(all(feature = "uring", target_os = "linux"))]
+    {
+        super::uring::serve(server, addr, limiter).await
+    }
+    #[cfg(not(all(feature = "uring", target_os = "linux")))]
+    {
+        let listener = TcpListener::bind(addr).await?;
+        tracing::info!("Listening on tcp://{}", listener.local_addr()?);
+        serve_listener(server, listener, limiter).await
+    }
+}
+
+/// Serves line-delimited JSON-RPC on every connection accepted from
+/// `listener`, each in its own task, until the listener fails.
+pub async fn serve_listener(
+    server: Arc<McpServer>,
+    listener: TcpListener,
+    limiter: Arc<ConnectionLimiter>,
+) -> io::Result<()> {
+    let ping_interval = super::ping_interval_from_env();
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        // Refused connections are closed by dropping the stream.
+        let Ok(permit) = limiter.admit(peer.ip()) else {
+            continue;
+        };
+        let server = server.clone();
+        tokio::spawn(async move {
+            let _permit = permit;
+            let (reader, mut writer) = stream.into_split();
+            let mut reader = BufReader::new(reader);
+            let served =
+                match auth::authenticate_lines(&server, &mut reader, &mut writer, peer).await {
+                    Ok(true) => {
+                        super::serve_lines_with_pings(server, reader, writer, ping_interval).await
+                    }
+                    Ok(false) => Ok(()),
+                    Err(e) => Err(e),
+                };
+            if let Err(e) = served {
+                tracing::warn!("TCP connection from {} failed: {}", peer, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+    use tokio::net::TcpStream;
+
+    #[tokio::test]
+    async fn test_serves_concurrent_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_listener(
+            Arc::new(McpServer::new()),
+            listener,
+            Arc::default(),
+        ));
+
+        let mut clients = Vec::new();
+        for i in 0..3 {
+            let stream = TcpStream::connect(addr).await.unwrap();
+            clients.push((i, BufReader::new(stream)));
+        }
+        // Requests are sent on every connection before any is read, so each
+        // connection must be served independently.
+        for (i, client) in &mut clients {
+            let request = format!(
+                r#"{{"jsonrpc":"2.0","id":{},"method":"tools/call","params":{{"name":"echo","arguments":{{"text":"c{}"}}}}}}"#,
+                i, i
+            );
+            let handshake = concat!(
+                r#"{"jsonrpc":"2.0","id":"init","method":"initialize","params":{}}"#,
+                "\n",
+                r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+                "\n"
+            );
+            client
+                .get_mut()
+                .write_all(format!("{}{}\n", handshake, request).as_bytes())
+                .await
+                .unwrap();
+        }
+        for (i, client) in &mut clients {
+            let mut line = String::new();
+            client.read_line(&mut line).await.unwrap();
+            if line.contains(r#""id":"init""#) {
+                line.clear();
+                client.read_line(&mut line).await.unwrap();
+            }
+            let response: Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(response["id"], *i);
+            assert_eq!(
+                response["result"]["content"][0]["text"],
+                format!("Echo: c{}", i)
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_connections_open_with_a_token_when_one_is_required() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut server = McpServer::new();
+        server.set_token_auth(auth::TokenAuth::new(["s3cret"]));
+        tokio::spawn(serve_listener(Arc::new(server), listener, Arc::default()));
+        let ping = r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#;
+
+        let mut refused = BufReader::new(TcpStream::connect(addr).await.unwrap());
+        refused
+            .get_mut()
+            .write_all(format!("{}\n", ping).as_bytes())
+            .await
+            .unwrap();
+        let mut line = String::new();
+        refused.read_line(&mut line).await.unwrap();
+        let response: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(response["error"]["code"], auth::UNAUTHORIZED);
+        line.clear();
+        assert_eq!(refused.read_line(&mut line).await.unwrap(), 0);
+
+        let mut admitted = BufReader::new(TcpStream::connect(addr).await.unwrap());
+        admitted
+            .get_mut()
+            .write_all(format!("Authorization: Bearer s3cret\n{}\n", ping).as_bytes())
+            .await
+            .unwrap();
+        line.clear();
+        admitted.read_line(&mut line).await.unwrap();
+        let response: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(response["id"], 1);
+        assert!(response["error"].is_null());
+    }
+}
@@ -0,0 +1,91 @@
+//! The wire inspector behind `mcp-server --trace-io <file>`: every JSON-RPC
+//! message a transport reads or writes, exactly as it crossed the wire, one
+//! per line with the time, a direction marker and the session:
+//!
+//! ```text
+//! 2026-10-15T09:30:00.125Z --> 4b1f… {"jsonrpc":"2.0","id":1,"method":"tools/list"}
+//! 2026-10-15T09:30:00.127Z <-- 4b1f… {"jsonrpc":"2.0","id":1,"result":{"tools":[]}}
+//! ```
+//!
+//! `-->` is a message from the client and `<--` one to it. Messages
+//! before a session exists, such as an HTTP `initialize`, show `-` for the
+//! session. Line breaks inside a message are escaped, so each record stays
+//! on one line.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use crate::security::rfc3339;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// From the client.
+    In,
+    /// To the client.
+    Out,
+}
+
+impl Direction {
+    fn marker(self) -> &'static str {
+        match self {
+            Self::In => "-->",
+            Self::Out => "<--",
+        }
+    }
+}
+
+/// Where traced messages go.
+pub struct WireTrace {
+    file: Mutex<File>,
+}
+
+impl WireTrace {
+    /// Traces into `path`, replacing what it held.
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            file: Mutex::new(File::create(path)?),
+        })
+    }
+
+    /// Records `message`, as read or written by a transport, for `session`.
+    /// A failed write is reported, and the message still goes out.
+    pub fn record(&self, session: Option<&str>, direction: Direction, message: &[u8]) {
+        let message = String::from_utf8_lossy(message);
+        let message = message.trim_end().replace('\r', "\\r").replace('\n', "\\n");
+        let line = format!(
+            "{} {} {} {}\n",
+            rfc3339(SystemTime::now()),
+            direction.marker(),
+            session.unwrap_or("-"),
+            message
+        );
+        // One write per record, so records from concurrent sessions never
+        // interleave.
+        if let Err(e) = self.file.lock().unwrap().write_all(line.as_bytes()) {
+            tracing::warn!("Failed to write the wire trace: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_records_are_one_line_each() {
+        let path = std::env::temp_dir().join(format!("mcp-trace-{}.log", uuid::Uuid::new_v4()));
+        let trace = WireTrace::create(&path).unwrap();
+        trace.record(Some("s1"), Direction::In, b"{\"id\":1}\n");
+        trace.record(None, Direction::Out, b"{\n  \"id\": 1\n}");
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with("Z --> s1 {\"id\":1}"));
+        assert!(lines[1].ends_with("Z <-- - {\\n  \"id\": 1\\n}"));
+    }
+}
@@ -0,0 +1,296 @@
+//! The tcp transport on io_uring, behind the `uring` feature on Linux.
+//! Reads and writes are submitted to a ring shared by every connection of
+//! a worker thread, rather than made as a syscall each, which cuts the
+//! syscall count when many connections are busy at once. Each worker runs
+//! its own single-threaded runtime and accepts from the same listener, so
+//! the kernel spreads connections across them.
+//!
+//! Where the kernel refuses io_uring, as older kernels and some container
+//! sandboxes do, [`serve`] falls back to the epoll-based
+//! [`tcp::serve_listener`](super::tcp::serve_listener).
+
+use std::future::Future;
+use std::io;
+use std::net::{Shutdown, SocketAddr};
+use std::pin::Pin;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncWrite, ReadBuf};
+use tokio::sync::oneshot;
+use tokio_uring::net::{TcpListener, TcpStream};
+use tokio_uring::BufResult;
+
+use super::access::ConnectionLimiter;
+use super::auth;
+use crate::McpServer;
+
+/// Bytes read from a connection at a time.
+const READ_BUFFER: usize = 16 * 1024;
+
+/// Worker threads from `MCP_URING_THREADS`, or one per CPU. A malformed
+/// value is reported and ignored.
+pub fn threads_from_env() -> usize {
+    let default = || std::thread::available_parallelism().map_or(1, |n| n.get());
+    match std::env::var("MCP_URING_THREADS") {
+        Ok(value) => match value.trim().parse() {
+            Ok(threads) if threads > 0 => threads,
+            _ => {
+                tracing::warn!("Invalid MCP_URING_THREADS '{}'", value);
+                default()
+            }
+        },
+        Err(_) => default(),
+    }
+}
+
+/// Whether this kernel lets the process set up a ring.
+pub fn is_supported() -> bool {
+    // On a thread of its own, since a runtime cannot be built inside
+    // another.
+    std::thread::spawn(|| tokio_uring::Runtime::new(&tokio_uring::builder()).is_ok())
+        .join()
+        .unwrap_or(false)
+}
+
+/// Listens on `addr` and serves until the listener fails, on io_uring if
+/// the kernel allows it.
+pub async fn serve(
+    server: Arc<McpServer>,
+    addr: SocketAddr,
+    limiter: Arc<ConnectionLimiter>,
+) -> io::Result<()> {
+    let listener = std::net::TcpListener::bind(addr)?;
+    tracing::info!("Listening on tcp://{}", listener.local_addr()?);
+    serve_listener(server, listener, limiter).await
+}
+
+/// Serves line-delimited JSON-RPC on every connection accepted from
+/// `listener` until it fails, with [`threads_from_env`] workers.
+pub async fn serve_listener(
+    server: Arc<McpServer>,
+    listener: std::net::TcpListener,
+    limiter: Arc<ConnectionLimiter>,
+) -> io::Result<()> {
+    if !tokio::task::spawn_blocking(is_supported)
+        .await
+        .unwrap_or(false)
+    {
+        tracing::warn!("io_uring is unavailable; serving tcp with epoll");
+        listener.set_nonblocking(true)?;
+        let listener = tokio::net::TcpListener::from_std(listener)?;
+        return super::tcp::serve_listener(server, listener, limiter).await;
+    }
+    let mut workers = Vec::new();
+    for worker in 0..threads_from_env() {
+        let (server, listener, limiter) = (server.clone(), listener.try_clone()?, limiter.clone());
+        let (done, finished) = oneshot::channel();
+        std::thread::Builder::new()
+            .name(format!("mcp-uring-{}", worker))
+            .spawn(move || {
+                let _ = done.send(run_worker(server, listener, limiter));
+            })?;
+        workers.push(finished);
+    }
+    // Workers only return when accepting fails, and then all of them will.
+    for finished in workers {
+        finished
+            .await
+            .unwrap_or_else(|_| Err(io::Error::other("io_uring worker panicked")))?;
+    }
+    Ok(())
+}
+
+fn run_worker(
+    server: Arc<McpServer>,
+    listener: std::net::TcpListener,
+    limiter: Arc<ConnectionLimiter>,
+) -> io::Result<()> {
+    let runtime = tokio_uring::Runtime::new(&tokio_uring::builder())?;
+    runtime.block_on(async move {
+        let ping_interval = super::ping_interval_from_env();
+        let listener = TcpListener::from_std(listener);
+        loop {
+            let (stream, peer) = listener.accept().await?;
+            // Refused connections are closed by dropping the stream.
+            let Ok(permit) = limiter.admit(peer.ip()) else {
+                continue;
+            };
+            let server = server.clone();
+            tokio_uring::spawn(async move {
+                let _permit = permit;
+                let stream = Rc::new(stream);
+                let (mut reader, mut writer) = (Reader::new(stream.clone()), Writer::new(stream));
+                let served = match auth::authenticate_lines(&server, &mut reader, &mut writer, peer)
+                    .await
+                {
+                    Ok(true) => {
+                        super::serve_lines_with_pings(server, reader, writer, ping_interval).await
+                    }
+                    Ok(false) => Ok(()),
+                    Err(e) => Err(e),
+                };
+                if let Err(e) = served {
+                    tracing::warn!("TCP connection from {} failed: {}", peer, e);
+                }
+            });
+        }
+    })
+}
+
+type Pending<T> = Pin<Box<dyn Future<Output = BufResult<T, Vec<u8>>>>>;
+
+/// The reading half of a connection, buffered like a `BufReader`. The
+/// buffer is handed to the ring for each read and comes back filled.
+struct Reader {
+    stream: Rc<TcpStream>,
+    buffer: Vec<u8>,
+    read: usize,
+    pending: Option<Pending<usize>>,
+}
+
+impl Reader {
+    fn new(stream: Rc<TcpStream>) -> Self {
+        Self {
+            stream,
+            buffer: Vec::with_capacity(READ_BUFFER),
+            read: 0,
+            pending: None,
+        }
+    }
+}
+
+impl AsyncBufRead for Reader {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        // The buffer is with the ring while a read is pending.
+        if this.pending.is_some() || this.read == this.buffer.len() {
+            let pending = this.pending.get_or_insert_with(|| {
+                let stream = this.stream.clone();
+                let mut buffer = std::mem::take(&mut this.buffer);
+                buffer.clear();
+                Box::pin(async move { stream.read(buffer).await })
+            });
+            let (result, buffer) = ready!(pending.as_mut().poll(cx));
+            this.pending = None;
+            this.buffer = buffer;
+            this.read = 0;
+            result?;
+        }
+        // Empty once the peer has closed its end.
+        Poll::Ready(Ok(&this.buffer[this.read..]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        self.get_mut().read += amt;
+    }
+}
+
+impl AsyncRead for Reader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let available = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let n = available.len().min(buf.remaining());
+        buf.put_slice(&available[..n]);
+        self.consume(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// The writing half of a connection. A write is copied into a buffer the
+/// ring owns until it completes; the next write, flush or shutdown waits
+/// for it, so at most one is in flight.
+struct Writer {
+    stream: Rc<TcpStream>,
+    /// Reused for each write.
+    spare: Vec<u8>,
+    pending: Option<Pending<()>>,
+}
+
+impl Writer {
+    fn new(stream: Rc<TcpStream>) -> Self {
+        Self {
+            stream,
+            spare: Vec::new(),
+            pending: None,
+        }
+    }
+
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let Some(pending) = &mut self.pending {
+            let (result, buffer) = ready!(pending.as_mut().poll(cx));
+            self.pending = None;
+            self.spare = buffer;
+            result?;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl AsyncWrite for Writer {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        let mut buffer = std::mem::take(&mut this.spare);
+        buffer.clear();
+        buffer.extend_from_slice(buf);
+        let stream = this.stream.clone();
+        this.pending = Some(Box::pin(async move { stream.write_all(buffer).await }));
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_pending(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_pending(cx))?;
+        Poll::Ready(this.stream.shutdown(Shutdown::Write))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+    #[tokio::test]
+    async fn test_serves_connections_with_or_without_uring() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_listener(
+            Arc::new(McpServer::new()),
+            listener,
+            Arc::default(),
+        ));
+
+        let stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let (reader, mut writer) = stream.into_split();
+        let input = concat!(
+            r#"{"jsonrpc":"2.0","id":0,"method":"initialize","params":{}}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","method":"notifications/initialized"}"#,
+            "\n",
+            r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"echo","arguments":{"text":"ring"}}}"#,
+            "\n"
+        );
+        writer.write_all(input.as_bytes()).await.unwrap();
+        let mut lines = BufReader::new(reader).lines();
+        let mut responses = Vec::new();
+        while responses.len() < 2 {
+            let line = lines.next_line().await.unwrap().unwrap();
+            responses.push(serde_json::from_str::<Value>(&line).unwrap());
+        }
+        let echo = responses.iter().find(|r| r["id"] == 1).unwrap();
+        assert_eq!(echo["result"]["content"][0]["text"], "Echo: ring");
+    }
+}
@@ -0,0 +1,299 @@
+//! `mcp-server self-update`, for servers installed outside a package
+//! manager. `MCP_UPDATE_URL` names a JSON manifest of the latest release:
+//!
+//! ```json
+//! {"version": "0.2.0", "targets": {"x86_64-unknown-linux-gnu": {"url": "https://releases.example.com/0.2.0/mcp-server-x86_64-unknown-linux-gnu", "sha256": "<hex>", "signature": "<base64>"}}}
+//! ```
+//!
+//! Each target's Ed25519 `signature`, checked against
+//! `MCP_UPDATE_PUBLIC_KEY`, covers the version, the target and the binary's
+//! SHA-256 (see [`signed_statement`]). It is checked before the version is
+//! compared, so a tampered manifest cannot claim a newer version or point
+//! at an older binary. The downloaded binary must then match `sha256`
+//! before it replaces the running executable; one that does not is never
+//! written. The manifest and binaries are only fetched over HTTPS.
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+use tokio::net::TcpStream;
+
+use crate::crypto;
+use crate::http::client::{self, Url};
+
+const TIMEOUT: Duration = Duration::from_secs(120);
+const MAX_MANIFEST_BYTES: usize = 1024 * 1024;
+const MAX_BINARY_BYTES: usize = 256 * 1024 * 1024;
+
+/// Where releases are published, and the key they are signed with.
+pub struct UpdateConfig {
+    pub manifest: Url,
+    pub public_key: VerifyingKey,
+}
+
+impl UpdateConfig {
+    /// From `MCP_UPDATE_URL`, an `https://` URL, and
+    /// `MCP_UPDATE_PUBLIC_KEY`, a base64 Ed25519 public key. Both are
+    /// required.
+    pub fn from_env() -> Result<Self, String> {
+        let url = std::env::var("MCP_UPDATE_URL").map_err(|_| "MCP_UPDATE_URL is not set")?;
+        let key = std::env::var("MCP_UPDATE_PUBLIC_KEY")
+            .map_err(|_| "MCP_UPDATE_PUBLIC_KEY is not set")?;
+        Ok(Self {
+            manifest: https(&url)?,
+            public_key: public_key(&key)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Manifest {
+    pub version: String,
+    /// Releases by target triple.
+    pub targets: BTreeMap<String, Release>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub url: String,
+    /// Hex SHA-256 of the binary.
+    pub sha256: String,
+    /// Base64 Ed25519 signature of the [`signed_statement`] for this
+    /// release.
+    pub signature: String,
+}
+
+/// What `self-update` found, and whether it installed it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UpdateReport {
+    pub current: String,
+    pub latest: String,
+    pub updated: bool,
+}
+
+/// Fetches the manifest and, when it names a newer version than this
+/// build's, or any version with `force`, downloads, verifies and installs
+/// it over `executable`. With `apply` unset it only reports.
+pub async fn run(
+    config: &UpdateConfig,
+    executable: &Path,
+    apply: bool,
+    force: bool,
+) -> Result<UpdateReport, String> {
+    let current = env!("CARGO_PKG_VERSION");
+    let manifest: Manifest =
+        serde_json::from_slice(&fetch(&config.manifest, MAX_MANIFEST_BYTES).await?)
+            .map_err(|e| format!("Invalid update manifest: {}", e))?;
+    let target = env!("MCP_BUILD_TARGET");
+    let release = manifest
+        .targets
+        .get(target)
+        .ok_or_else(|| format!("Release {} has no binary for {}", manifest.version, target))?;
+    verify(&config.public_key, &manifest.version, target, release)?;
+
+    let mut report = UpdateReport {
+        current: current.to_string(),
+        latest: manifest.version.clone(),
+        updated: false,
+    };
+    if !apply || !(force || newer(&manifest.version, current)) {
+        return Ok(report);
+    }
+
+    let binary = fetch(&https(&release.url)?, MAX_BINARY_BYTES).await?;
+    check_digest(&binary, &release.sha256)?;
+    replace(executable, &binary)?;
+    report.updated = true;
+    Ok(report)
+}
+
+async fn fetch(url: &Url, max_bytes: usize) -> Result<Vec<u8>, String> {
+    let fetch = async {
+        let stream = TcpStream::connect((url.host.as_str(), url.port))
+            .await
+            .map_err(|e| e.to_string())?;
+        client::get(stream, url, max_bytes).await
+    };
+    let fetched = tokio::time::timeout(TIMEOUT, fetch)
+        .await
+        .map_err(|_| format!("Request timed out after {}s", TIMEOUT.as_secs()))??;
+    match fetched.status {
+        200 => Ok(fetched.body),
+        status => Err(format!("GET {} returned HTTP {}", url.path, status)),
+    }
+}
+
+fn https(url: &str) -> Result<Url, String> {
+    let parsed = Url::parse(url)?;
+    if !parsed.tls {
+        return Err(format!("Updates are only fetched over HTTPS, not {}", url));
+    }
+    Ok(parsed)
+}
+
+fn public_key(text: &str) -> Result<VerifyingKey, String> {
+    let bytes: [u8; 32] = crate::base64::decode(text.trim())
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("MCP_UPDATE_PUBLIC_KEY must be a base64 Ed25519 public key")?;
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("Invalid update public key: {}", e))
+}
+
+/// What a release's signature covers: `<version>\n<target>\n<sha256>`,
+/// with the digest in lowercase hex.
+pub fn signed_statement(version: &str, target: &str, sha256: &str) -> String {
+    format!("{}\n{}\n{}", version, target, sha256.to_ascii_lowercase())
+}
+
+/// Checks `release`'s signature over its version, target and digest.
+pub fn verify(
+    key: &VerifyingKey,
+    version: &str,
+    target: &str,
+    release: &Release,
+) -> Result<(), String> {
+    let bytes: [u8; 64] = crate::base64::decode(release.signature.trim())
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("Release signature is not a base64 Ed25519 signature")?;
+    let statement = signed_statement(version, target, &release.sha256);
+    key.verify_strict(statement.as_bytes(), &Signature::from_bytes(&bytes))
+        .map_err(|_| "Release signature does not match; nothing was installed".to_string())
+}
+
+/// Checks a downloaded binary against the signed digest.
+fn check_digest(binary: &[u8], sha256: &str) -> Result<(), String> {
+    if !crypto::hex(&crypto::sha256(binary)).eq_ignore_ascii_case(sha256) {
+        return Err(
+            "Downloaded binary does not match the signed SHA-256; it was not installed".to_string(),
+        );
+    }
+    Ok(())
+}
+
+/// Whether dotted version `latest` is above `current`, comparing numeric
+/// parts and ignoring pre-release suffixes.
+fn newer(latest: &str, current: &str) -> bool {
+    let parts = |version: &str| -> Vec<u64> {
+        let mut parts: Vec<u64> = version
+            .trim_start_matches('v')
+            .split(['-', '+'])
+            .next()
+            .unwrap_or_default()
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect();
+        // So `1.0` and `1.0.0` are the same version.
+        while parts.last() == Some(&0) {
+            parts.pop();
+        }
+        parts
+    };
+    parts(latest) > parts(current)
+}
+
+/// Writes `binary` next to `executable` with the same permissions, then
+/// renames it over the original, so the executable is always either the
+/// old binary or the whole new one.
+fn replace(executable: &Path, binary: &[u8]) -> Result<(), String> {
+    let staged = executable.with_extension("update");
+    let write = || -> std::io::Result<()> {
+        let permissions = std::fs::metadata(executable)?.permissions();
+        let mut file = std::fs::File::create(&staged)?;
+        std::io::Write::write_all(&mut file, binary)?;
+        file.sync_all()?;
+        std::fs::set_permissions(&staged, permissions)?;
+        std::fs::rename(&staged, executable)
+    };
+    write().map_err(|e| {
+        let _ = std::fs::remove_file(&staged);
+        format!(
+            "Failed to install update at {}: {}",
+            executable.display(),
+            e
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    #[test]
+    fn test_signature_covers_version_target_and_digest() {
+        let signing = SigningKey::from_bytes(&[7; 32]);
+        let key = public_key(&crate::base64::encode(signing.verifying_key().as_bytes())).unwrap();
+        let binary = b"\x7fELF new server";
+        let sha256 = crypto::hex(&crypto::sha256(binary));
+        let statement = signed_statement("0.2.0", "x86_64-unknown-linux-gnu", &sha256);
+        let release = Release {
+            url: "https://releases.example.com/0.2.0/mcp-server".to_string(),
+            sha256: sha256.clone(),
+            signature: crate::base64::encode(&signing.sign(statement.as_bytes()).to_bytes()),
+        };
+
+        assert!(verify(&key, "0.2.0", "x86_64-unknown-linux-gnu", &release).is_ok());
+        // A manifest claiming another version or target for the same
+        // signature is refused, so an old release cannot pose as new.
+        assert!(verify(&key, "9.0.0", "x86_64-unknown-linux-gnu", &release).is_err());
+        assert!(verify(&key, "0.2.0", "aarch64-apple-darwin", &release).is_err());
+        let swapped = Release {
+            sha256: crypto::hex(&crypto::sha256(b"\x7fELF old server")),
+            ..release.clone()
+        };
+        assert!(verify(&key, "0.2.0", "x86_64-unknown-linux-gnu", &swapped).is_err());
+        let garbled = Release {
+            signature: "not base64!".to_string(),
+            ..release
+        };
+        assert!(verify(&key, "0.2.0", "x86_64-unknown-linux-gnu", &garbled).is_err());
+        assert!(public_key("AAAA").is_err());
+
+        assert!(check_digest(binary, &sha256.to_uppercase()).is_ok());
+        assert!(check_digest(b"\x7fELF tampered", &sha256).is_err());
+    }
+
+    #[test]
+    fn test_only_https_is_fetched() {
+        assert!(https("https://releases.example.com/latest.json").is_ok());
+        assert!(https("http://releases.example.com/latest.json")
+            .unwrap_err()
+            .contains("only fetched over HTTPS"));
+    }
+
+    #[test]
+    fn test_newer_versions() {
+        assert!(newer("0.2.0", "0.1.0"));
+        assert!(newer("v0.10.0", "0.9.3"));
+        assert!(newer("1.0", "0.9.9"));
+        assert!(!newer("0.1.0", "0.1.0"));
+        assert!(!newer("0.1.0-rc.1", "0.1.0"));
+        assert!(!newer("0.0.9", "0.1.0"));
+        assert!(!newer("0.1.0", "0.1"));
+    }
+
+    #[test]
+    fn test_replace_keeps_permissions() {
+        let dir = std::env::temp_dir().join(format!("update-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let executable = dir.join("mcp-server");
+        std::fs::write(&executable, b"old").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(&executable, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        replace(&executable, b"new").unwrap();
+        assert_eq!(std::fs::read(&executable).unwrap(), b"new");
+        assert!(!executable.with_extension("update").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&executable).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o755);
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,268 @@
+//! Files clients upload over the HTTP transport. An upload is stored in the
+//! session's [workspace](crate::workspace) under `workspace://uploads/` and
+//! then appears in that session's `resources/list`, so a client can hand the
+//! server a file and ask for it to be analysed.
+
+use async_trait::async_trait;
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+use tokio::process::Command;
+
+use crate::session::Session;
+
+pub const DEFAULT_MAX_BYTES: usize = 10 * 1024 * 1024;
+pub const DEFAULT_ALLOWED_TYPES: &str = "text/*,application/json,application/pdf,image/*";
+const SCAN_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A stored upload, as listed in `resources/list`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Upload {
+    pub uri: String,
+    pub name: String,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    pub size: u64,
+}
+
+/// Inspects an upload before it is accepted, e.g. with a virus scanner.
+#[async_trait]
+pub trait UploadScanner: Send + Sync {
+    /// `Err` carries the reason the file at `path` is refused.
+    async fn scan(&self, path: &Path, upload: &Upload) -> Result<(), String>;
+}
+
+/// Runs a command with the file's path appended, such as
+/// `clamscan --no-summary`. Exit status zero accepts the file; otherwise
+/// the first line of its output is the reason.
+pub struct CommandScanner {
+    pub program: String,
+    pub args: Vec<String>,
+}
+
+#[async_trait]
+impl UploadScanner for CommandScanner {
+    async fn scan(&self, path: &Path, _upload: &Upload) -> Result<(), String> {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args).arg(path);
+        let output = crate::script::run_with_input(command, "", SCAN_TIMEOUT).await?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let report = String::from_utf8_lossy(&output.stdout);
+        let reason = report
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("rejected by scanner");
+        Err(reason.trim().to_string())
+    }
+}
+
+/// Why an upload was refused; each variant maps to an HTTP status.
+#[derive(Debug, Clone, PartialEq)]
+pub enum UploadError {
+    InvalidName(String),
+    TooLarge(usize),
+    TypeNotAllowed(String),
+    /// The scanner refused the file.
+    Rejected(String),
+    Failed(String),
+}
+
+impl UploadError {
+    pub fn status(&self) -> u16 {
+        match self {
+            Self::InvalidName(_) => 400,
+            Self::TooLarge(_) => 413,
+            Self::TypeNotAllowed(_) => 415,
+            Self::Rejected(_) => 422,
+            Self::Failed(_) => 500,
+        }
+    }
+
+    pub fn message(&self) -> String {
+        match self {
+            Self::InvalidName(name) => format!("Invalid upload name '{}'", name),
+            Self::TooLarge(max) => format!("Uploads are limited to {} bytes", max),
+            Self::TypeNotAllowed(mime) => format!("Uploads of type '{}' are not allowed", mime),
+            Self::Rejected(reason) => format!("Upload rejected: {}", reason),
+            Self::Failed(e) => format!("Upload failed: {}", e),
+        }
+    }
+}
+
+/// Limits applied to every upload.
+pub struct UploadPolicy {
+    pub max_bytes: usize,
+    /// MIME types, exact or as `type/*`.
+    pub allowed_types: Vec<String>,
+    pub scanner: Option<Box<dyn UploadScanner>>,
+}
+
+impl Default for UploadPolicy {
+    fn default() -> Self {
+        Self {
+            max_bytes: DEFAULT_MAX_BYTES,
+            allowed_types: parse_types(DEFAULT_ALLOWED_TYPES),
+            scanner: None,
+        }
+    }
+}
+
+fn parse_types(list: &str) -> Vec<String> {
+    list.split(',')
+        .map(|t| t.trim().to_ascii_lowercase())
+        .filter(|t| !t.is_empty())
+        .collect()
+}
+
+impl UploadPolicy {
+    /// Policy from `MCP_UPLOAD_MAX_BYTES`, `MCP_UPLOAD_ALLOWED_TYPES`
+    /// (comma-separated) and `MCP_UPLOAD_SCAN_COMMAND`.
+    pub fn from_env() -> Self {
+        let mut policy = Self::default();
+        if let Some(max) = std::env::var("MCP_UPLOAD_MAX_BYTES")
+            .ok()
+            .and_then(|s| s.parse().ok())
+        {
+            policy.max_bytes = max;
+        }
+        if let Ok(types) = std::env::var("MCP_UPLOAD_ALLOWED_TYPES") {
+            policy.allowed_types = parse_types(&types);
+        }
+        if let Ok(command) = std::env::var("MCP_UPLOAD_SCAN_COMMAND") {
+            let mut words = command.split_whitespace().map(str::to_string);
+            if let Some(program) = words.next() {
+                policy.scanner = Some(Box::new(CommandScanner {
+                    program,
+                    args: words.collect(),
+                }));
+            }
+        }
+        policy
+    }
+
+    fn type_allowed(&self, mime_type: &str) -> bool {
+        self.allowed_types
+            .iter()
+            .any(|allowed| match allowed.strip_suffix("/*") {
+                Some(family) => mime_type.split_once('/').is_some_and(|(t, _)| t == family),
+                None => allowed == mime_type,
+            })
+    }
+
+    /// Checks, stores and scans `contents` as `name` in `session`'s
+    /// workspace, replacing an earlier upload of the same name. A file the
+    /// scanner refuses is deleted again, along with any upload it replaced.
+    pub async fn accept(
+        &self,
+        session: &Session,
+        name: &str,
+        mime_type: &str,
+        contents: &[u8],
+    ) -> Result<Upload, UploadError> {
+        if name.is_empty()
+            || name.len() > 255
+            || name.starts_with('.')
+            || name.contains(['/', '\\', '\0'])
+        {
+            return Err(UploadError::InvalidName(name.to_string()));
+        }
+        if contents.len() > self.max_bytes {
+            return Err(UploadError::TooLarge(self.max_bytes));
+        }
+        // Parameters such as `; charset=utf-8` do not affect the check.
+        let mime_type = mime_type
+            .split(';')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_ascii_lowercase();
+        if !self.type_allowed(&mime_type) {
+            return Err(UploadError::TypeNotAllowed(mime_type));
+        }
+
+        let workspace = session.workspace().map_err(UploadError::Failed)?;
+        let upload = Upload {
+            uri: format!("{}uploads/{}", crate::workspace::SCHEME, name),
+            name: name.to_string(),
+            mime_type,
+            size: contents.len() as u64,
+        };
+        workspace
+            .write(&upload.uri, contents)
+            .map_err(UploadError::Failed)?;
+        if let Some(scanner) = &self.scanner {
+            let path = workspace
+                .resolve(&upload.uri)
+                .map_err(UploadError::Failed)?;
+            if let Err(reason) = scanner.scan(&path, &upload).await {
+                let _ = workspace.delete(&upload.uri);
+                session.remove_upload(&upload.uri);
+                return Err(UploadError::Rejected(reason));
+            }
+        }
+        session.add_upload(upload.clone());
+        Ok(upload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct RejectEicar;
+
+    #[async_trait]
+    impl UploadScanner for RejectEicar {
+        async fn scan(&self, path: &Path, _upload: &Upload) -> Result<(), String> {
+            let contents = std::fs::read(path).map_err(|e| e.to_string())?;
+            if contents.starts_with(b"X5O!") {
+                return Err("Eicar-Test-Signature FOUND".to_string());
+            }
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_accept_applies_limits_and_scanner() {
+        let policy = UploadPolicy {
+            max_bytes: 16,
+            scanner: Some(Box::new(RejectEicar)),
+            ..UploadPolicy::default()
+        };
+        let session = Session::new();
+
+        let upload = policy
+            .accept(&session, "notes.txt", "text/plain; charset=utf-8", b"hello")
+            .await
+            .unwrap();
+        assert_eq!(upload.uri, "workspace://uploads/notes.txt");
+        assert_eq!(upload.mime_type, "text/plain");
+        assert_eq!(session.uploads(), vec![upload]);
+
+        let refused = |name: &'static str, mime: &'static str, body: &'static [u8]| {
+            let policy = &policy;
+            let session = &session;
+            async move { policy.accept(session, name, mime, body).await.unwrap_err() }
+        };
+        assert_eq!(refused("../x", "text/plain", b"").await.status(), 400);
+        assert_eq!(
+            refused("big.txt", "text/plain", &[b'a'; 17]).await.status(),
+            413
+        );
+        assert_eq!(
+            refused("a.exe", "application/x-msdownload", b"MZ")
+                .await
+                .status(),
+            415
+        );
+        assert_eq!(
+            refused("eicar.txt", "text/plain", b"X5O!P%@AP").await,
+            UploadError::Rejected("Eicar-Test-Signature FOUND".to_string())
+        );
+        let workspace = session.workspace().unwrap();
+        assert!(workspace.read("workspace://uploads/eicar.txt").is_err());
+        assert_eq!(session.uploads().len(), 1);
+    }
+}
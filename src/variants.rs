@@ -0,0 +1,211 @@
+//! A/B testing of tool descriptions. Each session is shown one variant of a
+//! tool's description, chosen by weight but fixed for the session, and the
+//! outcome of every call is counted against the variant the caller saw, so
+//! authors can tell which wording models use correctly.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// The registered description, which stays in the running unless a
+/// configured variant takes this name.
+pub const CONTROL: &str = "control";
+
+/// An alternative description of one tool.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct VariantConfig {
+    pub name: String,
+    /// Replaces the registered description. Omitted for a `control` entry
+    /// that only sets its weight.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Relative share of sessions shown this variant; 0 takes it out of
+    /// rotation while keeping its counters.
+    #[serde(default = "default_weight")]
+    pub weight: u32,
+}
+
+fn default_weight() -> u32 {
+    1
+}
+
+/// Outcomes of the calls made by sessions shown one variant.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VariantStats {
+    pub calls: u64,
+    pub successes: u64,
+    /// Calls rejected for their arguments, the failures a description
+    /// most directly causes. Also counted in `errors`.
+    pub invalid_arguments: u64,
+    pub errors: u64,
+}
+
+/// How a call went, for [`DescriptionVariants::record`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Success,
+    InvalidArguments,
+    Error,
+}
+
+#[derive(Default)]
+pub struct DescriptionVariants {
+    /// Variants by tool, each list including [`CONTROL`].
+    variants: BTreeMap<String, Vec<VariantConfig>>,
+    stats: Mutex<BTreeMap<String, BTreeMap<String, VariantStats>>>,
+}
+
+impl DescriptionVariants {
+    pub fn new(configs: BTreeMap<String, Vec<VariantConfig>>) -> Result<Self, String> {
+        let mut variants = BTreeMap::new();
+        let mut stats = BTreeMap::new();
+        for (tool, mut configs) in configs {
+            for (i, config) in configs.iter().enumerate() {
+                if configs[..i].iter().any(|c| c.name == config.name) {
+                    return Err(format!(
+                        "Tool '{}' has two variants named '{}'",
+                        tool, config.name
+                    ));
+                }
+                if config.description.is_none() && config.name != CONTROL {
+                    return Err(format!(
+                        "Variant '{}' of tool '{}' has no description",
+                        config.name, tool
+                    ));
+                }
+            }
+            if !configs.iter().any(|c| c.name == CONTROL) {
+                configs.insert(
+                    0,
+                    VariantConfig {
+                        name: CONTROL.to_string(),
+                        description: None,
+                        weight: default_weight(),
+                    },
+                );
+            }
+            if configs.iter().all(|c| c.weight == 0) {
+                return Err(format!("Every variant of tool '{}' has weight 0", tool));
+            }
+            let counters = configs
+                .iter()
+                .map(|c| (c.name.clone(), VariantStats::default()))
+                .collect();
+            stats.insert(tool.clone(), counters);
+            variants.insert(tool, configs);
+        }
+        Ok(Self {
+            variants,
+            stats: Mutex::new(stats),
+        })
+    }
+
+    /// Reads a JSON file of variant lists keyed by tool name.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Self::new(serde_json::from_str(&text).map_err(|e| e.to_string())?)
+    }
+
+    /// Variants from `MCP_TOOL_VARIANTS_FILE`, or none when unset. A file
+    /// that fails to load is reported and ignored.
+    pub fn from_env() -> Self {
+        let Ok(path) = std::env::var("MCP_TOOL_VARIANTS_FILE") else {
+            return Self::default();
+        };
+        Self::load(Path::new(&path)).unwrap_or_else(|e| {
+            tracing::warn!("Failed to load tool variants from {}: {}", path, e);
+            Self::default()
+        })
+    }
+
+    /// The variant of `tool` that `session` sees, with its description, or
+    /// `None` when the tool is not being tested. Requests outside a session
+    /// see the control.
+    pub fn assign(&self, tool: &str, session: Option<&str>) -> Option<(&str, Option<&str>)> {
+        let variants = self.variants.get(tool)?;
+        let Some(session) = session else {
+            let control = variants.iter().find(|v| v.name == CONTROL)?;
+            return Some((CONTROL, control.description.as_deref()));
+        };
+        let total: u64 = variants.iter().map(|v| u64::from(v.weight)).sum();
+        let digest = crate::crypto::sha256(format!("{}\0{}", session, tool).as_bytes());
+        let mut point = u64::from_be_bytes(digest[..8].try_into().unwrap()) % total;
+        for variant in variants {
+            if point < u64::from(variant.weight) {
+                return Some((&variant.name, variant.description.as_deref()));
+            }
+            point -= u64::from(variant.weight);
+        }
+        unreachable!("the point is below the total weight")
+    }
+
+    pub fn record(&self, tool: &str, variant: &str, outcome: Outcome) {
+        let mut stats = self.stats.lock().unwrap();
+        let Some(stats) = stats.get_mut(tool).and_then(|s| s.get_mut(variant)) else {
+            return;
+        };
+        stats.calls += 1;
+        match outcome {
+            Outcome::Success => stats.successes += 1,
+            Outcome::InvalidArguments => {
+                stats.invalid_arguments += 1;
+                stats.errors += 1;
+            }
+            Outcome::Error => stats.errors += 1,
+        }
+    }
+
+    /// Counters of every variant, by tool and then variant name.
+    pub fn stats(&self) -> BTreeMap<String, BTreeMap<String, VariantStats>> {
+        self.stats.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: serde_json::Value) -> Result<DescriptionVariants, String> {
+        DescriptionVariants::new(serde_json::from_value(json).unwrap())
+    }
+
+    #[test]
+    fn test_assigns_sessions_by_weight() {
+        let variants = parse(serde_json::json!({
+            "echo": [
+                {"name": "terse", "description": "Echo text", "weight": 3},
+                {"name": "control", "weight": 1}
+            ]
+        }))
+        .unwrap();
+        assert_eq!(variants.assign("other", Some("s")), None);
+        assert_eq!(variants.assign("echo", None), Some((CONTROL, None)));
+
+        let mut shown = BTreeMap::new();
+        for i in 0..400 {
+            let session = format!("session-{}", i);
+            let (name, _) = variants.assign("echo", Some(&session)).unwrap();
+            // The same session always sees the same variant.
+            assert_eq!(variants.assign("echo", Some(&session)).unwrap().0, name);
+            *shown.entry(name.to_string()).or_insert(0) += 1;
+        }
+        assert!((250..350).contains(&shown["terse"]), "{:?}", shown);
+
+        variants.record("echo", "terse", Outcome::Success);
+        variants.record("echo", "terse", Outcome::InvalidArguments);
+        variants.record("echo", "control", Outcome::Error);
+        let stats = variants.stats();
+        assert_eq!(stats["echo"]["terse"].calls, 2);
+        assert_eq!(stats["echo"]["terse"].invalid_arguments, 1);
+        assert_eq!(stats["echo"]["terse"].errors, 1);
+        assert_eq!(stats["echo"]["control"].errors, 1);
+
+        assert!(parse(serde_json::json!({"echo": [{"name": "a"}]})).is_err());
+        assert!(parse(serde_json::json!({
+            "echo": [{"name": "control", "weight": 0}]
+        }))
+        .is_err());
+    }
+}
@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use std::time::Duration;
+use tokio::net::TcpStream;
+
+use super::{Backend, DirEntry};
+use crate::http::client::{self, Url};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_BYTES: usize = 16 * 1024 * 1024;
+
+/// Files served by an `http://` or `https://` server, read-only. HTTP has no directory
+/// listing, so the mount lists nothing and paths must be known.
+pub struct HttpBackend {
+    base: Url,
+}
+
+impl HttpBackend {
+    pub fn new(base_url: &str) -> Result<Self, String> {
+        let mut base = Url::parse(base_url)?;
+        if !base.path.ends_with('/') {
+            base.path.push('/');
+        }
+        Ok(Self { base })
+    }
+}
+
+/// Percent-encodes each segment of a `/`-separated path.
+pub(crate) fn encode_path(path: &str) -> String {
+    let mut encoded = String::new();
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[async_trait]
+impl Backend for HttpBackend {
+    async fn list(&self, _prefix: &str, _limit: usize) -> Result<Vec<DirEntry>, String> {
+        Ok(Vec::new())
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, String> {
+        let url = Url {
+            path: format!("{}{}", self.base.path, encode_path(path)),
+            ..self.base.clone()
+        };
+        let fetch = async {
+            let stream = TcpStream::connect((url.host.as_str(), url.port))
+                .await
+                .map_err(|e| e.to_string())?;
+            client::get(stream, &url, MAX_BYTES).await
+        };
+        let fetched = tokio::time::timeout(TIMEOUT, fetch)
+            .await
+            .map_err(|_| format!("Request timed out after {}s", TIMEOUT.as_secs()))??;
+        match fetched.status {
+            200 => Ok(fetched.body),
+            404 => Err(format!("{} not found", path)),
+            status => Err(format!("GET {} returned HTTP {}", url.path, status)),
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+
+use super::{Backend, DirEntry};
+
+/// A directory on the local disk. Hidden files and directories, such as
+/// `.env` and `.git`, are neither listed nor reached, and symlinks leading
+/// outside the directory or to a hidden file are not followed. Besides
+/// [`Mount`](super::Mount)s, [`FileProvider`](crate::resources::file::FileProvider)
+/// and session [`Workspace`](crate::workspace::Workspace)s resolve their
+/// paths through one, so every local path follows the same rules.
+pub struct LocalDir {
+    root: PathBuf,
+}
+
+impl LocalDir {
+    pub fn new(root: impl AsRef<Path>) -> Result<Self, String> {
+        let root = root.as_ref();
+        Ok(Self {
+            root: root
+                .canonicalize()
+                .map_err(|e| format!("Failed to open {}: {}", root.display(), e))?,
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// The file `path` names, if it is a plain relative path without hidden
+    /// components and stays inside the root once symlinks in its existing
+    /// ancestors are resolved.
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, String> {
+        let normal = super::normalize(path)?;
+        if normal.split('/').any(is_hidden) {
+            return Err(format!("'{}' is hidden", path));
+        }
+        let full = self.root.join(&normal);
+        let mut existing = full.as_path();
+        while !existing.exists() {
+            existing = existing
+                .parent()
+                .ok_or_else(|| format!("Invalid path '{}'", path))?;
+        }
+        let real = existing.canonicalize().map_err(|e| e.to_string())?;
+        let Ok(relative) = real.strip_prefix(&self.root) else {
+            return Err(format!("'{}' is outside the mount", path));
+        };
+        if relative
+            .iter()
+            .any(|part| is_hidden(&part.to_string_lossy()))
+        {
+            return Err(format!("'{}' is hidden", path));
+        }
+        Ok(full)
+    }
+
+    /// The files at or under `prefix`, sorted by path, at most `limit`.
+    pub fn files(&self, prefix: &str, limit: usize) -> Result<Vec<DirEntry>, String> {
+        let start = self.resolve(prefix)?;
+        let mut entries = Vec::new();
+        if start.is_file() {
+            let size = std::fs::metadata(&start).map_err(|e| e.to_string())?.len();
+            entries.push(DirEntry {
+                path: prefix.to_string(),
+                size,
+            });
+        } else if start.is_dir() {
+            collect(&self.root, &start, limit, &mut entries);
+        }
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(entries)
+    }
+}
+
+#[async_trait]
+impl Backend for LocalDir {
+    async fn list(&self, prefix: &str, limit: usize) -> Result<Vec<DirEntry>, String> {
+        self.files(prefix, limit)
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, String> {
+        let file = self.resolve(path)?;
+        std::fs::read(&file).map_err(|e| format!("Failed to read {}: {}", path, e))
+    }
+
+    async fn write(&self, path: &str, contents: &[u8]) -> Result<(), String> {
+        let file = self.resolve(path)?;
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(&file, contents).map_err(|e| format!("Failed to write {}: {}", path, e))
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), String> {
+        let file = self.resolve(path)?;
+        std::fs::remove_file(&file).map_err(|e| format!("Failed to delete {}: {}", path, e))
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+}
+
+fn collect(root: &Path, dir: &Path, limit: usize, entries: &mut Vec<DirEntry>) {
+    let Ok(children) = std::fs::read_dir(dir) else {
+        return;
+    };
+    for child in children.flatten() {
+        if entries.len() >= limit {
+            return;
+        }
+        if is_hidden(&child.file_name().to_string_lossy()) {
+            continue;
+        }
+        let Ok(file_type) = child.file_type() else {
+            continue;
+        };
+        let path = child.path();
+        if file_type.is_dir() {
+            collect(root, &path, limit, entries);
+        } else if file_type.is_file() {
+            let relative = path.strip_prefix(root).unwrap_or(&path);
+            entries.push(DirEntry {
+                path: relative.to_string_lossy().replace('\\', "/"),
+                size: child.metadata().map_or(0, |m| m.len()),
+            });
+        }
+    }
+}
+
+fn is_hidden(name: &str) -> bool {
+    name.starts_with('.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stays_inside_root() {
+        let base = std::env::temp_dir().join(format!("mcp-vfs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(base.join("root/docs")).unwrap();
+        std::fs::write(base.join("outside.txt"), "secret").unwrap();
+        std::fs::write(base.join("root/.hidden"), "x").unwrap();
+        let dir = LocalDir::new(base.join("root")).unwrap();
+
+        dir.write("docs/a.md", b"# A").await.unwrap();
+        dir.write("new/dir/b.txt", b"b").await.unwrap();
+        let listed: Vec<String> = dir
+            .list("", 10)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|e| e.path)
+            .collect();
+        assert_eq!(listed, vec!["docs/a.md", "new/dir/b.txt"]);
+        assert_eq!(dir.read("docs/a.md").await.unwrap(), b"# A");
+        assert!(dir.read(".hidden").await.unwrap_err().contains("hidden"));
+        assert!(dir.write(".git/config", b"x").await.is_err());
+
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(base.join("outside.txt"), base.join("root/link")).unwrap();
+            assert!(dir.read("link").await.unwrap_err().contains("outside"));
+            std::os::unix::fs::symlink(base.join("root/.hidden"), base.join("root/alias")).unwrap();
+            assert!(dir.read("alias").await.unwrap_err().contains("hidden"));
+        }
+        dir.delete("docs/a.md").await.unwrap();
+        assert!(dir.read("docs/a.md").await.is_err());
+        std::fs::remove_dir_all(&base).unwrap();
+    }
+}
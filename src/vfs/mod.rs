@@ -0,0 +1,534 @@
+//! A mount table of file backends. Each [`Mount`] puts a backend, such as a
+//! local directory, an S3 bucket, a tar archive or an HTTP server, under a
+//! URI prefix like `docs://` or `s3://reports/`. The `fs_*` tools and the
+//! [`VfsProvider`] resource provider go through the [`Vfs`], so paths are
+//! checked the same way everywhere and every mount's access rules and quota
+//! apply whichever backend is behind it.
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path};
+
+use crate::resources::{Resource, ResourceContents, ResourceProvider, ResourceTemplate};
+
+pub mod http;
+pub mod local;
+pub mod s3;
+pub mod tar;
+
+/// Listing stops after this many files so a large mount cannot flood the
+/// client.
+pub const MAX_LISTED_FILES: usize = 1000;
+
+/// A file in a backend listing, with its path relative to the mount.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DirEntry {
+    pub path: String,
+    pub size: u64,
+}
+
+/// Storage behind a mount. Paths are relative, `/`-separated and already
+/// normalized by [`normalize`]; the empty path is the mount's root.
+#[async_trait]
+pub trait Backend: Send + Sync {
+    /// Every file under `prefix`, recursively, up to `limit` entries.
+    async fn list(&self, prefix: &str, limit: usize) -> Result<Vec<DirEntry>, String>;
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, String>;
+
+    /// Creates or replaces a file. Backends that cannot change keep the
+    /// default, which refuses.
+    async fn write(&self, _path: &str, _contents: &[u8]) -> Result<(), String> {
+        Err("This backend is read-only".to_string())
+    }
+
+    async fn delete(&self, _path: &str) -> Result<(), String> {
+        Err("This backend is read-only".to_string())
+    }
+
+    /// Whether [`write`](Self::write) and [`delete`](Self::delete) can work.
+    fn writable(&self) -> bool {
+        false
+    }
+}
+
+/// Turns a relative path into its canonical `a/b/c` form, refusing anything
+/// that could leave the root: `..`, absolute paths and drive prefixes.
+pub fn normalize(path: &str) -> Result<String, String> {
+    let mut parts = Vec::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => match part.to_str() {
+                Some(part) if !part.contains('\\') => parts.push(part),
+                _ => return Err(format!("Invalid path '{}'", path)),
+            },
+            Component::CurDir => {}
+            _ => return Err(format!("Invalid path '{}'", path)),
+        }
+    }
+    Ok(parts.join("/"))
+}
+
+/// What a tool may do on a mount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    Read,
+    Write,
+}
+
+/// A backend under a URI prefix, with its access rules.
+pub struct Mount {
+    pub prefix: String,
+    pub backend: Box<dyn Backend>,
+    pub read_only: bool,
+    /// Tools allowed to use the mount; `None` allows every tool.
+    pub tools: Option<Vec<String>>,
+    /// Most bytes the mount may hold, checked on every write.
+    pub quota: Option<u64>,
+}
+
+impl Mount {
+    /// A read-only mount open to every tool. `prefix` must contain `://`
+    /// and end in `/` unless it ends in `://`.
+    pub fn new(prefix: &str, backend: impl Backend + 'static) -> Result<Self, String> {
+        if !prefix.contains("://") || !prefix.ends_with('/') {
+            return Err(format!(
+                "Mount prefix '{}' must look like 'name://' or 'name://dir/'",
+                prefix
+            ));
+        }
+        Ok(Self {
+            prefix: prefix.to_string(),
+            backend: Box::new(backend),
+            read_only: true,
+            tools: None,
+            quota: None,
+        })
+    }
+
+    pub fn writable(mut self) -> Self {
+        self.read_only = false;
+        self
+    }
+
+    pub fn for_tools(mut self, tools: Vec<String>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    pub fn with_quota(mut self, bytes: u64) -> Self {
+        self.quota = Some(bytes);
+        self
+    }
+
+    fn check(&self, tool: &str, operation: Operation) -> Result<(), String> {
+        if self
+            .tools
+            .as_ref()
+            .is_some_and(|tools| !tools.iter().any(|t| t == tool))
+        {
+            return Err(format!("{} may not use {}", tool, self.prefix));
+        }
+        if operation == Operation::Write && (self.read_only || !self.backend.writable()) {
+            return Err(format!("{} is read-only", self.prefix));
+        }
+        Ok(())
+    }
+}
+
+/// The mount table.
+#[derive(Default)]
+pub struct Vfs {
+    /// Longest prefix first, so `s3://a/b/` wins over `s3://a/`.
+    mounts: Vec<Mount>,
+}
+
+impl Vfs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `mount`, replacing any mount with the same prefix.
+    pub fn mount(&mut self, mount: Mount) {
+        self.mounts.retain(|m| m.prefix != mount.prefix);
+        self.mounts.push(mount);
+        self.mounts
+            .sort_by_key(|m| std::cmp::Reverse(m.prefix.len()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mounts.is_empty()
+    }
+
+    pub fn mounts(&self) -> impl Iterator<Item = &Mount> {
+        self.mounts.iter()
+    }
+
+    /// The mount a URI falls under and the normalized path within it.
+    pub fn resolve(&self, uri: &str) -> Result<(&Mount, String), String> {
+        let (mount, rest) = self
+            .mounts
+            .iter()
+            .find_map(|m| uri.strip_prefix(&m.prefix).map(|rest| (m, rest)))
+            .ok_or_else(|| format!("No mount for '{}'", uri))?;
+        Ok((mount, normalize(rest)?))
+    }
+
+    fn authorize(
+        &self,
+        tool: &str,
+        uri: &str,
+        operation: Operation,
+    ) -> Result<(&Mount, String), String> {
+        let (mount, path) = self.resolve(uri)?;
+        mount.check(tool, operation)?;
+        Ok((mount, path))
+    }
+
+    /// Files under `uri` as full URIs.
+    pub async fn list(&self, tool: &str, uri: &str) -> Result<Vec<DirEntry>, String> {
+        let (mount, path) = self.authorize(tool, uri, Operation::Read)?;
+        let entries = mount.backend.list(&path, MAX_LISTED_FILES).await?;
+        Ok(entries
+            .into_iter()
+            .map(|entry| DirEntry {
+                path: format!("{}{}", mount.prefix, entry.path),
+                size: entry.size,
+            })
+            .collect())
+    }
+
+    pub async fn read(&self, tool: &str, uri: &str) -> Result<Vec<u8>, String> {
+        let (mount, path) = self.authorize(tool, uri, Operation::Read)?;
+        if path.is_empty() {
+            return Err(format!("'{}' is a directory", uri));
+        }
+        mount.backend.read(&path).await
+    }
+
+    /// Writes unless the mount is read-only, closed to `tool`, or would
+    /// grow past its quota.
+    pub async fn write(&self, tool: &str, uri: &str, contents: &[u8]) -> Result<(), String> {
+        let (mount, path) = self.authorize(tool, uri, Operation::Write)?;
+        if path.is_empty() {
+            return Err(format!("'{}' is a directory", uri));
+        }
+        if let Some(quota) = mount.quota {
+            let entries = mount.backend.list("", usize::MAX).await?;
+            let used: u64 = entries
+                .iter()
+                .filter(|e| e.path != path)
+                .map(|e| e.size)
+                .sum();
+            let after = used + contents.len() as u64;
+            if after > quota {
+                return Err(format!(
+                    "Quota of {} bytes for {} exceeded ({} bytes would be used)",
+                    quota, mount.prefix, after
+                ));
+            }
+        }
+        mount.backend.write(&path, contents).await
+    }
+
+    pub async fn delete(&self, tool: &str, uri: &str) -> Result<(), String> {
+        let (mount, path) = self.authorize(tool, uri, Operation::Write)?;
+        if path.is_empty() {
+            return Err(format!("'{}' is a directory", uri));
+        }
+        mount.backend.delete(&path).await
+    }
+
+    /// Reads a `{"mounts": [...]}` JSON file; see [`MountConfig`].
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let file: VfsFile = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+        let mut vfs = Self::new();
+        for config in file.mounts {
+            vfs.mount(config.build()?);
+        }
+        Ok(vfs)
+    }
+
+    /// Mounts from `MCP_VFS_FILE`, or none when unset. A file that fails to
+    /// load is reported and ignored.
+    pub fn from_env() -> Self {
+        let Ok(path) = std::env::var("MCP_VFS_FILE") else {
+            return Self::default();
+        };
+        Self::load(Path::new(&path)).unwrap_or_else(|e| {
+            tracing::warn!("Failed to load mounts from {}: {}", path, e);
+            Self::default()
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct VfsFile {
+    mounts: Vec<MountConfig>,
+}
+
+/// One entry of the mounts file.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MountConfig {
+    pub prefix: String,
+    #[serde(flatten)]
+    pub backend: BackendConfig,
+    #[serde(default = "default_read_only")]
+    pub read_only: bool,
+    pub tools: Option<Vec<String>>,
+    pub quota_bytes: Option<u64>,
+}
+
+fn default_read_only() -> bool {
+    true
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum BackendConfig {
+    Local {
+        path: String,
+    },
+    Tar {
+        path: String,
+    },
+    Http {
+        url: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    S3 {
+        endpoint: String,
+        bucket: String,
+        #[serde(default = "default_region")]
+        region: String,
+        /// Key prefix within the bucket.
+        #[serde(default)]
+        root: String,
+        #[serde(default = "default_access_key_env")]
+        access_key_env: String,
+        #[serde(default = "default_secret_key_env")]
+        secret_key_env: String,
+    },
+}
+
+fn default_region() -> String {
+    "us-east-1".to_string()
+}
+
+fn default_access_key_env() -> String {
+    "AWS_ACCESS_KEY_ID".to_string()
+}
+
+fn default_secret_key_env() -> String {
+    "AWS_SECRET_ACCESS_KEY".to_string()
+}
+
+impl MountConfig {
+    pub fn build(self) -> Result<Mount, String> {
+        let env = |name: &str| {
+            std::env::var(name).map_err(|_| format!("{} is not set for {}", name, self.prefix))
+        };
+        let mut mount = match &self.backend {
+            BackendConfig::Local { path } => Mount::new(&self.prefix, local::LocalDir::new(path)?),
+            BackendConfig::Tar { path } => Mount::new(&self.prefix, tar::TarArchive::open(path)?),
+            BackendConfig::Http { url } => Mount::new(&self.prefix, http::HttpBackend::new(url)?),
+            BackendConfig::S3 {
+                endpoint,
+                bucket,
+                region,
+                root,
+                access_key_env,
+                secret_key_env,
+            } => Mount::new(
+                &self.prefix,
+                s3::S3Bucket {
+                    endpoint: s3::endpoint(endpoint)?,
+                    bucket: bucket.clone(),
+                    region: region.clone(),
+                    root: normalize(root)?,
+                    credentials: s3::Credentials {
+                        access_key: env(access_key_env)?,
+                        secret_key: env(secret_key_env)?,
+                    },
+                },
+            ),
+        }?;
+        mount.read_only = self.read_only;
+        mount.tools = self.tools;
+        mount.quota = self.quota_bytes;
+        Ok(mount)
+    }
+}
+
+/// Serves every mount's files as resources under their mount URIs.
+pub struct VfsProvider {
+    pub vfs: std::sync::Arc<Vfs>,
+}
+
+/// Tool name checked against mount ACLs for resource reads.
+pub const RESOURCES_CLIENT: &str = "resources";
+
+#[async_trait]
+impl ResourceProvider for VfsProvider {
+    async fn list(&self) -> Result<Vec<Resource>, String> {
+        let mut listed = Vec::new();
+        for mount in self.vfs.mounts() {
+            if mount.check(RESOURCES_CLIENT, Operation::Read).is_err() {
+                continue;
+            }
+            let remaining = MAX_LISTED_FILES.saturating_sub(listed.len());
+            if remaining == 0 {
+                break;
+            }
+            for entry in mount.backend.list("", remaining).await? {
+                listed.push(Resource {
+                    uri: format!("{}{}", mount.prefix, entry.path),
+                    mime_type: crate::resources::file::mime_type(Path::new(&entry.path))
+                        .map(str::to_string),
+                    name: entry.path,
+                    description: None,
+                });
+            }
+        }
+        Ok(listed)
+    }
+
+    /// Any file of a readable mount, since listings stop at
+    /// [`MAX_LISTED_FILES`].
+    fn templates(&self) -> Vec<ResourceTemplate> {
+        self.vfs
+            .mounts()
+            .filter(|mount| mount.check(RESOURCES_CLIENT, Operation::Read).is_ok())
+            .map(|mount| ResourceTemplate {
+                uri_template: format!("{}{{+path}}", mount.prefix),
+                name: mount.prefix.clone(),
+                description: Some("A file on the mount".to_string()),
+                mime_type: None,
+            })
+            .collect()
+    }
+
+    async fn read(&self, uri: &str) -> Result<Option<Vec<ResourceContents>>, String> {
+        if self.vfs.resolve(uri).is_err() {
+            return Ok(None);
+        }
+        let bytes = self.vfs.read(RESOURCES_CLIENT, uri).await?;
+        let mime = crate::resources::file::mime_type(Path::new(uri)).map(str::to_string);
+        Ok(Some(vec![match String::from_utf8(bytes) {
+            Ok(text) => ResourceContents::text(uri, mime, text),
+            Err(e) => ResourceContents::blob(uri, mime, e.as_bytes()),
+        }]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    #[derive(Default)]
+    struct MemoryBackend(Mutex<BTreeMap<String, Vec<u8>>>);
+
+    #[async_trait]
+    impl Backend for MemoryBackend {
+        async fn list(&self, prefix: &str, limit: usize) -> Result<Vec<DirEntry>, String> {
+            Ok(self
+                .0
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(path, _)| path.starts_with(prefix))
+                .take(limit)
+                .map(|(path, bytes)| DirEntry {
+                    path: path.clone(),
+                    size: bytes.len() as u64,
+                })
+                .collect())
+        }
+
+        async fn read(&self, path: &str) -> Result<Vec<u8>, String> {
+            self.0
+                .lock()
+                .unwrap()
+                .get(path)
+                .cloned()
+                .ok_or_else(|| format!("{} not found", path))
+        }
+
+        async fn write(&self, path: &str, contents: &[u8]) -> Result<(), String> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(path.to_string(), contents.to_vec());
+            Ok(())
+        }
+
+        fn writable(&self) -> bool {
+            true
+        }
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("a/./b/c.txt").unwrap(), "a/b/c.txt");
+        assert_eq!(normalize("").unwrap(), "");
+        assert!(normalize("../etc/passwd").is_err());
+        assert!(normalize("a/../../b").is_err());
+        assert!(normalize("/etc/passwd").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_mounts_apply_acls_and_quotas() {
+        let mut vfs = Vfs::new();
+        vfs.mount(Mount::new("mem://", MemoryBackend::default()).unwrap());
+        vfs.mount(
+            Mount::new("mem://scratch/", MemoryBackend::default())
+                .unwrap()
+                .writable()
+                .for_tools(vec!["fs_write".to_string(), "fs_read".to_string()])
+                .with_quota(8),
+        );
+        assert!(Mount::new("mem", MemoryBackend::default()).is_err());
+
+        vfs.write("fs_write", "mem://scratch/a.txt", b"12345")
+            .await
+            .unwrap();
+        // Replacing a file only counts the difference.
+        vfs.write("fs_write", "mem://scratch/a.txt", b"1234567")
+            .await
+            .unwrap();
+        assert!(vfs
+            .write("fs_write", "mem://scratch/b.txt", b"12")
+            .await
+            .unwrap_err()
+            .contains("Quota"));
+        assert_eq!(
+            vfs.read("fs_read", "mem://scratch/./a.txt").await.unwrap(),
+            b"1234567"
+        );
+        assert!(vfs
+            .read("other", "mem://scratch/a.txt")
+            .await
+            .unwrap_err()
+            .contains("may not use"));
+        assert!(vfs.read("fs_read", "mem://scratch/../a.txt").await.is_err());
+        assert!(vfs
+            .write("fs_write", "mem://a.txt", b"x")
+            .await
+            .unwrap_err()
+            .contains("read-only"));
+        assert!(vfs.read("fs_read", "elsewhere://a").await.is_err());
+
+        let listed = vfs.list("fs_read", "mem://scratch/").await.unwrap();
+        assert_eq!(
+            listed,
+            vec![DirEntry {
+                path: "mem://scratch/a.txt".to_string(),
+                size: 7
+            }]
+        );
+    }
+}
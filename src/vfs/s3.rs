@@ -0,0 +1,265 @@
+//! An S3-compatible bucket over HTTPS, such as AWS, MinIO or a VPC
+//! endpoint, addressed path-style and signed with AWS Signature Version 4.
+
+use async_trait::async_trait;
+use std::time::{Duration, SystemTime};
+use tokio::net::TcpStream;
+
+use super::http::encode_path;
+use super::{Backend, DirEntry};
+use crate::crypto;
+use crate::http::client::{self, Fetched, Url};
+
+const TIMEOUT: Duration = Duration::from_secs(30);
+const MAX_BYTES: usize = 16 * 1024 * 1024;
+
+pub struct Credentials {
+    pub access_key: String,
+    pub secret_key: String,
+}
+
+pub struct S3Bucket {
+    pub endpoint: Url,
+    pub bucket: String,
+    pub region: String,
+    /// Key prefix the mount is rooted at, without a trailing `/`.
+    pub root: String,
+    pub credentials: Credentials,
+}
+
+/// Parses an `https://` endpoint. Plain HTTP is refused: it would expose
+/// every object read or written, and the signed requests could be replayed.
+pub fn endpoint(url: &str) -> Result<Url, String> {
+    let endpoint = Url::parse(url)?;
+    if !endpoint.tls {
+        return Err(format!("S3 endpoint {} must be an https:// URL", url));
+    }
+    Ok(endpoint)
+}
+
+/// The SigV4 key for one day, region and service.
+fn signing_key(secret: &str, date: &str, region: &str, service: &str) -> [u8; 32] {
+    let key = crypto::hmac_sha256(format!("AWS4{}", secret).as_bytes(), date.as_bytes());
+    let key = crypto::hmac_sha256(&key, region.as_bytes());
+    let key = crypto::hmac_sha256(&key, service.as_bytes());
+    crypto::hmac_sha256(&key, b"aws4_request")
+}
+
+/// Query-string encoding as SigV4 wants it: everything but unreserved
+/// characters, including `/`.
+fn encode_query(value: &str) -> String {
+    encode_path(value).replace('/', "%2F")
+}
+
+impl S3Bucket {
+    fn key(&self, path: &str) -> String {
+        match (self.root.is_empty(), path.is_empty()) {
+            (true, _) => path.to_string(),
+            (false, true) => self.root.clone(),
+            (false, false) => format!("{}/{}", self.root, path),
+        }
+    }
+
+    /// Sends a signed request for `key` with `query` pairs, which must be
+    /// sorted by name.
+    async fn send(
+        &self,
+        method: &str,
+        key: &str,
+        query: &[(&str, String)],
+        body: &[u8],
+    ) -> Result<Fetched, String> {
+        let stamp = crate::security::rfc3339(SystemTime::now());
+        // `2024-01-02T03:04:05.678Z` becomes `20240102T030405Z`.
+        let amz_date = format!("{}Z", &stamp.replace(['-', ':'], "")[..15]);
+        let date = &amz_date[..8];
+        let payload_hash = crypto::hex(&crypto::sha256(body));
+        let host = self.endpoint.authority();
+
+        let path = format!("/{}/{}", self.bucket, encode_path(key));
+        let query = query
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, encode_query(value)))
+            .collect::<Vec<_>>()
+            .join("&");
+        let canonical = format!(
+            "{}\n{}\n{}\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\nhost;x-amz-content-sha256;x-amz-date\n{}",
+            method, path, query, host, payload_hash, amz_date, payload_hash
+        );
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            amz_date,
+            scope,
+            crypto::hex(&crypto::sha256(canonical.as_bytes()))
+        );
+        let key = signing_key(&self.credentials.secret_key, date, &self.region, "s3");
+        let signature = crypto::hex(&crypto::hmac_sha256(&key, to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders=host;x-amz-content-sha256;x-amz-date, Signature={}",
+            self.credentials.access_key, scope, signature
+        );
+
+        let url = Url {
+            path: if query.is_empty() {
+                path
+            } else {
+                format!("{}?{}", path, query)
+            },
+            ..self.endpoint.clone()
+        };
+        let headers = [
+            ("x-amz-content-sha256", payload_hash),
+            ("x-amz-date", amz_date.clone()),
+            ("Authorization", authorization),
+        ];
+        let exchange = async {
+            let stream = TcpStream::connect((url.host.as_str(), url.port))
+                .await
+                .map_err(|e| e.to_string())?;
+            client::request(stream, method, &url, &headers, body, MAX_BYTES).await
+        };
+        let fetched = tokio::time::timeout(TIMEOUT, exchange)
+            .await
+            .map_err(|_| format!("S3 request timed out after {}s", TIMEOUT.as_secs()))??;
+        if !(200..300).contains(&fetched.status) {
+            let body = String::from_utf8_lossy(&fetched.body);
+            let code = tag(&body, "Code").unwrap_or_default();
+            return Err(format!(
+                "S3 {} {} returned HTTP {} {}",
+                method, url.path, fetched.status, code
+            ));
+        }
+        Ok(fetched)
+    }
+}
+
+/// The text of the first `<name>` element in `xml`, unescaped.
+fn tag(xml: &str, name: &str) -> Option<String> {
+    let open = format!("<{}>", name);
+    let start = xml.find(&open)? + open.len();
+    let end = start + xml[start..].find(&format!("</{}>", name))?;
+    Some(
+        xml[start..end]
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&"),
+    )
+}
+
+/// Entries and continuation token of a `ListObjectsV2` response page.
+fn parse_listing(xml: &str) -> (Vec<(String, u64)>, Option<String>) {
+    let objects = xml
+        .split("<Contents>")
+        .skip(1)
+        .filter_map(|object| {
+            let key = tag(object, "Key")?;
+            let size = tag(object, "Size")?.parse().ok()?;
+            Some((key, size))
+        })
+        .collect();
+    let next = (tag(xml, "IsTruncated").as_deref() == Some("true"))
+        .then(|| tag(xml, "NextContinuationToken"))
+        .flatten();
+    (objects, next)
+}
+
+#[async_trait]
+impl Backend for S3Bucket {
+    async fn list(&self, prefix: &str, limit: usize) -> Result<Vec<DirEntry>, String> {
+        let mut key_prefix = self.key(prefix);
+        if !key_prefix.is_empty() {
+            key_prefix.push('/');
+        }
+        let root = if self.root.is_empty() {
+            String::new()
+        } else {
+            format!("{}/", self.root)
+        };
+        let mut entries = Vec::new();
+        let mut token: Option<String> = None;
+        loop {
+            let mut query = Vec::new();
+            if let Some(token) = &token {
+                query.push(("continuation-token", token.clone()));
+            }
+            query.push(("list-type", "2".to_string()));
+            query.push(("prefix", key_prefix.clone()));
+            let fetched = self.send("GET", "", &query, &[]).await?;
+            let (objects, next) = parse_listing(&String::from_utf8_lossy(&fetched.body));
+            for (key, size) in objects {
+                if entries.len() >= limit {
+                    return Ok(entries);
+                }
+                if let Some(path) = key.strip_prefix(&root).filter(|p| !p.ends_with('/')) {
+                    entries.push(DirEntry {
+                        path: path.to_string(),
+                        size,
+                    });
+                }
+            }
+            match next {
+                Some(next) => token = Some(next),
+                None => return Ok(entries),
+            }
+        }
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, String> {
+        Ok(self.send("GET", &self.key(path), &[], &[]).await?.body)
+    }
+
+    async fn write(&self, path: &str, contents: &[u8]) -> Result<(), String> {
+        self.send("PUT", &self.key(path), &[], contents).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, path: &str) -> Result<(), String> {
+        self.send("DELETE", &self.key(path), &[], &[]).await?;
+        Ok(())
+    }
+
+    fn writable(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_signing_and_listing() {
+        // From the AWS Signature Version 4 documentation.
+        assert_eq!(
+            crypto::hex(&signing_key(
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+                "20120215",
+                "us-east-1",
+                "iam"
+            )),
+            "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d"
+        );
+        assert_eq!(encode_query("a b/c"), "a%20b%2Fc");
+        assert_eq!(endpoint("https://minio:9000").unwrap().port, 9000);
+        assert!(endpoint("http://minio:9000")
+            .unwrap_err()
+            .contains("must be an https:// URL"));
+
+        let xml = "<ListBucketResult><IsTruncated>true</IsTruncated>\
+            <Contents><Key>docs/a&amp;b.md</Key><Size>12</Size></Contents>\
+            <Contents><Key>docs/c.md</Key><Size>3</Size></Contents>\
+            <NextContinuationToken>t1</NextContinuationToken></ListBucketResult>";
+        let (objects, next) = parse_listing(xml);
+        assert_eq!(
+            objects,
+            vec![
+                ("docs/a&b.md".to_string(), 12),
+                ("docs/c.md".to_string(), 3)
+            ]
+        );
+        assert_eq!(next.as_deref(), Some("t1"));
+    }
+}
@@ -0,0 +1,168 @@
+use async_trait::async_trait;
+use std::collections::BTreeMap;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use super::{normalize, Backend, DirEntry};
+
+const BLOCK: u64 = 512;
+
+/// A read-only view of an uncompressed tar archive. The index is read when
+/// the archive is opened; file contents are read on demand.
+pub struct TarArchive {
+    path: PathBuf,
+    /// Offset and size of each regular file's contents, by normalized path.
+    files: BTreeMap<String, (u64, u64)>,
+}
+
+impl TarArchive {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, String> {
+        let path = path.as_ref().to_path_buf();
+        let mut file = std::fs::File::open(&path)
+            .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+        let files = index(&mut file).map_err(|e| format!("{}: {}", path.display(), e))?;
+        Ok(Self { path, files })
+    }
+}
+
+fn index(archive: &mut impl Read) -> Result<BTreeMap<String, (u64, u64)>, String> {
+    let mut files = BTreeMap::new();
+    let mut offset = 0;
+    // Set by a GNU `L` entry for the header that follows it.
+    let mut long_name: Option<String> = None;
+    let mut header = [0u8; BLOCK as usize];
+    loop {
+        match archive.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.to_string()),
+        }
+        offset += BLOCK;
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let size = octal(&header[124..136])?;
+        let padded = size.div_ceil(BLOCK) * BLOCK;
+        let mut name = field(&header[0..100]);
+        if &header[257..262] == b"ustar" {
+            let prefix = field(&header[345..500]);
+            if !prefix.is_empty() {
+                name = format!("{}/{}", prefix, name);
+            }
+        }
+        match header[156] {
+            b'L' => {
+                let mut data = vec![0; padded as usize];
+                archive.read_exact(&mut data).map_err(|e| e.to_string())?;
+                long_name = Some(field(&data[..size as usize]));
+            }
+            kind => {
+                let name = long_name.take().unwrap_or(name);
+                if kind == b'0' || kind == 0 {
+                    if let Ok(path) = normalize(&name) {
+                        files.insert(path, (offset, size));
+                    }
+                }
+                std::io::copy(&mut archive.by_ref().take(padded), &mut std::io::sink())
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        offset += padded;
+    }
+    Ok(files)
+}
+
+fn field(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|&b| b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).to_string()
+}
+
+fn octal(bytes: &[u8]) -> Result<u64, String> {
+    let text = field(bytes);
+    let text = text.trim();
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).map_err(|_| "Malformed tar header".to_string())
+}
+
+#[async_trait]
+impl Backend for TarArchive {
+    async fn list(&self, prefix: &str, limit: usize) -> Result<Vec<DirEntry>, String> {
+        Ok(self
+            .files
+            .iter()
+            .filter(|(path, _)| {
+                prefix.is_empty()
+                    || path.as_str() == prefix
+                    || path
+                        .strip_prefix(prefix)
+                        .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .take(limit)
+            .map(|(path, (_, size))| DirEntry {
+                path: path.clone(),
+                size: *size,
+            })
+            .collect())
+    }
+
+    async fn read(&self, path: &str) -> Result<Vec<u8>, String> {
+        let (offset, size) = *self
+            .files
+            .get(path)
+            .ok_or_else(|| format!("{} is not in the archive", path))?;
+        let mut file = std::fs::File::open(&self.path).map_err(|e| e.to_string())?;
+        file.seek(SeekFrom::Start(offset))
+            .map_err(|e| e.to_string())?;
+        let mut contents = vec![0; size as usize];
+        file.read_exact(&mut contents).map_err(|e| e.to_string())?;
+        Ok(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(name: &str, size: usize, kind: u8) -> Vec<u8> {
+        let mut header = vec![0u8; BLOCK as usize];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        let size = format!("{:011o}\0", size);
+        header[124..136].copy_from_slice(size.as_bytes());
+        header[156] = kind;
+        header[257..263].copy_from_slice(b"ustar\0");
+        header
+    }
+
+    fn entry(archive: &mut Vec<u8>, name: &str, contents: &[u8], kind: u8) {
+        archive.extend(header(name, contents.len(), kind));
+        archive.extend_from_slice(contents);
+        let padding = (BLOCK as usize - contents.len() % BLOCK as usize) % BLOCK as usize;
+        archive.extend(std::iter::repeat_n(0, padding));
+    }
+
+    #[tokio::test]
+    async fn test_indexes_and_reads_files() {
+        let mut archive = Vec::new();
+        entry(&mut archive, "docs/", b"", b'5');
+        entry(&mut archive, "docs/readme.md", b"# Hello", b'0');
+        let long = format!("{}/deep.txt", "d".repeat(120));
+        entry(&mut archive, "././@LongLink", long.as_bytes(), b'L');
+        entry(&mut archive, "truncated", b"deep", b'0');
+        entry(&mut archive, "../escape.txt", b"no", b'0');
+        archive.extend([0; 1024]);
+        let path = std::env::temp_dir().join(format!("mcp-vfs-{}.tar", uuid::Uuid::new_v4()));
+        std::fs::write(&path, &archive).unwrap();
+
+        let tar = TarArchive::open(&path).unwrap();
+        let listed = tar.list("", 10).await.unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[1].path, "docs/readme.md");
+        assert_eq!(tar.read("docs/readme.md").await.unwrap(), b"# Hello");
+        assert_eq!(tar.read(&long).await.unwrap(), b"deep");
+        assert_eq!(tar.list("docs", 10).await.unwrap().len(), 1);
+        assert!(tar.write("docs/new.md", b"x").await.is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,232 @@
+use serde::Deserialize;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::task::JoinHandle;
+
+use crate::crypto;
+use crate::events::{Event, EventBus, EventKind};
+use crate::http;
+
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An endpoint that receives the selected events as JSON POSTs.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    pub events: Vec<EventKind>,
+    /// When set, each delivery carries `X-MCP-Signature-256: sha256=<hex>`,
+    /// the HMAC-SHA256 of the body under this secret.
+    #[serde(default)]
+    pub secret: Option<String>,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry; it doubles on each further attempt.
+    #[serde(default = "default_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_retry_delay_ms() -> u64 {
+    1000
+}
+
+#[derive(Debug, Deserialize)]
+struct WebhookFile {
+    webhooks: Vec<Webhook>,
+}
+
+/// Reads a `{"webhooks": [...]}` JSON file, rejecting unusable URLs.
+pub fn load(path: &Path) -> Result<Vec<Webhook>, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let file: WebhookFile = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+    for hook in &file.webhooks {
+        http::Url::parse(&hook.url)?;
+    }
+    Ok(file.webhooks)
+}
+
+/// Webhooks from `MCP_WEBHOOKS_FILE`, or none when unset. A file that fails
+/// to load is reported and ignored.
+pub fn from_env() -> Vec<Webhook> {
+    let Ok(path) = std::env::var("MCP_WEBHOOKS_FILE") else {
+        return Vec::new();
+    };
+    load(Path::new(&path)).unwrap_or_else(|e| {
+        tracing::warn!("Failed to load webhooks from {}: {}", path, e);
+        Vec::new()
+    })
+}
+
+/// Value of the signature header for `body`.
+pub fn sign(secret: &str, body: &[u8]) -> String {
+    format!(
+        "sha256={}",
+        crypto::hex(&crypto::hmac_sha256(secret.as_bytes(), body))
+    )
+}
+
+/// Delivers every matching event from `events` to the webhooks, each
+/// delivery in its own task so a slow endpoint does not hold up others.
+pub fn spawn(events: &EventBus, hooks: Vec<Webhook>) -> JoinHandle<()> {
+    let mut receiver = events.subscribe();
+    let hooks: Vec<Arc<Webhook>> = hooks.into_iter().map(Arc::new).collect();
+    tokio::spawn(async move {
+        loop {
+            let event = match receiver.recv().await {
+                Ok(event) => Arc::new(event),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!("Webhooks fell behind; {} events not delivered", skipped);
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+            for hook in hooks.iter().filter(|h| h.events.contains(&event.kind)) {
+                let (hook, event) = (hook.clone(), event.clone());
+                tokio::spawn(async move {
+                    if let Err(e) = deliver(&hook, &event).await {
+                        tracing::warn!("Webhook delivery to {} failed: {}", hook.url, e);
+                    }
+                });
+            }
+        }
+    })
+}
+
+/// POSTs `event`, retrying with exponential backoff until a 2xx response
+/// or the retries are used up.
+pub async fn deliver(hook: &Webhook, event: &Event) -> Result<(), String> {
+    let url = http::Url::parse(&hook.url)?;
+    let body = serde_json::to_vec(event).unwrap();
+    let kind = serde_json::to_value(event.kind).unwrap();
+    let mut headers = vec![
+        ("Content-Type", "application/json".to_string()),
+        ("X-MCP-Event", kind.as_str().unwrap_or_default().to_string()),
+        ("X-MCP-Delivery", event.id.clone()),
+    ];
+    if let Some(secret) = &hook.secret {
+        headers.push(("X-MCP-Signature-256", sign(secret, &body)));
+    }
+
+    let mut delay = Duration::from_millis(hook.retry_delay_ms);
+    let mut attempt = 0;
+    loop {
+        let error = match http::post(&url, &headers, &body, DELIVERY_TIMEOUT).await {
+            Ok(status) if (200..300).contains(&status) => return Ok(()),
+            Ok(status) => format!("HTTP {}", status),
+            Err(e) => e,
+        };
+        if attempt >= hook.max_retries {
+            return Err(format!("{} after {} attempts", error, attempt + 1));
+        }
+        attempt += 1;
+        tokio::time::sleep(delay).await;
+        delay *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Answers each connection with the next status and returns the raw
+    /// requests it received.
+    async fn endpoint(statuses: Vec<u16>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for status in statuses {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                // Read headers, then the body announced by Content-Length.
+                loop {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_string();
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length: usize = text
+                            .lines()
+                            .find_map(|l| l.strip_prefix("Content-Length: "))
+                            .and_then(|l| l.trim().parse().ok())
+                            .unwrap_or(0);
+                        if request.len() >= end + 4 + length {
+                            break;
+                        }
+                    }
+                }
+                stream
+                    .write_all(
+                        format!("HTTP/1.1 {} X\r\nContent-Length: 0\r\n\r\n", status).as_bytes(),
+                    )
+                    .await
+                    .unwrap();
+                requests.push(String::from_utf8(request).unwrap());
+            }
+            requests
+        });
+        (url, handle)
+    }
+
+    fn hook(url: String) -> Webhook {
+        Webhook {
+            url,
+            events: vec![EventKind::ToolCallCompleted],
+            secret: Some("s3cret".to_string()),
+            max_retries: 2,
+            retry_delay_ms: 1,
+        }
+    }
+
+    #[test]
+    fn test_sign_is_hmac_of_body() {
+        assert_eq!(
+            sign("Jefe", b"what do ya want for nothing?"),
+            "sha256=5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delivery_is_signed_and_retried() {
+        let (url, requests) = endpoint(vec![500, 200]).await;
+        let bus = EventBus::new();
+        let mut receiver = bus.subscribe();
+        bus.publish(
+            EventKind::ToolCallCompleted,
+            serde_json::json!({"tool": "echo"}),
+        );
+        let event = receiver.recv().await.unwrap();
+
+        deliver(&hook(url), &event).await.unwrap();
+
+        let requests = requests.await.unwrap();
+        assert_eq!(requests.len(), 2);
+        let (headers, body) = requests[1].split_once("\r\n\r\n").unwrap();
+        assert!(headers.contains("X-MCP-Event: tool_call_completed\r\n"));
+        assert!(requests[1].contains(&format!(
+            "X-MCP-Signature-256: {}\r\n",
+            sign("s3cret", body.as_bytes())
+        )));
+        let payload: serde_json::Value = serde_json::from_str(body).unwrap();
+        assert_eq!(payload["data"]["tool"], "echo");
+    }
+
+    #[tokio::test]
+    async fn test_delivery_gives_up_after_retries() {
+        let (url, requests) = endpoint(vec![503, 503, 503]).await;
+        let bus = EventBus::new();
+        let mut receiver = bus.subscribe();
+        bus.publish(EventKind::ToolCallCompleted, serde_json::json!({}));
+        let event = receiver.recv().await.unwrap();
+
+        let err = deliver(&hook(url), &event).await.unwrap_err();
+        assert_eq!(err, "HTTP 503 after 3 attempts");
+        assert_eq!(requests.await.unwrap().len(), 3);
+    }
+}
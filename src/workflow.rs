@@ -0,0 +1,407 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::assertions;
+
+/// A named procedure: the states an instance moves through and the tool
+/// calls allowed to move it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Workflow {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub initial: String,
+    pub states: BTreeMap<String, State>,
+}
+
+/// A state with no transitions is final.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct State {
+    #[serde(default)]
+    pub transitions: Vec<Transition>,
+}
+
+/// Calling `tool` moves the instance to `to`, provided every guard passes.
+/// Guards are `assert` assertions evaluated with the call's arguments as
+/// `actual`, e.g. `{"path": "/env", "op": "eq", "expected": "staging"}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Transition {
+    pub tool: String,
+    pub to: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub guards: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkflowFile {
+    workflows: Vec<Workflow>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Step {
+    pub from: String,
+    pub to: String,
+    pub tool: String,
+    pub at: u64,
+}
+
+/// One run of a workflow.
+pub struct Instance {
+    pub id: String,
+    pub workflow: Arc<Workflow>,
+    pub state: String,
+    pub history: Vec<Step>,
+}
+
+impl Instance {
+    /// The transition a call to `tool` with `arguments` would take, or why
+    /// it is not allowed from the current state.
+    pub fn transition(&self, tool: &str, arguments: &Value) -> Result<Transition, String> {
+        let transitions = &self.workflow.states[&self.state].transitions;
+        if transitions.is_empty() {
+            return Err(format!("Workflow instance '{}' is complete", self.id));
+        }
+        let transition = transitions.iter().find(|t| t.tool == tool).ok_or_else(|| {
+            format!(
+                "Tool '{}' is not allowed in state '{}'; allowed: {}",
+                tool,
+                self.state,
+                self.allowed_tools().join(", ")
+            )
+        })?;
+
+        if !transition.guards.is_empty() {
+            let guards: Vec<Value> = transition
+                .guards
+                .iter()
+                .map(|guard| {
+                    let mut guard = guard.clone();
+                    guard["actual"] = arguments.clone();
+                    guard
+                })
+                .collect();
+            let report = assertions::evaluate(&guards)?;
+            if report["passed"] != true {
+                let failed: Vec<String> = report["results"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter(|r| r["passed"] != true)
+                    .map(|r| match r["message"].as_str() {
+                        Some(message) => message.to_string(),
+                        None => format!("guard {}", r["index"]),
+                    })
+                    .collect();
+                return Err(format!(
+                    "Guard failed for '{}' in state '{}': {}",
+                    tool,
+                    self.state,
+                    failed.join(", ")
+                ));
+            }
+        }
+        Ok(transition.clone())
+    }
+
+    /// Moves to the transition's target and records the step.
+    pub fn advance(&mut self, transition: &Transition) {
+        self.history.push(Step {
+            from: self.state.clone(),
+            to: transition.to.clone(),
+            tool: transition.tool.clone(),
+            at: unix_now(),
+        });
+        self.state = transition.to.clone();
+    }
+
+    pub fn allowed_tools(&self) -> Vec<&str> {
+        self.workflow.states[&self.state]
+            .transitions
+            .iter()
+            .map(|t| t.tool.as_str())
+            .collect()
+    }
+
+    pub fn status(&self) -> Value {
+        let allowed = self.allowed_tools();
+        serde_json::json!({
+            "instanceId": self.id,
+            "workflow": self.workflow.name,
+            "state": self.state,
+            "completed": allowed.is_empty(),
+            "allowedTools": allowed,
+            "history": self.history,
+        })
+    }
+}
+
+/// Workflow definitions and their running instances, backing the
+/// `workflow_*` tools.
+#[derive(Default)]
+pub struct WorkflowEngine {
+    definitions: RwLock<HashMap<String, Arc<Workflow>>>,
+    instances: Mutex<HashMap<String, Arc<tokio::sync::Mutex<Instance>>>>,
+}
+
+impl WorkflowEngine {
+    /// Engine with the workflows from `MCP_WORKFLOWS_FILE`, or none when
+    /// unset. A file that fails to load is reported and ignored.
+    pub fn from_env() -> Self {
+        let engine = Self::default();
+        if let Ok(path) = std::env::var("MCP_WORKFLOWS_FILE") {
+            if let Err(e) = engine.load(Path::new(&path)) {
+                tracing::warn!("Failed to load workflows from {}: {}", path, e);
+            }
+        }
+        engine
+    }
+
+    /// Adds every workflow in a `{"workflows": [...]}` JSON file.
+    pub fn load(&self, path: &Path) -> Result<(), String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let file: WorkflowFile = serde_json::from_str(&text).map_err(|e| e.to_string())?;
+        for workflow in file.workflows {
+            self.define(workflow)?;
+        }
+        Ok(())
+    }
+
+    /// Adds a workflow after checking that its states and guards are
+    /// consistent, replacing any workflow with the same name.
+    pub fn define(&self, workflow: Workflow) -> Result<(), String> {
+        let invalid = |reason: String| format!("Invalid workflow '{}': {}", workflow.name, reason);
+        if !workflow.states.contains_key(&workflow.initial) {
+            return Err(invalid(format!(
+                "initial state '{}' is not defined",
+                workflow.initial
+            )));
+        }
+        for (name, state) in &workflow.states {
+            for transition in &state.transitions {
+                if !workflow.states.contains_key(&transition.to) {
+                    return Err(invalid(format!(
+                        "state '{}' transitions to undefined state '{}'",
+                        name, transition.to
+                    )));
+                }
+                if transition.tool.starts_with("workflow_") {
+                    return Err(invalid(format!(
+                        "state '{}' cannot transition through '{}'",
+                        name, transition.tool
+                    )));
+                }
+                let guards: Vec<Value> = transition
+                    .guards
+                    .iter()
+                    .map(|guard| {
+                        let mut guard = guard.clone();
+                        guard["actual"] = serde_json::json!({});
+                        guard
+                    })
+                    .collect();
+                assertions::evaluate(&guards).map_err(|e| {
+                    invalid(format!(
+                        "guard on '{}' in state '{}': {}",
+                        transition.tool, name, e
+                    ))
+                })?;
+            }
+        }
+
+        self.definitions
+            .write()
+            .unwrap()
+            .insert(workflow.name.clone(), Arc::new(workflow));
+        Ok(())
+    }
+
+    pub fn definitions(&self) -> Vec<Arc<Workflow>> {
+        let mut definitions: Vec<_> = self.definitions.read().unwrap().values().cloned().collect();
+        definitions.sort_by(|a, b| a.name.cmp(&b.name));
+        definitions
+    }
+
+    /// Starts a new instance of `workflow` in its initial state.
+    pub fn start(&self, workflow: &str) -> Result<Arc<tokio::sync::Mutex<Instance>>, String> {
+        let workflow = self
+            .definitions
+            .read()
+            .unwrap()
+            .get(workflow)
+            .cloned()
+            .ok_or_else(|| format!("Unknown workflow '{}'", workflow))?;
+        let instance = Instance {
+            id: uuid::Uuid::new_v4().to_string(),
+            state: workflow.initial.clone(),
+            workflow,
+            history: Vec::new(),
+        };
+        let id = instance.id.clone();
+        let instance = Arc::new(tokio::sync::Mutex::new(instance));
+        self.instances.lock().unwrap().insert(id, instance.clone());
+        Ok(instance)
+    }
+
+    pub fn instance(&self, id: &str) -> Option<Arc<tokio::sync::Mutex<Instance>>> {
+        self.instances.lock().unwrap().get(id).cloned()
+    }
+
+    /// Drops instances that finished more than `max_age` ago and returns
+    /// how many. Instances in use are left for the next pass.
+    pub fn expire_finished(&self, max_age: Duration) -> usize {
+        let cutoff = unix_now().saturating_sub(max_age.as_secs());
+        let mut instances = self.instances.lock().unwrap();
+        let before = instances.len();
+        instances.retain(|_, instance| {
+            let Ok(instance) = instance.try_lock() else {
+                return true;
+            };
+            let finished_at = instance.history.last().map(|step| step.at);
+            !(instance.allowed_tools().is_empty() && finished_at.is_some_and(|at| at <= cutoff))
+        });
+        before - instances.len()
+    }
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn release() -> Workflow {
+        serde_json::from_value(json!({
+            "name": "release",
+            "initial": "draft",
+            "states": {
+                "draft": {"transitions": [{"tool": "python_run", "to": "tested"}]},
+                "tested": {"transitions": [{
+                    "tool": "echo",
+                    "to": "released",
+                    "guards": [{"path": "/text", "op": "matches", "expected": "^approved", "message": "needs approval"}]
+                }]},
+                "released": {}
+            }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_transitions_follow_definition() {
+        let engine = WorkflowEngine::default();
+        engine.define(release()).unwrap();
+
+        let instance = engine.start("release").unwrap();
+        let mut instance = instance.lock().await;
+        assert_eq!(instance.state, "draft");
+
+        let err = instance.transition("echo", &json!({})).unwrap_err();
+        assert_eq!(
+            err,
+            "Tool 'echo' is not allowed in state 'draft'; allowed: python_run"
+        );
+
+        let transition = instance.transition("python_run", &json!({})).unwrap();
+        instance.advance(&transition);
+
+        let err = instance
+            .transition("echo", &json!({"text": "ship it"}))
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "Guard failed for 'echo' in state 'tested': needs approval"
+        );
+
+        let transition = instance
+            .transition("echo", &json!({"text": "approved by ops"}))
+            .unwrap();
+        instance.advance(&transition);
+
+        let status = instance.status();
+        assert_eq!(status["state"], "released");
+        assert_eq!(status["completed"], true);
+        assert_eq!(status["history"].as_array().unwrap().len(), 2);
+        assert!(instance
+            .transition("echo", &json!({}))
+            .unwrap_err()
+            .ends_with("is complete"));
+    }
+
+    #[tokio::test]
+    async fn test_finished_instances_expire() {
+        let engine = WorkflowEngine::default();
+        engine.define(release()).unwrap();
+        let running = engine.start("release").unwrap();
+        let finished = engine.start("release").unwrap();
+        {
+            let mut finished = finished.lock().await;
+            let transition = finished.transition("python_run", &json!({})).unwrap();
+            finished.advance(&transition);
+            let arguments = json!({"text": "approved"});
+            let transition = finished.transition("echo", &arguments).unwrap();
+            finished.advance(&transition);
+        }
+
+        assert_eq!(engine.expire_finished(Duration::from_secs(3600)), 0);
+        assert_eq!(engine.expire_finished(Duration::ZERO), 1);
+        let running = running.lock().await;
+        assert!(engine.instance(&running.id).is_some());
+        assert!(engine.instance(&finished.lock().await.id).is_none());
+    }
+
+    #[test]
+    fn test_define_rejects_inconsistent_workflows() {
+        let engine = WorkflowEngine::default();
+
+        let mut workflow = release();
+        workflow.initial = "missing".to_string();
+        assert_eq!(
+            engine.define(workflow).unwrap_err(),
+            "Invalid workflow 'release': initial state 'missing' is not defined"
+        );
+
+        let mut workflow = release();
+        workflow.states.remove("released");
+        assert!(engine
+            .define(workflow)
+            .unwrap_err()
+            .contains("undefined state 'released'"));
+
+        let mut workflow = release();
+        workflow.states.get_mut("draft").unwrap().transitions[0].guards =
+            vec![json!({"op": "approx"})];
+        assert!(engine
+            .define(workflow)
+            .unwrap_err()
+            .contains("Unknown operator 'approx'"));
+
+        assert!(engine.start("release").is_err());
+    }
+
+    #[test]
+    fn test_load_reads_workflow_file() {
+        let path =
+            std::env::temp_dir().join(format!("mcp-workflows-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(
+            &path,
+            serde_json::to_string(&json!({"workflows": [release()]})).unwrap(),
+        )
+        .unwrap();
+
+        let engine = WorkflowEngine::default();
+        engine.load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(engine.definitions()[0].name, "release");
+    }
+}
@@ -0,0 +1,189 @@
+//! Per-session scratch directories. Each [`Session`](crate::session::Session)
+//! gets its own directory under the system temp directory the first time a
+//! `workspace_*` tool touches it, and the directory is removed when the
+//! session ends, so multi-step file work in one session never sees another's
+//! files. Paths are given as `workspace://<relative path>` and resolved by a
+//! [`LocalDir`], so they follow the same rules as a `local` mount.
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::vfs::local::LocalDir;
+
+pub const SCHEME: &str = "workspace://";
+pub const DEFAULT_QUOTA_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Quota for every workspace, from `MCP_WORKSPACE_QUOTA_BYTES`.
+pub fn quota_from_env() -> u64 {
+    std::env::var("MCP_WORKSPACE_QUOTA_BYTES")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(DEFAULT_QUOTA_BYTES)
+}
+
+/// A file in a workspace listing.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Entry {
+    pub uri: String,
+    pub size: u64,
+}
+
+pub struct Workspace {
+    dir: LocalDir,
+    quota: u64,
+    /// Bytes currently stored. Writes go through [`Workspace::write`], so
+    /// this is kept in step instead of walking the tree each time.
+    used: Mutex<u64>,
+}
+
+impl Workspace {
+    /// Creates an empty workspace directory.
+    pub fn create(quota: u64) -> Result<Self, String> {
+        let root = std::env::temp_dir().join(format!("mcp-workspace-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&root).map_err(|e| format!("Failed to create workspace: {}", e))?;
+        Ok(Self {
+            dir: LocalDir::new(&root)?,
+            quota,
+            used: Mutex::new(0),
+        })
+    }
+
+    pub fn root(&self) -> &Path {
+        self.dir.root()
+    }
+
+    pub fn quota(&self) -> u64 {
+        self.quota
+    }
+
+    pub fn used(&self) -> u64 {
+        *self.used.lock().unwrap()
+    }
+
+    /// The file a `workspace://` URI names. Only plain relative paths
+    /// without hidden components are accepted, and symlinks are not followed
+    /// out of the workspace, so a URI can never reach outside it.
+    pub fn resolve(&self, uri: &str) -> Result<PathBuf, String> {
+        let relative = uri
+            .strip_prefix(SCHEME)
+            .ok_or_else(|| format!("Expected a {} URI, got '{}'", SCHEME, uri))?;
+        match self.dir.resolve(relative) {
+            Ok(path) if path != self.dir.root() => Ok(path),
+            _ => Err(format!("Invalid workspace path '{}'", relative)),
+        }
+    }
+
+    /// Writes `contents` to `uri`, replacing any existing file, unless the
+    /// workspace would grow past its quota.
+    pub fn write(&self, uri: &str, contents: &[u8]) -> Result<(), String> {
+        let path = self.resolve(uri)?;
+        let mut used = self.used.lock().unwrap();
+        let replaced = std::fs::metadata(&path)
+            .ok()
+            .filter(|m| m.is_file())
+            .map_or(0, |m| m.len());
+        let after = *used - replaced + contents.len() as u64;
+        if after > self.quota {
+            return Err(format!(
+                "Workspace quota of {} bytes exceeded ({} bytes would be used)",
+                self.quota, after
+            ));
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(&path, contents).map_err(|e| e.to_string())?;
+        *used = after;
+        Ok(())
+    }
+
+    pub fn read(&self, uri: &str) -> Result<Vec<u8>, String> {
+        let path = self.resolve(uri)?;
+        std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", uri, e))
+    }
+
+    pub fn delete(&self, uri: &str) -> Result<(), String> {
+        let path = self.resolve(uri)?;
+        let mut used = self.used.lock().unwrap();
+        let size = std::fs::metadata(&path)
+            .map_err(|e| format!("Failed to delete {}: {}", uri, e))?
+            .len();
+        std::fs::remove_file(&path).map_err(|e| format!("Failed to delete {}: {}", uri, e))?;
+        *used -= size;
+        Ok(())
+    }
+
+    /// Every file, sorted by URI.
+    pub fn list(&self) -> Vec<Entry> {
+        self.dir
+            .files("", usize::MAX)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|file| Entry {
+                uri: format!("{}{}", SCHEME, file.path),
+                size: file.size,
+            })
+            .collect()
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(self.dir.root());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_quota_and_cleanup() {
+        let workspace = Workspace::create(10).unwrap();
+        let root = workspace.root().to_path_buf();
+
+        assert!(workspace.resolve("workspace://../etc/passwd").is_err());
+        assert!(workspace.resolve("workspace:///etc/passwd").is_err());
+        assert!(workspace.resolve("workspace://").is_err());
+        assert!(workspace.resolve("notes.txt").is_err());
+        assert!(workspace.resolve("workspace://.env").is_err());
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink("/etc", root.join("etc")).unwrap();
+            assert!(workspace.resolve("workspace://etc/passwd").is_err());
+            std::fs::remove_file(root.join("etc")).unwrap();
+        }
+
+        workspace
+            .write("workspace://a/notes.txt", b"12345")
+            .unwrap();
+        workspace.write("workspace://b.txt", b"12345").unwrap();
+        assert!(workspace
+            .write("workspace://c.txt", b"1")
+            .unwrap_err()
+            .contains("quota"));
+        // Replacing a file only counts the difference.
+        workspace.write("workspace://b.txt", b"123").unwrap();
+        assert_eq!(workspace.used(), 8);
+        assert_eq!(workspace.read("workspace://a/notes.txt").unwrap(), b"12345");
+        assert_eq!(
+            workspace.list(),
+            vec![
+                Entry {
+                    uri: "workspace://a/notes.txt".to_string(),
+                    size: 5
+                },
+                Entry {
+                    uri: "workspace://b.txt".to_string(),
+                    size: 3
+                },
+            ]
+        );
+
+        workspace.delete("workspace://a/notes.txt").unwrap();
+        assert_eq!(workspace.used(), 3);
+        drop(workspace);
+        assert!(!root.exists());
+    }
+}
@@ -0,0 +1,48 @@
+//! `MCP_MEMORY_FILE` is read by `McpServer::new`, so these run in their own
+//! process rather than alongside the unit tests.
+
+use mcp_server::{McpRequest, McpServer};
+use serde_json::{json, Value};
+use std::path::Path;
+
+fn create_entity(name: &str) -> McpRequest {
+    McpRequest {
+        jsonrpc: "2.0".to_string(),
+        id: Some(json!(1)),
+        method: "tools/call".to_string(),
+        params: Some(json!({
+            "name": "memory_create_entities",
+            "arguments": {"entities": [{"name": name, "entityType": "person"}]}
+        })),
+    }
+}
+
+/// Starts a server on `path` and tries one write through `memory_*`.
+async fn write_through_server(path: &Path) -> Value {
+    std::env::set_var("MCP_MEMORY_FILE", path);
+    let server = McpServer::new();
+    std::env::remove_var("MCP_MEMORY_FILE");
+    let response = server.handle_request(create_entity("alice")).await.unwrap();
+    response.result.unwrap()
+}
+
+#[tokio::test]
+async fn test_unreadable_memory_file_is_never_overwritten() {
+    let dir = std::env::temp_dir().join(format!("mcp-memory-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir(&dir).unwrap();
+
+    // A directory where the file should be.
+    let result = write_through_server(&dir).await;
+    assert_eq!(result["isError"], true);
+    assert!(dir.is_dir());
+
+    // A file that exists but cannot be read as a graph: not UTF-8.
+    let file = dir.join("graph.json");
+    let bytes = b"{\"entities\": [{\"name\": \"bob\xff\"}]}".to_vec();
+    std::fs::write(&file, &bytes).unwrap();
+    let result = write_through_server(&file).await;
+    assert_eq!(result["isError"], true);
+    assert_eq!(std::fs::read(&file).unwrap(), bytes);
+
+    std::fs::remove_dir_all(dir).unwrap();
+}